@@ -0,0 +1,155 @@
+use crate::config::Config;
+use crate::frame_logic::registry::{FrameRegistry, DEFAULT_FRAME};
+use crate::frame_logic::CastId;
+use actix_web::{web, HttpResponse};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+// Octicon clients show next to the composer action
+const COMPOSER_ICON: &str = "package";
+
+// Shown under the name; clients cut off anything past 20 characters
+const COMPOSER_DESCRIPTION: &str = "Buy GOAT in a cast";
+
+// Title of the form the composer action opens
+const COMPOSER_FORM_TITLE: &str = "Buy GOAT";
+
+const CAST_ACTION_ICON: &str = "gift";
+const CAST_ACTION_DESCRIPTION: &str = "Gift GOAT to this caster";
+
+// Screen of the default frame the cast action opens, when the frame has it
+const CAST_ACTION_SCREEN: &str = "gift";
+
+#[derive(Serialize)]
+struct ActionType {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+// What GET /api/composer tells a client installing the composer action
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposerMetadata {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: String,
+    icon: &'static str,
+    description: &'static str,
+    about_url: String,
+    image_url: String,
+    action: ActionType,
+}
+
+impl ComposerMetadata {
+    pub fn from_config(config: &Config) -> Self {
+        ComposerMetadata {
+            kind: "composer",
+            name: config.miniapp_name.clone(),
+            icon: COMPOSER_ICON,
+            description: COMPOSER_DESCRIPTION,
+            about_url: format!("{}/", config.domain),
+            image_url: format!("{}/assets/{}", config.asset_origin(), config.miniapp_icon),
+            action: ActionType { kind: "post" },
+        }
+    }
+}
+
+// The page a composer action opens over the cast being written
+#[derive(Serialize)]
+pub struct ComposerForm {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    url: String,
+}
+
+pub async fn composer_metadata(config: web::Data<Config>) -> HttpResponse {
+    HttpResponse::Ok().json(ComposerMetadata::from_config(&config))
+}
+
+// The store's home page doubles as the form, so there's nothing in the post to check:
+// the user buys from their own wallet there and attaches the result to their cast
+pub async fn composer_form(config: web::Data<Config>) -> HttpResponse {
+    HttpResponse::Ok().json(ComposerForm {
+        kind: "form",
+        title: COMPOSER_FORM_TITLE,
+        url: format!("{}/", config.domain),
+    })
+}
+
+// What GET /api/cast-action tells a client installing the cast action
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CastActionMetadata {
+    name: String,
+    icon: &'static str,
+    description: &'static str,
+    about_url: String,
+    action: ActionType,
+}
+
+impl CastActionMetadata {
+    pub fn from_config(config: &Config) -> Self {
+        CastActionMetadata {
+            name: config.miniapp_name.clone(),
+            icon: CAST_ACTION_ICON,
+            description: CAST_ACTION_DESCRIPTION,
+            about_url: format!("{}/", config.domain),
+            action: ActionType { kind: "post" },
+        }
+    }
+}
+
+// Clients post cast actions in camelCase, with the cast the action was used on
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CastActionRequest {
+    untrusted_data: CastActionData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CastActionData {
+    fid: Option<u64>,
+    cast_id: CastId,
+}
+
+// A frame for the client to show in place of the action
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CastActionFrame {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    frame_url: String,
+}
+
+pub async fn cast_action_metadata(config: web::Data<Config>) -> HttpResponse {
+    HttpResponse::Ok().json(CastActionMetadata::from_config(&config))
+}
+
+// Opens the store's Gift screen over the cast. Nothing is done on the user's behalf
+// here, and the frame validates its own posts, so the message isn't checked.
+pub async fn cast_action(
+    req: web::Json<CastActionRequest>,
+    config: web::Data<Config>,
+    registry: web::Data<FrameRegistry>,
+) -> HttpResponse {
+    let data = &req.untrusted_data;
+    info!(
+        "fid {:?} used the cast action on {} by fid {}",
+        data.fid, data.cast_id.hash, data.cast_id.fid
+    );
+
+    let has_gift = registry
+        .frame(DEFAULT_FRAME)
+        .is_some_and(|frame| frame.screen(CAST_ACTION_SCREEN).is_some());
+    let frame_url = if has_gift {
+        format!("{}/?screen={}", config.domain, CAST_ACTION_SCREEN)
+    } else {
+        format!("{}/", config.domain)
+    };
+    HttpResponse::Ok().json(CastActionFrame {
+        kind: "frame",
+        frame_url,
+    })
+}
@@ -0,0 +1,95 @@
+use actix_web::web;
+use log::error;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// One button press on a frame
+#[derive(Clone, Debug, Serialize)]
+pub struct ClickEvent {
+    // Seconds since the Unix epoch
+    pub timestamp: u64,
+    // Verified when validation is on, otherwise whatever the client claimed
+    pub fid: Option<u64>,
+    pub frame: String,
+    pub button_index: usize,
+    // The experiment arm the pressed screen showed, as "<experiment>:<arm>"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+impl ClickEvent {
+    pub fn now(fid: Option<u64>, frame: &str, button_index: usize) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        ClickEvent {
+            timestamp,
+            fid,
+            frame: frame.to_string(),
+            button_index,
+            variant: None,
+        }
+    }
+}
+
+pub type RecordFuture<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+// Somewhere to keep click events for later analysis. Recording never fails the
+// request, so sinks log their own errors.
+pub trait AnalyticsSink: Send + Sync {
+    fn record(&self, event: ClickEvent) -> RecordFuture<'_>;
+}
+
+// Used when ANALYTICS_PATH isn't set
+pub struct NoopSink;
+
+impl AnalyticsSink for NoopSink {
+    fn record(&self, _event: ClickEvent) -> RecordFuture<'_> {
+        Box::pin(async {})
+    }
+}
+
+// Appends one JSON object per line to a file
+pub struct JsonlSink {
+    file: Arc<Mutex<File>>,
+}
+
+impl JsonlSink {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(JsonlSink {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+}
+
+impl AnalyticsSink for JsonlSink {
+    fn record(&self, event: ClickEvent) -> RecordFuture<'_> {
+        let file = self.file.clone();
+        Box::pin(async move {
+            let mut line = match serde_json::to_string(&event) {
+                Ok(line) => line,
+                Err(err) => {
+                    error!("Failed to serialize click event: {}", err);
+                    return;
+                }
+            };
+            line.push('\n');
+
+            // Each line goes out in a single write under the lock, so concurrent
+            // clicks never interleave; the blocking write runs off the worker thread
+            let written = web::block(move || file.lock().unwrap().write_all(line.as_bytes())).await;
+            match written {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => error!("Failed to write click event: {}", err),
+                Err(err) => error!("Failed to write click event: {}", err),
+            }
+        })
+    }
+}
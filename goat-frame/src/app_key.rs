@@ -0,0 +1,104 @@
+use crate::errors::AppError;
+use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+use log::{error, warn};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+// A JSON Farcaster Signature as clients post webhook events: base64url JSON header
+// and payload, and an Ed25519 signature over "<header>.<payload>"
+#[derive(Deserialize)]
+pub struct SignedEvent {
+    pub header: String,
+    pub payload: String,
+    pub signature: String,
+}
+
+// Who signed an event. Only app keys sign webhook events; custody signatures are
+// for account associations.
+#[derive(Deserialize)]
+pub struct EventHeader {
+    pub fid: u64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    // 0x-prefixed hex Ed25519 public key
+    pub key: String,
+}
+
+#[derive(Deserialize)]
+struct OnChainSignerEvent {
+    fid: u64,
+}
+
+// Checks an event was signed by one of the fid's app keys and that the key is still
+// registered in the Key Registry, which hubs index, so a forged event can't register
+// or remove notification tokens for someone else
+pub async fn verify_event(
+    client: &reqwest::Client,
+    hub_url: &str,
+    event: &SignedEvent,
+) -> Result<EventHeader, AppError> {
+    let header: EventHeader = decode_part(&event.header)?;
+    if header.kind != "app_key" {
+        return Err(forged_event());
+    }
+    let key = hex::decode(header.key.trim_start_matches("0x")).map_err(|_| malformed_event())?;
+    let signature = BASE64_URL_SAFE_NO_PAD
+        .decode(event.signature.trim_end_matches('='))
+        .map_err(|_| malformed_event())?;
+    let signed = format!("{}.{}", event.header, event.payload);
+    UnparsedPublicKey::new(&ED25519, &key)
+        .verify(signed.as_bytes(), &signature)
+        .map_err(|_| forged_event())?;
+
+    let response = client
+        .get(format!("{}/v1/onChainSignersByFid", hub_url))
+        .query(&[
+            ("fid", header.fid.to_string()),
+            ("signer", format!("0x{}", hex::encode(&key))),
+        ])
+        .send()
+        .await
+        .map_err(|err| {
+            error!("Failed to reach Farcaster hub at {}: {}", hub_url, err);
+            AppError::InternalServerError
+        })?;
+
+    // Hubs answer with an error status for keys that were never added or were removed
+    if response.status().is_client_error() {
+        warn!(
+            "Hub has no app key {} for fid {}: status {}",
+            header.key,
+            header.fid,
+            response.status()
+        );
+        return Err(forged_event());
+    }
+    if !response.status().is_success() {
+        error!("Hub returned status {} for a signer", response.status());
+        return Err(AppError::InternalServerError);
+    }
+    let signer: OnChainSignerEvent = response.json().await.map_err(|err| {
+        error!("Unexpected response from Farcaster hub: {}", err);
+        AppError::InternalServerError
+    })?;
+    if signer.fid != header.fid {
+        return Err(forged_event());
+    }
+    Ok(header)
+}
+
+pub fn decode_part<T: DeserializeOwned>(part: &str) -> Result<T, AppError> {
+    let json = BASE64_URL_SAFE_NO_PAD
+        .decode(part.trim_end_matches('='))
+        .map_err(|_| malformed_event())?;
+    serde_json::from_slice(&json).map_err(|_| malformed_event())
+}
+
+fn malformed_event() -> AppError {
+    AppError::BadRequest("Malformed webhook event".to_string())
+}
+
+fn forged_event() -> AppError {
+    AppError::Unauthorized("Webhook event failed signature verification".to_string())
+}
@@ -0,0 +1,32 @@
+use serde::Deserialize;
+use std::time::Duration;
+
+// The frame has to answer quickly, so don't wait long on the APR source
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+struct AprResponse {
+    // Percent, e.g. 12.5 for 12.5%
+    apr: f64,
+}
+
+// Fetches the current pool APR from APR_URL, which answers with {"apr": <percent>}
+pub async fn fetch_apr(client: &reqwest::Client, url: &str) -> Result<f64, String> {
+    let response = client
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let body: AprResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+    if !body.apr.is_finite() {
+        return Err(format!("invalid apr {}", body.apr));
+    }
+    Ok(body.apr)
+}
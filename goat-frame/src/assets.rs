@@ -0,0 +1,148 @@
+use crate::assets::optimize::OptimizedAsset;
+use crate::config::Config;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use tiny_keccak::{Hasher, Keccak};
+
+pub mod optimize;
+
+// Where optimized variants are served, under /assets
+pub const OPTIMIZED_PREFIX: &str = "optimized/";
+
+// Content hashes of the static assets, computed once since they don't change at runtime
+#[derive(Default)]
+pub struct AssetHashes {
+    // Path relative to the assets directory, with forward slashes, to a quoted ETag
+    etags: HashMap<String, String>,
+    // Assets served as their optimized variant instead, by path, to the variant's
+    // path under /assets
+    optimized: HashMap<String, String>,
+}
+
+impl AssetHashes {
+    pub fn scan(dir: &Path) -> io::Result<Self> {
+        let mut hashes = AssetHashes::default();
+        hashes.scan_dir(dir, "")?;
+        Ok(hashes)
+    }
+
+    fn scan_dir(&mut self, dir: &Path, prefix: &str) -> io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = format!("{}{}", prefix, name);
+            if entry.file_type()?.is_dir() {
+                self.scan_dir(&entry.path(), &format!("{}/", relative))?;
+            } else {
+                let contents = std::fs::read(entry.path())?;
+                self.etags.insert(relative, content_etag(&contents));
+            }
+        }
+        Ok(())
+    }
+
+    // Serves these assets as their variants in cache_dir from now on
+    pub fn with_optimized(
+        mut self,
+        cache_dir: &Path,
+        optimized: &[OptimizedAsset],
+    ) -> io::Result<Self> {
+        for asset in optimized {
+            let contents = std::fs::read(cache_dir.join(&asset.variant))?;
+            let served = format!("{}{}", OPTIMIZED_PREFIX, asset.variant);
+            self.etags.insert(served.clone(), content_etag(&contents));
+            self.optimized.insert(asset.path.clone(), served);
+        }
+        Ok(self)
+    }
+
+    pub fn etag(&self, path: &str) -> Option<&str> {
+        self.etags.get(path).map(String::as_str)
+    }
+
+    // Public URL of an asset with its content hash in the query, since clients cache
+    // frame images by URL (Warpcast aggressively) and would otherwise keep showing an
+    // old version. Files that weren't scanned get a plain URL, and optimized ones
+    // their variant's, which is named for its content already.
+    pub fn url(&self, domain: &str, path: &str) -> String {
+        if let Some(variant) = self.optimized.get(path) {
+            return format!("{}/assets/{}", domain, variant);
+        }
+        match self.version(path) {
+            Some(version) => format!("{}/assets/{}?v={}", domain, path, version),
+            None => format!("{}/assets/{}", domain, path),
+        }
+    }
+
+    // The short content hash that versions an asset's URLs; always the original's,
+    // which is what templates draw on
+    pub fn version(&self, path: &str) -> Option<&str> {
+        self.etag(path)
+            .map(|etag| &etag.trim_matches('"')[..VERSION_LEN])
+    }
+}
+
+// Hex digits of the content hash used in versioned asset URLs
+const VERSION_LEN: usize = 8;
+
+fn content_etag(contents: &[u8]) -> String {
+    let mut hasher = Keccak::v256();
+    hasher.update(contents);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    // Half the hash is plenty to tell versions of a file apart
+    format!("\"{}\"", hex::encode(&hash[..16]))
+}
+
+// Adds Cache-Control and content-hash ETags to /assets responses and answers
+// matching If-None-Match requests with 304 without touching the file
+pub async fn cache_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let max_age = req
+        .app_data::<web::Data<Config>>()
+        .map_or(0, |config| config.asset_cache_secs);
+    let cache_control = format!("public, max-age={}", max_age);
+
+    let relative = req.path().trim_start_matches("/assets/");
+    let etag = req
+        .app_data::<web::Data<AssetHashes>>()
+        .and_then(|hashes| hashes.etag(relative))
+        .map(str::to_string);
+
+    if let Some(etag) = &etag {
+        let matches = req
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                value.trim() == "*" || value.split(',').any(|tag| tag.trim() == etag)
+            });
+        if matches {
+            let response = HttpResponse::NotModified()
+                .insert_header((header::ETAG, etag.as_str()))
+                .insert_header((header::CACHE_CONTROL, cache_control))
+                .finish();
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    let mut res = next.call(req).await?;
+    if res.status().is_success() {
+        let headers = res.headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&cache_control) {
+            headers.insert(header::CACHE_CONTROL, value);
+        }
+        if let Some(value) = etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+            headers.insert(header::ETAG, value);
+        }
+    }
+    Ok(res.map_into_left_body())
+}
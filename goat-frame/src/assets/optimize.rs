@@ -0,0 +1,146 @@
+use super::{content_etag, VERSION_LEN};
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngEncoder};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use log::warn;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+// Clients show frame images at most about 600 pixels wide, so twice that covers
+// high-density screens; larger assets are scaled down to fit
+pub const MAX_SIDE: u32 = 1200;
+
+// An asset whose optimized variant is smaller than it, and so served instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizedAsset {
+    // Relative to the assets directory, with forward slashes
+    pub path: String,
+    // Relative to the cache directory, named for the original's content hash
+    pub variant: String,
+    pub original_bytes: u64,
+    pub optimized_bytes: u64,
+}
+
+// Writes a recompressed copy of every PNG under assets_dir into cache_dir, scaled
+// down to MAX_SIDE and without an alpha channel when nothing is transparent.
+// Variants are named for the original's content, so a restart reuses them and a
+// changed asset gets a new one; anything else in cache_dir is removed. PNGs that
+// can't be decoded are left as they are.
+pub fn optimize_assets(assets_dir: &Path, cache_dir: &Path) -> io::Result<Vec<OptimizedAsset>> {
+    std::fs::create_dir_all(cache_dir)?;
+    let mut optimized = Vec::new();
+    let mut variants = HashSet::new();
+    optimize_dir(assets_dir, cache_dir, "", &mut optimized, &mut variants)?;
+    remove_stale(cache_dir, "", &variants)?;
+    Ok(optimized)
+}
+
+fn optimize_dir(
+    dir: &Path,
+    cache_dir: &Path,
+    prefix: &str,
+    optimized: &mut Vec<OptimizedAsset>,
+    variants: &mut HashSet<String>,
+) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            let prefix = format!("{}/", relative);
+            optimize_dir(&entry.path(), cache_dir, &prefix, optimized, variants)?;
+            continue;
+        }
+        let Some(stem) = name.strip_suffix(".png") else {
+            continue;
+        };
+        let contents = std::fs::read(entry.path())?;
+        let version = &content_etag(&contents)[1..=VERSION_LEN];
+        let variant = format!("{}{}.{}.png", prefix, stem, version);
+        let path = cache_dir.join(&variant);
+        let optimized_bytes = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                let Some(png) = recompress(&relative, &contents) else {
+                    continue;
+                };
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, &png)?;
+                png.len() as u64
+            }
+        };
+        // Kept even when it's no smaller, so the next start doesn't try again
+        variants.insert(variant.clone());
+        if optimized_bytes < contents.len() as u64 {
+            optimized.push(OptimizedAsset {
+                path: relative,
+                variant,
+                original_bytes: contents.len() as u64,
+                optimized_bytes,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn recompress(path: &str, contents: &[u8]) -> Option<Vec<u8>> {
+    let mut img = match image::load_from_memory_with_format(contents, ImageFormat::Png) {
+        Ok(img) => img,
+        Err(err) => {
+            warn!(
+                "Serving assets/{} as is, it can't be optimized: {}",
+                path, err
+            );
+            return None;
+        }
+    };
+    if img.width() > MAX_SIDE || img.height() > MAX_SIDE {
+        img = img.resize(MAX_SIDE, MAX_SIDE, FilterType::Lanczos3);
+    }
+    let opaque =
+        !img.color().has_alpha() || img.to_rgba8().pixels().all(|pixel| pixel.0[3] == u8::MAX);
+    if opaque {
+        img = DynamicImage::ImageRgb8(img.to_rgb8());
+    }
+    let mut png = Vec::new();
+    let encoder =
+        PngEncoder::new_with_quality(&mut png, CompressionType::Best, PngFilter::Adaptive);
+    match img.write_with_encoder(encoder) {
+        Ok(()) => Some(png),
+        Err(err) => {
+            warn!(
+                "Serving assets/{} as is, it can't be optimized: {}",
+                path, err
+            );
+            None
+        }
+    }
+}
+
+// Removes variants of assets that have since changed or gone. Only files named like
+// a variant are touched, in case ASSET_CACHE_PATH points somewhere shared.
+fn remove_stale(dir: &Path, prefix: &str, variants: &HashSet<String>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            remove_stale(&entry.path(), &format!("{}/", relative), variants)?;
+        } else if is_variant(&name) && !variants.contains(&relative) {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+// "<stem>.<version>.png", as optimize_dir names them
+fn is_variant(name: &str) -> bool {
+    let version = name
+        .strip_suffix(".png")
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, version)| version);
+    version.is_some_and(|v| v.len() == VERSION_LEN && v.bytes().all(|b| b.is_ascii_hexdigit()))
+}
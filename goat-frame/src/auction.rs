@@ -0,0 +1,249 @@
+use crate::config::Config;
+use crate::countdown::unix_now;
+use crate::errors::AppError;
+use crate::notifications::{self, Notification, NotificationStore};
+use crate::tx::{self, TransactionParams, TransactionResponse};
+use actix_web::web;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// The highest bid and who made it, when bidding closes, and a bid of the value sent
+const HIGHEST_BID_SIGNATURE: &str = "highestBid()";
+const END_TIME_SIGNATURE: &str = "endTime()";
+const BID_SIGNATURE: &str = "bid()";
+
+// Most bids remembered at once; further bidders just aren't told they're outbid
+const MAX_BIDDERS: usize = 10_000;
+
+// The auction as AUCTION_CONTRACT has it. No bids yet is an amount of 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AuctionState {
+    pub bidder: [u8; 20],
+    pub amount: u128,
+    // Unix seconds
+    pub ends_at: u64,
+}
+
+pub async fn fetch_auction(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    contract: &[u8; 20],
+) -> Result<AuctionState, String> {
+    let input = tx::function_selector(HIGHEST_BID_SIGNATURE);
+    let highest = tx::eth_call(client, rpc_url, contract, &input).await?;
+    let input = tx::function_selector(END_TIME_SIGNATURE);
+    let end_time = tx::eth_call(client, rpc_url, contract, &input).await?;
+
+    let (Some(bidder), Some(amount), Some(ends_at)) = (
+        highest.get(12..32),
+        tx::decode_amount(&highest, 1),
+        tx::decode_amount(&end_time, 0),
+    ) else {
+        return Err(format!(
+            "invalid auction 0x{} ending 0x{}",
+            hex::encode(&highest),
+            hex::encode(&end_time)
+        ));
+    };
+    Ok(AuctionState {
+        bidder: bidder
+            .try_into()
+            .map_err(|_| "invalid bidder".to_string())?,
+        amount,
+        ends_at: u64::try_from(ends_at).unwrap_or(u64::MAX),
+    })
+}
+
+// The least a bid can be: the highest plus increment_bps basis points of it, rounded
+// down as the contract would, and always more than it; anything before the first bid
+pub fn min_bid(highest: u128, increment_bps: u32) -> u128 {
+    let increment = tx::mul_div(highest, u128::from(increment_bps), 10_000).unwrap_or(u128::MAX);
+    highest.saturating_add(increment.max(1))
+}
+
+// The auction as last read, for AUCTION_POLL_SECS, and the bids made here by fids a
+// hub vouched for, so whoever's bid is beaten can be told
+pub struct Auction {
+    ttl: Duration,
+    latest: Mutex<Option<(Instant, AuctionState)>>,
+    // The fid and amount of each bidder's latest bid, by address, until it's beaten
+    bids: Mutex<HashMap<[u8; 20], (u64, u128)>>,
+}
+
+impl Auction {
+    pub fn new(ttl_secs: u64) -> Self {
+        Auction {
+            ttl: Duration::from_secs(ttl_secs),
+            latest: Mutex::default(),
+            bids: Mutex::default(),
+        }
+    }
+
+    // The auction to draw, None without AUCTION_CONTRACT or when it can't be read
+    pub async fn get(&self, client: &reqwest::Client, config: &Config) -> Option<AuctionState> {
+        if let Some((stored, state)) = *self.latest.lock().unwrap() {
+            if stored.elapsed() < self.ttl {
+                return Some(state);
+            }
+        }
+        self.refresh(client, config)
+            .await
+            .inspect_err(|err| warn!("Failed to read the auction: {}", err))
+            .ok()
+    }
+
+    async fn refresh(
+        &self,
+        client: &reqwest::Client,
+        config: &Config,
+    ) -> Result<AuctionState, String> {
+        let (Some(contract), Some(rpc_url)) = (&config.auction_contract, &config.rpc_url) else {
+            return Err("AUCTION_CONTRACT is not configured".to_string());
+        };
+        let contract = tx::parse_address(contract).map_err(|err| err.to_string())?;
+        let state = fetch_auction(client, rpc_url, &contract).await?;
+        *self.latest.lock().unwrap() = Some((Instant::now(), state));
+        Ok(state)
+    }
+
+    // Only for an fid and address from a validated message, as anyone could claim a
+    // bid to have the real bidder's fid told nothing, or a stranger told everything
+    pub fn remember_bid(&self, address: [u8; 20], fid: u64, amount: u128) {
+        let mut bids = self.bids.lock().unwrap();
+        if bids.len() >= MAX_BIDDERS && !bids.contains_key(&address) {
+            return;
+        }
+        bids.insert(address, (fid, amount));
+    }
+
+    // Reads the auction and tells everyone who bid here and has since been beaten,
+    // however many bids came in since the last look. Returns the fids told.
+    pub async fn check_outbid(
+        &self,
+        client: &reqwest::Client,
+        config: &Config,
+        store: &NotificationStore,
+    ) -> Result<Vec<u64>, String> {
+        let state = self.refresh(client, config).await?;
+        let outbid: Vec<u64> = {
+            let mut bids = self.bids.lock().unwrap();
+            // Once bidding is over there's nothing left to bid again on
+            if state.ends_at <= unix_now() {
+                bids.clear();
+                return Ok(Vec::new());
+            }
+            let beaten: Vec<[u8; 20]> = bids
+                .iter()
+                .filter(|(address, (_, amount))| {
+                    **address != state.bidder && *amount < state.amount
+                })
+                .map(|(address, _)| *address)
+                .collect();
+            beaten
+                .iter()
+                .filter_map(|address| bids.remove(address))
+                .map(|(fid, _)| fid)
+                .collect()
+        };
+
+        let notification = Notification::outbid(state.ends_at, state.amount, config);
+        let mut told = Vec::new();
+        for fid in outbid {
+            match notifications::send(client, store, fid, &notification).await {
+                Ok(true) => {
+                    info!("Told fid {} they were outbid", fid);
+                    told.push(fid);
+                }
+                Ok(false) => {}
+                Err(err) => warn!("Failed to tell fid {} they were outbid: {}", fid, err),
+            }
+        }
+        Ok(told)
+    }
+}
+
+// Reads the auction every AUCTION_POLL_SECS for as long as the server runs, telling
+// bidders when they're outbid
+pub async fn watch_for_outbids(
+    auction: web::Data<Auction>,
+    store: web::Data<NotificationStore>,
+    client: web::Data<reqwest::Client>,
+    config: web::Data<Config>,
+) {
+    let mut interval = actix_web::rt::time::interval(Duration::from_secs(config.auction_poll_secs));
+    loop {
+        interval.tick().await;
+        if let Err(err) = auction.check_outbid(&client, &config, &store).await {
+            warn!("Failed to check the auction for outbids: {}", err);
+        }
+    }
+}
+
+// A bid of the GOAT the user typed into the Bid screen's input, paid from the wallet
+// the client connected. It has to beat the highest bid by AUCTION_MIN_INCREMENT_BPS
+// before the auction ends, as the contract would otherwise refuse it. The signed
+// wallet and fid of the post, when it has them, are told if the bid is beaten.
+pub async fn bid_transaction(
+    signed_bidder: Option<([u8; 20], u64)>,
+    address: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+    auction: &Auction,
+) -> Result<TransactionResponse, AppError> {
+    let (Some(contract), Some(rpc_url)) = (&config.auction_contract, &config.rpc_url) else {
+        error!("AUCTION_CONTRACT is not configured");
+        return Err(AppError::InternalServerError);
+    };
+    let bidder = tx::parse_address(address.unwrap_or_default())?;
+    let value = tx::parse_amount(amount.unwrap_or_default())?;
+    if value == 0 {
+        return Err(AppError::BadRequest(
+            "Enter how much GOAT to bid".to_string(),
+        ));
+    }
+
+    // Checked when the config was loaded. Read fresh, as a bid checked against a
+    // highest bid since beaten would fail.
+    let contract = tx::parse_address(contract)?;
+    let state = match fetch_auction(client, rpc_url, &contract).await {
+        Ok(state) => state,
+        Err(err) => {
+            error!("Failed to read the auction: {}", err);
+            return Err(AppError::InternalServerError);
+        }
+    };
+    if state.ends_at <= unix_now() {
+        return Err(AppError::BadRequest("The auction has ended".to_string()));
+    }
+    let min_bid = min_bid(state.amount, config.auction_min_increment_bps);
+    if value < min_bid {
+        return Err(AppError::BadRequest(format!(
+            "Bid at least {} GOAT",
+            tx::format_amount(min_bid)
+        )));
+    }
+    tx::check_balance(client, config, &bidder, value).await?;
+    if let Some((address, fid)) = signed_bidder {
+        auction.remember_bid(address, fid, value);
+    }
+
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([{
+                "type": "function",
+                "name": "bid",
+                "stateMutability": "payable",
+                "inputs": [],
+                "outputs": []
+            }]),
+            to: format!("0x{}", hex::encode(contract)),
+            data: format!("0x{}", hex::encode(tx::function_selector(BID_SIGNATURE))),
+            value: value.to_string(),
+        },
+    })
+}
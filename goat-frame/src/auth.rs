@@ -0,0 +1,460 @@
+use crate::config::Config;
+use crate::errors::AppError;
+use crate::tx;
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+// How long a client has to get a nonce signed and posted back
+const NONCE_TTL: Duration = Duration::from_secs(300);
+
+// SIWF messages name the fid signing in as a resource
+const FID_RESOURCE_PREFIX: &str = "- farcaster://fid/";
+
+// Sign-in messages are signed for OP mainnet, where Farcaster's registries live
+const SIWF_CHAIN_ID: u64 = 10;
+
+// A sign-in message must have been issued within the nonce's lifetime, allowing a
+// client clock this far ahead of ours
+const CLOCK_SKEW_SECS: u64 = 60;
+
+struct Nonces {
+    issued: HashMap<String, Instant>,
+    last_sweep: Instant,
+}
+
+// Sign In With Farcaster for the mini app pages: they fetch a nonce, have the user
+// sign a SIWF message with it, and trade the signature for a session token to send
+// as "Authorization: Bearer <token>" on API calls
+pub struct Sessions {
+    secret: String,
+    ttl_secs: u64,
+    nonces: Mutex<Nonces>,
+}
+
+impl Sessions {
+    // Without a secret, tokens only last until the server restarts
+    pub fn new(secret: Option<&str>, ttl_secs: u64) -> Self {
+        Sessions {
+            secret: secret.map_or_else(|| Uuid::new_v4().simple().to_string(), str::to_string),
+            ttl_secs,
+            nonces: Mutex::new(Nonces {
+                issued: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn issue_nonce(&self) -> String {
+        // SIWE nonces must be alphanumeric, so no hyphens
+        let nonce = Uuid::new_v4().simple().to_string();
+        let now = Instant::now();
+        let mut nonces = self.nonces.lock().unwrap();
+        // Drop nonces nobody used once per TTL so they can't pile up
+        if now.duration_since(nonces.last_sweep) >= NONCE_TTL {
+            nonces
+                .issued
+                .retain(|_, issued| now.duration_since(*issued) < NONCE_TTL);
+            nonces.last_sweep = now;
+        }
+        nonces.issued.insert(nonce.clone(), now);
+        nonce
+    }
+
+    // Uses up a nonce, so each signed message signs in once
+    pub fn take_nonce(&self, nonce: &str) -> bool {
+        let mut nonces = self.nonces.lock().unwrap();
+        nonces
+            .issued
+            .remove(nonce)
+            .is_some_and(|issued| issued.elapsed() < NONCE_TTL)
+    }
+
+    // A token for the fid and when it expires, in Unix seconds
+    pub fn mint(&self, fid: u64) -> (String, u64) {
+        let expires_at = unix_now() + self.ttl_secs;
+        let claims = format!("{}.{}", fid, expires_at);
+        let signature = hex::encode(self.mac(&claims).finalize().into_bytes());
+        (format!("{}.{}", claims, signature), expires_at)
+    }
+
+    // The fid a token was minted for, if it's ours and hasn't expired
+    pub fn verify(&self, token: &str) -> Option<u64> {
+        let (claims, signature) = token.rsplit_once('.')?;
+        let signature = hex::decode(signature).ok()?;
+        self.mac(claims).verify_slice(&signature).ok()?;
+        let (fid, expires_at) = claims.split_once('.')?;
+        let expires_at: u64 = expires_at.parse().ok()?;
+        if expires_at <= unix_now() {
+            return None;
+        }
+        fid.parse().ok()
+    }
+
+    fn mac(&self, claims: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts any key length");
+        mac.update(claims.as_bytes());
+        mac
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// The parts of a SIWF message (an EIP-4361 sign-in message) we check; times are in
+// Unix seconds
+#[derive(Debug, PartialEq)]
+pub struct SiwfMessage {
+    pub domain: String,
+    pub address: [u8; 20],
+    pub uri: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: u64,
+    pub expiration_time: Option<u64>,
+    pub not_before: Option<u64>,
+    pub fid: u64,
+}
+
+pub fn parse_message(message: &str) -> Result<SiwfMessage, AppError> {
+    let invalid =
+        |problem: &str| AppError::BadRequest(format!("Invalid SIWF message: {}", problem));
+    let mut lines = message.lines();
+    let domain = lines
+        .next()
+        .and_then(|line| line.strip_suffix(" wants you to sign in with your Ethereum account:"))
+        .ok_or_else(|| invalid("no domain"))?;
+    let address = lines
+        .next()
+        .and_then(|line| tx::parse_address(line.trim()).ok())
+        .ok_or_else(|| invalid("no address"))?;
+
+    let (mut uri, mut chain_id, mut nonce, mut fid) = (None, None, None, None);
+    let (mut issued_at, mut expiration_time, mut not_before) = (None, None, None);
+    let time = |value: &str, field: &str| {
+        parse_timestamp(value.trim()).ok_or_else(|| invalid(&format!("bad {}", field)))
+    };
+    for line in lines {
+        if let Some(value) = line.strip_prefix("URI: ") {
+            uri = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Chain ID: ") {
+            chain_id = Some(value.trim().parse().map_err(|_| invalid("bad chain id"))?);
+        } else if let Some(value) = line.strip_prefix("Nonce: ") {
+            nonce = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Issued At: ") {
+            issued_at = Some(time(value, "issued at")?);
+        } else if let Some(value) = line.strip_prefix("Expiration Time: ") {
+            expiration_time = Some(time(value, "expiration time")?);
+        } else if let Some(value) = line.strip_prefix("Not Before: ") {
+            not_before = Some(time(value, "not before")?);
+        } else if let Some(value) = line.strip_prefix(FID_RESOURCE_PREFIX) {
+            fid = Some(value.trim().parse().map_err(|_| invalid("bad fid"))?);
+        }
+    }
+    Ok(SiwfMessage {
+        domain: domain.to_string(),
+        address,
+        uri: uri.ok_or_else(|| invalid("no uri"))?,
+        chain_id: chain_id.ok_or_else(|| invalid("no chain id"))?,
+        nonce: nonce.ok_or_else(|| invalid("no nonce"))?,
+        issued_at: issued_at.ok_or_else(|| invalid("no issued at"))?,
+        expiration_time,
+        not_before,
+        fid: fid.ok_or_else(|| invalid("no fid resource"))?,
+    })
+}
+
+// An RFC 3339 time as EIP-4361 has them, e.g. 2026-01-01T00:00:00.000Z, in Unix
+// seconds
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let (date, rest) = value.split_once(['T', 't'])?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (time, offset) = match rest.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => {
+            let at = rest.rfind(['+', '-'])?;
+            let (hours, minutes) = rest[at + 1..].split_once(':')?;
+            let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            (
+                &rest[..at],
+                if &rest[at..=at] == "-" {
+                    -offset
+                } else {
+                    offset
+                },
+            )
+        }
+    };
+    let time = time.split_once('.').map_or(time, |(whole, _)| whole);
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    u64::try_from(seconds - offset).ok()
+}
+
+// Days from 1970-01-01 to a proleptic Gregorian date, after Howard Hinnant's
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Unix seconds as RFC 3339, for tests to write sign-in messages with
+#[cfg(test)]
+pub fn format_timestamp(unix: u64) -> String {
+    let (days, seconds) = ((unix / 86_400) as i64 + 719_468, unix % 86_400);
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.000Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// Checks the message is signed in to this app on Farcaster's chain, and is good now:
+// issued no earlier than its nonce could have been, not yet expired, and not waiting
+// for a Not Before still to come
+fn check_message(message: &SiwfMessage, domain: &str) -> Result<(), AppError> {
+    let refuse = |problem: &str| AppError::Unauthorized(format!("Sign-in message {}", problem));
+    if message.domain != expected_domain(domain) {
+        warn!("Sign-in message for another domain: {}", message.domain);
+        return Err(refuse("is for another domain"));
+    }
+    let same_origin = match (url::Url::parse(&message.uri), url::Url::parse(domain)) {
+        (Ok(uri), Ok(domain)) => uri.origin() == domain.origin(),
+        _ => false,
+    };
+    if !same_origin {
+        return Err(refuse("is for another URI"));
+    }
+    if message.chain_id != SIWF_CHAIN_ID {
+        return Err(refuse("is for another chain"));
+    }
+    let now = unix_now();
+    if message.issued_at > now + CLOCK_SKEW_SECS
+        || message.issued_at + NONCE_TTL.as_secs() + CLOCK_SKEW_SECS < now
+    {
+        return Err(refuse("was not issued just now"));
+    }
+    if message
+        .expiration_time
+        .is_some_and(|expires| expires <= now)
+    {
+        return Err(refuse("has expired"));
+    }
+    if message
+        .not_before
+        .is_some_and(|not_before| not_before > now + CLOCK_SKEW_SECS)
+    {
+        return Err(refuse("is not valid yet"));
+    }
+    Ok(())
+}
+
+// The hash a wallet signs for personal_sign (EIP-191)
+pub fn personal_message_hash(message: &str) -> [u8; 32] {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message.as_bytes());
+    tx::keccak256(&prefixed)
+}
+
+// What a sign-in message's domain must be: DOMAIN's host, and port if it has one
+fn expected_domain(domain: &str) -> String {
+    let url = url::Url::parse(domain).ok();
+    let host = url
+        .as_ref()
+        .and_then(|url| url.host_str())
+        .unwrap_or_default();
+    match url.as_ref().and_then(|url| url.port()) {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+#[derive(Deserialize)]
+struct IdRegistryEvent {
+    fid: u64,
+}
+
+// Checks the signature recovers to the message's address
+fn verify_signature(message: &SiwfMessage, text: &str, signature: &str) -> Result<(), AppError> {
+    let signature: [u8; 65] = hex::decode(signature.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| AppError::BadRequest("Invalid signature".to_string()))?;
+    let signer = tx::recover_signer(&personal_message_hash(text), &signature);
+    if signer != Some(message.address) {
+        return Err(AppError::Unauthorized(
+            "Sign-in signature doesn't match its address".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Checks the message's address is the custody address of the fid it signs in as
+async fn verify_custody(
+    client: &reqwest::Client,
+    config: &Config,
+    message: &SiwfMessage,
+) -> Result<(), AppError> {
+    let hub_url = &config.hub_url;
+    let response = client
+        .get(format!("{}/v1/onChainIdRegistryEventByAddress", hub_url))
+        .query(&[("address", format!("0x{}", hex::encode(message.address)))])
+        .send()
+        .await
+        .map_err(|err| {
+            error!("Failed to reach Farcaster hub at {}: {}", hub_url, err);
+            AppError::InternalServerError
+        })?;
+    // Hubs answer with an error status for addresses that don't own an fid
+    if response.status().is_client_error() {
+        return Err(not_custody());
+    }
+    if !response.status().is_success() {
+        error!(
+            "Hub returned status {} for a custody address",
+            response.status()
+        );
+        return Err(AppError::InternalServerError);
+    }
+    let event: IdRegistryEvent = response.json().await.map_err(|err| {
+        error!("Unexpected response from Farcaster hub: {}", err);
+        AppError::InternalServerError
+    })?;
+    if event.fid != message.fid {
+        return Err(not_custody());
+    }
+    Ok(())
+}
+
+fn not_custody() -> AppError {
+    AppError::Unauthorized("Address is not the fid's custody address".to_string())
+}
+
+#[derive(Serialize)]
+struct NonceResponse {
+    nonce: String,
+}
+
+pub async fn nonce(sessions: web::Data<Sessions>) -> HttpResponse {
+    HttpResponse::Ok().json(NonceResponse {
+        nonce: sessions.issue_nonce(),
+    })
+}
+
+// A SIWF message and its signature, as the mini app SDK's signIn returns them
+#[derive(Deserialize)]
+pub struct SignInRequest {
+    message: String,
+    signature: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignInResponse {
+    token: String,
+    fid: u64,
+    // Unix seconds
+    expires_at: u64,
+}
+
+// Unlike frame actions, sign-ins are checked even with SKIP_VALIDATION, as a session
+// lets its holder act as the fid for as long as it lasts
+pub async fn sign_in(
+    req: web::Json<SignInRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    sessions: web::Data<Sessions>,
+) -> Result<HttpResponse, AppError> {
+    let message = parse_message(&req.message)?;
+    check_message(&message, &config.domain)?;
+    // Only a signature from the address uses the nonce up, so no one else can spend it
+    verify_signature(&message, &req.message, &req.signature)?;
+    if !sessions.take_nonce(&message.nonce) {
+        return Err(AppError::Unauthorized(
+            "Unknown or expired nonce".to_string(),
+        ));
+    }
+    verify_custody(&client, &config, &message).await?;
+
+    info!("fid {} signed in", message.fid);
+    let (token, expires_at) = sessions.mint(message.fid);
+    Ok(HttpResponse::Ok().json(SignInResponse {
+        token,
+        fid: message.fid,
+        expires_at,
+    }))
+}
+
+// The signed-in user of an API call, from its bearer token
+pub struct Session {
+    pub fid: u64,
+}
+
+impl FromRequest for Session {
+    type Error = AppError;
+    type Future = Ready<Result<Self, AppError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let fid = match (token, req.app_data::<web::Data<Sessions>>()) {
+            (Some(token), Some(sessions)) => sessions.verify(token.trim()),
+            _ => None,
+        };
+        ready(
+            fid.map(|fid| Session { fid })
+                .ok_or_else(|| AppError::Unauthorized("Sign in with Farcaster first".to_string())),
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct MeResponse {
+    fid: u64,
+}
+
+// Who the token belongs to, so a page can check its session is still good
+pub async fn me(session: Session) -> HttpResponse {
+    HttpResponse::Ok().json(MeResponse { fid: session.fid })
+}
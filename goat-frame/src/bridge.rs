@@ -0,0 +1,222 @@
+use crate::config::Config;
+use crate::countdown::unix_now;
+use crate::errors::AppError;
+use crate::frame_html::FrameMeta;
+use crate::frame_logic::Button;
+use crate::receipts::Receipts;
+use crate::tx::{self, TransactionParams, TransactionResponse, TxStatus};
+use log::{error, warn};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// The GOAT side's deposit of the value sent, for a recipient on a chain, and the
+// destination side's record of the deposits it has paid out
+const DEPOSIT_SIGNATURE: &str = "deposit(address,uint256)";
+const PROCESSED_SIGNATURE: &str = "processed(bytes32)";
+
+// Template the status frame draws a deposit with
+const STATUS_TEMPLATE: &str = "bridge_status";
+
+// A deposit still on its way is looked for on the destination chain at most this
+// often, however many times its image is drawn
+const RECHECK: Duration = Duration::from_secs(10);
+
+// Past this many tracked deposits the oldest are forgotten, and looked up afresh
+// if asked for again
+const MAX_TRACKED: usize = 10_000;
+
+// Where a bridge deposit is: its GOAT-side transaction as its receipt has it until
+// it's mined, then on its way until the destination chain has paid it out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BridgeStatus {
+    Sent,
+    Pending,
+    Bridging,
+    Arrived,
+    Failed,
+}
+
+impl BridgeStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            BridgeStatus::Sent => "Sent",
+            BridgeStatus::Pending => "Pending",
+            BridgeStatus::Bridging => "Bridging",
+            BridgeStatus::Arrived => "Arrived",
+            BridgeStatus::Failed => "Failed",
+        }
+    }
+
+    // The badge's color, the same in either theme and as TxStatus's where they match
+    pub fn color(self) -> &'static str {
+        match self {
+            BridgeStatus::Sent => TxStatus::Sent.color(),
+            BridgeStatus::Pending => TxStatus::Pending.color(),
+            BridgeStatus::Bridging => "#006fee",
+            BridgeStatus::Arrived => TxStatus::Confirmed.color(),
+            BridgeStatus::Failed => TxStatus::Failed.color(),
+        }
+    }
+
+    // Neither a paid out nor a reverted deposit goes anywhere else
+    pub fn is_settled(self) -> bool {
+        matches!(self, BridgeStatus::Arrived | BridgeStatus::Failed)
+    }
+}
+
+// Whether BRIDGE_DESTINATION_CONTRACT has paid out the deposit sent in hash
+pub async fn fetch_arrived(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    contract: &[u8; 20],
+    hash: &str,
+) -> Result<bool, String> {
+    let deposit = hex::decode(hash.trim_start_matches("0x"))
+        .map_err(|_| format!("invalid transaction hash {:?}", hash))?;
+    let mut input = tx::function_selector(PROCESSED_SIGNATURE).to_vec();
+    input.extend_from_slice(&deposit);
+    let output = tx::eth_call(client, rpc_url, contract, &input).await?;
+    tx::decode_amount(&output, 0)
+        .map(|processed| processed != 0)
+        .ok_or_else(|| format!("invalid processed flag 0x{}", hex::encode(&output)))
+}
+
+// What's known of the deposits users sent. Arrived ones are kept as they are; those
+// on their way are looked for again once RECHECK has passed.
+#[derive(Default)]
+pub struct Bridge {
+    tracked: Mutex<HashMap<String, (Instant, BridgeStatus)>>,
+}
+
+impl Bridge {
+    // The deposit sent in hash, a checked transaction id. Sent when there's no
+    // RPC_URL to ask; a failed lookup on the destination chain keeps it Bridging.
+    pub async fn status(
+        &self,
+        client: &reqwest::Client,
+        config: &Config,
+        receipts: &Receipts,
+        hash: &str,
+    ) -> BridgeStatus {
+        let known = self.tracked.lock().unwrap().get(hash).copied();
+        if let Some((checked, status)) = known {
+            if status.is_settled() || checked.elapsed() < RECHECK {
+                return status;
+            }
+        }
+        let status = match receipts
+            .status(client, config.rpc_url.as_deref(), hash)
+            .await
+        {
+            TxStatus::Sent => return BridgeStatus::Sent,
+            TxStatus::Pending => return BridgeStatus::Pending,
+            TxStatus::Failed => BridgeStatus::Failed,
+            TxStatus::Confirmed => match self.arrived(client, config, hash).await {
+                Ok(true) => BridgeStatus::Arrived,
+                Ok(false) => BridgeStatus::Bridging,
+                Err(err) => {
+                    warn!("Failed to look for bridge deposit {}: {}", hash, err);
+                    BridgeStatus::Bridging
+                }
+            },
+        };
+        let mut tracked = self.tracked.lock().unwrap();
+        if tracked.len() >= MAX_TRACKED && !tracked.contains_key(hash) {
+            if let Some(oldest) = tracked
+                .iter()
+                .min_by_key(|(_, (checked, _))| *checked)
+                .map(|(hash, _)| hash.clone())
+            {
+                tracked.remove(&oldest);
+            }
+        }
+        tracked.insert(hash.to_string(), (Instant::now(), status));
+        status
+    }
+
+    async fn arrived(
+        &self,
+        client: &reqwest::Client,
+        config: &Config,
+        hash: &str,
+    ) -> Result<bool, String> {
+        let (Some(contract), Some(rpc_url)) = (
+            &config.bridge_destination_contract,
+            &config.bridge_destination_rpc_url,
+        ) else {
+            return Err("BRIDGE_DESTINATION_CONTRACT is not configured".to_string());
+        };
+        let contract = tx::parse_address(contract).map_err(|err| err.to_string())?;
+        fetch_arrived(client, rpc_url, &contract, hash).await
+    }
+}
+
+// Bridges the GOAT the user typed into the Top-up input to the same address, the
+// wallet the client connected, on BRIDGE_DESTINATION_CHAIN_ID
+pub async fn bridge_transaction(
+    address: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<TransactionResponse, AppError> {
+    let (Some(contract), Some(destination_chain_id)) =
+        (&config.bridge_contract, config.bridge_destination_chain_id)
+    else {
+        error!("BRIDGE_CONTRACT is not configured");
+        return Err(AppError::InternalServerError);
+    };
+    let recipient = tx::parse_address(address.unwrap_or_default())?;
+    let value = tx::parse_amount(amount.unwrap_or_default())?;
+    if value == 0 {
+        return Err(AppError::BadRequest(
+            "Enter how much GOAT to bridge".to_string(),
+        ));
+    }
+    tx::check_balance(client, config, &recipient, value).await?;
+
+    // Checked when the config was loaded
+    let contract = tx::parse_address(contract)?;
+    let mut data = tx::function_selector(DEPOSIT_SIGNATURE).to_vec();
+    data.extend_from_slice(&tx::encode_address(&recipient));
+    data.extend_from_slice(&tx::encode_uint(destination_chain_id));
+
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([{
+                "type": "function",
+                "name": "deposit",
+                "stateMutability": "payable",
+                "inputs": [
+                    { "name": "recipient", "type": "address" },
+                    { "name": "destinationChainId", "type": "uint256" }
+                ],
+                "outputs": []
+            }]),
+            to: format!("0x{}", hex::encode(contract)),
+            data: format!("0x{}", hex::encode(data)),
+            value: value.to_string(),
+        },
+    })
+}
+
+// The frame a deposit is followed on, whose Refresh posts back to /bridge/<hash> to
+// look again. Its image's URL changes every RECHECK so clients fetch it fresh.
+pub fn status_frame(hash: &str, config: &Config) -> FrameMeta {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("tx", hash)
+        .append_pair("t", &(unix_now() / RECHECK.as_secs()).to_string())
+        .finish();
+    let image = format!(
+        "{}/images/{}.png?{}",
+        config.asset_origin(),
+        STATUS_TEMPLATE,
+        query
+    );
+    FrameMeta::new(&image, &format!("{}/bridge/{}", config.domain, hash))
+        .image_alt("Your GOAT on its way across the bridge")
+        .buttons(vec![Button::new("Refresh")])
+        .open_frames(config.open_frames)
+}
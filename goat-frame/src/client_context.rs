@@ -0,0 +1,41 @@
+use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+use serde::Deserialize;
+
+// What a mini app reads from sdk.context at launch, passed back to us by its page so
+// screens can be personalized. Clients don't sign it, so it only ever changes how a
+// screen looks, never which buttons it has or what they do.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ClientContext {
+    pub user: ContextUser,
+    // The viewer's language, e.g. "es-MX", which the page adds from navigator.language
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+// Profile fields are missing for users who haven't set them
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextUser {
+    pub fid: u64,
+    pub username: Option<String>,
+    pub display_name: Option<String>,
+    pub pfp_url: Option<String>,
+    pub location: Option<ContextLocation>,
+}
+
+// Where the user says they are, e.g. "Austin, TX, USA"
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextLocation {
+    pub place_id: Option<String>,
+    pub description: String,
+}
+
+// Decodes the ?context= a mini app page adds when it reloads itself: base64url JSON.
+// Anything unreadable is ignored, since the page works the same without it.
+pub fn decode(param: &str) -> Option<ClientContext> {
+    let json = BASE64_URL_SAFE_NO_PAD
+        .decode(param.trim_end_matches('='))
+        .ok()?;
+    serde_json::from_slice(&json).ok()
+}
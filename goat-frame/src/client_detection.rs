@@ -0,0 +1,105 @@
+use actix_web::http::header;
+use actix_web::HttpRequest;
+
+// The undated version every current client understands
+pub const VNEXT: &str = "vNext";
+
+// The first dated versions whose clients collect text and send transactions. Clients
+// pinned to an earlier date get screens without what they can't show.
+const TEXT_INPUT_SINCE: &str = "2024-02-01";
+const TRANSACTIONS_SINCE: &str = "2024-02-09";
+
+// The app showing the frame, as far as the request tells us
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ClientKind {
+    Warpcast,
+    // Another Farcaster client, e.g. one posting signed messages without a Warpcast user agent
+    Farcaster,
+    // XMTP and Lens clients posting with a client_protocol
+    OpenFrames,
+    // A GET from something that didn't identify itself, such as a link previewer
+    #[default]
+    Unknown,
+}
+
+impl ClientKind {
+    // Open Frames clients only reliably show 1.91:1 images. Everyone else gets each
+    // screen's configured ratio, including unknown clients, which we can't second-guess.
+    pub fn supports_square_images(self) -> bool {
+        self != ClientKind::OpenFrames
+    }
+}
+
+// The frame version a client speaks and what it can do with a frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    // What fc:frame names: vNext, or the dated version a Farcaster client posted with
+    pub version: String,
+    pub transactions: bool,
+    pub text_input: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            version: VNEXT.to_string(),
+            transactions: true,
+            text_input: true,
+        }
+    }
+}
+
+// Reads a post's client_protocol, e.g. "farcaster@2024-02-01" or "xmtp@2024-02-09".
+// Clients that name no version, vNext, or one that isn't a date (like Lens's "1.1")
+// are taken to support everything, as we can't second-guess them.
+pub fn capabilities(client_protocol: Option<&str>) -> Capabilities {
+    let Some((protocol, version)) = client_protocol.and_then(|p| p.split_once('@')) else {
+        return Capabilities::default();
+    };
+    if !is_date(version) {
+        return Capabilities::default();
+    }
+    Capabilities {
+        // Open Frames clients read of:version, which stays vNext
+        version: if protocol == "farcaster" {
+            version.to_string()
+        } else {
+            VNEXT.to_string()
+        },
+        // YYYY-MM-DD dates order the same as their strings
+        transactions: version >= TRANSACTIONS_SINCE,
+        text_input: version >= TEXT_INPUT_SINCE,
+    }
+}
+
+fn is_date(version: &str) -> bool {
+    let parts: Vec<&str> = version.split('-').collect();
+    matches!(parts.as_slice(), [year, month, day]
+        if year.len() == 4 && month.len() == 2 && day.len() == 2
+            && parts.iter().all(|part| part.bytes().all(|b| b.is_ascii_digit())))
+}
+
+// Works out the client from the user agent and a frame post's protocol: None for
+// GETs, which carry no payload, and "farcaster" for posts without a client_protocol
+pub fn detect(user_agent: Option<&str>, client_protocol: Option<&str>) -> ClientKind {
+    let protocol = client_protocol.map(|protocol| protocol.split('@').next().unwrap_or_default());
+    match protocol {
+        Some("farcaster") | None => {}
+        Some(_) => return ClientKind::OpenFrames,
+    }
+
+    let user_agent = user_agent.unwrap_or_default().to_ascii_lowercase();
+    if user_agent.contains("warpcast") {
+        ClientKind::Warpcast
+    } else if protocol.is_some() {
+        ClientKind::Farcaster
+    } else {
+        ClientKind::Unknown
+    }
+}
+
+pub fn user_agent(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+}
@@ -1,12 +1,875 @@
+use crate::errors::ConfigError;
+use crate::frame_logic::AspectRatio;
+use crate::images::theme::{Theme, Themes};
+use crate::tx;
 use serde::Deserialize;
+use std::path::Path;
+use url::{Host, Url};
 
 #[derive(Clone, Deserialize)]
 pub struct Config {
     pub domain: String,
+
+    // Origin of a CDN in front of this server, e.g. https://cdn.example.com; frame
+    // images and asset URLs point there instead of DOMAIN, and the CDN fetches what
+    // it hasn't cached from DOMAIN. Unset serves them from DOMAIN.
+    #[serde(default)]
+    pub cdn_base_url: Option<String>,
+
+    // Address the HTTP server binds to
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+
+    // Farcaster hub used to validate signed frame messages
+    #[serde(default = "default_hub_url")]
+    pub hub_url: String,
+
+    // Trust untrusted_data as-is; only meant for local development and tests
+    #[serde(default)]
+    pub skip_validation: bool,
+
+    // Also serve Open Frames clients such as XMTP and Lens. Their posts can't be checked
+    // against a Farcaster hub, so they're handled like unsigned requests without an fid.
+    #[serde(default)]
+    pub open_frames: bool,
+
+    // Key fc:frame:state is signed with so clients can't forge where they are in a
+    // flow; unset sends state unsigned
+    #[serde(default)]
+    pub state_secret: Option<String>,
+
+    // How long a frame's state stays good; a press on a frame left open longer starts
+    // the flow over rather than act on what it showed. 0 never expires state.
+    #[serde(default = "default_state_ttl_secs")]
+    pub state_ttl_secs: u64,
+
+    // Origins allowed to call /api/frame and load /assets cross-origin, comma separated;
+    // when unset, any while DOMAIN is a loopback address (local development) and
+    // DOMAIN's own otherwise. "*" on its own is only taken in local development.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+
+    // Contract the Buy & Boost transaction is sent to, and the chain it lives on
+    #[serde(default)]
+    pub boost_contract: Option<String>,
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u64,
+
+    // Uniswap V2-style router the Buy flow swaps GOAT through for SWAP_TOKEN, by way of
+    // the router's wrapped GOAT, taking up to SWAP_SLIPPAGE_BPS (basis points, 50 is
+    // 0.5%) less than quoted. Quotes come from RPC_URL, so it must be set too.
+    #[serde(default)]
+    pub swap_router: Option<String>,
+    #[serde(default)]
+    pub swap_wrapped_goat: Option<String>,
+    #[serde(default)]
+    pub swap_token: Option<String>,
+    #[serde(default = "default_swap_slippage_bps")]
+    pub swap_slippage_bps: u32,
+    // That router's SWAP_WRAPPED_GOAT/SWAP_TOKEN pair, whose reserves price the token side
+    // of Add Liquidity. Needs the SWAP_ settings, and the same slippage applies.
+    #[serde(default)]
+    pub liquidity_pair: Option<String>,
+
+    // Contract the Bid screen's auction runs on, read with highestBid() and endTime()
+    // and bid on with bid(). A bid must beat the highest by AUCTION_MIN_INCREMENT_BPS
+    // (basis points, 500 is 5%). It's read again every AUCTION_POLL_SECS, to draw and
+    // to tell bidders who bid here when they're outbid. Needs RPC_URL.
+    #[serde(default)]
+    pub auction_contract: Option<String>,
+    #[serde(default = "default_auction_min_increment_bps")]
+    pub auction_min_increment_bps: u32,
+    #[serde(default = "default_auction_poll_secs")]
+    pub auction_poll_secs: u64,
+
+    // Contract the Reward screen reads the viewer's claimable GOAT from, with
+    // claimable(address), and whose claim() its Claim button calls. Needs RPC_URL.
+    #[serde(default)]
+    pub rewards_contract: Option<String>,
+
+    // Bridge the Top-up screen sends GOAT out through to the user's own address on
+    // BRIDGE_DESTINATION_CHAIN_ID, with BRIDGE_CONTRACT's deposit(address,uint256).
+    // BRIDGE_DESTINATION_CONTRACT, read through BRIDGE_DESTINATION_RPC_URL, answers
+    // processed(bytes32) for a deposit's transaction hash once it has paid out there.
+    // Set all four or none; needs RPC_URL.
+    #[serde(default)]
+    pub bridge_contract: Option<String>,
+    #[serde(default)]
+    pub bridge_destination_chain_id: Option<u64>,
+    #[serde(default)]
+    pub bridge_destination_rpc_url: Option<String>,
+    #[serde(default)]
+    pub bridge_destination_contract: Option<String>,
+
+    // JSON-RPC endpoint for that chain. When set, the wallet's balance is checked before
+    // a Buy & Boost transaction is built, so the user hears why rather than seeing it fail.
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+
+    // Signs the session tokens mini app pages get from Sign In With Farcaster. Unset
+    // picks a random secret at startup, which signs everyone out on a restart.
+    #[serde(default)]
+    pub session_secret: Option<String>,
+
+    // How long a session token lasts
+    #[serde(default = "default_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+
+    // The app account that asks users for signers, so we can cast for users who opt
+    // in: its fid, its custody address, and a JSON-RPC signer holding that address's
+    // key (e.g. Clef) to sign requests with eth_signTypedData_v4. Set all three or none.
+    #[serde(default)]
+    pub app_fid: Option<u64>,
+    #[serde(default)]
+    pub app_custody_address: Option<String>,
+    #[serde(default)]
+    pub app_signer_rpc_url: Option<String>,
+
+    // Client API signed key requests are made through
+    #[serde(default = "default_signed_key_request_url")]
+    pub signed_key_request_url: String,
+
+    // File signer keys are kept in; unset keeps them in memory only
+    #[serde(default)]
+    pub signers_path: Option<String>,
+
+    // Pool APR source for screens with apr_images, and the percent that counts as high
+    #[serde(default)]
+    pub apr_url: Option<String>,
+    #[serde(default = "default_high_apr_percent")]
+    pub high_apr_percent: f64,
+
+    // Token price history for image templates' charts, with {range} for "24h" or "7d",
+    // and how long a fetch is reused
+    #[serde(default)]
+    pub price_url: Option<String>,
+    #[serde(default = "default_price_ttl_secs")]
+    pub price_ttl_secs: u64,
+
+    // Ranked rows for image templates' leaderboards, and how long a fetch is reused
+    #[serde(default)]
+    pub leaderboard_url: Option<String>,
+    #[serde(default = "default_leaderboard_ttl_secs")]
+    pub leaderboard_ttl_secs: u64,
+
+    // The Moxie fan token's current price for image templates, from a bonding curve
+    // or DEX quote service answering {"price": 0.043}, and how long a fetch is reused
+    #[serde(default)]
+    pub moxie_price_url: Option<String>,
+    #[serde(default = "default_moxie_price_ttl_secs")]
+    pub moxie_price_ttl_secs: u64,
+
+    // Image shape for screens that don't set aspect_ratio, "wide" or "square";
+    // unset leaves it to the client's 1.91:1 default
+    #[serde(default)]
+    pub default_aspect_ratio: Option<AspectRatio>,
+
+    // Checkout site post_redirect buttons with a "/" path send users to
+    #[serde(default)]
+    pub checkout_base: Option<String>,
+
+    // Where the Top Up button on a not-enough-GOAT error frame sends users, e.g. a
+    // bridge; unset leaves the button off
+    #[serde(default)]
+    pub top_up_url: Option<String>,
+
+    // Address the Top-up screen asks users to send GOAT to, drawn into its image as
+    // text and a QR code; unset leaves both off
+    #[serde(default)]
+    pub deposit_address: Option<String>,
+
+    // Hosts post_redirect buttons may send users to, comma separated; the
+    // Moxie web app the Rewards screen links to unless set
+    #[serde(default = "default_redirect_allowlist")]
+    pub redirect_allowlist: Vec<String>,
+
+    // Fids that already hold a balance, comma separated; buttons marked
+    // hide_for_known are left off their screens for these users
+    #[serde(default)]
+    pub known_fids: Vec<u64>,
+
+    // Frame requests allowed per fid (or IP without validation) each minute; 0 disables the limit
+    #[serde(default = "default_rate_limit_per_min")]
+    pub rate_limit_per_min: u32,
+
+    // How long a response is replayed for retries with the same idempotency key; 0
+    // disables. Has to cover MAX_MESSAGE_AGE_SECS, or a signed retry the cache has
+    // forgotten would be refused as a replay.
+    #[serde(default = "default_idempotency_ttl_secs")]
+    pub idempotency_ttl_secs: u64,
+
+    // How long the page shown after a transaction is replayed for repeat posts of the
+    // same transactionId; 0 disables
+    #[serde(default = "default_tx_confirmation_ttl_secs")]
+    pub tx_confirmation_ttl_secs: u64,
+
+    // Signed frame messages older than this, or replayed within it, are refused; 0 disables
+    #[serde(default = "default_max_message_age_secs")]
+    pub max_message_age_secs: u64,
+
+    // File click events are appended to as JSON lines; unset keeps no record
+    #[serde(default)]
+    pub analytics_path: Option<String>,
+
+    // How long shutdown waits for in-flight requests before dropping them
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    // How long clients may cache /assets responses
+    #[serde(default = "default_asset_cache_secs")]
+    pub asset_cache_secs: u64,
+
+    // Whether /assets/ lists its files; handy locally, but keep it off in production
+    #[serde(default)]
+    pub asset_listing: bool,
+
+    // Where startup writes smaller copies of the PNG assets that frames are served
+    // instead, and whether to skip that and serve the originals
+    #[serde(default = "default_asset_cache_path")]
+    pub asset_cache_path: String,
+    #[serde(default)]
+    pub skip_asset_optimization: bool,
+
+    // Adds x-frame-handler, x-frame-state and server-timing headers to frame responses
+    // for troubleshooting; they expose unsigned state, so keep it off in production
+    #[serde(default)]
+    pub debug_headers: bool,
+
+    // File mini app notification tokens are kept in; unset keeps them in memory only
+    #[serde(default)]
+    pub notifications_path: Option<String>,
+
+    // Hosts clients may have us post notifications to, comma separated. A webhook event
+    // naming any other, or a URL that isn't https, is refused rather than stored.
+    #[serde(default = "default_notification_hosts")]
+    pub notification_hosts: Vec<String>,
+
+    // File the gifts sent from the Gift flow are appended to as JSON lines; unset keeps
+    // them in memory only
+    #[serde(default)]
+    pub gifts_path: Option<String>,
+
+    // Link preview shown where frames aren't supported. OG_IMAGE is a file under
+    // assets/; unset uses the frame's own image.
+    #[serde(default = "default_og_title")]
+    pub og_title: String,
+    #[serde(default = "default_og_description")]
+    pub og_description: String,
+    #[serde(default)]
+    pub og_image: Option<String>,
+
+    // Mini app name and icon (a file under assets/) listed in /.well-known/farcaster.json
+    #[serde(default = "default_miniapp_name")]
+    pub miniapp_name: String,
+    #[serde(default = "default_miniapp_icon")]
+    pub miniapp_icon: String,
+
+    // Image (a file under assets/) and #rrggbb background clients show while the mini
+    // app loads, e.g. after a launch_frame button
+    #[serde(default = "default_miniapp_icon")]
+    pub miniapp_splash_image: String,
+    #[serde(default = "default_miniapp_splash_background")]
+    pub miniapp_splash_background: String,
+
+    // Signed account association proving a Farcaster account owns DOMAIN, as generated
+    // by a Farcaster client's manifest tool; set all three or none
+    #[serde(default)]
+    pub account_association_header: Option<String>,
+    #[serde(default)]
+    pub account_association_payload: Option<String>,
+    #[serde(default)]
+    pub account_association_signature: Option<String>,
+
+    // PEM certificate chain and private key for serving HTTPS directly; set both or
+    // neither, and leave them unset when a proxy terminates TLS
+    #[serde(default)]
+    pub tls_cert_path: Option<String>,
+    #[serde(default)]
+    pub tls_key_path: Option<String>,
+
+    // Screens and buttons, loaded once at startup
+    #[serde(default = "default_frames_path")]
+    pub frames_path: String,
+
+    // Directory of per-locale translation files, e.g. locales/es.toml, loaded at startup
+    #[serde(default = "default_locales_path")]
+    pub locales_path: String,
+
+    // Locale for viewers whose mini app didn't report one, or reported one we have no
+    // translations for; unset shows frames.toml's text
+    #[serde(default)]
+    pub default_locale: Option<String>,
+
+    // Directory of SVG templates and JSON layouts served as PNGs at /images/<name>.png,
+    // loaded at startup
+    #[serde(default = "default_templates_path")]
+    pub templates_path: String,
+
+    // TrueType fonts generated images draw text in, comma separated and in fallback
+    // order, e.g. a brand font then a CJK one; unset uses the built-in bitmap font
+    #[serde(default)]
+    pub font_paths: Vec<String>,
+
+    // Directory of color emoji PNGs named like emoji_u1f525.png, e.g. the png/128
+    // directory of github.com/googlefonts/noto-emoji; unset draws emoji as "?"
+    #[serde(default)]
+    pub emoji_path: Option<String>,
+
+    // Branding stamped onto every generated image, for deployments that white-label
+    // the store: a logo under assets/ drawn in the bottom right corner, e.g.
+    // partner/logo.png (transparent PNGs keep their transparency), and footer text
+    // along the bottom left
+    #[serde(default)]
+    pub watermark_logo: Option<String>,
+    #[serde(default)]
+    pub watermark_text: Option<String>,
+
+    // Theme generated images use for viewers who haven't picked one, "dark" or "light"
+    #[serde(default)]
+    pub default_theme: Theme,
+
+    // Colors generated images draw in per theme, comma separated "name=#rrggbb" over
+    // the built-in background, text, muted, accent and banner, e.g.
+    // "accent=#f59e0b,card=#202020"
+    #[serde(default)]
+    pub theme_dark_colors: Vec<String>,
+    #[serde(default)]
+    pub theme_light_colors: Vec<String>,
+
+    // How long a fid's name and pfp are reused for generated images; 0 fetches each time
+    #[serde(default = "default_profile_ttl_secs")]
+    pub profile_ttl_secs: u64,
+
+    // How many generated images are kept in memory, and for how long, so the same one
+    // asked for again isn't drawn again; a size of 0 draws every request
+    #[serde(default = "default_image_cache_size")]
+    pub image_cache_size: usize,
+    #[serde(default = "default_image_cache_ttl_secs")]
+    pub image_cache_ttl_secs: u64,
+}
+
+fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_port() -> u16 {
+    8080
+}
+
+fn default_hub_url() -> String {
+    "https://nemes.farcaster.xyz:2281".to_string()
+}
+
+// Where Farcaster's own clients take notifications
+fn default_notification_hosts() -> Vec<String> {
+    vec![
+        "api.farcaster.xyz".to_string(),
+        "api.warpcast.com".to_string(),
+    ]
+}
+
+fn default_redirect_allowlist() -> Vec<String> {
+    vec!["moxie.xyz".to_string()]
+}
+
+fn default_swap_slippage_bps() -> u32 {
+    50
+}
+
+// Base mainnet
+fn default_chain_id() -> u64 {
+    8453
+}
+
+fn default_high_apr_percent() -> f64 {
+    10.0
+}
+
+fn default_price_ttl_secs() -> u64 {
+    60
+}
+
+fn default_leaderboard_ttl_secs() -> u64 {
+    300
+}
+
+fn default_moxie_price_ttl_secs() -> u64 {
+    60
+}
+
+fn default_auction_min_increment_bps() -> u32 {
+    500
+}
+
+fn default_auction_poll_secs() -> u64 {
+    15
+}
+
+fn default_rate_limit_per_min() -> u32 {
+    60
+}
+
+fn default_state_ttl_secs() -> u64 {
+    3600
+}
+
+// As long as MAX_MESSAGE_AGE_SECS's default
+fn default_idempotency_ttl_secs() -> u64 {
+    600
+}
+
+fn default_tx_confirmation_ttl_secs() -> u64 {
+    86400
+}
+
+fn default_signed_key_request_url() -> String {
+    "https://api.warpcast.com".to_string()
+}
+
+fn default_session_ttl_secs() -> u64 {
+    86400
+}
+
+fn default_max_message_age_secs() -> u64 {
+    600
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+fn default_asset_cache_path() -> String {
+    "asset-cache".to_string()
+}
+
+fn default_asset_cache_secs() -> u64 {
+    3600
+}
+
+fn default_og_title() -> String {
+    "GOAT Frame".to_string()
+}
+
+fn default_og_description() -> String {
+    "Buy, boost and gift GOAT right from your feed.".to_string()
+}
+
+fn default_miniapp_name() -> String {
+    "GOAT Store".to_string()
+}
+
+fn default_miniapp_icon() -> String {
+    "main.png".to_string()
+}
+
+fn default_miniapp_splash_background() -> String {
+    "#ffffff".to_string()
+}
+
+fn default_frames_path() -> String {
+    "frames.toml".to_string()
+}
+
+fn default_locales_path() -> String {
+    "locales".to_string()
+}
+
+fn default_templates_path() -> String {
+    "templates".to_string()
+}
+
+fn default_profile_ttl_secs() -> u64 {
+    600
+}
+
+fn default_image_cache_size() -> usize {
+    256
+}
+
+// As long as clients are told to cache them
+fn default_image_cache_ttl_secs() -> u64 {
+    60
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self, envy::Error> {
-        envy::from_env::<Config>()
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_iter(std::env::vars())
+    }
+
+    // Builds a Config from explicit KEY=value pairs, as from_env does with the environment
+    pub fn from_iter<I>(vars: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut config = envy::from_iter::<_, Config>(vars)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Where clients fetch images and assets from: the CDN when there is one
+    pub fn asset_origin(&self) -> &str {
+        self.cdn_base_url.as_deref().unwrap_or(&self.domain)
+    }
+
+    // Checks values that serde can't, normalizing them where that's unambiguous
+    pub fn validate(&mut self) -> Result<(), ConfigError> {
+        self.domain = normalize_origin("DOMAIN", &self.domain)?;
+        if let Some(cdn_base_url) = &self.cdn_base_url {
+            self.cdn_base_url = Some(normalize_origin("CDN_BASE_URL", cdn_base_url)?);
+        }
+        // Unset, local development may be called from anywhere and a deployment only
+        // from its own pages. A * alongside origins is dropped, with a warning, by
+        // AllowedOrigins::from_config.
+        let origins: Vec<&str> = self
+            .allowed_origins
+            .iter()
+            .map(|origin| origin.trim())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        if origins.is_empty() {
+            self.allowed_origins = if is_loopback(&self.domain) {
+                vec!["*".to_string()]
+            } else {
+                vec![domain_origin(&self.domain)]
+            };
+        } else if origins.iter().all(|origin| *origin == "*") && !is_loopback(&self.domain) {
+            return Err(ConfigError::Invalid(format!(
+                "ALLOWED_ORIGINS may only be * while DOMAIN is local, not {:?}",
+                self.domain
+            )));
+        }
+        // Compared with notification URLs' hosts, which parsing lowercases
+        self.notification_hosts = self
+            .notification_hosts
+            .iter()
+            .map(|host| host.trim().to_ascii_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect();
+        // Clients can't verify part of an account association
+        let association_parts = [
+            &self.account_association_header,
+            &self.account_association_payload,
+            &self.account_association_signature,
+        ]
+        .iter()
+        .filter(|part| part.is_some())
+        .count();
+        if association_parts != 0 && association_parts != 3 {
+            return Err(ConfigError::Invalid(
+                "ACCOUNT_ASSOCIATION_HEADER, ACCOUNT_ASSOCIATION_PAYLOAD and \
+                 ACCOUNT_ASSOCIATION_SIGNATURE must be set together"
+                    .to_string(),
+            ));
+        }
+        let app_parts = [
+            self.app_fid.is_some(),
+            self.app_custody_address.is_some(),
+            self.app_signer_rpc_url.is_some(),
+        ]
+        .iter()
+        .filter(|set| **set)
+        .count();
+        if app_parts != 0 && app_parts != 3 {
+            return Err(ConfigError::Invalid(
+                "APP_FID, APP_CUSTODY_ADDRESS and APP_SIGNER_RPC_URL must be set together"
+                    .to_string(),
+            ));
+        }
+        let background = self.miniapp_splash_background.as_str();
+        let is_hex_color = background.len() == 7
+            && background.starts_with('#')
+            && background[1..].bytes().all(|b| b.is_ascii_hexdigit());
+        if !is_hex_color {
+            return Err(ConfigError::Invalid(format!(
+                "MINIAPP_SPLASH_BACKGROUND {:?} must be a #rrggbb color",
+                background
+            )));
+        }
+        // Half a TLS setup would otherwise quietly serve plaintext
+        if self.tls_cert_path.is_some() != self.tls_key_path.is_some() {
+            return Err(ConfigError::Invalid(
+                "TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string(),
+            ));
+        }
+        let swap = [
+            ("SWAP_ROUTER", &self.swap_router),
+            ("SWAP_WRAPPED_GOAT", &self.swap_wrapped_goat),
+            ("SWAP_TOKEN", &self.swap_token),
+        ];
+        let swap_parts = swap.iter().filter(|(_, part)| part.is_some()).count();
+        if swap_parts != 0 && (swap_parts != 3 || self.rpc_url.is_none()) {
+            return Err(ConfigError::Invalid(
+                "SWAP_ROUTER, SWAP_WRAPPED_GOAT and SWAP_TOKEN must be set together, with \
+                 RPC_URL"
+                    .to_string(),
+            ));
+        }
+        for (name, address) in swap {
+            if let Some(address) = address {
+                tx::parse_address(address).map_err(|_| {
+                    ConfigError::Invalid(format!(
+                        "{} {:?} must be a 0x-prefixed 20 byte address",
+                        name, address
+                    ))
+                })?;
+            }
+        }
+        if let Some(pair) = &self.liquidity_pair {
+            if self.swap_router.is_none() {
+                return Err(ConfigError::Invalid(
+                    "LIQUIDITY_PAIR needs SWAP_ROUTER, SWAP_WRAPPED_GOAT and SWAP_TOKEN"
+                        .to_string(),
+                ));
+            }
+            tx::parse_address(pair).map_err(|_| {
+                ConfigError::Invalid(format!(
+                    "LIQUIDITY_PAIR {:?} must be a 0x-prefixed 20 byte address",
+                    pair
+                ))
+            })?;
+        }
+        // Signed messages are only refused as replays while they're young enough to
+        // be taken at all, so retries have to be answered from the cache that long
+        if self.max_message_age_secs != 0 && self.idempotency_ttl_secs < self.max_message_age_secs {
+            return Err(ConfigError::Invalid(format!(
+                "IDEMPOTENCY_TTL_SECS {} must be at least MAX_MESSAGE_AGE_SECS {}",
+                self.idempotency_ttl_secs, self.max_message_age_secs
+            )));
+        }
+        if self.swap_slippage_bps >= 10_000 {
+            return Err(ConfigError::Invalid(format!(
+                "SWAP_SLIPPAGE_BPS {} must be under 10000",
+                self.swap_slippage_bps
+            )));
+        }
+        if let Some(contract) = &self.boost_contract {
+            tx::parse_address(contract).map_err(|_| {
+                ConfigError::Invalid(format!(
+                    "BOOST_CONTRACT {:?} must be a 0x-prefixed 20 byte address",
+                    contract
+                ))
+            })?;
+        }
+        if let Some(contract) = &self.auction_contract {
+            if self.rpc_url.is_none() {
+                return Err(ConfigError::Invalid(
+                    "AUCTION_CONTRACT needs RPC_URL".to_string(),
+                ));
+            }
+            tx::parse_address(contract).map_err(|_| {
+                ConfigError::Invalid(format!(
+                    "AUCTION_CONTRACT {:?} must be a 0x-prefixed 20 byte address",
+                    contract
+                ))
+            })?;
+            if self.auction_poll_secs == 0 {
+                return Err(ConfigError::Invalid(
+                    "AUCTION_POLL_SECS must be at least 1".to_string(),
+                ));
+            }
+        }
+        if let Some(contract) = &self.rewards_contract {
+            if self.rpc_url.is_none() {
+                return Err(ConfigError::Invalid(
+                    "REWARDS_CONTRACT needs RPC_URL".to_string(),
+                ));
+            }
+            tx::parse_address(contract).map_err(|_| {
+                ConfigError::Invalid(format!(
+                    "REWARDS_CONTRACT {:?} must be a 0x-prefixed 20 byte address",
+                    contract
+                ))
+            })?;
+        }
+        // A deposit can't be followed to a chain that's only half configured
+        let bridge_parts = [
+            self.bridge_contract.is_some(),
+            self.bridge_destination_chain_id.is_some(),
+            self.bridge_destination_rpc_url.is_some(),
+            self.bridge_destination_contract.is_some(),
+        ];
+        if bridge_parts.contains(&true) {
+            if bridge_parts.contains(&false) || self.rpc_url.is_none() {
+                return Err(ConfigError::Invalid(
+                    "BRIDGE_CONTRACT, BRIDGE_DESTINATION_CHAIN_ID, BRIDGE_DESTINATION_RPC_URL \
+                     and BRIDGE_DESTINATION_CONTRACT must be set together, with RPC_URL"
+                        .to_string(),
+                ));
+            }
+            let contracts = [
+                ("BRIDGE_CONTRACT", &self.bridge_contract),
+                (
+                    "BRIDGE_DESTINATION_CONTRACT",
+                    &self.bridge_destination_contract,
+                ),
+            ];
+            for (name, contract) in contracts {
+                if let Some(contract) = contract {
+                    tx::parse_address(contract).map_err(|_| {
+                        ConfigError::Invalid(format!(
+                            "{} {:?} must be a 0x-prefixed 20 byte address",
+                            name, contract
+                        ))
+                    })?;
+                }
+            }
+        }
+        if let Some(address) = &self.deposit_address {
+            tx::parse_address(address).map_err(|_| {
+                ConfigError::Invalid(format!(
+                    "DEPOSIT_ADDRESS {:?} must be a 0x-prefixed 20 byte address",
+                    address
+                ))
+            })?;
+        }
+        // Variants written into assets/ would be hashed and served as assets themselves
+        let cache = Path::new(&self.asset_cache_path);
+        if cache.as_os_str().is_empty()
+            || cache.starts_with("assets")
+            || cache.starts_with("./assets")
+        {
+            return Err(ConfigError::Invalid(format!(
+                "ASSET_CACHE_PATH {:?} must be a directory outside assets/",
+                self.asset_cache_path
+            )));
+        }
+        Themes::from_config(self)?;
+        Ok(())
+    }
+}
+
+// The domain is interpolated as "{domain}/assets/...", so it must be an absolute
+// http(s) URL without a trailing slash
+fn normalize_origin(var: &str, domain: &str) -> Result<String, ConfigError> {
+    let invalid = |reason: &str| ConfigError::Invalid(format!("{} {:?} {}", var, domain, reason));
+
+    if domain.trim().is_empty() {
+        return Err(invalid("is empty"));
+    }
+    let url =
+        Url::parse(domain.trim()).map_err(|err| invalid(&format!("is not a URL: {}", err)))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(invalid("must start with http:// or https://"));
+    }
+    if url.host().is_none() {
+        return Err(invalid("has no host"));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid("must not have a query or fragment"));
+    }
+
+    Ok(domain.trim().trim_end_matches('/').to_string())
+}
+
+// DOMAIN's scheme, host and port, as browsers send it in Origin; DOMAIN is already
+// normalized
+fn domain_origin(domain: &str) -> String {
+    Url::parse(domain)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| domain.to_string())
+}
+
+fn is_loopback(domain: &str) -> bool {
+    Url::parse(domain).is_ok_and(|url| match url.host() {
+        Some(Host::Domain(host)) => host.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    })
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            domain: String::new(),
+            cdn_base_url: None,
+            host: default_host(),
+            port: default_port(),
+            hub_url: default_hub_url(),
+            skip_validation: false,
+            open_frames: false,
+            state_secret: None,
+            state_ttl_secs: default_state_ttl_secs(),
+            allowed_origins: Vec::new(),
+            boost_contract: None,
+            chain_id: default_chain_id(),
+            swap_router: None,
+            swap_wrapped_goat: None,
+            swap_token: None,
+            swap_slippage_bps: default_swap_slippage_bps(),
+            auction_contract: None,
+            auction_min_increment_bps: default_auction_min_increment_bps(),
+            auction_poll_secs: default_auction_poll_secs(),
+            rewards_contract: None,
+            bridge_contract: None,
+            bridge_destination_chain_id: None,
+            bridge_destination_rpc_url: None,
+            bridge_destination_contract: None,
+            liquidity_pair: None,
+            rpc_url: None,
+            session_secret: None,
+            session_ttl_secs: default_session_ttl_secs(),
+            app_fid: None,
+            app_custody_address: None,
+            app_signer_rpc_url: None,
+            signed_key_request_url: default_signed_key_request_url(),
+            signers_path: None,
+            apr_url: None,
+            high_apr_percent: default_high_apr_percent(),
+            price_url: None,
+            price_ttl_secs: default_price_ttl_secs(),
+            leaderboard_url: None,
+            leaderboard_ttl_secs: default_leaderboard_ttl_secs(),
+            moxie_price_url: None,
+            moxie_price_ttl_secs: default_moxie_price_ttl_secs(),
+            default_aspect_ratio: None,
+            checkout_base: None,
+            top_up_url: None,
+            deposit_address: None,
+            redirect_allowlist: default_redirect_allowlist(),
+            known_fids: Vec::new(),
+            rate_limit_per_min: default_rate_limit_per_min(),
+            idempotency_ttl_secs: default_idempotency_ttl_secs(),
+            tx_confirmation_ttl_secs: default_tx_confirmation_ttl_secs(),
+            max_message_age_secs: default_max_message_age_secs(),
+            analytics_path: None,
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            asset_cache_secs: default_asset_cache_secs(),
+            asset_listing: false,
+            asset_cache_path: default_asset_cache_path(),
+            skip_asset_optimization: false,
+            debug_headers: false,
+            notifications_path: None,
+            notification_hosts: default_notification_hosts(),
+            gifts_path: None,
+            og_title: default_og_title(),
+            og_description: default_og_description(),
+            og_image: None,
+            miniapp_name: default_miniapp_name(),
+            miniapp_icon: default_miniapp_icon(),
+            miniapp_splash_image: default_miniapp_icon(),
+            miniapp_splash_background: default_miniapp_splash_background(),
+            account_association_header: None,
+            account_association_payload: None,
+            account_association_signature: None,
+            tls_cert_path: None,
+            tls_key_path: None,
+            frames_path: default_frames_path(),
+            locales_path: default_locales_path(),
+            default_locale: None,
+            templates_path: default_templates_path(),
+            font_paths: Vec::new(),
+            emoji_path: None,
+            watermark_logo: None,
+            watermark_text: None,
+            default_theme: Theme::default(),
+            theme_dark_colors: Vec::new(),
+            theme_light_colors: Vec::new(),
+            profile_ttl_secs: default_profile_ttl_secs(),
+            image_cache_size: default_image_cache_size(),
+            image_cache_ttl_secs: default_image_cache_ttl_secs(),
+        }
     }
 }
@@ -0,0 +1,60 @@
+use actix_cors::Cors;
+use actix_web::http::header::HeaderValue;
+use log::warn;
+
+// Origins allowed to call the frame API and fetch assets from another site
+#[derive(Clone, Debug, PartialEq)]
+pub enum AllowedOrigins {
+    Any,
+    List(Vec<String>),
+}
+
+impl AllowedOrigins {
+    pub fn from_config(origins: &[String]) -> Result<Self, String> {
+        let explicit: Vec<String> = origins
+            .iter()
+            .map(|origin| origin.trim())
+            .filter(|origin| !origin.is_empty() && *origin != "*")
+            .map(str::to_string)
+            .collect();
+
+        if explicit.is_empty() {
+            return Ok(if origins.iter().any(|origin| origin.trim() == "*") {
+                AllowedOrigins::Any
+            } else {
+                AllowedOrigins::List(Vec::new())
+            });
+        }
+
+        if origins.iter().any(|origin| origin.trim() == "*") {
+            warn!(
+                "ALLOWED_ORIGINS mixes * with specific origins; only allowing {:?}",
+                explicit
+            );
+        }
+
+        // actix-cors panics on malformed origins, so reject them before the server starts
+        for origin in &explicit {
+            let has_scheme = origin.starts_with("https://") || origin.starts_with("http://");
+            if !has_scheme || HeaderValue::from_str(origin).is_err() {
+                return Err(format!("Invalid allowed origin: {:?}", origin));
+            }
+        }
+
+        Ok(AllowedOrigins::List(explicit))
+    }
+}
+
+pub fn cors(allowed_origins: &AllowedOrigins) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods(["GET", "POST"])
+        .allow_any_header()
+        .max_age(3600);
+
+    match allowed_origins {
+        AllowedOrigins::Any => cors.allow_any_origin(),
+        AllowedOrigins::List(origins) => origins
+            .iter()
+            .fold(cors, |cors, origin| cors.allowed_origin(origin)),
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Countdown images are drawn fresh at least this often: their URLs change every
+// STEP_SECS, so clients that cache images by URL fetch a new one
+pub const STEP_SECS: u64 = 60;
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// The URL parameter that changes every STEP_SECS
+pub fn cache_buster(now: u64) -> String {
+    (now / STEP_SECS).to_string()
+}
+
+// "Ends in 2h 13m" for an auction ending at ends_at (unix seconds), in the two
+// largest units, or "Ended" once it has. Under a minute is "Ends in 1m", so the
+// countdown doesn't claim 0m while bids are still taken.
+pub fn format_countdown(ends_at: u64, now: u64) -> String {
+    let Some(left) = ends_at.checked_sub(now).filter(|left| *left > 0) else {
+        return "Ended".to_string();
+    };
+    let minutes = left.div_ceil(60);
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    let time = if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    };
+    format!("Ends in {}", time)
+}
@@ -0,0 +1,62 @@
+use crate::frame_logic::state::FrameState;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::HttpResponse;
+use std::time::{Duration, Instant};
+
+// The unsigned state the response carries, so it can be read without decoding the page
+pub const STATE_HEADER: &str = "x-frame-state";
+
+// Which path handled the press: replay, redirect, error, or how process_button moved
+// through the screens (navigate:<screen>, back, stay or handoff:<frame>)
+pub const HANDLER_HEADER: &str = "x-frame-handler";
+
+// Standard Server-Timing, which browser dev tools show alongside the request
+pub const TIMING_HEADER: &str = "server-timing";
+
+// Names how a press moved the user, from the states before and after process_button
+pub fn navigation(before: &FrameState, after: &FrameState) -> String {
+    if let Some(frame) = after.frame.as_ref().filter(|_| after.frame != before.frame) {
+        format!("handoff:{}", frame)
+    } else if after.stack.len() < before.stack.len() {
+        "back".to_string()
+    } else if after.current() != before.current() {
+        format!("navigate:{}", after.current())
+    } else {
+        "stay".to_string()
+    }
+}
+
+// Adds the debug headers to a frame POST response. processing is how long
+// process_button and rendering the page took, when they ran.
+pub fn insert(
+    response: &mut HttpResponse,
+    handler: &str,
+    state: Option<&FrameState>,
+    started: Instant,
+    processing: Option<Duration>,
+) {
+    let mut timing = Vec::new();
+    if let Some(processing) = processing {
+        timing.push(format!("process;dur={:.3}", millis(processing)));
+    }
+    timing.push(format!("total;dur={:.3}", millis(started.elapsed())));
+
+    let headers = [
+        (HANDLER_HEADER, Some(handler.to_string())),
+        (STATE_HEADER, state.map(FrameState::encode)),
+        (TIMING_HEADER, Some(timing.join(", "))),
+    ];
+    for (name, value) in headers {
+        // Submitted text can make the state unrepresentable as a header; skip it then
+        let Some(value) = value.and_then(|value| HeaderValue::from_str(&value).ok()) else {
+            continue;
+        };
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(name), value);
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,61 @@
+use crate::config::Config;
+use crate::errors::AppError;
+use crate::frame_logic::{Button, ButtonAction};
+use log::warn;
+
+// Every error frame starts with these two buttons, so a press on one is understood
+// from the state alone, whichever error was shown
+pub const TRY_AGAIN_BUTTON: usize = 1;
+pub const START_OVER_BUTTON: usize = 2;
+
+// What a user sees when a press fails: the problem in words they can act on, drawn as
+// the frame's image, and buttons to get past it
+#[derive(Debug)]
+pub struct ErrorFrame {
+    pub message: String,
+    pub buttons: Vec<Button>,
+}
+
+impl ErrorFrame {
+    pub fn for_error(err: &AppError, config: &Config) -> Self {
+        // The state behind an expired session is thrown away, so there's nothing to try
+        // again; its one button is Try Again's, which now shows main
+        if let AppError::SessionExpired = err {
+            return ErrorFrame {
+                message: "This session expired, start over".to_string(),
+                buttons: vec![Button::new("Start Over")],
+            };
+        }
+
+        let (message, try_again) = match err {
+            AppError::InsufficientBalance { .. } => (format!("{}. Top up?", err), "Try Again"),
+            AppError::BadRequest(message) => (message.clone(), "Try Again"),
+            // Whatever the button led to was removed, so retrying it won't help
+            AppError::NotFound(_) => ("That's no longer here".to_string(), "Go Back"),
+            AppError::Unauthorized(_) => (
+                "We couldn't verify that press, please try again".to_string(),
+                "Try Again",
+            ),
+            AppError::TooManyRequests(retry_after) => (
+                format!("Slow down! Try again in {}s", retry_after),
+                "Try Again",
+            ),
+            // Internal details stay in the logs, as they do for API errors
+            AppError::SessionExpired
+            | AppError::InternalServerError
+            | AppError::FrameSpecViolation(_) => {
+                ("Something went wrong on our side".to_string(), "Try Again")
+            }
+        };
+
+        let mut buttons = vec![Button::new(try_again), Button::new("Start Over")];
+        if let (AppError::InsufficientBalance { .. }, Some(url)) = (err, &config.top_up_url) {
+            let link = ButtonAction::Link { url: url.clone() };
+            match Button::with_action("Top Up", link) {
+                Ok(button) => buttons.push(button),
+                Err(err) => warn!("Leaving off the Top Up button: {}", err),
+            }
+        }
+        ErrorFrame { message, buttons }
+    }
+}
@@ -0,0 +1,190 @@
+use actix_web::{web, HttpResponse};
+use font8x8::legacy::BASIC_LEGACY;
+use image::{ImageFormat, Rgb, RgbImage};
+use log::error;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::sync::Mutex;
+
+// 1.91:1, the default Farcaster frame aspect ratio
+const WIDTH: u32 = 764;
+const HEIGHT: u32 = 400;
+
+// Glyphs are 8x8 bitmaps drawn at this scale
+const SCALE: u32 = 3;
+const GLYPH_SIZE: u32 = 8 * SCALE;
+const LINE_HEIGHT: u32 = GLYPH_SIZE + 8;
+const MARGIN: u32 = 32;
+const MAX_CHARS_PER_LINE: usize = ((WIDTH - 2 * MARGIN) / GLYPH_SIZE) as usize;
+const MAX_LINES: usize = 4;
+
+// Error messages are unbounded (they can embed user input), so cap how many we keep
+const MAX_CACHED_IMAGES: usize = 256;
+
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 24]);
+const BANNER: Rgb<u8> = Rgb([200, 32, 32]);
+const TEXT: Rgb<u8> = Rgb([255, 255, 255]);
+
+// Draws the error text on a red banner and encodes the result as a PNG
+pub fn render_error_image(message: &str) -> Result<Vec<u8>, image::ImageError> {
+    let mut img = RgbImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+
+    let lines = wrap_text(message);
+    let banner_height = lines.len() as u32 * LINE_HEIGHT + MARGIN;
+    let banner_top = (HEIGHT - banner_height) / 2;
+    for y in banner_top..banner_top + banner_height {
+        for x in 0..WIDTH {
+            img.put_pixel(x, y, BANNER);
+        }
+    }
+
+    let mut y = banner_top + MARGIN / 2;
+    for line in &lines {
+        // Center each line horizontally
+        let line_width = line.len() as u32 * GLYPH_SIZE;
+        draw_text(&mut img, line, (WIDTH - line_width) / 2, y);
+        y += LINE_HEIGHT;
+    }
+
+    let mut bytes = Vec::new();
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+    Ok(bytes)
+}
+
+// Splits the message into lines that fit the image, truncating whatever doesn't
+fn wrap_text(message: &str) -> Vec<String> {
+    // The bitmap font only covers printable ASCII
+    let sanitized: String = message
+        .chars()
+        .map(|c| {
+            if c == ' ' || c.is_ascii_graphic() {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect();
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in sanitized.split_whitespace() {
+        let mut word = word;
+        // Hard-break words longer than a whole line
+        while word.len() > MAX_CHARS_PER_LINE {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            let (head, tail) = word.split_at(MAX_CHARS_PER_LINE);
+            lines.push(head.to_string());
+            word = tail;
+        }
+        if !current.is_empty() && current.len() + 1 + word.len() > MAX_CHARS_PER_LINE {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    if lines.is_empty() {
+        lines.push("Error".to_string());
+    }
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        let last = &mut lines[MAX_LINES - 1];
+        last.truncate(MAX_CHARS_PER_LINE - 3);
+        last.push_str("...");
+    }
+    lines
+}
+
+fn draw_text(img: &mut RgbImage, text: &str, x: u32, y: u32) {
+    for (i, c) in text.bytes().enumerate() {
+        let glyph = BASIC_LEGACY[(c & 0x7f) as usize];
+        let glyph_x = x + i as u32 * GLYPH_SIZE;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..8 {
+                if bits & (1 << col) == 0 {
+                    continue;
+                }
+                for dy in 0..SCALE {
+                    for dx in 0..SCALE {
+                        let px = glyph_x + col * SCALE + dx;
+                        let py = y + row as u32 * SCALE + dy;
+                        if px < WIDTH && py < HEIGHT {
+                            img.put_pixel(px, py, TEXT);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Rendered error images, keyed by a hash of their message
+#[derive(Default)]
+pub struct ErrorImages {
+    store: Mutex<ErrorImageStore>,
+}
+
+#[derive(Default)]
+struct ErrorImageStore {
+    images: HashMap<String, Vec<u8>>,
+    // Insertion order, so the oldest image is evicted first
+    order: VecDeque<String>,
+}
+
+impl ErrorImages {
+    // Renders the message if it hasn't been seen before and returns its id
+    pub fn id_for(&self, message: &str) -> Option<String> {
+        let mut hasher = DefaultHasher::new();
+        message.hash(&mut hasher);
+        let id = format!("{:016x}", hasher.finish());
+
+        if self.store.lock().unwrap().images.contains_key(&id) {
+            return Some(id);
+        }
+
+        // Render outside the lock; a concurrent render of the same message is harmless
+        let png = match render_error_image(message) {
+            Ok(png) => png,
+            Err(err) => {
+                error!("Failed to render error image: {}", err);
+                return None;
+            }
+        };
+
+        let mut store = self.store.lock().unwrap();
+        if store.images.contains_key(&id) {
+            return Some(id);
+        }
+        if store.order.len() >= MAX_CACHED_IMAGES {
+            if let Some(oldest) = store.order.pop_front() {
+                store.images.remove(&oldest);
+            }
+        }
+        store.images.insert(id.clone(), png);
+        store.order.push_back(id.clone());
+        Some(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Vec<u8>> {
+        self.store.lock().unwrap().images.get(id).cloned()
+    }
+}
+
+pub async fn serve_error_image(
+    id: web::Path<String>,
+    images: web::Data<ErrorImages>,
+) -> HttpResponse {
+    match images.get(&id) {
+        Some(png) => HttpResponse::Ok().content_type("image/png").body(png),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
@@ -1,3 +1,4 @@
+use actix_web::http::{header, StatusCode};
 use actix_web::{HttpResponse, ResponseError};
 use log::warn;
 use thiserror::Error;
@@ -9,21 +10,150 @@ pub enum AppError {
 
     #[error("Bad request: {0}")]
     BadRequest(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    // The wallet can't pay for what the user asked, in whole GOAT
+    #[error("Not enough GOAT: this wallet has {balance} and the boost needs {needed}")]
+    InsufficientBalance { balance: String, needed: String },
+
+    // The frame was left open past STATE_TTL_SECS, so its state can't be trusted to
+    // still hold, e.g. a quote
+    #[error("Frame state expired")]
+    SessionExpired,
+
+    // Seconds until the client may retry
+    #[error("Too many requests, retry in {0}s")]
+    TooManyRequests(u64),
+
+    // A frame we built breaks a limit clients enforce, so they'd refuse to show it
+    #[error("Frame spec violation: {0}")]
+    FrameSpecViolation(String),
 }
 
 impl ResponseError for AppError {
-    fn error_response(&self) -> HttpResponse {
-        match *self {
-            AppError::InternalServerError => {
-                // Log an internal server error warning but continue running
-                warn!("Internal server error occurred.");
-                HttpResponse::InternalServerError().json("Internal server error")
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::InternalServerError | AppError::FrameSpecViolation(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
             }
-            AppError::BadRequest(ref message) => {
-                // Log a bad request error
-                warn!("Bad request: {}", message);
-                HttpResponse::BadRequest().json(message)
+            AppError::BadRequest(_)
+            | AppError::InsufficientBalance { .. }
+            | AppError::SessionExpired => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // Log the full message; internal details stay out of the response body
+        warn!("{}", self);
+        let message = match self {
+            AppError::InternalServerError | AppError::FrameSpecViolation(_) => {
+                "Internal server error"
             }
+            AppError::TooManyRequests(_) => "Too many requests",
+            AppError::SessionExpired => "Session expired, start over",
+            AppError::BadRequest(message)
+            | AppError::NotFound(message)
+            | AppError::Unauthorized(message) => message,
+            AppError::InsufficientBalance { .. } => &self.to_string(),
+        };
+        let mut response = HttpResponse::build(self.status_code());
+        if let AppError::TooManyRequests(retry_after) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
         }
+        response.json(serde_json::json!({ "error": message }))
     }
 }
+
+// Problems with the frames file, reported once at startup
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("Failed to read frames file {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to parse frames file: {0}")]
+    Parse(toml::de::Error),
+
+    #[error("Frames file has no {0:?} screen")]
+    MissingScreen(String),
+
+    #[error("Screen {0:?} references missing image {1:?}")]
+    MissingImage(String, String),
+
+    #[error("Button {1:?} on screen {0:?} targets missing screen {2:?}")]
+    MissingTarget(String, String, String),
+
+    #[error("Screen {0:?} is invalid: {1}")]
+    InvalidScreen(String, String),
+
+    #[error("Frame {0:?} is invalid: {1}")]
+    InvalidFrame(String, String),
+}
+
+// Problems with the translation files, reported once at startup
+#[derive(Error, Debug)]
+pub enum I18nError {
+    #[error("Failed to read translations {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Failed to parse translations {0}: {1}")]
+    Parse(String, toml::de::Error),
+}
+
+// Problems rendering a generated image, or loading its template at startup
+#[derive(Error, Debug)]
+pub enum ImageError {
+    #[error("Failed to read image templates {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("Image template {0:?} can't be drawn: {1}")]
+    Template(String, String),
+
+    #[error("Unknown image template {0:?}")]
+    UnknownTemplate(String),
+
+    #[error("Unsupported SVG: {0}")]
+    Svg(String),
+
+    #[error("Failed to load image assets/{0}: {1}")]
+    Asset(String, image::ImageError),
+
+    #[error("Failed to encode image: {0}")]
+    Encode(image::ImageError),
+
+    #[error("Failed to load font {0}: {1}")]
+    Font(String, String),
+}
+
+// Problems with the environment configuration, reported once at startup
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read configuration: {0}")]
+    Env(#[from] envy::Error),
+
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+// Problems with the TLS certificate or key, reported once at startup
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("Failed to read {0}: {1}")]
+    Read(String, std::io::Error),
+
+    #[error("No PEM certificates found in {0}")]
+    NoCertificates(String),
+
+    #[error("No PEM private key found in {0}")]
+    NoPrivateKey(String),
+
+    #[error("Certificate and key were rejected: {0}")]
+    Rejected(rustls::Error),
+}
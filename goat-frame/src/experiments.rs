@@ -0,0 +1,85 @@
+use crate::frame_logic::registry::{ExperimentDef, FrameRegistry};
+use crate::frame_logic::state::{FrameState, MAIN_SCREEN};
+use crate::frame_logic::UserContext;
+use sha2::{Digest, Sha256};
+
+// The arm everyone without an fid sees: the screen exactly as configured
+pub const CONTROL: &str = "control";
+
+// Which arm of an experiment an fid lands in: 0 is control, then each variant in
+// order. It's a hash rather than a random draw so a user sees the same arm on every
+// press, across restarts and every server behind the load balancer.
+pub fn assign(experiment: &str, fid: u64, arms: usize) -> usize {
+    let digest = Sha256::digest(format!("{}:{}", experiment, fid));
+    let bucket = u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+    (bucket % arms.max(1) as u64) as usize
+}
+
+// An experiment and the arm a user saw, as "<experiment>:<arm>" in click events
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exposure {
+    pub experiment: String,
+    pub arm: String,
+}
+
+impl Exposure {
+    fn of(experiment: &ExperimentDef, user: &UserContext) -> Self {
+        Exposure {
+            experiment: experiment.name.clone(),
+            arm: experiment
+                .variant_for(user)
+                .map_or(CONTROL, |variant| &variant.name)
+                .to_string(),
+        }
+    }
+
+    // What a user sees on the screen a state posted to frame_name is on. Only fids are
+    // split, since an anonymous press can't be followed to a conversion.
+    pub fn on(
+        frame_name: &str,
+        state: &FrameState,
+        user: &UserContext,
+        registry: &FrameRegistry,
+    ) -> Option<Self> {
+        user.fid?;
+        if state.error {
+            return None;
+        }
+        let frame = registry.frame(state.frame.as_deref().unwrap_or(frame_name))?;
+        let experiment = frame.screen(state.current())?.experiment.as_ref()?;
+        Some(Exposure::of(experiment, user))
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}:{}", self.experiment, self.arm)
+    }
+}
+
+// The experiments a press converted: ones whose goal it moved forward to, from a screen
+// of the same frame the user passed through on the way there (main always is)
+pub fn conversions(
+    frame_name: &str,
+    before: &FrameState,
+    after: &FrameState,
+    user: &UserContext,
+    registry: &FrameRegistry,
+) -> Vec<Exposure> {
+    let frame_of = |state: &FrameState| state.frame.clone().unwrap_or(frame_name.to_string());
+    let handed_off = frame_of(before) != frame_of(after);
+    let moved = handed_off
+        || (before.current() != after.current() && after.stack.len() >= before.stack.len());
+    let frame = registry.frame(&frame_of(after));
+    let (Some(frame), Some(_), true, false) = (frame, user.fid, moved, after.error) else {
+        return Vec::new();
+    };
+    let mut converted: Vec<Exposure> = frame
+        .screens
+        .iter()
+        .filter(|(name, _)| *name == MAIN_SCREEN || after.stack.contains(name))
+        .filter_map(|(_, screen)| screen.experiment.as_ref())
+        .filter(|experiment| experiment.goal == after.current())
+        .map(|experiment| Exposure::of(experiment, user))
+        .collect();
+    converted.sort_by(|a, b| a.experiment.cmp(&b.experiment));
+    converted
+}
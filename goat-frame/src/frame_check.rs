@@ -0,0 +1,244 @@
+use crate::assets::AssetHashes;
+use crate::config::Config;
+use crate::frame_html::FrameMeta;
+use crate::frame_logic::registry::{FrameRegistry, MAX_BUTTONS};
+use crate::frame_logic::state::{FrameState, BACK_TARGET, MAIN_SCREEN};
+use crate::frame_logic::{render_screen, UserContext};
+use crate::images::font::Fonts;
+use crate::images::ImageTemplates;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Checks every screen of every frame the way a client would see it, collecting all
+// problems rather than stopping at the first like FrameRegistry::validate does
+pub fn check_registry(registry: &FrameRegistry, assets_dir: &Path, config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let mut frame_names: Vec<&String> = registry.frames.keys().collect();
+    frame_names.sort();
+    for frame_name in frame_names {
+        let frame = &registry.frames[frame_name];
+        if frame.screen(MAIN_SCREEN).is_none() {
+            problems.push(format!("{}: no {:?} screen", frame_name, MAIN_SCREEN));
+        }
+
+        let mut screen_names: Vec<&String> = frame.screens.keys().collect();
+        screen_names.sort();
+        for screen_name in screen_names {
+            let screen = &frame.screens[screen_name];
+            let name = format!("{}/{}", frame_name, screen_name);
+
+            if screen.buttons.is_empty() || screen.buttons.len() > MAX_BUTTONS {
+                problems.push(format!(
+                    "{}: has {} buttons, must have 1 to {}",
+                    name,
+                    screen.buttons.len(),
+                    MAX_BUTTONS
+                ));
+            }
+
+            let apr_images = screen
+                .apr_images
+                .iter()
+                .flat_map(|apr| [&apr.high, &apr.low]);
+            let variant_images = screen
+                .experiment
+                .iter()
+                .flat_map(|experiment| &experiment.variants)
+                .filter_map(|variant| variant.image.as_ref());
+            let images = std::iter::once(&screen.image)
+                .chain(&screen.wide_image)
+                .chain(apr_images)
+                .chain(variant_images);
+            for image in images {
+                if !assets_dir.join(image).is_file() {
+                    problems.push(format!("{}: missing asset assets/{}", name, image));
+                }
+            }
+
+            for button in &screen.buttons {
+                if let Some(other) = &button.frame {
+                    let opens = button.target.as_deref().unwrap_or(MAIN_SCREEN);
+                    let found = registry.frame(other).and_then(|other| other.screen(opens));
+                    if found.is_none() {
+                        problems.push(format!(
+                            "{}: button {:?} hands off to missing screen {}/{}",
+                            name, button.label, other, opens
+                        ));
+                    }
+                } else if let Some(target) = &button.target {
+                    if target != BACK_TARGET && frame.screen(target).is_none() {
+                        problems.push(format!(
+                            "{}: button {:?} targets missing screen {:?}",
+                            name, button.label, target
+                        ));
+                    }
+                }
+            }
+
+            let rendered = match render_screen(
+                screen,
+                &FrameState::default(),
+                &UserContext::default(),
+                &AssetHashes::default(),
+                config,
+            ) {
+                Ok(rendered) => rendered,
+                Err(err) => {
+                    problems.push(format!("{}: failed to render: {}", name, err));
+                    continue;
+                }
+            };
+            let post_url = match &screen.post_path {
+                Some(step) => format!("{}/f/{}/{}", config.domain, frame_name, step),
+                None => format!("{}/f/{}", config.domain, frame_name),
+            };
+            let meta =
+                FrameMeta::from_screen(rendered, &post_url).state(&FrameState::default().encode());
+            if let Err(err) = meta.validate() {
+                problems.push(format!("{}: {}", name, err));
+            }
+            let html = meta.to_html();
+            problems.extend(
+                check_meta_tags(&html)
+                    .into_iter()
+                    .map(|problem| format!("{}: {}", name, problem)),
+            );
+        }
+    }
+
+    match ImageTemplates::load(Path::new(&config.templates_path), assets_dir) {
+        Ok(templates) => {
+            if let Err(err) = registry.check_templates(|name| templates.placeholders(name)) {
+                problems.push(err.to_string());
+            }
+        }
+        Err(err) => problems.push(err.to_string()),
+    }
+    if let Err(err) = Fonts::load(&config.font_paths) {
+        problems.push(err.to_string());
+    }
+
+    if let Some(image) = &config.og_image {
+        if !assets_dir.join(image).is_file() {
+            problems.push(format!("OG_IMAGE: missing asset assets/{}", image));
+        }
+    }
+
+    // Anything else the server would refuse to start with, e.g. unsafe frame names
+    if problems.is_empty() {
+        if let Err(err) = registry.validate(assets_dir) {
+            problems.push(err.to_string());
+        }
+    }
+
+    problems
+}
+
+// Checks the fc:frame tags a client needs are present once each and well-formed
+pub fn check_meta_tags(html: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let tags = meta_properties(html);
+
+    for (property, count) in tags.iter().map(|(p, values)| (p, values.len())) {
+        if count > 1 {
+            problems.push(format!("{} appears {} times", property, count));
+        }
+    }
+
+    let first = |property: &str| tags.get(property).and_then(|values| values.first());
+    match first("fc:frame") {
+        Some(version) if version == "vNext" => {}
+        Some(version) => problems.push(format!("fc:frame is {:?}, expected \"vNext\"", version)),
+        None => problems.push("missing fc:frame".to_string()),
+    }
+    for property in ["fc:frame:image", "fc:frame:post_url"] {
+        match first(property) {
+            Some(url) if url.starts_with("https://") || url.starts_with("http://") => {}
+            Some(url) => problems.push(format!("{} is not an http(s) URL: {:?}", property, url)),
+            None => problems.push(format!("missing {}", property)),
+        }
+    }
+
+    let button_count = (1..)
+        .take_while(|i| tags.contains_key(&format!("fc:frame:button:{}", i)))
+        .count();
+    if button_count == 0 {
+        problems.push("has no fc:frame:button tags".to_string());
+    }
+    if button_count > MAX_BUTTONS {
+        problems.push(format!(
+            "has {} buttons, clients show at most {}",
+            button_count, MAX_BUTTONS
+        ));
+    }
+    for i in 1..=button_count {
+        if first(&format!("fc:frame:button:{}", i)).is_some_and(|label| label.trim().is_empty()) {
+            problems.push(format!("fc:frame:button:{} has an empty label", i));
+        }
+    }
+
+    problems
+}
+
+// Collects <meta property="..." content="..." /> values by property
+fn meta_properties(html: &str) -> HashMap<String, Vec<String>> {
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for tag in html.split("<meta ").skip(1) {
+        let (Some(property), Some(content)) = (attr(tag, "property"), attr(tag, "content")) else {
+            continue;
+        };
+        tags.entry(property).or_default().push(content);
+    }
+    tags
+}
+
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let start = tag.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = start + tag[start..].find('"')?;
+    Some(unescape_attr(&tag[start..end]))
+}
+
+fn unescape_attr(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// Backs --validate: prints a report and returns the process exit code
+pub fn run(config: &Config, assets_dir: &Path) -> i32 {
+    let contents = match std::fs::read_to_string(&config.frames_path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            eprintln!("Failed to read {}: {}", config.frames_path, err);
+            return 1;
+        }
+    };
+    let registry = match FrameRegistry::from_toml(&contents) {
+        Ok(registry) => registry,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+
+    let problems = check_registry(&registry, assets_dir, config);
+    if problems.is_empty() {
+        let screens: usize = registry.frames.values().map(|f| f.screens.len()).sum();
+        println!(
+            "{}: {} frames, {} screens, no problems found",
+            config.frames_path,
+            registry.frames.len(),
+            screens
+        );
+        0
+    } else {
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        println!("{}: {} problems found", config.frames_path, problems.len());
+        1
+    }
+}
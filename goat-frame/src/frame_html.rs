@@ -0,0 +1,341 @@
+use crate::client_detection::VNEXT;
+use crate::errors::AppError;
+use crate::frame_logic::registry::MAX_BUTTONS;
+use crate::frame_logic::{AspectRatio, Button, ButtonAction, RenderedScreen};
+use actix_web::http::header::{Accept, Header};
+use actix_web::HttpRequest;
+use serde::Serialize;
+use url::Url;
+
+// Open Frames protocols we answer, and the version each client expects us to name
+pub const OPEN_FRAMES_PROTOCOLS: [(&str, &str); 2] = [("xmtp", "2024-02-01"), ("lens", "1.1")];
+
+// Limits from the frames spec, in bytes
+pub const MAX_LABEL_BYTES: usize = 32;
+pub const MAX_INPUT_PLACEHOLDER_BYTES: usize = 32;
+const MAX_STATE_BYTES: usize = 4096;
+const MAX_POST_URL_BYTES: usize = 256;
+
+// Everything a client needs to show a frame, rendered as a complete HTML document.
+// It also serializes to JSON for tools that would rather not parse HTML; only the
+// frame itself is included, not the Open Frames or OpenGraph copies of it.
+#[derive(Serialize)]
+pub struct FrameMeta {
+    version: String,
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_alt: Option<String>,
+    post_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    input_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aspect_ratio: Option<AspectRatio>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+    buttons: Vec<Button>,
+    #[serde(skip)]
+    open_frames: bool,
+    #[serde(skip)]
+    open_graph: Option<OpenGraph>,
+}
+
+// og:* preview for sites and apps that don't understand frames
+#[derive(Clone, Debug)]
+pub struct OpenGraph {
+    pub title: String,
+    pub description: String,
+    pub image: String,
+}
+
+impl FrameMeta {
+    pub fn new(image: &str, post_url: &str) -> Self {
+        FrameMeta {
+            version: VNEXT.to_string(),
+            image: image.to_string(),
+            image_alt: None,
+            post_url: post_url.to_string(),
+            input_text: None,
+            aspect_ratio: None,
+            state: None,
+            buttons: Vec::new(),
+            open_frames: false,
+            open_graph: None,
+        }
+    }
+
+    // Starts from a rendered screen, keeping its image, ratio, input and buttons
+    pub fn from_screen(screen: RenderedScreen, post_url: &str) -> Self {
+        let mut meta = FrameMeta::new(&screen.image, post_url).buttons(screen.buttons);
+        if let Some(alt) = &screen.image_alt {
+            meta = meta.image_alt(alt);
+        }
+        if let Some(ratio) = screen.image_aspect_ratio {
+            meta = meta.aspect_ratio(ratio);
+        }
+        if let Some(placeholder) = &screen.input_text {
+            meta = meta.input_text(placeholder);
+        }
+        meta
+    }
+
+    // The fc:frame version to name, for clients pinned to a dated one
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn image_alt(mut self, alt: &str) -> Self {
+        self.image_alt = Some(alt.to_string());
+        self
+    }
+
+    pub fn input_text(mut self, placeholder: &str) -> Self {
+        self.input_text = Some(placeholder.to_string());
+        self
+    }
+
+    pub fn aspect_ratio(mut self, ratio: AspectRatio) -> Self {
+        self.aspect_ratio = Some(ratio);
+        self
+    }
+
+    pub fn state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_string());
+        self
+    }
+
+    pub fn buttons(mut self, buttons: Vec<Button>) -> Self {
+        self.buttons = buttons;
+        self
+    }
+
+    // Mirrors the frame in of:* tags so XMTP and Lens clients can show it too
+    pub fn open_frames(mut self, enabled: bool) -> Self {
+        self.open_frames = enabled;
+        self
+    }
+
+    pub fn open_graph(mut self, open_graph: OpenGraph) -> Self {
+        self.open_graph = Some(open_graph);
+        self
+    }
+
+    // Checks the frame against the limits clients enforce, so a frame they'd refuse to
+    // show fails here with the reason rather than as a blank embed
+    pub fn validate(&self) -> Result<(), AppError> {
+        let violation = |message: String| Err(AppError::FrameSpecViolation(message));
+
+        if self.buttons.len() > MAX_BUTTONS {
+            return violation(format!(
+                "{} buttons, at most {} allowed",
+                self.buttons.len(),
+                MAX_BUTTONS
+            ));
+        }
+        for (i, button) in self.buttons.iter().enumerate() {
+            if button.label.len() > MAX_LABEL_BYTES {
+                return violation(format!(
+                    "button {} label {:?} is {} bytes, at most {} allowed",
+                    i + 1,
+                    button.label,
+                    button.label.len(),
+                    MAX_LABEL_BYTES
+                ));
+            }
+        }
+        if let Some(placeholder) = self
+            .input_text
+            .as_ref()
+            .filter(|placeholder| placeholder.len() > MAX_INPUT_PLACEHOLDER_BYTES)
+        {
+            return violation(format!(
+                "input placeholder {:?} is over {} bytes",
+                placeholder, MAX_INPUT_PLACEHOLDER_BYTES
+            ));
+        }
+        if let Some(state) = self
+            .state
+            .as_ref()
+            .filter(|state| state.len() > MAX_STATE_BYTES)
+        {
+            return violation(format!(
+                "state is {} bytes, at most {} allowed",
+                state.len(),
+                MAX_STATE_BYTES
+            ));
+        }
+        if self.post_url.len() > MAX_POST_URL_BYTES {
+            return violation(format!(
+                "post_url is {} bytes, at most {} allowed",
+                self.post_url.len(),
+                MAX_POST_URL_BYTES
+            ));
+        }
+        if !is_secure_image_url(&self.image) {
+            return violation(format!("image {:?} is not an https URL", self.image));
+        }
+        Ok(())
+    }
+
+    // The frame's meta tags alone, one per line and each emitted once
+    pub fn meta_tags(&self) -> String {
+        let mut tags = String::new();
+        if let Some(og) = &self.open_graph {
+            tags.push_str(&meta_tag("og:title", &og.title));
+            tags.push_str(&meta_tag("og:description", &og.description));
+            tags.push_str(&meta_tag("og:image", &og.image));
+        }
+        tags.push_str(&meta_tag("fc:frame", &self.version));
+        tags.push_str(&self.frame_tags("fc:frame"));
+        if self.open_frames {
+            tags.push_str(&meta_tag("of:version", VNEXT));
+            for (protocol, version) in OPEN_FRAMES_PROTOCOLS {
+                tags.push_str(&meta_tag(&format!("of:accepts:{}", protocol), version));
+            }
+            tags.push_str(&self.frame_tags("of"));
+        }
+        tags
+    }
+
+    // Everything after the version tag, which Farcaster and Open Frames name alike
+    fn frame_tags(&self, prefix: &str) -> String {
+        let mut tags = meta_tag(&format!("{}:image", prefix), &self.image);
+        if let Some(alt) = &self.image_alt {
+            tags.push_str(&meta_tag(&format!("{}:image:alt", prefix), alt));
+        }
+        if let Some(ratio) = self.aspect_ratio {
+            tags.push_str(&meta_tag(
+                &format!("{}:image:aspect_ratio", prefix),
+                ratio.as_str(),
+            ));
+        }
+        if let Some(placeholder) = &self.input_text {
+            tags.push_str(&meta_tag(&format!("{}:input:text", prefix), placeholder));
+        }
+        tags.push_str(&button_meta_tags(prefix, &self.buttons));
+        tags.push_str(&meta_tag(&format!("{}:post_url", prefix), &self.post_url));
+        if let Some(state) = &self.state {
+            tags.push_str(&meta_tag(&format!("{}:state", prefix), state));
+        }
+        tags
+    }
+
+    pub fn to_html(&self) -> String {
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8">
+<meta name="viewport" content="width=device-width, initial-scale=1.0">
+<title>GOAT Frame</title>
+{}</head>
+<body>
+<h1>GOAT Frame</h1>
+</body>
+</html>
+"#,
+            self.meta_tags()
+        )
+    }
+}
+
+// How a frame goes back to the caller: as the page clients read the tags from, or as
+// the same FrameMeta in JSON for dashboards and tests that ask for it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameFormat {
+    Html,
+    Json,
+}
+
+impl FrameFormat {
+    // JSON only when the caller ranks it first; clients sending no Accept, or */*,
+    // get the page
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        let ranked = Accept::parse(req)
+            .map(|accept| accept.ranked())
+            .unwrap_or_default();
+        match ranked.first() {
+            Some(mime) if mime.essence_str() == "application/json" => FrameFormat::Json,
+            _ => FrameFormat::Html,
+        }
+    }
+
+    pub fn render(self, meta: &FrameMeta) -> String {
+        match self {
+            FrameFormat::Html => meta.to_html(),
+            FrameFormat::Json => serde_json::to_string(meta).unwrap_or_default(),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            FrameFormat::Html => "text/html",
+            FrameFormat::Json => "application/json",
+        }
+    }
+}
+
+// Clients only load https images; plain http is let through for a server on this
+// machine so local development still works
+fn is_secure_image_url(image: &str) -> bool {
+    let Ok(url) = Url::parse(image) else {
+        return false;
+    };
+    let local = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    url.scheme() == "https" || (url.scheme() == "http" && local)
+}
+
+// Escapes a value for use inside a double-quoted HTML attribute
+pub fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Renders the <prefix>:button:N meta tags, including action, target and post_url where set
+pub fn button_meta_tags(tag_prefix: &str, buttons: &[Button]) -> String {
+    let mut tags = String::new();
+    for (i, button) in buttons.iter().enumerate() {
+        let prefix = format!("{}:button:{}", tag_prefix, i + 1);
+        tags.push_str(&meta_tag(&prefix, &button.label));
+
+        let Some(action) = &button.action else {
+            continue;
+        };
+        tags.push_str(&meta_tag(&format!("{}:action", prefix), action.name()));
+        match action {
+            ButtonAction::Post { target: None }
+            | ButtonAction::PostRedirect
+            | ButtonAction::LaunchFrame { target: None } => {}
+            ButtonAction::Post {
+                target: Some(target),
+            }
+            | ButtonAction::LaunchFrame {
+                target: Some(target),
+            } => {
+                tags.push_str(&meta_tag(&format!("{}:target", prefix), target));
+            }
+            ButtonAction::Link { url } => {
+                tags.push_str(&meta_tag(&format!("{}:target", prefix), url));
+            }
+            ButtonAction::Mint { target } => {
+                tags.push_str(&meta_tag(&format!("{}:target", prefix), target));
+            }
+            ButtonAction::Tx { target, post_url } => {
+                tags.push_str(&meta_tag(&format!("{}:target", prefix), target));
+                tags.push_str(&meta_tag(&format!("{}:post_url", prefix), post_url));
+            }
+        }
+    }
+    tags
+}
+
+fn meta_tag(property: &str, content: &str) -> String {
+    format!(
+        "<meta property=\"{}\" content=\"{}\" />\n",
+        property,
+        escape_attr(content)
+    )
+}
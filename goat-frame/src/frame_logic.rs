@@ -1,74 +1,775 @@
+use crate::apr;
+use crate::assets::AssetHashes;
+use crate::client_context::{ClientContext, ContextUser};
+use crate::client_detection::{Capabilities, ClientKind};
+use crate::countdown;
+use crate::error_frame::{START_OVER_BUTTON, TRY_AGAIN_BUTTON};
 use crate::errors::AppError;
-use serde::Serialize;
+use crate::frame_html::{MAX_INPUT_PLACEHOLDER_BYTES, MAX_LABEL_BYTES};
+use crate::i18n::Catalog;
+use crate::images::theme::Theme;
+use crate::images::MAX_PARAM_BYTES;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+pub mod registry;
+pub mod state;
+pub mod validation;
+
+use crate::frame_logic::registry::{ButtonDef, FrameRegistry, ScreenDef};
+use crate::frame_logic::state::{FrameState, BACK_TARGET, MAIN_SCREEN};
+use log::warn;
+use std::sync::Arc;
+
+// How a client should treat a button press; a button without one is a plain post.
+// Not every action is used by the built-in screens yet.
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ButtonAction {
+    // A target posts this button somewhere other than the frame's post_url, e.g.
+    // another frame's /f/<name>
+    Post {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target: Option<String>,
+    },
+    // Always posts back here, since the redirect is looked up by the frame handler
+    PostRedirect,
+    Link {
+        url: String,
+    },
+    Mint {
+        target: String,
+    },
+    Tx {
+        target: String,
+        post_url: String,
+    },
+    // Opens a mini app, by default this server's home page, showing the splash
+    // configured in the manifest while it loads
+    LaunchFrame {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target: Option<String>,
+    },
+}
+
+impl ButtonAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ButtonAction::Post { .. } => "post",
+            ButtonAction::PostRedirect => "post_redirect",
+            ButtonAction::Link { .. } => "link",
+            ButtonAction::Mint { .. } => "mint",
+            ButtonAction::Tx { .. } => "tx",
+            ButtonAction::LaunchFrame { .. } => "launch_frame",
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), AppError> {
+        match self {
+            ButtonAction::Post { target: None }
+            | ButtonAction::PostRedirect
+            | ButtonAction::LaunchFrame { target: None } => Ok(()),
+            ButtonAction::Post {
+                target: Some(target),
+            } => require_http_url("post target", target),
+            ButtonAction::Link { url } => require_http_url("link url", url),
+            ButtonAction::Mint { target } => {
+                if is_mint_target(target) {
+                    Ok(())
+                } else {
+                    Err(AppError::BadRequest(format!(
+                        "Invalid mint target: {:?}",
+                        target
+                    )))
+                }
+            }
+            ButtonAction::Tx { target, post_url } => {
+                require_http_url("tx target", target)?;
+                require_http_url("tx post_url", post_url)
+            }
+            ButtonAction::LaunchFrame {
+                target: Some(target),
+            } => require_http_url("launch_frame target", target),
+        }
+    }
+
+    // Turns paths like /api/tx/buy_boost into absolute URLs on this server
+    pub fn resolve(&self, domain: &str) -> ButtonAction {
+        let absolute = |url: &String| {
+            if url.starts_with('/') {
+                format!("{}{}", domain, url)
+            } else {
+                url.clone()
+            }
+        };
+        match self {
+            ButtonAction::Post {
+                target: Some(target),
+            } => ButtonAction::Post {
+                target: Some(absolute(target)),
+            },
+            ButtonAction::Link { url } => ButtonAction::Link { url: absolute(url) },
+            ButtonAction::Tx { target, post_url } => ButtonAction::Tx {
+                target: absolute(target),
+                post_url: absolute(post_url),
+            },
+            ButtonAction::LaunchFrame { target } => ButtonAction::LaunchFrame {
+                target: Some(
+                    target
+                        .as_ref()
+                        .map_or_else(|| format!("{}/", domain), absolute),
+                ),
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+// Mint targets are CAIP-10 style asset ids: eip155:<chain id>:<contract>, optionally
+// followed by /<token id>, e.g. eip155:8453:0x0000000000000000000000000000000000000001/1
+fn is_mint_target(target: &str) -> bool {
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let Some(rest) = target.strip_prefix("eip155:") else {
+        return false;
+    };
+    let Some((chain_id, asset)) = rest.split_once(':') else {
+        return false;
+    };
+    let (contract, token_id) = match asset.split_once('/') {
+        Some((contract, token_id)) => (contract, Some(token_id)),
+        None => (asset, None),
+    };
+    let contract = contract.strip_prefix("0x").unwrap_or_default();
+    digits(chain_id)
+        && contract.len() == 40
+        && contract.bytes().all(|b| b.is_ascii_hexdigit())
+        && token_id.is_none_or(digits)
+}
+
+// Accepts absolute http(s) URLs, or paths on this server that get resolved when rendering
+fn require_http_url(what: &str, url: &str) -> Result<(), AppError> {
+    if url.starts_with("https://") || url.starts_with("http://") || url.starts_with('/') {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!("Invalid {}: {:?}", what, url)))
+    }
+}
+
+// Image shapes Farcaster can display; frames.toml may use either the name or the ratio
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AspectRatio {
+    #[serde(rename = "1.91:1", alias = "wide")]
+    Wide,
+    #[serde(rename = "1:1", alias = "square")]
+    Square,
+}
+
+impl AspectRatio {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AspectRatio::Wide => "1.91:1",
+            AspectRatio::Square => "1:1",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct Button {
     pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<ButtonAction>,
+}
+
+impl Button {
+    pub fn new(label: &str) -> Self {
+        Button {
+            label: label.to_string(),
+            action: None,
+        }
+    }
+
+    pub fn with_action(label: &str, action: ButtonAction) -> Result<Self, AppError> {
+        action.validate()?;
+        Ok(Button {
+            label: label.to_string(),
+            action: Some(action),
+        })
+    }
 }
 
 use crate::config::Config;
 
-pub fn process_button(
+// Placeholder in button labels replaced by the text the user last submitted
+const INPUT_PLACEHOLDER: &str = "{input}";
+
+// Placeholder in labels and alt text replaced by the user's name, when a mini app
+// passed us their profile, e.g. alt = "Hi {name}! ..."
+const NAME_PLACEHOLDER: &str = "{name}";
+
+// Stands in for the name of a user we know nothing about
+pub const ANONYMOUS_NAME: &str = "there";
+
+// Placeholder in link URLs replaced by the hash of the transaction just sent
+const TX_PLACEHOLDER: &str = "{tx}";
+
+// Farcaster caps text input at this many bytes
+const MAX_INPUT_BYTES: usize = 256;
+
+// Template parameters every generated image URL may carry, which a screen's params
+// can't be named after
+pub const TEMPLATE_PARAMS: [&str; 11] = [
+    "image", "v", "input", "name", "fid", "tx", "theme", "step", "steps", "ends_at", "t",
+];
+
+// Params a screen may give that the image route reads itself rather than a template
+// drawing them: recipient, the username whose pfp is drawn as avatar
+pub const ROUTE_PARAMS: [&str; 1] = ["recipient"];
+
+// Who pressed the button, as far as we know. The default is an anonymous user,
+// who sees every screen exactly as configured.
+#[derive(Clone, Debug, Default)]
+pub struct UserContext {
+    pub fid: Option<u64>,
+    // Whether the fid is in KNOWN_FIDS, i.e. already holds a balance
+    pub known: bool,
+    // The app they're using, which decides the image shapes we can send
+    pub client: ClientKind,
+    // The frame version their client speaks, and whether it can send transactions and
+    // text; what it can't do is left off screens
+    pub capabilities: Capabilities,
+    // Their profile from a mini app's context; only the name is shown so far, the
+    // picture and location are there for generated images
+    pub profile: Option<ContextUser>,
+    // Translations for their locale; None shows frames.toml's text
+    pub catalog: Option<Arc<Catalog>>,
+}
+
+impl UserContext {
+    pub fn lookup(fid: Option<u64>, config: &Config) -> Self {
+        UserContext {
+            fid,
+            known: fid.is_some_and(|fid| config.known_fids.contains(&fid)),
+            client: ClientKind::default(),
+            capabilities: Capabilities::default(),
+            profile: None,
+            catalog: None,
+        }
+    }
+
+    // Takes the profile from a mini app's context. It's unsigned, so it's dropped when
+    // it names someone other than the fid a signed message came from.
+    pub fn with_context(mut self, context: Option<ClientContext>) -> Self {
+        self.profile = context
+            .map(|context| context.user)
+            .filter(|profile| self.fid.is_none_or(|fid| fid == profile.fid));
+        self
+    }
+
+    // Frame text in their language, or as written when there's no translation
+    pub fn translate<'a>(&'a self, text: &'a str) -> &'a str {
+        self.catalog
+            .as_ref()
+            .and_then(|catalog| catalog.get(text))
+            .map_or(text, String::as_str)
+    }
+
+    // What {name} becomes: their display name, else their username
+    pub fn name(&self) -> &str {
+        self.profile
+            .as_ref()
+            .and_then(|profile| {
+                profile
+                    .display_name
+                    .as_deref()
+                    .or(profile.username.as_deref())
+            })
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(ANONYMOUS_NAME)
+    }
+}
+
+// The cast a frame was opened from
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct CastId {
+    pub fid: u64,
+    pub hash: String,
+}
+
+// One button press as the client described it, with the signed values in place of the
+// unsigned ones when validation is on. Frame state travels separately as FrameState.
+// Not every field is read by the built-in screens yet.
+#[allow(dead_code)]
+#[derive(Clone, Debug, Default)]
+pub struct FrameContext {
+    pub button_index: usize,
+    pub user: UserContext,
+    pub input_text: Option<String>,
+    // The frame URL the client loaded, which may differ from the one it posted to
+    pub url: Option<String>,
+    pub message_hash: Option<String>,
+    // Seconds since the Farcaster epoch (2021-01-01), per the client
+    pub timestamp: Option<u64>,
+    // 1 is Farcaster mainnet
+    pub network: Option<u32>,
+    pub cast_id: Option<CastId>,
+    // Connected wallet, only sent with the post that follows a tx button
+    pub address: Option<String>,
+    pub transaction_id: Option<String>,
+}
+
+// A screen resolved into what the client should display
+pub struct RenderedScreen {
+    pub image: String,
+    pub image_alt: Option<String>,
+    // The screen's ratio, else DEFAULT_ASPECT_RATIO; left unset, clients fall back to 1.91:1
+    pub image_aspect_ratio: Option<AspectRatio>,
+    pub buttons: Vec<Button>,
+    // Placeholder for the fc:frame:input:text box, if the screen asks for text
+    pub input_text: Option<String>,
+    // The screen's own step to post to under its frame's URL, if it has one
+    pub post_path: Option<String>,
+    // Why the text just submitted was refused; the page draws it in place of the image
+    pub invalid_input: Option<String>,
+}
+
+pub fn render_screen(
+    screen: &ScreenDef,
+    state: &FrameState,
+    user: &UserContext,
+    assets: &AssetHashes,
+    config: &Config,
+) -> Result<RenderedScreen, AppError> {
+    let input = state.input.as_deref().unwrap_or_default();
+    let transaction_id = state.transaction_id.as_deref().unwrap_or_default();
+    let variant = screen
+        .experiment
+        .as_ref()
+        .and_then(|experiment| experiment.variant_for(user));
+    let buttons = screen
+        .buttons_for(user)
+        .into_iter()
+        .map(|button| {
+            // The variant's copy replaces the configured label, and is translated like it
+            let copy = variant
+                .and_then(|variant| variant.labels.get(&button.label))
+                .unwrap_or(&button.label);
+            // Submitted text and translations can be long, so trim them to fit rather
+            // than break the frame
+            let label = truncate_bytes(
+                &user
+                    .translate(copy)
+                    .replace(INPUT_PLACEHOLDER, input)
+                    .replace(NAME_PLACEHOLDER, user.name()),
+                MAX_LABEL_BYTES,
+            );
+            match &button.action {
+                Some(action) => Button::with_action(
+                    &label,
+                    fill_transaction_id(action.resolve(&config.domain), transaction_id),
+                ),
+                None => Ok(Button::new(&label)),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let image = variant
+        .and_then(|variant| variant.image.as_ref())
+        .unwrap_or(&screen.image);
+    // Square screens fall back to their wide image, or the client's default shape,
+    // for clients that can't show square images
+    let (image, image_aspect_ratio, template) =
+        match screen.aspect_ratio.or(config.default_aspect_ratio) {
+            Some(AspectRatio::Square) if !user.client.supports_square_images() => (
+                screen.wide_image.as_ref().unwrap_or(image),
+                Some(AspectRatio::Wide),
+                // A template is drawn at its own size, which would be stretched
+                None,
+            ),
+            ratio => (image, ratio, screen.template.as_ref()),
+        };
+
+    let image = match template {
+        Some(template) => template_url(template, image, screen, state, user, assets, config),
+        None => assets.url(config.asset_origin(), image),
+    };
+
+    Ok(RenderedScreen {
+        image,
+        image_alt: screen.alt.as_ref().map(|alt| {
+            user.translate(alt)
+                .replace(INPUT_PLACEHOLDER, input)
+                .replace(NAME_PLACEHOLDER, user.name())
+        }),
+        image_aspect_ratio,
+        buttons,
+        input_text: screen
+            .input
+            .as_ref()
+            .filter(|_| user.capabilities.text_input)
+            .map(|input| {
+                truncate_bytes(
+                    user.translate(&input.placeholder),
+                    MAX_INPUT_PLACEHOLDER_BYTES,
+                )
+            }),
+        post_path: screen.post_path.clone(),
+        invalid_input: None,
+    })
+}
+
+// Checks a press posted to a step came from the screen with that step, so a stale
+// page's button index is refused rather than applied to whatever screen the state is on
+pub fn check_step(
+    frame_name: &str,
+    step: &str,
+    state: &FrameState,
+    registry: &FrameRegistry,
+) -> Result<(), AppError> {
+    let current = registry.frame(frame_name).and_then(|frame| {
+        frame
+            .screen(state.current())
+            .or_else(|| frame.screen(MAIN_SCREEN))
+    });
+    if current.and_then(ScreenDef::step) == Some(step) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Posted to step {:?} from screen {:?}",
+            step,
+            state.current()
+        )))
+    }
+}
+
+// Whether the index names a button on the screen the state is on (main if it's unknown)
+pub fn is_valid_button(
+    frame_name: &str,
+    button_index: usize,
+    state: &FrameState,
+    user: &UserContext,
+    registry: &FrameRegistry,
+) -> bool {
+    if state.error {
+        return [TRY_AGAIN_BUTTON, START_OVER_BUTTON].contains(&button_index);
+    }
+    registry
+        .frame(frame_name)
+        .and_then(|frame| {
+            frame
+                .screen(state.current())
+                .or_else(|| frame.screen(MAIN_SCREEN))
+        })
+        .is_some_and(|screen| (1..=screen.buttons_for(user).len()).contains(&button_index))
+}
+
+// The configured redirect of a post_redirect button, which is answered with a 302
+// instead of a new screen
+pub fn redirect_for<'a>(
+    frame_name: &str,
     button_index: usize,
+    state: &FrameState,
+    user: &UserContext,
+    registry: &'a FrameRegistry,
+) -> Option<&'a str> {
+    if state.error || state.is_expired() {
+        return None;
+    }
+    let frame = registry.frame(frame_name)?;
+    let screen = frame
+        .screen(state.current())
+        .or_else(|| frame.screen(MAIN_SCREEN))?;
+    let button = screen
+        .buttons_for(user)
+        .get(button_index.checked_sub(1)?)
+        .copied()?;
+    button.redirect.as_deref()
+}
+
+pub async fn process_button(
+    frame_name: &str,
+    context: &FrameContext,
+    mut state: FrameState,
+    registry: &FrameRegistry,
+    assets: &AssetHashes,
     config: &Config,
-) -> Result<(String, Vec<Button>), AppError> {
-    match button_index {
-        1 => Ok((
-            format!("{}/assets/buy_boost.png", config.domain),
-            vec![
-                Button {
-                    label: "Confirm".to_string(),
-                },
-                Button {
-                    label: "Back".to_string(),
-                },
-            ],
-        )),
-        2 => Ok((
-            format!("{}/assets/add_liquidity.png", config.domain),
-            vec![
-                Button {
-                    label: "Add".to_string(),
-                },
-                Button {
-                    label: "Back".to_string(),
-                },
-            ],
-        )),
-        3 => Ok((
-            format!("{}/assets/gift.png", config.domain),
-            vec![
-                Button {
-                    label: "Send Gift".to_string(),
-                },
-                Button {
-                    label: "Back".to_string(),
-                },
-            ],
-        )),
-        4 => Ok((
-            format!("{}/assets/more.png", config.domain),
-            vec![
-                Button {
-                    label: "Reward".to_string(),
-                },
-                Button {
-                    label: "Bid".to_string(),
-                },
-                Button {
-                    label: "Top-up".to_string(),
-                },
-                Button {
-                    label: "Back".to_string(),
-                },
-            ],
-        )),
-        _ => {
-            // Log an error if the button index is invalid
-            Err(AppError::BadRequest(format!(
-                "Invalid button index: {}",
-                button_index
-            )))
+    client: &reqwest::Client,
+) -> Result<(RenderedScreen, FrameState), AppError> {
+    let frame = registry
+        .frame(frame_name)
+        .ok_or_else(|| AppError::NotFound(format!("Unknown frame: {}", frame_name)))?;
+    let (button_index, user) = (context.button_index, &context.user);
+
+    // A transaction the user sent is confirmed however long their wallet took
+    if state.is_expired() && state.transaction_id.is_none() {
+        return Err(AppError::SessionExpired);
+    }
+
+    // State handed off to another frame is that frame's to use
+    if state
+        .frame
+        .as_deref()
+        .is_some_and(|other| other != frame_name)
+    {
+        warn!("Frame state for frame {:?}, resetting", state.frame);
+        state = FrameState::default();
+    }
+
+    // State may name a screen that has since been removed from the frames file
+    let current = match frame.screen(state.current()) {
+        Some(screen) => screen,
+        None => {
+            warn!(
+                "Unknown screen {:?} in frame state, resetting",
+                state.current()
+            );
+            state = FrameState::default();
+            frame
+                .screen(MAIN_SCREEN)
+                .ok_or(AppError::InternalServerError)?
+        }
+    };
+
+    // An error frame's buttons recover: Try Again shows the screen the user was on
+    // again, Start Over the frame's main screen
+    if state.error {
+        state.error = false;
+        match button_index {
+            TRY_AGAIN_BUTTON => {}
+            START_OVER_BUTTON => {
+                state = FrameState {
+                    frame: state.frame,
+                    theme: state.theme,
+                    ..FrameState::default()
+                }
+            }
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid button index on an error frame: {}",
+                    button_index
+                )))
+            }
         }
+        let screen = frame
+            .screen(state.current())
+            .ok_or(AppError::InternalServerError)?;
+        let rendered = render_live_screen(screen, &state, user, assets, config, client).await?;
+        return Ok((rendered, state));
+    }
+
+    // Farcaster numbers buttons from 1, and only the ones the previous screen showed exist
+    let buttons = current.buttons_for(user);
+    let Some(button) = button_index
+        .checked_sub(1)
+        .and_then(|i| buttons.get(i).copied())
+    else {
+        return Err(AppError::BadRequest(format!(
+            "Invalid button index: {} (expected 1 to {})",
+            button_index,
+            buttons.len()
+        )));
+    };
+
+    if let Some(input) = &current.input {
+        let text = context
+            .input_text
+            .as_deref()
+            .map(str::trim)
+            .unwrap_or_default();
+        let going_back = button.target.as_deref() == Some(BACK_TARGET);
+
+        // Clients without text input move on without it rather than ask forever
+        if !going_back && text.is_empty() && user.capabilities.text_input {
+            // Stay on the same screen and ask again rather than advancing without the text
+            if let Some(hint) = &input.required_hint {
+                let mut rendered =
+                    render_live_screen(current, &state, user, assets, config, client).await?;
+                rendered.input_text = Some(truncate_bytes(
+                    user.translate(hint),
+                    MAX_INPUT_PLACEHOLDER_BYTES,
+                ));
+                return Ok((rendered, state));
+            }
+        }
+        if !going_back && !text.is_empty() {
+            // Stay on the same screen with the rule's message rather than pass the text on
+            if let Some(rules) = input.rules.as_ref().filter(|rules| !rules.allows(text)) {
+                let mut rendered =
+                    render_live_screen(current, &state, user, assets, config, client).await?;
+                rendered.invalid_input = Some(user.translate(&rules.message).to_string());
+                return Ok((rendered, state));
+            }
+            state.input = Some(truncate_bytes(text, MAX_INPUT_BYTES));
+        }
+    }
+
+    // A tx button's target is where the client lands once the transaction is sent
+    let awaiting_tx =
+        matches!(button.action, Some(ButtonAction::Tx { .. })) && state.transaction_id.is_none();
+    if let (Some(other), false) = (&button.frame, awaiting_tx) {
+        return hand_off(
+            other, button, state, context, registry, assets, config, client,
+        )
+        .await;
+    }
+    if let Some(theme) = button.theme {
+        state.theme = Some(theme);
+    }
+    match button.target.as_deref() {
+        _ if awaiting_tx => {}
+        None => {}
+        Some(BACK_TARGET) => state.pop(),
+        Some(target) => state.push(target),
+    }
+
+    let screen = frame
+        .screen(state.current())
+        .ok_or(AppError::InternalServerError)?;
+    let rendered = render_live_screen(screen, &state, user, assets, config, client).await?;
+    Ok((rendered, state))
+}
+
+// Opens another frame, carrying over what the user entered and sent so it can pick
+// up where this one left off. The state is signed again with the rest of the page.
+#[allow(clippy::too_many_arguments)]
+async fn hand_off(
+    frame_name: &str,
+    button: &ButtonDef,
+    state: FrameState,
+    context: &FrameContext,
+    registry: &FrameRegistry,
+    assets: &AssetHashes,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<(RenderedScreen, FrameState), AppError> {
+    let frame = registry
+        .frame(frame_name)
+        .ok_or_else(|| AppError::NotFound(format!("Unknown frame: {}", frame_name)))?;
+    let mut next = FrameState {
+        input: state.input,
+        transaction_id: state.transaction_id,
+        theme: state.theme,
+        frame: Some(frame_name.to_string()),
+        ..FrameState::default()
+    };
+    if let Some(target) = button.target.as_deref().filter(|t| *t != MAIN_SCREEN) {
+        next.push(target);
+    }
+    let screen = frame
+        .screen(next.current())
+        .ok_or(AppError::InternalServerError)?;
+    let rendered = render_live_screen(screen, &next, &context.user, assets, config, client).await?;
+    Ok((rendered, next))
+}
+
+// Renders a screen, swapping in live data where the screen asks for it
+async fn render_live_screen(
+    screen: &ScreenDef,
+    state: &FrameState,
+    user: &UserContext,
+    assets: &AssetHashes,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<RenderedScreen, AppError> {
+    let mut rendered = render_screen(screen, state, user, assets, config)?;
+    if let (Some(images), Some(apr_url)) = (&screen.apr_images, &config.apr_url) {
+        match apr::fetch_apr(client, apr_url).await {
+            Ok(apr) => {
+                let image = if apr >= config.high_apr_percent {
+                    &images.high
+                } else {
+                    &images.low
+                };
+                rendered.image = assets.url(config.asset_origin(), image);
+            }
+            // The neutral image is still accurate, just less informative
+            Err(err) => warn!("Failed to fetch pool APR, using {}: {}", screen.image, err),
+        }
+    }
+    Ok(rendered)
+}
+
+// The generated image for a screen's template, given what it can draw of this press:
+// the screen's image (as chosen for this user and client), the text they entered,
+// their name and fid, the transaction they sent, the step of its flow, and the
+// screen's own params. An animated screen's is a GIF.
+fn template_url(
+    template: &str,
+    image: &str,
+    screen: &ScreenDef,
+    state: &FrameState,
+    user: &UserContext,
+    assets: &AssetHashes,
+    config: &Config,
+) -> String {
+    let fid = user.fid.map(|fid| fid.to_string());
+    let progress = screen
+        .progress
+        .map(|progress| (progress.step.to_string(), progress.steps.to_string()));
+    let ends_at = screen.ends_at.map(|ends_at| ends_at.to_string());
+    let buster = screen
+        .ends_at
+        .map(|_| countdown::cache_buster(countdown::unix_now()));
+    let params = [
+        ("image", Some(image)),
+        // Changes the URL when the base image does, as AssetHashes::url does
+        ("v", assets.version(image)),
+        ("input", state.input.as_deref()),
+        // Otherwise the image looks their name up from the fid
+        ("name", user.profile.as_ref().map(|_| user.name())),
+        ("fid", fid.as_deref()),
+        ("tx", state.transaction_id.as_deref()),
+        ("theme", state.theme.map(Theme::as_str)),
+        // Which step of its flow the screen is, for a step indicator
+        ("step", progress.as_ref().map(|(step, _)| step.as_str())),
+        ("steps", progress.as_ref().map(|(_, steps)| steps.as_str())),
+        // Drawn as a countdown, in an image whose URL changes as often as it does
+        ("ends_at", ends_at.as_deref()),
+        ("t", buster.as_deref()),
+    ];
+    let screen_params = screen.params.iter().map(|(name, value)| {
+        let value = user
+            .translate(value)
+            .replace(
+                INPUT_PLACEHOLDER,
+                state.input.as_deref().unwrap_or_default(),
+            )
+            .replace(NAME_PLACEHOLDER, user.name());
+        (name.as_str(), Some(value))
+    });
+    let params = params
+        .into_iter()
+        .map(|(name, value)| (name, value.map(str::to_string)))
+        .chain(screen_params);
+    // Always in the same order and without empty values, so the same image is the
+    // same URL, and a CDN keeping copies by URL serves it from one
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    for (name, value) in params {
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            query.append_pair(name, &truncate_bytes(&value, MAX_PARAM_BYTES));
+        }
+    }
+    format!(
+        "{}/images/{}.{}?{}",
+        config.asset_origin(),
+        template,
+        if screen.animated { "gif" } else { "png" },
+        query.finish()
+    )
+}
+
+fn fill_transaction_id(action: ButtonAction, transaction_id: &str) -> ButtonAction {
+    match action {
+        ButtonAction::Link { url } => ButtonAction::Link {
+            url: url.replace(TX_PLACEHOLDER, transaction_id),
+        },
+        other => other,
+    }
+}
+
+fn truncate_bytes(text: &str, max_bytes: usize) -> String {
+    let mut end = text.len().min(max_bytes);
+    while !text.is_char_boundary(end) {
+        end -= 1;
     }
+    text[..end].to_string()
 }
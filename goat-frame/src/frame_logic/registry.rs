@@ -0,0 +1,611 @@
+use crate::errors::RegistryError;
+use crate::experiments::{self, CONTROL};
+use crate::frame_html::MAX_LABEL_BYTES;
+use crate::frame_logic::state::{BACK_TARGET, MAIN_SCREEN};
+use crate::frame_logic::{AspectRatio, ButtonAction, UserContext, ROUTE_PARAMS, TEMPLATE_PARAMS};
+use crate::i18n::Translations;
+use crate::images::theme::{Theme, COLOR_PREFIX};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+// Farcaster renders at most this many buttons per frame
+pub const MAX_BUTTONS: usize = 4;
+
+// Screen names end up in the frame state, so keep them short
+const MAX_SCREEN_NAME_BYTES: usize = 64;
+
+// Frame names end up in URLs
+const MAX_FRAME_NAME_BYTES: usize = 64;
+
+// A screen's own template params, leaving room in the URL for the ones every image gets
+const MAX_SCREEN_PARAMS: usize = 8;
+
+// Longer flows than this are better split, and their step indicators get cramped
+const MAX_FLOW_STEPS: usize = 10;
+
+// Frame served at /, and at /api/frame for older posts
+pub const DEFAULT_FRAME: &str = "store";
+
+// Every frame the server hosts, keyed by the name in /f/{name}
+#[derive(Debug, Deserialize)]
+pub struct FrameRegistry {
+    pub frames: HashMap<String, FrameDef>,
+    // The screens' text in other languages, from LOCALES_PATH rather than this file
+    #[serde(skip)]
+    pub translations: Translations,
+}
+
+// Every screen a frame can show, keyed by the name buttons use to target it
+#[derive(Debug, Deserialize)]
+pub struct FrameDef {
+    pub screens: HashMap<String, ScreenDef>,
+    // Multi-step flows, each its screens in order, e.g. buy = ["buy", "confirm", "done"];
+    // their templates are told which step of how many the user is on
+    #[serde(default)]
+    pub flows: BTreeMap<String, Vec<String>>,
+}
+
+// Where a screen falls in its flow, counting from 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    pub step: usize,
+    pub steps: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScreenDef {
+    // File name under assets/
+    pub image: String,
+    // Describes the image for screen readers; "{input}" is replaced like in labels
+    #[serde(default)]
+    pub alt: Option<String>,
+    #[serde(default)]
+    pub aspect_ratio: Option<AspectRatio>,
+    // A 1.91:1 version of a square image, for clients that can't show square ones
+    #[serde(default)]
+    pub wide_image: Option<String>,
+    // Shown instead of image depending on the live pool APR, when APR_URL is set
+    #[serde(default)]
+    pub apr_images: Option<AprImages>,
+    #[serde(default)]
+    pub input: Option<InputDef>,
+    pub buttons: Vec<ButtonDef>,
+    // Step this screen's buttons post to, e.g. "confirm?flow=buy" posts to
+    // /f/<frame>/confirm?flow=buy, so a press says which screen it came from
+    #[serde(default)]
+    pub post_path: Option<String>,
+    // An A/B test of this screen's image or button copy
+    #[serde(default)]
+    pub experiment: Option<ExperimentDef>,
+    // Image template from TEMPLATES_PATH shown instead of image, e.g. image with the
+    // amount entered drawn on top
+    #[serde(default)]
+    pub template: Option<String>,
+    // More values for the template's placeholders, e.g. title = "Gift to @{input}";
+    // they're translated and take "{input}" and "{name}" like labels
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    // Shows the template as an animated GIF of its frames rather than a PNG of the first
+    #[serde(default)]
+    pub animated: bool,
+    // When the screen's auction ends, in unix seconds, for the template to count down
+    // to as {{countdown}}
+    #[serde(default)]
+    pub ends_at: Option<u64>,
+    // Taken from the frame's flows rather than set per screen
+    #[serde(skip)]
+    pub progress: Option<Progress>,
+}
+
+// Splits fids evenly between the screen as configured ("control") and each variant
+#[derive(Debug, Deserialize)]
+pub struct ExperimentDef {
+    pub name: String,
+    // Screen of the same frame whose arrival counts as a conversion
+    pub goal: String,
+    pub variants: Vec<VariantDef>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VariantDef {
+    pub name: String,
+    // Shown instead of the screen's image
+    #[serde(default)]
+    pub image: Option<String>,
+    // Button copy to show instead, keyed by the configured label
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+impl ExperimentDef {
+    // The variant a user sees, None for control and anyone without an fid
+    pub fn variant_for(&self, user: &UserContext) -> Option<&VariantDef> {
+        let arm = experiments::assign(&self.name, user.fid?, self.variants.len() + 1);
+        arm.checked_sub(1).and_then(|i| self.variants.get(i))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AprImages {
+    pub high: String,
+    pub low: String,
+}
+
+// A free-form text box shown under the image
+#[derive(Debug, Deserialize)]
+pub struct InputDef {
+    pub placeholder: String,
+    // When set, buttons other than Back need text and show this hint if it's missing
+    #[serde(default)]
+    pub required_hint: Option<String>,
+    // What submitted text must look like before buttons other than Back advance
+    #[serde(default)]
+    pub rules: Option<InputRules>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InputRules {
+    // The text must be a number in this range, e.g. an amount of GOAT
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    // The whole text must match, e.g. "[a-z0-9][a-z0-9-]{0,15}" for a username
+    #[serde(default)]
+    pub pattern: Option<Pattern>,
+    // Drawn as the screen's image when text breaks a rule, e.g. "Enter 1 to 1000 GOAT"
+    pub message: String,
+}
+
+impl InputRules {
+    pub fn allows(&self, text: &str) -> bool {
+        if self.min.is_some() || self.max.is_some() {
+            let Ok(number) = text.parse::<f64>() else {
+                return false;
+            };
+            let in_range = number.is_finite()
+                && self.min.is_none_or(|min| number >= min)
+                && self.max.is_none_or(|max| number <= max);
+            if !in_range {
+                return false;
+            }
+        }
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.0.is_match(text))
+    }
+}
+
+// A regex anchored to the whole text, compiled when the frames file is read so a
+// bad one stops the server from starting
+#[derive(Debug)]
+pub struct Pattern(Regex);
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&format!("^(?:{})$", pattern))
+            .map(Pattern)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ButtonDef {
+    pub label: String,
+    // Screen to open, "back" to return to the previous one, or nothing to stay put
+    #[serde(default)]
+    pub target: Option<String>,
+    #[serde(default)]
+    pub action: Option<ButtonAction>,
+    // Where a post_redirect button sends the user: a path under CHECKOUT_BASE or a full URL
+    #[serde(default)]
+    pub redirect: Option<String>,
+    // Left off the screen for fids in KNOWN_FIDS, e.g. a Top-up they don't need
+    #[serde(default)]
+    pub hide_for_known: bool,
+    // Another frame served here to hand the user off to, opening on target (main if
+    // unset) with the text and transaction they have so far
+    #[serde(default)]
+    pub frame: Option<String>,
+    // Switches the user's generated images to "dark" or "light" from here on
+    #[serde(default)]
+    pub theme: Option<Theme>,
+}
+
+impl FrameRegistry {
+    pub fn load(path: &Path, assets_dir: &Path) -> Result<Self, RegistryError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| RegistryError::Read(path.display().to_string(), err))?;
+        let registry = Self::from_toml(&contents)?;
+        registry.validate(assets_dir)?;
+        Ok(registry)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, RegistryError> {
+        let mut registry: FrameRegistry = toml::from_str(contents).map_err(RegistryError::Parse)?;
+        for FrameDef { screens, flows } in registry.frames.values_mut() {
+            for flow in flows.values() {
+                for (i, name) in flow.iter().enumerate() {
+                    if let Some(screen) = screens.get_mut(name) {
+                        screen.progress = Some(Progress {
+                            step: i + 1,
+                            steps: flow.len(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn frame(&self, name: &str) -> Option<&FrameDef> {
+        self.frames.get(name)
+    }
+
+    // Templates load separately from this file, so they're checked once both have.
+    // placeholders gives a template's, None for one that doesn't exist.
+    pub fn check_templates(
+        &self,
+        placeholders: impl Fn(&str) -> Option<BTreeSet<String>>,
+    ) -> Result<(), RegistryError> {
+        for (name, frame) in &self.frames {
+            for (screen_name, screen) in &frame.screens {
+                let Some(template) = &screen.template else {
+                    continue;
+                };
+                let invalid = |reason: String| {
+                    Err(RegistryError::InvalidScreen(
+                        format!("{}/{}", name, screen_name),
+                        reason,
+                    ))
+                };
+                let Some(placeholders) = placeholders(template) else {
+                    return invalid(format!("unknown image template {:?}", template));
+                };
+                // A param the template doesn't draw, nor the image route read, is most
+                // likely a typo
+                let unused =
+                    |p: &&String| !placeholders.contains(*p) && !ROUTE_PARAMS.contains(&p.as_str());
+                if let Some(param) = screen.params.keys().find(unused) {
+                    return invalid(format!(
+                        "template {:?} has no {{{{{}}}}} placeholder for param {:?}",
+                        template, param, param
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Checks every reference up front so a typo fails at startup rather than mid-flow
+    pub fn validate(&self, assets_dir: &Path) -> Result<(), RegistryError> {
+        for (name, frame) in &self.frames {
+            let url_safe = name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if name.is_empty() || name.len() > MAX_FRAME_NAME_BYTES || !url_safe {
+                return Err(RegistryError::InvalidFrame(
+                    name.clone(),
+                    "names must be short and use only letters, digits, - and _".to_string(),
+                ));
+            }
+            frame.validate(name, assets_dir)?;
+        }
+
+        // Results are counted by experiment name, so one can't cover two screens
+        let mut experiments = HashMap::new();
+        for (name, frame) in &self.frames {
+            for (screen_name, screen) in &frame.screens {
+                let Some(experiment) = &screen.experiment else {
+                    continue;
+                };
+                let screen = format!("{}/{}", name, screen_name);
+                if let Some(other) = experiments.insert(&experiment.name, screen.clone()) {
+                    return Err(RegistryError::InvalidScreen(
+                        screen,
+                        format!("experiment {:?} is also run on {}", experiment.name, other),
+                    ));
+                }
+            }
+        }
+
+        // Hand-offs are checked against the frame they lead to
+        for (name, frame) in &self.frames {
+            for (screen_name, screen) in &frame.screens {
+                for button in &screen.buttons {
+                    let Some(other) = &button.frame else {
+                        continue;
+                    };
+                    let opens = button.target.as_deref().unwrap_or(MAIN_SCREEN);
+                    if self
+                        .frame(other)
+                        .and_then(|other| other.screen(opens))
+                        .is_none()
+                    {
+                        return Err(RegistryError::MissingTarget(
+                            format!("{}/{}", name, screen_name),
+                            button.label.clone(),
+                            format!("{}/{}", other, opens),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ScreenDef {
+    // The post_path without its query, as it appears in the route
+    pub fn step(&self) -> Option<&str> {
+        self.post_path
+            .as_deref()
+            .map(|path| path.split('?').next().unwrap_or_default())
+    }
+
+    // The buttons this user sees, in order; button indexes count only these
+    pub fn buttons_for(&self, user: &UserContext) -> Vec<&ButtonDef> {
+        self.buttons
+            .iter()
+            .filter(|button| !(button.hide_for_known && user.known))
+            // A tx button does nothing in a client that can't send the transaction
+            .filter(|button| {
+                user.capabilities.transactions
+                    || !matches!(button.action, Some(ButtonAction::Tx { .. }))
+            })
+            .collect()
+    }
+}
+
+impl FrameDef {
+    pub fn screen(&self, name: &str) -> Option<&ScreenDef> {
+        self.screens.get(name)
+    }
+
+    fn validate_experiment(
+        &self,
+        name: &str,
+        screen_name: &str,
+        screen: &ScreenDef,
+        experiment: &ExperimentDef,
+    ) -> Result<(), RegistryError> {
+        let invalid = |reason: String| Err(RegistryError::InvalidScreen(name.to_string(), reason));
+        if experiment.name.is_empty() {
+            return invalid("experiments need a name".to_string());
+        }
+        if experiment.goal == screen_name || !self.screens.contains_key(&experiment.goal) {
+            return invalid(format!(
+                "experiment {:?} needs a goal screen other than its own, not {:?}",
+                experiment.name, experiment.goal
+            ));
+        }
+        if experiment.variants.is_empty() {
+            return invalid(format!("experiment {:?} has no variants", experiment.name));
+        }
+        // An image the live APR picks would hide the variant's
+        if screen.apr_images.is_some() && experiment.variants.iter().any(|v| v.image.is_some()) {
+            return invalid("variant images can't be combined with apr_images".to_string());
+        }
+        for (i, variant) in experiment.variants.iter().enumerate() {
+            let repeated = experiment.variants[..i]
+                .iter()
+                .any(|other| other.name == variant.name);
+            if variant.name.is_empty() || variant.name == CONTROL || repeated {
+                return invalid(format!(
+                    "variant {:?} needs a unique name other than {:?}",
+                    variant.name, CONTROL
+                ));
+            }
+            for (label, copy) in &variant.labels {
+                if !screen.buttons.iter().any(|button| &button.label == label) {
+                    return invalid(format!(
+                        "variant {:?} renames missing button {:?}",
+                        variant.name, label
+                    ));
+                }
+                if copy.is_empty() || copy.len() > MAX_LABEL_BYTES {
+                    return invalid(format!(
+                        "variant {:?} label {:?} must be 1 to {} bytes",
+                        variant.name, copy, MAX_LABEL_BYTES
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self, frame: &str, assets_dir: &Path) -> Result<(), RegistryError> {
+        if !self.screens.contains_key(MAIN_SCREEN) {
+            return Err(RegistryError::MissingScreen(format!(
+                "{}/{}",
+                frame, MAIN_SCREEN
+            )));
+        }
+
+        // A screen has one place in one flow, or its step would be ambiguous
+        let mut in_flows = HashSet::new();
+        for (flow, screens) in &self.flows {
+            let invalid = |reason: String| {
+                Err(RegistryError::InvalidFrame(
+                    frame.to_string(),
+                    format!("flow {:?} {}", flow, reason),
+                ))
+            };
+            if !(2..=MAX_FLOW_STEPS).contains(&screens.len()) {
+                return invalid(format!("must have 2 to {} screens", MAX_FLOW_STEPS));
+            }
+            if let Some(missing) = screens.iter().find(|s| !self.screens.contains_key(*s)) {
+                return invalid(format!("names missing screen {:?}", missing));
+            }
+            if let Some(repeated) = screens.iter().find(|s| !in_flows.insert(s.as_str())) {
+                return invalid(format!("repeats screen {:?}, already in a flow", repeated));
+            }
+        }
+
+        for (screen_name, screen) in &self.screens {
+            // Errors name the screen along with its frame
+            let name = format!("{}/{}", frame, screen_name);
+            if screen_name == BACK_TARGET || screen_name.len() > MAX_SCREEN_NAME_BYTES {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    "reserved or overly long name".to_string(),
+                ));
+            }
+            if screen.buttons.is_empty() || screen.buttons.len() > MAX_BUTTONS {
+                return Err(RegistryError::InvalidScreen(
+                    name.clone(),
+                    format!("must have 1 to {} buttons", MAX_BUTTONS),
+                ));
+            }
+            // The step is a path segment next to /f/<frame>/frame, which it can't shadow
+            if let Some(step) = screen.step() {
+                let url_safe = step
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                let query_safe = screen
+                    .post_path
+                    .as_deref()
+                    .is_some_and(|path| !path.contains(|c: char| c.is_whitespace() || c == '#'));
+                if step.is_empty()
+                    || step.len() > MAX_SCREEN_NAME_BYTES
+                    || step == "frame"
+                    || !url_safe
+                    || !query_safe
+                {
+                    return Err(RegistryError::InvalidScreen(
+                        name,
+                        "post_path must be a short step name, optionally with a query".to_string(),
+                    ));
+                }
+            }
+            // Everyone starts on main, so it looks the same whoever opens the frame
+            if screen_name == MAIN_SCREEN && screen.buttons.iter().any(|b| b.hide_for_known) {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    "main screen buttons can't be hidden per user".to_string(),
+                ));
+            }
+            if screen.buttons.iter().all(|b| b.hide_for_known) {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    "every button is hidden for known fids".to_string(),
+                ));
+            }
+            let apr_images = screen
+                .apr_images
+                .iter()
+                .flat_map(|apr| [&apr.high, &apr.low]);
+            let variant_images = screen
+                .experiment
+                .iter()
+                .flat_map(|experiment| &experiment.variants)
+                .filter_map(|variant| variant.image.as_ref());
+            let images = std::iter::once(&screen.image)
+                .chain(&screen.wide_image)
+                .chain(apr_images)
+                .chain(variant_images);
+            for image in images {
+                if !assets_dir.join(image).is_file() {
+                    return Err(RegistryError::MissingImage(name.clone(), image.clone()));
+                }
+            }
+            if let Some(experiment) = &screen.experiment {
+                self.validate_experiment(&name, screen_name, screen, experiment)?;
+            }
+            // The live APR picks between static images, which a template would hide
+            if screen.template.is_some() && screen.apr_images.is_some() {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    "template can't be combined with apr_images".to_string(),
+                ));
+            }
+            if !screen.params.is_empty() && screen.template.is_none() {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    "params need a template to fill".to_string(),
+                ));
+            }
+            if screen.animated && screen.template.is_none() {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    "animated needs a template to animate".to_string(),
+                ));
+            }
+            if screen.ends_at.is_some() && screen.template.is_none() {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    "ends_at needs a template to draw the countdown".to_string(),
+                ));
+            }
+            if screen.params.len() > MAX_SCREEN_PARAMS {
+                return Err(RegistryError::InvalidScreen(
+                    name,
+                    format!("at most {} template params", MAX_SCREEN_PARAMS),
+                ));
+            }
+            for param in screen.params.keys() {
+                let valid = !param.is_empty()
+                    && param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                // theme_ colors are filled in by the theme the image is asked for
+                let reserved =
+                    TEMPLATE_PARAMS.contains(&param.as_str()) || param.starts_with(COLOR_PREFIX);
+                if !valid || reserved {
+                    return Err(RegistryError::InvalidScreen(
+                        name,
+                        format!(
+                            "template param {:?} must be a placeholder name other than {} \
+                             and {}<color>",
+                            param,
+                            TEMPLATE_PARAMS.join(", "),
+                            COLOR_PREFIX
+                        ),
+                    ));
+                }
+            }
+
+            for button in &screen.buttons {
+                // A hand-off's target is a screen of the other frame, checked above
+                if let (Some(target), None) = (&button.target, &button.frame) {
+                    if target != BACK_TARGET && !self.screens.contains_key(target) {
+                        return Err(RegistryError::MissingTarget(
+                            name.clone(),
+                            button.label.clone(),
+                            target.clone(),
+                        ));
+                    }
+                }
+                if let Some(action) = &button.action {
+                    action.validate().map_err(|err| {
+                        RegistryError::InvalidScreen(name.clone(), err.to_string())
+                    })?;
+                }
+                let redirects = matches!(button.action, Some(ButtonAction::PostRedirect));
+                if redirects != button.redirect.is_some() {
+                    return Err(RegistryError::InvalidScreen(
+                        name.clone(),
+                        format!(
+                            "button {:?} needs both a post_redirect action and a redirect",
+                            button.label
+                        ),
+                    ));
+                }
+                // Only a press posted back here can change what the user sees next
+                if button.theme.is_some() && button.action.is_some() {
+                    return Err(RegistryError::InvalidScreen(
+                        name.clone(),
+                        format!(
+                            "button {:?} sets a theme, so it can't have an action",
+                            button.label
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
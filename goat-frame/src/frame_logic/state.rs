@@ -0,0 +1,162 @@
+use crate::images::theme::Theme;
+use hmac::{Hmac, Mac};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Farcaster drops frame state larger than this many bytes
+pub const MAX_STATE_BYTES: usize = 4096;
+
+// Deep enough for any real flow while keeping the encoded state far below the limit
+const MAX_STACK_DEPTH: usize = 32;
+
+// Screen every flow starts from and returns to
+pub const MAIN_SCREEN: &str = "main";
+
+// Button target that returns to the previous screen
+pub const BACK_TARGET: &str = "back";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameState {
+    // Names of the screens the user has visited, the current one last; main is always at the bottom
+    pub stack: Vec<String>,
+    // Text most recently submitted through an input box, e.g. a gift recipient
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    // Hash of the transaction the client just reported sending, only for the screen it lands on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    // Frame a button handed the user off to; the stack then names its screens and
+    // later posts go to it. None is whichever frame the state is posted to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<String>,
+    // Set while the user is looking at an error frame, whose buttons recover rather
+    // than act on the screen underneath
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub error: bool,
+    // Unix seconds after which a press on the frame showing this state starts over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    // Theme the user picked for generated images; None is DEFAULT_THEME's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+}
+
+impl Default for FrameState {
+    fn default() -> Self {
+        FrameState {
+            stack: vec![MAIN_SCREEN.to_string()],
+            input: None,
+            transaction_id: None,
+            frame: None,
+            error: false,
+            expires_at: None,
+            theme: None,
+        }
+    }
+}
+
+impl FrameState {
+    pub fn current(&self) -> &str {
+        self.stack.last().map_or(MAIN_SCREEN, String::as_str)
+    }
+
+    pub fn push(&mut self, screen: &str) {
+        if self.stack.len() >= MAX_STACK_DEPTH {
+            // Forget the oldest screen above main rather than growing without bound
+            self.stack.remove(1);
+        }
+        self.stack.push(screen.to_string());
+    }
+
+    pub fn pop(&mut self) {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+        }
+    }
+
+    // A copy to send out that expires ttl_secs from now; 0 never expires
+    pub fn expiring(&self, ttl_secs: u64) -> Self {
+        FrameState {
+            expires_at: (ttl_secs > 0).then(|| unix_now() + ttl_secs),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= unix_now())
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // Encodes the state followed by ".<hex HMAC-SHA256>" when there is a secret
+    pub fn encode_signed(&self, secret: Option<&str>) -> String {
+        let encoded = self.encode();
+        match secret {
+            Some(secret) => {
+                let signature = hex::encode(state_mac(secret, &encoded).finalize().into_bytes());
+                format!("{}.{}", encoded, signature)
+            }
+            None => encoded,
+        }
+    }
+
+    // Like decode, but state that isn't signed with the secret starts the user over
+    pub fn decode_signed(raw: &str, secret: Option<&str>) -> Self {
+        let Some(secret) = secret else {
+            return Self::decode(raw);
+        };
+        if raw.is_empty() {
+            return FrameState::default();
+        }
+
+        // The signature is hex, so the last dot always separates it from the JSON
+        let verified = raw.rsplit_once('.').filter(|(encoded, signature)| {
+            hex::decode(signature)
+                .is_ok_and(|signature| state_mac(secret, encoded).verify_slice(&signature).is_ok())
+        });
+        match verified {
+            Some((encoded, _)) => Self::decode(encoded),
+            None => {
+                warn!("Frame state with a missing or bad signature, resetting");
+                FrameState::default()
+            }
+        }
+    }
+
+    // Anything we can't make sense of starts the user over on main instead of erroring
+    pub fn decode(raw: &str) -> Self {
+        if raw.is_empty() || raw.len() > MAX_STATE_BYTES {
+            return FrameState::default();
+        }
+
+        match serde_json::from_str::<FrameState>(raw) {
+            Ok(state) if state.stack.first().map(String::as_str) == Some(MAIN_SCREEN) => state,
+            Ok(_) => {
+                warn!("Frame state without a main root, resetting");
+                FrameState::default()
+            }
+            Err(err) => {
+                warn!("Corrupt frame state, resetting: {}", err);
+                FrameState::default()
+            }
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn state_mac(secret: &str, encoded: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(encoded.as_bytes());
+    mac
+}
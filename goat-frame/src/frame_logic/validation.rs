@@ -0,0 +1,145 @@
+use crate::errors::AppError;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use log::{error, warn};
+use serde::Deserialize;
+
+// Fields of a frame action that a hub has confirmed were signed by the user
+pub struct ValidatedAction {
+    pub fid: u64,
+    // When the user signed the message, in seconds since the Farcaster epoch
+    pub timestamp: u64,
+    pub button_index: usize,
+    // Hash of the signed message, identical across client retries
+    pub message_hash: Option<String>,
+    // Text and state as signed, so a client can't swap them after the user pressed the button
+    pub input_text: Option<String>,
+    pub state: Option<String>,
+    // Hash of the transaction sent by a tx button, 0x-prefixed hex
+    pub transaction_id: Option<String>,
+    // The wallet the client connected, signed along with a tx button's post
+    pub address: Option<[u8; 20]>,
+}
+
+#[derive(Deserialize)]
+struct ValidateMessageResponse {
+    valid: bool,
+    message: Option<HubMessage>,
+}
+
+#[derive(Deserialize)]
+struct HubMessage {
+    data: HubMessageData,
+    #[serde(default)]
+    hash: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HubMessageData {
+    fid: u64,
+    timestamp: u64,
+    frame_action_body: Option<FrameActionBody>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameActionBody {
+    button_index: usize,
+    // Bytes fields, which the hub returns base64 encoded
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    input_text: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    transaction_id: String,
+    #[serde(default)]
+    address: String,
+}
+
+// Asks the hub whether message_bytes is a frame action a user really signed, on a
+// frame served from domain (DOMAIN) rather than one a signature was lifted from
+pub async fn validate_frame_message(
+    client: &reqwest::Client,
+    hub_url: &str,
+    domain: &str,
+    message_bytes: &str,
+) -> Result<ValidatedAction, AppError> {
+    // Farcaster clients send the signed protobuf message hex encoded
+    let bytes = hex::decode(message_bytes.trim_start_matches("0x"))
+        .map_err(|_| AppError::BadRequest("Malformed message bytes".to_string()))?;
+
+    let response = client
+        .post(format!("{}/v1/validateMessage", hub_url))
+        .header("Content-Type", "application/octet-stream")
+        .body(bytes)
+        .send()
+        .await
+        .map_err(|err| {
+            // The hub being down is our problem, not the caller's
+            error!("Failed to reach Farcaster hub at {}: {}", hub_url, err);
+            AppError::InternalServerError
+        })?;
+
+    if !response.status().is_success() {
+        warn!("Hub rejected message with status {}", response.status());
+        return Err(invalid_message());
+    }
+
+    let body: ValidateMessageResponse = response.json().await.map_err(|err| {
+        error!("Unexpected response from Farcaster hub: {}", err);
+        AppError::InternalServerError
+    })?;
+
+    // A valid message that isn't a frame action can't be acted upon either
+    let message = body
+        .message
+        .filter(|_| body.valid)
+        .ok_or_else(invalid_message)?;
+    let action = message.data.frame_action_body.ok_or_else(invalid_message)?;
+    let signed_url = decode_text(&action.url)?.unwrap_or_default();
+    let ours = url::Url::parse(domain).map(|domain| domain.origin());
+    let theirs = url::Url::parse(&signed_url).map(|url| url.origin());
+    if !matches!((ours, theirs), (Ok(ours), Ok(theirs)) if ours == theirs) {
+        warn!("Frame message was signed for {:?}", signed_url);
+        return Err(AppError::Unauthorized(
+            "Frame message was signed for another frame".to_string(),
+        ));
+    }
+
+    Ok(ValidatedAction {
+        fid: message.data.fid,
+        timestamp: message.data.timestamp,
+        button_index: action.button_index,
+        message_hash: message.hash,
+        input_text: decode_text(&action.input_text)?,
+        state: decode_text(&action.state)?,
+        transaction_id: decode_bytes(&action.transaction_id)?
+            .map(|hash| format!("0x{}", hex::encode(hash))),
+        address: decode_bytes(&action.address)?
+            .map(|address| address.try_into().map_err(|_| invalid_message()))
+            .transpose()?,
+    })
+}
+
+// Empty fields mean the user sent nothing, the same as a missing untrusted field
+fn decode_bytes(field: &str) -> Result<Option<Vec<u8>>, AppError> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    BASE64_STANDARD.decode(field).map(Some).map_err(|err| {
+        error!("Undecodable bytes field from Farcaster hub: {}", err);
+        AppError::InternalServerError
+    })
+}
+
+fn decode_text(field: &str) -> Result<Option<String>, AppError> {
+    decode_bytes(field)?
+        .map(|bytes| String::from_utf8(bytes).map_err(|_| invalid_message()))
+        .transpose()
+}
+
+fn invalid_message() -> AppError {
+    AppError::Unauthorized("Frame message failed validation".to_string())
+}
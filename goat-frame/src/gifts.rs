@@ -0,0 +1,241 @@
+use crate::config::Config;
+use crate::countdown::unix_now;
+use crate::errors::AppError;
+use crate::profiles;
+use crate::tx::{self, TransactionParams, TransactionResponse};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Farcaster usernames are at most 16 characters, and ENS names a little longer
+const MAX_USERNAME_LEN: usize = 64;
+
+// Most gifts held at once; past it the oldest are forgotten
+pub const MAX_GIFTS: usize = 10_000;
+
+// A gift as its transaction was built, by a sender a hub vouched for. Whether the
+// wallet went on to send it is for the chain to say.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gift {
+    pub from_fid: u64,
+    pub to_fid: u64,
+    pub to_username: String,
+    pub to_address: String,
+    // In wei, as a string since JSON numbers can't hold every amount
+    pub amount: String,
+    // Seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+// One line of GIFTS_PATH
+#[derive(Serialize, Deserialize)]
+pub struct SavedGift {
+    pub id: String,
+    #[serde(flatten)]
+    pub gift: Gift,
+}
+
+// The latest MAX_GIFTS gifts, appended to GIFTS_PATH as JSON lines so they survive
+// restarts. The file is rewritten with just those once it holds twice as many.
+pub struct GiftStore {
+    path: Option<PathBuf>,
+    gifts: Mutex<Gifts>,
+}
+
+struct Gifts {
+    // Oldest first
+    held: VecDeque<SavedGift>,
+    // Lines in the file, held or not
+    lines: usize,
+}
+
+impl GiftStore {
+    // Keeps gifts in memory only
+    pub fn in_memory() -> Self {
+        GiftStore {
+            path: None,
+            gifts: Mutex::new(Gifts {
+                held: VecDeque::new(),
+                lines: 0,
+            }),
+        }
+    }
+
+    // Loads the gifts saved at path, starting empty if the file doesn't exist yet
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut held = VecDeque::new();
+        let mut lines = 0;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let saved: SavedGift = serde_json::from_str(line)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            if held.len() == MAX_GIFTS {
+                held.pop_front();
+            }
+            held.push_back(saved);
+            lines += 1;
+        }
+        let store = GiftStore {
+            path: Some(path.to_path_buf()),
+            gifts: Mutex::new(Gifts { held, lines }),
+        };
+        if lines > MAX_GIFTS {
+            compact(path, &mut store.gifts.lock().unwrap())?;
+        }
+        Ok(store)
+    }
+
+    // Stores a gift under a new id, which it returns
+    pub fn record(&self, gift: Gift) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut gifts = self.gifts.lock().unwrap();
+        if gifts.held.len() == MAX_GIFTS {
+            gifts.held.pop_front();
+        }
+        gifts.held.push_back(SavedGift {
+            id: id.clone(),
+            gift,
+        });
+
+        // Written under the lock, as notification tokens are, to keep saves in order
+        if let Some(path) = &self.path {
+            let saved = if gifts.lines >= 2 * MAX_GIFTS {
+                compact(path, &mut gifts)
+            } else {
+                append(path, gifts.held.back().unwrap()).map(|()| gifts.lines += 1)
+            };
+            if let Err(err) = saved {
+                error!("Failed to save gifts to {}: {}", path.display(), err);
+            }
+        }
+        id
+    }
+}
+
+// Rewrites the file with only the gifts held
+fn compact(path: &Path, gifts: &mut Gifts) -> io::Result<()> {
+    let mut contents = String::new();
+    for saved in &gifts.held {
+        contents.push_str(&serde_json::to_string(saved).map_err(io::Error::other)?);
+        contents.push('\n');
+    }
+    std::fs::write(path, contents)?;
+    gifts.lines = gifts.held.len();
+    Ok(())
+}
+
+// Adds a gift to the end of the file in a single write
+fn append(path: &Path, saved: &SavedGift) -> io::Result<()> {
+    let mut line = serde_json::to_string(saved).map_err(io::Error::other)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(line.as_bytes())
+}
+
+// A username as typed, e.g. "@Alice ", as the hub knows it: "alice". None when it
+// can't be one.
+pub fn normalize_username(input: &str) -> Option<String> {
+    let username = input.trim().trim_start_matches('@').to_ascii_lowercase();
+    let valid = (1..=MAX_USERNAME_LEN).contains(&username.len())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then_some(username)
+}
+
+// Sends the GOAT typed into the Gift confirmation's input to the first address the
+// recipient, the username entered the screen before, has verified. The gift is
+// recorded once its transaction is built, when the post was signed by from_fid.
+pub async fn gift_transaction(
+    from_fid: Option<u64>,
+    address: Option<&str>,
+    recipient: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+    gifts: &GiftStore,
+) -> Result<TransactionResponse, AppError> {
+    let Some(username) = recipient.and_then(normalize_username) else {
+        return Err(AppError::BadRequest(
+            "Enter a Farcaster username to gift".to_string(),
+        ));
+    };
+    let sender = tx::parse_address(address.unwrap_or_default())?;
+    let value = tx::parse_amount(amount.unwrap_or_default())?;
+    if value == 0 {
+        return Err(AppError::BadRequest(
+            "Enter how much GOAT to gift".to_string(),
+        ));
+    }
+
+    let resolved = async {
+        let Some(to_fid) =
+            profiles::fetch_fid_by_username(client, &config.hub_url, &username).await?
+        else {
+            return Ok(None);
+        };
+        let address = profiles::fetch_verified_address(client, &config.hub_url, to_fid).await?;
+        Ok::<_, String>(Some((to_fid, address)))
+    };
+    let (to_fid, to_address) = match resolved.await {
+        Ok(Some((to_fid, Some(address)))) => (to_fid, address),
+        Ok(Some(_)) => {
+            return Err(AppError::BadRequest(format!(
+                "@{} hasn't verified a wallet yet",
+                username
+            )))
+        }
+        Ok(None) => {
+            return Err(AppError::BadRequest(format!(
+                "@{} isn't on Farcaster",
+                username
+            )))
+        }
+        Err(err) => {
+            error!("Failed to look up gift recipient @{}: {}", username, err);
+            return Err(AppError::InternalServerError);
+        }
+    };
+    let recipient = match tx::parse_address(&to_address) {
+        Ok(recipient) => recipient,
+        Err(_) => {
+            error!("Hub gave @{} an invalid address {:?}", username, to_address);
+            return Err(AppError::InternalServerError);
+        }
+    };
+    tx::check_balance(client, config, &sender, value).await?;
+
+    if let Some(from_fid) = from_fid {
+        gifts.record(Gift {
+            from_fid,
+            to_fid,
+            to_username: username,
+            to_address: format!("0x{}", hex::encode(recipient)),
+            amount: value.to_string(),
+            created_at: unix_now(),
+        });
+    }
+
+    // A plain transfer of the chain's own GOAT, so there's no contract to call
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([]),
+            to: format!("0x{}", hex::encode(recipient)),
+            data: "0x".to_string(),
+            value: value.to_string(),
+        },
+    })
+}
@@ -0,0 +1,71 @@
+use crate::config::Config;
+use actix_web::{web, HttpResponse};
+use log::warn;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// How long a successful hub ping counts towards readiness
+const HUB_CONTACT_TTL: Duration = Duration::from_secs(5);
+
+// Remembers when the hub last answered so readiness probes don't hammer it
+#[derive(Default)]
+pub struct Readiness {
+    last_hub_contact: Mutex<Option<Instant>>,
+}
+
+impl Readiness {
+    fn hub_recently_contacted(&self) -> bool {
+        let last = *self.last_hub_contact.lock().unwrap();
+        last.is_some_and(|at| at.elapsed() < HUB_CONTACT_TTL)
+    }
+
+    fn record_hub_contact(&self) {
+        *self.last_hub_contact.lock().unwrap() = Some(Instant::now());
+    }
+}
+
+pub async fn health() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+pub async fn ready(
+    config: Option<web::Data<Config>>,
+    readiness: web::Data<Readiness>,
+    client: web::Data<reqwest::Client>,
+) -> HttpResponse {
+    let Some(config) = config else {
+        return not_ready();
+    };
+
+    // Without validation the hub is never contacted, so it can't hold us back
+    if config.skip_validation || readiness.hub_recently_contacted() {
+        return is_ready();
+    }
+
+    match client
+        .get(format!("{}/v1/info", config.hub_url))
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => {
+            readiness.record_hub_contact();
+            is_ready()
+        }
+        Ok(response) => {
+            warn!("Hub ping returned status {}", response.status());
+            not_ready()
+        }
+        Err(err) => {
+            warn!("Hub ping failed: {}", err);
+            not_ready()
+        }
+    }
+}
+
+fn is_ready() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ready" }))
+}
+
+fn not_ready() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(serde_json::json!({ "status": "unavailable" }))
+}
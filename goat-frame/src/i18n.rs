@@ -0,0 +1,83 @@
+use crate::errors::I18nError;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+// One locale's translations, keyed by the English text in frames.toml
+pub type Catalog = HashMap<String, String>;
+
+// Translations of frame text, one TOML file per locale in LOCALES_PATH, e.g. es.toml:
+//   "Buy & Boost" = "Comprar y Boost"
+// Text without an entry is shown as written, so a catalog can be partial.
+#[derive(Debug, Default)]
+pub struct Translations {
+    catalogs: HashMap<String, Arc<Catalog>>,
+    // Used for viewers whose locale we don't know or don't have
+    default_locale: Option<String>,
+}
+
+impl Translations {
+    // A missing directory means no translations, so everyone sees frames.toml's text
+    pub fn load(dir: &Path, default_locale: Option<&str>) -> Result<Self, I18nError> {
+        let mut catalogs = HashMap::new();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Translations::new(catalogs, default_locale))
+            }
+            Err(err) => return Err(I18nError::Read(dir.display().to_string(), err)),
+        };
+        for entry in entries {
+            let path = entry
+                .map_err(|err| I18nError::Read(dir.display().to_string(), err))?
+                .path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+                continue;
+            }
+            let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|err| I18nError::Read(path.display().to_string(), err))?;
+            let catalog: Catalog = toml::from_str(&contents)
+                .map_err(|err| I18nError::Parse(path.display().to_string(), err))?;
+            catalogs.insert(normalize(locale), Arc::new(catalog));
+        }
+        Ok(Translations::new(catalogs, default_locale))
+    }
+
+    pub fn new(catalogs: HashMap<String, Arc<Catalog>>, default_locale: Option<&str>) -> Self {
+        Translations {
+            catalogs: catalogs
+                .into_iter()
+                .map(|(locale, catalog)| (normalize(&locale), catalog))
+                .collect(),
+            default_locale: default_locale.map(normalize),
+        }
+    }
+
+    // The catalog for a locale like "es-MX": the exact one, else its language's ("es"),
+    // else the default's. None shows frames.toml's text.
+    pub fn catalog(&self, locale: Option<&str>) -> Option<Arc<Catalog>> {
+        let lookup = |locale: &str| {
+            let locale = normalize(locale);
+            self.catalogs.get(&locale).or_else(|| {
+                let language = locale.split('-').next().unwrap_or_default();
+                self.catalogs.get(language)
+            })
+        };
+        locale
+            .and_then(lookup)
+            .or_else(|| self.default_locale.as_deref().and_then(lookup))
+            .cloned()
+    }
+
+    pub fn locales(&self) -> usize {
+        self.catalogs.len()
+    }
+}
+
+// Browsers say "es-MX", some platforms "es_MX"
+fn normalize(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Lets clients without signed messages mark retries of the same press
+pub const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+
+// Longest client-supplied key we'll store
+pub const MAX_KEY_LEN: usize = 128;
+
+// How long a retry waits for the first request with its key to be answered, and how
+// often it looks, before it's told to come back later
+const IN_FLIGHT_WAIT: Duration = Duration::from_secs(10);
+const IN_FLIGHT_POLL: Duration = Duration::from_millis(25);
+
+// What a retry is answered with
+#[derive(Clone, Debug, PartialEq)]
+pub enum Replay {
+    // A frame page or its JSON, in the format the key was made for
+    Page(String),
+    // Where a post_redirect button sent the user
+    Redirect(String),
+}
+
+enum Entry {
+    // Claimed by a request still being handled
+    InFlight,
+    Done(Replay),
+}
+
+struct Entries {
+    responses: HashMap<String, (Instant, Entry)>,
+    last_sweep: Instant,
+}
+
+// Remembers recent frame responses by idempotency key so a retried POST gets the
+// same answer without running the button again; a TTL of 0 turns it off. A key is
+// claimed before its request is handled, so a retry arriving meanwhile waits for
+// that answer rather than being handled, or refused as a replay, alongside it.
+pub struct IdempotencyCache {
+    ttl: Duration,
+    inner: Mutex<Entries>,
+}
+
+// How a request with an idempotency key goes on
+pub enum Reserved<'a> {
+    // It's the first, and answers with complete
+    Ours(Reservation<'a>),
+    // It's a retry, answered as the first was
+    Replay(Replay),
+    // The first is taking too long to answer
+    Busy,
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl_secs: u64) -> Self {
+        IdempotencyCache {
+            ttl: Duration::from_secs(ttl_secs),
+            inner: Mutex::new(Entries {
+                responses: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Replay> {
+        let inner = self.inner.lock().unwrap();
+        match inner.responses.get(key) {
+            Some((stored, Entry::Done(replay))) if stored.elapsed() < self.ttl => {
+                Some(replay.clone())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, replay: Replay) {
+        self.put(key, Entry::Done(replay));
+    }
+
+    // Claims key for this request, or waits for the request that already has to
+    // answer. Without a key, or with the cache off, nothing is claimed.
+    pub async fn reserve(&self, key: Option<String>) -> Reserved<'_> {
+        let Some(key) = key.filter(|_| !self.ttl.is_zero()) else {
+            return Reserved::Ours(Reservation {
+                cache: self,
+                key: None,
+            });
+        };
+        let started = Instant::now();
+        loop {
+            {
+                let inner = self.inner.lock().unwrap();
+                match inner.responses.get(&key) {
+                    Some((stored, entry)) if stored.elapsed() < self.ttl => match entry {
+                        Entry::Done(replay) => return Reserved::Replay(replay.clone()),
+                        Entry::InFlight if started.elapsed() >= IN_FLIGHT_WAIT => {
+                            return Reserved::Busy
+                        }
+                        Entry::InFlight => {}
+                    },
+                    _ => {
+                        drop(inner);
+                        self.put(key.clone(), Entry::InFlight);
+                        return Reserved::Ours(Reservation {
+                            cache: self,
+                            key: Some(key),
+                        });
+                    }
+                }
+            }
+            actix_web::rt::time::sleep(IN_FLIGHT_POLL).await;
+        }
+    }
+
+    fn put(&self, key: String, entry: Entry) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        // Drop expired responses once per TTL so the cache can't grow without bound
+        if now.duration_since(inner.last_sweep) >= self.ttl {
+            let ttl = self.ttl;
+            inner
+                .responses
+                .retain(|_, (stored, _)| now.duration_since(*stored) < ttl);
+            inner.last_sweep = now;
+        }
+        inner.responses.insert(key, (now, entry));
+    }
+
+    fn release(&self, key: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((_, Entry::InFlight)) = inner.responses.get(key) {
+            inner.responses.remove(key);
+        }
+    }
+}
+
+// A key claimed for the request being handled. Dropped without an answer, e.g. when
+// the request is refused, it's let go so a retry is handled afresh.
+pub struct Reservation<'a> {
+    cache: &'a IdempotencyCache,
+    key: Option<String>,
+}
+
+impl Reservation<'_> {
+    pub fn complete(mut self, replay: Replay) {
+        if let Some(key) = self.key.take() {
+            self.cache.insert(key, replay);
+        }
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.cache.release(&key);
+        }
+    }
+}
+
+// Post-transaction pages by transaction hash. They're kept far longer than retries
+// are, so a confirmation submitted twice, e.g. by a double-click once the wallet
+// closes, shows the same page again rather than being handled and recorded twice.
+pub struct ConfirmedTransactions(IdempotencyCache);
+
+impl ConfirmedTransactions {
+    pub fn new(ttl_secs: u64) -> Self {
+        ConfirmedTransactions(IdempotencyCache::new(ttl_secs))
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        match self.0.get(key) {
+            Some(Replay::Page(body)) => Some(body),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, body: String) {
+        self.0.insert(key, Replay::Page(body));
+    }
+}
@@ -0,0 +1,846 @@
+use crate::auction::{self, Auction};
+use crate::bridge::{Bridge, BridgeStatus};
+use crate::config::Config;
+use crate::countdown;
+use crate::errors::{AppError, ImageError};
+use crate::frame_logic::ANONYMOUS_NAME;
+use crate::gifts;
+use crate::images::cache::RenderCache;
+use crate::images::font::Fonts;
+use crate::images::layout::Layout;
+use crate::images::leaderboard::Page;
+use crate::images::theme::Themes;
+use crate::images::watermark::Watermark;
+use crate::leaderboard::{Leaderboard, Rows};
+use crate::metrics::Metrics;
+use crate::moxie::{self, MoxiePrice};
+use crate::price::{self, PriceHistory, PriceRange, Prices};
+use crate::profiles::{fetch_fid_by_username, Profile, ProfileCache};
+use crate::receipts::Receipts;
+use crate::rewards;
+use crate::tx::{self, TxStatus};
+use actix_web::http::header::{self, Accept, Header, Quality};
+use actix_web::{web, HttpRequest, HttpResponse};
+use font8x8::legacy::BASIC_LEGACY;
+use image::imageops::FilterType;
+use image::{ImageFormat, Rgb, RgbImage, RgbaImage};
+use log::{error, warn};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub mod cache;
+pub mod chart;
+pub mod emoji;
+pub mod font;
+pub mod gif;
+pub mod layout;
+pub mod leaderboard;
+pub mod qr;
+pub mod svg;
+pub mod theme;
+pub mod watermark;
+pub mod webp;
+
+// Query values end up drawn in the image, so keep them to a line or a tx hash
+pub const MAX_PARAM_BYTES: usize = 80;
+const MAX_PARAMS: usize = 16;
+
+// Live data changes, but not so fast that every view needs a fresh render
+const CACHE_CONTROL: &str = "public, max-age=60";
+
+// SVG templates from TEMPLATES_PATH, one per file: price.svg is served as
+// /images/price.png, and as an animated /images/price.gif when its <svg> sets
+// frames. /images/price.png comes as a WebP to clients that accept one unless
+// CDN_BASE_URL is set, and /images/price.webp does to any client. "{{name}}" in a
+// template is filled from the ?name= query parameter; with ?fid=, {{username}},
+// {{display_name}}, {{name}} and {{balance}} default to the fid's profile and
+// wallet. {{deposit_address}} and {{deposit_uri}} are DEPOSIT_ADDRESS's. Given
+// ?step= and ?steps=, {{progress}} is "step/steps", and given ?ends_at=,
+// {{countdown}} is the time left until then, e.g. "Ends in 2h 13m". Given ?tx=,
+// {{tx_short}} is the hash cut short and {{tx_status}} and {{tx_status_color}} where
+// its receipt says it is, and {{bridge_status}} and {{bridge_status_color}} where a
+// bridge deposit sent in it is. {{theme_<color>}} is the color of the ?theme=dark or
+// ?theme=light asked for, else the default theme's. A <name>.json layout is served
+// the same way, drawn from rows and columns of text and images rather than SVG.
+#[derive(Debug, Default)]
+pub struct ImageTemplates {
+    templates: HashMap<String, String>,
+    layouts: HashMap<String, Layout>,
+    assets: AssetImages,
+    fonts: Fonts,
+    themes: Themes,
+    watermark: Watermark,
+}
+
+impl ImageTemplates {
+    // A missing directory means no templates, so /images answers 404. Templates draw
+    // <image> hrefs from assets_dir.
+    pub fn load(dir: &Path, assets_dir: &Path) -> Result<Self, ImageError> {
+        let mut loaded = ImageTemplates::new(HashMap::new(), assets_dir);
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(loaded),
+            Err(err) => return Err(ImageError::Read(dir.display().to_string(), err)),
+        };
+        for entry in entries {
+            let path = entry
+                .map_err(|err| ImageError::Read(dir.display().to_string(), err))?
+                .path();
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            if extension != Some("svg") && extension != Some("json") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|err| ImageError::Read(path.display().to_string(), err))?;
+            let invalid = |err: ImageError| ImageError::Template(name.to_string(), err.to_string());
+            if loaded.contains(name) {
+                return Err(ImageError::Template(
+                    name.to_string(),
+                    "there's both a .svg and a .json of it".to_string(),
+                ));
+            }
+            if extension == Some("json") {
+                let layout = Layout::parse(&contents).map_err(invalid)?;
+                loaded.layouts.insert(name.to_string(), layout);
+            } else {
+                loaded.templates.insert(name.to_string(), contents);
+            }
+            // Check the template draws before anyone asks for it
+            let mut params = HashMap::new();
+            loaded.themes.fill_params(&mut params);
+            let svg = loaded.svg(name, &params).map_err(invalid)?;
+            svg::rasterize(&svg, &loaded.assets, &loaded.fonts, &LiveData::default())
+                .map_err(invalid)?;
+        }
+        Ok(loaded)
+    }
+
+    pub fn new(templates: HashMap<String, String>, assets_dir: &Path) -> Self {
+        ImageTemplates {
+            templates,
+            layouts: HashMap::new(),
+            assets: AssetImages::new(assets_dir),
+            fonts: Fonts::default(),
+            themes: Themes::default(),
+            watermark: Watermark::default(),
+        }
+    }
+
+    // Draws text in these fonts rather than only the built-in one
+    pub fn with_fonts(mut self, fonts: Fonts) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
+    // Colors templates in these themes rather than the built-in ones
+    pub fn with_themes(mut self, themes: Themes) -> Self {
+        self.themes = themes;
+        self
+    }
+
+    // Stamps this branding onto every image drawn
+    pub fn with_watermark(mut self, watermark: Watermark) -> Self {
+        self.watermark = watermark;
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.templates.contains_key(name) || self.layouts.contains_key(name)
+    }
+
+    pub fn render(
+        &self,
+        name: &str,
+        params: &HashMap<String, String>,
+        live: &LiveData,
+    ) -> Result<Vec<u8>, ImageError> {
+        encode_png(&self.draw(name, params, live)?)
+    }
+
+    // The same image as render, as a lossless WebP
+    pub fn render_webp(
+        &self,
+        name: &str,
+        params: &HashMap<String, String>,
+        live: &LiveData,
+    ) -> Result<Vec<u8>, ImageError> {
+        Ok(webp::encode_webp(&self.draw(name, params, live)?))
+    }
+
+    fn draw(
+        &self,
+        name: &str,
+        params: &HashMap<String, String>,
+        live: &LiveData,
+    ) -> Result<RgbImage, ImageError> {
+        let mut params = params.clone();
+        self.themes.fill_params(&mut params);
+        let mut img = svg::rasterize(&self.svg(name, &params)?, &self.assets, &self.fonts, live)?;
+        self.stamp(&mut img, &params);
+        Ok(img)
+    }
+
+    // The watermark in the text color of the theme params were filled for
+    fn stamp(&self, img: &mut RgbImage, params: &HashMap<String, String>) {
+        if self.watermark.is_empty() {
+            return;
+        }
+        let color = params
+            .get(&format!("{}text", theme::COLOR_PREFIX))
+            .and_then(|color| svg::color_from(color))
+            .unwrap_or(Rgb([255, 255, 255]));
+        self.watermark.stamp(img, &self.fonts, color);
+    }
+
+    // The template with params filled in, and a layout laid out, as SVG
+    fn svg(&self, name: &str, params: &HashMap<String, String>) -> Result<String, ImageError> {
+        if let Some(layout) = self.layouts.get(name) {
+            return Ok(layout.to_svg(params, &self.fonts));
+        }
+        self.templates
+            .get(name)
+            .map(|template| fill(template, params))
+            .ok_or_else(|| ImageError::UnknownTemplate(name.to_string()))
+    }
+
+    // Every frame of a template as a looping GIF; a still template makes a one-frame GIF
+    pub fn render_gif(
+        &self,
+        name: &str,
+        params: &HashMap<String, String>,
+        live: &LiveData,
+    ) -> Result<Vec<u8>, ImageError> {
+        let mut params = params.clone();
+        self.themes.fill_params(&mut params);
+        let svg = self.svg(name, &params)?;
+        let animation = svg::animation(&svg)?;
+        let frames = (0..animation.frames)
+            .map(|frame| {
+                let mut img = svg::rasterize_frame(&svg, &self.assets, &self.fonts, live, frame)?;
+                self.stamp(&mut img, &params);
+                Ok(img)
+            })
+            .collect::<Result<Vec<_>, ImageError>>()?;
+        Ok(gif::encode_gif(&frames, animation.delay_ms))
+    }
+
+    // The placeholders a template fills, None for a template that doesn't exist
+    pub fn placeholders(&self, name: &str) -> Option<BTreeSet<String>> {
+        if let Some(layout) = self.layouts.get(name) {
+            return Some(layout.placeholders());
+        }
+        self.templates
+            .get(name)
+            .map(|template| placeholders(template))
+    }
+
+    pub fn chart_ranges(&self, name: &str) -> Vec<PriceRange> {
+        self.templates
+            .get(name)
+            .map_or_else(Vec::new, |template| svg::chart_ranges(template))
+    }
+
+    // The rows of the leaderboard a template draws with these params, None without one
+    pub fn leaderboard_rows(&self, name: &str, params: &HashMap<String, String>) -> Option<Page> {
+        let template = self.templates.get(name)?;
+        svg::leaderboard_rows(&fill(template, params))
+            .ok()
+            .flatten()
+    }
+
+    // Whether a template draws the viewer's pfp, which has to be fetched first
+    pub fn draws_avatar(&self, name: &str) -> bool {
+        if let Some(layout) = self.layouts.get(name) {
+            return layout.draws_avatar();
+        }
+        self.templates
+            .get(name)
+            .is_some_and(|template| template.contains(&format!("href=\"{}\"", svg::AVATAR_HREF)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len() + self.layouts.len()
+    }
+}
+
+// What a template draws besides its assets and parameters, fetched for each render:
+// the viewer's pfp, the token's price history, and the leaderboard with the pfps of
+// the fids on the page shown
+#[derive(Default)]
+pub struct LiveData<'a> {
+    pub avatar: Option<&'a RgbImage>,
+    pub prices: HashMap<PriceRange, Prices>,
+    pub leaderboard: Option<Rows>,
+    pub leaderboard_avatars: HashMap<u64, &'a RgbImage>,
+}
+
+// The assets templates draw on, decoded once since the same few are drawn every time
+#[derive(Debug, Default)]
+pub struct AssetImages {
+    dir: PathBuf,
+    decoded: Mutex<HashMap<String, Arc<RgbImage>>>,
+}
+
+impl AssetImages {
+    pub fn new(dir: &Path) -> Self {
+        AssetImages {
+            dir: dir.to_path_buf(),
+            decoded: Mutex::default(),
+        }
+    }
+
+    // An href can come from a query parameter, so it may only name a file under assets/
+    pub fn get(&self, name: &str) -> Result<Arc<RgbImage>, ImageError> {
+        if name.is_empty() || name.starts_with('/') || name.split('/').any(|part| part == "..") {
+            return Err(ImageError::Svg(format!(
+                "image {:?} must be a file under assets/",
+                name
+            )));
+        }
+        if let Some(image) = self.decoded.lock().unwrap().get(name) {
+            return Ok(image.clone());
+        }
+        // Decode outside the lock; a concurrent decode of the same asset is harmless
+        let image = image::open(self.dir.join(name))
+            .map_err(|err| ImageError::Asset(name.to_string(), err))?
+            .to_rgb8();
+        let image = Arc::new(image);
+        self.decoded
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), image.clone());
+        Ok(image)
+    }
+}
+
+// Swaps each "{{name}}" for its escaped value, and placeholders without one for nothing.
+// Double braces leave the single ones in CSS and the like alone.
+pub fn fill(template: &str, params: &HashMap<String, String>) -> String {
+    fill_with(template, params, escape_xml)
+}
+
+// As fill, but values go in as they are, for layouts to measure and escape whole
+fn substitute(template: &str, params: &HashMap<String, String>) -> String {
+    fill_with(template, params, str::to_string)
+}
+
+fn fill_with(
+    template: &str,
+    params: &HashMap<String, String>,
+    escape: fn(&str) -> String,
+) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some((before, name, after)) = next_placeholder(rest) {
+        filled.push_str(before);
+        if let Some(value) = params.get(name) {
+            filled.push_str(&escape(value));
+        }
+        rest = after;
+    }
+    filled.push_str(rest);
+    filled
+}
+
+// The names of a template's placeholders, each once
+pub fn placeholders(template: &str) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut rest = template;
+    while let Some((_, name, after)) = next_placeholder(rest) {
+        names.insert(name.to_string());
+        rest = after;
+    }
+    names
+}
+
+// The text before the first "{{name}}", its name, and the text after it
+fn next_placeholder(text: &str) -> Option<(&str, &str, &str)> {
+    let mut from = 0;
+    while let Some(start) = text[from..].find("{{").map(|i| from + i) {
+        let after = &text[start + 2..];
+        let name_len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(after.len());
+        if name_len > 0 && after[name_len..].starts_with("}}") {
+            return Some((&text[..start], &after[..name_len], &after[name_len + 2..]));
+        }
+        from = start + 1;
+    }
+    None
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn encode_png(img: &RgbImage) -> Result<Vec<u8>, ImageError> {
+    let mut bytes = Vec::new();
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(ImageError::Encode)?;
+    Ok(bytes)
+}
+
+// The built-in font's glyphs are this many pixels square, before scaling
+pub const BITMAP_GLYPH_SIZE: u32 = 8;
+
+// Draws text in the built-in bitmap font with its top left corner at (x, y), clipped
+// to the image. The bitmap font only covers ASCII, so anything else is drawn as "?".
+pub fn draw_text(img: &mut RgbImage, text: &str, x: i64, y: i64, scale: u32, color: Rgb<u8>) {
+    let scale = scale.max(1) as i64;
+    for (i, c) in text.chars().enumerate() {
+        let c = if c.is_ascii() && !c.is_ascii_control() {
+            c
+        } else {
+            '?'
+        };
+        let glyph = BASIC_LEGACY[c as usize];
+        let glyph_x = x + i as i64 * BITMAP_GLYPH_SIZE as i64 * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..8 {
+                if bits & (1 << col) == 0 {
+                    continue;
+                }
+                let left = glyph_x + col * scale;
+                let top = y + row as i64 * scale;
+                fill_rect(img, left, top, scale, scale, color);
+            }
+        }
+    }
+}
+
+// Fills a rectangle, clipped to the image
+pub fn fill_rect(img: &mut RgbImage, x: i64, y: i64, width: i64, height: i64, color: Rgb<u8>) {
+    let clip = |start: i64, len: i64, max: u32| {
+        (
+            start.clamp(0, max as i64) as u32,
+            (start + len).clamp(0, max as i64) as u32,
+        )
+    };
+    let (left, right) = clip(x, width, img.width());
+    let (top, bottom) = clip(y, height, img.height());
+    for py in top..bottom {
+        for px in left..right {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
+// Draws an image with its top left corner at (x, y), scaled to width by height and
+// blended over what's already drawn by its alpha, clipped to the image
+pub fn blend_image(img: &mut RgbImage, image: &RgbaImage, x: i64, y: i64, width: u32, height: u32) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let scaled = image::imageops::resize(image, width, height, FilterType::Triangle);
+    for (px, py, pixel) in scaled.enumerate_pixels() {
+        let (x, y) = (x + px as i64, y + py as i64);
+        if x < 0 || y < 0 || x >= img.width() as i64 || y >= img.height() as i64 {
+            continue;
+        }
+        let alpha = pixel[3] as u32;
+        let under = img.get_pixel_mut(x as u32, y as u32);
+        let blend =
+            |i: usize| ((pixel[i] as u32 * alpha + under[i] as u32 * (255 - alpha)) / 255) as u8;
+        *under = Rgb([blend(0), blend(1), blend(2)]);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn serve_template_image(
+    file: web::Path<String>,
+    query: web::Query<HashMap<String, String>>,
+    templates: web::Data<ImageTemplates>,
+    cache: web::Data<RenderCache>,
+    metrics: web::Data<Metrics>,
+    profiles: web::Data<ProfileCache>,
+    prices: web::Data<PriceHistory>,
+    leaderboard: web::Data<Leaderboard>,
+    moxie: web::Data<MoxiePrice>,
+    receipts: web::Data<Receipts>,
+    auction: web::Data<Auction>,
+    bridge: web::Data<Bridge>,
+    client: web::Data<reqwest::Client>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    // <template>.png and .webp draw the first frame, <template>.gif all of them. Behind
+    // a CDN, which keys its copies on the URL alone, .png is always a PNG.
+    let negotiate = config.cdn_base_url.is_none();
+    let (template, encoding) = match file.rsplit_once('.') {
+        Some((template, "png")) if negotiate => {
+            (template.to_string(), ImageEncoding::negotiate(&req))
+        }
+        Some((template, "png")) => (template.to_string(), ImageEncoding::Png),
+        Some((template, "webp")) => (template.to_string(), ImageEncoding::Webp),
+        Some((template, "gif")) => (template.to_string(), ImageEncoding::Gif),
+        _ => return Err(AppError::NotFound(format!("Unknown image: {}", file))),
+    };
+    // Caches between here and the client keep a copy per Accept, when it mattered
+    let vary = negotiate && file.ends_with(".png");
+    let mut params = query.into_inner();
+    if params.len() > MAX_PARAMS || params.values().any(|value| value.len() > MAX_PARAM_BYTES) {
+        return Err(AppError::BadRequest(format!(
+            "At most {} parameters of up to {} bytes each",
+            MAX_PARAMS, MAX_PARAM_BYTES
+        )));
+    }
+    if !templates.contains(&template) {
+        return Err(AppError::NotFound(format!(
+            "Unknown image template: {}",
+            template
+        )));
+    }
+
+    // Keyed by the query as asked, so a hit skips the profile and price lookups too,
+    // and by the encoding served rather than the extension asked for
+    let key = RenderCache::key(&format!("{}.{}", template, encoding.extension()), &params);
+    if let Some(image) = cache.get(&key) {
+        metrics.record_image_cache(true);
+        return Ok(image_response(image, encoding, vary));
+    }
+    metrics.record_image_cache(false);
+
+    // Given an fid, templates can greet the viewer by name and draw their pfp
+    let fid = params.get("fid").and_then(|fid| fid.parse().ok());
+    let cached = match fid {
+        Some(fid) => {
+            let avatar = templates.draws_avatar(&template) && !params.contains_key("recipient");
+            Some(profiles.get(&client, &config.hub_url, fid, avatar).await)
+        }
+        None => None,
+    };
+    let profile = cached.as_ref().map(|cached| &cached.profile);
+    let names = [
+        ("username", profile.and_then(|p| p.username.as_deref())),
+        (
+            "display_name",
+            profile.and_then(|p| p.display_name.as_deref()),
+        ),
+        (
+            "name",
+            Some(profile.and_then(Profile::name).unwrap_or(ANONYMOUS_NAME)),
+        ),
+    ];
+    for (name, value) in names {
+        if let Some(value) = value {
+            params
+                .entry(name.to_string())
+                .or_insert_with(|| value.to_string());
+        }
+    }
+
+    // Given recipient, a username, a template draws whom a gift goes to: avatar is their
+    // pfp rather than the viewer's, and {{recipient_name}} their name
+    let recipient = params
+        .get("recipient")
+        .map(|username| gifts::normalize_username(username));
+    let recipient = match recipient {
+        Some(Some(username)) => {
+            match fetch_fid_by_username(&client, &config.hub_url, &username).await {
+                Ok(Some(fid)) => {
+                    let avatar = templates.draws_avatar(&template);
+                    Some(profiles.get(&client, &config.hub_url, fid, avatar).await)
+                }
+                Ok(None) => None,
+                Err(err) => {
+                    warn!("Failed to look up recipient @{}: {}", username, err);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    if let Some(name) = recipient.as_ref().and_then(|cached| cached.profile.name()) {
+        params
+            .entry("recipient_name".to_string())
+            .or_insert_with(|| name.to_string());
+    }
+
+    // The fid's GOAT balance, when the template shows it
+    let wants_balance = templates
+        .placeholders(&template)
+        .is_some_and(|names| names.contains("balance"));
+    let address = profile.and_then(|p| p.address.as_deref());
+    if let (true, Some(rpc_url), Some(address)) = (wants_balance, &config.rpc_url, address) {
+        let balance = match tx::parse_address(address) {
+            Ok(address) => tx::fetch_balance(&client, rpc_url, &address).await,
+            Err(err) => Err(err.to_string()),
+        };
+        match balance {
+            Ok(balance) => {
+                params
+                    .entry("balance".to_string())
+                    .or_insert_with(|| tx::format_amount(balance));
+            }
+            Err(err) => warn!("Failed to fetch balance of {}: {}", address, err),
+        }
+    }
+
+    // The fid's unclaimed rewards, when the template shows them. Only ever from the
+    // contract, so no one can draw rewards that aren't there to claim.
+    params.remove("claimable");
+    let wants_claimable = templates
+        .placeholders(&template)
+        .is_some_and(|names| names.contains("claimable"));
+    let rewards_contract = config.rewards_contract.as_deref();
+    if let (true, Some(rpc_url), Some(contract), Some(address)) =
+        (wants_claimable, &config.rpc_url, rewards_contract, address)
+    {
+        let claimable = match (tx::parse_address(contract), tx::parse_address(address)) {
+            (Ok(contract), Ok(account)) => {
+                rewards::fetch_claimable(&client, rpc_url, &contract, &account).await
+            }
+            (Err(err), _) | (_, Err(err)) => Err(err.to_string()),
+        };
+        match claimable {
+            Ok(claimable) => {
+                params.insert(
+                    "claimable".to_string(),
+                    format!("{} GOAT", tx::format_amount(claimable)),
+                );
+            }
+            Err(err) => warn!("Failed to fetch claimable rewards of {}: {}", address, err),
+        }
+    }
+
+    // The Moxie fan token's price, when the template shows it
+    let wants_moxie_price = templates
+        .placeholders(&template)
+        .is_some_and(|names| names.contains("moxie_price"));
+    if wants_moxie_price {
+        let url = config.moxie_price_url.as_deref();
+        if let Some(price) = moxie.get(&client, url).await {
+            params
+                .entry("moxie_price".to_string())
+                .or_insert_with(|| moxie::format_moxie_price(price));
+        }
+    }
+
+    // The Top-up address, as text and as an EIP-681 URI wallets open from a QR code.
+    // Only ever from config, so no one can serve a QR of their own address from here.
+    params.remove("deposit_address");
+    params.remove("deposit_uri");
+    if let Some(address) = &config.deposit_address {
+        let uri = format!("ethereum:{}@{}", address, config.chain_id);
+        params.insert("deposit_address".to_string(), address.clone());
+        params.insert("deposit_uri".to_string(), uri);
+    }
+
+    // A flow's step as "2/3", next to the <progress> bar drawn from step and steps
+    let progress = match (params.get("step"), params.get("steps")) {
+        (Some(step), Some(steps)) => Some(format!("{}/{}", step, steps)),
+        _ => None,
+    };
+    if let Some(progress) = progress {
+        params.entry("progress".to_string()).or_insert(progress);
+    }
+
+    // AUCTION_CONTRACT's highest bid, the least the next can be, and when it ends for
+    // the countdown, when the template draws them. Only ever from the contract, so no
+    // one can draw a bid that was never made.
+    let wants_auction = templates.placeholders(&template).is_some_and(|names| {
+        ["bid", "min_bid", "countdown"]
+            .iter()
+            .any(|name| names.contains(*name))
+    });
+    if wants_auction && config.auction_contract.is_some() {
+        for name in ["bid", "min_bid", "ends_at", "countdown"] {
+            params.remove(name);
+        }
+        if let Some(state) = auction.get(&client, &config).await {
+            let bid = match state.amount {
+                0 => "No bids yet".to_string(),
+                amount => format!("{} GOAT", tx::format_amount(amount)),
+            };
+            let min_bid = auction::min_bid(state.amount, config.auction_min_increment_bps);
+            params.insert("bid".to_string(), bid);
+            params.insert(
+                "min_bid".to_string(),
+                format!("{} GOAT", tx::format_amount(min_bid)),
+            );
+            params.insert("ends_at".to_string(), state.ends_at.to_string());
+        }
+    }
+
+    // How long the auction has left as of now, so cached copies are at most a
+    // countdown::STEP_SECS behind
+    let ends_at = params
+        .get("ends_at")
+        .and_then(|ends_at| ends_at.parse().ok());
+    if let Some(ends_at) = ends_at {
+        let left = countdown::format_countdown(ends_at, countdown::unix_now());
+        params.entry("countdown".to_string()).or_insert(left);
+    }
+
+    // The sent transaction's hash cut short, and its status as its receipt has it.
+    // Only ever from the receipt, so no one can draw a failed transaction as confirmed,
+    // and not cached while pending so the next view can catch the outcome.
+    params.remove("tx_status");
+    params.remove("tx_status_color");
+    let mut pending = false;
+    if let Some(hash) = params.get("tx").cloned() {
+        let hash = tx::parse_transaction_id(&hash)?;
+        params.insert("tx_short".to_string(), tx::short_hash(&hash));
+        let wants_status = templates
+            .placeholders(&template)
+            .is_some_and(|names| names.contains("tx_status") || names.contains("tx_status_color"));
+        if wants_status {
+            let status = receipts
+                .status(&client, config.rpc_url.as_deref(), &hash)
+                .await;
+            pending = status == TxStatus::Pending;
+            params.insert("tx_status".to_string(), status.label().to_string());
+            params.insert("tx_status_color".to_string(), status.color().to_string());
+        }
+    }
+
+    // Where a bridge deposit sent as tx is, on to its arrival on the destination chain,
+    // not cached until it gets there for the same reason
+    params.remove("bridge_status");
+    params.remove("bridge_status_color");
+    let wants_bridge_status = templates.placeholders(&template).is_some_and(|names| {
+        names.contains("bridge_status") || names.contains("bridge_status_color")
+    });
+    if let (true, Some(hash)) = (wants_bridge_status, params.get("tx")) {
+        let hash = tx::parse_transaction_id(hash)?;
+        let status = bridge.status(&client, &config, &receipts, &hash).await;
+        pending |= !status.is_settled() && status != BridgeStatus::Sent;
+        params.insert("bridge_status".to_string(), status.label().to_string());
+        params.insert(
+            "bridge_status_color".to_string(),
+            status.color().to_string(),
+        );
+    }
+
+    // Charts draw the price history, and {price} and {change} default to the first
+    // chart's latest price and its move over the range
+    let mut live = LiveData {
+        avatar: if params.contains_key("recipient") {
+            recipient.as_ref().and_then(|cached| cached.avatar.as_ref())
+        } else {
+            cached.as_ref().and_then(|cached| cached.avatar.as_ref())
+        },
+        ..LiveData::default()
+    };
+    for range in templates.chart_ranges(&template) {
+        let url = config.price_url.as_deref();
+        let Some(history) = prices.get(&client, url, range).await else {
+            continue;
+        };
+        if let Some(latest) = history.last() {
+            params
+                .entry("price".to_string())
+                .or_insert_with(|| price::format_price(*latest));
+        }
+        if let Some(change) = price::change(&history) {
+            params.entry("change".to_string()).or_insert(change);
+        }
+        live.prices.insert(range, history);
+    }
+
+    // The leaderboard, and the pfps of the fids on the page the template shows. Each
+    // is cached for PROFILE_TTL_SECS, so only the first view of a page waits on them.
+    let mut pfps = Vec::new();
+    if let Some(page) = templates.leaderboard_rows(&template, &params) {
+        let url = config.leaderboard_url.as_deref();
+        live.leaderboard = leaderboard.get(&client, url).await;
+        let rows = live.leaderboard.as_deref().map_or(&[][..], Vec::as_slice);
+        let shown = rows.get(page.range().start..).unwrap_or_default();
+        for fid in shown.iter().take(page.rows).filter_map(|row| row.fid) {
+            pfps.push((fid, profiles.get(&client, &config.hub_url, fid, true).await));
+        }
+    }
+    live.leaderboard_avatars = pfps
+        .iter()
+        .filter_map(|(fid, cached)| Some((*fid, cached.avatar.as_ref()?)))
+        .collect();
+
+    let rendered = match encoding {
+        ImageEncoding::Png => templates.render(&template, &params, &live),
+        ImageEncoding::Webp => templates.render_webp(&template, &params, &live),
+        ImageEncoding::Gif => templates.render_gif(&template, &params, &live),
+    };
+    match rendered {
+        Ok(image) => {
+            let image = web::Bytes::from(image);
+            if !pending {
+                cache.insert(key, image.clone());
+            }
+            Ok(image_response(image, encoding, vary))
+        }
+        Err(ImageError::UnknownTemplate(name)) => Err(AppError::NotFound(format!(
+            "Unknown image template: {}",
+            name
+        ))),
+        // Templates are checked at startup, so these come from the parameters
+        Err(err @ (ImageError::Svg(_) | ImageError::Asset(..))) => {
+            Err(AppError::BadRequest(err.to_string()))
+        }
+        Err(err) => {
+            error!("Failed to render image template {}: {}", template, err);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+fn image_response(image: web::Bytes, encoding: ImageEncoding, vary: bool) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(encoding.content_type())
+        .insert_header((header::CACHE_CONTROL, CACHE_CONTROL));
+    if vary {
+        response.insert_header((header::VARY, "Accept"));
+    }
+    response.body(image)
+}
+
+// How an /images response is encoded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageEncoding {
+    Png,
+    Webp,
+    Gif,
+}
+
+impl ImageEncoding {
+    // WebP when the caller accepts it at any quality, as browsers say they do; PNG,
+    // which every client draws, otherwise. Wildcards don't count, since clients
+    // sending */* may well not decode WebP.
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        let webp = Accept::parse(req).is_ok_and(|accept| {
+            accept
+                .iter()
+                .any(|item| item.item.essence_str() == "image/webp" && item.quality > Quality::ZERO)
+        });
+        if webp {
+            ImageEncoding::Webp
+        } else {
+            ImageEncoding::Png
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageEncoding::Png => "png",
+            ImageEncoding::Webp => "webp",
+            ImageEncoding::Gif => "gif",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ImageEncoding::Png => "image/png",
+            ImageEncoding::Webp => "image/webp",
+            ImageEncoding::Gif => "image/gif",
+        }
+    }
+}
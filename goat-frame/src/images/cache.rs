@@ -0,0 +1,91 @@
+use actix_web::web::Bytes;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Rendered PNGs by template and parameters, so the same image asked for again (every
+// viewer of a leaderboard, a client refetching) skips drawing and encoding it. Holds
+// at most capacity images, dropping the least recently used; 0 caches nothing.
+pub struct RenderCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    // Bumped on every lookup, so the lowest last use is the least recent
+    clock: u64,
+    images: HashMap<String, Entry>,
+}
+
+struct Entry {
+    rendered: Instant,
+    last_used: u64,
+    png: Bytes,
+}
+
+impl RenderCache {
+    pub fn new(capacity: usize, ttl_secs: u64) -> Self {
+        RenderCache {
+            capacity,
+            ttl: Duration::from_secs(ttl_secs),
+            entries: Mutex::default(),
+        }
+    }
+
+    // What decides an image: its template and every parameter it's filled with.
+    // Parameters are sorted, since the query they came from can list them any order.
+    pub fn key(template: &str, params: &HashMap<String, String>) -> String {
+        let mut params: Vec<_> = params.iter().collect();
+        params.sort();
+        let mut key = url::form_urlencoded::Serializer::new(String::new());
+        for (name, value) in params {
+            key.append_pair(name, value);
+        }
+        format!("{}?{}", template, key.finish())
+    }
+
+    pub fn get(&self, key: &str) -> Option<Bytes> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+        let entry = entries.images.get_mut(key)?;
+        if entry.rendered.elapsed() >= self.ttl {
+            entries.images.remove(key);
+            return None;
+        }
+        entry.last_used = clock;
+        Some(entry.png.clone())
+    }
+
+    pub fn insert(&self, key: String, png: Bytes) {
+        if self.capacity == 0 || self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        if entries.images.len() >= self.capacity && !entries.images.contains_key(&key) {
+            let ttl = self.ttl;
+            entries
+                .images
+                .retain(|_, entry| entry.rendered.elapsed() < ttl);
+        }
+        if entries.images.len() >= self.capacity && !entries.images.contains_key(&key) {
+            if let Some(oldest) = entries
+                .images
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            {
+                entries.images.remove(&oldest);
+            }
+        }
+        let entry = Entry {
+            rendered: Instant::now(),
+            last_used: entries.clock,
+            png,
+        };
+        entries.images.insert(key, entry);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::images::fill_rect;
+use image::{Rgb, RgbImage};
+
+// Draws prices as a line across the box, oldest at the left, scaled between their
+// low and high so the movement fills it. The area under the line is filled when
+// fill is set.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_line_chart(
+    img: &mut RgbImage,
+    prices: &[f64],
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+    stroke: Rgb<u8>,
+    stroke_width: i64,
+    fill: Option<Rgb<u8>>,
+) {
+    if prices.is_empty() || width <= 0 || height <= 0 {
+        return;
+    }
+    let low = prices.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = prices.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    // Keep the stroke inside the box at the high and low
+    let stroke_width = stroke_width.clamp(1, height);
+    let span = (height - stroke_width) as f64;
+    let line_y = |column: i64| {
+        let at = column as f64 / (width - 1).max(1) as f64 * (prices.len() - 1) as f64;
+        let (i, t) = (at.floor() as usize, at.fract());
+        let price = match prices.get(i + 1) {
+            Some(next) => prices[i] + (next - prices[i]) * t,
+            None => prices[i],
+        };
+        // A flat price sits in the middle rather than along the bottom
+        let share = if high > low {
+            (price - low) / (high - low)
+        } else {
+            0.5
+        };
+        y + ((1.0 - share) * span).round() as i64
+    };
+
+    let mut previous = line_y(0);
+    for column in 0..width {
+        let top = line_y(column);
+        if let Some(fill) = fill {
+            fill_rect(img, x + column, top, 1, y + height - top, fill);
+        }
+        // Join to the previous column so steep moves stay one line
+        let (from, to) = (previous.min(top), previous.max(top));
+        fill_rect(
+            img,
+            x + column - stroke_width / 2,
+            from,
+            stroke_width,
+            to - from + stroke_width,
+            stroke,
+        );
+        previous = top;
+    }
+}
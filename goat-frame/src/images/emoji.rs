@@ -0,0 +1,120 @@
+use crate::errors::ImageError;
+use crate::images::blend_image;
+use image::{RgbImage, RgbaImage};
+use log::warn;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// Emoji presentation selectors aren't in the atlas's file names, so they're left out
+// of the sequences looked up too
+pub const EMOJI_PRESENTATION: char = '\u{fe0f}';
+
+// Color emoji from EMOJI_PATH, a directory of PNGs named as in Noto Emoji's png/128:
+// emoji_u1f525.png for 🔥, emoji_u1f469_200d_1f4bb.png for the sequence 👩‍💻.
+// Images are decoded the first time they're drawn, since a full set is thousands.
+#[derive(Debug, Default)]
+pub struct EmojiAtlas {
+    dir: PathBuf,
+    // File names by the sequence they draw
+    files: HashMap<Vec<char>, String>,
+    // The most characters a sequence in the atlas has
+    longest: usize,
+    decoded: Mutex<HashMap<Vec<char>, Arc<RgbaImage>>>,
+}
+
+impl EmojiAtlas {
+    pub fn load(dir: &Path) -> Result<Self, ImageError> {
+        let read_err = |err| ImageError::Read(dir.display().to_string(), err);
+        let mut atlas = EmojiAtlas {
+            dir: dir.to_path_buf(),
+            ..EmojiAtlas::default()
+        };
+        for entry in std::fs::read_dir(dir).map_err(read_err)? {
+            let name = entry.map_err(read_err)?.file_name();
+            let Some(name) = name.to_str() else { continue };
+            let Some(sequence) = sequence_of(name) else {
+                continue;
+            };
+            atlas.longest = atlas.longest.max(sequence.len());
+            atlas.files.insert(sequence, name.to_string());
+        }
+        Ok(atlas)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    // The longest sequence text starts with that the atlas draws, as how many of
+    // text's characters it takes up (selectors included) and its image. An emoji
+    // that also has a text form, like © or ☺, is only drawn from the atlas when
+    // text_form is false or a selector asks for it; fonts draw it otherwise.
+    pub fn find(&self, text: &[char], text_form: bool) -> Option<(usize, Arc<RgbaImage>)> {
+        let mut sequence = Vec::with_capacity(self.longest);
+        let mut found = None;
+        for (i, c) in text.iter().enumerate() {
+            if *c == EMOJI_PRESENTATION {
+                continue;
+            }
+            if sequence.len() == self.longest {
+                break;
+            }
+            sequence.push(*c);
+            if self.files.contains_key(&sequence) {
+                found = Some((i + 1, sequence.clone()));
+            }
+        }
+        let (mut taken, sequence) = found?;
+        if text.get(taken) == Some(&EMOJI_PRESENTATION) {
+            taken += 1;
+        }
+        let selected = text[..taken].contains(&EMOJI_PRESENTATION);
+        if sequence.len() == 1 && text_form && !selected {
+            return None;
+        }
+        self.image(&sequence).map(|image| (taken, image))
+    }
+
+    // None when the PNG can't be read, so the text is drawn without it
+    fn image(&self, sequence: &[char]) -> Option<Arc<RgbaImage>> {
+        if let Some(image) = self.decoded.lock().unwrap().get(sequence) {
+            return Some(image.clone());
+        }
+        let path = self.dir.join(self.files.get(sequence)?);
+        let image = match image::open(&path) {
+            Ok(image) => Arc::new(image.to_rgba8()),
+            Err(err) => {
+                warn!("Failed to load emoji {}: {}", path.display(), err);
+                return None;
+            }
+        };
+        self.decoded
+            .lock()
+            .unwrap()
+            .insert(sequence.to_vec(), image.clone());
+        Some(image)
+    }
+}
+
+// The characters "emoji_u1f469_200d_1f4bb.png" draws
+fn sequence_of(file_name: &str) -> Option<Vec<char>> {
+    let codes = file_name.strip_prefix("emoji_u")?.strip_suffix(".png")?;
+    codes
+        .split('_')
+        .map(|code| u32::from_str_radix(code, 16).ok().and_then(char::from_u32))
+        .collect()
+}
+
+// How wide an emoji drawn size pixels tall is, keeping the image's shape
+pub fn emoji_width(image: &RgbaImage, size: f64) -> f64 {
+    (size * image.width() as f64 / image.height().max(1) as f64).round()
+}
+
+// Draws an emoji size pixels tall from x, sitting on the baseline like capital
+// letters do with a little below it, blended over what's already drawn
+pub fn draw_emoji(img: &mut RgbImage, emoji: &RgbaImage, x: f64, baseline: f64, size: f64) {
+    let (width, height) = (emoji_width(emoji, size) as u32, size.round() as u32);
+    let top = (baseline - size * 0.85).round() as i64;
+    blend_image(img, emoji, x.round() as i64, top, width, height);
+}
@@ -0,0 +1,760 @@
+use crate::errors::ImageError;
+use crate::images::emoji::{self, EmojiAtlas, EMOJI_PRESENTATION};
+use crate::images::{draw_text, BITMAP_GLYPH_SIZE};
+use image::{Rgb, RgbImage, RgbaImage};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+// Joiners and selectors shape the characters around them and draw nothing themselves
+const INVISIBLE: [char; 3] = ['\u{200d}', '\u{fe0e}', EMOJI_PRESENTATION];
+
+// The fonts from FONT_PATHS that templates draw text in. Each character comes from the
+// first font with a glyph for it, starting with those a <text> names in font-family,
+// so a brand font can be followed by a CJK one; characters none of them has, or all of
+// them without any fonts, fall back to the built-in 8x8 bitmap font. Emoji come from
+// the EMOJI_PATH atlas, in color, ahead of the fonts.
+#[derive(Debug, Default)]
+pub struct Fonts {
+    // With their file names, which font-family can use too
+    fonts: Vec<(String, Font)>,
+    emoji: EmojiAtlas,
+}
+
+// What a run of text is drawn as
+enum Piece<'a> {
+    Glyph(&'a Font, u16),
+    Bitmap(char),
+    Emoji(Arc<RgbaImage>),
+}
+
+impl Fonts {
+    pub fn load(paths: &[String]) -> Result<Self, ImageError> {
+        let mut fonts = Vec::with_capacity(paths.len());
+        for path in paths {
+            let data = std::fs::read(path)
+                .map_err(|err| ImageError::Font(path.clone(), err.to_string()))?;
+            let font =
+                Font::parse(data).map_err(|reason| ImageError::Font(path.clone(), reason))?;
+            let stem = Path::new(path)
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            fonts.push((stem, font));
+        }
+        Ok(Fonts {
+            fonts,
+            emoji: EmojiAtlas::default(),
+        })
+    }
+
+    // Draws emoji from this atlas rather than as the fonts' glyphs, or as "?"
+    pub fn with_emoji(mut self, emoji: EmojiAtlas) -> Self {
+        self.emoji = emoji;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    // The fonts to look in for text in family, a CSS list like "Inter, sans-serif"
+    fn chain(&self, family: Option<&str>) -> Vec<&Font> {
+        let mut chain: Vec<&Font> = Vec::with_capacity(self.fonts.len());
+        for name in family.into_iter().flat_map(|family| family.split(',')) {
+            let name = name.trim().trim_matches(|c| c == '"' || c == '\'');
+            for (stem, font) in &self.fonts {
+                let matches = stem.eq_ignore_ascii_case(name)
+                    || font
+                        .family
+                        .as_deref()
+                        .is_some_and(|f| f.eq_ignore_ascii_case(name));
+                if matches && !chain.iter().any(|f| std::ptr::eq(*f, font)) {
+                    chain.push(font);
+                }
+            }
+        }
+        for (_, font) in &self.fonts {
+            if !chain.iter().any(|f| std::ptr::eq(*f, font)) {
+                chain.push(font);
+            }
+        }
+        chain
+    }
+
+    // Text as the emoji, glyphs and bitmap characters it's drawn in. Emoji with a text
+    // form only come from the atlas when no font has a glyph for them.
+    fn pieces(&self, text: &str, family: Option<&str>) -> Vec<Piece<'_>> {
+        let chain = self.chain(family);
+        let chars: Vec<char> = text.chars().collect();
+        let mut pieces = Vec::with_capacity(chars.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let glyph = glyph_in(&chain, chars[i]);
+            if let Some((taken, image)) = self.emoji.find(&chars[i..], glyph.is_some()) {
+                pieces.push(Piece::Emoji(image));
+                i += taken;
+                continue;
+            }
+            match glyph {
+                Some((font, glyph)) => pieces.push(Piece::Glyph(font, glyph)),
+                None if INVISIBLE.contains(&chars[i]) => {}
+                None => pieces.push(Piece::Bitmap(chars[i])),
+            }
+            i += 1;
+        }
+        pieces
+    }
+
+    pub fn text_width(&self, text: &str, size: f64, family: Option<&str>) -> f64 {
+        self.pieces(text, family)
+            .iter()
+            .map(|piece| match piece {
+                Piece::Glyph(font, glyph) => font.advance(*glyph, size),
+                Piece::Bitmap(_) => (BITMAP_GLYPH_SIZE * bitmap_scale(size)) as f64,
+                Piece::Emoji(image) => emoji::emoji_width(image, size),
+            })
+            .sum()
+    }
+
+    // Draws text starting at x on the baseline, clipped to the image
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text(
+        &self,
+        img: &mut RgbImage,
+        text: &str,
+        x: f64,
+        baseline: f64,
+        size: f64,
+        family: Option<&str>,
+        color: Rgb<u8>,
+    ) {
+        let mut pen = x;
+        for piece in self.pieces(text, family) {
+            match piece {
+                Piece::Glyph(font, glyph) => {
+                    font.draw(img, glyph, pen, baseline, size, color);
+                    pen += font.advance(glyph, size);
+                }
+                Piece::Bitmap(c) => {
+                    let scale = bitmap_scale(size);
+                    let top = baseline.round() as i64 - (BITMAP_GLYPH_SIZE * scale) as i64;
+                    draw_text(img, &c.to_string(), pen.round() as i64, top, scale, color);
+                    pen += (BITMAP_GLYPH_SIZE * scale) as f64;
+                }
+                Piece::Emoji(image) => {
+                    emoji::draw_emoji(img, &image, pen, baseline, size);
+                    pen += emoji::emoji_width(&image, size);
+                }
+            }
+        }
+    }
+}
+
+fn glyph_in<'a>(chain: &[&'a Font], c: char) -> Option<(&'a Font, u16)> {
+    chain
+        .iter()
+        .find_map(|font| font.glyph(c).map(|glyph| (*font, glyph)))
+}
+
+// The bitmap font is drawn at whole multiples of its size
+fn bitmap_scale(size: f64) -> u32 {
+    (size / BITMAP_GLYPH_SIZE as f64).round().max(1.0) as u32
+}
+
+// A TrueType-outlined font (.ttf, or .otf with glyf outlines), parsed far enough to
+// draw text: character map, advances and quadratic glyph outlines. Hinting, kerning
+// and CFF outlines aren't supported.
+#[derive(Debug)]
+pub struct Font {
+    pub family: Option<String>,
+    data: Vec<u8>,
+    units_per_em: f64,
+    long_loca: bool,
+    num_glyphs: u16,
+    num_h_metrics: u16,
+    tables: HashMap<[u8; 4], usize>,
+    cmap: HashMap<char, u16>,
+}
+
+// Composite glyphs can nest; real fonts stop well short of this
+const MAX_COMPONENT_DEPTH: usize = 8;
+
+// Curves are flattened into this many lines
+const CURVE_STEPS: usize = 8;
+
+impl Font {
+    pub fn parse(data: Vec<u8>) -> Result<Font, String> {
+        match data.get(..4) {
+            Some(b"\x00\x01\x00\x00" | b"true") => {}
+            Some(b"OTTO") => {
+                return Err("CFF outlines aren't supported, use TrueType outlines".to_string())
+            }
+            Some(b"ttcf") => return Err("font collections aren't supported".to_string()),
+            _ => return Err("not a TrueType or OpenType font".to_string()),
+        }
+        let reader = Reader(&data);
+        let num_tables = reader.u16(4)? as usize;
+        let mut tables = HashMap::new();
+        for i in 0..num_tables {
+            let record = 12 + i * 16;
+            let tag: [u8; 4] = reader.bytes(record, 4)?.try_into().unwrap();
+            let offset = reader.u32(record + 8)? as usize;
+            let length = reader.u32(record + 12)? as usize;
+            reader.bytes(offset, length)?;
+            tables.insert(tag, offset);
+        }
+        let table = |tag: &[u8; 4]| {
+            tables
+                .get(tag)
+                .copied()
+                .ok_or_else(|| format!("no {} table", String::from_utf8_lossy(tag)))
+        };
+        for tag in [b"loca", b"glyf", b"hmtx"] {
+            table(tag)?;
+        }
+
+        let head = table(b"head")?;
+        let units_per_em = reader.u16(head + 18)? as f64;
+        if units_per_em == 0.0 {
+            return Err("unitsPerEm is 0".to_string());
+        }
+        let long_loca = reader.i16(head + 50)? == 1;
+        let num_h_metrics = reader.u16(table(b"hhea")? + 34)?;
+        let num_glyphs = reader.u16(table(b"maxp")? + 4)?;
+        let cmap = parse_cmap(&reader, table(b"cmap")?)?;
+        let family = tables
+            .get(b"name")
+            .and_then(|offset| parse_family(&reader, *offset));
+
+        Ok(Font {
+            family,
+            data,
+            units_per_em,
+            long_loca,
+            num_glyphs,
+            num_h_metrics,
+            tables,
+            cmap,
+        })
+    }
+
+    pub fn glyph(&self, c: char) -> Option<u16> {
+        self.cmap
+            .get(&c)
+            .copied()
+            .filter(|&glyph| glyph != 0 && glyph < self.num_glyphs)
+    }
+
+    // How far the pen moves after a glyph, in pixels at size
+    pub fn advance(&self, glyph: u16, size: f64) -> f64 {
+        let reader = Reader(&self.data);
+        let hmtx = self.tables[b"hmtx"];
+        let metric = glyph.min(self.num_h_metrics.saturating_sub(1)) as usize;
+        reader.u16(hmtx + metric * 4).unwrap_or(0) as f64 * size / self.units_per_em
+    }
+
+    // Draws a glyph with its origin at (x, baseline), blending its coverage into img
+    pub fn draw(
+        &self,
+        img: &mut RgbImage,
+        glyph: u16,
+        x: f64,
+        baseline: f64,
+        size: f64,
+        color: Rgb<u8>,
+    ) {
+        let mut contours = Vec::new();
+        if self
+            .outline(glyph, (1.0, 0.0, 0.0, 1.0, 0.0, 0.0), 0, &mut contours)
+            .is_err()
+        {
+            return;
+        }
+        let scale = size / self.units_per_em;
+        // Font units point up; image rows count down
+        let points: Vec<Vec<(f64, f64)>> = contours
+            .iter()
+            .map(|contour| {
+                contour
+                    .iter()
+                    .map(|(px, py)| (x + px * scale, baseline - py * scale))
+                    .collect()
+            })
+            .collect();
+        let (mut left, mut top) = (f64::INFINITY, f64::INFINITY);
+        let (mut right, mut bottom) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for (px, py) in points.iter().flatten() {
+            left = left.min(*px);
+            right = right.max(*px);
+            top = top.min(*py);
+            bottom = bottom.max(*py);
+        }
+        if !left.is_finite() {
+            return;
+        }
+        let (left, top) = (left.floor() as i64, top.floor() as i64);
+        let width = (right.ceil() as i64 - left).max(0) as usize + 2;
+        let height = (bottom.ceil() as i64 - top).max(0) as usize + 1;
+        if width * height > 4 * 2048 * 2048 {
+            return;
+        }
+
+        let mut coverage = Coverage::new(width, height);
+        for contour in &points {
+            for (i, from) in contour.iter().enumerate() {
+                let to = contour[(i + 1) % contour.len()];
+                coverage.line(
+                    (from.0 - left as f64, from.1 - top as f64),
+                    (to.0 - left as f64, to.1 - top as f64),
+                );
+            }
+        }
+        coverage.blend(img, left, top, color);
+    }
+
+    // Appends the glyph's contours, as flattened points in font units, under transform
+    // (xx, xy, yx, yy, dx, dy)
+    fn outline(
+        &self,
+        glyph: u16,
+        transform: (f64, f64, f64, f64, f64, f64),
+        depth: usize,
+        contours: &mut Vec<Vec<(f64, f64)>>,
+    ) -> Result<(), String> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Err("composite glyphs nest too deep".to_string());
+        }
+        let reader = Reader(&self.data);
+        let loca = self.tables[b"loca"];
+        let (start, end) = if self.long_loca {
+            let at = loca + glyph as usize * 4;
+            (reader.u32(at)? as usize, reader.u32(at + 4)? as usize)
+        } else {
+            let at = loca + glyph as usize * 2;
+            (
+                reader.u16(at)? as usize * 2,
+                reader.u16(at + 2)? as usize * 2,
+            )
+        };
+        // Spaces and the like have no outline
+        if end <= start {
+            return Ok(());
+        }
+        let at = self.tables[b"glyf"] + start;
+        let contour_count = reader.i16(at)?;
+        let map = |(px, py): (f64, f64)| {
+            let (xx, xy, yx, yy, dx, dy) = transform;
+            (px * xx + py * yx + dx, px * xy + py * yy + dy)
+        };
+        if contour_count >= 0 {
+            for contour in simple_glyph(&reader, at, contour_count as usize)? {
+                contours.push(flatten(&contour).into_iter().map(map).collect());
+            }
+            return Ok(());
+        }
+
+        let mut at = at + 10;
+        loop {
+            let flags = reader.u16(at)?;
+            let component = reader.u16(at + 2)?;
+            at += 4;
+            let (arg1, arg2) = if flags & 0x0001 != 0 {
+                let args = (reader.i16(at)? as f64, reader.i16(at + 2)? as f64);
+                at += 4;
+                args
+            } else {
+                let args = (reader.u8(at)? as i8 as f64, reader.u8(at + 1)? as i8 as f64);
+                at += 2;
+                args
+            };
+            let f2dot14 = |at: usize| reader.i16(at).map(|v| v as f64 / 16384.0);
+            let (xx, xy, yx, yy) = if flags & 0x0008 != 0 {
+                let scale = f2dot14(at)?;
+                at += 2;
+                (scale, 0.0, 0.0, scale)
+            } else if flags & 0x0040 != 0 {
+                let scales = (f2dot14(at)?, 0.0, 0.0, f2dot14(at + 2)?);
+                at += 4;
+                scales
+            } else if flags & 0x0080 != 0 {
+                let scales = (
+                    f2dot14(at)?,
+                    f2dot14(at + 2)?,
+                    f2dot14(at + 4)?,
+                    f2dot14(at + 6)?,
+                );
+                at += 8;
+                scales
+            } else {
+                (1.0, 0.0, 0.0, 1.0)
+            };
+            // Point-matched components (ARGS_ARE_XY_VALUES unset) are rare; place them
+            // at the origin rather than fail the glyph
+            let (dx, dy) = if flags & 0x0002 != 0 {
+                (arg1, arg2)
+            } else {
+                (0.0, 0.0)
+            };
+            let (pxx, pxy, pyx, pyy, pdx, pdy) = transform;
+            let combined = (
+                xx * pxx + xy * pyx,
+                xx * pxy + xy * pyy,
+                yx * pxx + yy * pyx,
+                yx * pxy + yy * pyy,
+                dx * pxx + dy * pyx + pdx,
+                dx * pxy + dy * pyy + pdy,
+            );
+            self.outline(component, combined, depth + 1, contours)?;
+            if flags & 0x0020 == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// A point of an outline, in font units, and whether it's on the curve or a control point
+type Point = (f64, f64, bool);
+
+// A glyph's contours
+fn simple_glyph(
+    reader: &Reader,
+    at: usize,
+    contour_count: usize,
+) -> Result<Vec<Vec<Point>>, String> {
+    let mut ends = Vec::with_capacity(contour_count);
+    for i in 0..contour_count {
+        ends.push(reader.u16(at + 10 + i * 2)? as usize);
+    }
+    let point_count = ends.last().map_or(0, |end| end + 1);
+    let instructions = reader.u16(at + 10 + contour_count * 2)? as usize;
+    let mut cursor = at + 12 + contour_count * 2 + instructions;
+
+    let mut flags = Vec::with_capacity(point_count);
+    while flags.len() < point_count {
+        let flag = reader.u8(cursor)?;
+        cursor += 1;
+        flags.push(flag);
+        if flag & 0x08 != 0 {
+            let repeat = reader.u8(cursor)?;
+            cursor += 1;
+            for _ in 0..repeat {
+                flags.push(flag);
+            }
+        }
+    }
+    flags.truncate(point_count);
+
+    // Coordinates are deltas, one byte (with a sign flag) or two each
+    let mut coordinates = |short: u8, same: u8| -> Result<Vec<f64>, String> {
+        let mut value = 0i32;
+        let mut values = Vec::with_capacity(point_count);
+        for flag in &flags {
+            if flag & short != 0 {
+                let delta = reader.u8(cursor)? as i32;
+                cursor += 1;
+                value += if flag & same != 0 { delta } else { -delta };
+            } else if flag & same == 0 {
+                value += reader.i16(cursor)? as i32;
+                cursor += 2;
+            }
+            values.push(value as f64);
+        }
+        Ok(values)
+    };
+    let xs = coordinates(0x02, 0x10)?;
+    let ys = coordinates(0x04, 0x20)?;
+
+    let mut contours = Vec::with_capacity(contour_count);
+    let mut start = 0;
+    for end in ends {
+        if end < start || end >= point_count {
+            return Err("malformed contour".to_string());
+        }
+        contours.push(
+            (start..=end)
+                .map(|i| (xs[i], ys[i], flags[i] & 0x01 != 0))
+                .collect(),
+        );
+        start = end + 1;
+    }
+    Ok(contours)
+}
+
+// Turns a contour's on and off curve points into a closed polyline. Two off curve
+// points in a row imply an on curve point halfway between them.
+fn flatten(contour: &[Point]) -> Vec<(f64, f64)> {
+    let mut points = Vec::new();
+    let Some(first_on) = contour.iter().position(|p| p.2) else {
+        // All off curve: start at the implied point between the first two
+        let Some((&a, &b)) = contour.first().zip(contour.get(1 % contour.len())) else {
+            return points;
+        };
+        let start = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0, true);
+        let mut with_start = vec![start];
+        with_start.extend_from_slice(contour);
+        return flatten(&with_start);
+    };
+    let n = contour.len();
+    let start = contour[first_on];
+    points.push((start.0, start.1));
+    let mut control: Option<(f64, f64)> = None;
+    let mut from = (start.0, start.1);
+    for i in 1..=n {
+        let (x, y, on) = contour[(first_on + i) % n];
+        match (on, control) {
+            (true, None) => {
+                points.push((x, y));
+                from = (x, y);
+            }
+            (true, Some(c)) => {
+                curve(&mut points, from, c, (x, y));
+                from = (x, y);
+                control = None;
+            }
+            (false, None) => control = Some((x, y)),
+            (false, Some(c)) => {
+                let mid = ((c.0 + x) / 2.0, (c.1 + y) / 2.0);
+                curve(&mut points, from, c, mid);
+                from = mid;
+                control = Some((x, y));
+            }
+        }
+    }
+    points
+}
+
+fn curve(points: &mut Vec<(f64, f64)>, from: (f64, f64), control: (f64, f64), to: (f64, f64)) {
+    for step in 1..=CURVE_STEPS {
+        let t = step as f64 / CURVE_STEPS as f64;
+        let u = 1.0 - t;
+        points.push((
+            u * u * from.0 + 2.0 * u * t * control.0 + t * t * to.0,
+            u * u * from.1 + 2.0 * u * t * control.1 + t * t * to.1,
+        ));
+    }
+}
+
+// Signed area coverage, accumulated along each row: every outline edge adds how much
+// of each pixel it covers, and a running sum across the row fills the inside
+struct Coverage {
+    width: usize,
+    height: usize,
+    area: Vec<f64>,
+}
+
+impl Coverage {
+    fn new(width: usize, height: usize) -> Self {
+        Coverage {
+            width,
+            height,
+            area: vec![0.0; width * height + 2],
+        }
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64)) {
+        if (from.1 - to.1).abs() <= f64::EPSILON {
+            return;
+        }
+        let (dir, from, to) = if from.1 < to.1 {
+            (1.0, from, to)
+        } else {
+            (-1.0, to, from)
+        };
+        let dxdy = (to.0 - from.0) / (to.1 - from.1);
+        let mut x = from.0;
+        let first_row = from.1.max(0.0) as usize;
+        if from.1 < 0.0 {
+            x -= from.1 * dxdy;
+        }
+        for row in first_row..self.height.min(to.1.ceil() as usize) {
+            let start = row * self.width;
+            let dy = ((row + 1) as f64).min(to.1) - (row as f64).max(from.1);
+            let next_x = x + dxdy * dy;
+            let d = dy * dir;
+            let (x0, x1) = if x < next_x { (x, next_x) } else { (next_x, x) };
+            let (x0, x1) = (x0.max(0.0), x1.max(0.0));
+            let x0_floor = x0.floor();
+            let x0i = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil as usize;
+            if x1i <= x0i + 1 {
+                let mid = 0.5 * (x + next_x) - x0_floor;
+                self.area[start + x0i] += d - d * mid;
+                self.area[start + x0i + 1] += d * mid;
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+                self.area[start + x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    self.area[start + x0i + 1] += d * (1.0 - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    self.area[start + x0i + 1] += d * (a1 - a0);
+                    for xi in x0i + 2..x1i - 1 {
+                        self.area[start + xi] += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f64 * s;
+                    self.area[start + x1i - 1] += d * (1.0 - a2 - am);
+                }
+                self.area[start + x1i] += d * am;
+            }
+            x = next_x;
+        }
+    }
+
+    fn blend(&self, img: &mut RgbImage, left: i64, top: i64, color: Rgb<u8>) {
+        let mut sum = 0.0;
+        for (i, area) in self.area[..self.width * self.height].iter().enumerate() {
+            sum += area;
+            let alpha = sum.abs().min(1.0);
+            let (px, py) = (
+                left + (i % self.width) as i64,
+                top + (i / self.width) as i64,
+            );
+            if alpha < 1.0 / 255.0
+                || px < 0
+                || py < 0
+                || px >= img.width() as i64
+                || py >= img.height() as i64
+            {
+                continue;
+            }
+            let pixel = img.get_pixel_mut(px as u32, py as u32);
+            for (channel, target) in pixel.0.iter_mut().zip(color.0) {
+                *channel = (*channel as f64 * (1.0 - alpha) + target as f64 * alpha).round() as u8;
+            }
+        }
+    }
+}
+
+// Maps characters to glyphs from the first Unicode subtable, format 4 (the BMP) or
+// 12 (everything, e.g. CJK extensions), preferring 12
+fn parse_cmap(reader: &Reader, cmap: usize) -> Result<HashMap<char, u16>, String> {
+    let count = reader.u16(cmap + 2)? as usize;
+    let mut best: Option<(u16, usize)> = None;
+    for i in 0..count {
+        let record = cmap + 4 + i * 8;
+        let platform = reader.u16(record)?;
+        let encoding = reader.u16(record + 2)?;
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        if !unicode {
+            continue;
+        }
+        let at = cmap + reader.u32(record + 4)? as usize;
+        let format = reader.u16(at)?;
+        if (format == 4 || format == 12) && best.is_none_or(|(best, _)| format > best) {
+            best = Some((format, at));
+        }
+    }
+    let (format, at) = best.ok_or("no Unicode character map")?;
+
+    let mut map = HashMap::new();
+    if format == 12 {
+        let groups = reader.u32(at + 12)? as usize;
+        for group in 0..groups {
+            let record = at + 16 + group * 12;
+            let (first, last) = (reader.u32(record)?, reader.u32(record + 4)?);
+            let glyph = reader.u32(record + 8)?;
+            for (i, code) in (first..=last.min(first.saturating_add(0xFFFF))).enumerate() {
+                if let (Some(c), Ok(glyph)) =
+                    (char::from_u32(code), u16::try_from(glyph + i as u32))
+                {
+                    map.insert(c, glyph);
+                }
+            }
+        }
+        return Ok(map);
+    }
+
+    let segments = reader.u16(at + 6)? as usize / 2;
+    let ends = at + 14;
+    let starts = ends + segments * 2 + 2;
+    let deltas = starts + segments * 2;
+    let range_offsets = deltas + segments * 2;
+    for segment in 0..segments {
+        let end = reader.u16(ends + segment * 2)?;
+        let start = reader.u16(starts + segment * 2)?;
+        let delta = reader.u16(deltas + segment * 2)?;
+        let range_offset_at = range_offsets + segment * 2;
+        let range_offset = reader.u16(range_offset_at)? as usize;
+        for code in start..=end {
+            if code == 0xFFFF {
+                break;
+            }
+            let glyph = if range_offset == 0 {
+                code.wrapping_add(delta)
+            } else {
+                let at = range_offset_at + range_offset + (code - start) as usize * 2;
+                match reader.u16(at)? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(delta),
+                }
+            };
+            if let (Some(c), true) = (char::from_u32(code as u32), glyph != 0) {
+                map.insert(c, glyph);
+            }
+        }
+    }
+    Ok(map)
+}
+
+// The family name (name id 1), from a Windows Unicode or Mac Roman record
+fn parse_family(reader: &Reader, name: usize) -> Option<String> {
+    let count = reader.u16(name + 2).ok()? as usize;
+    let strings = name + reader.u16(name + 4).ok()? as usize;
+    for i in 0..count {
+        let record = name + 6 + i * 12;
+        let (platform, name_id) = (reader.u16(record).ok()?, reader.u16(record + 6).ok()?);
+        if name_id != 1 {
+            continue;
+        }
+        let length = reader.u16(record + 8).ok()? as usize;
+        let offset = reader.u16(record + 10).ok()? as usize;
+        let bytes = reader.bytes(strings + offset, length).ok()?;
+        let family = match platform {
+            0 | 3 => {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                String::from_utf16(&units).ok()?
+            }
+            1 => bytes.iter().map(|&b| b as char).collect(),
+            _ => continue,
+        };
+        return Some(family);
+    }
+    None
+}
+
+// Big-endian reads that fail, rather than panic, past the end of a malformed font
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes(&self, at: usize, len: usize) -> Result<&[u8], String> {
+        at.checked_add(len)
+            .and_then(|end| self.0.get(at..end))
+            .ok_or_else(|| "truncated font".to_string())
+    }
+
+    fn u8(&self, at: usize) -> Result<u8, String> {
+        Ok(self.bytes(at, 1)?[0])
+    }
+
+    fn u16(&self, at: usize) -> Result<u16, String> {
+        let bytes = self.bytes(at, 2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&self, at: usize) -> Result<i16, String> {
+        self.u16(at).map(|v| v as i16)
+    }
+
+    fn u32(&self, at: usize) -> Result<u32, String> {
+        let bytes = self.bytes(at, 4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
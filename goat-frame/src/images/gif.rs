@@ -0,0 +1,178 @@
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+
+// GIF codes grow to this many bits before the table starts over
+const MAX_CODE_BITS: u32 = 12;
+
+// Every frame of an image shares one 256-color table
+const PALETTE_SIZE: usize = 256;
+
+// Encodes frames as a GIF that loops forever, showing each for delay_ms (rounded to
+// the format's hundredths of a second). Frames are drawn from one template, so
+// they're the same size and at most svg::MAX_SIZE a side. Images with more than 256
+// colors across all frames, like anti-aliased text, are mapped onto a fixed palette
+// of 6 reds, 7 greens and 6 blues.
+pub fn encode_gif(frames: &[RgbImage], delay_ms: u32) -> Vec<u8> {
+    let (width, height) = frames.first().map_or((0, 0), |frame| {
+        let (width, height) = frame.dimensions();
+        (width as u16, height as u16)
+    });
+    let palette = Palette::of(frames);
+    let delay = (delay_ms / 10).clamp(2, u16::MAX as u32) as u16;
+
+    let mut gif = Vec::new();
+    gif.extend_from_slice(b"GIF89a");
+    gif.extend_from_slice(&width.to_le_bytes());
+    gif.extend_from_slice(&height.to_le_bytes());
+    // A global table of 2^8 colors, 8 bits per channel
+    gif.extend_from_slice(&[0xF7, 0, 0]);
+    for index in 0..PALETTE_SIZE {
+        gif.extend_from_slice(&palette.colors.get(index).map_or([0; 3], |color| color.0));
+    }
+    // Loops forever
+    gif.extend_from_slice(&[0x21, 0xFF, 11]);
+    gif.extend_from_slice(b"NETSCAPE2.0");
+    gif.extend_from_slice(&[3, 1, 0, 0, 0]);
+
+    for frame in frames {
+        // Each frame replaces the last, shown for delay hundredths of a second
+        gif.extend_from_slice(&[0x21, 0xF9, 4, 0x04]);
+        gif.extend_from_slice(&delay.to_le_bytes());
+        gif.extend_from_slice(&[0, 0]);
+        gif.push(0x2C);
+        gif.extend_from_slice(&[0, 0, 0, 0]);
+        gif.extend_from_slice(&width.to_le_bytes());
+        gif.extend_from_slice(&height.to_le_bytes());
+        gif.push(0);
+        let indices: Vec<u8> = frame.pixels().map(|pixel| palette.index(*pixel)).collect();
+        gif.push(8);
+        for block in lzw(&indices).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend_from_slice(block);
+        }
+        gif.push(0);
+    }
+    gif.push(0x3B);
+    gif
+}
+
+struct Palette {
+    colors: Vec<Rgb<u8>>,
+    // None when the frames have too many colors to list, and the color cube is used
+    exact: Option<HashMap<Rgb<u8>, u8>>,
+}
+
+impl Palette {
+    fn of(frames: &[RgbImage]) -> Self {
+        let mut exact = HashMap::new();
+        for pixel in frames.iter().flat_map(|frame| frame.pixels()) {
+            if !exact.contains_key(pixel) {
+                if exact.len() == PALETTE_SIZE {
+                    return Palette::cube();
+                }
+                exact.insert(*pixel, exact.len() as u8);
+            }
+        }
+        let mut colors = vec![Rgb([0; 3]); exact.len()];
+        for (color, index) in &exact {
+            colors[*index as usize] = *color;
+        }
+        Palette {
+            colors,
+            exact: Some(exact),
+        }
+    }
+
+    fn cube() -> Self {
+        let level = |value: usize, levels: usize| (value * 255 / (levels - 1)) as u8;
+        let mut colors = Vec::with_capacity(6 * 7 * 6);
+        for r in 0..6 {
+            for g in 0..7 {
+                for b in 0..6 {
+                    colors.push(Rgb([level(r, 6), level(g, 7), level(b, 6)]));
+                }
+            }
+        }
+        Palette {
+            colors,
+            exact: None,
+        }
+    }
+
+    fn index(&self, pixel: Rgb<u8>) -> u8 {
+        if let Some(exact) = &self.exact {
+            return exact[&pixel];
+        }
+        let level = |value: u8, levels: u32| (value as u32 * (levels - 1) + 127) / 255;
+        let [r, g, b] = pixel.0;
+        ((level(r, 6) * 7 + level(g, 7)) * 6 + level(b, 6)) as u8
+    }
+}
+
+// Compresses 8-bit color indices the way GIF image data is: variable-width LZW codes
+// packed least significant bit first
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    let mut out = BitWriter::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let (mut next, mut bits) = (END + 1, 9);
+    out.write(CLEAR, bits);
+
+    let mut indices = indices.iter();
+    let Some(&first) = indices.next() else {
+        out.write(END, bits);
+        return out.finish();
+    };
+    let mut prefix = first as u16;
+    for &index in indices {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        out.write(prefix, bits);
+        if next == 1 << MAX_CODE_BITS {
+            // The table is full, so start over rather than stop learning
+            out.write(CLEAR, bits);
+            table.clear();
+            (next, bits) = (END + 1, 9);
+        } else {
+            table.insert((prefix, index), next);
+            // Decoders widen codes as soon as the next one needs it
+            if next == 1 << bits && bits < MAX_CODE_BITS {
+                bits += 1;
+            }
+            next += 1;
+        }
+        prefix = index as u16;
+    }
+    out.write(prefix, bits);
+    out.write(END, bits);
+    out.finish()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, bits: u32) {
+        self.buffer |= (code as u32) << self.len;
+        self.len += bits;
+        while self.len >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
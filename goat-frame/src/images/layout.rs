@@ -0,0 +1,411 @@
+use crate::errors::ImageError;
+use crate::images::font::Fonts;
+use crate::images::svg::AVATAR_HREF;
+use crate::images::{escape_xml, placeholders, substitute};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+// Lines of text take this much more height than their font size
+const LINE_HEIGHT: f64 = 1.25;
+
+// A template written as boxes rather than coordinates, from templates/<name>.json:
+// {"width": 1024, "height": 1024, "root": {"type": "column", "children": [...]}}.
+// Rows lay their children out left to right and columns top to bottom, like CSS
+// flexbox; text and images are the leaves. Strings take "{{name}}" placeholders.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layout {
+    pub width: u32,
+    pub height: u32,
+    pub root: Node,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Node {
+    Row(Container),
+    Column(Container),
+    Text(Text),
+    Image(Image),
+}
+
+// width and height fix a node's size, else it takes what its content needs; grow
+// shares out the room left in its row or column, in proportion to each grow
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Container {
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    #[serde(default)]
+    pub grow: f64,
+    #[serde(default)]
+    pub padding: f64,
+    // Between each child
+    #[serde(default)]
+    pub gap: f64,
+    // Where children sit across the row or column
+    #[serde(default)]
+    pub align: Align,
+    // Where they sit along it, when none grows into the room left
+    #[serde(default)]
+    pub justify: Justify,
+    pub background: Option<String>,
+    #[serde(default)]
+    pub children: Vec<Node>,
+}
+
+// Text on one line, cut off at the image's edge rather than wrapped. anchor places
+// it in a box wider than it, as SVG's text-anchor does.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Text {
+    pub text: String,
+    #[serde(default = "default_size")]
+    pub size: f64,
+    #[serde(default = "default_color")]
+    pub color: String,
+    pub font: Option<String>,
+    #[serde(default)]
+    pub anchor: Anchor,
+    #[serde(default)]
+    pub grow: f64,
+}
+
+// An asset under assets/, or "avatar" for the viewer's pfp, stretched to its size
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Image {
+    pub href: String,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub grow: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Justify {
+    #[default]
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Anchor {
+    #[default]
+    Start,
+    Middle,
+    End,
+}
+
+fn default_size() -> f64 {
+    16.0
+}
+
+fn default_color() -> String {
+    "#000000".to_string()
+}
+
+// Where a node was placed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Layout {
+    pub fn parse(json: &str) -> Result<Self, ImageError> {
+        let layout: Layout = serde_json::from_str(json)
+            .map_err(|err| ImageError::Svg(format!("invalid layout: {}", err)))?;
+        layout.root.check()?;
+        Ok(layout)
+    }
+
+    // Lays the boxes out with params filled in, as the SVG svg::rasterize draws
+    pub fn to_svg(&self, params: &HashMap<String, String>, fonts: &Fonts) -> String {
+        let mut svg = format!("<svg width=\"{}\" height=\"{}\">", self.width, self.height);
+        let root = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: self.width as f64,
+            height: self.height as f64,
+        };
+        self.root.draw(root, params, fonts, &mut svg);
+        svg.push_str("</svg>");
+        svg
+    }
+
+    // The placeholders in the layout's strings, each once
+    pub fn placeholders(&self) -> BTreeSet<String> {
+        let mut strings = Vec::new();
+        self.root.strings(&mut strings);
+        strings.into_iter().flat_map(placeholders).collect()
+    }
+
+    pub fn draws_avatar(&self) -> bool {
+        let mut strings = Vec::new();
+        self.root.images(&mut strings);
+        strings.iter().any(|href| href.trim() == AVATAR_HREF)
+    }
+}
+
+impl Node {
+    fn grow(&self) -> f64 {
+        match self {
+            Node::Row(c) | Node::Column(c) => c.grow,
+            Node::Text(text) => text.grow,
+            Node::Image(image) => image.grow,
+        }
+    }
+
+    // Whether the node's size across a row (its height) or column (its width) is set,
+    // which stretching leaves alone
+    fn fixed_across(&self, row: bool) -> bool {
+        match self {
+            Node::Row(c) | Node::Column(c) => if row { c.height } else { c.width }.is_some(),
+            Node::Text(_) => false,
+            Node::Image(_) => true,
+        }
+    }
+
+    // Sizes can't be negative or grow back from it, so they're refused when loaded
+    fn check(&self) -> Result<(), ImageError> {
+        let (numbers, children): (Vec<f64>, &[Node]) = match self {
+            Node::Row(c) | Node::Column(c) => (
+                [c.grow, c.padding, c.gap]
+                    .into_iter()
+                    .chain(c.width)
+                    .chain(c.height)
+                    .collect(),
+                &c.children,
+            ),
+            Node::Text(text) => (vec![text.size, text.grow], &[]),
+            Node::Image(image) => (vec![image.width, image.height, image.grow], &[]),
+        };
+        if numbers.iter().any(|n| !n.is_finite() || *n < 0.0) {
+            return Err(ImageError::Svg(
+                "layout sizes must be numbers of at least 0".to_string(),
+            ));
+        }
+        children.iter().try_for_each(Node::check)
+    }
+
+    // The width and height the node takes when not stretched or grown
+    fn measure(&self, params: &HashMap<String, String>, fonts: &Fonts) -> (f64, f64) {
+        match self {
+            Node::Text(text) => {
+                let filled = substitute(&text.text, params);
+                let family = text.font.as_ref().map(|font| substitute(font, params));
+                let width = fonts.text_width(&filled, text.size, family.as_deref());
+                (width.ceil(), (text.size * LINE_HEIGHT).ceil())
+            }
+            Node::Image(image) => (image.width, image.height),
+            Node::Row(c) | Node::Column(c) => {
+                let row = matches!(self, Node::Row(_));
+                let sizes = c.children.iter().map(|child| child.measure(params, fonts));
+                let (mut main, mut cross) = (0.0, 0.0_f64);
+                for (width, height) in sizes {
+                    let (along, across) = if row {
+                        (width, height)
+                    } else {
+                        (height, width)
+                    };
+                    main += along;
+                    cross = cross.max(across);
+                }
+                main += c.gap * c.children.len().saturating_sub(1) as f64;
+                let (width, height) = if row { (main, cross) } else { (cross, main) };
+                (
+                    c.width.unwrap_or(width + c.padding * 2.0),
+                    c.height.unwrap_or(height + c.padding * 2.0),
+                )
+            }
+        }
+    }
+
+    fn draw(&self, area: Rect, params: &HashMap<String, String>, fonts: &Fonts, svg: &mut String) {
+        match self {
+            Node::Text(text) => {
+                let filled = escape_xml(&substitute(&text.text, params));
+                let (x, anchor) = match text.anchor {
+                    Anchor::Start => (area.x, "start"),
+                    Anchor::Middle => (area.x + area.width / 2.0, "middle"),
+                    Anchor::End => (area.x + area.width, "end"),
+                };
+                // Centred in its box; y is the baseline, a little below the middle
+                let y = area.y + area.height / 2.0 + text.size * 0.35;
+                let family = text
+                    .font
+                    .as_ref()
+                    .map(|font| {
+                        format!(" font-family=\"{}\"", escape_xml(&substitute(font, params)))
+                    })
+                    .unwrap_or_default();
+                let _ = write!(
+                    svg,
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"{}\" text-anchor=\"{}\"{}>{}</text>",
+                    x.round(),
+                    y.round(),
+                    text.size,
+                    escape_xml(&substitute(&text.color, params)),
+                    anchor,
+                    family,
+                    filled
+                );
+            }
+            Node::Image(image) => {
+                let _ = write!(
+                    svg,
+                    "<image href=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                    escape_xml(&substitute(&image.href, params)),
+                    area.x.round(),
+                    area.y.round(),
+                    area.width.round(),
+                    area.height.round()
+                );
+            }
+            Node::Row(c) | Node::Column(c) => {
+                if let Some(background) = &c.background {
+                    let _ = write!(
+                        svg,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                        area.x.round(),
+                        area.y.round(),
+                        area.width.round(),
+                        area.height.round(),
+                        escape_xml(&substitute(background, params))
+                    );
+                }
+                let row = matches!(self, Node::Row(_));
+                for (child, placed) in c.place(row, area, params, fonts) {
+                    child.draw(placed, params, fonts, svg);
+                }
+            }
+        }
+    }
+
+    // Every string filled in the node and its children
+    fn strings<'a>(&'a self, strings: &mut Vec<&'a str>) {
+        match self {
+            Node::Text(text) => {
+                strings.push(&text.text);
+                strings.push(&text.color);
+                strings.extend(text.font.as_deref());
+            }
+            Node::Image(image) => strings.push(&image.href),
+            Node::Row(c) | Node::Column(c) => {
+                strings.extend(c.background.as_deref());
+                c.children.iter().for_each(|child| child.strings(strings));
+            }
+        }
+    }
+
+    fn images<'a>(&'a self, hrefs: &mut Vec<&'a str>) {
+        match self {
+            Node::Image(image) => hrefs.push(&image.href),
+            Node::Text(_) => {}
+            Node::Row(c) | Node::Column(c) => {
+                c.children.iter().for_each(|child| child.images(hrefs));
+            }
+        }
+    }
+}
+
+impl Container {
+    // Each child's box inside this one's, which is area less the padding
+    pub fn place<'a>(
+        &'a self,
+        row: bool,
+        area: Rect,
+        params: &HashMap<String, String>,
+        fonts: &Fonts,
+    ) -> Vec<(&'a Node, Rect)> {
+        let inner = Rect {
+            x: area.x + self.padding,
+            y: area.y + self.padding,
+            width: (area.width - self.padding * 2.0).max(0.0),
+            height: (area.height - self.padding * 2.0).max(0.0),
+        };
+        let (main_size, cross_size) = if row {
+            (inner.width, inner.height)
+        } else {
+            (inner.height, inner.width)
+        };
+        let sizes: Vec<(f64, f64)> = self
+            .children
+            .iter()
+            .map(|child| {
+                let (width, height) = child.measure(params, fonts);
+                if row {
+                    (width, height)
+                } else {
+                    (height, width)
+                }
+            })
+            .collect();
+        let gaps = self.gap * self.children.len().saturating_sub(1) as f64;
+        // Overflowing children aren't shrunk; the rasterizer clips what's off the image
+        let room = (main_size - gaps - sizes.iter().map(|(main, _)| main).sum::<f64>()).max(0.0);
+        let grow: f64 = self.children.iter().map(Node::grow).sum();
+        let (mut offset, spacing) = match self.justify {
+            _ if grow > 0.0 => (0.0, self.gap),
+            Justify::Start => (0.0, self.gap),
+            Justify::Center => (room / 2.0, self.gap),
+            Justify::End => (room, self.gap),
+            Justify::SpaceBetween if self.children.len() > 1 => {
+                (0.0, self.gap + room / (self.children.len() - 1) as f64)
+            }
+            Justify::SpaceBetween => (0.0, self.gap),
+        };
+
+        let mut placed = Vec::with_capacity(self.children.len());
+        for (child, (main, cross)) in self.children.iter().zip(sizes) {
+            let main = if grow > 0.0 {
+                main + room * child.grow() / grow
+            } else {
+                main
+            };
+            let (cross, cross_offset) = match self.align {
+                Align::Start => (cross, 0.0),
+                Align::Center => (cross, (cross_size - cross) / 2.0),
+                Align::End => (cross, cross_size - cross),
+                Align::Stretch if child.fixed_across(row) => (cross, 0.0),
+                Align::Stretch => (cross_size, 0.0),
+            };
+            let rect = if row {
+                Rect {
+                    x: inner.x + offset,
+                    y: inner.y + cross_offset,
+                    width: main,
+                    height: cross,
+                }
+            } else {
+                Rect {
+                    x: inner.x + cross_offset,
+                    y: inner.y + offset,
+                    width: cross,
+                    height: main,
+                }
+            };
+            placed.push((child, rect));
+            offset += main + spacing;
+        }
+        placed
+    }
+}
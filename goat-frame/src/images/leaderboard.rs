@@ -0,0 +1,214 @@
+use crate::images::fill_rect;
+use crate::images::font::Fonts;
+use crate::images::svg::{draw_image, fill_circle, AVATAR_PLACEHOLDER};
+use crate::leaderboard::{format_amount, LeaderboardRow};
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+use std::ops::Range;
+
+// The most rows a page fits while staying readable in a frame image
+pub const MAX_PAGE_ROWS: usize = 10;
+
+// Cut names end in this, which every font can draw
+const ELLIPSIS: &str = "...";
+
+// Which rows a <leaderboard> shows: page counts from 1, rows at a time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Page {
+    pub rows: usize,
+    pub page: usize,
+}
+
+impl Page {
+    // The indices of this page's rows, which may run past the end of the board
+    pub fn range(self) -> Range<usize> {
+        let start = (self.page - 1).saturating_mul(self.rows);
+        start..start.saturating_add(self.rows)
+    }
+
+    pub fn count(self, total: usize) -> usize {
+        total.div_ceil(self.rows).max(1)
+    }
+}
+
+// Where a leaderboard goes and what it's drawn in
+pub struct Layout<'a> {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+    pub family: Option<&'a str>,
+    // Names and amounts
+    pub text: Rgb<u8>,
+    // Ranks and the page arrows
+    pub accent: Rgb<u8>,
+    // The page number
+    pub muted: Rgb<u8>,
+    // Every other row is striped in this, when set
+    pub stripe: Option<Rgb<u8>>,
+}
+
+// Draws a page of the leaderboard as rows of rank, pfp, username and amount, with a
+// footer of "page/pages" between arrows to the pages before and after. The box is
+// split into a band per row plus the footer; names too long for their row are cut
+// short. Rows without an fid, or whose pfp couldn't be fetched, get a gray circle.
+pub fn draw_leaderboard(
+    img: &mut RgbImage,
+    rows: &[LeaderboardRow],
+    avatars: &HashMap<u64, &RgbImage>,
+    fonts: &Fonts,
+    page: Page,
+    layout: &Layout,
+) {
+    let band = layout.height / (page.rows as i64 + 1);
+    if band <= 0 || layout.width <= 0 {
+        return;
+    }
+    let size = band as f64 * 0.3;
+    let pad = band / 4;
+    let family = layout.family;
+    // Ranks line up in a column wide enough for the longest
+    let rank_width = fonts.text_width("#000", size, family).ceil() as i64;
+    let avatar = band * 7 / 10;
+    let right = layout.x + layout.width - pad;
+
+    let shown = rows.get(page.range().start..).unwrap_or_default();
+    for (i, row) in shown.iter().take(page.rows).enumerate() {
+        let top = layout.y + i as i64 * band;
+        if let (Some(stripe), 0) = (layout.stripe, i % 2) {
+            fill_rect(img, layout.x, top, layout.width, band, stripe);
+        }
+        // y is the text's baseline, a little below the band's middle
+        let baseline = top as f64 + band as f64 / 2.0 + size * 0.35;
+        let mut left = layout.x + pad;
+        let rank = format!("#{}", row.rank);
+        fonts.draw_text(
+            img,
+            &rank,
+            left as f64,
+            baseline,
+            size,
+            family,
+            layout.accent,
+        );
+        left += rank_width + pad;
+
+        let avatar_top = top + (band - avatar) / 2;
+        match row.fid.and_then(|fid| avatars.get(&fid)) {
+            Some(pfp) => draw_image(
+                img,
+                pfp,
+                left,
+                avatar_top,
+                avatar as u32,
+                avatar as u32,
+                true,
+            ),
+            None => {
+                let r = avatar as f64 / 2.0;
+                let (cx, cy) = (left as f64 + r, avatar_top as f64 + r);
+                fill_circle(img, cx, cy, r, AVATAR_PLACEHOLDER);
+            }
+        }
+        left += avatar + pad;
+
+        let amount = format_amount(row.amount);
+        let amount_width = fonts.text_width(&amount, size, family);
+        let amount_left = right as f64 - amount_width;
+        fonts.draw_text(
+            img,
+            &amount,
+            amount_left,
+            baseline,
+            size,
+            family,
+            layout.text,
+        );
+        let room = amount_left - pad as f64 - left as f64;
+        let name = truncate_to_width(fonts, &row.username, size, family, room);
+        fonts.draw_text(img, &name, left as f64, baseline, size, family, layout.text);
+    }
+
+    // The footer, with arrows only where there's a page to go to
+    let top = layout.y + page.rows as i64 * band;
+    let pages = page.count(rows.len());
+    let label = format!("{}/{}", page.page, pages);
+    let baseline = top as f64 + band as f64 / 2.0 + size * 0.35;
+    let label_left =
+        layout.x as f64 + (layout.width as f64 - fonts.text_width(&label, size, family)) / 2.0;
+    fonts.draw_text(
+        img,
+        &label,
+        label_left.floor(),
+        baseline,
+        size,
+        family,
+        layout.muted,
+    );
+    let (arrow_width, arrow_height) = (band * 2 / 5, band / 2);
+    let arrow_top = top + (band - arrow_height) / 2;
+    if page.page > 1 {
+        draw_arrow(
+            img,
+            layout.x + pad,
+            arrow_top,
+            arrow_width,
+            arrow_height,
+            true,
+            layout.accent,
+        );
+    }
+    if page.page < pages {
+        let left = right - arrow_width;
+        draw_arrow(
+            img,
+            left,
+            arrow_top,
+            arrow_width,
+            arrow_height,
+            false,
+            layout.accent,
+        );
+    }
+}
+
+// The longest start of text, ending in "...", that fits in width; all of it if it
+// fits, and nothing if not even its first character does
+pub fn truncate_to_width(
+    fonts: &Fonts,
+    text: &str,
+    size: f64,
+    family: Option<&str>,
+    width: f64,
+) -> String {
+    if fonts.text_width(text, size, family) <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.to_string();
+    while cut.pop().is_some() && !cut.trim_end().is_empty() {
+        let shortened = format!("{}{}", cut.trim_end(), ELLIPSIS);
+        if fonts.text_width(&shortened, size, family) <= width {
+            return shortened;
+        }
+    }
+    String::new()
+}
+
+// A triangle in the width by height box, pointing left or right
+fn draw_arrow(
+    img: &mut RgbImage,
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+    left: bool,
+    color: Rgb<u8>,
+) {
+    for column in 0..width {
+        // How far the column is from the point, as a share of the arrow's width
+        let from_point = if left { column } else { width - 1 - column };
+        let half = (height as f64 / 2.0 * (from_point + 1) as f64 / width as f64).round() as i64;
+        let middle = y + height / 2;
+        fill_rect(img, x + column, middle - half, 1, half * 2, color);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::images::fill_rect;
+use image::{Rgb, RgbImage};
+
+// Versions 1 to 10 at level M hold up to 213 bytes, far more than an address or a
+// payment URI needs, and stay coarse enough to scan off a phone-sized frame
+const MAX_VERSION: usize = 10;
+pub const MAX_QR_BYTES: usize = 213;
+
+// Per version at error correction level M: the error correction codewords of each
+// block, and how many blocks the codewords are split into
+const ECC_PER_BLOCK: [usize; MAX_VERSION + 1] = [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+const BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+
+// Level M's two format bits
+const LEVEL_M: u32 = 0b00;
+
+// Scanners need this many light modules around the code
+pub const QUIET_ZONE: usize = 4;
+
+// A QR code's modules, dark or light, row by row
+#[derive(Clone, Debug, PartialEq)]
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    // Data as bytes at error correction level M, in the smallest version it fits;
+    // None for more than MAX_QR_BYTES
+    pub fn encode(data: &[u8]) -> Option<Self> {
+        let version = (1..=MAX_VERSION).find(|&version| {
+            let count_bits = if version < 10 { 8 } else { 16 };
+            4 + count_bits + data.len() * 8 <= data_codewords(version) * 8
+        })?;
+        let mut code = Grid::new(version);
+        code.draw_function_patterns();
+        code.draw_codewords(&add_error_correction(version, &codewords(version, data)));
+
+        // The mask leaving the fewest patterns a scanner could trip over
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut masked = code.clone();
+                masked.apply_mask(mask);
+                masked.draw_format_bits(mask);
+                masked.penalty()
+            })
+            .unwrap_or(0);
+        code.apply_mask(mask);
+        code.draw_format_bits(mask);
+        Some(QrCode {
+            size: code.size,
+            modules: code.modules,
+        })
+    }
+
+    // Modules per side, not counting the quiet zone
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn dark(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+}
+
+// Draws a code with its quiet zone into the width-pixel square at (x, y), each module
+// the same whole number of pixels and the code centered in what's left over
+pub fn draw_qr(
+    img: &mut RgbImage,
+    code: &QrCode,
+    x: i64,
+    y: i64,
+    width: i64,
+    dark: Rgb<u8>,
+    light: Rgb<u8>,
+) {
+    fill_rect(img, x, y, width, width, light);
+    let modules = (code.size() + 2 * QUIET_ZONE) as i64;
+    let scale = (width / modules).max(1);
+    let offset = (width - code.size() as i64 * scale) / 2;
+    for my in 0..code.size() {
+        for mx in 0..code.size() {
+            if code.dark(mx, my) {
+                let (px, py) = (mx as i64 * scale, my as i64 * scale);
+                fill_rect(img, x + offset + px, y + offset + py, scale, scale, dark);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Grid {
+    version: usize,
+    size: usize,
+    modules: Vec<bool>,
+    // Finder, timing, alignment, format and version modules, which masks leave alone
+    function: Vec<bool>,
+}
+
+impl Grid {
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        Grid {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        }
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        for (x, y) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i64..=4 {
+                for dx in -4i64..=4 {
+                    let (xx, yy) = (x as i64 + dx, y as i64 + dy);
+                    if (0..size as i64).contains(&xx) && (0..size as i64).contains(&yy) {
+                        let ring = dx.abs().max(dy.abs());
+                        self.set_function(xx as usize, yy as usize, ring != 2 && ring != 4);
+                    }
+                }
+            }
+        }
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                // The finder patterns already take these corners
+                if (i == 0 && (j == 0 || j == last)) || (i == last && j == 0) {
+                    continue;
+                }
+                for dy in -2i64..=2 {
+                    for dx in -2i64..=2 {
+                        let dark = dx.abs().max(dy.abs()) != 1;
+                        self.set_function((x as i64 + dx) as usize, (y as i64 + dy) as usize, dark);
+                    }
+                }
+            }
+        }
+        // Reserved now and drawn for real once the mask is picked
+        self.draw_format_bits(0);
+        if self.version >= 7 {
+            let mut remainder = self.version as u32;
+            for _ in 0..12 {
+                remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1F25);
+            }
+            let bits = (self.version as u32) << 12 | remainder;
+            for i in 0..18 {
+                let dark = (bits >> i) & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u32) {
+        let size = self.size;
+        let data = LEVEL_M << 3 | mask;
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 == 1;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    // Fills the modules left over in two-column zigzags from the bottom right
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        while right >= 1 {
+            // The vertical timing pattern has a column to itself
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward {
+                        size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if !self.function[y * size + x] && i < codewords.len() * 8 {
+                        self.modules[y * size + x] = (codewords[i >> 3] >> (7 - (i & 7))) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                if invert && !self.function[y * self.size + x] {
+                    self.modules[y * self.size + x] ^= true;
+                }
+            }
+        }
+    }
+
+    // The spec's penalty for long runs, 2x2 blocks, finder look-alikes and imbalance
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let at = |x: usize, y: usize| self.modules[y * size + x];
+        let mut penalty = 0;
+        for transposed in [false, true] {
+            let line = |a: usize| -> Vec<bool> {
+                (0..size)
+                    .map(|b| if transposed { at(a, b) } else { at(b, a) })
+                    .collect()
+            };
+            for a in 0..size {
+                let line = line(a);
+                let mut run = 1;
+                for b in 1..=size {
+                    if b < size && line[b] == line[b - 1] {
+                        run += 1;
+                        continue;
+                    }
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+                let finder = [true, false, true, true, true, false, true];
+                for start in 0..size.saturating_sub(6) {
+                    if line[start..start + 7] != finder {
+                        continue;
+                    }
+                    let light = |from: usize, to: usize| (from..to).all(|i| !line[i]);
+                    let before = start >= 4 && light(start - 4, start);
+                    let after = start + 11 <= size && light(start + 7, start + 11);
+                    if before || after {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = at(x, y);
+                if at(x + 1, y) == color && at(x, y + 1) == color && at(x + 1, y + 1) == color {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|dark| **dark).count();
+        let percent = dark * 100 / (size * size);
+        penalty + percent.abs_diff(50) / 5 * 10
+    }
+}
+
+// Centers of the alignment patterns along each axis
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let last = version * 4 + 10;
+    let mut positions: Vec<usize> = (0..count - 1).map(|i| last - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+// Modules left for data and error correction once the function patterns are drawn
+fn raw_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let alignments = version / 7 + 2;
+        modules -= (25 * alignments - 10) * alignments - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_modules(version) / 8 - ECC_PER_BLOCK[version] * BLOCKS[version]
+}
+
+// Byte mode: the mode, the length, the data, then padding to fill the version
+fn codewords(version: usize, data: &[u8]) -> Vec<u8> {
+    let mut bits: Vec<bool> = Vec::new();
+    let mut push = |value: usize, len: usize| {
+        bits.extend((0..len).rev().map(|i| (value >> i) & 1 == 1));
+    };
+    push(0b0100, 4);
+    push(data.len(), if version < 10 { 8 } else { 16 });
+    for &byte in data {
+        push(byte as usize, 8);
+    }
+    let capacity = data_codewords(version) * 8;
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    bits.extend(std::iter::repeat_n(false, (8 - bits.len() % 8) % 8));
+    let mut codewords: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, bit| acc << 1 | *bit as u8))
+        .collect();
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() == data_codewords(version) {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+// Splits the data into the version's blocks, adds each one's error correction, and
+// interleaves them the way they're read back
+fn add_error_correction(version: usize, data: &[u8]) -> Vec<u8> {
+    let (blocks, ecc_len) = (BLOCKS[version], ECC_PER_BLOCK[version]);
+    let raw_codewords = raw_modules(version) / 8;
+    let short_blocks = blocks - raw_codewords % blocks;
+    let short_len = raw_codewords / blocks;
+    let divisor = reed_solomon_divisor(ecc_len);
+
+    let mut split: Vec<Vec<u8>> = Vec::with_capacity(blocks);
+    let mut from = 0;
+    for i in 0..blocks {
+        let len = short_len - ecc_len + usize::from(i >= short_blocks);
+        let block = &data[from..from + len];
+        from += len;
+        let mut codewords = block.to_vec();
+        // Short blocks get a placeholder so every block lines up when interleaving
+        if i < short_blocks {
+            codewords.push(0);
+        }
+        codewords.extend(reed_solomon_remainder(block, &divisor));
+        split.push(codewords);
+    }
+    let mut interleaved = Vec::with_capacity(raw_codewords);
+    for i in 0..split[0].len() {
+        for (j, block) in split.iter().enumerate() {
+            if i != short_len - ecc_len || j >= short_blocks {
+                interleaved.push(block[i]);
+            }
+        }
+    }
+    interleaved
+}
+
+pub fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut divisor = vec![0; degree];
+    divisor[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            divisor[j] = gf_multiply(divisor[j], root);
+            if j + 1 < degree {
+                divisor[j] ^= divisor[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    divisor
+}
+
+pub fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut remainder = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ remainder.remove(0);
+        remainder.push(0);
+        for (r, &coefficient) in remainder.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(coefficient, factor);
+        }
+    }
+    remainder
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u16 >> i) & 1) * x as u16;
+    }
+    z as u8
+}
@@ -0,0 +1,547 @@
+use crate::errors::ImageError;
+use crate::images::font::Fonts;
+use crate::images::leaderboard::{self, Layout, Page, MAX_PAGE_ROWS};
+use crate::images::qr::{self, QrCode};
+use crate::images::{chart, fill_rect, AssetImages, LiveData};
+use crate::price::PriceRange;
+use crate::profiles::AVATAR_SIZE;
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+// Larger than any frame image needs, and small enough to render quickly
+pub const MAX_SIZE: u32 = 2048;
+
+// What's left where nothing is drawn
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+
+// The most steps a <progress> bar splits into
+const MAX_STEPS: usize = 10;
+
+// Animated templates are served as GIFs of up to this many frames
+pub const MAX_FRAMES: usize = 30;
+const DEFAULT_FRAME_DELAY_MS: u32 = 500;
+
+// <image href="avatar"> draws the viewer's pfp rather than an asset
+pub const AVATAR_HREF: &str = "avatar";
+pub const AVATAR_PLACEHOLDER: Rgb<u8> = Rgb([128, 128, 128]);
+
+static ROOT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<svg\b([^>]*)>").unwrap());
+static ELEMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?s)<(rect|circle|image|chart|qr|progress|leaderboard)\b([^>]*?)/?>|<text\b([^>]*)>(.*?)</text>",
+    )
+    .unwrap()
+});
+static ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([\w:-]+)\s*=\s*"([^"]*)""#).unwrap());
+
+// Draws the subset of SVG frame templates use: an <svg> with a width and height, and
+// <rect>, <circle>, <image> and <text> in document order. Anything else, like <g>, is
+// skipped, so templates can keep the wrappers editors add. An <image> href names a
+// file under assets/, or "avatar" for the viewer's pfp, and one left empty by a
+// placeholder is skipped. A <chart> (ours, not SVG's) draws the price history over its
+// range, "24h" or "7d", as a line in its stroke color. Text is drawn at font-size in
+// the fonts from FONT_PATHS, font-family first, else the built-in bitmap font. A <qr>
+// (ours too) draws its data, e.g. an address, as a QR code in a size-pixel square,
+// fill on background; one left empty by a placeholder is skipped. A <progress> (ours)
+// draws a flow's steps as a row of segments across its box, the first step of them in
+// fill and the rest in background; it's skipped outside a flow, when steps is empty.
+// A <leaderboard> (ours) draws page of the LEADERBOARD_URL rows, rows at a time, in
+// fill, with ranks and page arrows in accent, the page number in muted and every
+// other row striped in background if set; it's skipped when there's no leaderboard.
+pub fn rasterize(
+    svg: &str,
+    assets: &AssetImages,
+    fonts: &Fonts,
+    live: &LiveData,
+) -> Result<RgbImage, ImageError> {
+    rasterize_frame(svg, assets, fonts, live, 0)
+}
+
+// How many frames a template animates through and how long each shows, from
+// <svg frames="10" frame-delay="1000">; a template without frames is a still image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Animation {
+    pub frames: usize,
+    pub delay_ms: u32,
+}
+
+pub fn animation(svg: &str) -> Result<Animation, ImageError> {
+    let root = attributes(&root(svg)?[1]);
+    let frames = root.get("frames").map_or(Ok(1.0), |n| parse(n, "frames"))?;
+    if frames.fract() != 0.0 || !(1.0..=MAX_FRAMES as f64).contains(&frames) {
+        return Err(ImageError::Svg(format!(
+            "frames must be a whole number from 1 to {}",
+            MAX_FRAMES
+        )));
+    }
+    let delay_ms = root
+        .get("frame-delay")
+        .map_or(Ok(DEFAULT_FRAME_DELAY_MS as f64), |ms| {
+            parse(ms, "frame-delay")
+        })?;
+    Ok(Animation {
+        frames: frames as usize,
+        delay_ms: delay_ms.max(0.0) as u32,
+    })
+}
+
+// One frame of an animated template, counting from 0: elements with frame="2" or
+// frame="0-3" are only drawn in those frames, the rest in all of them
+pub fn rasterize_frame(
+    svg: &str,
+    assets: &AssetImages,
+    fonts: &Fonts,
+    live: &LiveData,
+    frame: usize,
+) -> Result<RgbImage, ImageError> {
+    let frames = animation(svg)?.frames;
+    let root = attributes(&root(svg)?[1]);
+    let width = number(&root, "width")? as u32;
+    let height = number(&root, "height")? as u32;
+    if !(1..=MAX_SIZE).contains(&width) || !(1..=MAX_SIZE).contains(&height) {
+        return Err(ImageError::Svg(format!(
+            "size {}x{} must be 1 to {} pixels a side",
+            width, height, MAX_SIZE
+        )));
+    }
+
+    let mut img = RgbImage::from_pixel(width, height, BACKGROUND);
+    for element in ELEMENT.captures_iter(svg) {
+        let tag = element
+            .get(2)
+            .or(element.get(3))
+            .map_or("", |tag| tag.as_str());
+        if !shown_in(&attributes(tag), frame, frames)? {
+            continue;
+        }
+        match element.get(1).map(|tag| tag.as_str()) {
+            Some("rect") => {
+                let attrs = attributes(&element[2]);
+                let Some(color) = fill(&attrs)? else { continue };
+                fill_rect(
+                    &mut img,
+                    optional(&attrs, "x")? as i64,
+                    optional(&attrs, "y")? as i64,
+                    number(&attrs, "width")? as i64,
+                    number(&attrs, "height")? as i64,
+                    color,
+                );
+            }
+            Some("image") => {
+                let attrs = attributes(&element[2]);
+                let href = attrs.get("href").or_else(|| attrs.get("xlink:href"));
+                let Some(href) = href
+                    .map(|href| unescape(href.trim()))
+                    .filter(|h| !h.is_empty())
+                else {
+                    continue;
+                };
+                let (x, y) = (optional(&attrs, "x")? as i64, optional(&attrs, "y")? as i64);
+                let size = |name: &str, natural: u32| {
+                    attrs
+                        .get(name)
+                        .map_or(Ok(natural as f64), |value| parse(value, name))
+                        .map(|value| value as u32)
+                };
+                // The viewer's pfp, cropped to a circle, or a gray one when they have none
+                if href == AVATAR_HREF {
+                    let (width, height) =
+                        (size("width", AVATAR_SIZE)?, size("height", AVATAR_SIZE)?);
+                    let (rx, ry) = (width as f64 / 2.0, height as f64 / 2.0);
+                    match live.avatar {
+                        Some(avatar) => draw_image(&mut img, avatar, x, y, width, height, true),
+                        None => fill_circle(
+                            &mut img,
+                            x as f64 + rx,
+                            y as f64 + ry,
+                            rx.min(ry),
+                            AVATAR_PLACEHOLDER,
+                        ),
+                    }
+                    continue;
+                }
+                let asset = assets.get(&href)?;
+                // Stretched to width and height when given, as with preserveAspectRatio="none"
+                let width = size("width", asset.width())?;
+                let height = size("height", asset.height())?;
+                draw_image(&mut img, &asset, x, y, width, height, false);
+            }
+            Some("chart") => {
+                let attrs = attributes(&element[2]);
+                let range = attrs.get("range").map_or("24h", String::as_str);
+                let range = PriceRange::parse(range)
+                    .ok_or_else(|| ImageError::Svg(format!("unknown chart range {:?}", range)))?;
+                let stroke = match attrs.get("stroke") {
+                    Some(stroke) => color_from(stroke.trim()).ok_or_else(|| {
+                        ImageError::Svg(format!("unsupported color {:?}", stroke))
+                    })?,
+                    None => Rgb([0, 0, 0]),
+                };
+                // Unlike shapes, a chart is only a line unless it asks for a fill
+                let area = match attrs.get("fill") {
+                    Some(_) => fill(&attrs)?,
+                    None => None,
+                };
+                let stroke_width = attrs
+                    .get("stroke-width")
+                    .map_or(Ok(2.0), |width| parse(width, "stroke-width"))?;
+                // No price feed, no chart; the rest of the image still makes sense
+                let Some(prices) = live.prices.get(&range) else {
+                    continue;
+                };
+                chart::draw_line_chart(
+                    &mut img,
+                    prices,
+                    optional(&attrs, "x")? as i64,
+                    optional(&attrs, "y")? as i64,
+                    number(&attrs, "width")? as i64,
+                    number(&attrs, "height")? as i64,
+                    stroke,
+                    stroke_width as i64,
+                    area,
+                );
+            }
+            Some("qr") => {
+                let attrs = attributes(&element[2]);
+                let data = attrs.get("data").map(|data| unescape(data.trim()));
+                let Some(data) = data.filter(|data| !data.is_empty()) else {
+                    continue;
+                };
+                let code = QrCode::encode(data.as_bytes()).ok_or_else(|| {
+                    ImageError::Svg(format!("qr data is over {} bytes", qr::MAX_QR_BYTES))
+                })?;
+                let dark = fill(&attrs)?.unwrap_or(Rgb([0, 0, 0]));
+                let light = match attrs.get("background") {
+                    Some(color) => color_from(color.trim())
+                        .ok_or_else(|| ImageError::Svg(format!("unsupported color {:?}", color)))?,
+                    None => Rgb([255, 255, 255]),
+                };
+                qr::draw_qr(
+                    &mut img,
+                    &code,
+                    optional(&attrs, "x")? as i64,
+                    optional(&attrs, "y")? as i64,
+                    number(&attrs, "size")? as i64,
+                    dark,
+                    light,
+                );
+            }
+            Some("progress") => {
+                let attrs = attributes(&element[2]);
+                let count = |name: &str| match attrs.get(name).map(|n| n.trim()) {
+                    None | Some("") => Ok(None),
+                    Some(n) => n
+                        .parse::<usize>()
+                        .map(Some)
+                        .map_err(|_| ImageError::Svg(format!("{} is not a count: {:?}", name, n))),
+                };
+                let (Some(step), Some(steps)) = (count("step")?, count("steps")?) else {
+                    continue;
+                };
+                if steps == 0 || steps > MAX_STEPS || step > steps {
+                    return Err(ImageError::Svg(format!(
+                        "progress must be a step of 1 to {} steps",
+                        MAX_STEPS
+                    )));
+                }
+                let done = fill(&attrs)?.unwrap_or(Rgb([0, 0, 0]));
+                let to_do = match attrs.get("background") {
+                    Some(color) => color_from(color.trim())
+                        .ok_or_else(|| ImageError::Svg(format!("unsupported color {:?}", color)))?,
+                    None => Rgb([128, 128, 128]),
+                };
+                let (x, y) = (optional(&attrs, "x")? as i64, optional(&attrs, "y")? as i64);
+                let (width, height) = (
+                    number(&attrs, "width")? as i64,
+                    number(&attrs, "height")? as i64,
+                );
+                // Segments split the width, a gap as wide as the bar is tall between each
+                let gap = height.min(width / steps as i64 / 4);
+                let segment = (width - gap * (steps as i64 - 1)) / steps as i64;
+                for i in 0..steps {
+                    let color = if i < step { done } else { to_do };
+                    let left = x + i as i64 * (segment + gap);
+                    fill_rect(&mut img, left, y, segment, height, color);
+                }
+            }
+            Some("leaderboard") => {
+                let attrs = attributes(&element[2]);
+                let page = leaderboard_page(&attrs)?;
+                let color = |name: &str, default: Rgb<u8>| match attrs.get(name) {
+                    Some(color) => color_from(color.trim())
+                        .ok_or_else(|| ImageError::Svg(format!("unsupported color {:?}", color))),
+                    None => Ok(default),
+                };
+                let layout = Layout {
+                    x: optional(&attrs, "x")? as i64,
+                    y: optional(&attrs, "y")? as i64,
+                    width: number(&attrs, "width")? as i64,
+                    height: number(&attrs, "height")? as i64,
+                    family: attrs.get("font-family").map(String::as_str),
+                    text: fill(&attrs)?.unwrap_or(Rgb([0, 0, 0])),
+                    accent: color("accent", Rgb([0, 0, 0]))?,
+                    muted: color("muted", Rgb([128, 128, 128]))?,
+                    stripe: attrs
+                        .contains_key("background")
+                        .then(|| color("background", BACKGROUND))
+                        .transpose()?,
+                };
+                let Some(rows) = &live.leaderboard else {
+                    continue;
+                };
+                leaderboard::draw_leaderboard(
+                    &mut img,
+                    rows,
+                    &live.leaderboard_avatars,
+                    fonts,
+                    page,
+                    &layout,
+                );
+            }
+            Some(_) => {
+                let attrs = attributes(&element[2]);
+                let Some(color) = fill(&attrs)? else { continue };
+                fill_circle(
+                    &mut img,
+                    optional(&attrs, "cx")?,
+                    optional(&attrs, "cy")?,
+                    number(&attrs, "r")?,
+                    color,
+                );
+            }
+            None => {
+                let attrs = attributes(&element[3]);
+                let Some(color) = fill(&attrs)? else { continue };
+                let text = unescape(element[4].trim());
+                let size = attrs
+                    .get("font-size")
+                    .map_or(Ok(16.0), |size| parse(size, "font-size"))?;
+                let family = attrs.get("font-family").map(String::as_str);
+                let width = || fonts.text_width(&text, size, family);
+                let shift = match attrs.get("text-anchor").map(String::as_str) {
+                    Some("middle") => (width() / 2.0).floor(),
+                    Some("end") => width(),
+                    _ => 0.0,
+                };
+                // y is the baseline, as in SVG
+                let (x, y) = (optional(&attrs, "x")?, optional(&attrs, "y")?);
+                fonts.draw_text(&mut img, &text, x - shift, y, size, family, color);
+            }
+        }
+    }
+    Ok(img)
+}
+
+// Draws src scaled to width by height at (x, y), clipped to the image and, for
+// avatars, to the circle inside that box
+pub fn draw_image(
+    img: &mut RgbImage,
+    src: &RgbImage,
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+    circle: bool,
+) {
+    if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {
+        return;
+    }
+    let resized;
+    let src = if src.dimensions() == (width, height) {
+        src
+    } else {
+        resized = imageops::resize(src, width, height, FilterType::Triangle);
+        &resized
+    };
+    let (rx, ry) = (width as f64 / 2.0, height as f64 / 2.0);
+    for (sx, sy, pixel) in src.enumerate_pixels() {
+        let (dx, dy) = ((sx as f64 + 0.5 - rx) / rx, (sy as f64 + 0.5 - ry) / ry);
+        if circle && dx * dx + dy * dy > 1.0 {
+            continue;
+        }
+        let (px, py) = (x + sx as i64, y + sy as i64);
+        if px >= 0 && py >= 0 && px < img.width() as i64 && py < img.height() as i64 {
+            img.put_pixel(px as u32, py as u32, *pixel);
+        }
+    }
+}
+
+// The rows a template's first <leaderboard> shows, whose pfps have to be fetched
+// before drawing it; None when it has none
+pub fn leaderboard_rows(svg: &str) -> Result<Option<Page>, ImageError> {
+    let first = ELEMENT.captures_iter(svg).find(|element| {
+        element
+            .get(1)
+            .is_some_and(|tag| tag.as_str() == "leaderboard")
+    });
+    first
+        .map(|element| leaderboard_page(&attributes(&element[2])))
+        .transpose()
+}
+
+// A <leaderboard>'s rows a page, 5 unless set, and its page, the first when left empty
+fn leaderboard_page(attrs: &HashMap<String, String>) -> Result<Page, ImageError> {
+    let count = |name: &str, default: usize| match attrs.get(name).map(|n| n.trim()) {
+        None | Some("") => Ok(default),
+        Some(n) => n
+            .parse::<usize>()
+            .map_err(|_| ImageError::Svg(format!("{} is not a count: {:?}", name, n))),
+    };
+    let (rows, page) = (count("rows", 5)?, count("page", 1)?);
+    if !(1..=MAX_PAGE_ROWS).contains(&rows) || page == 0 {
+        return Err(ImageError::Svg(format!(
+            "a leaderboard shows 1 to {} rows a page, from page 1",
+            MAX_PAGE_ROWS
+        )));
+    }
+    Ok(Page { rows, page })
+}
+
+// The price ranges a template charts, which have to be fetched before drawing it
+pub fn chart_ranges(svg: &str) -> Vec<PriceRange> {
+    let mut ranges = Vec::new();
+    for element in ELEMENT.captures_iter(svg) {
+        if element.get(1).is_none_or(|tag| tag.as_str() != "chart") {
+            continue;
+        }
+        let attrs = attributes(&element[2]);
+        let range = PriceRange::parse(attrs.get("range").map_or("24h", String::as_str));
+        if let Some(range) = range.filter(|range| !ranges.contains(range)) {
+            ranges.push(range);
+        }
+    }
+    ranges
+}
+
+pub fn fill_circle(img: &mut RgbImage, cx: f64, cy: f64, r: f64, color: Rgb<u8>) {
+    let (top, bottom) = ((cy - r).floor() as i64, (cy + r).ceil() as i64);
+    for y in top..=bottom {
+        let dy = y as f64 + 0.5 - cy;
+        if dy.abs() > r {
+            continue;
+        }
+        let half = (r * r - dy * dy).sqrt();
+        let left = (cx - half).round() as i64;
+        let right = (cx + half).round() as i64;
+        fill_rect(img, left, y, right - left, 1, color);
+    }
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    ATTRIBUTE
+        .captures_iter(tag)
+        .map(|attr| (attr[1].to_string(), attr[2].to_string()))
+        .collect()
+}
+
+fn root(svg: &str) -> Result<regex::Captures<'_>, ImageError> {
+    ROOT.captures(svg)
+        .ok_or_else(|| ImageError::Svg("no <svg> element".to_string()))
+}
+
+// Whether an element's frame attribute, a frame or a range of them, includes this one
+fn shown_in(
+    attrs: &HashMap<String, String>,
+    frame: usize,
+    frames: usize,
+) -> Result<bool, ImageError> {
+    let Some(range) = attrs.get("frame") else {
+        return Ok(true);
+    };
+    let (first, last) = range.split_once('-').unwrap_or((range, range));
+    let bound = |value: &str| {
+        value
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n < frames)
+            .ok_or_else(|| {
+                ImageError::Svg(format!(
+                    "frame {:?} must be frames from 0 to {}",
+                    range,
+                    frames - 1
+                ))
+            })
+    };
+    let (first, last) = (bound(first)?, bound(last)?);
+    Ok((first..=last).contains(&frame))
+}
+
+fn number(attrs: &HashMap<String, String>, name: &str) -> Result<f64, ImageError> {
+    let value = attrs
+        .get(name)
+        .ok_or_else(|| ImageError::Svg(format!("missing {}", name)))?;
+    parse(value, name)
+}
+
+// Positions default to 0, as in SVG
+fn optional(attrs: &HashMap<String, String>, name: &str) -> Result<f64, ImageError> {
+    attrs.get(name).map_or(Ok(0.0), |value| parse(value, name))
+}
+
+fn parse(value: &str, name: &str) -> Result<f64, ImageError> {
+    value
+        .trim()
+        .trim_end_matches("px")
+        .parse()
+        .ok()
+        .filter(|n: &f64| n.is_finite())
+        .ok_or_else(|| ImageError::Svg(format!("{} is not a number: {:?}", name, value)))
+}
+
+// None for fill="none", or a fill placeholder left empty; SVG fills with black
+// when unset
+fn fill(attrs: &HashMap<String, String>) -> Result<Option<Rgb<u8>>, ImageError> {
+    match attrs.get("fill").map(|fill| fill.trim()) {
+        None => Ok(Some(Rgb([0, 0, 0]))),
+        Some("" | "none" | "transparent") => Ok(None),
+        Some(color) => color_from(color)
+            .map(Some)
+            .ok_or_else(|| ImageError::Svg(format!("unsupported color {:?}", color))),
+    }
+}
+
+// #rgb, #rrggbb or a basic color name
+pub fn color_from(color: &str) -> Option<Rgb<u8>> {
+    let named = match color.to_ascii_lowercase().as_str() {
+        "black" => Some([0, 0, 0]),
+        "white" => Some([255, 255, 255]),
+        "red" => Some([255, 0, 0]),
+        "green" => Some([0, 128, 0]),
+        "blue" => Some([0, 0, 255]),
+        "gray" | "grey" => Some([128, 128, 128]),
+        _ => None,
+    };
+    if let Some(rgb) = named {
+        return Some(Rgb(rgb));
+    }
+    let hex = color.strip_prefix('#')?;
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+        3 => {
+            let mut rgb = [0; 3];
+            for (i, digit) in hex.char_indices() {
+                rgb[i] = channel(&digit.to_string())? * 17;
+            }
+            Some(Rgb(rgb))
+        }
+        6 if hex.is_ascii() => Some(Rgb([
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        ])),
+        _ => None,
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
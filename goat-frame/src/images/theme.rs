@@ -0,0 +1,148 @@
+use crate::config::Config;
+use crate::errors::ConfigError;
+use crate::images::svg;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+// Templates draw in a theme's colors through "{{theme_<color>}}", e.g.
+// fill="{{theme_background}}"
+pub const COLOR_PREFIX: &str = "theme_";
+
+// The colors every theme has unless THEME_DARK_COLORS or THEME_LIGHT_COLORS change them
+const DARK_COLORS: [(&str, &str); 5] = [
+    ("background", "#181818"),
+    ("text", "#ffffff"),
+    ("muted", "#bbbbbb"),
+    ("accent", "#a78bfa"),
+    ("banner", "#c82020"),
+];
+const LIGHT_COLORS: [(&str, &str); 5] = [
+    ("background", "#f4f4f4"),
+    ("text", "#181818"),
+    ("muted", "#555555"),
+    ("accent", "#6d28d9"),
+    ("banner", "#c82020"),
+];
+
+// How a viewer likes their images, picked with a button and kept in their frame state
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Theme::Dark),
+            "light" => Some(Theme::Light),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+}
+
+// Each theme's colors by name, and the theme viewers who haven't picked one see
+#[derive(Clone, Debug)]
+pub struct Themes {
+    dark: BTreeMap<String, String>,
+    light: BTreeMap<String, String>,
+    default: Theme,
+}
+
+impl Default for Themes {
+    fn default() -> Self {
+        let colors = |defaults: [(&str, &str); 5]| {
+            defaults
+                .into_iter()
+                .map(|(name, color)| (name.to_string(), color.to_string()))
+                .collect()
+        };
+        Themes {
+            dark: colors(DARK_COLORS),
+            light: colors(LIGHT_COLORS),
+            default: Theme::default(),
+        }
+    }
+}
+
+impl Themes {
+    // The built-in colors with the config's "name=#rrggbb" entries over them. A color
+    // only one theme has would leave the other's templates without it, so both need it.
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut themes = Themes {
+            default: config.default_theme,
+            ..Themes::default()
+        };
+        for (var, entries, colors) in [
+            (
+                "THEME_DARK_COLORS",
+                &config.theme_dark_colors,
+                &mut themes.dark,
+            ),
+            (
+                "THEME_LIGHT_COLORS",
+                &config.theme_light_colors,
+                &mut themes.light,
+            ),
+        ] {
+            for entry in entries {
+                let (name, color) = entry.split_once('=').ok_or_else(|| {
+                    ConfigError::Invalid(format!("{} entry {:?} must be name=color", var, entry))
+                })?;
+                let (name, color) = (name.trim(), color.trim());
+                let valid_name = !name.is_empty()
+                    && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+                if !valid_name || svg::color_from(color).is_none() {
+                    return Err(ConfigError::Invalid(format!(
+                        "{} entry {:?} must be a name of letters, digits and _ and a color",
+                        var, entry
+                    )));
+                }
+                colors.insert(name.to_string(), color.to_string());
+            }
+        }
+        if let Some(name) = themes
+            .dark
+            .keys()
+            .find(|name| !themes.light.contains_key(*name))
+            .or_else(|| {
+                themes
+                    .light
+                    .keys()
+                    .find(|name| !themes.dark.contains_key(*name))
+            })
+        {
+            return Err(ConfigError::Invalid(format!(
+                "THEME_DARK_COLORS and THEME_LIGHT_COLORS must both set {:?}",
+                name
+            )));
+        }
+        Ok(themes)
+    }
+
+    // The colors of the theme a viewer asked for by name, else the default's
+    pub fn colors(&self, theme: Option<&str>) -> &BTreeMap<String, String> {
+        match theme.and_then(Theme::parse).unwrap_or(self.default) {
+            Theme::Dark => &self.dark,
+            Theme::Light => &self.light,
+        }
+    }
+
+    // Adds the colors of the ?theme= a template is asked for as theme_<color>, over
+    // any the query set itself
+    pub fn fill_params(&self, params: &mut HashMap<String, String>) {
+        let colors = self.colors(params.get("theme").map(String::as_str));
+        for (name, color) in colors {
+            params.insert(format!("{}{}", COLOR_PREFIX, name), color.clone());
+        }
+    }
+}
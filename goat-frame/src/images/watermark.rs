@@ -0,0 +1,77 @@
+use crate::errors::ImageError;
+use crate::images::blend_image;
+use crate::images::font::Fonts;
+use image::{Rgb, RgbImage, RgbaImage};
+use std::path::Path;
+
+// The logo is this share of the image's height, and the footer text half that
+const LOGO_HEIGHT: f64 = 1.0 / 12.0;
+const TEXT_SIZE: f64 = 1.0 / 24.0;
+const MARGIN: f64 = 1.0 / 40.0;
+
+// Text smaller than this can't be read, however small the image
+const MIN_TEXT_SIZE: f64 = 12.0;
+
+// A partner's branding stamped onto every generated image: WATERMARK_LOGO in the
+// bottom right corner, keeping its transparency, and WATERMARK_TEXT along the bottom
+// left in the theme's text color. Neither set stamps nothing.
+#[derive(Debug, Default)]
+pub struct Watermark {
+    logo: Option<RgbaImage>,
+    text: Option<String>,
+}
+
+impl Watermark {
+    // logo is a file under assets_dir, e.g. "partner/logo.png"
+    pub fn load(
+        logo: Option<&str>,
+        text: Option<&str>,
+        assets_dir: &Path,
+    ) -> Result<Self, ImageError> {
+        let logo = match logo {
+            Some(name) => {
+                if name.is_empty() || name.starts_with('/') || name.split('/').any(|p| p == "..") {
+                    return Err(ImageError::Svg(format!(
+                        "logo {:?} must be a file under assets/",
+                        name
+                    )));
+                }
+                let image = image::open(assets_dir.join(name))
+                    .map_err(|err| ImageError::Asset(name.to_string(), err))?;
+                Some(image.to_rgba8())
+            }
+            None => None,
+        };
+        Ok(Watermark {
+            logo,
+            text: text.filter(|text| !text.is_empty()).map(str::to_string),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.logo.is_none() && self.text.is_none()
+    }
+
+    // Sized to the image, so a square frame and a 1.91:1 one look alike
+    pub fn stamp(&self, img: &mut RgbImage, fonts: &Fonts, color: Rgb<u8>) {
+        let (width, height) = (img.width() as f64, img.height() as f64);
+        let margin = (height * MARGIN).round();
+        if let Some(logo) = &self.logo {
+            let logo_height = (height * LOGO_HEIGHT).round();
+            let logo_width =
+                (logo_height * logo.width() as f64 / logo.height().max(1) as f64).round();
+            blend_image(
+                img,
+                logo,
+                (width - margin - logo_width) as i64,
+                (height - margin - logo_height) as i64,
+                logo_width as u32,
+                logo_height as u32,
+            );
+        }
+        if let Some(text) = &self.text {
+            let size = (height * TEXT_SIZE).round().max(MIN_TEXT_SIZE);
+            fonts.draw_text(img, text, margin, height - margin, size, None, color);
+        }
+    }
+}
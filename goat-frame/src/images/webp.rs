@@ -0,0 +1,541 @@
+use image::RgbImage;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// Predictor modes are picked for blocks of 2^4 = 16 pixels a side
+const PREDICTOR_BITS: u32 = 4;
+
+// The predictors tried for each block: left, top, top-left, the averages of left
+// with top-left and top, of top-left with top, and Select and ClampAddSubtractFull.
+// Those that look at the top-right pixel, which wraps around at the right edge, and
+// ClampAddSubtractHalf, whose rounding differs between decoders, aren't used.
+const PREDICTORS: [u32; 8] = [1, 2, 4, 6, 7, 8, 11, 12];
+
+// Green codes are followed by 24 length prefixes; there's no color cache
+const GREEN_ALPHABET: usize = 256 + 24;
+const DISTANCE_ALPHABET: usize = 40;
+const MAX_CODE_LENGTH: u8 = 15;
+const MAX_CODE_LENGTH_CODE_LENGTH: u8 = 7;
+
+// The order code length code lengths are written in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+// Backward references copy up to this many pixels from this far back at most
+const MAX_LENGTH: usize = 4096;
+const WINDOW: usize = 1 << 16;
+const MIN_LENGTH: usize = 3;
+// How many earlier matches of a position's hash are compared before giving up
+const MAX_CHAIN: usize = 16;
+const HASH_BITS: u32 = 16;
+
+// Encodes an image as a lossless WebP (VP8L): green is subtracted from red and
+// blue, each block is predicted from its neighbours, and what's left is compressed
+// with backward references and prefix codes. Images are drawn at most svg::MAX_SIZE
+// a side, well within WebP's 16384.
+pub fn encode_webp(img: &RgbImage) -> Vec<u8> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let mut argb: Vec<u32> = img
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b] = pixel.0.map(u32::from);
+            0xff00_0000 | r << 16 | g << 8 | b
+        })
+        .collect();
+
+    let mut w = BitWriter::default();
+    w.write(0x2f, 8);
+    w.write(width as u32 - 1, 14);
+    w.write(height as u32 - 1, 14);
+    // No alpha, version 0
+    w.write(0, 1);
+    w.write(0, 3);
+
+    // Subtract green
+    w.write(1, 1);
+    w.write(2, 2);
+    for pixel in &mut argb {
+        let green = (*pixel >> 8) & 0xff;
+        let red = ((*pixel >> 16).wrapping_sub(green)) & 0xff;
+        let blue = (*pixel).wrapping_sub(green) & 0xff;
+        *pixel = (*pixel & 0xff00_ff00) | red << 16 | blue;
+    }
+
+    // Predictor, with each block's mode in the green of a smaller image
+    w.write(1, 1);
+    w.write(0, 2);
+    w.write(PREDICTOR_BITS - 2, 3);
+    let (modes, residuals) = predict(&argb, width, height);
+    let literals: Vec<Token> = modes.iter().map(|mode| Token::Literal(*mode)).collect();
+    write_image(&mut w, &literals, false);
+    w.write(0, 1);
+
+    write_image(&mut w, &backward_references(&residuals, width), true);
+    let data = w.finish();
+
+    let padding = data.len() % 2;
+    let mut webp = Vec::with_capacity(20 + data.len() + padding);
+    webp.extend_from_slice(b"RIFF");
+    webp.extend_from_slice(&((12 + data.len() + padding) as u32).to_le_bytes());
+    webp.extend_from_slice(b"WEBPVP8L");
+    webp.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    webp.extend_from_slice(&data);
+    webp.resize(webp.len() + padding, 0);
+    webp
+}
+
+// Each block's best predictor, and every pixel less its prediction
+fn predict(argb: &[u32], width: usize, height: usize) -> (Vec<u32>, Vec<u32>) {
+    let block = 1 << PREDICTOR_BITS;
+    let (blocks_x, blocks_y) = (width.div_ceil(block), height.div_ceil(block));
+    let mut modes = Vec::with_capacity(blocks_x * blocks_y);
+    let mut residuals = vec![0; argb.len()];
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let pixels = || {
+                (by * block..((by + 1) * block).min(height)).flat_map(move |y| {
+                    (bx * block..((bx + 1) * block).min(width)).map(move |x| (x, y))
+                })
+            };
+            // The mode whose residuals are closest to zero, a stand-in for their entropy
+            let cost = |mode: u32| -> u32 {
+                pixels()
+                    .map(|(x, y)| {
+                        let residual =
+                            sub_pixels(argb[y * width + x], prediction(argb, width, x, y, mode));
+                        (0..4)
+                            .map(|i| {
+                                let channel = (residual >> (i * 8)) & 0xff;
+                                channel.min(256 - channel)
+                            })
+                            .sum::<u32>()
+                    })
+                    .sum()
+            };
+            let mode = PREDICTORS
+                .into_iter()
+                .min_by_key(|mode| cost(*mode))
+                .unwrap_or(1);
+            modes.push(0xff00_0000 | mode << 8);
+            for (x, y) in pixels() {
+                let i = y * width + x;
+                residuals[i] = sub_pixels(argb[i], prediction(argb, width, x, y, mode));
+            }
+        }
+    }
+    (modes, residuals)
+}
+
+fn prediction(argb: &[u32], width: usize, x: usize, y: usize, mode: u32) -> u32 {
+    let i = y * width + x;
+    match (x, y) {
+        (0, 0) => 0xff00_0000,
+        (_, 0) => argb[i - 1],
+        (0, _) => argb[i - width],
+        _ => {
+            let (left, top, top_left) = (argb[i - 1], argb[i - width], argb[i - width - 1]);
+            match mode {
+                1 => left,
+                2 => top,
+                4 => top_left,
+                6 => average(left, top_left),
+                7 => average(left, top),
+                8 => average(top_left, top),
+                11 => select(left, top, top_left),
+                _ => clamp_add_subtract_full(left, top, top_left),
+            }
+        }
+    }
+}
+
+fn channels(pixel: u32) -> [i32; 4] {
+    [24, 16, 8, 0].map(|shift| ((pixel >> shift) & 0xff) as i32)
+}
+
+fn from_channels(channels: [i32; 4]) -> u32 {
+    channels
+        .iter()
+        .zip([24, 16, 8, 0])
+        .fold(0, |pixel, (channel, shift)| {
+            pixel | ((*channel as u32 & 0xff) << shift)
+        })
+}
+
+fn sub_pixels(a: u32, b: u32) -> u32 {
+    let (a, b) = (channels(a), channels(b));
+    from_channels([0, 1, 2, 3].map(|i| a[i] - b[i]))
+}
+
+fn average(a: u32, b: u32) -> u32 {
+    let (a, b) = (channels(a), channels(b));
+    from_channels([0, 1, 2, 3].map(|i| (a[i] + b[i]) / 2))
+}
+
+fn select(left: u32, top: u32, top_left: u32) -> u32 {
+    let (l, t, tl) = (channels(left), channels(top), channels(top_left));
+    let estimate = [0, 1, 2, 3].map(|i| l[i] + t[i] - tl[i]);
+    let distance = |p: [i32; 4]| (0..4).map(|i| (estimate[i] - p[i]).abs()).sum::<i32>();
+    if distance(l) < distance(t) {
+        left
+    } else {
+        top
+    }
+}
+
+fn clamp_add_subtract_full(left: u32, top: u32, top_left: u32) -> u32 {
+    let (l, t, tl) = (channels(left), channels(top), channels(top_left));
+    from_channels([0, 1, 2, 3].map(|i| (l[i] + t[i] - tl[i]).clamp(0, 255)))
+}
+
+enum Token {
+    Literal(u32),
+    // Copies length pixels from some way back, given as a distance code
+    Copy { length: usize, distance_code: usize },
+}
+
+// Finds runs of pixels seen shortly before, through a hash chain of pixel pairs
+fn backward_references(pixels: &[u32], width: usize) -> Vec<Token> {
+    let hash = |i: usize| {
+        let pair = (pixels[i] as u64) << 32 | pixels[i + 1] as u64;
+        (pair.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (64 - HASH_BITS)) as usize
+    };
+    // The latest position of each hash, and before each position the one before it
+    let mut heads = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; pixels.len()];
+    let insert = |i: usize, heads: &mut [usize], previous: &mut [usize]| {
+        if i + 1 < pixels.len() {
+            let h = hash(i);
+            previous[i] = heads[h];
+            heads[h] = i;
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < pixels.len() {
+        let (mut best_length, mut best_distance) = (0, 0);
+        if i + 1 < pixels.len() {
+            let mut candidate = heads[hash(i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let limit = MAX_LENGTH.min(pixels.len() - i);
+                let length = (0..limit)
+                    .find(|k| pixels[candidate + k] != pixels[i + k])
+                    .unwrap_or(limit);
+                if length > best_length {
+                    (best_length, best_distance) = (length, i - candidate);
+                }
+                // Nothing further back can copy more
+                if length == limit {
+                    break;
+                }
+                candidate = previous[candidate];
+                chain += 1;
+            }
+        }
+        if best_length >= MIN_LENGTH {
+            tokens.push(Token::Copy {
+                length: best_length,
+                distance_code: distance_code(best_distance, width),
+            });
+            for j in i..i + best_length {
+                insert(j, &mut heads, &mut previous);
+            }
+            i += best_length;
+        } else {
+            tokens.push(Token::Literal(pixels[i]));
+            insert(i, &mut heads, &mut previous);
+            i += 1;
+        }
+    }
+    tokens
+}
+
+// The pixel directly above and the one to the left have short codes of their own;
+// other distances are offset past the 120 short ones
+fn distance_code(distance: usize, width: usize) -> usize {
+    if distance == width {
+        1
+    } else if distance == 1 {
+        2
+    } else {
+        distance + 120
+    }
+}
+
+// A length or distance code as its prefix symbol and extra bits
+fn prefix_encode(value: usize) -> (usize, u32, u32) {
+    let v = value - 1;
+    if v < 4 {
+        return (v, 0, 0);
+    }
+    let high = usize::BITS - 1 - v.leading_zeros();
+    let second = (v >> (high - 1)) & 1;
+    let extra_bits = high - 1;
+    (
+        2 * high as usize + second,
+        extra_bits,
+        (v & ((1 << extra_bits) - 1)) as u32,
+    )
+}
+
+// Writes an entropy-coded image: no color cache, one group of five prefix codes
+// (with the bit saying so only in the main image), then the pixels
+fn write_image(w: &mut BitWriter, tokens: &[Token], main: bool) {
+    w.write(0, 1);
+    if main {
+        w.write(0, 1);
+    }
+    let mut green = vec![0u32; GREEN_ALPHABET];
+    let mut red = vec![0u32; 256];
+    let mut blue = vec![0u32; 256];
+    let mut alpha = vec![0u32; 256];
+    let mut distance = vec![0u32; DISTANCE_ALPHABET];
+    for token in tokens {
+        match token {
+            Token::Literal(pixel) => {
+                let [a, r, g, b] = channels(*pixel);
+                green[g as usize] += 1;
+                red[r as usize] += 1;
+                blue[b as usize] += 1;
+                alpha[a as usize] += 1;
+            }
+            Token::Copy {
+                length,
+                distance_code: code,
+            } => {
+                green[256 + prefix_encode(*length).0] += 1;
+                distance[prefix_encode(*code).0] += 1;
+            }
+        }
+    }
+    let [green, red, blue, alpha, distance] =
+        [green, red, blue, alpha, distance].map(|counts| write_prefix_code(w, &counts));
+
+    for token in tokens {
+        match token {
+            Token::Literal(pixel) => {
+                let [a, r, g, b] = channels(*pixel);
+                green.write(w, g as usize);
+                red.write(w, r as usize);
+                blue.write(w, b as usize);
+                alpha.write(w, a as usize);
+            }
+            Token::Copy {
+                length,
+                distance_code: code,
+            } => {
+                let (symbol, bits, extra) = prefix_encode(*length);
+                green.write(w, 256 + symbol);
+                w.write(extra, bits);
+                let (symbol, bits, extra) = prefix_encode(*code);
+                distance.write(w, symbol);
+                w.write(extra, bits);
+            }
+        }
+    }
+}
+
+// Canonical prefix codes, bit-reversed since they're read a bit at a time from
+// the least significant end
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u16>,
+}
+
+impl PrefixCode {
+    fn write(&self, w: &mut BitWriter, symbol: usize) {
+        w.write(self.codes[symbol] as u32, self.lengths[symbol] as u32);
+    }
+
+    // A code of one symbol takes no bits
+    fn single() -> Self {
+        PrefixCode {
+            lengths: vec![0; GREEN_ALPHABET],
+            codes: vec![0; GREEN_ALPHABET],
+        }
+    }
+
+    fn canonical(lengths: Vec<u8>) -> Self {
+        let mut count = [0u16; 16];
+        for length in lengths.iter().filter(|l| **l > 0) {
+            count[*length as usize] += 1;
+        }
+        let mut next = [0u16; 16];
+        for length in 1..16 {
+            next[length] = (next[length - 1] + count[length - 1]) << 1;
+        }
+        let codes = lengths
+            .iter()
+            .map(|length| {
+                if *length == 0 {
+                    return 0;
+                }
+                let code = next[*length as usize];
+                next[*length as usize] += 1;
+                code.reverse_bits() >> (16 - *length as u32)
+            })
+            .collect();
+        PrefixCode { lengths, codes }
+    }
+}
+
+// Writes the prefix code for symbols used counts times: a simple code when at most
+// one symbol, below 256, is used, else code lengths themselves prefix coded
+fn write_prefix_code(w: &mut BitWriter, counts: &[u32]) -> PrefixCode {
+    let used: Vec<usize> = (0..counts.len()).filter(|s| counts[*s] > 0).collect();
+    if used.len() <= 1 && used.iter().all(|s| *s < 256) {
+        let symbol = used.first().copied().unwrap_or(0) as u32;
+        w.write(1, 1);
+        w.write(0, 1);
+        if symbol < 2 {
+            w.write(0, 1);
+            w.write(symbol, 1);
+        } else {
+            w.write(1, 1);
+            w.write(symbol, 8);
+        }
+        return PrefixCode::single();
+    }
+    let mut counts = counts.to_vec();
+    // A lone symbol past 255 is given a partner, so the code has two of one bit
+    if used.len() == 1 {
+        counts[if used[0] == 0 { 1 } else { 0 }] = 1;
+    }
+    let lengths = code_lengths(&counts, MAX_CODE_LENGTH);
+    w.write(0, 1);
+    write_code_lengths(w, &lengths);
+    PrefixCode::canonical(lengths)
+}
+
+// Run-length codes the lengths: 0 to 15 as themselves, 16 repeating the last
+// non-zero length 3 to 6 times, 17 for 3 to 10 zeros and 18 for 11 to 138
+fn write_code_lengths(w: &mut BitWriter, lengths: &[u8]) {
+    let mut tokens: Vec<(usize, u32, u32)> = Vec::new();
+    let mut i = 0;
+    while i < lengths.len() {
+        let length = lengths[i];
+        let run = lengths[i..].iter().take_while(|l| **l == length).count();
+        if length == 0 && run >= 3 {
+            let run = run.min(138);
+            tokens.push(if run <= 10 {
+                (17, 3, run as u32 - 3)
+            } else {
+                (18, 7, run as u32 - 11)
+            });
+            i += run;
+            continue;
+        }
+        tokens.push((length as usize, 0, 0));
+        i += 1;
+        let mut repeats = run - 1;
+        if length != 0 {
+            while repeats >= 3 {
+                let n = repeats.min(6);
+                tokens.push((16, 2, n as u32 - 3));
+                repeats -= n;
+                i += n;
+            }
+        }
+    }
+
+    let mut counts = [0u32; 19];
+    for (symbol, _, _) in &tokens {
+        counts[*symbol] += 1;
+    }
+    // A code needs two symbols to be a complete one
+    if counts.iter().filter(|c| **c > 0).count() == 1 {
+        let unused = counts.iter().position(|c| *c == 0).unwrap_or(0);
+        counts[unused] = 1;
+    }
+    let code_lengths = code_lengths(&counts, MAX_CODE_LENGTH_CODE_LENGTH);
+    let written = CODE_LENGTH_ORDER
+        .iter()
+        .rposition(|symbol| code_lengths[*symbol] > 0)
+        .map_or(4, |last| (last + 1).max(4));
+    w.write(written as u32 - 4, 4);
+    for symbol in &CODE_LENGTH_ORDER[..written] {
+        w.write(code_lengths[*symbol] as u32, 3);
+    }
+    // Lengths for the whole alphabet follow
+    w.write(0, 1);
+    let code = PrefixCode::canonical(code_lengths);
+    for (symbol, bits, extra) in tokens {
+        code.write(w, symbol);
+        w.write(extra, bits);
+    }
+}
+
+// Huffman code lengths for symbols used counts times, none longer than max_length.
+// Rare symbols are made less rare until the longest code fits.
+fn code_lengths(counts: &[u32], max_length: u8) -> Vec<u8> {
+    let mut counts = counts.to_vec();
+    loop {
+        let lengths = huffman_lengths(&counts);
+        if lengths.iter().all(|length| *length <= max_length) {
+            return lengths;
+        }
+        for count in counts.iter_mut().filter(|c| **c > 0) {
+            *count = (*count >> 1).max(1);
+        }
+    }
+}
+
+fn huffman_lengths(counts: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0u8; counts.len()];
+    // Leaves are symbols, the rest join two nodes
+    let mut parents: Vec<usize> = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for count in counts.iter().filter(|c| **c > 0) {
+        heap.push(Reverse((*count as u64, parents.len())));
+        parents.push(usize::MAX);
+    }
+    let leaves: Vec<usize> = (0..counts.len()).filter(|s| counts[*s] > 0).collect();
+    while heap.len() > 1 {
+        let (Some(Reverse((a, a_node))), Some(Reverse((b, b_node)))) = (heap.pop(), heap.pop())
+        else {
+            break;
+        };
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[a_node] = node;
+        parents[b_node] = node;
+        heap.push(Reverse((a + b, node)));
+    }
+    for (leaf, symbol) in leaves.iter().enumerate() {
+        let (mut node, mut depth) = (leaf, 0u8);
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            depth = depth.saturating_add(1);
+        }
+        lengths[*symbol] = depth;
+    }
+    lengths
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= (value as u64) << self.len;
+        self.len += bits;
+        while self.len >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
@@ -0,0 +1,123 @@
+use log::warn;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Leaderboards are drawn while the client waits, so don't wait long on the feed
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Rows past this are never paged to, so they aren't kept either
+pub const MAX_ROWS: usize = 100;
+
+// Names past this are cut before they're measured to fit
+const MAX_USERNAME_CHARS: usize = 64;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct LeaderboardRow {
+    pub rank: u32,
+    pub username: String,
+    pub amount: f64,
+    // Rows with an fid are drawn with that user's pfp
+    #[serde(default)]
+    pub fid: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct LeaderboardResponse {
+    rows: Vec<LeaderboardRow>,
+}
+
+// Fetches the leaderboard from LEADERBOARD_URL, which answers with
+// {"rows": [{"rank": 1, "username": "alice", "amount": 1200.5, "fid": 3}, ...]}
+pub async fn fetch_leaderboard(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Vec<LeaderboardRow>, String> {
+    let response = client
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let mut body: LeaderboardResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+    if body.rows.iter().any(|row| !row.amount.is_finite()) {
+        return Err("amounts must be numbers".to_string());
+    }
+    body.rows.sort_by_key(|row| row.rank);
+    body.rows.truncate(MAX_ROWS);
+    for row in &mut body.rows {
+        if let Some((cut, _)) = row.username.char_indices().nth(MAX_USERNAME_CHARS) {
+            row.username.truncate(cut);
+        }
+    }
+    Ok(body.rows)
+}
+
+// Rows in rank order, shared between the images drawn from them
+pub type Rows = Arc<Vec<LeaderboardRow>>;
+
+// The latest leaderboard, so every page drawn within the TTL shares one fetch
+pub struct Leaderboard {
+    ttl: Duration,
+    latest: Mutex<Option<(Instant, Rows)>>,
+}
+
+impl Leaderboard {
+    pub fn new(ttl_secs: u64) -> Self {
+        Leaderboard {
+            ttl: Duration::from_secs(ttl_secs),
+            latest: Mutex::default(),
+        }
+    }
+
+    // None when LEADERBOARD_URL isn't set or the feed fails, which leaves the rows out
+    pub async fn get(&self, client: &reqwest::Client, url: Option<&str>) -> Option<Rows> {
+        let url = url?;
+        if let Some((stored, rows)) = &*self.latest.lock().unwrap() {
+            if stored.elapsed() < self.ttl {
+                return Some(rows.clone());
+            }
+        }
+        match fetch_leaderboard(client, url).await {
+            Ok(rows) => {
+                let rows = Arc::new(rows);
+                *self.latest.lock().unwrap() = Some((Instant::now(), rows.clone()));
+                Some(rows)
+            }
+            Err(err) => {
+                warn!("Failed to fetch the leaderboard: {}", err);
+                None
+            }
+        }
+    }
+}
+
+// Amounts with thousands separated and at most two decimals, e.g. "12,345.5"
+pub fn format_amount(amount: f64) -> String {
+    let fixed = format!("{:.2}", amount.abs());
+    let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let fraction = fraction.trim_end_matches('0');
+    let sign = if amount < 0.0 && fixed.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+        "-"
+    } else {
+        ""
+    };
+    if fraction.is_empty() {
+        format!("{}{}", sign, grouped)
+    } else {
+        format!("{}{}.{}", sign, grouped, fraction)
+    }
+}
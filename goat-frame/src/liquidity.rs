@@ -0,0 +1,247 @@
+use crate::config::Config;
+use crate::countdown::unix_now;
+use crate::errors::AppError;
+use crate::swaps;
+use crate::tx::{self, TransactionParams, TransactionResponse};
+use log::error;
+
+// The pair's reserves, the token's allowance for the router, and the two calls the
+// Add Liquidity screen's buttons send
+const GET_RESERVES_SIGNATURE: &str = "getReserves()";
+const ALLOWANCE_SIGNATURE: &str = "allowance(address,address)";
+const APPROVE_SIGNATURE: &str = "approve(address,uint256)";
+const ADD_LIQUIDITY_SIGNATURE: &str =
+    "addLiquidityETH(address,uint256,uint256,uint256,address,uint256)";
+
+// What both buttons work out from the input: who's adding, how much GOAT, and how
+// much SWAP_TOKEN goes with it at the pair's current price
+struct Deposit {
+    router: [u8; 20],
+    token: [u8; 20],
+    owner: [u8; 20],
+    goat: u128,
+    token_amount: u128,
+    allowance: u128,
+}
+
+// Adds the GOAT the user typed into the Add Liquidity input to the pool, with as much
+// SWAP_TOKEN as the pair's reserves pair it with. Either side may come in up to
+// SWAP_SLIPPAGE_BPS under that, should the price move before it lands. The router
+// must already be allowed to take the token, which the Approve button sees to.
+pub async fn liquidity_transaction(
+    address: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<TransactionResponse, AppError> {
+    let deposit = deposit(address, amount, config, client).await?;
+    if deposit.allowance < deposit.token_amount {
+        return Err(AppError::BadRequest(format!(
+            "Approve {} tokens for the pool first",
+            tx::format_amount(deposit.token_amount)
+        )));
+    }
+    tx::check_balance(client, config, &deposit.owner, deposit.goat).await?;
+
+    let mut data = tx::function_selector(ADD_LIQUIDITY_SIGNATURE).to_vec();
+    data.extend_from_slice(&tx::encode_address(&deposit.token));
+    data.extend_from_slice(&tx::encode_amount(deposit.token_amount));
+    data.extend_from_slice(&tx::encode_amount(swaps::min_out(
+        deposit.token_amount,
+        config.swap_slippage_bps,
+    )));
+    data.extend_from_slice(&tx::encode_amount(swaps::min_out(
+        deposit.goat,
+        config.swap_slippage_bps,
+    )));
+    data.extend_from_slice(&tx::encode_address(&deposit.owner));
+    data.extend_from_slice(&tx::encode_uint(unix_now() + swaps::DEADLINE_SECS));
+
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([{
+                "type": "function",
+                "name": "addLiquidityETH",
+                "stateMutability": "payable",
+                "inputs": [
+                    { "name": "token", "type": "address" },
+                    { "name": "amountTokenDesired", "type": "uint256" },
+                    { "name": "amountTokenMin", "type": "uint256" },
+                    { "name": "amountETHMin", "type": "uint256" },
+                    { "name": "to", "type": "address" },
+                    { "name": "deadline", "type": "uint256" }
+                ],
+                "outputs": [
+                    { "name": "amountToken", "type": "uint256" },
+                    { "name": "amountETH", "type": "uint256" },
+                    { "name": "liquidity", "type": "uint256" }
+                ]
+            }]),
+            to: format!("0x{}", hex::encode(deposit.router)),
+            data: format!("0x{}", hex::encode(data)),
+            value: deposit.goat.to_string(),
+        },
+    })
+}
+
+// Allows the router to take the token side of the GOAT in the input, with room for
+// the price rising by SWAP_SLIPPAGE_BPS before Add is tapped
+pub async fn approve_transaction(
+    address: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<TransactionResponse, AppError> {
+    let deposit = deposit(address, amount, config, client).await?;
+    if deposit.allowance >= deposit.token_amount {
+        return Err(AppError::BadRequest(
+            "Already approved, tap Add".to_string(),
+        ));
+    }
+    let headroom = 10_000 + u128::from(config.swap_slippage_bps);
+    let approved = tx::mul_div(deposit.token_amount, headroom, 10_000).unwrap_or(u128::MAX);
+
+    let mut data = tx::function_selector(APPROVE_SIGNATURE).to_vec();
+    data.extend_from_slice(&tx::encode_address(&deposit.router));
+    data.extend_from_slice(&tx::encode_amount(approved));
+
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([{
+                "type": "function",
+                "name": "approve",
+                "stateMutability": "nonpayable",
+                "inputs": [
+                    { "name": "spender", "type": "address" },
+                    { "name": "amount", "type": "uint256" }
+                ],
+                "outputs": [{ "name": "", "type": "bool" }]
+            }]),
+            to: format!("0x{}", hex::encode(deposit.token)),
+            data: format!("0x{}", hex::encode(data)),
+            value: "0".to_string(),
+        },
+    })
+}
+
+async fn deposit(
+    address: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<Deposit, AppError> {
+    let (Some(pair), Some(router), Some(wrapped_goat), Some(token), Some(rpc_url)) = (
+        &config.liquidity_pair,
+        &config.swap_router,
+        &config.swap_wrapped_goat,
+        &config.swap_token,
+        &config.rpc_url,
+    ) else {
+        error!("LIQUIDITY_PAIR is not configured");
+        return Err(AppError::InternalServerError);
+    };
+    let owner = tx::parse_address(address.unwrap_or_default())?;
+    let goat = tx::parse_amount(amount.unwrap_or_default())?;
+    if goat == 0 {
+        return Err(AppError::BadRequest(
+            "Enter how much GOAT to add".to_string(),
+        ));
+    }
+
+    // Checked when the config was loaded
+    let pair = tx::parse_address(pair)?;
+    let router = tx::parse_address(router)?;
+    let wrapped_goat = tx::parse_address(wrapped_goat)?;
+    let token = tx::parse_address(token)?;
+    let looked_up = async {
+        let (goat_reserve, token_reserve) =
+            reserves(client, rpc_url, &pair, &wrapped_goat, &token).await?;
+        let allowance = allowance(client, rpc_url, &token, &owner, &router).await?;
+        Ok::<_, String>((goat_reserve, token_reserve, allowance))
+    };
+    let (goat_reserve, token_reserve, allowance) = match looked_up.await {
+        Ok(looked_up) => looked_up,
+        Err(err) => {
+            error!("Failed to look up the liquidity pair: {}", err);
+            return Err(AppError::InternalServerError);
+        }
+    };
+    if goat_reserve == 0 || token_reserve == 0 {
+        return Err(AppError::BadRequest(
+            "The pool has no liquidity to match yet".to_string(),
+        ));
+    }
+    let token_amount = match quote_token(goat, goat_reserve, token_reserve) {
+        Some(token_amount) if token_amount > 0 => token_amount,
+        Some(_) => {
+            return Err(AppError::BadRequest(format!(
+                "{} GOAT is too little to add",
+                tx::format_amount(goat)
+            )))
+        }
+        None => {
+            return Err(AppError::BadRequest(format!(
+                "{} GOAT is more than the pool can match",
+                tx::format_amount(goat)
+            )))
+        }
+    };
+
+    Ok(Deposit {
+        router,
+        token,
+        owner,
+        goat,
+        token_amount,
+        allowance,
+    })
+}
+
+// How much token goes with goat at the reserves' price, rounded down as the router
+// does. None when that's past u128.
+pub fn quote_token(goat: u128, goat_reserve: u128, token_reserve: u128) -> Option<u128> {
+    tx::mul_div(goat, token_reserve, goat_reserve)
+}
+
+// The pair's GOAT and token reserves. The pair keeps its tokens in address order, so
+// which is which depends on how the two addresses compare.
+async fn reserves(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    pair: &[u8; 20],
+    wrapped_goat: &[u8; 20],
+    token: &[u8; 20],
+) -> Result<(u128, u128), String> {
+    let input = tx::function_selector(GET_RESERVES_SIGNATURE);
+    let output = tx::eth_call(client, rpc_url, pair, &input).await?;
+    let (Some(reserve0), Some(reserve1)) =
+        (tx::decode_amount(&output, 0), tx::decode_amount(&output, 1))
+    else {
+        return Err(format!("invalid reserves 0x{}", hex::encode(&output)));
+    };
+    Ok(if wrapped_goat < token {
+        (reserve0, reserve1)
+    } else {
+        (reserve1, reserve0)
+    })
+}
+
+// How much of token owner has let spender take
+async fn allowance(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    token: &[u8; 20],
+    owner: &[u8; 20],
+    spender: &[u8; 20],
+) -> Result<u128, String> {
+    let mut input = tx::function_selector(ALLOWANCE_SIGNATURE).to_vec();
+    input.extend_from_slice(&tx::encode_address(owner));
+    input.extend_from_slice(&tx::encode_address(spender));
+    let output = tx::eth_call(client, rpc_url, token, &input).await?;
+    tx::decode_amount(&output, 0)
+        .ok_or_else(|| format!("invalid allowance 0x{}", hex::encode(&output)))
+}
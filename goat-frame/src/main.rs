@@ -1,120 +1,1244 @@
 use actix_files as fs;
-use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::http::header;
+use actix_web::middleware::from_fn;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
 use dotenv::dotenv;
-use log::{error, info}; // Import error to log warnings
-use serde::{Deserialize, Serialize};
+use log::{error, info, warn}; // Import error to log warnings
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+mod actions;
+mod analytics;
+mod app_key;
+mod apr;
+mod assets;
+mod auction;
+mod auth;
+mod bridge;
+mod client_context;
+mod client_detection;
 mod config;
+mod cors;
+mod countdown;
+mod debug_headers;
+mod error_frame;
+mod error_image;
 mod errors;
+mod experiments;
+mod frame_check;
+mod frame_html;
 mod frame_logic;
+mod gifts;
+mod health;
+mod i18n;
+mod idempotency;
+mod images;
+mod leaderboard;
+mod liquidity;
+mod manifest;
+mod metrics;
+mod moxie;
+mod notifications;
+mod price;
+mod profiles;
+mod rate_limit;
+mod receipts;
+mod redirect;
+mod replay;
+mod rewards;
+mod secp256k1;
+mod shutdown;
+mod signers;
+mod swaps;
+mod tls;
+mod trace;
+mod tx;
 
+#[cfg(test)]
+mod tests;
+
+use crate::analytics::{AnalyticsSink, ClickEvent, JsonlSink, NoopSink};
+use crate::assets::{optimize, AssetHashes};
+use crate::auction::Auction;
+use crate::auth::Sessions;
+use crate::bridge::Bridge;
+use crate::client_context::ClientContext;
 use crate::config::Config;
+use crate::cors::AllowedOrigins;
+use crate::error_frame::ErrorFrame;
+use crate::error_image::ErrorImages;
 use crate::errors::AppError;
-use crate::frame_logic::Button;
+use crate::experiments::Exposure;
+use crate::frame_html::{FrameFormat, FrameMeta, OpenGraph};
+use crate::frame_logic::registry::{FrameRegistry, DEFAULT_FRAME};
+use crate::frame_logic::state::{FrameState, MAIN_SCREEN};
+use crate::frame_logic::validation;
+use crate::frame_logic::{AspectRatio, CastId, FrameContext, RenderedScreen, UserContext};
+use crate::gifts::GiftStore;
+use crate::i18n::Translations;
+use crate::idempotency::{
+    ConfirmedTransactions, IdempotencyCache, Replay, Reserved, IDEMPOTENCY_HEADER, MAX_KEY_LEN,
+};
+use crate::images::cache::RenderCache;
+use crate::images::emoji::EmojiAtlas;
+use crate::images::font::Fonts;
+use crate::images::theme::Themes;
+use crate::images::watermark::Watermark;
+use crate::images::ImageTemplates;
+use crate::leaderboard::Leaderboard;
+use crate::metrics::{FrameErrorKind, Metrics};
+use crate::moxie::MoxiePrice;
+use crate::notifications::NotificationStore;
+use crate::price::PriceHistory;
+use crate::profiles::ProfileCache;
+use crate::rate_limit::{RateKey, RateLimiter};
+use crate::receipts::Receipts;
+use crate::replay::ReplayGuard;
+use crate::shutdown::InFlight;
+use crate::signers::SignerStore;
+use crate::trace::TraceId;
+use crate::tx::TransactionResponse;
 
+// Warpcast and most Farcaster clients post camelCase, while older clients and Open
+// Frames libraries use snake_case, so every multi-word field accepts both
 #[derive(Deserialize)]
 struct FrameRequest {
-    untrusted_data: UntrustedData, // Use snake case
+    // Set by Open Frames clients, e.g. "xmtp@2024-02-01"; Farcaster clients usually
+    // leave it out, or name the dated version they're pinned to
+    #[serde(alias = "clientProtocol")]
+    client_protocol: Option<String>,
+    #[serde(alias = "untrustedData")]
+    untrusted_data: UntrustedData,
+    #[serde(alias = "trustedData")]
+    trusted_data: Option<TrustedData>,
+    // sdk.context, from mini app pages that post to the frame
+    context: Option<ClientContext>,
+}
+
+#[derive(Deserialize)]
+struct TrustedData {
+    #[serde(alias = "messageBytes")]
+    message_bytes: String,
 }
 
 #[derive(Deserialize)]
 struct UntrustedData {
-    button_index: usize, // Use snake case
-}
-
-#[derive(Serialize)]
-struct FrameResponse {
-    image: String,
-    buttons: Vec<Button>,
-}
-
-async fn index(config: web::Data<Config>) -> Result<HttpResponse, AppError> {
-    let html = format!(
-        r#"
-    <!DOCTYPE html>
-    <html lang="en">
-    <head>
-        <meta charset="UTF-8">
-        <meta name="viewport" content="width=device-width, initial-scale=1.0">
-        <title>GOAT Frame</title>
-        <meta property="fc:frame" content="vNext" />
-        <meta property="fc:frame:image" content="{}/assets/main.png" />
-        <meta property="fc:frame:button:1" content="Buy & Boost" />
-        <meta property="fc:frame:button:2" content="Add Liquidity" />
-        <meta property="fc:frame:button:3" content="Gift" />
-        <meta property="fc:frame:button:4" content="More" />
-        <meta property="fc:frame:post_url" content="{}/api/frame" />
-    </head>
-    <body>
-        <h1>GOAT Frame</h1>
-    </body>
-    </html>
-    "#,
-        config.domain, config.domain
-    );
+    #[serde(alias = "buttonIndex")]
+    button_index: usize,
+    state: Option<String>,
+    #[serde(alias = "inputText")]
+    input_text: Option<String>,
+    // Sent along with the post that follows a tx button
+    #[serde(alias = "transactionId")]
+    transaction_id: Option<String>,
+    fid: Option<u64>,
+    address: Option<String>,
+    url: Option<String>,
+    #[serde(alias = "messageHash")]
+    message_hash: Option<String>,
+    timestamp: Option<u64>,
+    network: Option<u32>,
+    #[serde(alias = "castId")]
+    cast_id: Option<CastId>,
+}
+
+#[derive(Deserialize)]
+struct IndexQuery {
+    // Screen to open the frame on, e.g. ?screen=gift; main when left out
+    screen: Option<String>,
+    // A mini app's sdk.context as base64url JSON, added by its page on launch
+    context: Option<String>,
+}
+
+// The viewer's locale, as their mini app reported it
+fn locale(context: &Option<ClientContext>) -> Option<&str> {
+    context
+        .as_ref()
+        .and_then(|context| context.locale.as_deref())
+}
+
+// The frame a route serves and the URL its buttons post back to, which is the page
+// itself: /f/{name} routes carry the name, while the rest serve the default frame
+fn frame_route(req: &HttpRequest) -> (String, String) {
+    match req.match_info().get("name") {
+        Some(name) => (name.to_string(), format!("/f/{}", name)),
+        None => (DEFAULT_FRAME.to_string(), "/".to_string()),
+    }
+}
+
+// Screens with a post_path post to that step under their frame, whichever URL served
+// them; the rest post to the frame's URL
+fn screen_post_url(
+    config: &Config,
+    frame: &str,
+    post_path: &str,
+    screen: &RenderedScreen,
+) -> String {
+    match &screen.post_path {
+        Some(step) => format!("{}/f/{}/{}", config.domain, frame, step),
+        None => format!("{}{}", config.domain, post_path),
+    }
+}
 
-    // Check if the html is properly formed; log an error and continue if it's not
-    if html.is_empty() {
-        error!("Failed to generate HTML. Falling back to default content.");
-        return Err(AppError::InternalServerError); // Logs the error, does not halt the program
-    } // may need to design this with proper typescript elements from deno to layer on to this through the binary that it can present through.
+fn unknown_frame(name: &str) -> AppError {
+    AppError::NotFound(format!("Unknown frame: {}", name))
+}
+
+async fn index(
+    query: web::Query<IndexQuery>,
+    http_req: HttpRequest,
+    config: web::Data<Config>,
+    registry: web::Data<FrameRegistry>,
+    assets: web::Data<AssetHashes>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    let (name, post_path) = frame_route(&http_req);
+    let frame = registry.frame(&name).ok_or_else(|| unknown_frame(&name))?;
+
+    // Opening partway in still leaves main underneath, so Back works as usual
+    let mut state = FrameState::default();
+    if let Some(screen) = query
+        .screen
+        .as_deref()
+        .filter(|screen| *screen != MAIN_SCREEN)
+    {
+        if frame.screen(screen).is_none() {
+            return Err(AppError::NotFound(format!("Unknown screen: {}", screen)));
+        }
+        state.push(screen);
+    }
+    let screen = frame
+        .screen(state.current())
+        .ok_or(AppError::InternalServerError)?;
+    let context = query.context.as_deref().and_then(client_context::decode);
+    let user = UserContext {
+        client: client_detection::detect(client_detection::user_agent(&http_req), None),
+        catalog: registry.translations.catalog(locale(&context)),
+        ..UserContext::default()
+    }
+    .with_context(context);
+    let rendered = frame_logic::render_screen(screen, &state, &user, &assets, &config)?;
+    let open_graph = OpenGraph {
+        title: config.og_title.clone(),
+        description: config.og_description.clone(),
+        image: match &config.og_image {
+            Some(image) => assets.url(config.asset_origin(), image),
+            None => rendered.image.clone(),
+        },
+    };
+
+    let post_url = screen_post_url(&config, &name, &post_path, &rendered);
+    let meta = FrameMeta::from_screen(rendered, &post_url)
+        .state(
+            &state
+                .expiring(config.state_ttl_secs)
+                .encode_signed(config.state_secret.as_deref()),
+        )
+        .open_frames(config.open_frames)
+        .open_graph(open_graph);
+    meta.validate()?;
+    let format = FrameFormat::negotiate(&http_req);
+    info!("[{}] Serving frame {}", trace_id, name);
 
-    Ok(HttpResponse::Ok().content_type("text/html").body(html))
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(format.render(&meta)))
 }
 
+// Each piece of shared state arrives as its own extractor
+#[allow(clippy::too_many_arguments)]
 async fn handle_frame(
     req: web::Json<FrameRequest>,
+    http_req: HttpRequest,
     config: web::Data<Config>,
+    registry: web::Data<FrameRegistry>,
+    assets: web::Data<AssetHashes>,
+    client: web::Data<reqwest::Client>,
+    error_images: web::Data<ErrorImages>,
+    metrics: web::Data<Metrics>,
+    rate_limiter: web::Data<RateLimiter>,
+    analytics: web::Data<dyn AnalyticsSink>,
+    idempotency: web::Data<IdempotencyCache>,
+    confirmed: web::Data<ConfirmedTransactions>,
+    replay_guard: web::Data<ReplayGuard>,
+    trace_id: TraceId,
 ) -> Result<HttpResponse, AppError> {
-    info!("Received button click: {}", req.untrusted_data.button_index);
+    let started = Instant::now();
+    metrics.record_request();
+    let mut req = req.into_inner();
+    let (name, post_path) = frame_route(&http_req);
+    // A press posted to a step is answered from there, including with errors
+    let step = http_req.match_info().get("step");
+    let post_url = match step {
+        Some(_) => format!("{}{}", config.domain, http_req.uri()),
+        None => format!("{}{}", config.domain, post_path),
+    };
+    if registry.frame(&name).is_none() {
+        return Err(unknown_frame(&name));
+    }
+
+    let protocol = req
+        .client_protocol
+        .as_deref()
+        .map_or("farcaster", |protocol| {
+            protocol.split('@').next().unwrap_or_default()
+        });
+    let open_frame = protocol != "farcaster";
+    if open_frame && !config.open_frames {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported client protocol: {}",
+            protocol
+        )));
+    }
+
+    // Only fall back to the unsigned payload when validation is explicitly disabled,
+    // or for Open Frames clients, which a Farcaster hub can't vouch for
+    let client_key = http_req
+        .headers()
+        .get(IDEMPOTENCY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LEN)
+        .map(str::to_string);
+    let (button_index, fid, rate_key, idempotency_key, signed_message) =
+        if config.skip_validation || open_frame {
+            // An unsigned fid is whatever the client says, so charge the connection instead
+            let ip = http_req
+                .connection_info()
+                .realip_remote_addr()
+                .unwrap_or("unknown")
+                .to_string();
+            (
+                req.untrusted_data.button_index,
+                // Other networks' user ids aren't fids
+                req.untrusted_data.fid.filter(|_| !open_frame),
+                RateKey::Ip(ip),
+                client_key,
+                None,
+            )
+        } else {
+            let Some(trusted_data) = req.trusted_data.as_ref() else {
+                metrics.record_error(FrameErrorKind::Validation);
+                return Err(AppError::BadRequest("Missing trusted_data".to_string()));
+            };
+            let action = validation::validate_frame_message(
+                &client,
+                &config.hub_url,
+                &config.domain,
+                &trusted_data.message_bytes,
+            )
+            .await
+            .inspect_err(|_| metrics.record_error(FrameErrorKind::Validation))?;
+            info!(
+                "[{}] Validated frame message from fid {}",
+                trace_id, action.fid
+            );
+            replay_guard
+                .check_timestamp(action.timestamp)
+                .inspect_err(|_| metrics.record_error(FrameErrorKind::Validation))?;
+            // The signed text and state replace whatever the client sent alongside them
+            req.untrusted_data.input_text = action.input_text;
+            req.untrusted_data.state = action.state;
+            req.untrusted_data.transaction_id = action.transaction_id;
+            req.untrusted_data.message_hash = action.message_hash.clone();
+            (
+                action.button_index,
+                Some(action.fid),
+                RateKey::Fid(action.fid),
+                action.message_hash.clone().or(client_key),
+                action.message_hash.map(|hash| (hash, action.timestamp)),
+            )
+        };
+
+    // Keys are scoped to the caller and frame so one user can't replay another's response,
+    // and to the format so a page is never replayed as JSON or the other way round
+    let format = FrameFormat::negotiate(&http_req);
+    let idempotency_key =
+        idempotency_key.map(|key| format!("{:?}:{}:{:?}:{}", rate_key, name, format, key));
+    // Claimed before the press is handled, so a retry arriving meanwhile is answered
+    // as this one is rather than refused as a replay below
+    let reservation = match idempotency.reserve(idempotency_key).await {
+        Reserved::Ours(reservation) => reservation,
+        Reserved::Replay(replay) => {
+            info!("[{}] Replaying response for a repeated request", trace_id);
+            let mut response = match replay {
+                Replay::Page(body) => HttpResponse::Ok()
+                    .content_type(format.content_type())
+                    .body(body),
+                Replay::Redirect(location) => HttpResponse::Found()
+                    .insert_header((header::LOCATION, location))
+                    .finish(),
+            };
+            if config.debug_headers {
+                debug_headers::insert(&mut response, "replay", None, started, None);
+            }
+            return Ok(response);
+        }
+        Reserved::Busy => {
+            warn!("[{}] Repeated request still being handled", trace_id);
+            return Err(AppError::TooManyRequests(1));
+        }
+    };
+
+    // Before the hash is recorded, so a press turned away here can be retried
+    if let Err(retry_after) = rate_limiter.check(rate_key.clone()) {
+        warn!("[{}] Rate limited {:?}", trace_id, rate_key);
+        return Err(AppError::TooManyRequests(retry_after.as_secs().max(1)));
+    }
+
+    // A retry the cache no longer covers is indistinguishable from a replay, so it's
+    // refused. IDEMPOTENCY_TTL_SECS is at least MAX_MESSAGE_AGE_SECS, so that's only
+    // once the message is too old anyway.
+    if let Some((hash, timestamp)) = &signed_message {
+        replay_guard
+            .check_hash(hash, *timestamp)
+            .inspect_err(|_| metrics.record_error(FrameErrorKind::Validation))?;
+    }
+
+    info!("[{}] Received button click: {}", trace_id, button_index);
+
+    let data = req.untrusted_data;
+    let mut state = FrameState::decode_signed(
+        data.state.as_deref().unwrap_or_default(),
+        config.state_secret.as_deref(),
+    );
+    state.transaction_id = data
+        .transaction_id
+        .as_deref()
+        .map(tx::parse_transaction_id)
+        .transpose()?;
+    if let Some(step) = step {
+        frame_logic::check_step(&name, step, &state, &registry)
+            .inspect_err(|_| metrics.record_error(FrameErrorKind::InvalidButton))?;
+    }
+
+    // Each message from a double-click is signed separately, so only the transaction
+    // hash ties them together
+    let confirmation_key = state
+        .transaction_id
+        .as_ref()
+        .map(|hash| format!("{:?}:{}:{:?}:{}", rate_key, name, format, hash));
+    if let Some(cached) = confirmation_key
+        .as_deref()
+        .and_then(|key| confirmed.get(key))
+    {
+        info!(
+            "[{}] Transaction already confirmed, replaying its page",
+            trace_id
+        );
+        reservation.complete(Replay::Page(cached.clone()));
+        let mut response = HttpResponse::Ok()
+            .content_type(format.content_type())
+            .body(cached);
+        if config.debug_headers {
+            debug_headers::insert(&mut response, "replay", None, started, None);
+        }
+        return Ok(response);
+    }
+    let context = FrameContext {
+        button_index,
+        user: UserContext {
+            client: client_detection::detect(
+                client_detection::user_agent(&http_req),
+                Some(protocol),
+            ),
+            capabilities: client_detection::capabilities(req.client_protocol.as_deref()),
+            catalog: registry.translations.catalog(locale(&req.context)),
+            ..UserContext::lookup(fid, &config)
+        }
+        .with_context(req.context),
+        input_text: data.input_text,
+        url: data.url,
+        message_hash: data.message_hash,
+        timestamp: data.timestamp,
+        network: data.network,
+        cast_id: data.cast_id,
+        address: data.address,
+        transaction_id: state.transaction_id.clone(),
+    };
+    let user = &context.user;
+    if frame_logic::is_valid_button(&name, button_index, &state, user, &registry) {
+        metrics.record_click(button_index);
+    }
+    let click = ClickEvent {
+        variant: Exposure::on(&name, &state, user, &registry).map(|seen| seen.label()),
+        ..ClickEvent::now(user.fid, &name, button_index)
+    };
+
+    // post_redirect buttons leave the frame, so answer with a 302 rather than a screen
+    if let Some(target) = frame_logic::redirect_for(&name, button_index, &state, user, &registry) {
+        let location = redirect::checkout_url(target, user.fid, &config)?;
+        analytics.record(click).await;
+        info!("[{}] Redirecting to {}", trace_id, location);
+        reservation.complete(Replay::Redirect(location.clone()));
+        let mut response = HttpResponse::Found()
+            .insert_header((header::LOCATION, location))
+            .finish();
+        if config.debug_headers {
+            debug_headers::insert(&mut response, "redirect", Some(&state), started, None);
+        }
+        return Ok(response);
+    }
 
     // Handle frame logic and return an error if an asset fails to load
-    match frame_logic::process_button(req.untrusted_data.button_index, &config) {
-        Ok((image, buttons)) => {
-            let response = FrameResponse { image, buttons };
-            Ok(HttpResponse::Ok().json(response))
+    let processing_started = Instant::now();
+    match frame_logic::process_button(
+        &name,
+        &context,
+        state.clone(),
+        &registry,
+        &assets,
+        &config,
+        &client,
+    )
+    .await
+    {
+        Ok((mut rendered, next_state)) => {
+            analytics.record(click).await;
+            for converted in experiments::conversions(&name, &state, &next_state, user, &registry) {
+                metrics.record_conversion(&converted);
+            }
+            if let Some(exposure) = Exposure::on(&name, &next_state, user, &registry) {
+                metrics.record_exposure(&exposure);
+            }
+            // Refused text shows why on an error image, keeping the screen's box and buttons
+            if let Some(message) = rendered.invalid_input.clone() {
+                if let Some(id) = error_images.id_for(&message) {
+                    rendered.image = format!("{}/assets/error/{}.png", config.asset_origin(), id);
+                    rendered.image_aspect_ratio = Some(AspectRatio::Wide);
+                }
+                rendered.image_alt = Some(message);
+            }
+            // After a hand-off the page belongs to the other frame, so it posts there
+            let post_url = match &next_state.frame {
+                Some(frame) if *frame != name => {
+                    screen_post_url(&config, frame, &format!("/f/{}", frame), &rendered)
+                }
+                _ => screen_post_url(&config, &name, &post_path, &rendered),
+            };
+            // Clients read the next screen from the same fc:frame tags as the first one
+            let meta = FrameMeta::from_screen(rendered, &post_url)
+                .version(&user.capabilities.version)
+                .state(
+                    &next_state
+                        .expiring(config.state_ttl_secs)
+                        .encode_signed(config.state_secret.as_deref()),
+                )
+                .open_frames(config.open_frames);
+            meta.validate()
+                .inspect_err(|_| metrics.record_error(FrameErrorKind::Processing))?;
+            let body = format.render(&meta);
+            let processing = processing_started.elapsed();
+            reservation.complete(Replay::Page(body.clone()));
+            if let Some(key) = confirmation_key {
+                confirmed.insert(key, body.clone());
+            }
+            let mut response = HttpResponse::Ok()
+                .content_type(format.content_type())
+                .body(body);
+            if config.debug_headers {
+                let handler = debug_headers::navigation(&state, &next_state);
+                debug_headers::insert(
+                    &mut response,
+                    &handler,
+                    Some(&next_state),
+                    started,
+                    Some(processing),
+                );
+            }
+            Ok(response)
         }
         Err(err) => {
-            error!(
-                "Failed to process button click: {}. Error: {}",
-                req.untrusted_data.button_index, err
-            );
-            // Return default frame with an error logged
-            let response = FrameResponse {
-                image: format!("{}/assets/main.png", config.domain),
-                buttons: vec![
-                    Button {
-                        label: "Error Occurred".to_string(),
-                    },
-                    Button {
-                        label: "Try Again".to_string(),
+            // A frame left open too long isn't a failure, only a restart
+            if let AppError::SessionExpired = err {
+                info!("[{}] Frame state expired, starting over", trace_id);
+            } else {
+                metrics.record_error(
+                    if frame_logic::is_valid_button(&name, button_index, &state, user, &registry) {
+                        FrameErrorKind::Processing
+                    } else {
+                        FrameErrorKind::InvalidButton
                     },
-                ],
+                );
+                error!(
+                    "[{}] Failed to process button click: {}. Error: {}",
+                    trace_id, button_index, err
+                );
+            }
+            // Show what went wrong and how to get past it, on the home image if it can't
+            // be drawn
+            let error_frame = ErrorFrame::for_error(&err, &config);
+            let image = match error_images.id_for(&error_frame.message) {
+                Some(id) => format!("{}/assets/error/{}.png", config.asset_origin(), id),
+                None => assets.url(config.asset_origin(), "main.png"),
             };
-            Ok(HttpResponse::Ok().json(response)) // Return the response despite the error
+            // Keep the user where they were, so Try Again returns them there, unless their
+            // state expired and starts over
+            let error_state = match err {
+                AppError::SessionExpired => FrameState::default(),
+                _ => state.clone(),
+            };
+            let error_state = FrameState {
+                error: true,
+                ..error_state.expiring(config.state_ttl_secs)
+            };
+            let mut meta = FrameMeta::new(&image, &post_url)
+                .version(&user.capabilities.version)
+                .image_alt(&error_frame.message)
+                .buttons(error_frame.buttons)
+                .state(&error_state.encode_signed(config.state_secret.as_deref()))
+                .open_frames(config.open_frames);
+            if let Some(ratio) = config.default_aspect_ratio {
+                meta = meta.aspect_ratio(ratio);
+            }
+            // Held to the same limits as any other frame, e.g. a long post URL
+            meta.validate()?;
+            let body = format.render(&meta);
+            reservation.complete(Replay::Page(body.clone()));
+            let mut response = HttpResponse::Ok()
+                .content_type(format.content_type())
+                .body(body);
+            if config.debug_headers {
+                debug_headers::insert(
+                    &mut response,
+                    "error",
+                    Some(&state),
+                    started,
+                    Some(processing_started.elapsed()),
+                );
+            }
+            Ok(response) // Return the response despite the error
         }
     }
 }
 
+async fn handle_bid_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    auction: web::Data<Auction>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    let signed_bidder = signed_action(&req, &config, &client)
+        .await?
+        .and_then(|action| Some((action.address?, action.fid)));
+    let built = auction::bid_transaction(
+        signed_bidder,
+        req.untrusted_data.address.as_deref(),
+        req.untrusted_data.input_text.as_deref(),
+        &config,
+        &client,
+        &auction,
+    )
+    .await;
+    transaction_response(built, "bid", req.untrusted_data.fid, &trace_id)
+}
+
+async fn handle_bridge_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    let built = bridge::bridge_transaction(
+        req.untrusted_data.address.as_deref(),
+        req.untrusted_data.input_text.as_deref(),
+        &config,
+        &client,
+    )
+    .await;
+    transaction_response(built, "bridge", req.untrusted_data.fid, &trace_id)
+}
+
+// The Top-up screen's Bridge button lands here with the deposit it sent, and the
+// status frame's Refresh on /bridge/<hash>. Either way it's the chain, not the
+// poster, that says where the deposit is, so the press isn't checked.
+async fn handle_bridge_sent(
+    req: web::Json<FrameRequest>,
+    http_req: HttpRequest,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    let hash = req
+        .untrusted_data
+        .transaction_id
+        .as_deref()
+        .map(tx::parse_transaction_id)
+        .transpose()?
+        .ok_or_else(|| AppError::BadRequest("Missing transaction id".to_string()))?;
+    bridge_status_response(&hash, &http_req, &config)
+}
+
+async fn handle_bridge_status(
+    hash: web::Path<String>,
+    http_req: HttpRequest,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    let hash = tx::parse_transaction_id(&hash)?;
+    bridge_status_response(&hash, &http_req, &config)
+}
+
+fn bridge_status_response(
+    hash: &str,
+    http_req: &HttpRequest,
+    config: &Config,
+) -> Result<HttpResponse, AppError> {
+    let meta = bridge::status_frame(hash, config);
+    meta.validate()?;
+    let format = FrameFormat::negotiate(http_req);
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(format.render(&meta)))
+}
+
+async fn handle_buy_boost_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    // The user's own wallet signs this, so a forged fid or address only affects the forger
+    let built = tx::buy_boost_transaction(
+        req.untrusted_data.fid,
+        req.untrusted_data.address.as_deref(),
+        req.untrusted_data.input_text.as_deref(),
+        &config,
+        &client,
+    )
+    .await;
+    transaction_response(built, "Buy & Boost", req.untrusted_data.fid, &trace_id)
+}
+
+async fn handle_rewards_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    let built =
+        rewards::claim_transaction(req.untrusted_data.address.as_deref(), &config, &client).await;
+    transaction_response(built, "rewards claim", req.untrusted_data.fid, &trace_id)
+}
+
+async fn handle_swap_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    let built = swaps::swap_transaction(
+        req.untrusted_data.address.as_deref(),
+        req.untrusted_data.input_text.as_deref(),
+        &config,
+        &client,
+    )
+    .await;
+    transaction_response(built, "swap", req.untrusted_data.fid, &trace_id)
+}
+
+async fn handle_gift_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    gifts: web::Data<GiftStore>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    // Only a sender the hub vouches for is recorded, with the state and amount they
+    // signed rather than whatever came alongside
+    let signed = signed_action(&req, &config, &client).await?;
+    let (from_fid, state, amount) = match signed {
+        Some(action) => (Some(action.fid), action.state, action.input_text),
+        None => (
+            None,
+            req.untrusted_data.state.clone(),
+            req.untrusted_data.input_text.clone(),
+        ),
+    };
+    // The recipient was entered on the screen before this one, so it's in the state
+    let state = FrameState::decode_signed(
+        state.as_deref().unwrap_or_default(),
+        config.state_secret.as_deref(),
+    );
+    let built = gifts::gift_transaction(
+        from_fid,
+        req.untrusted_data.address.as_deref(),
+        state.input.as_deref(),
+        amount.as_deref(),
+        &config,
+        &client,
+        &gifts,
+    )
+    .await;
+    transaction_response(built, "gift", req.untrusted_data.fid, &trace_id)
+}
+
+async fn handle_liquidity_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    let built = liquidity::liquidity_transaction(
+        req.untrusted_data.address.as_deref(),
+        req.untrusted_data.input_text.as_deref(),
+        &config,
+        &client,
+    )
+    .await;
+    transaction_response(built, "Add Liquidity", req.untrusted_data.fid, &trace_id)
+}
+
+async fn handle_liquidity_approve_tx(
+    req: web::Json<FrameRequest>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    trace_id: TraceId,
+) -> Result<HttpResponse, AppError> {
+    let built = liquidity::approve_transaction(
+        req.untrusted_data.address.as_deref(),
+        req.untrusted_data.input_text.as_deref(),
+        &config,
+        &client,
+    )
+    .await;
+    transaction_response(
+        built,
+        "liquidity approval",
+        req.untrusted_data.fid,
+        &trace_id,
+    )
+}
+
+// The hub's view of a tx button's post, when it carries a signed message and
+// validation is on. Transactions are built for anyone, as the wallet still has to
+// send them, but only what's signed is remembered against an fid.
+async fn signed_action(
+    req: &FrameRequest,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<Option<validation::ValidatedAction>, AppError> {
+    match &req.trusted_data {
+        Some(trusted_data) if !config.skip_validation => validation::validate_frame_message(
+            client,
+            &config.hub_url,
+            &config.domain,
+            &trusted_data.message_bytes,
+        )
+        .await
+        .map(Some),
+        _ => Ok(None),
+    }
+}
+
+// Answers a tx button with the transaction built for it
+fn transaction_response(
+    built: Result<TransactionResponse, AppError>,
+    what: &str,
+    fid: Option<u64>,
+    trace_id: &TraceId,
+) -> Result<HttpResponse, AppError> {
+    let transaction = match built {
+        Ok(transaction) => transaction,
+        // Clients show the user a failed transaction request's message, so problems
+        // they can fix, like a low balance, are answered in that shape
+        Err(err @ (AppError::BadRequest(_) | AppError::InsufficientBalance { .. })) => {
+            let message = match err {
+                AppError::BadRequest(message) => message,
+                err => err.to_string(),
+            };
+            warn!("[{}] Refused {} transaction: {}", trace_id, what, message);
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({ "message": message })));
+        }
+        Err(err) => return Err(err),
+    };
+    info!(
+        "[{}] Built {} transaction for fid {:?}",
+        trace_id, what, fid
+    );
+
+    Ok(HttpResponse::Ok().json(transaction))
+}
+
+// ETags come from content hashes in assets::cache_headers rather than file metadata
+fn asset_files(listing: bool) -> fs::Files {
+    let files = fs::Files::new("", "assets").use_etag(false);
+    if listing {
+        files.show_files_listing()
+    } else {
+        files
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init();
 
-    let config = Config::from_env().expect("Server configuration");
+    // Authoring check: report problems in the frames file and exit without binding a port
+    if std::env::args().skip(1).any(|arg| arg == "--validate") {
+        let config = Config::from_env().unwrap_or_else(|err| {
+            eprintln!("{}; checking with a placeholder DOMAIN", err);
+            let defaults = Config::default();
+            Config {
+                domain: "https://localhost".to_string(),
+                frames_path: std::env::var("FRAMES_PATH").unwrap_or(defaults.frames_path.clone()),
+                ..defaults
+            }
+        });
+        std::process::exit(frame_check::run(&config, Path::new("assets")));
+    }
+
+    let config = Config::from_env().unwrap_or_else(|err| panic!("Server configuration: {}", err));
+    let mut registry = FrameRegistry::load(Path::new(&config.frames_path), Path::new("assets"))
+        .unwrap_or_else(|err| panic!("Invalid frames configuration: {}", err));
+    registry.translations = Translations::load(
+        Path::new(&config.locales_path),
+        config.default_locale.as_deref(),
+    )
+    .unwrap_or_else(|err| panic!("Invalid translations: {}", err));
+    info!(
+        "Loaded translations for {} locales",
+        registry.translations.locales()
+    );
+    if registry.frame(DEFAULT_FRAME).is_none() {
+        warn!(
+            "No {:?} frame configured; / and /api/frame will return 404",
+            DEFAULT_FRAME
+        );
+    }
+    let registry = web::Data::new(registry);
+    let mut fonts =
+        Fonts::load(&config.font_paths).unwrap_or_else(|err| panic!("Invalid FONT_PATHS: {}", err));
+    info!("Loaded {} fonts", fonts.len());
+    if let Some(emoji_path) = &config.emoji_path {
+        let emoji = EmojiAtlas::load(Path::new(emoji_path))
+            .unwrap_or_else(|err| panic!("Invalid EMOJI_PATH: {}", err));
+        info!("Loaded {} emoji", emoji.len());
+        fonts = fonts.with_emoji(emoji);
+    }
+    let themes =
+        Themes::from_config(&config).unwrap_or_else(|err| panic!("Invalid themes: {}", err));
+    let watermark = Watermark::load(
+        config.watermark_logo.as_deref(),
+        config.watermark_text.as_deref(),
+        Path::new("assets"),
+    )
+    .unwrap_or_else(|err| panic!("Invalid WATERMARK_LOGO: {}", err));
+    let templates = ImageTemplates::load(Path::new(&config.templates_path), Path::new("assets"))
+        .unwrap_or_else(|err| panic!("Invalid image templates: {}", err))
+        .with_fonts(fonts)
+        .with_themes(themes)
+        .with_watermark(watermark);
+    registry
+        .check_templates(|name| templates.placeholders(name))
+        .unwrap_or_else(|err| panic!("Invalid frames configuration: {}", err));
+    info!("Loaded {} image templates", templates.len());
+    let templates = web::Data::new(templates);
+    let profiles = web::Data::new(ProfileCache::new(config.profile_ttl_secs));
+    let prices = web::Data::new(PriceHistory::new(config.price_ttl_secs));
+    let leaderboard = web::Data::new(Leaderboard::new(config.leaderboard_ttl_secs));
+    let moxie_price = web::Data::new(MoxiePrice::new(config.moxie_price_ttl_secs));
+    let auction = web::Data::new(Auction::new(config.auction_poll_secs));
+    let receipts = web::Data::new(Receipts::default());
+    let bridge = web::Data::new(Bridge::default());
+    let render_cache = web::Data::new(RenderCache::new(
+        config.image_cache_size,
+        config.image_cache_ttl_secs,
+    ));
+    let mut asset_hashes = AssetHashes::scan(Path::new("assets"))
+        .unwrap_or_else(|err| panic!("Failed to hash assets: {}", err));
+    let asset_cache = PathBuf::from(&config.asset_cache_path);
+    if !config.skip_asset_optimization {
+        let optimized = optimize::optimize_assets(Path::new("assets"), &asset_cache)
+            .unwrap_or_else(|err| panic!("Failed to optimize assets: {}", err));
+        let before: u64 = optimized.iter().map(|asset| asset.original_bytes).sum();
+        let after: u64 = optimized.iter().map(|asset| asset.optimized_bytes).sum();
+        info!(
+            "Optimized {} assets from {} KB to {} KB",
+            optimized.len(),
+            before / 1024,
+            after / 1024
+        );
+        asset_hashes = asset_hashes
+            .with_optimized(&asset_cache, &optimized)
+            .unwrap_or_else(|err| panic!("Failed to hash optimized assets: {}", err));
+    }
+    let asset_hashes = web::Data::new(asset_hashes);
+    let asset_listing = config.asset_listing;
+    let bind_addr = (config.host.clone(), config.port);
+    let tls_config = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert), Some(key)) => Some(
+            tls::load_server_config(Path::new(cert), Path::new(key))
+                .unwrap_or_else(|err| panic!("Invalid TLS configuration: {}", err)),
+        ),
+        _ => None,
+    };
+    let shutdown_timeout = config.shutdown_timeout_secs;
+    let allowed_origins = AllowedOrigins::from_config(&config.allowed_origins)
+        .unwrap_or_else(|err| panic!("Invalid CORS configuration: {}", err));
     let config = web::Data::new(config);
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .expect("HTTP client");
+    let client = web::Data::new(client);
+    let readiness = web::Data::new(health::Readiness::default());
+    let error_images = web::Data::new(ErrorImages::default());
+    let metrics = web::Data::new(Metrics::default());
+    let rate_limiter = web::Data::new(RateLimiter::new(config.rate_limit_per_min));
+    let analytics: Arc<dyn AnalyticsSink> = match &config.analytics_path {
+        Some(path) => Arc::new(
+            JsonlSink::open(Path::new(path))
+                .unwrap_or_else(|err| panic!("Failed to open analytics file {}: {}", path, err)),
+        ),
+        None => Arc::new(NoopSink),
+    };
+    let analytics = web::Data::from(analytics);
+    let idempotency = web::Data::new(IdempotencyCache::new(config.idempotency_ttl_secs));
+    let confirmed = web::Data::new(ConfirmedTransactions::new(config.tx_confirmation_ttl_secs));
+    let replay_guard = web::Data::new(ReplayGuard::new(config.max_message_age_secs));
+    let notification_store = match &config.notifications_path {
+        Some(path) => NotificationStore::open(Path::new(path))
+            .unwrap_or_else(|err| panic!("Failed to open notification tokens {}: {}", path, err)),
+        None => NotificationStore::in_memory(),
+    };
+    let notification_store = web::Data::new(notification_store);
+    let gift_store = match &config.gifts_path {
+        Some(path) => GiftStore::open(Path::new(path))
+            .unwrap_or_else(|err| panic!("Failed to open gifts {}: {}", path, err)),
+        None => GiftStore::in_memory(),
+    };
+    let gift_store = web::Data::new(gift_store);
+    let signer_store = match &config.signers_path {
+        Some(path) => SignerStore::open(Path::new(path))
+            .unwrap_or_else(|err| panic!("Failed to open signers {}: {}", path, err)),
+        None => SignerStore::in_memory(),
+    };
+    let signer_store = web::Data::new(signer_store);
+    let sessions = web::Data::new(Sessions::new(
+        config.session_secret.as_deref(),
+        config.session_ttl_secs,
+    ));
+    let in_flight = web::Data::new(InFlight::default());
+    let server_in_flight = in_flight.clone();
+
+    if config.auction_contract.is_some() {
+        actix_web::rt::spawn(auction::watch_for_outbids(
+            auction.clone(),
+            notification_store.clone(),
+            client.clone(),
+            config.clone(),
+        ));
+    }
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(config.clone())
+            .app_data(registry.clone())
+            .app_data(client.clone())
+            .app_data(readiness.clone())
+            .app_data(error_images.clone())
+            .app_data(asset_hashes.clone())
+            .app_data(metrics.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(analytics.clone())
+            .app_data(idempotency.clone())
+            .app_data(confirmed.clone())
+            .app_data(replay_guard.clone())
+            .app_data(notification_store.clone())
+            .app_data(gift_store.clone())
+            .app_data(sessions.clone())
+            .app_data(signer_store.clone())
+            .app_data(in_flight.clone())
+            .app_data(templates.clone())
+            .app_data(profiles.clone())
+            .app_data(prices.clone())
+            .app_data(leaderboard.clone())
+            .app_data(moxie_price.clone())
+            .app_data(auction.clone())
+            .app_data(receipts.clone())
+            .app_data(bridge.clone())
+            .app_data(render_cache.clone())
+            .wrap(from_fn(shutdown::track_in_flight))
             .wrap(actix_web::middleware::Logger::default())
-            .service(fs::Files::new("/assets", "assets").show_files_listing())
-            .route("/", web::get().to(index))
-            .route("/api/frame", web::post().to(handle_frame))
-    })
-    .bind(("0.0.0.0", 8080))?
-    .run()
-    .await
+            .wrap(from_fn(trace::trace_id))
+            // CORS only applies to what other sites embed; the index page is left alone
+            .service(
+                web::scope("/assets")
+                    .wrap(from_fn(assets::cache_headers))
+                    .wrap(cors::cors(&allowed_origins))
+                    // Registered ahead of the static files so it isn't shadowed by them
+                    .route(
+                        "/error/{id}.png",
+                        web::get().to(error_image::serve_error_image),
+                    )
+                    .service(fs::Files::new("/optimized", &asset_cache).use_etag(false))
+                    .service(asset_files(asset_listing)),
+            )
+            .service(
+                web::scope("/images")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route("/{file}", web::get().to(images::serve_template_image)),
+            )
+            // A frame's page and its interactions share one URL, as validators expect:
+            // GET serves the first screen and POST handles button presses
+            .service(
+                web::resource("/").route(web::get().to(index)).route(
+                    web::post()
+                        .to(handle_frame)
+                        .wrap(cors::cors(&allowed_origins)),
+                ),
+            )
+            .service(
+                web::resource("/f/{name}")
+                    .route(web::get().to(index))
+                    .route(
+                        web::post()
+                            .to(handle_frame)
+                            .wrap(cors::cors(&allowed_origins)),
+                    ),
+            )
+            // The same frames under their longer name; pages still post to /f/{name}
+            .service(
+                web::resource("/frames/{name}")
+                    .route(web::get().to(index))
+                    .route(
+                        web::post()
+                            .to(handle_frame)
+                            .wrap(cors::cors(&allowed_origins)),
+                    ),
+            )
+            .service(
+                web::resource("/frames/{name}/{step}").route(
+                    web::post()
+                        .to(handle_frame)
+                        .wrap(cors::cors(&allowed_origins)),
+                ),
+            )
+            // Where frames shared before the routes were unified still post
+            .service(
+                web::resource("/api/frame")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_frame)),
+            )
+            .service(
+                web::resource("/f/{name}/frame")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_frame)),
+            )
+            // Screens with a post_path post to their own step
+            .service(
+                web::resource("/f/{name}/{step}").route(
+                    web::post()
+                        .to(handle_frame)
+                        .wrap(cors::cors(&allowed_origins)),
+                ),
+            )
+            .service(
+                web::resource("/bridge")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_bridge_sent)),
+            )
+            .service(
+                web::resource("/bridge/{hash}")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::get().to(handle_bridge_status))
+                    .route(web::post().to(handle_bridge_status)),
+            )
+            .service(
+                web::resource("/api/tx/bid")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_bid_tx)),
+            )
+            .service(
+                web::resource("/api/tx/bridge")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_bridge_tx)),
+            )
+            .service(
+                web::resource("/api/tx/buy_boost")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_buy_boost_tx)),
+            )
+            .service(
+                web::resource("/api/tx/rewards")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_rewards_tx)),
+            )
+            .service(
+                web::resource("/api/tx/swap")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_swap_tx)),
+            )
+            .service(
+                web::resource("/api/tx/gift")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_gift_tx)),
+            )
+            .service(
+                web::resource("/api/tx/liquidity")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_liquidity_tx)),
+            )
+            .service(
+                web::resource("/api/tx/liquidity/approve")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::post().to(handle_liquidity_approve_tx)),
+            )
+            .service(
+                web::resource("/api/composer")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::get().to(actions::composer_metadata))
+                    .route(web::post().to(actions::composer_form)),
+            )
+            .service(
+                web::resource("/api/cast-action")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::get().to(actions::cast_action_metadata))
+                    .route(web::post().to(actions::cast_action)),
+            )
+            // Sign In With Farcaster for the mini app pages
+            .service(
+                web::scope("/api/auth")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route("/nonce", web::get().to(auth::nonce))
+                    .route("/verify", web::post().to(auth::sign_in))
+                    .route("/me", web::get().to(auth::me)),
+            )
+            // Onboards a signer for the signed-in user: POST requests one, GET polls it
+            .service(
+                web::resource("/api/signer")
+                    .wrap(cors::cors(&allowed_origins))
+                    .route(web::get().to(signers::signer_status))
+                    .route(web::post().to(signers::request_signer)),
+            )
+            .route("/api/webhook", web::post().to(notifications::webhook))
+            .route(
+                "/.well-known/farcaster.json",
+                web::get().to(manifest::farcaster_manifest),
+            )
+            .route("/health", web::get().to(health::health))
+            .route("/ready", web::get().to(health::ready))
+            .route("/metrics", web::get().to(metrics::metrics))
+    });
+    let server = match tls_config {
+        Some(tls_config) => {
+            info!("Serving HTTPS on {}:{}", bind_addr.0, bind_addr.1);
+            server.bind_rustls_0_23(bind_addr, tls_config)?
+        }
+        None => server.bind(bind_addr)?,
+    };
+    let server = server
+        .shutdown_timeout(shutdown_timeout)
+        // Signals are handled in shutdown::drain_on_signal so the drain can be logged
+        .disable_signals()
+        .run();
+
+    let drain = actix_web::rt::spawn(shutdown::drain_on_signal(server.handle(), server_in_flight));
+    server.await?;
+    // Let the drain finish reporting before the process exits
+    let _ = drain.await;
+    Ok(())
 }
 
 // next add the line 317 DEPLOYMENT.md
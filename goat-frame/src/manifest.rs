@@ -0,0 +1,73 @@
+use crate::config::Config;
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+// Mini app manifest version clients understand
+const MANIFEST_VERSION: &str = "1";
+
+// What /.well-known/farcaster.json tells clients about the store as a mini app
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    // Signed proof that a Farcaster account owns DOMAIN; left out until configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_association: Option<AccountAssociation>,
+    pub frame: MiniApp,
+}
+
+#[derive(Serialize)]
+pub struct AccountAssociation {
+    pub header: String,
+    pub payload: String,
+    pub signature: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MiniApp {
+    pub version: String,
+    pub name: String,
+    pub icon_url: String,
+    pub home_url: String,
+    pub splash_image_url: String,
+    pub splash_background_color: String,
+    pub webhook_url: String,
+}
+
+impl Manifest {
+    pub fn from_config(config: &Config) -> Self {
+        let account_association = match (
+            &config.account_association_header,
+            &config.account_association_payload,
+            &config.account_association_signature,
+        ) {
+            (Some(header), Some(payload), Some(signature)) => Some(AccountAssociation {
+                header: header.clone(),
+                payload: payload.clone(),
+                signature: signature.clone(),
+            }),
+            _ => None,
+        };
+
+        Manifest {
+            account_association,
+            frame: MiniApp {
+                version: MANIFEST_VERSION.to_string(),
+                name: config.miniapp_name.clone(),
+                icon_url: format!("{}/assets/{}", config.asset_origin(), config.miniapp_icon),
+                home_url: format!("{}/", config.domain),
+                splash_image_url: format!(
+                    "{}/assets/{}",
+                    config.asset_origin(),
+                    config.miniapp_splash_image
+                ),
+                splash_background_color: config.miniapp_splash_background.clone(),
+                webhook_url: format!("{}/api/webhook", config.domain),
+            },
+        }
+    }
+}
+
+pub async fn farcaster_manifest(config: web::Data<Config>) -> HttpResponse {
+    HttpResponse::Ok().json(Manifest::from_config(&config))
+}
@@ -0,0 +1,168 @@
+use crate::experiments::Exposure;
+use crate::frame_logic::registry::MAX_BUTTONS;
+use actix_web::{web, HttpResponse};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+// Why a frame request didn't produce the next screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameErrorKind {
+    InvalidButton,
+    Validation,
+    Processing,
+}
+
+impl FrameErrorKind {
+    const ALL: [FrameErrorKind; 3] = [
+        FrameErrorKind::InvalidButton,
+        FrameErrorKind::Validation,
+        FrameErrorKind::Processing,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            FrameErrorKind::InvalidButton => "invalid_button",
+            FrameErrorKind::Validation => "validation",
+            FrameErrorKind::Processing => "processing",
+        }
+    }
+}
+
+// Counters for the frame API, exposed in Prometheus text format at /metrics
+#[derive(Default)]
+pub struct Metrics {
+    requests: AtomicU64,
+    button_clicks: [AtomicU64; MAX_BUTTONS],
+    errors: [AtomicU64; FrameErrorKind::ALL.len()],
+    // Exposures and conversions by experiment and arm
+    experiments: Mutex<BTreeMap<(String, String), (u64, u64)>>,
+    // Generated images served from the render cache, and ones drawn for the request
+    image_cache_hits: AtomicU64,
+    image_cache_misses: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Only counts indices a frame can actually show; anything else is an error
+    pub fn record_click(&self, button_index: usize) {
+        if let Some(counter) = button_index
+            .checked_sub(1)
+            .and_then(|i| self.button_clicks.get(i))
+        {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_error(&self, kind: FrameErrorKind) {
+        self.errors[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_exposure(&self, exposure: &Exposure) {
+        self.count_experiment(exposure, |(exposures, _)| *exposures += 1);
+    }
+
+    pub fn record_conversion(&self, exposure: &Exposure) {
+        self.count_experiment(exposure, |(_, conversions)| *conversions += 1);
+    }
+
+    pub fn record_image_cache(&self, hit: bool) {
+        let counter = if hit {
+            &self.image_cache_hits
+        } else {
+            &self.image_cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn count_experiment(&self, exposure: &Exposure, count: impl FnOnce(&mut (u64, u64))) {
+        let mut experiments = self.experiments.lock().unwrap();
+        let key = (exposure.experiment.clone(), exposure.arm.clone());
+        count(experiments.entry(key).or_default());
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP frame_requests_total Frame API requests received.\n");
+        out.push_str("# TYPE frame_requests_total counter\n");
+        let _ = writeln!(
+            out,
+            "frame_requests_total {}",
+            self.requests.load(Ordering::Relaxed)
+        );
+
+        out.push_str("# HELP frame_button_clicks_total Button presses by 1-based index.\n");
+        out.push_str("# TYPE frame_button_clicks_total counter\n");
+        for (i, counter) in self.button_clicks.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "frame_button_clicks_total{{button=\"{}\"}} {}",
+                i + 1,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+
+        out.push_str("# HELP frame_errors_total Frame requests that ended in an error.\n");
+        out.push_str("# TYPE frame_errors_total counter\n");
+        for kind in FrameErrorKind::ALL {
+            let _ = writeln!(
+                out,
+                "frame_errors_total{{reason=\"{}\"}} {}",
+                kind.label(),
+                self.errors[kind as usize].load(Ordering::Relaxed)
+            );
+        }
+
+        out.push_str(
+            "# HELP frame_image_cache_requests_total Generated image requests, by whether the render cache had them.\n",
+        );
+        out.push_str("# TYPE frame_image_cache_requests_total counter\n");
+        for (result, counter) in [
+            ("hit", &self.image_cache_hits),
+            ("miss", &self.image_cache_misses),
+        ] {
+            let _ = writeln!(
+                out,
+                "frame_image_cache_requests_total{{result=\"{}\"}} {}",
+                result,
+                counter.load(Ordering::Relaxed)
+            );
+        }
+
+        // Only arms someone has seen are listed, since a restart forgets the rest
+        let experiments = self.experiments.lock().unwrap();
+        out.push_str("# HELP frame_experiment_exposures_total Experiment screens shown, by arm.\n");
+        out.push_str("# TYPE frame_experiment_exposures_total counter\n");
+        for ((experiment, arm), (exposures, _)) in experiments.iter() {
+            let _ = writeln!(
+                out,
+                "frame_experiment_exposures_total{{experiment=\"{}\",variant=\"{}\"}} {}",
+                experiment, arm, exposures
+            );
+        }
+        out.push_str(
+            "# HELP frame_experiment_conversions_total Experiment goals reached, by arm seen.\n",
+        );
+        out.push_str("# TYPE frame_experiment_conversions_total counter\n");
+        for ((experiment, arm), (_, conversions)) in experiments.iter() {
+            let _ = writeln!(
+                out,
+                "frame_experiment_conversions_total{{experiment=\"{}\",variant=\"{}\"}} {}",
+                experiment, arm, conversions
+            );
+        }
+
+        out
+    }
+}
+
+pub async fn metrics(metrics: web::Data<Metrics>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render())
+}
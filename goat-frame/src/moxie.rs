@@ -0,0 +1,87 @@
+use log::warn;
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Prices are drawn while the client waits, so don't wait long on the quote
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Deserialize)]
+struct MoxiePriceResponse {
+    // In MOXIE per fan token
+    price: f64,
+}
+
+// Fetches the fan token's current price from MOXIE_PRICE_URL, which answers with
+// {"price": <MOXIE>}, whether it's quoted from the bonding curve or a DEX
+pub async fn fetch_moxie_price(client: &reqwest::Client, url: &str) -> Result<f64, String> {
+    let response = client
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let body: MoxiePriceResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+    if !body.price.is_finite() || body.price < 0.0 {
+        return Err(format!("invalid price {}", body.price));
+    }
+    Ok(body.price)
+}
+
+// The latest price, so every image drawn within the TTL shares one fetch
+pub struct MoxiePrice {
+    ttl: Duration,
+    latest: Mutex<Option<(Instant, f64)>>,
+}
+
+impl MoxiePrice {
+    pub fn new(ttl_secs: u64) -> Self {
+        MoxiePrice {
+            ttl: Duration::from_secs(ttl_secs),
+            latest: Mutex::default(),
+        }
+    }
+
+    // None when MOXIE_PRICE_URL isn't set or the quote fails, which leaves the price out
+    pub async fn get(&self, client: &reqwest::Client, url: Option<&str>) -> Option<f64> {
+        let url = url?;
+        if let Some((stored, price)) = *self.latest.lock().unwrap() {
+            if stored.elapsed() < self.ttl {
+                return Some(price);
+            }
+        }
+        match fetch_moxie_price(client, url).await {
+            Ok(price) => {
+                *self.latest.lock().unwrap() = Some((Instant::now(), price));
+                Some(price)
+            }
+            Err(err) => {
+                warn!("Failed to fetch the Moxie price: {}", err);
+                None
+            }
+        }
+    }
+}
+
+// "0.043 MOXIE": prices of 1 and up to at most two decimals, smaller ones to three
+// significant digits
+pub fn format_moxie_price(price: f64) -> String {
+    let digits = if price >= 1.0 || price == 0.0 {
+        2
+    } else {
+        (2 - price.log10().floor() as i32).max(0) as usize
+    };
+    let fixed = format!("{:.*}", digits, price);
+    let trimmed = if fixed.contains('.') {
+        fixed.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &fixed
+    };
+    format!("{} MOXIE", trimmed)
+}
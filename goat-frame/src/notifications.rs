@@ -0,0 +1,268 @@
+use crate::app_key::{self, SignedEvent};
+use crate::config::Config;
+use crate::errors::AppError;
+use crate::tx;
+use actix_web::{web, HttpResponse};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Limits clients place on a notification, in characters
+const MAX_TITLE_CHARS: usize = 32;
+const MAX_BODY_CHARS: usize = 128;
+
+// Where and how a client lets us notify one user, from a frame_added or
+// notifications_enabled event
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NotificationDetails {
+    pub url: String,
+    pub token: String,
+}
+
+// Notification tokens by fid, saved to NOTIFICATIONS_PATH so they survive restarts
+pub struct NotificationStore {
+    path: Option<PathBuf>,
+    tokens: Mutex<HashMap<u64, NotificationDetails>>,
+}
+
+impl NotificationStore {
+    // Keeps tokens in memory only
+    pub fn in_memory() -> Self {
+        NotificationStore {
+            path: None,
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Loads the tokens saved at path, starting empty if the file doesn't exist yet
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let tokens = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(NotificationStore {
+            path: Some(path.to_path_buf()),
+            tokens: Mutex::new(tokens),
+        })
+    }
+
+    pub fn get(&self, fid: u64) -> Option<NotificationDetails> {
+        self.tokens.lock().unwrap().get(&fid).cloned()
+    }
+
+    // Stores or, with None, forgets a user's token
+    pub fn set(&self, fid: u64, details: Option<NotificationDetails>) {
+        let mut tokens = self.tokens.lock().unwrap();
+        match details {
+            Some(details) => tokens.insert(fid, details),
+            None => tokens.remove(&fid),
+        };
+
+        // Events are rare and the file small, so it's rewritten under the lock to keep
+        // saves in order
+        if let Some(path) = &self.path {
+            let saved = serde_json::to_string(&*tokens)
+                .map_err(io::Error::other)
+                .and_then(|json| std::fs::write(path, json));
+            if let Err(err) = saved {
+                error!(
+                    "Failed to save notification tokens to {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum WebhookEvent {
+    #[serde(rename_all = "camelCase")]
+    FrameAdded {
+        notification_details: Option<NotificationDetails>,
+    },
+    FrameRemoved,
+    #[serde(rename_all = "camelCase")]
+    NotificationsEnabled {
+        notification_details: NotificationDetails,
+    },
+    NotificationsDisabled,
+}
+
+// Receives the mini app lifecycle events clients send to the manifest's webhookUrl.
+// SKIP_VALIDATION trusts the header's fid without checking the signature.
+pub async fn webhook(
+    event: web::Json<SignedEvent>,
+    store: web::Data<NotificationStore>,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+) -> Result<HttpResponse, AppError> {
+    let header: app_key::EventHeader = if config.skip_validation {
+        app_key::decode_part(&event.header)?
+    } else {
+        app_key::verify_event(&client, &config.hub_url, &event).await?
+    };
+    let payload: WebhookEvent = app_key::decode_part(&event.payload)?;
+    let details = match &payload {
+        WebhookEvent::FrameAdded {
+            notification_details,
+        } => notification_details.as_ref(),
+        WebhookEvent::NotificationsEnabled {
+            notification_details,
+        } => Some(notification_details),
+        _ => None,
+    };
+    if let Some(details) = details {
+        check_url(&details.url, &config)?;
+    }
+
+    match payload {
+        WebhookEvent::FrameAdded {
+            notification_details,
+        } => {
+            info!("fid {} added the mini app", header.fid);
+            store.set(header.fid, notification_details);
+        }
+        WebhookEvent::NotificationsEnabled {
+            notification_details,
+        } => {
+            info!("fid {} enabled notifications", header.fid);
+            store.set(header.fid, Some(notification_details));
+        }
+        WebhookEvent::FrameRemoved | WebhookEvent::NotificationsDisabled => {
+            info!("fid {} turned off notifications", header.fid);
+            store.set(header.fid, None);
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "success": true })))
+}
+
+// Notifications are posted wherever the event said, so only to a known client's host
+// over https, lest an event point us at a server of the sender's choosing
+fn check_url(url: &str, config: &Config) -> Result<(), AppError> {
+    let allowed = url::Url::parse(url).is_ok_and(|url| {
+        url.scheme() == "https"
+            && url.host_str().is_some_and(|host| {
+                config
+                    .notification_hosts
+                    .iter()
+                    .any(|allowed| allowed == host)
+            })
+    });
+    if !allowed {
+        warn!("Refused notification URL {}", url);
+        return Err(AppError::BadRequest(
+            "Notification URL isn't a known client's".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// A message for one user. The id is used by clients to drop duplicates, so reuse it
+// when retrying the same message.
+pub struct Notification {
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub target_url: String,
+}
+
+impl Notification {
+    // For the auction ending at ends_at, whose highest bid is now bid, in wei
+    pub fn outbid(ends_at: u64, bid: u128, config: &Config) -> Self {
+        Notification {
+            id: format!("outbid-{}-{}", ends_at, bid),
+            title: "You've been outbid".to_string(),
+            body: format!(
+                "The highest bid is now {} GOAT. Bid again to win it back.",
+                tx::format_amount(bid)
+            ),
+            target_url: format!("{}/", config.domain),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendRequest<'a> {
+    notification_id: &'a str,
+    title: &'a str,
+    body: &'a str,
+    target_url: &'a str,
+    tokens: [&'a str; 1],
+}
+
+#[derive(Deserialize)]
+struct SendResponse {
+    result: SendResult,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendResult {
+    #[serde(default)]
+    successful_tokens: Vec<String>,
+    #[serde(default)]
+    invalid_tokens: Vec<String>,
+}
+
+// Sends a notification to a user, if they've enabled them. Returns whether the client
+// accepted it; a token the client reports as invalid is forgotten.
+pub async fn send(
+    client: &reqwest::Client,
+    store: &NotificationStore,
+    fid: u64,
+    notification: &Notification,
+) -> Result<bool, AppError> {
+    let Some(details) = store.get(fid) else {
+        return Ok(false);
+    };
+    if notification.title.chars().count() > MAX_TITLE_CHARS
+        || notification.body.chars().count() > MAX_BODY_CHARS
+    {
+        error!("Notification {:?} is too long to send", notification.id);
+        return Err(AppError::InternalServerError);
+    }
+
+    let request = SendRequest {
+        notification_id: &notification.id,
+        title: &notification.title,
+        body: &notification.body,
+        target_url: &notification.target_url,
+        tokens: [&details.token],
+    };
+    let response = client
+        .post(&details.url)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|err| {
+            error!("Failed to reach notification URL {}: {}", details.url, err);
+            AppError::InternalServerError
+        })?;
+    if !response.status().is_success() {
+        warn!(
+            "Notification URL {} returned status {}",
+            details.url,
+            response.status()
+        );
+        return Ok(false);
+    }
+
+    let body: SendResponse = response.json().await.map_err(|err| {
+        error!("Unexpected response from notification URL: {}", err);
+        AppError::InternalServerError
+    })?;
+    if body.result.invalid_tokens.contains(&details.token) {
+        info!("Forgetting invalid notification token for fid {}", fid);
+        store.set(fid, None);
+    }
+    Ok(body.result.successful_tokens.contains(&details.token))
+}
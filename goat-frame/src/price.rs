@@ -0,0 +1,136 @@
+use log::warn;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Charts are drawn while the client waits, so don't wait long on the price feed
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Where in PRICE_URL the range goes
+pub const RANGE_PLACEHOLDER: &str = "{range}";
+
+// How far back a price chart looks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PriceRange {
+    Day,
+    Week,
+}
+
+impl PriceRange {
+    pub fn parse(range: &str) -> Option<Self> {
+        match range {
+            "24h" => Some(PriceRange::Day),
+            "7d" => Some(PriceRange::Week),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PriceRange::Day => "24h",
+            PriceRange::Week => "7d",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PriceResponse {
+    // [unix timestamp, price] pairs, oldest first
+    prices: Vec<(f64, f64)>,
+}
+
+// Fetches the token's price history from PRICE_URL with {range} filled in, which
+// answers with {"prices": [[<timestamp>, <price>], ...]}
+pub async fn fetch_prices(
+    client: &reqwest::Client,
+    url: &str,
+    range: PriceRange,
+) -> Result<Vec<f64>, String> {
+    let response = client
+        .get(url.replace(RANGE_PLACEHOLDER, range.as_str()))
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let mut body: PriceResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+    body.prices.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let prices: Vec<f64> = body.prices.into_iter().map(|(_, price)| price).collect();
+    if prices.is_empty() || prices.iter().any(|price| !price.is_finite()) {
+        return Err("no valid prices".to_string());
+    }
+    Ok(prices)
+}
+
+// Prices oldest first, shared between the images drawn from them
+pub type Prices = Arc<Vec<f64>>;
+
+// Recent price history by range, so every chart drawn in a minute shares one fetch
+pub struct PriceHistory {
+    ttl: Duration,
+    ranges: Mutex<HashMap<PriceRange, (Instant, Prices)>>,
+}
+
+impl PriceHistory {
+    pub fn new(ttl_secs: u64) -> Self {
+        PriceHistory {
+            ttl: Duration::from_secs(ttl_secs),
+            ranges: Mutex::default(),
+        }
+    }
+
+    // None when PRICE_URL isn't set or the feed fails, which leaves the chart out
+    pub async fn get(
+        &self,
+        client: &reqwest::Client,
+        url: Option<&str>,
+        range: PriceRange,
+    ) -> Option<Prices> {
+        let url = url?;
+        if let Some((stored, prices)) = self.ranges.lock().unwrap().get(&range) {
+            if stored.elapsed() < self.ttl {
+                return Some(prices.clone());
+            }
+        }
+        match fetch_prices(client, url, range).await {
+            Ok(prices) => {
+                let prices = Arc::new(prices);
+                self.ranges
+                    .lock()
+                    .unwrap()
+                    .insert(range, (Instant::now(), prices.clone()));
+                Some(prices)
+            }
+            Err(err) => {
+                warn!("Failed to fetch {} prices: {}", range.as_str(), err);
+                None
+            }
+        }
+    }
+}
+
+// "+4.2%" or "-0.8%" from the first price to the last
+pub fn change(prices: &[f64]) -> Option<String> {
+    let (first, last) = (prices.first()?, prices.last()?);
+    if *first == 0.0 {
+        return None;
+    }
+    Some(format!("{:+.1}%", (last - first) / first * 100.0))
+}
+
+// Prices in a line of text: enough significant digits for a token worth cents
+pub fn format_price(price: f64) -> String {
+    if price >= 100.0 {
+        format!("{:.0}", price)
+    } else if price >= 1.0 {
+        format!("{:.2}", price)
+    } else {
+        format!("{:.4}", price)
+    }
+}
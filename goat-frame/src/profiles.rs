@@ -0,0 +1,326 @@
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use log::{debug, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Images are rendered while the client waits, so don't wait long on the hub or a pfp host
+const FETCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Larger pfps aren't worth downloading for a thumbnail
+const MAX_PFP_BYTES: usize = 2 * 1024 * 1024;
+
+// Pfps are drawn small, so they're kept at this size rather than as uploaded
+pub const AVATAR_SIZE: u32 = 128;
+
+// Most fids seen recently; the oldest is dropped past this
+const MAX_PROFILES: usize = 1024;
+
+// What a fid has set on Farcaster, as their hub reports it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub username: Option<String>,
+    pub display_name: Option<String>,
+    pub pfp_url: Option<String>,
+    // Their first verified Ethereum address, whose balance templates can show
+    pub address: Option<String>,
+}
+
+impl Profile {
+    // Their display name, else their username
+    pub fn name(&self) -> Option<&str> {
+        self.display_name
+            .as_deref()
+            .or(self.username.as_deref())
+            .filter(|name| !name.trim().is_empty())
+    }
+}
+
+#[derive(Deserialize)]
+struct UserDataResponse {
+    #[serde(default)]
+    messages: Vec<UserDataMessage>,
+}
+
+#[derive(Deserialize)]
+struct UserDataMessage {
+    data: UserData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserData {
+    user_data_body: Option<UserDataBody>,
+}
+
+#[derive(Deserialize)]
+struct UserDataBody {
+    #[serde(rename = "type")]
+    kind: String,
+    value: String,
+}
+
+pub async fn fetch_profile(
+    client: &reqwest::Client,
+    hub_url: &str,
+    fid: u64,
+) -> Result<Profile, String> {
+    let response = client
+        .get(format!("{}/v1/userDataByFid", hub_url))
+        .query(&[("fid", fid)])
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let body: UserDataResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+
+    let mut profile = Profile::default();
+    for body in body
+        .messages
+        .into_iter()
+        .filter_map(|m| m.data.user_data_body)
+    {
+        match body.kind.as_str() {
+            "USER_DATA_TYPE_USERNAME" => profile.username = Some(body.value),
+            "USER_DATA_TYPE_DISPLAY" => profile.display_name = Some(body.value),
+            "USER_DATA_TYPE_PFP" => profile.pfp_url = Some(body.value),
+            _ => {}
+        }
+    }
+    Ok(profile)
+}
+
+#[derive(Deserialize)]
+struct UsernameProof {
+    fid: u64,
+}
+
+// The fid that owns a username, None when nobody does
+pub async fn fetch_fid_by_username(
+    client: &reqwest::Client,
+    hub_url: &str,
+    username: &str,
+) -> Result<Option<u64>, String> {
+    let response = client
+        .get(format!("{}/v1/userNameProofByName", hub_url))
+        .query(&[("name", username)])
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let proof: UsernameProof = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+    Ok(Some(proof.fid))
+}
+
+#[derive(Deserialize)]
+struct VerificationsResponse {
+    #[serde(default)]
+    messages: Vec<VerificationMessage>,
+}
+
+#[derive(Deserialize)]
+struct VerificationMessage {
+    data: Verification,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Verification {
+    // Older hubs call it verificationAddEthAddressBody
+    #[serde(alias = "verificationAddEthAddressBody")]
+    verification_add_address_body: Option<VerificationBody>,
+}
+
+#[derive(Deserialize)]
+struct VerificationBody {
+    address: String,
+    // Missing on hubs from before Solana verifications, which only had Ethereum
+    #[serde(default)]
+    protocol: Option<String>,
+}
+
+// The first Ethereum address the fid has verified, if any
+pub async fn fetch_verified_address(
+    client: &reqwest::Client,
+    hub_url: &str,
+    fid: u64,
+) -> Result<Option<String>, String> {
+    let response = client
+        .get(format!("{}/v1/verificationsByFid", hub_url))
+        .query(&[("fid", fid)])
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let body: VerificationsResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+    Ok(body
+        .messages
+        .into_iter()
+        .filter_map(|m| m.data.verification_add_address_body)
+        .find(|body| {
+            body.protocol
+                .as_deref()
+                .is_none_or(|protocol| protocol == "PROTOCOL_ETHEREUM")
+        })
+        .map(|body| body.address))
+}
+
+// Downloads and shrinks a pfp. The URL is whatever the user set, so only public
+// https hosts are fetched. This build decodes PNG pfps only.
+pub async fn fetch_avatar(client: &reqwest::Client, url: &str) -> Result<RgbImage, String> {
+    let parsed = url::Url::parse(url).map_err(|err| format!("invalid URL: {}", err))?;
+    let public_host = matches!(parsed.host(), Some(url::Host::Domain(host)) if host != "localhost");
+    if parsed.scheme() != "https" || !public_host {
+        return Err("not a public https URL".to_string());
+    }
+    let response = client
+        .get(parsed)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_PFP_BYTES as u64)
+    {
+        return Err("too large".to_string());
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|err| format!("download failed: {}", err))?;
+    if bytes.len() > MAX_PFP_BYTES {
+        return Err("too large".to_string());
+    }
+    let image = image::load_from_memory(&bytes)
+        .map_err(|err| format!("undecodable: {}", err))?
+        .to_rgb8();
+    Ok(imageops::resize(
+        &image,
+        AVATAR_SIZE,
+        AVATAR_SIZE,
+        FilterType::Triangle,
+    ))
+}
+
+// A profile with its pfp decoded, ready to draw
+#[derive(Debug, Default)]
+pub struct CachedProfile {
+    pub profile: Profile,
+    pub avatar: Option<RgbImage>,
+    // Whether the pfp was asked for, so one that can't be drawn isn't fetched again
+    avatar_fetched: bool,
+}
+
+// Recently fetched profiles by fid, so drawing someone's image again doesn't wait on
+// the hub and their pfp host; a TTL of 0 fetches every time
+pub struct ProfileCache {
+    ttl: Duration,
+    profiles: Mutex<HashMap<u64, (Instant, Arc<CachedProfile>)>>,
+}
+
+impl ProfileCache {
+    pub fn new(ttl_secs: u64) -> Self {
+        ProfileCache {
+            ttl: Duration::from_secs(ttl_secs),
+            profiles: Mutex::default(),
+        }
+    }
+
+    // Someone whose profile can't be fetched is drawn without it, and asked for again
+    // next time rather than cached
+    pub async fn get(
+        &self,
+        client: &reqwest::Client,
+        hub_url: &str,
+        fid: u64,
+        with_avatar: bool,
+    ) -> Arc<CachedProfile> {
+        if let Some(cached) = self
+            .cached(fid)
+            .filter(|c| !with_avatar || c.avatar_fetched)
+        {
+            return cached;
+        }
+        let mut profile = match fetch_profile(client, hub_url, fid).await {
+            Ok(profile) => profile,
+            Err(err) => {
+                warn!("Failed to fetch profile of fid {}: {}", fid, err);
+                return Arc::default();
+            }
+        };
+        // Without it the profile still draws, just not the balance
+        profile.address = fetch_verified_address(client, hub_url, fid)
+            .await
+            .inspect_err(|err| debug!("Skipping address of fid {}: {}", fid, err))
+            .ok()
+            .flatten();
+        let avatar = match profile.pfp_url.as_deref().filter(|_| with_avatar) {
+            Some(url) => fetch_avatar(client, url)
+                .await
+                .inspect_err(|err| debug!("Skipping pfp of fid {} at {}: {}", fid, url, err))
+                .ok(),
+            None => None,
+        };
+        let cached = Arc::new(CachedProfile {
+            profile,
+            avatar,
+            avatar_fetched: with_avatar,
+        });
+        self.insert(fid, cached.clone());
+        cached
+    }
+
+    fn cached(&self, fid: u64) -> Option<Arc<CachedProfile>> {
+        let profiles = self.profiles.lock().unwrap();
+        profiles
+            .get(&fid)
+            .filter(|(stored, _)| stored.elapsed() < self.ttl)
+            .map(|(_, cached)| cached.clone())
+    }
+
+    fn insert(&self, fid: u64, cached: Arc<CachedProfile>) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut profiles = self.profiles.lock().unwrap();
+        if profiles.len() >= MAX_PROFILES && !profiles.contains_key(&fid) {
+            let ttl = self.ttl;
+            profiles.retain(|_, (stored, _)| stored.elapsed() < ttl);
+            if let Some(oldest) = profiles
+                .iter()
+                .min_by_key(|(_, (stored, _))| *stored)
+                .map(|(fid, _)| *fid)
+            {
+                profiles.remove(&oldest);
+            }
+        }
+        profiles.insert(fid, (Instant::now(), cached));
+    }
+}
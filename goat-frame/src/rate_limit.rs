@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Requests are counted in fixed one-minute windows, outside tests
+const WINDOW: Duration = Duration::from_secs(60);
+
+// Who a request is charged to: the verified fid, or the source IP when there isn't one
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateKey {
+    Fid(u64),
+    Ip(String),
+}
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+struct Windows {
+    windows: HashMap<RateKey, Window>,
+    last_sweep: Instant,
+}
+
+// Caps how often one user can press buttons; a limit of 0 turns it off
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    inner: Mutex<Windows>,
+}
+
+impl RateLimiter {
+    pub fn new(limit_per_min: u32) -> Self {
+        RateLimiter {
+            limit: limit_per_min,
+            window: WINDOW,
+            inner: Mutex::new(Windows {
+                windows: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    // Counts in shorter windows, so tests don't wait out a minute
+    #[cfg(test)]
+    pub fn with_window(limit: u32, window: Duration) -> Self {
+        RateLimiter {
+            window,
+            ..Self::new(limit)
+        }
+    }
+
+    // Counts a request against the key, or says how long until it may try again
+    pub fn check(&self, key: RateKey) -> Result<(), Duration> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+
+        // Drop expired windows once per window so one-off visitors don't pile up
+        if now.duration_since(inner.last_sweep) >= self.window {
+            inner
+                .windows
+                .retain(|_, window| now.duration_since(window.started) < self.window);
+            inner.last_sweep = now;
+        }
+
+        let window = inner.windows.entry(key).or_insert(Window {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            *window = Window {
+                started: now,
+                count: 0,
+            };
+        }
+
+        if window.count >= self.limit {
+            return Err(self.window - now.duration_since(window.started));
+        }
+        window.count += 1;
+        Ok(())
+    }
+}
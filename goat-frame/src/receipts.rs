@@ -0,0 +1,60 @@
+use crate::tx::{self, TxStatus};
+use log::warn;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// A pending transaction's receipt is asked for again at most this often, however
+// many times its image is drawn
+const RECHECK: Duration = Duration::from_secs(5);
+
+// Past this many tracked transactions the oldest are forgotten, and looked up
+// afresh if asked for again
+const MAX_TRACKED: usize = 10_000;
+
+// What's known of the transactions users sent, from their receipts. Settled ones
+// are kept as they are; pending ones are checked again once RECHECK has passed.
+#[derive(Default)]
+pub struct Receipts {
+    tracked: Mutex<HashMap<String, (Instant, TxStatus)>>,
+}
+
+impl Receipts {
+    // Sent when there's no RPC_URL to ask. A failed lookup keeps what was known,
+    // and a transaction never looked up is taken as pending.
+    pub async fn status(
+        &self,
+        client: &reqwest::Client,
+        rpc_url: Option<&str>,
+        hash: &str,
+    ) -> TxStatus {
+        let Some(rpc_url) = rpc_url else {
+            return TxStatus::Sent;
+        };
+        let known = self.tracked.lock().unwrap().get(hash).copied();
+        if let Some((checked, status)) = known {
+            if status.is_settled() || checked.elapsed() < RECHECK {
+                return status;
+            }
+        }
+        let status = match tx::fetch_receipt_status(client, rpc_url, hash).await {
+            Ok(status) => status,
+            Err(err) => {
+                warn!("Failed to fetch the receipt of {}: {}", hash, err);
+                known.map_or(TxStatus::Pending, |(_, status)| status)
+            }
+        };
+        let mut tracked = self.tracked.lock().unwrap();
+        if tracked.len() >= MAX_TRACKED && !tracked.contains_key(hash) {
+            if let Some(oldest) = tracked
+                .iter()
+                .min_by_key(|(_, (checked, _))| *checked)
+                .map(|(hash, _)| hash.clone())
+            {
+                tracked.remove(&oldest);
+            }
+        }
+        tracked.insert(hash.to_string(), (Instant::now(), status));
+        status
+    }
+}
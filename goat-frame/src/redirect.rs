@@ -0,0 +1,43 @@
+use crate::config::Config;
+use crate::errors::AppError;
+use log::{error, warn};
+use url::Url;
+
+// Where a post_redirect button sends the user. Paths are resolved against
+// CHECKOUT_BASE, and the final host must be on REDIRECT_ALLOWLIST so a bad
+// frames file can't turn us into an open redirect.
+pub fn checkout_url(redirect: &str, fid: Option<u64>, config: &Config) -> Result<String, AppError> {
+    let fid = fid.ok_or_else(|| AppError::BadRequest("Missing fid".to_string()))?;
+
+    let url = if redirect.starts_with('/') {
+        let Some(base) = &config.checkout_base else {
+            error!("CHECKOUT_BASE is not configured");
+            return Err(AppError::InternalServerError);
+        };
+        Url::parse(&format!("{}{}", base.trim_end_matches('/'), redirect))
+    } else {
+        Url::parse(redirect)
+    };
+    let mut url = url.map_err(|err| {
+        error!("Invalid redirect {:?}: {}", redirect, err);
+        AppError::InternalServerError
+    })?;
+
+    if !is_allowed_host(&url, &config.redirect_allowlist) {
+        warn!("Refusing redirect to {} (host not allowed)", url);
+        return Err(AppError::BadRequest(
+            "Redirect target not allowed".to_string(),
+        ));
+    }
+
+    url.query_pairs_mut().append_pair("fid", &fid.to_string());
+    Ok(url.to_string())
+}
+
+fn is_allowed_host(url: &Url, allowlist: &[String]) -> bool {
+    let web = url.scheme() == "https" || url.scheme() == "http";
+    let host = url.host_str().unwrap_or_default();
+    web && allowlist
+        .iter()
+        .any(|allowed| allowed.trim().eq_ignore_ascii_case(host))
+}
@@ -0,0 +1,84 @@
+use crate::errors::AppError;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Farcaster message timestamps count seconds from 2021-01-01 00:00:00 UTC
+pub const FARCASTER_EPOCH: u64 = 1_609_459_200;
+
+struct Seen {
+    // When each hash's message stops passing the timestamp check
+    hashes: HashMap<String, Instant>,
+    last_sweep: Instant,
+}
+
+// Refuses signed frame messages that are too old, or that have already been acted on,
+// so a captured Buy or Gift confirmation can't be posted again. Hashes only need
+// remembering until their message's timestamp is a window old, since it fails the
+// timestamp check from then on; a window of 0 turns both checks off.
+pub struct ReplayGuard {
+    window: Duration,
+    seen: Mutex<Seen>,
+}
+
+impl ReplayGuard {
+    pub fn new(max_age_secs: u64) -> Self {
+        ReplayGuard {
+            window: Duration::from_secs(max_age_secs),
+            seen: Mutex::new(Seen {
+                hashes: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    // Checks a message's signed timestamp against the clock, allowing the same
+    // leeway either side for clients whose clocks run ahead
+    pub fn check_timestamp(&self, timestamp: u64) -> Result<(), AppError> {
+        if self.window.is_zero() {
+            return Ok(());
+        }
+        if farcaster_now().abs_diff(timestamp) > self.window.as_secs() {
+            return Err(AppError::Unauthorized(
+                "Frame message has expired".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Records a message hash, failing if it was already seen while its message, signed
+    // at timestamp, still passes check_timestamp. One dated ahead does for longer.
+    pub fn check_hash(&self, message_hash: &str, timestamp: u64) -> Result<(), AppError> {
+        if self.window.is_zero() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let ahead = Duration::from_secs(timestamp.saturating_sub(farcaster_now()));
+        let mut seen = self.seen.lock().unwrap();
+        if now.duration_since(seen.last_sweep) >= self.window {
+            seen.hashes.retain(|_, expires| now < *expires);
+            seen.last_sweep = now;
+        }
+        let replayed = seen
+            .hashes
+            .get(message_hash)
+            .is_some_and(|expires| now < *expires);
+        if replayed {
+            return Err(AppError::Unauthorized(
+                "Frame message was already used".to_string(),
+            ));
+        }
+        seen.hashes
+            .insert(message_hash.to_string(), now + ahead + self.window);
+        Ok(())
+    }
+}
+
+// The current time as a Farcaster timestamp
+fn farcaster_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+        .saturating_sub(FARCASTER_EPOCH)
+}
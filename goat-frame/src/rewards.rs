@@ -0,0 +1,63 @@
+use crate::config::Config;
+use crate::errors::AppError;
+use crate::tx::{self, TransactionParams, TransactionResponse};
+use log::error;
+
+// What an account has earned and not yet claimed, and the claim of all of it
+const CLAIMABLE_SIGNATURE: &str = "claimable(address)";
+const CLAIM_SIGNATURE: &str = "claim()";
+
+// The GOAT REWARDS_CONTRACT holds for account, in wei
+pub async fn fetch_claimable(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    contract: &[u8; 20],
+    account: &[u8; 20],
+) -> Result<u128, String> {
+    let mut input = tx::function_selector(CLAIMABLE_SIGNATURE).to_vec();
+    input.extend_from_slice(&tx::encode_address(account));
+    let output = tx::eth_call(client, rpc_url, contract, &input).await?;
+    tx::decode_amount(&output, 0)
+        .ok_or_else(|| format!("invalid claimable amount 0x{}", hex::encode(&output)))
+}
+
+// Claims the connected wallet's rewards. The contract pays out whatever is claimable
+// when it runs, so this only checks there's something to claim.
+pub async fn claim_transaction(
+    address: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<TransactionResponse, AppError> {
+    let (Some(contract), Some(rpc_url)) = (&config.rewards_contract, &config.rpc_url) else {
+        error!("REWARDS_CONTRACT is not configured");
+        return Err(AppError::InternalServerError);
+    };
+    let account = tx::parse_address(address.unwrap_or_default())?;
+    // Checked when the config was loaded
+    let contract = tx::parse_address(contract)?;
+    match fetch_claimable(client, rpc_url, &contract, &account).await {
+        Ok(0) => return Err(AppError::BadRequest("No rewards to claim yet".to_string())),
+        Ok(_) => {}
+        Err(err) => {
+            error!("Failed to fetch claimable rewards: {}", err);
+            return Err(AppError::InternalServerError);
+        }
+    }
+
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([{
+                "type": "function",
+                "name": "claim",
+                "stateMutability": "nonpayable",
+                "inputs": [],
+                "outputs": []
+            }]),
+            to: format!("0x{}", hex::encode(contract)),
+            data: format!("0x{}", hex::encode(tx::function_selector(CLAIM_SIGNATURE))),
+            value: "0".to_string(),
+        },
+    })
+}
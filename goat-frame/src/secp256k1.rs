@@ -0,0 +1,374 @@
+// Just enough of the secp256k1 curve to recover who signed a hash, as Ethereum's
+// ecrecover does, without trusting an RPC node to say. Numbers are 256 bits held in
+// four 64 bit limbs, least significant first. Nothing here is secret, so none of it
+// needs to run in constant time.
+//
+// ring, the crypto the TLS stack already brings in, has no secp256k1, and recovery
+// is all sign-in needs of it, so it's done here rather than by pulling in a second,
+// much larger crypto crate (k256 or libsecp256k1's bindings) for one function. It's
+// only ever handed public values, and is tested against published signatures and
+// the malformed ones recovery has to turn away.
+
+type U256 = [u64; 4];
+
+// The field the curve y² = x³ + 7 is over, the order of its generator G, and G
+const P: U256 = [
+    0xFFFF_FFFE_FFFF_FC2F,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+    0xFFFF_FFFF_FFFF_FFFF,
+];
+const N: U256 = [
+    0xBFD2_5E8C_D036_4141,
+    0xBAAE_DCE6_AF48_A03B,
+    0xFFFF_FFFF_FFFF_FFFE,
+    0xFFFF_FFFF_FFFF_FFFF,
+];
+const G: Point = Point {
+    x: [
+        0x59F2_815B_16F8_1798,
+        0x029B_FCDB_2DCE_28D9,
+        0x55A0_6295_CE87_0B07,
+        0x79BE_667E_F9DC_BBAC,
+    ],
+    y: [
+        0x9C47_D08F_FB10_D4B8,
+        0xFD17_B448_A685_5419,
+        0x5DA4_FBFC_0E11_08A8,
+        0x483A_DA77_26A3_C465,
+    ],
+    z: ONE,
+};
+
+const ZERO: U256 = [0; 4];
+const ONE: U256 = [1, 0, 0, 0];
+
+// 2²⁵⁶ mod P, which folds the top half of a product back into the bottom
+const FOLD: u128 = 0x1_0000_03D1;
+
+// The public key, x then y, that signed hash with the 64 byte r and s and a
+// recovery id of 0 or 1 (v - 27). None when the signature can't have come from any,
+// or has an s in the upper half of the order: n - s signs the same hash too, so
+// like Ethereum transactions since EIP-2 only the lower is taken.
+pub fn recover(hash: &[u8; 32], rs: &[u8; 64], recovery_id: u8) -> Option<[u8; 64]> {
+    let (r, s) = (from_be(&rs[..32]), from_be(&rs[32..]));
+    if r == ZERO || s == ZERO || !less(&r, &N) || less(&shr1(&N), &s) || recovery_id > 1 {
+        return None;
+    }
+
+    // R, the point the signer's nonce made, has r as its x and y of the given parity
+    let x = r;
+    let rhs = fe_add(&fe_mul(&fe_mul(&x, &x), &x), &[7, 0, 0, 0]);
+    let mut y = fe_pow(&rhs, &sqrt_exponent());
+    if fe_mul(&y, &y) != rhs {
+        return None;
+    }
+    if (y[0] & 1) as u8 != recovery_id {
+        y = sub_mod(&ZERO, &y, &P);
+    }
+    let point = Point { x, y, z: ONE };
+
+    // The key is r⁻¹(sR - eG)
+    let e = reduce_once(from_be(hash), &N);
+    let r_inv = scalar_inv(&r);
+    let u1 = scalar_mul(&sub_mod(&ZERO, &e, &N), &r_inv);
+    let u2 = scalar_mul(&s, &r_inv);
+    let key = G.mul(&u1).add(&point.mul(&u2));
+    let (x, y) = key.affine()?;
+
+    let mut public_key = [0u8; 64];
+    public_key[..32].copy_from_slice(&to_be(&x));
+    public_key[32..].copy_from_slice(&to_be(&y));
+    Some(public_key)
+}
+
+// Signs hash with a private key, returning r, s and v (27 or 28), for tests to sign
+// messages they then recover. The nonce comes from the key and hash, so signing the
+// same hash again gives the same signature.
+#[cfg(test)]
+pub fn sign(hash: &[u8; 32], private_key: &[u8; 32]) -> [u8; 65] {
+    let d = from_be(private_key);
+    let e = reduce_once(from_be(hash), &N);
+    let mut seed = private_key.to_vec();
+    seed.extend_from_slice(hash);
+    let k = reduce_once(from_be(&crate::tx::keccak256(&seed)), &N);
+    let (x, y) = G.mul(&k).affine().unwrap();
+    let r = reduce_once(x, &N);
+    let mut s = scalar_mul(&scalar_inv(&k), &add_mod(&e, &scalar_mul(&r, &d), &N));
+    let mut recovery_id = (y[0] & 1) as u8;
+    // Ethereum only takes the lower of s and n - s
+    if less(&shr1(&N), &s) {
+        s = sub_mod(&ZERO, &s, &N);
+        recovery_id ^= 1;
+    }
+    let mut signature = [0u8; 65];
+    signature[..32].copy_from_slice(&to_be(&r));
+    signature[32..64].copy_from_slice(&to_be(&s));
+    signature[64] = 27 + recovery_id;
+    signature
+}
+
+// A point in Jacobian coordinates, (x / z², y / z³); z of 0 is the point at infinity
+#[derive(Clone, Copy)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+impl Point {
+    const INFINITY: Point = Point {
+        x: ONE,
+        y: ONE,
+        z: ZERO,
+    };
+
+    fn is_infinity(&self) -> bool {
+        self.z == ZERO
+    }
+
+    fn double(&self) -> Point {
+        if self.is_infinity() || self.y == ZERO {
+            return Point::INFINITY;
+        }
+        let a = fe_mul(&self.x, &self.x);
+        let b = fe_mul(&self.y, &self.y);
+        let c = fe_mul(&b, &b);
+        let x_b = fe_add(&self.x, &b);
+        let d = fe_sub(&fe_sub(&fe_mul(&x_b, &x_b), &a), &c);
+        let d = fe_add(&d, &d);
+        let e = fe_add(&fe_add(&a, &a), &a);
+        let f = fe_mul(&e, &e);
+        let x = fe_sub(&f, &fe_add(&d, &d));
+        let c8 = fe_add(&c, &c);
+        let c8 = fe_add(&c8, &c8);
+        let c8 = fe_add(&c8, &c8);
+        let y = fe_sub(&fe_mul(&e, &fe_sub(&d, &x)), &c8);
+        let z = fe_mul(&self.y, &self.z);
+        Point {
+            x,
+            y,
+            z: fe_add(&z, &z),
+        }
+    }
+
+    fn add(&self, other: &Point) -> Point {
+        if self.is_infinity() {
+            return *other;
+        }
+        if other.is_infinity() {
+            return *self;
+        }
+        let z1z1 = fe_mul(&self.z, &self.z);
+        let z2z2 = fe_mul(&other.z, &other.z);
+        let u1 = fe_mul(&self.x, &z2z2);
+        let u2 = fe_mul(&other.x, &z1z1);
+        let s1 = fe_mul(&fe_mul(&self.y, &other.z), &z2z2);
+        let s2 = fe_mul(&fe_mul(&other.y, &self.z), &z1z1);
+        if u1 == u2 {
+            return if s1 == s2 {
+                self.double()
+            } else {
+                Point::INFINITY
+            };
+        }
+        let h = fe_sub(&u2, &u1);
+        let r = fe_sub(&s2, &s1);
+        let h2 = fe_mul(&h, &h);
+        let h3 = fe_mul(&h, &h2);
+        let u1h2 = fe_mul(&u1, &h2);
+        let x = fe_sub(&fe_sub(&fe_mul(&r, &r), &h3), &fe_add(&u1h2, &u1h2));
+        let y = fe_sub(&fe_mul(&r, &fe_sub(&u1h2, &x)), &fe_mul(&s1, &h3));
+        let z = fe_mul(&fe_mul(&h, &self.z), &other.z);
+        Point { x, y, z }
+    }
+
+    fn mul(&self, k: &U256) -> Point {
+        let mut result = Point::INFINITY;
+        for bit in (0..256).rev() {
+            result = result.double();
+            if (k[bit / 64] >> (bit % 64)) & 1 == 1 {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+
+    fn affine(&self) -> Option<(U256, U256)> {
+        if self.is_infinity() {
+            return None;
+        }
+        let z_inv = fe_pow(&self.z, &sub(&P, &[2, 0, 0, 0]).0);
+        let z_inv2 = fe_mul(&z_inv, &z_inv);
+        Some((
+            fe_mul(&self.x, &z_inv2),
+            fe_mul(&fe_mul(&self.y, &z_inv2), &z_inv),
+        ))
+    }
+}
+
+fn from_be(bytes: &[u8]) -> U256 {
+    let mut limbs = ZERO;
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let end = 32 - 8 * i;
+        *limb = u64::from_be_bytes(bytes[end - 8..end].try_into().unwrap());
+    }
+    limbs
+}
+
+fn to_be(a: &U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, limb) in a.iter().enumerate() {
+        let end = 32 - 8 * i;
+        bytes[end - 8..end].copy_from_slice(&limb.to_be_bytes());
+    }
+    bytes
+}
+
+fn less(a: &U256, b: &U256) -> bool {
+    a.iter().rev().cmp(b.iter().rev()).is_lt()
+}
+
+fn add(a: &U256, b: &U256) -> (U256, bool) {
+    let mut sum = ZERO;
+    let mut carry = false;
+    for i in 0..4 {
+        let (s, c1) = a[i].overflowing_add(b[i]);
+        let (s, c2) = s.overflowing_add(carry as u64);
+        sum[i] = s;
+        carry = c1 || c2;
+    }
+    (sum, carry)
+}
+
+fn sub(a: &U256, b: &U256) -> (U256, bool) {
+    let mut difference = ZERO;
+    let mut borrow = false;
+    for i in 0..4 {
+        let (d, b1) = a[i].overflowing_sub(b[i]);
+        let (d, b2) = d.overflowing_sub(borrow as u64);
+        difference[i] = d;
+        borrow = b1 || b2;
+    }
+    (difference, borrow)
+}
+
+fn shr1(a: &U256) -> U256 {
+    let mut shifted = ZERO;
+    for i in 0..4 {
+        shifted[i] = a[i] >> 1 | a.get(i + 1).map_or(0, |next| next << 63);
+    }
+    shifted
+}
+
+// a mod m for an a under 2m
+fn reduce_once(a: U256, m: &U256) -> U256 {
+    if less(&a, m) {
+        a
+    } else {
+        sub(&a, m).0
+    }
+}
+
+// a + b and a - b mod m, for a and b under m
+fn add_mod(a: &U256, b: &U256, m: &U256) -> U256 {
+    match add(a, b) {
+        (sum, true) => sub(&sum, m).0,
+        (sum, false) => reduce_once(sum, m),
+    }
+}
+
+fn sub_mod(a: &U256, b: &U256, m: &U256) -> U256 {
+    match sub(a, b) {
+        (difference, true) => add(&difference, m).0,
+        (difference, false) => difference,
+    }
+}
+
+fn mul_wide(a: &U256, b: &U256) -> [u64; 8] {
+    let mut product = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let t = a[i] as u128 * b[j] as u128 + product[i + j] as u128 + carry;
+            product[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        product[i + 4] = carry as u64;
+    }
+    product
+}
+
+fn fe_add(a: &U256, b: &U256) -> U256 {
+    add_mod(a, b, &P)
+}
+
+fn fe_sub(a: &U256, b: &U256) -> U256 {
+    sub_mod(a, b, &P)
+}
+
+// P is 2²⁵⁶ less FOLD, so a product's top half times FOLD can stand in for it, twice
+// over since that can carry past 256 bits again
+fn fe_mul(a: &U256, b: &U256) -> U256 {
+    let wide = mul_wide(a, b);
+    let mut folded = [0u64; 5];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let t = wide[i] as u128 + wide[i + 4] as u128 * FOLD + carry;
+        folded[i] = t as u64;
+        carry = t >> 64;
+    }
+    folded[4] = carry as u64;
+
+    let mut result = ZERO;
+    let mut carry = folded[4] as u128 * FOLD;
+    for i in 0..4 {
+        let t = folded[i] as u128 + carry;
+        result[i] = t as u64;
+        carry = t >> 64;
+    }
+    if carry != 0 {
+        result = add(&result, &[FOLD as u64, 0, 0, 0]).0;
+    }
+    reduce_once(result, &P)
+}
+
+fn fe_pow(base: &U256, exponent: &U256) -> U256 {
+    pow(base, exponent, fe_mul)
+}
+
+// (P + 1) / 4, as P is 3 mod 4 a square's root is it to this power
+fn sqrt_exponent() -> U256 {
+    shr1(&shr1(&add(&P, &ONE).0))
+}
+
+// Products mod N are rare enough here to reduce a bit at a time
+fn scalar_mul(a: &U256, b: &U256) -> U256 {
+    let wide = mul_wide(a, b);
+    let mut remainder = ZERO;
+    for bit in (0..512).rev() {
+        let overflow = remainder[3] >> 63 == 1;
+        remainder = add(&remainder, &remainder).0;
+        remainder[0] |= (wide[bit / 64] >> (bit % 64)) & 1;
+        if overflow || !less(&remainder, &N) {
+            remainder = sub(&remainder, &N).0;
+        }
+    }
+    remainder
+}
+
+// N is prime, so a⁻¹ is a to the power N - 2
+fn scalar_inv(a: &U256) -> U256 {
+    pow(a, &sub(&N, &[2, 0, 0, 0]).0, scalar_mul)
+}
+
+fn pow(base: &U256, exponent: &U256, mul: fn(&U256, &U256) -> U256) -> U256 {
+    let mut result = ONE;
+    for bit in (0..256).rev() {
+        result = mul(&result, &result);
+        if (exponent[bit / 64] >> (bit % 64)) & 1 == 1 {
+            result = mul(&result, base);
+        }
+    }
+    result
+}
@@ -0,0 +1,81 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServerHandle, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+use log::{info, warn};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Requests currently being handled, so shutdown can report what it waited for
+#[derive(Default)]
+pub struct InFlight(AtomicUsize);
+
+impl InFlight {
+    pub fn current(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Decrements on drop so cancelled and failed requests are released too
+struct InFlightGuard(web::Data<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0 .0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub async fn track_in_flight(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let _guard = req
+        .app_data::<web::Data<InFlight>>()
+        .cloned()
+        .map(|in_flight| {
+            in_flight.0.fetch_add(1, Ordering::SeqCst);
+            InFlightGuard(in_flight)
+        });
+    next.call(req).await
+}
+
+// Resolves on Ctrl-C, or SIGTERM where there is one
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut sigterm = signal(SignalKind::terminate()).expect("SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = sigterm.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+// Stops accepting connections on SIGTERM/SIGINT and lets in-flight requests finish,
+// up to the server's shutdown timeout
+pub async fn drain_on_signal(server: ServerHandle, in_flight: web::Data<InFlight>) {
+    shutdown_signal().await;
+
+    let pending = in_flight.current();
+    info!(
+        "Shutting down: no longer accepting connections, draining {} in-flight requests",
+        pending
+    );
+    server.stop(true).await;
+
+    let abandoned = in_flight.current();
+    if abandoned > 0 {
+        warn!(
+            "Shutdown timeout reached with {} requests still in flight",
+            abandoned
+        );
+    }
+    info!(
+        "Shutdown complete: drained {} requests",
+        pending.saturating_sub(abandoned)
+    );
+}
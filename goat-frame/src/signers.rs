@@ -0,0 +1,351 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::countdown::unix_now;
+use crate::errors::AppError;
+use crate::tx;
+use actix_web::{web, HttpResponse};
+use log::{error, info};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+#[cfg(unix)]
+use std::fs::Permissions;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// How long the user has to approve a signer request in their client
+const REQUEST_TTL_SECS: u64 = 86400;
+
+// The contract clients check a signed key request's EIP-712 signature against, on OP
+// mainnet
+const KEY_REQUEST_VALIDATOR: &str = "0x00000000fc700472606ed4fa22623acf62c60553";
+const OP_MAINNET_CHAIN_ID: u64 = 10;
+
+// Where a user's signer is in onboarding
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerState {
+    // Waiting for the user to approve the request in their client
+    Pending,
+    // Added onchain, so the key can publish for the user
+    Approved,
+}
+
+// An Ed25519 key we asked a user to add as a signer for their fid
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignerRecord {
+    // Hex PKCS#8 document holding the private key
+    pub private_key: String,
+    // 0x-prefixed hex public key
+    pub public_key: String,
+    // The client's token for polling the request
+    pub token: String,
+    pub deeplink_url: String,
+    pub state: SignerState,
+    // When the request lapses unapproved, in Unix seconds. Records saved before there
+    // was one read as long lapsed.
+    #[serde(default)]
+    pub deadline: u64,
+}
+
+impl SignerRecord {
+    // A request the user never approved in time, which the client won't take any more
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.state == SignerState::Pending && self.deadline <= now
+    }
+
+    // The key to sign casts with, once the user approved it. Nothing publishes yet;
+    // this is for posting "I just boosted ..." casts for users who opted in.
+    #[allow(dead_code)]
+    pub fn key_pair(&self) -> Option<Ed25519KeyPair> {
+        if self.state != SignerState::Approved {
+            return None;
+        }
+        let pkcs8 = hex::decode(&self.private_key).ok()?;
+        Ed25519KeyPair::from_pkcs8(&pkcs8).ok()
+    }
+}
+
+// Signer keys by fid, saved to SIGNERS_PATH so they survive restarts. The file holds
+// private keys that can post as each user, so keep it as private as the server's
+// own secrets.
+pub struct SignerStore {
+    path: Option<PathBuf>,
+    signers: Mutex<HashMap<u64, SignerRecord>>,
+}
+
+impl SignerStore {
+    // Keeps keys in memory only
+    pub fn in_memory() -> Self {
+        SignerStore {
+            path: None,
+            signers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Loads the keys saved at path, starting empty if the file doesn't exist yet
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let signers = match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+        Ok(SignerStore {
+            path: Some(path.to_path_buf()),
+            signers: Mutex::new(signers),
+        })
+    }
+
+    pub fn get(&self, fid: u64) -> Option<SignerRecord> {
+        self.signers.lock().unwrap().get(&fid).cloned()
+    }
+
+    pub fn set(&self, fid: u64, record: SignerRecord) {
+        let mut signers = self.signers.lock().unwrap();
+        signers.insert(fid, record);
+
+        // Requests are rare and the file small, so it's rewritten under the lock to keep
+        // saves in order
+        if let Some(path) = &self.path {
+            let saved = serde_json::to_string(&*signers)
+                .map_err(io::Error::other)
+                .and_then(|json| write_private(path, &json));
+            if let Err(err) = saved {
+                error!("Failed to save signers to {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+// Writes the file readable and writable by the server's own user alone, tightening
+// one that was made with looser permissions
+fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+// The app account that requests signers, from APP_FID, APP_CUSTODY_ADDRESS and
+// APP_SIGNER_RPC_URL
+struct AppAccount<'a> {
+    fid: u64,
+    custody_address: &'a str,
+    signer_rpc_url: &'a str,
+}
+
+fn app_account(config: &Config) -> Result<AppAccount<'_>, AppError> {
+    match (
+        config.app_fid,
+        &config.app_custody_address,
+        &config.app_signer_rpc_url,
+    ) {
+        (Some(fid), Some(custody_address), Some(signer_rpc_url)) => Ok(AppAccount {
+            fid,
+            custody_address,
+            signer_rpc_url,
+        }),
+        _ => Err(AppError::NotFound("Signers aren't enabled".to_string())),
+    }
+}
+
+// The EIP-712 SignedKeyRequest the app's custody address signs, vouching that the
+// app asked for the key
+pub fn signed_key_request(request_fid: u64, key: &str, deadline: u64) -> serde_json::Value {
+    serde_json::json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" }
+            ],
+            "SignedKeyRequest": [
+                { "name": "requestFid", "type": "uint256" },
+                { "name": "key", "type": "bytes" },
+                { "name": "deadline", "type": "uint256" }
+            ]
+        },
+        "primaryType": "SignedKeyRequest",
+        "domain": {
+            "name": "Farcaster SignedKeyRequestValidator",
+            "version": "1",
+            "chainId": OP_MAINNET_CHAIN_ID,
+            "verifyingContract": KEY_REQUEST_VALIDATOR
+        },
+        "message": {
+            "requestFid": request_fid,
+            "key": key,
+            "deadline": deadline
+        }
+    })
+}
+
+#[derive(Deserialize)]
+struct KeyRequestResponse {
+    result: KeyRequestResult,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyRequestResult {
+    signed_key_request: KeyRequest,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyRequest {
+    token: String,
+    deeplink_url: String,
+    // "pending", "approved" once the user signed, "completed" once it's onchain
+    state: String,
+    user_fid: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SignerResponse {
+    state: SignerState,
+    // Opens the user's client to approve the key
+    deeplink_url: String,
+}
+
+impl From<&SignerRecord> for SignerResponse {
+    fn from(record: &SignerRecord) -> Self {
+        SignerResponse {
+            state: record.state,
+            deeplink_url: record.deeplink_url.clone(),
+        }
+    }
+}
+
+// Starts onboarding a signer for the signed-in user: makes a key, has the app's
+// custody address sign a request for it, and registers the request with the client,
+// whose deep link the page opens for the user to approve. A user who already has a
+// signer, or a request still open, gets theirs back instead.
+pub async fn request_signer(
+    session: Session,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    store: web::Data<SignerStore>,
+) -> Result<HttpResponse, AppError> {
+    let app = app_account(&config)?;
+    if let Some(record) = current(&store, session.fid) {
+        return Ok(HttpResponse::Ok().json(SignerResponse::from(&record)));
+    }
+
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).map_err(|_| {
+        error!("Failed to generate a signer key");
+        AppError::InternalServerError
+    })?;
+    let key_pair =
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).map_err(|_| AppError::InternalServerError)?;
+    let public_key = format!("0x{}", hex::encode(key_pair.public_key().as_ref()));
+    let deadline = unix_now() + REQUEST_TTL_SECS;
+
+    let typed_data = signed_key_request(app.fid, &public_key, deadline);
+    let signature = tx::rpc_call(
+        &client,
+        app.signer_rpc_url,
+        "eth_signTypedData_v4",
+        serde_json::json!([app.custody_address, typed_data.to_string()]),
+    )
+    .await
+    .map_err(|err| {
+        error!("Failed to sign a signed key request: {}", err);
+        AppError::InternalServerError
+    })?;
+
+    let response = client
+        .post(format!(
+            "{}/v2/signed-key-requests",
+            config.signed_key_request_url
+        ))
+        .json(&serde_json::json!({
+            "key": public_key,
+            "requestFid": app.fid,
+            "signature": signature,
+            "deadline": deadline
+        }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| {
+            error!("Failed to create a signed key request: {}", err);
+            AppError::InternalServerError
+        })?;
+    let request = read_key_request(response).await?;
+
+    info!("Requested a signer for fid {}", session.fid);
+    let record = SignerRecord {
+        private_key: hex::encode(pkcs8.as_ref()),
+        public_key,
+        token: request.token,
+        deeplink_url: request.deeplink_url,
+        state: SignerState::Pending,
+        deadline,
+    };
+    store.set(session.fid, record.clone());
+    Ok(HttpResponse::Ok().json(SignerResponse::from(&record)))
+}
+
+// Where the signed-in user's signer is, polling the client while it's pending. The
+// page calls this until the state is approved.
+pub async fn signer_status(
+    session: Session,
+    config: web::Data<Config>,
+    client: web::Data<reqwest::Client>,
+    store: web::Data<SignerStore>,
+) -> Result<HttpResponse, AppError> {
+    app_account(&config)?;
+    let mut record = current(&store, session.fid)
+        .ok_or_else(|| AppError::NotFound("No signer requested".to_string()))?;
+    if record.state == SignerState::Pending {
+        let response = client
+            .get(format!(
+                "{}/v2/signed-key-request",
+                config.signed_key_request_url
+            ))
+            .query(&[("token", &record.token)])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| {
+                error!("Failed to poll a signed key request: {}", err);
+                AppError::InternalServerError
+            })?;
+        let request = read_key_request(response).await?;
+        // Only a key the same user added can publish for them
+        if request.state == "completed" && request.user_fid == Some(session.fid) {
+            info!("fid {} approved their signer", session.fid);
+            record.state = SignerState::Approved;
+            store.set(session.fid, record.clone());
+        }
+    }
+    Ok(HttpResponse::Ok().json(SignerResponse::from(&record)))
+}
+
+// The user's signer, unless it's a request that lapsed, which is as good as none
+fn current(store: &SignerStore, fid: u64) -> Option<SignerRecord> {
+    store
+        .get(fid)
+        .filter(|record| !record.is_expired(unix_now()))
+}
+
+async fn read_key_request(response: reqwest::Response) -> Result<KeyRequest, AppError> {
+    let body: KeyRequestResponse = response.json().await.map_err(|err| {
+        error!("Unexpected signed key request response: {}", err);
+        AppError::InternalServerError
+    })?;
+    Ok(body.result.signed_key_request)
+}
@@ -0,0 +1,141 @@
+use crate::config::Config;
+use crate::countdown::unix_now;
+use crate::errors::AppError;
+use crate::tx::{self, TransactionParams, TransactionResponse};
+use log::error;
+
+// Router entry points: the quote for a path, and the swap of the value sent along it
+const GET_AMOUNTS_OUT_SIGNATURE: &str = "getAmountsOut(uint256,address[])";
+const SWAP_SIGNATURE: &str = "swapExactETHForTokens(uint256,address[],address,uint256)";
+
+// The router refuses the swap once this long has passed since it was built, rather
+// than filling it at whatever the price has become
+pub const DEADLINE_SECS: u64 = 20 * 60;
+
+// A swap of the GOAT the user typed into the Buy input for SWAP_TOKEN, paid from and
+// sent to the wallet the client connected. The least it accepts is the router's
+// quote less SWAP_SLIPPAGE_BPS.
+pub async fn swap_transaction(
+    address: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<TransactionResponse, AppError> {
+    let (Some(router), Some(wrapped_goat), Some(token), Some(rpc_url)) = (
+        &config.swap_router,
+        &config.swap_wrapped_goat,
+        &config.swap_token,
+        &config.rpc_url,
+    ) else {
+        error!("SWAP_ROUTER is not configured");
+        return Err(AppError::InternalServerError);
+    };
+    let address = tx::parse_address(address.unwrap_or_default())?;
+    let amount_in = tx::parse_amount(amount.unwrap_or_default())?;
+    if amount_in == 0 {
+        return Err(AppError::BadRequest(
+            "Enter how much GOAT to swap".to_string(),
+        ));
+    }
+    tx::check_balance(client, config, &address, amount_in).await?;
+
+    // Checked when the config was loaded
+    let router = tx::parse_address(router)?;
+    let path = [tx::parse_address(wrapped_goat)?, tx::parse_address(token)?];
+    let quote = match quote(client, rpc_url, &router, amount_in, &path).await {
+        Ok(quote) => quote,
+        Err(err) => {
+            error!("Failed to quote a swap: {}", err);
+            return Err(AppError::InternalServerError);
+        }
+    };
+    let min_out = min_out(quote, config.swap_slippage_bps);
+    if min_out == 0 {
+        return Err(AppError::BadRequest(format!(
+            "{} GOAT is too little to swap",
+            tx::format_amount(amount_in)
+        )));
+    }
+
+    let mut data = tx::function_selector(SWAP_SIGNATURE).to_vec();
+    data.extend_from_slice(&tx::encode_amount(min_out));
+    // The path is the one dynamic argument, so it goes after the four heads
+    data.extend_from_slice(&tx::encode_uint(4 * 32));
+    data.extend_from_slice(&tx::encode_address(&address));
+    data.extend_from_slice(&tx::encode_uint(unix_now() + DEADLINE_SECS));
+    data.extend_from_slice(&encode_path(&path));
+
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([{
+                "type": "function",
+                "name": "swapExactETHForTokens",
+                "stateMutability": "payable",
+                "inputs": [
+                    { "name": "amountOutMin", "type": "uint256" },
+                    { "name": "path", "type": "address[]" },
+                    { "name": "to", "type": "address" },
+                    { "name": "deadline", "type": "uint256" }
+                ],
+                "outputs": [{ "name": "amounts", "type": "uint256[]" }]
+            }]),
+            to: format!("0x{}", hex::encode(router)),
+            data: format!("0x{}", hex::encode(data)),
+            value: amount_in.to_string(),
+        },
+    })
+}
+
+// The least of quote to accept, slippage_bps basis points under it, rounded down
+pub fn min_out(quote: u128, slippage_bps: u32) -> u128 {
+    let kept = 10_000 - u128::from(slippage_bps.min(10_000));
+    quote / 10_000 * kept + quote % 10_000 * kept / 10_000
+}
+
+// How much of the path's last token amount_in buys, asking the router with eth_call
+async fn quote(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    router: &[u8; 20],
+    amount_in: u128,
+    path: &[[u8; 20]],
+) -> Result<u128, String> {
+    let mut input = tx::function_selector(GET_AMOUNTS_OUT_SIGNATURE).to_vec();
+    input.extend_from_slice(&tx::encode_amount(amount_in));
+    input.extend_from_slice(&tx::encode_uint(2 * 32));
+    input.extend_from_slice(&encode_path(path));
+    let output = tx::eth_call(client, rpc_url, router, &input).await?;
+    last_amount(&output).ok_or_else(|| format!("invalid quote 0x{}", hex::encode(&output)))
+}
+
+// A uint256[] is its length and then its elements
+pub fn encode_path(path: &[[u8; 20]]) -> Vec<u8> {
+    let mut encoded = tx::encode_uint(path.len() as u64).to_vec();
+    for address in path {
+        encoded.extend_from_slice(&tx::encode_address(address));
+    }
+    encoded
+}
+
+// The last element of an ABI-encoded uint256[] return value: an offset to the array,
+// its length, then its elements. None if it's malformed or past u128.
+pub fn last_amount(output: &[u8]) -> Option<u128> {
+    let word = |at: usize| output.get(at..at.checked_add(32)?);
+    let as_usize = |bytes: &[u8]| -> Option<usize> {
+        bytes[..24].iter().all(|b| *b == 0).then_some(())?;
+        usize::try_from(u64::from_be_bytes(bytes[24..].try_into().ok()?)).ok()
+    };
+    let offset = as_usize(word(0)?)?;
+    let len = as_usize(word(offset)?)?;
+    if len == 0 {
+        return None;
+    }
+    // The elements follow the length, so the last ends len words past it
+    let last = word(offset.checked_add(len.checked_mul(32)?)?)?;
+    if last[..16].iter().any(|b| *b != 0) {
+        return None;
+    }
+    Some(u128::from_be_bytes(last[16..].try_into().ok()?))
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod actions_tests {
+    use crate::actions::{cast_action, cast_action_metadata, composer_form, composer_metadata};
+    use crate::tests::test_registry;
+    use crate::Config;
+    use actix_web::{http::StatusCode, test, web, App};
+
+    fn config() -> web::Data<Config> {
+        web::Data::new(Config {
+            domain: "https://frame.example.com".to_string(),
+            ..Config::default()
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_composer_metadata() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .route("/api/composer", web::get().to(composer_metadata)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/api/composer").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert the action describes itself as a composer action that posts back
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "composer",
+                "name": "GOAT Store",
+                "icon": "package",
+                "description": "Buy GOAT in a cast",
+                "aboutUrl": "https://frame.example.com/",
+                "imageUrl": "https://frame.example.com/assets/main.png",
+                "action": { "type": "post" }
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_composer_post_opens_form() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .route("/api/composer", web::post().to(composer_form)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/composer")
+            .set_json(serde_json::json!({
+                "untrustedData": { "fid": 2, "buttonIndex": 1 },
+                "trustedData": { "messageBytes": "0a0b0c" }
+            }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert the client is sent to the store to buy
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "form",
+                "title": "Buy GOAT",
+                "url": "https://frame.example.com/"
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_cast_action_metadata() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .route("/api/cast-action", web::get().to(cast_action_metadata)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/api/cast-action")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert the action is named after the store and posts back here
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "name": "GOAT Store",
+                "icon": "gift",
+                "description": "Gift GOAT to this caster",
+                "aboutUrl": "https://frame.example.com/",
+                "action": { "type": "post" }
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_cast_action_opens_gift_frame() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .app_data(web::Data::new(test_registry()))
+                .route("/api/cast-action", web::post().to(cast_action)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/cast-action")
+            .set_json(serde_json::json!({
+                "untrustedData": {
+                    "fid": 2,
+                    "buttonIndex": 1,
+                    "castId": { "fid": 226, "hash": "0xa48dd46161d8e57725f5e26e34ec19c13ff7f3b9" }
+                },
+                "trustedData": { "messageBytes": "0a0b0c" }
+            }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert the client is handed the store frame, opened on Gift
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "frame",
+                "frameUrl": "https://frame.example.com/?screen=gift"
+            })
+        );
+
+        // Assert a post without the cast it was used on is refused
+        let req = test::TestRequest::post()
+            .uri("/api/cast-action")
+            .set_json(serde_json::json!({ "untrustedData": { "fid": 2 } }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+mod analytics_tests {
+    use crate::analytics::{AnalyticsSink, ClickEvent, JsonlSink};
+    use crate::assets::AssetHashes;
+    use crate::error_image::ErrorImages;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::ReplayGuard;
+    use crate::tests::test_registry;
+    use crate::{handle_frame, Config};
+    use actix_web::{test, web, App};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    // A fresh file under the system temp directory
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("goat-frame-{}.jsonl", uuid::Uuid::new_v4()))
+    }
+
+    #[actix_web::test]
+    async fn test_click_is_appended_as_json_line() {
+        let path = temp_path();
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+        let sink: Arc<dyn AnalyticsSink> = Arc::new(JsonlSink::open(&path).unwrap());
+
+        // Create a mock application with the same routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(web::Data::from(sink))
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 2,
+                    "fid": 42
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Assert exactly one complete line describing the click was written
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert!(contents.ends_with('\n'));
+        let event: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(event["fid"], 42);
+        assert_eq!(event["frame"], "store");
+        assert_eq!(event["button_index"], 2);
+        assert!(event["timestamp"].as_u64().unwrap() > 0);
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_records_do_not_interleave() {
+        let path = temp_path();
+        let sink = Arc::new(JsonlSink::open(&path).unwrap());
+
+        let handles: Vec<_> = (0..50)
+            .map(|i| {
+                let sink = sink.clone();
+                actix_web::rt::spawn(async move {
+                    sink.record(ClickEvent::now(Some(i), "store", 1)).await
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Assert every line is a whole event
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 50);
+        for line in lines {
+            let event: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(event["frame"], "store");
+        }
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod app_key_tests {
+    use crate::app_key::{verify_event, SignedEvent};
+    use crate::errors::AppError;
+    use crate::tests::spawn_mock_server;
+    use actix_web::{web, HttpResponse};
+    use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use std::collections::HashMap;
+
+    fn key_pair() -> Ed25519KeyPair {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+    }
+
+    // Signs a frame_added event for fid with key, as a client would
+    fn sign(key: &Ed25519KeyPair, fid: u64, kind: &str) -> SignedEvent {
+        let encode = |value: serde_json::Value| BASE64_URL_SAFE_NO_PAD.encode(value.to_string());
+        let header = encode(serde_json::json!({
+            "fid": fid,
+            "type": kind,
+            "key": format!("0x{}", hex::encode(key.public_key().as_ref())),
+        }));
+        let payload = encode(serde_json::json!({ "event": "frame_added" }));
+        let signature = key.sign(format!("{}.{}", header, payload).as_bytes());
+        SignedEvent {
+            header,
+            payload,
+            signature: BASE64_URL_SAFE_NO_PAD.encode(signature.as_ref()),
+        }
+    }
+
+    // Mock hub whose Key Registry holds a single app key for fid 42
+    async fn hub_with_key(key: &Ed25519KeyPair) -> String {
+        let registered = format!("0x{}", hex::encode(key.public_key().as_ref()));
+        spawn_mock_server(move |cfg| {
+            let registered = registered.clone();
+            cfg.route(
+                "/v1/onChainSignersByFid",
+                web::get().to(move |query: web::Query<HashMap<String, String>>| {
+                    let found = query.get("fid").map(String::as_str) == Some("42")
+                        && query.get("signer") == Some(&registered);
+                    async move {
+                        if found {
+                            HttpResponse::Ok().json(serde_json::json!({
+                                "type": "EVENT_TYPE_SIGNER",
+                                "fid": 42,
+                                "signerEventBody": { "eventType": "SIGNER_EVENT_TYPE_ADD" }
+                            }))
+                        } else {
+                            HttpResponse::BadRequest().json(serde_json::json!({
+                                "errCode": "not_found",
+                                "details": "no signer found"
+                            }))
+                        }
+                    }
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_registered_app_key_is_trusted() {
+        let key = key_pair();
+        let hub = hub_with_key(&key).await;
+
+        let header = verify_event(&reqwest::Client::new(), &hub, &sign(&key, 42, "app_key"))
+            .await
+            .unwrap();
+
+        // Assert the verified header names the signing fid
+        assert_eq!(header.fid, 42);
+    }
+
+    #[actix_web::test]
+    async fn test_forged_events_are_refused() {
+        let key = key_pair();
+        let hub = hub_with_key(&key).await;
+        let client = reqwest::Client::new();
+
+        // A payload swapped after signing
+        let mut tampered = sign(&key, 42, "app_key");
+        tampered.payload = BASE64_URL_SAFE_NO_PAD.encode(r#"{"event":"frame_removed"}"#.as_bytes());
+        // A validly signed event claiming someone else's fid
+        let other_fid = sign(&key, 7, "app_key");
+        // A key the hub has never seen
+        let unregistered = sign(&key_pair(), 42, "app_key");
+        // A custody signature, which webhook events never use
+        let custody = sign(&key, 42, "custody");
+
+        // Assert each is refused as unauthorized
+        for event in [tampered, other_fid, unregistered, custody] {
+            let result = verify_event(&client, &hub, &event).await;
+            assert!(matches!(result, Err(AppError::Unauthorized(_))));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_unreachable_hub_is_a_server_error() {
+        let key = key_pair();
+
+        // Assert a hub outage isn't reported as the client's fault
+        let result = verify_event(
+            &reqwest::Client::new(),
+            "http://127.0.0.1:1",
+            &sign(&key, 42, "app_key"),
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::InternalServerError)));
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod apr_tests {
+    use crate::assets::AssetHashes;
+    use crate::config::Config;
+    use crate::frame_logic::registry::DEFAULT_FRAME;
+    use crate::frame_logic::state::FrameState;
+    use crate::frame_logic::{process_button, UserContext};
+    use crate::tests::{press, spawn_mock_server, test_registry};
+    use actix_web::{web, HttpResponse};
+
+    // Mock APR source reporting a 25% pool APR
+    fn high_apr(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/apr",
+            web::get().to(|| async { HttpResponse::Ok().json(serde_json::json!({ "apr": 25.0 })) }),
+        );
+    }
+
+    // Mock APR source reporting a 3% pool APR
+    fn low_apr(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/apr",
+            web::get().to(|| async { HttpResponse::Ok().json(serde_json::json!({ "apr": 3.0 })) }),
+        );
+    }
+
+    // Mock APR source that is having a bad day
+    fn broken_apr(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/apr",
+            web::get().to(|| async { HttpResponse::InternalServerError().finish() }),
+        );
+    }
+
+    // Opens Add Liquidity with APR_URL pointing at the given source
+    async fn add_liquidity_image(apr_url: &str) -> String {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            apr_url: Some(apr_url.to_string()),
+            ..Config::default()
+        };
+        let (rendered, _) = process_button(
+            DEFAULT_FRAME,
+            &press(2, None, &UserContext::default()),
+            FrameState::default(),
+            &test_registry(),
+            &AssetHashes::default(),
+            &config,
+            &reqwest::Client::new(),
+        )
+        .await
+        .unwrap();
+        rendered.image
+    }
+
+    #[actix_web::test]
+    async fn test_high_apr_picks_high_image() {
+        let base = spawn_mock_server(high_apr).await;
+
+        // Assert a 25% APR clears the default 10% threshold
+        assert_eq!(
+            add_liquidity_image(&format!("{}/apr", base)).await,
+            "http://localhost/assets/add_liquidity_high_apr.png"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_low_apr_picks_low_image() {
+        let base = spawn_mock_server(low_apr).await;
+
+        // Assert a 3% APR shows the low APR art
+        assert_eq!(
+            add_liquidity_image(&format!("{}/apr", base)).await,
+            "http://localhost/assets/add_liquidity_low_apr.png"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_failed_fetch_falls_back_to_neutral_image() {
+        let base = spawn_mock_server(broken_apr).await;
+
+        // Assert an upstream error and an unreachable source both keep the neutral image
+        assert_eq!(
+            add_liquidity_image(&format!("{}/apr", base)).await,
+            "http://localhost/assets/add_liquidity.png"
+        );
+        assert_eq!(
+            add_liquidity_image("http://127.0.0.1:1/apr").await,
+            "http://localhost/assets/add_liquidity.png"
+        );
+    }
+
+    #[test]
+    fn test_apr_images_differ() {
+        let read = |name: &str| std::fs::read(format!("assets/{}", name)).unwrap();
+
+        // Assert each APR shows art of its own, not a copy of the neutral image
+        let images = [
+            read("add_liquidity.png"),
+            read("add_liquidity_high_apr.png"),
+            read("add_liquidity_low_apr.png"),
+        ];
+        assert_ne!(images[0], images[1]);
+        assert_ne!(images[0], images[2]);
+        assert_ne!(images[1], images[2]);
+    }
+}
@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod assets_tests {
+    use crate::assets::optimize::{optimize_assets, MAX_SIDE};
+    use crate::assets::{cache_headers, AssetHashes};
+    use crate::{asset_files, Config};
+    use actix_web::middleware::from_fn;
+    use actix_web::{http::header, http::StatusCode, test, web, App};
+    use image::{ImageFormat, Rgba, RgbaImage};
+    use std::path::{Path, PathBuf};
+
+    #[actix_web::test]
+    async fn test_asset_conditional_request_returns_not_modified() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            asset_cache_secs: 600,
+            ..Config::default()
+        });
+        let hashes = web::Data::new(AssetHashes::scan(Path::new("assets")).unwrap());
+
+        // Create a mock application with the same /assets wiring as in main.rs
+        let app = test::init_service(
+            App::new().app_data(config).app_data(hashes).service(
+                web::scope("/assets")
+                    .wrap(from_fn(cache_headers))
+                    .service(asset_files(false)),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/assets/main.png")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the first fetch carries the configured max-age and a content ETag
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CACHE_CONTROL).unwrap(),
+            "public, max-age=600"
+        );
+        let etag = resp.headers().get(header::ETAG).unwrap().clone();
+
+        let req = test::TestRequest::get()
+            .uri("/assets/main.png")
+            .insert_header((header::IF_NONE_MATCH, etag.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the conditional fetch is answered with 304 and no body
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get(header::ETAG).unwrap(), etag);
+        let body = test::read_body(resp).await;
+        assert!(body.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_asset_etag_tracks_file_contents() {
+        let hashes = AssetHashes::scan(Path::new("assets")).unwrap();
+
+        // Assert different images get different ETags and unknown paths get none
+        let main = hashes.etag("main.png").unwrap();
+        let gift = hashes.etag("gift.png").unwrap();
+        assert_ne!(main, gift);
+        assert!(main.starts_with('"') && main.ends_with('"'));
+        assert!(hashes.etag("missing.png").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_asset_urls_carry_content_version() {
+        let hashes = AssetHashes::scan(Path::new("assets")).unwrap();
+
+        // Assert scanned files get a version from their ETag, and others a plain URL
+        let etag = hashes.etag("main.png").unwrap();
+        assert_eq!(
+            hashes.url("https://frame.example.com", "main.png"),
+            format!(
+                "https://frame.example.com/assets/main.png?v={}",
+                &etag[1..9]
+            )
+        );
+        assert_ne!(
+            hashes.url("https://frame.example.com", "main.png"),
+            hashes.url("https://frame.example.com", "gift.png")
+        );
+        assert_eq!(
+            hashes.url("https://frame.example.com", "missing.png"),
+            "https://frame.example.com/assets/missing.png"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_asset_listing_disabled_by_default() {
+        let app =
+            test::init_service(App::new().service(
+                web::scope("/assets").service(asset_files(Config::default().asset_listing)),
+            ))
+            .await;
+
+        let req = test::TestRequest::get().uri("/assets/").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the directory isn't browsable without ASSET_LISTING
+        assert_ne!(resp.status(), StatusCode::OK);
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[actix_web::test]
+    async fn test_optimize_assets() {
+        let (assets, cache) = (temp_dir(), temp_dir());
+        std::fs::create_dir_all(assets.join("rewards")).unwrap();
+        // An oversized opaque image stored with alpha, saved without compression
+        let big = RgbaImage::from_fn(2400, 1200, |x, y| {
+            Rgba([(x / 10) as u8, (y / 10) as u8, 0, 255])
+        });
+        big.save_with_format(assets.join("rewards/big.png"), ImageFormat::Png)
+            .unwrap();
+        std::fs::copy("assets/main.png", assets.join("main.png")).unwrap();
+        std::fs::write(assets.join("notes.txt"), "not an image").unwrap();
+        // Left by an older version of big.png, and by someone else
+        std::fs::create_dir_all(cache.join("rewards")).unwrap();
+        std::fs::write(cache.join("rewards/big.0badf00d.png"), "stale").unwrap();
+        std::fs::write(cache.join("README"), "keep").unwrap();
+
+        // Assert the PNG gets a smaller variant scaled to fit frames, named for its
+        // content, while what isn't a PNG is left alone
+        let optimized = optimize_assets(&assets, &cache).unwrap();
+        assert_eq!(optimized.len(), 1);
+        let asset = &optimized[0];
+        assert_eq!(asset.path, "rewards/big.png");
+        assert!(asset.variant.starts_with("rewards/big.") && asset.variant.ends_with(".png"));
+        assert!(asset.optimized_bytes < asset.original_bytes);
+        let variant = image::open(cache.join(&asset.variant)).unwrap();
+        assert_eq!(
+            (variant.width(), variant.height()),
+            (MAX_SIDE, MAX_SIDE / 2)
+        );
+        assert!(!variant.color().has_alpha());
+
+        // Assert stale variants are removed, other files kept, and a restart reuses
+        // what's there
+        assert!(!cache.join("rewards/big.0badf00d.png").exists());
+        assert!(cache.join("README").exists());
+        assert_eq!(optimize_assets(&assets, &cache).unwrap(), optimized);
+
+        // Assert frames link the variant, versioned by its name, and it has an ETag
+        let hashes = AssetHashes::scan(&assets)
+            .unwrap()
+            .with_optimized(&cache, &optimized)
+            .unwrap();
+        let served = format!("optimized/{}", asset.variant);
+        assert_eq!(
+            hashes.url("http://localhost", "rewards/big.png"),
+            format!("http://localhost/assets/{}", served)
+        );
+        assert!(hashes.etag(&served).is_some());
+        assert!(hashes
+            .url("http://localhost", "main.png")
+            .starts_with("http://localhost/assets/main.png?v="));
+
+        std::fs::remove_dir_all(&assets).unwrap();
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+}
@@ -0,0 +1,343 @@
+#[cfg(test)]
+mod auction_tests {
+    use crate::auction::{min_bid, Auction};
+    use crate::bridge::Bridge;
+    use crate::countdown::unix_now;
+    use crate::images::cache::RenderCache;
+    use crate::images::{self, ImageTemplates};
+    use crate::leaderboard::Leaderboard;
+    use crate::metrics::Metrics;
+    use crate::moxie::MoxiePrice;
+    use crate::notifications::{NotificationDetails, NotificationStore};
+    use crate::price::PriceHistory;
+    use crate::profiles::ProfileCache;
+    use crate::receipts::Receipts;
+    use crate::tests::spawn_mock_server;
+    use crate::tx::{encode_address, encode_amount, encode_uint, function_selector, parse_address};
+    use crate::{handle_bid_tx, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000000ab";
+    const ALICE: &str = "0x1111111111111111111111111111111111111111";
+    const BOB: &str = "0x2222222222222222222222222222222222222222";
+    const CAROL: &str = "0x3333333333333333333333333333333333333333";
+    const GOAT: u128 = 10u128.pow(18);
+
+    // The highest bidder and bid, and when bidding ends
+    type Highest = Arc<Mutex<(&'static str, u128, u64)>>;
+
+    // Mock RPC node running the auction in highest, where every wallet holds 1000 GOAT
+    async fn rpc_with_auction(highest: Highest) -> String {
+        spawn_mock_server(move |cfg| {
+            let highest = highest.clone();
+            cfg.route(
+                "/",
+                web::post().to(move |body: web::Json<serde_json::Value>| {
+                    let (bidder, amount, ends_at) = *highest.lock().unwrap();
+                    async move {
+                        let output = match body["method"].as_str() {
+                            Some("eth_getBalance") => encode_amount(1000 * GOAT).to_vec(),
+                            Some("eth_call") => {
+                                assert_eq!(body["params"][0]["to"], CONTRACT);
+                                let data = body["params"][0]["data"].as_str().unwrap();
+                                let selector = |signature| {
+                                    format!("0x{}", hex::encode(function_selector(signature)))
+                                };
+                                if data == selector("highestBid()") {
+                                    let mut output =
+                                        encode_address(&parse_address(bidder).unwrap()).to_vec();
+                                    output.extend_from_slice(&encode_amount(amount));
+                                    output
+                                } else {
+                                    assert_eq!(data, selector("endTime()"));
+                                    encode_uint(ends_at).to_vec()
+                                }
+                            }
+                            method => panic!("unexpected {:?}", method),
+                        };
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": body["id"],
+                            "result": format!("0x{}", hex::encode(output))
+                        }))
+                    }
+                }),
+            );
+        })
+        .await
+    }
+
+    // Mock hub that vouches for every message as fid 7 with CAROL's wallet connected
+    fn carols_hub(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/v1/validateMessage",
+            web::post().to(|| async {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "valid": true,
+                    "message": {
+                        "data": {
+                            "fid": 7,
+                            "timestamp": 110000000,
+                            "frameActionBody": {
+                                "url": "aHR0cDovL2xvY2FsaG9zdA==",
+                                "buttonIndex": 1,
+                                "address": "MzMzMzMzMzMzMzMzMzMzMzMzMzM="
+                            }
+                        }
+                    }
+                }))
+            }),
+        );
+    }
+
+    // A client that accepts every notification, keeping what it's sent, and a store
+    // where each of fids has notifications on with it
+    async fn notified(fids: &[u64]) -> (NotificationStore, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let sent: Arc<Mutex<Vec<serde_json::Value>>> = Arc::default();
+        let notify_url = {
+            let sent = sent.clone();
+            spawn_mock_server(move |cfg| {
+                let sent = sent.clone();
+                cfg.route(
+                    "/notify",
+                    web::post().to(move |body: web::Json<serde_json::Value>| {
+                        sent.lock().unwrap().push(body.clone());
+                        async {
+                            HttpResponse::Ok().json(serde_json::json!({
+                                "result": { "successfulTokens": ["token"], "invalidTokens": [] }
+                            }))
+                        }
+                    }),
+                );
+            })
+            .await
+        };
+        let store = NotificationStore::in_memory();
+        for fid in fids {
+            store.set(
+                *fid,
+                Some(NotificationDetails {
+                    url: format!("{}/notify", notify_url),
+                    token: "token".to_string(),
+                }),
+            );
+        }
+        (store, sent)
+    }
+
+    fn config(rpc_url: String) -> Config {
+        Config {
+            rpc_url: Some(rpc_url),
+            auction_contract: Some(CONTRACT.to_string()),
+            ..Config::default()
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_bid_transaction() {
+        let ends_at = unix_now() + 3600;
+        let highest: Highest = Arc::new(Mutex::new((ALICE, 100 * GOAT, ends_at)));
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            hub_url: spawn_mock_server(carols_hub).await,
+            ..config(rpc_with_auction(highest.clone()).await)
+        };
+        let auction = web::Data::new(Auction::new(15));
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config.clone()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(auction.clone())
+                .route("/api/tx/bid", web::post().to(handle_bid_tx)),
+        )
+        .await;
+        let post = |amount: &str, signed: bool| {
+            let mut body = serde_json::json!({
+                "untrustedData": {
+                    "buttonIndex": 1,
+                    "fid": 42,
+                    "address": BOB,
+                    "inputText": amount
+                }
+            });
+            if signed {
+                body["trustedData"] = serde_json::json!({ "messageBytes": "0a0b0c" });
+            }
+            test::TestRequest::post()
+                .uri("/api/tx/bid")
+                .set_json(body)
+                .to_request()
+        };
+        let bid = |amount: &str| post(amount, false);
+        let refused = |resp: actix_web::dev::ServiceResponse| async move {
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            body["message"].as_str().unwrap().to_string()
+        };
+
+        // Assert a bid beating the highest by the default 5% pays it to bid()
+        let resp = test::call_service(&app, bid("105")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["params"]["to"], CONTRACT);
+        assert_eq!(body["params"]["value"], (105 * GOAT).to_string());
+        assert_eq!(
+            body["params"]["data"],
+            format!("0x{}", hex::encode(function_selector("bid()")))
+        );
+
+        // Assert a smaller raise, or no amount, is refused with what's needed
+        assert_eq!(
+            refused(test::call_service(&app, bid("104.9")).await).await,
+            "Bid at least 105 GOAT"
+        );
+        assert_eq!(
+            refused(test::call_service(&app, bid("")).await).await,
+            "Enter how much GOAT to bid"
+        );
+
+        // Assert only the signed bid is remembered, against the fid and wallet the hub
+        // vouched for rather than those the post claimed
+        let resp = test::call_service(&app, post("110", true)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let (store, _) = notified(&[7, 42]).await;
+        *highest.lock().unwrap() = (BOB, 120 * GOAT, ends_at);
+        let client = reqwest::Client::new();
+        assert_eq!(
+            auction.check_outbid(&client, &config, &store).await,
+            Ok(vec![7])
+        );
+
+        // Assert nothing is bid once the auction is over
+        highest.lock().unwrap().2 = unix_now() - 1;
+        assert_eq!(
+            refused(test::call_service(&app, bid("200")).await).await,
+            "The auction has ended"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_outbid_bidders_are_notified() {
+        let ends_at = unix_now() + 3600;
+        let highest: Highest = Arc::new(Mutex::new((ALICE, 100 * GOAT, ends_at)));
+        let config = config(rpc_with_auction(highest.clone()).await);
+        let client = reqwest::Client::new();
+
+        let (store, sent) = notified(&[42, 43]).await;
+        let auction = Auction::new(15);
+        auction.remember_bid(parse_address(ALICE).unwrap(), 42, 100 * GOAT);
+
+        // Assert a steady auction tells no one, least of all its highest bidder
+        for _ in 0..2 {
+            assert_eq!(
+                auction.check_outbid(&client, &config, &store).await,
+                Ok(vec![])
+            );
+        }
+
+        // Assert everyone beaten between two looks is told, not just whoever led at
+        // the first
+        auction.remember_bid(parse_address(CAROL).unwrap(), 43, 120 * GOAT);
+        *highest.lock().unwrap() = (BOB, 150 * GOAT, ends_at);
+        let mut told = auction
+            .check_outbid(&client, &config, &store)
+            .await
+            .unwrap();
+        told.sort();
+        assert_eq!(told, vec![42, 43]);
+        {
+            let sent = sent.lock().unwrap();
+            assert_eq!(sent.len(), 2);
+            assert_eq!(sent[0]["title"], "You've been outbid");
+            assert_eq!(
+                sent[0]["body"],
+                "The highest bid is now 150 GOAT. Bid again to win it back."
+            );
+        }
+
+        // Assert each is told once, and an outbid bidder who didn't bid here isn't
+        // anyone to tell
+        *highest.lock().unwrap() = (ALICE, 200 * GOAT, ends_at);
+        assert_eq!(
+            auction.check_outbid(&client, &config, &store).await,
+            Ok(vec![])
+        );
+
+        // Assert bids still standing when bidding closes are forgotten
+        auction.remember_bid(parse_address(BOB).unwrap(), 42, 210 * GOAT);
+        *highest.lock().unwrap() = (CAROL, 300 * GOAT, unix_now() - 1);
+        assert_eq!(
+            auction.check_outbid(&client, &config, &store).await,
+            Ok(vec![])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_auction_is_drawn() {
+        let highest: Highest = Arc::new(Mutex::new((ALICE, 100 * GOAT, unix_now() + 3600)));
+        let text = |content: &str| {
+            format!(
+                r#"<svg width="240" height="20"><text y="16" font-size="16">{}</text></svg>"#,
+                content
+            )
+        };
+        let templates = ImageTemplates::new(
+            HashMap::from([
+                ("a".to_string(), text("{{bid}} / {{min_bid}}")),
+                ("drawn".to_string(), text("100 GOAT / 105 GOAT")),
+            ]),
+            Path::new("assets"),
+        );
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(templates))
+                .app_data(web::Data::new(RenderCache::new(16, 60)))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(ProfileCache::new(60)))
+                .app_data(web::Data::new(PriceHistory::new(60)))
+                .app_data(web::Data::new(Leaderboard::new(60)))
+                .app_data(web::Data::new(MoxiePrice::new(60)))
+                .app_data(web::Data::new(Receipts::default()))
+                .app_data(web::Data::new(Auction::new(15)))
+                .app_data(web::Data::new(Bridge::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(config(rpc_with_auction(highest).await)))
+                .route(
+                    "/images/{file}",
+                    web::get().to(images::serve_template_image),
+                ),
+        )
+        .await;
+        let render = |uri: &str| {
+            let app = &app;
+            let uri = uri.to_string();
+            async move {
+                let resp =
+                    test::call_service(app, test::TestRequest::get().uri(&uri).to_request()).await;
+                assert_eq!(resp.status(), 200);
+                test::read_body(resp).await
+            }
+        };
+
+        // Assert the bids are drawn as the contract has them, and forged ones are ignored
+        let drawn = render("/images/drawn.png").await;
+        assert_eq!(render("/images/a.png").await, drawn);
+        assert_eq!(
+            render("/images/a.png?bid=999%20GOAT&min_bid=1%20GOAT").await,
+            drawn
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_min_bid() {
+        // Assert the next bid has to beat the highest by the increment, or at all
+        assert_eq!(min_bid(0, 500), 1);
+        assert_eq!(min_bid(1000, 500), 1050);
+        assert_eq!(min_bid(10, 500), 11);
+        assert_eq!(min_bid(1000, 0), 1001);
+        assert_eq!(min_bid(u128::MAX, 500), u128::MAX);
+    }
+}
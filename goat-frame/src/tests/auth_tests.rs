@@ -0,0 +1,228 @@
+#[cfg(test)]
+mod auth_tests {
+    use crate::auth::{self, format_timestamp, parse_message, personal_message_hash, Sessions};
+    use crate::config::Config;
+    use crate::countdown::unix_now;
+    use crate::secp256k1;
+    use crate::tests::spawn_mock_server;
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+
+    // Private key 1, and the address it has
+    const KEY: [u8; 32] = {
+        let mut key = [0u8; 32];
+        key[31] = 1;
+        key
+    };
+    const ADDRESS: &str = "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf";
+
+    // A message for domain issued just now
+    fn siwf_message(domain: &str, nonce: &str, fid: u64) -> String {
+        siwf_message_at(domain, nonce, fid, unix_now())
+    }
+
+    fn siwf_message_at(domain: &str, nonce: &str, fid: u64, issued_at: u64) -> String {
+        format!(
+            "{} wants you to sign in with your Ethereum account:\n\
+             {}\n\n\
+             Farcaster Auth\n\n\
+             URI: http://{}/\n\
+             Version: 1\n\
+             Chain ID: 10\n\
+             Nonce: {}\n\
+             Issued At: {}\n\
+             Resources:\n\
+             - farcaster://fid/{}",
+            domain,
+            ADDRESS,
+            domain,
+            nonce,
+            format_timestamp(issued_at),
+            fid
+        )
+    }
+
+    // A hub where ADDRESS is the custody address of fid 42
+    async fn mock_hub() -> String {
+        spawn_mock_server(|cfg| {
+            cfg.route(
+                "/v1/onChainIdRegistryEventByAddress",
+                web::get().to(|query: web::Query<serde_json::Value>| async move {
+                    if query["address"] == ADDRESS {
+                        HttpResponse::Ok().json(serde_json::json!({ "fid": 42 }))
+                    } else {
+                        HttpResponse::NotFound().finish()
+                    }
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_parse_message() {
+        let message = parse_message(&siwf_message("localhost", "abc12345", 42)).unwrap();
+
+        // Assert the domain, address, nonce and fid are read
+        assert_eq!(message.domain, "localhost");
+        assert_eq!(
+            hex::encode(message.address),
+            ADDRESS.trim_start_matches("0x")
+        );
+        assert_eq!(message.nonce, "abc12345");
+        assert_eq!(message.fid, 42);
+        assert_eq!(message.uri, "http://localhost/");
+        assert_eq!(message.chain_id, 10);
+        assert!(unix_now().abs_diff(message.issued_at) <= 1);
+        assert_eq!(message.expiration_time, None);
+
+        // Assert times are read in UTC whatever their offset, with or without fractions
+        let at = |issued_at: &str| {
+            let text = siwf_message("localhost", "abc12345", 42)
+                .replace(&format_timestamp(message.issued_at), issued_at);
+            parse_message(&text).map(|message| message.issued_at)
+        };
+        assert_eq!(at("2026-01-01T00:00:00.000Z").unwrap(), 1_767_225_600);
+        assert_eq!(at("2026-01-01T01:30:00+01:30").unwrap(), 1_767_225_600);
+        assert_eq!(at("2025-12-31T19:00:00-05:00").unwrap(), 1_767_225_600);
+        assert_eq!(format_timestamp(1_767_225_600), "2026-01-01T00:00:00.000Z");
+        assert!(at("yesterday").is_err());
+        assert!(at("2026-13-01T00:00:00Z").is_err());
+
+        // Assert a message without an fid resource is refused
+        let without_fid = siwf_message("localhost", "abc12345", 42).replace("farcaster://", "");
+        assert!(parse_message(&without_fid).is_err());
+        assert!(parse_message("hello").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_session_tokens() {
+        let sessions = Sessions::new(Some("secret"), 3600);
+        let (token, _) = sessions.mint(42);
+
+        // Assert a token verifies as its fid, and only with the secret that minted it
+        assert_eq!(sessions.verify(&token), Some(42));
+        assert_eq!(Sessions::new(Some("other"), 3600).verify(&token), None);
+        assert_eq!(sessions.verify(&token.replacen("42", "43", 1)), None);
+
+        // Assert an expired token is refused
+        let (expired, _) = Sessions::new(Some("secret"), 0).mint(42);
+        assert_eq!(sessions.verify(&expired), None);
+
+        // Assert nonces are single use
+        let nonce = sessions.issue_nonce();
+        assert!(sessions.take_nonce(&nonce));
+        assert!(!sessions.take_nonce(&nonce));
+        assert!(!sessions.take_nonce("never-issued"));
+    }
+
+    #[actix_web::test]
+    async fn test_sign_in_and_call_api() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            hub_url: mock_hub().await,
+            // Sign-in checks who signed whatever SKIP_VALIDATION says
+            skip_validation: true,
+            ..Config::default()
+        });
+        let sessions = web::Data::new(Sessions::new(Some("secret"), 3600));
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(sessions.clone())
+                .route("/api/auth/nonce", web::get().to(auth::nonce))
+                .route("/api/auth/verify", web::post().to(auth::sign_in))
+                .route("/api/auth/me", web::get().to(auth::me)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/api/auth/nonce").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let nonce = body["nonce"].as_str().unwrap().to_string();
+        let signed = |message: String, key: &[u8; 32]| {
+            let signature = secp256k1::sign(&personal_message_hash(&message), key);
+            test::TestRequest::post()
+                .uri("/api/auth/verify")
+                .set_json(serde_json::json!({
+                    "message": message,
+                    "signature": format!("0x{}", hex::encode(signature))
+                }))
+                .to_request()
+        };
+        let sign_in = |message: String| signed(message, &KEY);
+
+        // Assert messages for another domain or another fid are refused
+        let resp = test::call_service(&app, sign_in(siwf_message("evil.com", &nonce, 42))).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let nonce = sessions.issue_nonce();
+        let resp = test::call_service(&app, sign_in(siwf_message("localhost", &nonce, 7))).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // Assert messages for another URI or chain, expired, or not issued just now
+        // are refused
+        let now = unix_now();
+        let message = siwf_message_at("localhost", &sessions.issue_nonce(), 42, now);
+        let issued_at = format!("Issued At: {}", format_timestamp(now));
+        for refused in [
+            message.replace("URI: http://localhost/", "URI: https://evil.com/"),
+            message.replace("Chain ID: 10", "Chain ID: 1"),
+            message.replace(
+                &issued_at,
+                &format!("Issued At: {}", format_timestamp(now - 3600)),
+            ),
+            message.replace(
+                &issued_at,
+                &format!("Issued At: {}", format_timestamp(now + 3600)),
+            ),
+            message.replace(
+                &issued_at,
+                &format!(
+                    "{}\nExpiration Time: {}",
+                    issued_at,
+                    format_timestamp(now - 1)
+                ),
+            ),
+            message.replace(
+                &issued_at,
+                &format!(
+                    "{}\nNot Before: {}",
+                    issued_at,
+                    format_timestamp(now + 3600)
+                ),
+            ),
+        ] {
+            let resp = test::call_service(&app, sign_in(refused)).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // Assert a message signed by a key other than its address's is refused, without
+        // using up the nonce for the address's own signature
+        let nonce = sessions.issue_nonce();
+        let resp = test::call_service(
+            &app,
+            signed(siwf_message("localhost", &nonce, 42), &[7u8; 32]),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // Assert the custody address's fid signs in, and only once per nonce
+        let resp = test::call_service(&app, sign_in(siwf_message("localhost", &nonce, 42))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["fid"], 42);
+        let token = body["token"].as_str().unwrap().to_string();
+        let resp = test::call_service(&app, sign_in(siwf_message("localhost", &nonce, 42))).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // Assert the token authenticates API calls
+        let req = test::TestRequest::get()
+            .uri("/api/auth/me")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["fid"], 42);
+        let req = test::TestRequest::get().uri("/api/auth/me").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,307 @@
+#[cfg(test)]
+mod bridge_tests {
+    use crate::auction::Auction;
+    use crate::bridge::{Bridge, BridgeStatus};
+    use crate::images::cache::RenderCache;
+    use crate::images::{self, ImageTemplates};
+    use crate::leaderboard::Leaderboard;
+    use crate::metrics::Metrics;
+    use crate::moxie::MoxiePrice;
+    use crate::price::PriceHistory;
+    use crate::profiles::ProfileCache;
+    use crate::receipts::Receipts;
+    use crate::tests::spawn_mock_server;
+    use crate::tx::{encode_address, encode_amount, encode_uint, function_selector, parse_address};
+    use crate::{handle_bridge_sent, handle_bridge_status, handle_bridge_tx, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const BRIDGE: &str = "0x00000000000000000000000000000000000000bb";
+    const DESTINATION: &str = "0x00000000000000000000000000000000000000dd";
+    const WALLET: &str = "0x1111111111111111111111111111111111111111";
+    const GOAT: u128 = 10u128.pow(18);
+
+    fn hash(byte: &str) -> String {
+        format!("0x{}", byte.repeat(32))
+    }
+
+    // Mock GOAT-side RPC node where every wallet holds 10 GOAT, and deposits 0xaa..
+    // and 0xbb.. are mined, 0xcc.. isn't yet and 0xdd.. reverted
+    async fn goat_rpc() -> String {
+        spawn_mock_server(|cfg| {
+            cfg.route(
+                "/",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    let result = match body["method"].as_str() {
+                        Some("eth_getBalance") => {
+                            serde_json::json!(format!("0x{:x}", 10 * GOAT))
+                        }
+                        Some("eth_getTransactionReceipt") => {
+                            match body["params"][0].as_str().unwrap_or_default() {
+                                hash if hash.starts_with("0xaa") || hash.starts_with("0xbb") => {
+                                    serde_json::json!({ "status": "0x1" })
+                                }
+                                hash if hash.starts_with("0xdd") => {
+                                    serde_json::json!({ "status": "0x0" })
+                                }
+                                _ => serde_json::Value::Null,
+                            }
+                        }
+                        method => panic!("unexpected {:?}", method),
+                    };
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": result
+                    }))
+                }),
+            );
+        })
+        .await
+    }
+
+    // Mock destination RPC node that has paid out 0xaa.. and nothing else, counting
+    // the deposits looked for
+    async fn destination_rpc(calls: Arc<AtomicUsize>) -> String {
+        spawn_mock_server(move |cfg| {
+            let calls = calls.clone();
+            cfg.route(
+                "/",
+                web::post().to(move |body: web::Json<serde_json::Value>| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        assert_eq!(body["method"], "eth_call");
+                        assert_eq!(body["params"][0]["to"], DESTINATION);
+                        let data = body["params"][0]["data"].as_str().unwrap();
+                        let selector = hex::encode(function_selector("processed(bytes32)"));
+                        let deposit = data.strip_prefix(&format!("0x{}", selector)).unwrap();
+                        let processed = u128::from(deposit == "aa".repeat(32));
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": body["id"],
+                            "result": format!("0x{}", hex::encode(encode_amount(processed)))
+                        }))
+                    }
+                }),
+            );
+        })
+        .await
+    }
+
+    fn config(rpc_url: String, destination_rpc_url: String) -> Config {
+        Config {
+            domain: "http://localhost".to_string(),
+            rpc_url: Some(rpc_url),
+            bridge_contract: Some(BRIDGE.to_string()),
+            bridge_destination_chain_id: Some(8453),
+            bridge_destination_rpc_url: Some(destination_rpc_url),
+            bridge_destination_contract: Some(DESTINATION.to_string()),
+            ..Config::default()
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_bridge_transaction() {
+        let destination = destination_rpc(Arc::default()).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config(goat_rpc().await, destination)))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/bridge", web::post().to(handle_bridge_tx)),
+        )
+        .await;
+        let bridge = |amount: &str| {
+            test::TestRequest::post()
+                .uri("/api/tx/bridge")
+                .set_json(serde_json::json!({
+                    "untrustedData": { "buttonIndex": 1, "address": WALLET, "inputText": amount }
+                }))
+                .to_request()
+        };
+
+        // Assert the GOAT entered is deposited for the same wallet on the destination chain
+        let resp = test::call_service(&app, bridge("2.5")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let mut data = function_selector("deposit(address,uint256)").to_vec();
+        data.extend_from_slice(&encode_address(&parse_address(WALLET).unwrap()));
+        data.extend_from_slice(&encode_uint(8453));
+        assert_eq!(body["params"]["to"], BRIDGE);
+        assert_eq!(body["params"]["value"], (5 * GOAT / 2).to_string());
+        assert_eq!(body["params"]["data"], format!("0x{}", hex::encode(data)));
+
+        // Assert no amount, or more than the wallet holds, is refused
+        let resp = test::call_service(&app, bridge("")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Enter how much GOAT to bridge");
+        let resp = test::call_service(&app, bridge("11")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_deposits_are_followed_to_arrival() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let config = config(goat_rpc().await, destination_rpc(calls.clone()).await);
+        let client = reqwest::Client::new();
+        let (bridge, receipts) = (Bridge::default(), Receipts::default());
+        let status = |byte: &'static str| {
+            let (bridge, receipts, client, config) = (&bridge, &receipts, &client, &config);
+            async move { bridge.status(client, config, receipts, &hash(byte)).await }
+        };
+
+        // Assert a deposit is pending until mined, then bridging until the destination
+        // chain has paid it out, and one that reverted never gets there
+        assert_eq!(status("cc").await, BridgeStatus::Pending);
+        assert_eq!(status("bb").await, BridgeStatus::Bridging);
+        assert_eq!(status("aa").await, BridgeStatus::Arrived);
+        assert_eq!(status("dd").await, BridgeStatus::Failed);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Assert an arrival is remembered, and a deposit on its way isn't looked for
+        // again straight away
+        assert_eq!(status("aa").await, BridgeStatus::Arrived);
+        assert_eq!(status("bb").await, BridgeStatus::Bridging);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Assert without RPC_URL the deposit is only known to be sent
+        let config = Config {
+            rpc_url: None,
+            ..config.clone()
+        };
+        assert_eq!(
+            Bridge::default()
+                .status(&client, &config, &Receipts::default(), &hash("aa"))
+                .await,
+            BridgeStatus::Sent
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_status_frame() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Config {
+                    domain: "http://localhost".to_string(),
+                    ..Config::default()
+                }))
+                .route("/bridge", web::post().to(handle_bridge_sent))
+                .route("/bridge/{hash}", web::get().to(handle_bridge_status))
+                .route("/bridge/{hash}", web::post().to(handle_bridge_status)),
+        )
+        .await;
+        let sent = |transaction_id: serde_json::Value| {
+            test::TestRequest::post()
+                .uri("/bridge")
+                .set_json(serde_json::json!({
+                    "untrustedData": { "buttonIndex": 1, "transactionId": transaction_id }
+                }))
+                .to_request()
+        };
+
+        // Assert the Bridge button lands on the deposit's status, whose Refresh posts
+        // back to it
+        let resp = test::call_service(&app, sent(serde_json::json!(hash("AA")))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let html = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(html.contains(&format!(
+            "/images/bridge_status.png?tx={}&amp;t=",
+            hash("aa")
+        )));
+        assert!(html.contains(&format!(
+            r#"content="http://localhost/bridge/{}""#,
+            hash("aa")
+        )));
+        assert!(html.contains(r#"content="Refresh""#));
+
+        // Assert the status is there to look at again, by GET or a Refresh press
+        for req in [
+            test::TestRequest::get().uri(&format!("/bridge/{}", hash("aa"))),
+            test::TestRequest::post()
+                .uri(&format!("/bridge/{}", hash("aa")))
+                .set_json(serde_json::json!({ "untrustedData": { "buttonIndex": 1 } })),
+        ] {
+            let resp = test::call_service(&app, req.to_request()).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        // Assert anything but a transaction hash is refused
+        for resp in [
+            test::call_service(&app, sent(serde_json::Value::Null)).await,
+            test::call_service(&app, sent(serde_json::json!("0xaa"))).await,
+            test::call_service(
+                &app,
+                test::TestRequest::get().uri("/bridge/nope").to_request(),
+            )
+            .await,
+        ] {
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_status_is_drawn() {
+        let config = config(goat_rpc().await, destination_rpc(Arc::default()).await);
+        let text = |content: &str| {
+            format!(
+                r#"<svg width="120" height="20"><text y="16" font-size="16">{}</text></svg>"#,
+                content
+            )
+        };
+        let templates = ImageTemplates::new(
+            HashMap::from([
+                ("b".to_string(), text("{{bridge_status}}")),
+                ("arrived".to_string(), text("Arrived")),
+                ("bridging".to_string(), text("Bridging")),
+            ]),
+            Path::new("assets"),
+        );
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(templates))
+                .app_data(web::Data::new(RenderCache::new(16, 60)))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(ProfileCache::new(60)))
+                .app_data(web::Data::new(PriceHistory::new(60)))
+                .app_data(web::Data::new(Leaderboard::new(60)))
+                .app_data(web::Data::new(MoxiePrice::new(60)))
+                .app_data(web::Data::new(Receipts::default()))
+                .app_data(web::Data::new(Auction::new(15)))
+                .app_data(web::Data::new(Bridge::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(config))
+                .route(
+                    "/images/{file}",
+                    web::get().to(images::serve_template_image),
+                ),
+        )
+        .await;
+        let render = |uri: String| {
+            let app = &app;
+            async move {
+                let resp =
+                    test::call_service(app, test::TestRequest::get().uri(&uri).to_request()).await;
+                assert_eq!(resp.status(), 200);
+                test::read_body(resp).await
+            }
+        };
+
+        // Assert the deposit's status is drawn as the chains have it, and a forged one
+        // is ignored
+        assert_eq!(
+            render(format!("/images/b.png?tx={}", hash("aa"))).await,
+            render("/images/arrived.png".to_string()).await
+        );
+        assert_eq!(
+            render(format!(
+                "/images/b.png?tx={}&bridge_status=Arrived",
+                hash("bb")
+            ))
+            .await,
+            render("/images/bridging.png".to_string()).await
+        );
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod client_context_tests {
+    use crate::client_context::{decode, ClientContext, ContextLocation, ContextUser};
+    use crate::config::Config;
+    use crate::frame_logic::UserContext;
+    use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+
+    fn context(fid: u64, display_name: Option<&str>) -> ClientContext {
+        ClientContext {
+            user: ContextUser {
+                fid,
+                username: Some("alice".to_string()),
+                display_name: display_name.map(str::to_string),
+                ..ContextUser::default()
+            },
+            locale: None,
+        }
+    }
+
+    #[test]
+    fn test_decode_context() {
+        let json = r#"{
+            "user": {
+                "fid": 42,
+                "username": "alice",
+                "displayName": "Alice",
+                "pfpUrl": "https://example.com/alice.png",
+                "location": { "placeId": "abc", "description": "Austin, TX, USA" }
+            },
+            "client": { "clientFid": 9152, "added": false }
+        }"#;
+        let context = decode(&BASE64_URL_SAFE_NO_PAD.encode(json)).unwrap();
+
+        // Assert the profile is read and fields we don't use are ignored
+        assert_eq!(
+            context.user,
+            ContextUser {
+                fid: 42,
+                username: Some("alice".to_string()),
+                display_name: Some("Alice".to_string()),
+                pfp_url: Some("https://example.com/alice.png".to_string()),
+                location: Some(ContextLocation {
+                    place_id: Some("abc".to_string()),
+                    description: "Austin, TX, USA".to_string(),
+                }),
+            }
+        );
+
+        // Assert anything that isn't base64url JSON with a user is ignored
+        assert!(decode("not base64!").is_none());
+        assert!(decode(&BASE64_URL_SAFE_NO_PAD.encode("{}")).is_none());
+    }
+
+    #[test]
+    fn test_user_name_from_context() {
+        let config = Config::default();
+
+        // Assert the display name is preferred, then the username, then a stand-in
+        let user = UserContext::default().with_context(Some(context(42, Some("Alice"))));
+        assert_eq!(user.name(), "Alice");
+        let user = UserContext::default().with_context(Some(context(42, None)));
+        assert_eq!(user.name(), "alice");
+        assert_eq!(UserContext::default().name(), "there");
+
+        // Assert a context for someone other than the signed fid is dropped
+        let user = UserContext::lookup(Some(7), &config).with_context(Some(context(42, None)));
+        assert!(user.profile.is_none());
+        let user = UserContext::lookup(Some(42), &config).with_context(Some(context(42, None)));
+        assert_eq!(user.name(), "alice");
+    }
+}
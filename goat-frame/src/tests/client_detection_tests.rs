@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod client_detection_tests {
+    use crate::client_detection::{capabilities, detect, Capabilities, ClientKind};
+
+    #[test]
+    fn test_detect_client() {
+        let warpcast_ua = Some("Mozilla/5.0 (iPhone) Warpcast/1.0");
+
+        // Assert the payload's protocol wins, then the user agent
+        for (user_agent, protocol, expected) in [
+            (warpcast_ua, Some("farcaster"), ClientKind::Warpcast),
+            (warpcast_ua, None, ClientKind::Warpcast),
+            (Some("curl/8.0"), Some("farcaster"), ClientKind::Farcaster),
+            (None, Some("farcaster@vNext"), ClientKind::Farcaster),
+            (warpcast_ua, Some("xmtp@2024-02-01"), ClientKind::OpenFrames),
+            (None, Some("lens@1.1"), ClientKind::OpenFrames),
+            (Some("facebookexternalhit/1.1"), None, ClientKind::Unknown),
+            (None, None, ClientKind::Unknown),
+        ] {
+            assert_eq!(
+                detect(user_agent, protocol),
+                expected,
+                "{:?} {:?}",
+                user_agent,
+                protocol
+            );
+        }
+    }
+
+    #[test]
+    fn test_only_open_frames_clients_lose_square_images() {
+        // Assert unknown clients keep the configured shape rather than being guessed at
+        assert!(ClientKind::Warpcast.supports_square_images());
+        assert!(ClientKind::Farcaster.supports_square_images());
+        assert!(ClientKind::Unknown.supports_square_images());
+        assert!(!ClientKind::OpenFrames.supports_square_images());
+    }
+
+    #[test]
+    fn test_capabilities_follow_dated_versions() {
+        // Assert undated and current versions get everything
+        for protocol in [
+            None,
+            Some("farcaster"),
+            Some("farcaster@vNext"),
+            Some("lens@1.1"),
+        ] {
+            assert_eq!(
+                capabilities(protocol),
+                Capabilities::default(),
+                "{:?}",
+                protocol
+            );
+        }
+        assert_eq!(
+            capabilities(Some("farcaster@2024-03-01")),
+            Capabilities {
+                version: "2024-03-01".to_string(),
+                ..Capabilities::default()
+            }
+        );
+
+        // Assert a client pinned before transactions keeps input, and before input loses both
+        let before_tx = capabilities(Some("farcaster@2024-02-05"));
+        assert_eq!(before_tx.version, "2024-02-05");
+        assert!(before_tx.text_input && !before_tx.transactions);
+        let launch = capabilities(Some("farcaster@2024-01-26"));
+        assert!(!launch.text_input && !launch.transactions);
+
+        // Assert Open Frames clients keep naming vNext, whatever they support
+        let xmtp = capabilities(Some("xmtp@2024-02-01"));
+        assert_eq!(xmtp.version, "vNext");
+        assert!(!xmtp.transactions);
+    }
+}
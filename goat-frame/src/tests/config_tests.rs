@@ -0,0 +1,499 @@
+#[cfg(test)]
+mod config_tests {
+    use crate::config::Config;
+    use crate::cors::AllowedOrigins;
+    use crate::errors::ConfigError;
+    use crate::frame_logic::AspectRatio;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_custom_host_and_port() {
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("HOST", "127.0.0.1"),
+            ("PORT", "9090"),
+        ]))
+        .unwrap();
+
+        // Assert the bind address comes from the environment
+        assert_eq!(config.host, "127.0.0.1");
+        assert_eq!(config.port, 9090);
+    }
+
+    #[test]
+    fn test_default_host_and_port() {
+        let config = Config::from_iter(vars(&[("DOMAIN", "http://localhost")])).unwrap();
+
+        // Assert unset variables keep the historical 0.0.0.0:8080
+        assert_eq!(config.host, "0.0.0.0");
+        assert_eq!(config.port, 8080);
+    }
+
+    #[test]
+    fn test_unparseable_port_is_an_error() {
+        let result = Config::from_iter(vars(&[("DOMAIN", "http://localhost"), ("PORT", "eighty")]));
+
+        // Assert a bad port is reported rather than panicking
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_domain_trailing_slash_is_stripped() {
+        let config = Config::from_iter(vars(&[("DOMAIN", "https://frame.example.com/")])).unwrap();
+
+        // Assert URLs built from the domain won't get a double slash
+        assert_eq!(config.domain, "https://frame.example.com");
+    }
+
+    #[test]
+    fn test_valid_domain_is_unchanged() {
+        let config = Config::from_iter(vars(&[("DOMAIN", "http://localhost:8080/frame")])).unwrap();
+
+        // Assert an already normalized domain passes through untouched
+        assert_eq!(config.domain, "http://localhost:8080/frame");
+    }
+
+    #[test]
+    fn test_domain_without_scheme_is_rejected() {
+        // Assert bare hosts and non-http schemes are refused
+        for domain in [
+            "frame.example.com",
+            "localhost:8080",
+            "ftp://frame.example.com",
+        ] {
+            let result = Config::from_iter(vars(&[("DOMAIN", domain)]));
+            assert!(
+                matches!(result, Err(ConfigError::Invalid(_))),
+                "{} was accepted",
+                domain
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_domain_is_rejected() {
+        let result = Config::from_iter(vars(&[("DOMAIN", "")]));
+
+        // Assert an empty domain doesn't silently produce relative URLs
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_shutdown_timeout_from_env() {
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("SHUTDOWN_TIMEOUT_SECS", "5"),
+        ]))
+        .unwrap();
+        let default = Config::from_iter(vars(&[("DOMAIN", "http://localhost")])).unwrap();
+
+        // Assert the drain timeout is configurable and defaults to 30 seconds
+        assert_eq!(config.shutdown_timeout_secs, 5);
+        assert_eq!(default.shutdown_timeout_secs, 30);
+    }
+
+    #[test]
+    fn test_tls_paths_must_be_set_together() {
+        // Assert a cert without a key, or a key without a cert, is refused
+        for half in [
+            ("TLS_CERT_PATH", "/etc/goat/cert.pem"),
+            ("TLS_KEY_PATH", "/etc/goat/key.pem"),
+        ] {
+            let result = Config::from_iter(vars(&[("DOMAIN", "https://localhost"), half]));
+            assert!(
+                matches!(result, Err(ConfigError::Invalid(_))),
+                "{} alone was accepted",
+                half.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_tls_paths_both_or_neither() {
+        let both = Config::from_iter(vars(&[
+            ("DOMAIN", "https://localhost"),
+            ("TLS_CERT_PATH", "/etc/goat/cert.pem"),
+            ("TLS_KEY_PATH", "/etc/goat/key.pem"),
+        ]))
+        .unwrap();
+        let neither = Config::from_iter(vars(&[("DOMAIN", "https://localhost")])).unwrap();
+
+        // Assert both paths enable TLS and leaving both out keeps plain HTTP
+        assert_eq!(both.tls_cert_path.as_deref(), Some("/etc/goat/cert.pem"));
+        assert_eq!(both.tls_key_path.as_deref(), Some("/etc/goat/key.pem"));
+        assert_eq!(neither.tls_cert_path, None);
+        assert_eq!(neither.tls_key_path, None);
+    }
+
+    #[test]
+    fn test_default_aspect_ratio_from_env() {
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("DEFAULT_ASPECT_RATIO", "square"),
+        ]))
+        .unwrap();
+        let unset = Config::from_iter(vars(&[("DOMAIN", "http://localhost")])).unwrap();
+
+        // Assert the ratio name parses and unset leaves it to the client
+        assert_eq!(config.default_aspect_ratio, Some(AspectRatio::Square));
+        assert_eq!(unset.default_aspect_ratio, None);
+    }
+
+    #[test]
+    fn test_splash_background_must_be_a_hex_color() {
+        let teal = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("MINIAPP_SPLASH_BACKGROUND", "#0A8f7c"),
+        ]))
+        .unwrap();
+
+        // Assert #rrggbb is accepted in either case and anything else fails at startup
+        assert_eq!(teal.miniapp_splash_background, "#0A8f7c");
+        for background in ["white", "#fff", "0a8f7c", "#0a8f7g"] {
+            let result = Config::from_iter(vars(&[
+                ("DOMAIN", "http://localhost"),
+                ("MINIAPP_SPLASH_BACKGROUND", background),
+            ]));
+            assert!(
+                matches!(result, Err(ConfigError::Invalid(_))),
+                "{}",
+                background
+            );
+        }
+    }
+
+    #[test]
+    fn test_app_account_must_be_set_together() {
+        let result =
+            Config::from_iter(vars(&[("DOMAIN", "http://localhost"), ("APP_FID", "9152")]));
+
+        // Assert a partial app account fails at startup rather than when a signer is requested
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("APP_FID", "9152"),
+            (
+                "APP_CUSTODY_ADDRESS",
+                "0x1111111111111111111111111111111111111111",
+            ),
+            ("APP_SIGNER_RPC_URL", "http://localhost:8550"),
+        ]))
+        .unwrap();
+        assert_eq!(config.app_fid, Some(9152));
+    }
+
+    #[test]
+    fn test_deposit_address_must_be_an_address() {
+        let address = "0x1234567890abcdef1234567890abcdef12345678";
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("DEPOSIT_ADDRESS", address),
+        ]))
+        .unwrap();
+
+        // Assert the Top-up address is checked at startup rather than drawn as a bad QR
+        assert_eq!(config.deposit_address.as_deref(), Some(address));
+        for bad in [
+            "1234567890abcdef1234567890abcdef12345678",
+            "0x1234",
+            "goat.eth",
+        ] {
+            let result = Config::from_iter(vars(&[
+                ("DOMAIN", "http://localhost"),
+                ("DEPOSIT_ADDRESS", bad),
+            ]));
+            assert!(matches!(result, Err(ConfigError::Invalid(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_asset_cache_path_must_be_outside_assets() {
+        let config = Config::from_iter(vars(&[("DOMAIN", "http://localhost")])).unwrap();
+
+        // Assert optimized assets go to their own directory by default, and never
+        // into assets/ where they'd be served as assets themselves
+        assert_eq!(config.asset_cache_path, "asset-cache");
+        for bad in ["", "assets", "./assets/optimized"] {
+            let result = Config::from_iter(vars(&[
+                ("DOMAIN", "http://localhost"),
+                ("ASSET_CACHE_PATH", bad),
+            ]));
+            assert!(matches!(result, Err(ConfigError::Invalid(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_cdn_base_url_serves_assets() {
+        let config = Config::from_iter(vars(&[("DOMAIN", "http://localhost")])).unwrap();
+
+        // Assert without a CDN assets come from DOMAIN
+        assert_eq!(config.asset_origin(), "http://localhost");
+
+        // Assert a CDN origin is normalized like DOMAIN and takes over assets
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("CDN_BASE_URL", "https://cdn.example.com/"),
+        ]))
+        .unwrap();
+        assert_eq!(config.asset_origin(), "https://cdn.example.com");
+        assert_eq!(config.domain, "http://localhost");
+        let result = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("CDN_BASE_URL", "cdn.example.com"),
+        ]));
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_swap_must_be_set_together() {
+        let swap = [
+            ("DOMAIN", "http://localhost"),
+            ("RPC_URL", "http://localhost:8545"),
+            ("SWAP_ROUTER", "0x00000000000000000000000000000000000000aa"),
+            (
+                "SWAP_WRAPPED_GOAT",
+                "0x00000000000000000000000000000000000000bb",
+            ),
+            ("SWAP_TOKEN", "0x00000000000000000000000000000000000000cc"),
+        ];
+
+        // Assert a full swap setup loads with the default slippage
+        let config = Config::from_iter(vars(&swap)).unwrap();
+        assert_eq!(config.swap_slippage_bps, 50);
+
+        // Assert part of one, one without RPC_URL, a bad address, all the slippage or a
+        // liquidity pair without a router is refused
+        let mut bad_address = swap;
+        bad_address[4].1 = "0xcc";
+        let mut all_slippage = swap.to_vec();
+        all_slippage.push(("SWAP_SLIPPAGE_BPS", "10000"));
+        let mut bad_pair = swap.to_vec();
+        bad_pair.push(("LIQUIDITY_PAIR", "0xdd"));
+        for bad in [
+            &swap[..4],
+            &[swap[0], swap[2], swap[3], swap[4]][..],
+            &bad_address[..],
+            &all_slippage[..],
+            &bad_pair[..],
+            &[swap[0], ("LIQUIDITY_PAIR", swap[2].1)][..],
+        ] {
+            let result = Config::from_iter(vars(bad));
+            assert!(matches!(result, Err(ConfigError::Invalid(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_rewards_contract_needs_rpc() {
+        let rewards = [
+            ("DOMAIN", "http://localhost"),
+            ("RPC_URL", "http://localhost:8545"),
+            (
+                "REWARDS_CONTRACT",
+                "0x00000000000000000000000000000000000000ee",
+            ),
+        ];
+
+        // Assert a contract with an RPC node to read it through loads
+        let config = Config::from_iter(vars(&rewards)).unwrap();
+        assert!(config.rewards_contract.is_some());
+
+        // Assert one without RPC_URL, or that isn't an address, is refused
+        for bad in [
+            &[rewards[0], rewards[2]][..],
+            &[rewards[0], rewards[1], ("REWARDS_CONTRACT", "0xee")][..],
+        ] {
+            let result = Config::from_iter(vars(bad));
+            assert!(matches!(result, Err(ConfigError::Invalid(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_boost_contract_must_be_an_address() {
+        let boost = |contract: &str| {
+            Config::from_iter(vars(&[
+                ("DOMAIN", "http://localhost"),
+                ("BOOST_CONTRACT", contract),
+            ]))
+        };
+
+        // Assert a contract address loads, and a typo is refused rather than sent to
+        assert!(boost("0x00000000000000000000000000000000000000ab").is_ok());
+        for bad in ["0xab", "00000000000000000000000000000000000000ab", "boost"] {
+            assert!(
+                matches!(boost(bad), Err(ConfigError::Invalid(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_auction_config() {
+        let auction = [
+            ("DOMAIN", "http://localhost"),
+            ("RPC_URL", "http://localhost:8545"),
+            (
+                "AUCTION_CONTRACT",
+                "0x00000000000000000000000000000000000000ab",
+            ),
+        ];
+
+        // Assert a contract loads with a 5% increment, read every 15 seconds
+        let config = Config::from_iter(vars(&auction)).unwrap();
+        assert!(config.auction_contract.is_some());
+        assert_eq!(config.auction_min_increment_bps, 500);
+        assert_eq!(config.auction_poll_secs, 15);
+
+        // Assert one without RPC_URL, that isn't an address, or never read, is refused
+        for bad in [
+            &[auction[0], auction[2]][..],
+            &[auction[0], auction[1], ("AUCTION_CONTRACT", "0xab")][..],
+            &[
+                auction[0],
+                auction[1],
+                auction[2],
+                ("AUCTION_POLL_SECS", "0"),
+            ][..],
+        ] {
+            let result = Config::from_iter(vars(bad));
+            assert!(matches!(result, Err(ConfigError::Invalid(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_bridge_needs_every_part() {
+        let bridge = [
+            ("DOMAIN", "http://localhost"),
+            ("RPC_URL", "http://localhost:8545"),
+            (
+                "BRIDGE_CONTRACT",
+                "0x00000000000000000000000000000000000000bb",
+            ),
+            ("BRIDGE_DESTINATION_CHAIN_ID", "8453"),
+            ("BRIDGE_DESTINATION_RPC_URL", "http://localhost:8546"),
+            (
+                "BRIDGE_DESTINATION_CONTRACT",
+                "0x00000000000000000000000000000000000000dd",
+            ),
+        ];
+
+        // Assert a bridge with both sides and an RPC node for each loads
+        let config = Config::from_iter(vars(&bridge)).unwrap();
+        assert_eq!(config.bridge_destination_chain_id, Some(8453));
+
+        // Assert one missing a part or RPC_URL, or with a contract that isn't an address,
+        // is refused
+        for bad in [
+            &[bridge[0], bridge[2], bridge[3], bridge[4], bridge[5]][..],
+            &[bridge[0], bridge[1], bridge[2], bridge[3], bridge[4]][..],
+            &[bridge[0], bridge[1], bridge[3], bridge[4], bridge[5]][..],
+            &[
+                bridge[0],
+                bridge[1],
+                ("BRIDGE_CONTRACT", "0xbb"),
+                bridge[3],
+                bridge[4],
+                bridge[5],
+            ][..],
+        ] {
+            let result = Config::from_iter(vars(bad));
+            assert!(matches!(result, Err(ConfigError::Invalid(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_notification_hosts() {
+        // Assert Farcaster's clients are allowed by default
+        let config = Config::from_iter(vars(&[("DOMAIN", "http://localhost")])).unwrap();
+        assert_eq!(
+            config.notification_hosts,
+            vec!["api.farcaster.xyz", "api.warpcast.com"]
+        );
+
+        // Assert hosts set are compared as URLs have them
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            (
+                "NOTIFICATION_HOSTS",
+                "API.Client.example, ,push.example.com",
+            ),
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.notification_hosts,
+            vec!["api.client.example", "push.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_idempotency_ttl_covers_message_age() {
+        // Assert by default a retry is answered for as long as its message is accepted
+        let config = Config::from_iter(vars(&[("DOMAIN", "http://localhost")])).unwrap();
+        assert!(config.idempotency_ttl_secs >= config.max_message_age_secs);
+
+        // Assert a TTL shorter than the message-age window is refused, unless messages
+        // of any age are accepted
+        let result = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("IDEMPOTENCY_TTL_SECS", "60"),
+            ("MAX_MESSAGE_AGE_SECS", "600"),
+        ]));
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "http://localhost"),
+            ("IDEMPOTENCY_TTL_SECS", "60"),
+            ("MAX_MESSAGE_AGE_SECS", "0"),
+        ]))
+        .unwrap();
+        assert_eq!(config.idempotency_ttl_secs, 60);
+    }
+
+    #[test]
+    fn test_allowed_origins() {
+        // Assert by default only DOMAIN's own pages may call the API cross-origin
+        let config =
+            Config::from_iter(vars(&[("DOMAIN", "https://frame.example.com:8443/goat/")])).unwrap();
+        assert_eq!(
+            config.allowed_origins,
+            vec!["https://frame.example.com:8443"]
+        );
+
+        // Assert any origin is only taken in local development
+        let result = Config::from_iter(vars(&[
+            ("DOMAIN", "https://frame.example.com"),
+            ("ALLOWED_ORIGINS", "*"),
+        ]));
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+        for domain in ["http://localhost:8080", "http://127.0.0.1"] {
+            let config =
+                Config::from_iter(vars(&[("DOMAIN", domain), ("ALLOWED_ORIGINS", "*")])).unwrap();
+            assert_eq!(config.allowed_origins, vec!["*"]);
+        }
+
+        // Assert local development allows any origin by default
+        let config = Config::from_iter(vars(&[("DOMAIN", "http://localhost:8080")])).unwrap();
+        assert_eq!(
+            AllowedOrigins::from_config(&config.allowed_origins),
+            Ok(AllowedOrigins::Any)
+        );
+
+        // Assert a * alongside origins is dropped in favour of them, not refused
+        let config = Config::from_iter(vars(&[
+            ("DOMAIN", "https://frame.example.com"),
+            ("ALLOWED_ORIGINS", "*,https://debugger.example.com"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            AllowedOrigins::from_config(&config.allowed_origins),
+            Ok(AllowedOrigins::List(vec![
+                "https://debugger.example.com".to_string()
+            ]))
+        );
+    }
+}
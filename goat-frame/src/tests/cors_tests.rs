@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod cors_tests {
+    use crate::assets::AssetHashes;
+    use crate::cors::{cors, AllowedOrigins};
+    use crate::error_image::ErrorImages;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::ReplayGuard;
+    use crate::tests::{noop_analytics, test_registry};
+    use crate::{handle_frame, index, Config};
+    use actix_web::{http::header, test, web, App};
+
+    fn origins(list: &[&str]) -> Vec<String> {
+        list.iter().map(|origin| origin.to_string()).collect()
+    }
+
+    #[actix_web::test]
+    async fn test_frame_api_echoes_allowed_origin() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+        let allowed =
+            AllowedOrigins::from_config(&origins(&["https://debugger.example.com"])).unwrap();
+
+        // Create a mock application with the same CORS wiring as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index))
+                .service(
+                    web::resource("/api/frame")
+                        .wrap(cors(&allowed))
+                        .app_data(web::Data::new(AssetHashes::default()))
+                        .route(web::post().to(handle_frame)),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .insert_header((header::ORIGIN, "https://debugger.example.com"))
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the allowed origin is echoed back
+        assert!(resp.status().is_success());
+        assert_eq!(
+            resp.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "https://debugger.example.com"
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((header::ORIGIN, "https://debugger.example.com"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the index page is left without CORS headers
+        assert!(resp.status().is_success());
+        assert!(resp
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_explicit_origins_win_over_wildcard() {
+        let allowed =
+            AllowedOrigins::from_config(&origins(&["*", "https://a.example.com"])).unwrap();
+
+        // Assert the wildcard is dropped in favour of the explicit list
+        assert_eq!(
+            allowed,
+            AllowedOrigins::List(origins(&["https://a.example.com"]))
+        );
+        assert_eq!(
+            AllowedOrigins::from_config(&origins(&["*"])).unwrap(),
+            AllowedOrigins::Any
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_origin_is_rejected() {
+        // Assert an origin without a scheme fails at startup instead of panicking later
+        assert!(AllowedOrigins::from_config(&origins(&["example.com"])).is_err());
+    }
+}
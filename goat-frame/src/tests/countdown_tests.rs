@@ -0,0 +1,34 @@
+#[cfg(test)]
+mod countdown_tests {
+    use crate::countdown::{cache_buster, format_countdown, STEP_SECS};
+
+    #[test]
+    fn test_format_countdown() {
+        let now = 1_700_000_000;
+
+        // Assert the time left is shown in its two largest units, minutes rounded up
+        assert_eq!(
+            format_countdown(now + 3 * 86400 + 5 * 3600 + 59, now),
+            "Ends in 3d 5h"
+        );
+        assert_eq!(
+            format_countdown(now + 2 * 3600 + 13 * 60, now),
+            "Ends in 2h 13m"
+        );
+        assert_eq!(format_countdown(now + 3599, now), "Ends in 1h 0m");
+        assert_eq!(format_countdown(now + 1, now), "Ends in 1m");
+
+        // Assert an auction at or past its end has ended
+        assert_eq!(format_countdown(now, now), "Ended");
+        assert_eq!(format_countdown(now - 60, now), "Ended");
+    }
+
+    #[test]
+    fn test_cache_buster_changes_every_step() {
+        let now = 1_700_000_000 / STEP_SECS * STEP_SECS;
+
+        // Assert the buster holds for a step and moves on with the next
+        assert_eq!(cache_buster(now), cache_buster(now + STEP_SECS - 1));
+        assert_ne!(cache_buster(now), cache_buster(now + STEP_SECS));
+    }
+}
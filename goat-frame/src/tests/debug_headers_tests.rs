@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod debug_headers_tests {
+    use crate::assets::AssetHashes;
+    use crate::debug_headers::{navigation, HANDLER_HEADER, STATE_HEADER, TIMING_HEADER};
+    use crate::error_image::ErrorImages;
+    use crate::frame_logic::state::FrameState;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::ReplayGuard;
+    use crate::tests::{noop_analytics, test_registry};
+    use crate::{handle_frame, Config};
+    use actix_web::{test, web, App};
+
+    fn press(button_index: usize, state: &str) -> serde_json::Value {
+        serde_json::json!({
+            "untrusted_data": { "button_index": button_index, "state": state }
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_debug_headers_describe_each_press() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            debug_headers: true,
+            ..Config::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        // Open More from Main
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(press(4, ""))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the headers name the branch, carry the readable state and time the press
+        let header = |name: &str| resp.headers().get(name).unwrap().to_str().unwrap();
+        assert_eq!(header(HANDLER_HEADER), "navigate:more");
+        assert_eq!(header(STATE_HEADER), r#"{"stack":["main","more"]}"#);
+        let timing = header(TIMING_HEADER);
+        assert!(timing.starts_with("process;dur="), "{}", timing);
+        assert!(timing.contains(", total;dur="), "{}", timing);
+
+        // Press Back, then an index More doesn't have
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(press(4, r#"{"stack":["main","more"]}"#))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(HANDLER_HEADER).unwrap(), "back");
+
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(press(9, ""))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(HANDLER_HEADER).unwrap(), "error");
+    }
+
+    #[actix_web::test]
+    async fn test_debug_headers_are_off_by_default() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(press(4, ""))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert nothing about the state leaks unless asked for
+        assert!(resp.status().is_success());
+        for name in [HANDLER_HEADER, STATE_HEADER, TIMING_HEADER] {
+            assert!(resp.headers().get(name).is_none(), "{}", name);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_navigation_names() {
+        let main = FrameState::default();
+        let mut more = FrameState::default();
+        more.push("more");
+
+        // Assert pushes, pops and staying put are told apart
+        assert_eq!(navigation(&main, &more), "navigate:more");
+        assert_eq!(navigation(&more, &main), "back");
+        assert_eq!(navigation(&more, &more), "stay");
+
+        // Assert moving to another frame is named after it
+        let rewards = FrameState {
+            frame: Some("rewards".to_string()),
+            ..FrameState::default()
+        };
+        assert_eq!(navigation(&more, &rewards), "handoff:rewards");
+        assert_eq!(navigation(&rewards, &rewards), "stay");
+    }
+}
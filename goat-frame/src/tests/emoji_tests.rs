@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod emoji_tests {
+    use crate::images::emoji::EmojiAtlas;
+    use crate::images::font::Fonts;
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+    use std::path::PathBuf;
+
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+    const GREEN: Rgb<u8> = Rgb([0, 255, 0]);
+    const BLUE: Rgb<u8> = Rgb([0, 0, 255]);
+
+    // 🔥 in red, 👩 in green, 👩‍💻 in blue and ❤ in half see-through red
+    fn atlas_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let emoji = [
+            ("emoji_u1f525.png", Rgba([255, 0, 0, 255])),
+            ("emoji_u1f469.png", Rgba([0, 255, 0, 255])),
+            ("emoji_u1f469_200d_1f4bb.png", Rgba([0, 0, 255, 255])),
+            ("emoji_u2764.png", Rgba([255, 0, 0, 128])),
+        ];
+        for (name, color) in emoji {
+            RgbaImage::from_pixel(8, 8, color)
+                .save(dir.join(name))
+                .unwrap();
+        }
+        std::fs::write(dir.join("LICENSE"), "not an emoji").unwrap();
+        dir
+    }
+
+    fn colors(img: &RgbImage) -> Vec<Rgb<u8>> {
+        let mut colors: Vec<Rgb<u8>> = Vec::new();
+        for pixel in img.pixels() {
+            if !colors.contains(pixel) {
+                colors.push(*pixel);
+            }
+        }
+        colors
+    }
+
+    #[test]
+    fn test_emoji_take_the_room_of_their_sequence() {
+        let dir = atlas_dir();
+        let atlas = EmojiAtlas::load(&dir).unwrap();
+        assert_eq!(atlas.len(), 4);
+        let fonts = Fonts::default().with_emoji(atlas);
+
+        // Assert an emoji is as wide as the text is tall, joined and selected sequences
+        // drawn as one
+        assert_eq!(fonts.text_width("🔥", 16.0, None), 16.0);
+        assert_eq!(fonts.text_width("a🔥", 16.0, None), 32.0);
+        assert_eq!(fonts.text_width("👩\u{200d}💻", 16.0, None), 16.0);
+        assert_eq!(fonts.text_width("❤\u{fe0f}", 16.0, None), 16.0);
+
+        // Assert without an atlas joiners and selectors still take no room
+        let plain = Fonts::default();
+        assert_eq!(plain.text_width("👩\u{200d}💻", 16.0, None), 32.0);
+        assert_eq!(plain.text_width("❤\u{fe0f}", 16.0, None), 16.0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_emoji_are_drawn_in_color() {
+        let dir = atlas_dir();
+        let fonts = Fonts::default().with_emoji(EmojiAtlas::load(&dir).unwrap());
+        let draw = |text: &str| {
+            let mut img = RgbImage::from_pixel(40, 20, Rgb([255, 255, 255]));
+            fonts.draw_text(&mut img, text, 0.0, 16.0, 16.0, None, Rgb([0, 0, 0]));
+            img
+        };
+
+        // Assert each emoji is drawn as its image, the longest sequence winning
+        let img = draw("🔥👩\u{200d}💻");
+        assert_eq!(*img.get_pixel(8, 8), RED);
+        assert_eq!(*img.get_pixel(24, 8), BLUE);
+        assert!(!colors(&img).contains(&GREEN));
+
+        // Assert see-through emoji are blended over what's under them
+        let img = draw("❤");
+        assert_eq!(*img.get_pixel(8, 8), Rgb([255, 127, 127]));
+
+        // Assert a sequence the atlas doesn't have falls back to its parts
+        let img = draw("👩\u{200d}🔥");
+        assert_eq!(*img.get_pixel(8, 8), GREEN);
+        assert_eq!(*img.get_pixel(24, 8), RED);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
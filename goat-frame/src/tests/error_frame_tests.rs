@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod error_frame_tests {
+    use crate::config::Config;
+    use crate::error_frame::ErrorFrame;
+    use crate::errors::AppError;
+    use crate::frame_logic::ButtonAction;
+
+    fn labels(frame: &ErrorFrame) -> Vec<&str> {
+        frame
+            .buttons
+            .iter()
+            .map(|button| button.label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_each_error_gets_its_own_message() {
+        let config = Config::default();
+        for (err, message, first) in [
+            (
+                AppError::BadRequest("Invalid button index: 9".to_string()),
+                "Invalid button index: 9",
+                "Try Again",
+            ),
+            (
+                AppError::NotFound("Unknown frame: nope".to_string()),
+                "That's no longer here",
+                "Go Back",
+            ),
+            (
+                AppError::TooManyRequests(12),
+                "Slow down! Try again in 12s",
+                "Try Again",
+            ),
+            (
+                AppError::FrameSpecViolation("post_url too long".to_string()),
+                "Something went wrong on our side",
+                "Try Again",
+            ),
+        ] {
+            let frame = ErrorFrame::for_error(&err, &config);
+
+            // Assert the user sees something actionable, never internal details
+            assert_eq!(frame.message, message);
+            assert_eq!(labels(&frame), [first, "Start Over"]);
+        }
+    }
+
+    #[test]
+    fn test_expired_session_only_starts_over() {
+        let frame = ErrorFrame::for_error(&AppError::SessionExpired, &Config::default());
+
+        // Assert there's nothing to try again, only the one way forward
+        assert_eq!(frame.message, "This session expired, start over");
+        assert_eq!(labels(&frame), ["Start Over"]);
+    }
+
+    #[test]
+    fn test_low_balance_offers_top_up_when_configured() {
+        let err = AppError::InsufficientBalance {
+            balance: "2".to_string(),
+            needed: "2.5".to_string(),
+        };
+
+        // Assert there's nowhere to top up without TOP_UP_URL
+        let frame = ErrorFrame::for_error(&err, &Config::default());
+        assert_eq!(
+            frame.message,
+            "Not enough GOAT: this wallet has 2 and the boost needs 2.5. Top up?"
+        );
+        assert_eq!(labels(&frame), ["Try Again", "Start Over"]);
+
+        let config = Config {
+            top_up_url: Some("https://bridge.example.com".to_string()),
+            ..Config::default()
+        };
+        let frame = ErrorFrame::for_error(&err, &config);
+
+        // Assert the link comes after the two buttons every error frame starts with
+        assert_eq!(labels(&frame), ["Try Again", "Start Over", "Top Up"]);
+        assert!(matches!(
+            &frame.buttons[2].action,
+            Some(ButtonAction::Link { url }) if url == "https://bridge.example.com"
+        ));
+    }
+}
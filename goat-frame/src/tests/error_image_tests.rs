@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod error_image_tests {
+    use crate::error_image::{render_error_image, serve_error_image, ErrorImages};
+    use crate::idempotency::IdempotencyCache;
+    use crate::metrics::Metrics;
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::ReplayGuard;
+    use crate::tests::noop_analytics;
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn test_error_route_returns_png() {
+        let images = web::Data::new(ErrorImages::default());
+        let id = images.id_for("Invalid button index: 999").unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(images.clone())
+                .route("/assets/error/{id}.png", web::get().to(serve_error_image)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/assets/error/{}.png", id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the route serves a PNG that decodes
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+        let body = test::read_body(resp).await;
+        let img = image::load_from_memory_with_format(&body, image::ImageFormat::Png).unwrap();
+        assert_eq!(img.width(), 764);
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_error_id_is_not_found() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .route("/assets/error/{id}.png", web::get().to(serve_error_image)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/assets/error/deadbeef.png")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert ids that were never rendered aren't served
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_same_message_is_cached() {
+        let images = ErrorImages::default();
+
+        // Assert the id is stable for a message and differs between messages
+        assert_eq!(images.id_for("boom"), images.id_for("boom"));
+        assert_ne!(images.id_for("boom"), images.id_for("bang"));
+    }
+
+    #[actix_web::test]
+    async fn test_long_and_malformed_messages_render() {
+        let long = "x".repeat(10_000);
+        let words = "word ".repeat(500);
+        let malformed = "\u{0}\u{1f410} caf\u{e9}\n\t<script>";
+
+        // Assert none of these panic or produce an empty image
+        for message in [long.as_str(), words.as_str(), malformed, ""] {
+            assert!(!render_error_image(message).unwrap().is_empty());
+        }
+    }
+}
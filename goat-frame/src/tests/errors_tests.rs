@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod errors_tests {
+    use crate::errors::AppError;
+    use actix_web::{http::StatusCode, test, web, App};
+
+    // Fails with whichever variant the path names
+    async fn failing_handler(path: web::Path<String>) -> Result<String, AppError> {
+        Err(match path.as_str() {
+            "internal" => AppError::InternalServerError,
+            "bad_request" => AppError::BadRequest("Invalid button index: 9".to_string()),
+            "not_found" => AppError::NotFound("Unknown frame: nope".to_string()),
+            "spec" => AppError::FrameSpecViolation("5 buttons, at most 4 allowed".to_string()),
+            "balance" => AppError::InsufficientBalance {
+                balance: "2".to_string(),
+                needed: "2.5".to_string(),
+            },
+            _ => AppError::Unauthorized("Frame message failed validation".to_string()),
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_app_errors_map_to_status_and_json_body() {
+        let app =
+            test::init_service(App::new().route("/fail/{variant}", web::get().to(failing_handler)))
+                .await;
+
+        for (variant, status, message) in [
+            (
+                "internal",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            ),
+            (
+                "bad_request",
+                StatusCode::BAD_REQUEST,
+                "Invalid button index: 9",
+            ),
+            ("not_found", StatusCode::NOT_FOUND, "Unknown frame: nope"),
+            (
+                "spec",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error",
+            ),
+            (
+                "balance",
+                StatusCode::BAD_REQUEST,
+                "Not enough GOAT: this wallet has 2 and the boost needs 2.5",
+            ),
+            (
+                "unauthorized",
+                StatusCode::UNAUTHORIZED,
+                "Frame message failed validation",
+            ),
+        ] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/fail/{}", variant))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+
+            // Assert each variant gets its own status and an {"error": ...} body
+            assert_eq!(resp.status(), status, "{}", variant);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert_eq!(body, serde_json::json!({ "error": message }));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_app_error_display_names_the_kind() {
+        // Assert log lines say what went wrong, not just the message
+        assert_eq!(
+            AppError::NotFound("Unknown frame: nope".to_string()).to_string(),
+            "Not found: Unknown frame: nope"
+        );
+        assert_eq!(
+            AppError::Unauthorized("bad signature".to_string()).to_string(),
+            "Unauthorized: bad signature"
+        );
+    }
+}
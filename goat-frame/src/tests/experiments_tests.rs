@@ -0,0 +1,197 @@
+#[cfg(test)]
+mod experiments_tests {
+    use crate::assets::AssetHashes;
+    use crate::config::Config;
+    use crate::errors::RegistryError;
+    use crate::experiments::{assign, conversions, Exposure, CONTROL};
+    use crate::frame_logic::registry::{FrameRegistry, DEFAULT_FRAME};
+    use crate::frame_logic::state::{FrameState, MAIN_SCREEN};
+    use crate::frame_logic::{render_screen, UserContext};
+    use crate::metrics::Metrics;
+    use std::path::Path;
+
+    const FRAMES: &str = r#"
+        [frames.store.screens.main]
+        image = "main.png"
+        buttons = [{ label = "Buy", target = "buy" }, { label = "More", target = "more" }]
+        experiment = { name = "cta", goal = "buy", variants = [
+            { name = "bold", image = "more.png", labels = { "Buy" = "Buy Now!" } },
+        ] }
+
+        [frames.store.screens.buy]
+        image = "buy_boost.png"
+        buttons = [{ label = "Back", target = "back" }]
+
+        [frames.store.screens.more]
+        image = "more.png"
+        buttons = [{ label = "Buy", target = "buy" }, { label = "Back", target = "back" }]
+    "#;
+
+    fn registry(frames: &str) -> FrameRegistry {
+        FrameRegistry::from_toml(frames).unwrap()
+    }
+
+    // The first fid that lands on the given arm of the test experiment
+    fn user_on(registry: &FrameRegistry, arm: &str) -> UserContext {
+        (1..)
+            .map(|fid| UserContext {
+                fid: Some(fid),
+                ..UserContext::default()
+            })
+            .find(|user| {
+                Exposure::on(DEFAULT_FRAME, &FrameState::default(), user, registry)
+                    .is_some_and(|seen| seen.arm == arm)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_assign_is_stable_and_uses_every_arm() {
+        // Assert an fid always lands on the same arm
+        assert_eq!(assign("cta", 42, 3), assign("cta", 42, 3));
+
+        // Assert a few hundred fids reach every arm, roughly evenly
+        let mut arms = [0; 3];
+        for fid in 0..300 {
+            arms[assign("cta", fid, 3)] += 1;
+        }
+        assert!(arms.iter().all(|&count| count > 60), "{:?}", arms);
+    }
+
+    #[test]
+    fn test_variant_changes_copy_and_image() {
+        let registry = registry(FRAMES);
+        registry.validate(Path::new("assets")).unwrap();
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let main = registry
+            .frame(DEFAULT_FRAME)
+            .and_then(|frame| frame.screen(MAIN_SCREEN))
+            .unwrap();
+        let render = |user: &UserContext| {
+            render_screen(
+                main,
+                &FrameState::default(),
+                user,
+                &AssetHashes::default(),
+                &config,
+            )
+            .unwrap()
+        };
+
+        // Assert the variant's fids see its label and image
+        let rendered = render(&user_on(&registry, "bold"));
+        assert_eq!(rendered.buttons[0].label, "Buy Now!");
+        assert_eq!(rendered.buttons[1].label, "More");
+        assert_eq!(rendered.image, "http://localhost/assets/more.png");
+
+        // Assert control, and anyone without an fid, see the screen as configured
+        for user in [user_on(&registry, CONTROL), UserContext::default()] {
+            let rendered = render(&user);
+            assert_eq!(rendered.buttons[0].label, "Buy");
+            assert_eq!(rendered.image, "http://localhost/assets/main.png");
+        }
+    }
+
+    #[test]
+    fn test_conversions_credit_the_arm_seen() {
+        let registry = registry(FRAMES);
+        let user = user_on(&registry, "bold");
+        let main = FrameState::default();
+        let mut more = FrameState::default();
+        more.push("more");
+        let mut buy = more.clone();
+        buy.push("buy");
+
+        // Assert reaching the goal, directly or through another screen, converts
+        let bold = Exposure {
+            experiment: "cta".to_string(),
+            arm: "bold".to_string(),
+        };
+        let mut direct = FrameState::default();
+        direct.push("buy");
+        assert_eq!(
+            conversions(DEFAULT_FRAME, &main, &direct, &user, &registry),
+            vec![bold.clone()]
+        );
+        assert_eq!(
+            conversions(DEFAULT_FRAME, &more, &buy, &user, &registry),
+            vec![bold]
+        );
+
+        // Assert staying, going back and anonymous presses don't
+        assert!(conversions(DEFAULT_FRAME, &buy, &buy, &user, &registry).is_empty());
+        assert!(conversions(DEFAULT_FRAME, &buy, &more, &user, &registry).is_empty());
+        assert!(conversions(
+            DEFAULT_FRAME,
+            &main,
+            &direct,
+            &UserContext::default(),
+            &registry
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_registry_rejects_bad_experiments() {
+        let cases = [
+            // The goal must be another screen of the frame
+            FRAMES.replace(r#"goal = "buy""#, r#"goal = "nowhere""#),
+            FRAMES.replace(r#"goal = "buy""#, r#"goal = "main""#),
+            // "control" is the screen as configured
+            FRAMES.replace(r#"name = "bold""#, r#"name = "control""#),
+            // Relabeling needs a button to relabel
+            FRAMES.replace(r#"{ "Buy" = "#, r#"{ "Sell" = "#),
+            FRAMES.replace(
+                r#""Buy Now!""#,
+                r#""Buy this GOAT right now, it is going fast""#,
+            ),
+        ];
+        for frames in cases {
+            // Assert the main screen is named as the problem
+            let err = registry(&frames).validate(Path::new("assets")).unwrap_err();
+            assert!(
+                matches!(err, RegistryError::InvalidScreen(ref name, _) if name == "store/main"),
+                "{}",
+                err
+            );
+        }
+
+        // Assert a variant image must exist like any other
+        let frames = FRAMES.replace(
+            r#"image = "more.png", labels"#,
+            r#"image = "x.png", labels"#,
+        );
+        let err = registry(&frames).validate(Path::new("assets")).unwrap_err();
+        assert!(matches!(err, RegistryError::MissingImage(_, ref image) if image == "x.png"));
+    }
+
+    #[test]
+    fn test_metrics_count_each_arm() {
+        let metrics = Metrics::default();
+        let seen = |arm: &str| Exposure {
+            experiment: "cta".to_string(),
+            arm: arm.to_string(),
+        };
+        metrics.record_exposure(&seen("bold"));
+        metrics.record_exposure(&seen("bold"));
+        metrics.record_exposure(&seen(CONTROL));
+        metrics.record_conversion(&seen("bold"));
+
+        // Assert exposures and conversions are listed per arm
+        let out = metrics.render();
+        assert!(
+            out.contains("frame_experiment_exposures_total{experiment=\"cta\",variant=\"bold\"} 2")
+        );
+        assert!(out.contains(
+            "frame_experiment_exposures_total{experiment=\"cta\",variant=\"control\"} 1"
+        ));
+        assert!(out
+            .contains("frame_experiment_conversions_total{experiment=\"cta\",variant=\"bold\"} 1"));
+        assert!(out.contains(
+            "frame_experiment_conversions_total{experiment=\"cta\",variant=\"control\"} 0"
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(test)]
+mod font_tests {
+    use crate::errors::ImageError;
+    use crate::images::font::{Font, Fonts};
+    use crate::images::svg::rasterize;
+    use crate::images::{AssetImages, LiveData};
+    use image::{Rgb, RgbImage};
+    use std::path::Path;
+
+    const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+    const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+
+    // A TrueType font named "Tiny" whose only glyph, for "A", is a full em square
+    fn tiny_font() -> Vec<u8> {
+        let u16s =
+            |values: &[u16]| -> Vec<u8> { values.iter().flat_map(|v| v.to_be_bytes()).collect() };
+
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[34..36].copy_from_slice(&2u16.to_be_bytes());
+        let maxp = [u16s(&[0, 0x5000]), u16s(&[2])].concat();
+        let hmtx = u16s(&[500, 0, 1000, 0]);
+        let square = u16s(&[
+            1, 0, 0, 1000, 1000, // one contour and its bounds
+            3, 0, // last point, no instructions
+        ]);
+        let flags = [1u8; 4];
+        let coordinates = u16s(&[0, 1000, 0, (-1000i16) as u16, 0, 0, 1000, 0]);
+        let glyf = [square, flags.to_vec(), coordinates].concat();
+        let loca = u16s(&[0, 0, glyf.len() as u16 / 2]);
+        let cmap = u16s(&[
+            0,
+            1,
+            3,
+            1,
+            0,
+            12, // one Windows Unicode subtable, at 12
+            4,
+            32,
+            0,
+            4,
+            0,
+            0,
+            0, // format 4, two segments
+            0x41,
+            0xFFFF,
+            0, // ends
+            0x41,
+            0xFFFF, // starts
+            1u16.wrapping_sub(0x41),
+            1, // deltas
+            0,
+            0, // range offsets
+        ]);
+        let family: Vec<u8> = "Tiny"
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        let name = [
+            u16s(&[0, 1, 18, 3, 1, 0x409, 1, family.len() as u16, 0]),
+            family,
+        ]
+        .concat();
+
+        let tables: [(&[u8; 4], Vec<u8>); 8] = [
+            (b"cmap", cmap),
+            (b"glyf", glyf),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", loca),
+            (b"maxp", maxp),
+            (b"name", name),
+        ];
+        let mut font = u16s(&[1, 0, tables.len() as u16, 0, 0, 0]);
+        let mut offset = 12 + tables.len() * 16;
+        let mut data = Vec::new();
+        for (tag, table) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&[0; 4]);
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            data.extend_from_slice(table);
+            offset += table.len();
+        }
+        font.extend(data);
+        font
+    }
+
+    fn tiny_fonts() -> Fonts {
+        let path = std::env::temp_dir().join(format!("goat-frame-{}.ttf", uuid::Uuid::new_v4()));
+        std::fs::write(&path, tiny_font()).unwrap();
+        let fonts = Fonts::load(&[path.display().to_string()]).unwrap();
+        std::fs::remove_file(path).unwrap();
+        fonts
+    }
+
+    #[test]
+    fn test_parse_reads_glyphs_and_metrics() {
+        let font = Font::parse(tiny_font()).unwrap();
+
+        // Assert characters map to glyphs, and ones the font lacks don't
+        assert_eq!(font.family.as_deref(), Some("Tiny"));
+        assert_eq!(font.glyph('A'), Some(1));
+        assert_eq!(font.glyph('B'), None);
+        assert_eq!(font.advance(1, 20.0), 20.0);
+    }
+
+    #[test]
+    fn test_unusable_fonts_are_refused() {
+        // Assert CFF outlines, truncated files and missing files fail at startup
+        let cff = [b"OTTO".as_slice(), &[0; 8]].concat();
+        assert!(Font::parse(cff).unwrap_err().contains("CFF"));
+        assert!(Font::parse(tiny_font()[..40].to_vec()).is_err());
+        assert!(matches!(
+            Fonts::load(&["no_such_font.ttf".to_string()]),
+            Err(ImageError::Font(..))
+        ));
+    }
+
+    #[test]
+    fn test_draw_text_falls_back_to_bitmap_glyphs() {
+        let fonts = tiny_fonts();
+        let mut img = RgbImage::from_pixel(80, 40, WHITE);
+
+        fonts.draw_text(&mut img, "A.", 10.0, 30.0, 20.0, None, BLACK);
+
+        // Assert "A" fills its em square above the baseline, and "." comes from the
+        // bitmap font at 3x, after A's advance
+        assert_eq!(*img.get_pixel(15, 15), BLACK);
+        assert_eq!(*img.get_pixel(29, 29), BLACK);
+        assert_eq!(*img.get_pixel(5, 15), WHITE);
+        assert_eq!(*img.get_pixel(15, 31), WHITE);
+        assert_eq!(fonts.text_width("A.", 20.0, None), 20.0 + 24.0);
+        let dot = (30..54).any(|x| *img.get_pixel(x, 24) == BLACK);
+        assert!(dot);
+    }
+
+    #[test]
+    fn test_rasterize_draws_text_in_loaded_fonts() {
+        let svg = r#"<svg width="40" height="40"><text x="20" y="30" font-size="20" font-family="'Tiny', sans-serif" text-anchor="middle">A</text></svg>"#;
+        let assets = AssetImages::new(Path::new("assets"));
+
+        // Assert centered text is measured with the font it's drawn in
+        let img = rasterize(svg, &assets, &tiny_fonts(), &LiveData::default()).unwrap();
+        assert_eq!(*img.get_pixel(10, 10), BLACK);
+        assert_eq!(*img.get_pixel(29, 29), BLACK);
+        assert_eq!(*img.get_pixel(31, 20), WHITE);
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod frame_check_tests {
+    use crate::config::Config;
+    use crate::frame_check::{check_meta_tags, check_registry};
+    use crate::frame_logic::registry::FrameRegistry;
+    use crate::tests::test_registry;
+    use std::path::Path;
+
+    fn config() -> Config {
+        Config {
+            domain: "https://localhost".to_string(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_shipped_frames_pass() {
+        let problems = check_registry(&test_registry(), Path::new("assets"), &config());
+
+        // Assert the frames we deploy have nothing to report
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn test_missing_og_image_is_reported() {
+        let config = Config {
+            og_image: Some("preview.png".to_string()),
+            ..config()
+        };
+
+        let problems = check_registry(&test_registry(), Path::new("assets"), &config);
+
+        // Assert a preview image that isn't in assets/ is caught before deploying
+        assert_eq!(problems, vec!["OG_IMAGE: missing asset assets/preview.png"]);
+    }
+
+    #[test]
+    fn test_broken_frames_report_every_problem() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [
+                { label = "1" }, { label = "2" }, { label = "3" }, { label = "4" }, { label = "5" },
+            ]
+
+            [frames.airdrop.screens.main]
+            image = "missing.png"
+            buttons = [{ label = "Claim" }]
+            "#,
+        )
+        .unwrap();
+
+        let problems = check_registry(&registry, Path::new("assets"), &config());
+
+        // Assert both the five-button screen and the missing asset are reported
+        assert!(problems
+            .iter()
+            .any(|p| p == "store/main: has 5 buttons, must have 1 to 4"));
+        assert!(problems
+            .iter()
+            .any(|p| p == "airdrop/main: missing asset assets/missing.png"));
+    }
+
+    #[test]
+    fn test_unknown_template_is_reported() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            template = "nope"
+            buttons = [{ label = "Go" }]
+            "#,
+        )
+        .unwrap();
+
+        let problems = check_registry(&registry, Path::new("assets"), &config());
+
+        // Assert a screen can't name a template TEMPLATES_PATH doesn't have
+        assert_eq!(
+            problems,
+            vec![r#"Screen "store/main" is invalid: unknown image template "nope""#]
+        );
+    }
+
+    #[test]
+    fn test_template_param_without_placeholder_is_reported() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "gift.png"
+            template = "gift_confirm"
+            params = { titel = "Gift to @{input}" }
+            buttons = [{ label = "Go" }]
+            "#,
+        )
+        .unwrap();
+
+        let problems = check_registry(&registry, Path::new("assets"), &config());
+
+        // Assert a param the template never draws is caught as the typo it likely is
+        assert_eq!(
+            problems,
+            vec![
+                r#"Screen "store/main" is invalid: template "gift_confirm" has no {{titel}} placeholder for param "titel""#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_meta_tag_check_flags_malformed_html() {
+        let html = r#"
+            <meta property="fc:frame" content="vNext" />
+            <meta property="fc:frame:image" content="main.png" />
+            <meta property="fc:frame:button:1" content=" " />
+            <meta property="fc:frame:button:1" content="Again" />
+        "#;
+
+        let problems = check_meta_tags(html);
+
+        // Assert relative images, missing post_url, blank and repeated buttons are caught
+        assert!(
+            problems.contains(&"fc:frame:image is not an http(s) URL: \"main.png\"".to_string())
+        );
+        assert!(problems.contains(&"missing fc:frame:post_url".to_string()));
+        assert!(problems.contains(&"fc:frame:button:1 appears 2 times".to_string()));
+        assert!(problems.contains(&"fc:frame:button:1 has an empty label".to_string()));
+    }
+}
@@ -0,0 +1,281 @@
+#[cfg(test)]
+mod frame_html_tests {
+    use crate::errors::AppError;
+    use crate::frame_html::{FrameFormat, FrameMeta};
+    use crate::frame_logic::{AspectRatio, Button, ButtonAction};
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_four_button_frame_has_each_tag_once() {
+        let buttons = vec![
+            Button::new("Buy & Boost"),
+            Button::new("Add Liquidity"),
+            Button::with_action(
+                "Docs",
+                ButtonAction::Link {
+                    url: "https://example.com/docs".to_string(),
+                },
+            )
+            .unwrap(),
+            Button::new("More"),
+        ];
+        let html = FrameMeta::new(
+            "http://localhost/assets/main.png",
+            "http://localhost/api/frame",
+        )
+        .aspect_ratio(AspectRatio::Square)
+        .input_text("Recipient username")
+        .state(r#"{"stack":["main"]}"#)
+        .buttons(buttons)
+        .to_html();
+
+        // Assert every expected tag appears exactly once, escaped where needed
+        for tag in [
+            r#"<meta property="fc:frame" content="vNext" />"#,
+            r#"<meta property="fc:frame:image" content="http://localhost/assets/main.png" />"#,
+            r#"<meta property="fc:frame:image:aspect_ratio" content="1:1" />"#,
+            r#"<meta property="fc:frame:input:text" content="Recipient username" />"#,
+            r#"<meta property="fc:frame:button:1" content="Buy &amp; Boost" />"#,
+            r#"<meta property="fc:frame:button:2" content="Add Liquidity" />"#,
+            r#"<meta property="fc:frame:button:3" content="Docs" />"#,
+            r#"<meta property="fc:frame:button:3:action" content="link" />"#,
+            r#"<meta property="fc:frame:button:3:target" content="https://example.com/docs" />"#,
+            r#"<meta property="fc:frame:button:4" content="More" />"#,
+            r#"<meta property="fc:frame:post_url" content="http://localhost/api/frame" />"#,
+            r#"<meta property="fc:frame:state" content="{&quot;stack&quot;:[&quot;main&quot;]}" />"#,
+        ] {
+            assert_eq!(html.matches(tag).count(), 1, "{}", tag);
+        }
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("fc:frame:button:5"));
+    }
+
+    #[test]
+    fn test_optional_tags_are_omitted() {
+        let tags = FrameMeta::new(
+            "http://localhost/assets/main.png",
+            "http://localhost/api/frame",
+        )
+        .buttons(vec![Button::new("Go")])
+        .meta_tags();
+
+        // Assert nothing is emitted for unset input, ratio, state or preview
+        assert!(!tags.contains("fc:frame:input:text"));
+        assert!(!tags.contains("fc:frame:image:aspect_ratio"));
+        assert!(!tags.contains("fc:frame:state"));
+        assert!(!tags.contains("og:"));
+    }
+
+    #[test]
+    fn test_builder_serializes_to_json() {
+        let meta = FrameMeta::new("http://localhost/assets/main.png", "http://localhost/")
+            .aspect_ratio(AspectRatio::Square)
+            .buttons(vec![
+                Button::new("Buy"),
+                Button::with_action(
+                    "Docs",
+                    ButtonAction::Link {
+                        url: "https://example.com/docs".to_string(),
+                    },
+                )
+                .unwrap(),
+            ])
+            .state(r#"{"stack":["main"]}"#)
+            .open_frames(true);
+
+        // Assert buttons are kept in the order added, for both HTML and JSON
+        let html = meta.to_html();
+        assert!(html.contains(r#"<meta property="fc:frame:button:1" content="Buy" />"#));
+        assert!(html.contains(r#"<meta property="fc:frame:button:2" content="Docs" />"#));
+
+        // Assert the JSON carries the frame alone, leaving unset fields out
+        assert_eq!(
+            serde_json::to_value(&meta).unwrap(),
+            serde_json::json!({
+                "version": "vNext",
+                "image": "http://localhost/assets/main.png",
+                "post_url": "http://localhost/",
+                "aspect_ratio": "1:1",
+                "state": r#"{"stack":["main"]}"#,
+                "buttons": [
+                    { "label": "Buy" },
+                    {
+                        "label": "Docs",
+                        "action": { "type": "link", "url": "https://example.com/docs" }
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_labels_and_urls_are_escaped() {
+        let html = FrameMeta::new(
+            "http://localhost/assets/a.png?x=1&y=\"2\"",
+            "http://localhost/api/frame",
+        )
+        .buttons(vec![Button::new("<script>alert(1)</script>")])
+        .to_html();
+
+        // Assert config-supplied text can't break out of the attribute
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("a.png?x=1&amp;y=&quot;2&quot;"));
+    }
+
+    #[test]
+    fn test_open_frames_tags_mirror_the_frame() {
+        let meta = FrameMeta::new(
+            "http://localhost/assets/main.png",
+            "http://localhost/api/frame",
+        )
+        .state("{}")
+        .buttons(vec![Button::new("Go")]);
+        let farcaster_only = meta.meta_tags();
+        let tags = meta.open_frames(true).meta_tags();
+
+        // Assert of:* tags are only added on request, after the unchanged fc:frame ones
+        assert!(!farcaster_only.contains("of:"));
+        assert!(tags.starts_with(&farcaster_only));
+        for tag in [
+            r#"<meta property="of:version" content="vNext" />"#,
+            r#"<meta property="of:accepts:xmtp" content="2024-02-01" />"#,
+            r#"<meta property="of:accepts:lens" content="1.1" />"#,
+            r#"<meta property="of:image" content="http://localhost/assets/main.png" />"#,
+            r#"<meta property="of:button:1" content="Go" />"#,
+            r#"<meta property="of:post_url" content="http://localhost/api/frame" />"#,
+            r#"<meta property="of:state" content="{}" />"#,
+        ] {
+            assert_eq!(tags.matches(tag).count(), 1, "{}", tag);
+        }
+    }
+
+    #[test]
+    fn test_image_alt_is_tagged_for_each_protocol() {
+        let meta = FrameMeta::new(
+            "http://localhost/assets/main.png",
+            "http://localhost/api/frame",
+        )
+        .image_alt("GOAT \"Store\" home")
+        .buttons(vec![Button::new("Go")]);
+
+        // Assert the alt text follows the image, escaped, under both prefixes
+        let tags = meta.open_frames(true).meta_tags();
+        for tag in [
+            r#"<meta property="fc:frame:image:alt" content="GOAT &quot;Store&quot; home" />"#,
+            r#"<meta property="of:image:alt" content="GOAT &quot;Store&quot; home" />"#,
+        ] {
+            assert_eq!(tags.matches(tag).count(), 1, "{}", tag);
+        }
+
+        // Assert frames without alt text don't get an empty tag
+        let plain = FrameMeta::new(
+            "http://localhost/assets/main.png",
+            "http://localhost/api/frame",
+        );
+        assert!(!plain.meta_tags().contains("image:alt"));
+    }
+
+    #[test]
+    fn test_validate_accepts_frames_within_limits() {
+        for image in [
+            "https://frame.example.com/assets/main.png",
+            "http://localhost/assets/main.png",
+        ] {
+            let meta = FrameMeta::new(image, "https://frame.example.com/api/frame")
+                .input_text("Recipient username")
+                .state("{}")
+                .buttons(vec![
+                    Button::new("A"),
+                    Button::new("B"),
+                    Button::new("C"),
+                    Button::new("D"),
+                ]);
+
+            // Assert https images, or http on this machine, pass with four buttons
+            assert!(meta.validate().is_ok(), "{}", image);
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_spec_violations() {
+        let post_url = "https://frame.example.com/api/frame";
+        let image = "https://frame.example.com/assets/main.png";
+        for (meta, reason) in [
+            (
+                FrameMeta::new(image, post_url).buttons(vec![Button::new("Go"); 5]),
+                "5 buttons",
+            ),
+            (
+                FrameMeta::new(image, post_url).buttons(vec![Button::new(&"x".repeat(33))]),
+                "label",
+            ),
+            (
+                FrameMeta::new(image, post_url).input_text(&"x".repeat(33)),
+                "input placeholder",
+            ),
+            (
+                FrameMeta::new(image, post_url).state(&"x".repeat(4097)),
+                "state",
+            ),
+            (
+                FrameMeta::new(image, &format!("{}?{}", post_url, "x".repeat(256))),
+                "post_url",
+            ),
+            (
+                FrameMeta::new("http://frame.example.com/assets/main.png", post_url),
+                "not an https URL",
+            ),
+        ] {
+            // Assert each limit fails with an error that says which one
+            match meta.validate() {
+                Err(AppError::FrameSpecViolation(message)) => {
+                    assert!(message.contains(reason), "{}", message)
+                }
+                other => panic!("expected a spec violation for {}, got {:?}", reason, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_follows_accept_ranking() {
+        let negotiate = |accept: Option<&str>| {
+            let mut req = TestRequest::post();
+            if let Some(accept) = accept {
+                req = req.insert_header(("Accept", accept));
+            }
+            FrameFormat::negotiate(&req.to_http_request())
+        };
+
+        // Assert clients that don't ask, or take anything, get the page
+        assert_eq!(negotiate(None), FrameFormat::Html);
+        assert_eq!(negotiate(Some("*/*")), FrameFormat::Html);
+        assert_eq!(
+            negotiate(Some("text/html, application/json;q=0.9")),
+            FrameFormat::Html
+        );
+
+        // Assert JSON is sent only when it's preferred
+        assert_eq!(negotiate(Some("application/json")), FrameFormat::Json);
+        assert_eq!(
+            negotiate(Some("text/html;q=0.5, application/json")),
+            FrameFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_both_formats_render_the_same_frame() {
+        let meta = FrameMeta::new("https://example.com/a.png", "https://example.com/")
+            .buttons(vec![Button::new("Go")])
+            .state("abc");
+
+        let html = FrameFormat::Html.render(&meta);
+        assert!(html.contains(r#"<meta property="fc:frame:button:1" content="Go" />"#));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&FrameFormat::Json.render(&meta)).unwrap();
+        assert_eq!(json["image"], "https://example.com/a.png");
+        assert_eq!(json["buttons"][0]["label"], "Go");
+        assert_eq!(json["state"], "abc");
+    }
+}
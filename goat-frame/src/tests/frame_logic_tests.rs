@@ -1,51 +1,1804 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use crate::assets::AssetHashes;
+    use crate::client_detection::ClientKind;
     use crate::config::Config;
+    use crate::countdown;
+    use crate::errors::{AppError, RegistryError};
+    use crate::frame_html::{button_meta_tags, FrameMeta};
+    use crate::frame_logic::registry::{FrameRegistry, DEFAULT_FRAME};
+    use crate::frame_logic::state::FrameState;
+    use crate::frame_logic::{
+        process_button, render_screen, AspectRatio, Button, ButtonAction, UserContext,
+    };
+    use crate::images::theme::Theme;
+    use crate::tests::{press, test_registry};
+    use std::path::Path;
 
-    #[test]
-    fn test_process_button_buy_boost() {
+    #[actix_web::test]
+    async fn test_process_button_buy_boost() {
         // Mock configuration with a test domain
         let config = Config {
             domain: "http://localhost".to_string(),
+            ..Config::default()
         };
-        
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
         // Test the Buy & Boost button
-        let result = process_button(1, &config).unwrap();
-        
+        let (result, _) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
         // Assert the correct image and buttons are returned
-        assert_eq!(result.0, "http://localhost/assets/buy_boost.png");
-        assert_eq!(result.1[0].label, "Confirm");
-        assert_eq!(result.1[1].label, "Back");
+        assert_eq!(
+            result.image,
+            "http://localhost/images/buy_boost.png?image=buy_boost.png&step=1&steps=2"
+        );
+        assert_eq!(result.buttons[0].label, "Confirm");
+        assert_eq!(
+            result.buttons[0].action,
+            Some(ButtonAction::Tx {
+                target: "http://localhost/api/tx/swap".to_string(),
+                post_url: "http://localhost/".to_string(),
+            })
+        );
+        assert_eq!(result.buttons[1].label, "Boost");
+        assert_eq!(
+            result.buttons[1].action,
+            Some(ButtonAction::Tx {
+                target: "http://localhost/api/tx/buy_boost".to_string(),
+                post_url: "http://localhost/".to_string(),
+            })
+        );
+        assert_eq!(result.buttons[2].label, "Back");
     }
 
-    #[test]
-    fn test_process_button_add_liquidity() {
+    #[actix_web::test]
+    async fn test_process_button_add_liquidity() {
         // Mock configuration with a test domain
         let config = Config {
             domain: "http://localhost".to_string(),
+            ..Config::default()
         };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
 
         // Test the Add Liquidity button
-        let result = process_button(2, &config).unwrap();
+        let (result, _) = process_button(
+            DEFAULT_FRAME,
+            &press(2, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
 
         // Assert the correct image and buttons are returned
-        assert_eq!(result.0, "http://localhost/assets/add_liquidity.png");
-        assert_eq!(result.1[0].label, "Add");
-        assert_eq!(result.1[1].label, "Back");
+        assert_eq!(result.image, "http://localhost/assets/add_liquidity.png");
+        assert_eq!(result.buttons[0].label, "Approve");
+        assert_eq!(result.buttons[1].label, "Add");
+        assert_eq!(result.buttons[2].label, "Back");
+        assert_eq!(
+            result.buttons[1].action,
+            Some(ButtonAction::Tx {
+                target: "http://localhost/api/tx/liquidity".to_string(),
+                post_url: "http://localhost/".to_string(),
+            })
+        );
     }
 
-    #[test]
-    fn test_process_button_invalid() {
+    #[actix_web::test]
+    async fn test_process_button_invalid() {
         // Mock configuration with a test domain
         let config = Config {
             domain: "http://localhost".to_string(),
+            ..Config::default()
         };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
 
         // Test an invalid button index
-        let result = process_button(999, &config);
+        let result = process_button(
+            DEFAULT_FRAME,
+            &press(999, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await;
 
         // Assert that the function returns an error
         assert!(result.is_err());
     }
+
+    #[actix_web::test]
+    async fn test_expired_state_starts_over() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        let expired = FrameState {
+            expires_at: Some(1),
+            ..FrameState::default()
+        };
+
+        // Assert a press on a frame left open too long isn't acted on
+        let result = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            expired.clone(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::SessionExpired)));
+
+        // Assert a transaction the user sent is still confirmed
+        let sent = FrameState {
+            transaction_id: Some("0xabc".to_string()),
+            ..expired
+        };
+        let result = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            sent,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_error_frame_buttons_recover() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        // An error frame shown over the Buy & Boost screen
+        let mut failed = FrameState {
+            error: true,
+            ..FrameState::default()
+        };
+        failed.push("buy_boost");
+
+        // Assert Try Again shows the screen the user was on, not its first button's target
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            failed.clone(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            rendered.image,
+            "http://localhost/images/buy_boost.png?image=buy_boost.png&step=1&steps=2"
+        );
+        assert_eq!(state.current(), "buy_boost");
+        assert!(!state.error);
+
+        // Assert Start Over returns to main
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(2, None, &UserContext::default()),
+            failed.clone(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rendered.image, "http://localhost/assets/main.png");
+        assert_eq!(state, FrameState::default());
+
+        // Assert an index the error frame never showed is refused
+        let result = process_button(
+            DEFAULT_FRAME,
+            &press(3, None, &UserContext::default()),
+            failed,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_process_button_rejects_index_zero() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        let result = process_button(
+            DEFAULT_FRAME,
+            &press(0, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await;
+
+        // Assert 0 is refused with the valid range for the four-button main screen
+        assert!(matches!(
+            result,
+            Err(AppError::BadRequest(ref message))
+                if message == "Invalid button index: 0 (expected 1 to 4)"
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_process_button_range_follows_previous_screen() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        // Open Buy & Boost, which only shows three buttons
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert its last button works but the one after it doesn't
+        assert!(process_button(
+            DEFAULT_FRAME,
+            &press(3, None, &UserContext::default()),
+            state.clone(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client
+        )
+        .await
+        .is_ok());
+        let result = process_button(
+            DEFAULT_FRAME,
+            &press(4, None, &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(AppError::BadRequest(ref message))
+                if message == "Invalid button index: 4 (expected 1 to 3)"
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_process_button_more_then_back() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        // Walk Main -> More through the encoded state, as a client would
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(4, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rendered.image, "http://localhost/assets/more.png");
+        assert_eq!(state.current(), "more");
+        let state = FrameState::decode(&state.encode());
+
+        // Press Back, the 4th button on the More screen
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(4, None, &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        let (image, buttons) = (rendered.image, rendered.buttons);
+
+        // Assert we land back on Main with its original buttons
+        assert_eq!(state.current(), "main");
+        assert_eq!(image, "http://localhost/assets/main.png");
+        assert_eq!(buttons.len(), 4);
+        assert_eq!(buttons[0].label, "Buy & Boost");
+        assert_eq!(buttons[3].label, "More");
+    }
+
+    #[actix_web::test]
+    async fn test_back_unwinds_nested_screens() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            state_secret: Some("secret".to_string()),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        let secret = config.state_secret.as_deref();
+
+        // Main -> Gift -> Gift confirmation -> Back -> Back, with signed state between
+        // presses as a client would carry it
+        let mut state = FrameState::default();
+        let mut screens = Vec::new();
+        for (button_index, input) in [(3, None), (1, Some("alice")), (2, None), (2, None)] {
+            let (_, next) = process_button(
+                DEFAULT_FRAME,
+                &press(button_index, input, &UserContext::default()),
+                state,
+                &registry,
+                &AssetHashes::default(),
+                &config,
+                &client,
+            )
+            .await
+            .unwrap();
+            state = FrameState::decode_signed(&next.encode_signed(secret), secret);
+            screens.push(state.current().to_string());
+        }
+
+        // Assert each Back returns to the screen before it, not straight to Main
+        assert_eq!(screens, ["gift", "gift_confirm", "gift", "main"]);
+    }
+
+    #[actix_web::test]
+    async fn test_process_button_stays_on_sub_screen() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        // Confirm on Buy & Boost has nowhere to go yet
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        let image = rendered.image;
+
+        // Assert the user is still on Buy & Boost
+        assert_eq!(state.current(), "buy_boost");
+        assert_eq!(
+            image,
+            "http://localhost/images/buy_boost.png?image=buy_boost.png&step=1&steps=2"
+        );
+    }
+
+    #[test]
+    fn test_meta_tags_for_each_action() {
+        let buttons = vec![
+            Button::new("Plain"),
+            Button::with_action("Checkout", ButtonAction::PostRedirect).unwrap(),
+            Button::with_action(
+                "Docs",
+                ButtonAction::Link {
+                    url: "https://example.com/docs".to_string(),
+                },
+            )
+            .unwrap(),
+            Button::with_action(
+                "Mint",
+                ButtonAction::Mint {
+                    target: "eip155:8453:0x00000000000000000000000000000000000000aa/1".to_string(),
+                },
+            )
+            .unwrap(),
+        ];
+
+        let tags = button_meta_tags("fc:frame", &buttons);
+
+        // Assert a plain button gets only its label
+        assert!(tags.contains(r#"<meta property="fc:frame:button:1" content="Plain" />"#));
+        assert!(!tags.contains("fc:frame:button:1:action"));
+
+        // Assert each action variant emits its action and target tags
+        assert!(tags.contains(r#"property="fc:frame:button:2:action" content="post_redirect""#));
+        assert!(tags.contains(r#"property="fc:frame:button:3:action" content="link""#));
+        assert!(tags
+            .contains(r#"property="fc:frame:button:3:target" content="https://example.com/docs""#));
+        assert!(tags.contains(r#"property="fc:frame:button:4:action" content="mint""#));
+        assert!(
+            tags.contains(r#"property="fc:frame:button:4:target" content="eip155:8453:0x00000000000000000000000000000000000000aa/1""#)
+        );
+    }
+
+    #[test]
+    fn test_meta_tags_for_tx_and_post_actions() {
+        let buttons = vec![
+            Button::with_action("Post", ButtonAction::Post { target: None }).unwrap(),
+            Button::with_action(
+                "Buy",
+                ButtonAction::Tx {
+                    target: "https://example.com/api/tx".to_string(),
+                    post_url: "https://example.com/api/frame".to_string(),
+                },
+            )
+            .unwrap(),
+        ];
+
+        let tags = button_meta_tags("fc:frame", &buttons);
+
+        // Assert explicit post and tx buttons emit their tags
+        assert!(tags.contains(r#"property="fc:frame:button:1:action" content="post""#));
+        assert!(tags.contains(r#"property="fc:frame:button:2:action" content="tx""#));
+        assert!(tags.contains(
+            r#"property="fc:frame:button:2:target" content="https://example.com/api/tx""#
+        ));
+        assert!(tags.contains(
+            r#"property="fc:frame:button:2:post_url" content="https://example.com/api/frame""#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_post_button_target_resolves_against_domain() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+
+        // Gift hands off to a frame of its own, while Buy stays on this one
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [
+                { label = "Buy" },
+                { label = "Gift", action = { type = "post", target = "/f/gift/frame" } },
+            ]
+            "#,
+        )
+        .unwrap();
+        let rendered = crate::frame_logic::render_screen(
+            registry
+                .frame(DEFAULT_FRAME)
+                .unwrap()
+                .screen("main")
+                .unwrap(),
+            &FrameState::default(),
+            &UserContext::default(),
+            &AssetHashes::default(),
+            &config,
+        )
+        .unwrap();
+        let tags = button_meta_tags("fc:frame", &rendered.buttons);
+
+        // Assert only the targeted button overrides where it posts
+        assert!(!tags.contains("fc:frame:button:1:target"));
+        assert!(tags.contains(r#"property="fc:frame:button:2:action" content="post""#));
+        assert!(tags.contains(
+            r#"property="fc:frame:button:2:target" content="http://localhost/f/gift/frame""#
+        ));
+
+        // Assert a target that isn't a URL or path is refused
+        assert!(Button::with_action(
+            "Gift",
+            ButtonAction::Post {
+                target: Some("gift".to_string())
+            }
+        )
+        .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_launch_frame_button_opens_the_mini_app() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [
+                { label = "More", action = { type = "launch_frame" } },
+                { label = "Auction", action = { type = "launch_frame", target = "/f/auction" } },
+            ]
+            "#,
+        )
+        .unwrap();
+        let rendered = crate::frame_logic::render_screen(
+            registry
+                .frame(DEFAULT_FRAME)
+                .unwrap()
+                .screen("main")
+                .unwrap(),
+            &FrameState::default(),
+            &UserContext::default(),
+            &AssetHashes::default(),
+            &config,
+        )
+        .unwrap();
+        let tags = button_meta_tags("fc:frame", &rendered.buttons);
+
+        // Assert an untargeted launch opens the home page and a path resolves against the domain
+        assert!(tags.contains(r#"property="fc:frame:button:1:action" content="launch_frame""#));
+        assert!(tags.contains(r#"property="fc:frame:button:1:target" content="http://localhost/""#));
+        assert!(tags.contains(
+            r#"property="fc:frame:button:2:target" content="http://localhost/f/auction""#
+        ));
+    }
+
+    #[test]
+    fn test_invalid_actions_fail_to_construct() {
+        // Assert actions missing their url or target are rejected
+        assert!(Button::with_action("Docs", ButtonAction::Link { url: String::new() }).is_err());
+        for target in [
+            "not-caip10",
+            "eip155:8453:0x1234/1",
+            "eip155:base:0x00000000000000000000000000000000000000aa",
+            "eip155:8453:0x00000000000000000000000000000000000000aa/",
+        ] {
+            let mint = ButtonAction::Mint {
+                target: target.to_string(),
+            };
+            assert!(Button::with_action("Mint", mint).is_err(), "{}", target);
+        }
+        assert!(Button::with_action(
+            "Buy",
+            ButtonAction::Tx {
+                target: "https://example.com/api/tx".to_string(),
+                post_url: String::new()
+            }
+        )
+        .is_err());
+        assert!(Button::with_action(
+            "More",
+            ButtonAction::LaunchFrame {
+                target: Some("more".to_string())
+            }
+        )
+        .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_process_button_uses_configured_frames() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+
+        // A sample frames file that renames the gift flow and links out to send
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Gift Now", target = "gift" }]
+
+            [frames.store.screens.gift]
+            image = "gift.png"
+            buttons = [
+                { label = "Send", action = { type = "link", url = "https://example.com/gift" } },
+                { label = "Back", target = "back" },
+            ]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        let client = reqwest::Client::new();
+
+        let (rendered, _) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        let (image, buttons) = (rendered.image, rendered.buttons);
+
+        // Assert the configured image, labels and actions are returned
+        assert_eq!(image, "http://localhost/assets/gift.png");
+        assert_eq!(buttons[0].label, "Send");
+        assert_eq!(
+            buttons[0].action,
+            Some(ButtonAction::Link {
+                url: "https://example.com/gift".to_string()
+            })
+        );
+        assert_eq!(buttons[1].label, "Back");
+    }
+
+    #[test]
+    fn test_registry_rejects_missing_image() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "does_not_exist.png"
+            buttons = [{ label = "Go" }]
+            "#,
+        )
+        .unwrap();
+
+        // Assert the missing asset is reported by name
+        let err = registry.validate(Path::new("assets")).unwrap_err();
+        assert!(
+            matches!(err, RegistryError::MissingImage(_, ref image) if image == "does_not_exist.png")
+        );
+    }
+
+    #[test]
+    fn test_registry_rejects_missing_target() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Go", target = "nowhere" }]
+            "#,
+        )
+        .unwrap();
+
+        // Assert the dangling target is reported by name
+        let err = registry.validate(Path::new("assets")).unwrap_err();
+        assert!(
+            matches!(err, RegistryError::MissingTarget(_, _, ref target) if target == "nowhere")
+        );
+    }
+
+    #[test]
+    fn test_registry_rejects_misplaced_template_params() {
+        for (screen, reason) in [
+            (
+                r#"params = { title = "Hi" }"#,
+                "params need a template to fill",
+            ),
+            ("animated = true", "animated needs a template"),
+            ("ends_at = 1798761600", "ends_at needs a template"),
+            (
+                r#"template = "gift_confirm"
+                params = { input = "Hi" }"#,
+                "must be a placeholder name other than",
+            ),
+            (
+                r#"template = "gift_confirm"
+                params = { "a b" = "Hi" }"#,
+                "must be a placeholder name other than",
+            ),
+            (
+                r#"template = "gift_confirm"
+                params = { theme_text = "red" }"#,
+                "must be a placeholder name other than",
+            ),
+        ] {
+            let registry = FrameRegistry::from_toml(&format!(
+                r#"
+                [frames.store.screens.main]
+                image = "main.png"
+                {}
+                buttons = [{{ label = "Go" }}]
+                "#,
+                screen
+            ))
+            .unwrap();
+
+            // Assert params and animation need a template, and params can't stand in
+            // for the ones every generated image gets
+            let err = registry.validate(Path::new("assets")).unwrap_err();
+            assert!(
+                matches!(err, RegistryError::InvalidScreen(_, ref r) if r.contains(reason)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_theme_button_switches_generated_images() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [
+                { label = "Light", theme = "light" },
+                { label = "Next", target = "sent" },
+            ]
+
+            [frames.store.screens.sent]
+            image = "buy_boost.png"
+            template = "tx_status"
+            buttons = [{ label = "Back", target = "back" }]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        let client = reqwest::Client::new();
+
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        let state = FrameState::decode(&state.encode());
+
+        // Assert the press keeps the user where they are with the theme picked
+        assert_eq!(state.current(), "main");
+        assert_eq!(state.theme, Some(Theme::Light));
+
+        // Assert later generated images are asked for in that theme
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(2, None, &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(state.theme, Some(Theme::Light));
+        assert_eq!(
+            rendered.image,
+            "http://localhost/images/tx_status.png?image=buy_boost.png&theme=light"
+        );
+    }
+
+    #[test]
+    fn test_images_come_from_the_cdn() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            cdn_base_url: Some("https://cdn.example.com".to_string()),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Go", action = { type = "link", url = "/boost" } }]
+
+            [frames.store.screens.sent]
+            image = "buy_boost.png"
+            template = "tx_status"
+            buttons = [{ label = "Back", target = "back" }]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        let frame = registry.frame("store").unwrap();
+        let render = |name: &str| {
+            render_screen(
+                frame.screen(name).unwrap(),
+                &FrameState::default(),
+                &UserContext::default(),
+                &AssetHashes::default(),
+                &config,
+            )
+            .unwrap()
+        };
+
+        // Assert asset and generated images point at the CDN, while links stay on
+        // DOMAIN
+        let main = render("main");
+        assert_eq!(main.image, "https://cdn.example.com/assets/main.png");
+        assert_eq!(
+            main.buttons[0].action,
+            Some(ButtonAction::Link {
+                url: "http://localhost/boost".to_string()
+            })
+        );
+        assert_eq!(
+            render("sent").image,
+            "https://cdn.example.com/images/tx_status.png?image=buy_boost.png"
+        );
+    }
+
+    #[test]
+    fn test_animated_screen_shows_a_gif() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            template = "auction"
+            animated = true
+            buttons = [{ label = "Bid" }]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        let screen = registry.frame("store").unwrap().screen("main").unwrap();
+
+        // Assert the image is the template's GIF rather than its PNG
+        let rendered = render_screen(
+            screen,
+            &FrameState::default(),
+            &UserContext::default(),
+            &AssetHashes::default(),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(
+            rendered.image,
+            "http://localhost/images/auction.gif?image=main.png"
+        );
+    }
+
+    #[test]
+    fn test_countdown_screen_changes_url_every_step() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            template = "auction"
+            ends_at = 1798761600
+            buttons = [{ label = "Bid" }]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        let screen = registry.frame("store").unwrap().screen("main").unwrap();
+
+        // Assert the image carries the end time and the current step, so it's
+        // fetched again once the countdown moves on
+        let before = countdown::cache_buster(countdown::unix_now());
+        let rendered = render_screen(
+            screen,
+            &FrameState::default(),
+            &UserContext::default(),
+            &AssetHashes::default(),
+            &config,
+        )
+        .unwrap();
+        let after = countdown::cache_buster(countdown::unix_now());
+        assert!(
+            [before, after].iter().any(|buster| rendered.image
+                == format!(
+                    "http://localhost/images/auction.png?image=main.png&ends_at=1798761600&t={}",
+                    buster
+                )),
+            "{}",
+            rendered.image
+        );
+    }
+
+    #[test]
+    fn test_registry_rejects_theme_button_with_action() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [
+                { label = "Light", theme = "light", action = { type = "link", url = "https://example.com" } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        // Assert a theme button has to post back here for the theme to take
+        let err = registry.validate(Path::new("assets")).unwrap_err();
+        assert!(
+            matches!(err, RegistryError::InvalidScreen(_, ref r) if r.contains("sets a theme")),
+            "{}",
+            err
+        );
+
+        // Assert themes other than dark and light don't parse
+        assert!(FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Blue", theme = "blue" }]
+            "#,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_flows_number_their_screens() {
+        let flows = |flows: &str| {
+            FrameRegistry::from_toml(&format!(
+                r#"
+                [frames.store.flows]
+                {}
+
+                [frames.store.screens.main]
+                image = "main.png"
+                buttons = [{{ label = "Buy", target = "confirm" }}]
+
+                [frames.store.screens.confirm]
+                image = "buy_boost.png"
+                buttons = [{{ label = "Done", target = "done" }}]
+
+                [frames.store.screens.done]
+                image = "buy_boost.png"
+                buttons = [{{ label = "Back", target = "back" }}]
+                "#,
+                flows
+            ))
+            .unwrap()
+        };
+
+        // Assert each screen of a flow knows its step, and others have none
+        let registry = flows(r#"buy = ["main", "confirm", "done"]"#);
+        registry.validate(Path::new("assets")).unwrap();
+        let step = |screen: &str| {
+            let progress = registry
+                .frame("store")
+                .unwrap()
+                .screen(screen)
+                .unwrap()
+                .progress;
+            progress.map(|progress| (progress.step, progress.steps))
+        };
+        assert_eq!(step("main"), Some((1, 3)));
+        assert_eq!(step("done"), Some((3, 3)));
+        assert_eq!(
+            flows("")
+                .frame("store")
+                .unwrap()
+                .screen("done")
+                .unwrap()
+                .progress,
+            None
+        );
+
+        // Assert flows of one screen, of missing screens, or that share one are refused
+        for bad in [
+            r#"buy = ["main"]"#,
+            r#"buy = ["main", "checkout"]"#,
+            r#"buy = ["main", "confirm", "main"]"#,
+            "buy = [\"main\", \"confirm\"]\ngift = [\"confirm\", \"done\"]",
+        ] {
+            let err = flows(bad).validate(Path::new("assets")).unwrap_err();
+            assert!(
+                matches!(err, RegistryError::InvalidFrame(ref frame, _) if frame == "store"),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_registry_rejects_unsafe_frame_name() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames."a/b".screens.main]
+            image = "main.png"
+            buttons = [{ label = "Go" }]
+            "#,
+        )
+        .unwrap();
+
+        // Assert a frame name that can't sit in /f/{name} is rejected
+        let err = registry.validate(Path::new("assets")).unwrap_err();
+        assert!(matches!(err, RegistryError::InvalidFrame(ref name, _) if name == "a/b"));
+    }
+
+    #[actix_web::test]
+    async fn test_hand_off_carries_state_to_the_other_frame() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.gift.screens.main]
+            image = "gift.png"
+            buttons = [{ label = "Rewards", frame = "rewards" }]
+
+            [frames.rewards.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Claim" }]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        let client = reqwest::Client::new();
+        let gifted = FrameState {
+            input: Some("alice".to_string()),
+            transaction_id: Some(format!("0x{}", "ab".repeat(32))),
+            ..FrameState::default()
+        };
+
+        let (_, state) = process_button(
+            "gift",
+            &press(1, None, &UserContext::default()),
+            gifted.clone(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert the user lands on Rewards' main with what they entered and sent
+        assert_eq!(state.frame.as_deref(), Some("rewards"));
+        assert_eq!(state.stack, vec!["main"]);
+        assert_eq!(state.input, gifted.input);
+        assert_eq!(state.transaction_id, gifted.transaction_id);
+
+        // Assert state handed to Rewards starts over if it's posted to another frame
+        let (_, state) = process_button(
+            "gift",
+            &press(1, None, &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(state.input, None);
+    }
+
+    #[actix_web::test]
+    async fn test_hand_off_to_missing_screen_fails_validation() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.gift.screens.main]
+            image = "gift.png"
+            buttons = [{ label = "Rewards", frame = "rewards", target = "claim" }]
+
+            [frames.rewards.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Claim" }]
+            "#,
+        )
+        .unwrap();
+
+        // Assert the target is looked up in the frame handed off to
+        let err = registry.validate(Path::new("assets")).unwrap_err();
+        assert!(
+            matches!(err, RegistryError::MissingTarget(_, _, ref target) if target == "rewards/claim")
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_post_path_fails_validation() {
+        // Assert steps that aren't a single safe path segment, or shadow /frame, are refused
+        for post_path in ["", "frame", "a/b", "confirm#top", "confirm?flow=a b"] {
+            let registry = FrameRegistry::from_toml(&format!(
+                r#"
+                [frames.store.screens.main]
+                image = "main.png"
+                post_path = "{}"
+                buttons = [{{ label = "Buy" }}]
+                "#,
+                post_path
+            ))
+            .unwrap();
+            let err = registry.validate(Path::new("assets")).unwrap_err();
+            assert!(
+                matches!(err, RegistryError::InvalidScreen(..)),
+                "{:?}",
+                post_path
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_input_rules_refuse_bad_text() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            input = { placeholder = "Amount", rules = { min = 1, max = 1000, message = "Enter 1 to 1000 GOAT" } }
+            buttons = [{ label = "Buy", target = "gift" }, { label = "Back", target = "back" }]
+
+            [frames.store.screens.gift]
+            image = "gift.png"
+            input = { placeholder = "Username", rules = { pattern = "[a-z0-9][a-z0-9-]{0,15}", message = "Enter a username" } }
+            buttons = [{ label = "Send", target = "main" }]
+            "#,
+        )
+        .unwrap();
+        let client = reqwest::Client::new();
+        let submit = |text: &'static str, state: FrameState| {
+            let registry = &registry;
+            let (config, client) = (&config, &client);
+            async move {
+                process_button(
+                    DEFAULT_FRAME,
+                    &press(1, Some(text), &UserContext::default()),
+                    state,
+                    registry,
+                    &AssetHashes::default(),
+                    config,
+                    client,
+                )
+                .await
+                .unwrap()
+            }
+        };
+
+        // Assert text outside the range, or not a number, keeps the user on the screen
+        for text in ["0", "1001", "ten", "NaN"] {
+            let (rendered, state) = submit(text, FrameState::default()).await;
+            assert_eq!(state.current(), "main", "{}", text);
+            assert_eq!(state.input, None);
+            assert_eq!(
+                rendered.invalid_input.as_deref(),
+                Some("Enter 1 to 1000 GOAT")
+            );
+        }
+
+        // Assert text that fits advances, and patterns must match the whole text
+        let (rendered, state) = submit("12.5", FrameState::default()).await;
+        assert_eq!(state.current(), "gift");
+        assert_eq!(rendered.invalid_input, None);
+        let (rendered, _) = submit("alice; drop", state.clone()).await;
+        assert_eq!(rendered.invalid_input.as_deref(), Some("Enter a username"));
+        let (_, state) = submit("alice", state).await;
+        assert_eq!(state.input.as_deref(), Some("alice"));
+
+        // Assert a pattern that isn't a regex stops the frames file from loading
+        assert!(FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            input = { placeholder = "Username", rules = { pattern = "[a-z", message = "?" } }
+            buttons = [{ label = "Send" }]
+            "#,
+        )
+        .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_gift_screen_emits_input_tag() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        // Open the Gift screen
+        let (rendered, _) = process_button(
+            DEFAULT_FRAME,
+            &press(3, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert the recipient input box is rendered
+        assert_eq!(rendered.input_text.as_deref(), Some("Recipient username"));
+        assert!(
+            FrameMeta::from_screen(rendered, "http://localhost/api/frame")
+                .meta_tags()
+                .contains(
+                    r#"<meta property="fc:frame:input:text" content="Recipient username" />"#
+                )
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_gift_requires_recipient() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(3, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Press Send Gift with only whitespace in the box
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, Some("  "), &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert the Gift screen is shown again with the hint
+        assert_eq!(state.current(), "gift");
+        assert_eq!(rendered.input_text.as_deref(), Some("Recipient required"));
+    }
+
+    #[actix_web::test]
+    async fn test_gift_recipient_reaches_confirmation() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(3, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Submit a recipient, round-tripping the state as a client would
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, Some("alice"), &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        let state = FrameState::decode(&state.encode());
+
+        // Assert the confirmation frame names the recipient
+        assert_eq!(state.current(), "gift_confirm");
+        assert_eq!(state.input.as_deref(), Some("alice"));
+        assert_eq!(rendered.buttons[0].label, "Gift @alice");
+        assert_eq!(
+            rendered.image_alt.as_deref(),
+            Some("Confirm your gift to @alice")
+        );
+        assert_eq!(rendered.input_text.as_deref(), Some("Amount in GOAT"));
+        assert_eq!(
+            rendered.buttons[0].action,
+            Some(ButtonAction::Tx {
+                target: "http://localhost/api/tx/gift".to_string(),
+                post_url: "http://localhost/".to_string(),
+            })
+        );
+
+        // Assert its image is the gift template, drawn with the recipient entered
+        assert_eq!(
+            rendered.image,
+            "http://localhost/images/gift_confirm.png?image=gift.png&input=alice&step=2&steps=3&recipient=alice&title=Gift+to+%40alice"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_long_recipient_is_trimmed_to_fit_label() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(3, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        let recipient = "é".repeat(40);
+        let (rendered, _) = process_button(
+            DEFAULT_FRAME,
+            &press(1, Some(&recipient), &UserContext::default()),
+            state,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert "Gift @{input}" is cut at a character boundary within the label limit
+        let label = &rendered.buttons[0].label;
+        assert!(label.starts_with("Gift @é"));
+        assert!(label.len() <= 32 && label.len() >= 31, "{}", label);
+        assert!(
+            FrameMeta::from_screen(rendered, "http://localhost/api/frame")
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_wide_only_clients_get_wide_images() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "gift.png"
+            aspect_ratio = "square"
+            wide_image = "main.png"
+            buttons = [{ label = "Go" }]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        let main = registry
+            .frame(DEFAULT_FRAME)
+            .unwrap()
+            .screen("main")
+            .unwrap();
+
+        let render = |client| {
+            let user = UserContext {
+                client,
+                ..UserContext::default()
+            };
+            crate::frame_logic::render_screen(
+                main,
+                &FrameState::default(),
+                &user,
+                &AssetHashes::default(),
+                &config,
+            )
+            .unwrap()
+        };
+
+        // Assert Farcaster clients get the square image as configured
+        let square = render(ClientKind::Warpcast);
+        assert_eq!(square.image, "http://localhost/assets/gift.png");
+        assert_eq!(square.image_aspect_ratio, Some(AspectRatio::Square));
+
+        // Assert Open Frames clients get the wide variant instead
+        let wide = render(ClientKind::OpenFrames);
+        assert_eq!(wide.image, "http://localhost/assets/main.png");
+        assert_eq!(wide.image_aspect_ratio, Some(AspectRatio::Wide));
+    }
+
+    #[actix_web::test]
+    async fn test_square_screen_emits_aspect_ratio_tag() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        // Open the Buy & Boost screen, which has square art
+        let (rendered, _) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert the 1:1 ratio is passed on to the client
+        assert_eq!(rendered.image_aspect_ratio, Some(AspectRatio::Square));
+        assert!(
+            FrameMeta::from_screen(rendered, "http://localhost/api/frame")
+                .meta_tags()
+                .contains(r#"<meta property="fc:frame:image:aspect_ratio" content="1:1" />"#)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_default_screen_omits_aspect_ratio_tag() {
+        // Mock configuration with a test domain
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        // Open the More screen, which keeps the default wide image
+        let (rendered, _) = process_button(
+            DEFAULT_FRAME,
+            &press(4, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert no ratio tag is emitted so clients use their default
+        assert_eq!(rendered.image_aspect_ratio, None);
+        assert!(
+            !FrameMeta::from_screen(rendered, "http://localhost/api/frame")
+                .meta_tags()
+                .contains("fc:frame:image:aspect_ratio")
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_configured_default_aspect_ratio() {
+        // Mock configuration that makes unmarked screens square
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            default_aspect_ratio: Some(AspectRatio::Square),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        // Open the More screen, which has no ratio of its own
+        let (more, _) = process_button(
+            DEFAULT_FRAME,
+            &press(4, None, &UserContext::default()),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert screens without a ratio fall back to the configured one
+        assert_eq!(more.image_aspect_ratio, Some(AspectRatio::Square));
+    }
+
+    #[test]
+    fn test_aspect_ratio_accepts_names_and_ratios() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            aspect_ratio = "1:1"
+            buttons = [{ label = "Go", target = "wide" }]
+
+            [frames.store.screens.wide]
+            image = "more.png"
+            aspect_ratio = "wide"
+            buttons = [{ label = "Back", target = "back" }]
+            "#,
+        )
+        .unwrap();
+        let frame = registry.frame(DEFAULT_FRAME).unwrap();
+
+        // Assert both spellings parse to the same ratios
+        assert_eq!(
+            frame.screen("main").unwrap().aspect_ratio,
+            Some(AspectRatio::Square)
+        );
+        assert_eq!(
+            frame.screen("wide").unwrap().aspect_ratio,
+            Some(AspectRatio::Wide)
+        );
+    }
+
+    // Opens More from Main as the given user and returns the labels on both screens
+    async fn main_and_more_labels(user: &UserContext) -> (Vec<String>, Vec<String>) {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            known_fids: vec![3],
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+
+        let main = crate::frame_logic::render_screen(
+            registry
+                .frame(DEFAULT_FRAME)
+                .unwrap()
+                .screen("main")
+                .unwrap(),
+            &FrameState::default(),
+            user,
+            &AssetHashes::default(),
+            &config,
+        )
+        .unwrap();
+        let (more, _) = process_button(
+            DEFAULT_FRAME,
+            &press(4, None, user),
+            FrameState::default(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        let labels = |buttons: Vec<Button>| buttons.into_iter().map(|b| b.label).collect();
+        (labels(main.buttons), labels(more.buttons))
+    }
+
+    #[actix_web::test]
+    async fn test_known_fid_skips_top_up() {
+        let config = Config {
+            known_fids: vec![3],
+            ..Config::default()
+        };
+        let user = UserContext::lookup(Some(3), &config);
+
+        let (main, more) = main_and_more_labels(&user).await;
+
+        // Assert Main is untouched and More drops Top-up
+        assert_eq!(main, ["Buy & Boost", "Add Liquidity", "Gift", "More"]);
+        assert_eq!(more, ["Reward", "Bid", "Back"]);
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_and_missing_fid_see_every_button() {
+        let config = Config {
+            known_fids: vec![3],
+            ..Config::default()
+        };
+
+        for user in [
+            UserContext::lookup(Some(4), &config),
+            UserContext::lookup(None, &config),
+            UserContext::default(),
+        ] {
+            let (main, more) = main_and_more_labels(&user).await;
+
+            // Assert both screens match the frames file exactly
+            assert_eq!(main, ["Buy & Boost", "Add Liquidity", "Gift", "More"]);
+            assert_eq!(more, ["Reward", "Bid", "Top-up", "Back"]);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_known_fid_button_indexes_follow_visible_buttons() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            known_fids: vec![3],
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        let user = UserContext::lookup(Some(3), &config);
+        let mut more = FrameState::default();
+        more.push("more");
+
+        // Back is the 3rd button this user was shown, and there is no 4th
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(3, None, &user),
+            more.clone(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        let result = process_button(
+            DEFAULT_FRAME,
+            &press(4, None, &user),
+            more,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await;
+
+        // Assert Back returns to Main and the hidden slot is rejected
+        assert_eq!(state.current(), "main");
+        assert!(matches!(
+            result,
+            Err(AppError::BadRequest(ref message))
+                if message == "Invalid button index: 4 (expected 1 to 3)"
+        ));
+    }
+
+    #[test]
+    fn test_main_screen_buttons_cannot_be_hidden() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Top-up", hide_for_known = true }, { label = "More" }]
+            "#,
+        )
+        .unwrap();
+
+        // Assert the registry refuses a main screen that varies by user
+        assert!(matches!(
+            registry.validate(Path::new("assets")),
+            Err(RegistryError::InvalidScreen(ref name, _)) if name == "store/main"
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_tx_button_advances_once_sent() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let registry = test_registry();
+        let client = reqwest::Client::new();
+        let mut on_buy_boost = FrameState::default();
+        on_buy_boost.push("buy_boost");
+
+        // A post for Confirm without a transaction leaves the user where they were
+        let (_, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            on_buy_boost.clone(),
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+        assert_eq!(state.current(), "buy_boost");
+
+        // The follow-up post carries the hash of the sent transaction
+        let hash = format!("0x{}", "ab".repeat(32));
+        on_buy_boost.transaction_id = Some(hash.clone());
+        let (rendered, state) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            on_buy_boost,
+            &registry,
+            &AssetHashes::default(),
+            &config,
+            &client,
+        )
+        .await
+        .unwrap();
+
+        // Assert the user lands on the sent screen with a link to their transaction
+        assert_eq!(state.current(), "buy_boost_sent");
+        assert_eq!(
+            rendered.buttons[0].action,
+            Some(ButtonAction::Link {
+                url: format!("https://explorer.goat.network/tx/{}", hash),
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_process_button_versions_image_urls() {
+        let config = Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        };
+        let assets = AssetHashes::scan(Path::new("assets")).unwrap();
+
+        let (rendered, _) = process_button(
+            DEFAULT_FRAME,
+            &press(1, None, &UserContext::default()),
+            FrameState::default(),
+            &test_registry(),
+            &assets,
+            &config,
+            &reqwest::Client::new(),
+        )
+        .await
+        .unwrap();
+
+        // Assert the image URL changes whenever the file does, so clients refetch it,
+        // including when it's the base of a generated image
+        let version = assets.version("buy_boost.png").unwrap();
+        assert_eq!(
+            assets.url("http://localhost", "buy_boost.png"),
+            format!("http://localhost/assets/buy_boost.png?v={}", version)
+        );
+        assert_eq!(
+            rendered.image,
+            format!(
+                "http://localhost/images/buy_boost.png?image=buy_boost.png&v={}&step=1&steps=2",
+                version
+            )
+        );
+    }
 }
@@ -0,0 +1,223 @@
+#[cfg(test)]
+mod gif_tests {
+    use crate::errors::ImageError;
+    use crate::images::font::Fonts;
+    use crate::images::gif::encode_gif;
+    use crate::images::svg::{animation, rasterize_frame, Animation};
+    use crate::images::{AssetImages, ImageTemplates, LiveData};
+    use image::{Rgb, RgbImage};
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    // What a GIF decodes to: its size, each frame's delay and pixels, and whether it loops
+    struct Decoded {
+        width: u32,
+        height: u32,
+        loops: bool,
+        frames: Vec<(u16, Vec<Rgb<u8>>)>,
+    }
+
+    fn decode(gif: &[u8]) -> Decoded {
+        assert_eq!(&gif[..6], b"GIF89a");
+        let le = |at: usize| u16::from_le_bytes([gif[at], gif[at + 1]]);
+        let (width, height) = (le(6) as u32, le(8) as u32);
+        assert_eq!(gif[10], 0xF7);
+        let palette: Vec<Rgb<u8>> = gif[13..13 + 768]
+            .chunks(3)
+            .map(|c| Rgb([c[0], c[1], c[2]]))
+            .collect();
+        let mut at = 13 + 768;
+        let sub_blocks = |at: &mut usize| {
+            let mut data = Vec::new();
+            while gif[*at] != 0 {
+                let len = gif[*at] as usize;
+                data.extend_from_slice(&gif[*at + 1..*at + 1 + len]);
+                *at += len + 1;
+            }
+            *at += 1;
+            data
+        };
+        let (mut loops, mut delay, mut frames) = (false, 0, Vec::new());
+        loop {
+            match gif[at] {
+                0x21 => {
+                    let label = gif[at + 1];
+                    at += 2;
+                    let data = sub_blocks(&mut at);
+                    match label {
+                        0xFF => loops = data.starts_with(b"NETSCAPE2.0"),
+                        0xF9 => delay = u16::from_le_bytes([data[1], data[2]]),
+                        _ => {}
+                    }
+                }
+                0x2C => {
+                    assert_eq!((le(at + 5), le(at + 7)), (width as u16, height as u16));
+                    assert_eq!(gif[at + 10], 8);
+                    at += 11;
+                    let indices = lzw_decode(&sub_blocks(&mut at));
+                    let pixels = indices.iter().map(|i| palette[*i as usize]).collect();
+                    frames.push((delay, pixels));
+                }
+                0x3B => break,
+                other => panic!("unexpected block {:#x}", other),
+            }
+        }
+        Decoded {
+            width,
+            height,
+            loops,
+            frames,
+        }
+    }
+
+    // A plain GIF LZW decoder, written apart from the encoder to check it
+    fn lzw_decode(data: &[u8]) -> Vec<u8> {
+        let (clear, end) = (256usize, 257usize);
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            *table = (0..=255u8).map(|b| vec![b]).collect();
+            table.extend([Vec::new(), Vec::new()]);
+        };
+        reset(&mut table);
+        let (mut bits, mut pos, mut out) = (9usize, 0usize, Vec::new());
+        let mut previous: Option<Vec<u8>> = None;
+        loop {
+            let mut code = 0;
+            for i in 0..bits {
+                let bit = (data[(pos + i) / 8] >> ((pos + i) % 8)) & 1;
+                code |= (bit as usize) << i;
+            }
+            pos += bits;
+            if code == clear {
+                reset(&mut table);
+                bits = 9;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = match (&previous, table.get(code)) {
+                (_, Some(entry)) => entry.clone(),
+                (Some(previous), None) => [previous.clone(), vec![previous[0]]].concat(),
+                (None, None) => panic!("code {} before any data", code),
+            };
+            out.extend_from_slice(&entry);
+            if let Some(previous) = previous {
+                table.push([previous, vec![entry[0]]].concat());
+                if table.len() == 1 << bits && bits < 12 {
+                    bits += 1;
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn test_encode_gif_round_trips() {
+        // Two frames with a few colors, and a noisy one big enough to fill the code table
+        let mut noisy = RgbImage::new(160, 120);
+        for (x, y, pixel) in noisy.enumerate_pixels_mut() {
+            let n = (x * 7919 + y * 104729) % 200;
+            *pixel = Rgb([n as u8, (n * 3) as u8, 255 - n as u8]);
+        }
+        let plain = RgbImage::from_fn(160, 120, |x, _| {
+            if x < 80 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        });
+        let gif = encode_gif(&[plain.clone(), noisy.clone()], 250);
+
+        // Assert it loops, shows each frame for the delay, and keeps every color exactly
+        let decoded = decode(&gif);
+        assert_eq!((decoded.width, decoded.height), (160, 120));
+        assert!(decoded.loops);
+        assert_eq!(decoded.frames.len(), 2);
+        for ((delay, pixels), frame) in decoded.frames.iter().zip([&plain, &noisy]) {
+            assert_eq!(*delay, 25);
+            assert_eq!(pixels, &frame.pixels().copied().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_encode_gif_maps_many_colors_to_the_cube() {
+        let gradient = RgbImage::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, 128]));
+        let decoded = decode(&encode_gif(std::slice::from_ref(&gradient), 0));
+
+        // Assert colors land on the nearest of the fixed palette's, and the delay is
+        // the shortest clients honor
+        let (delay, pixels) = &decoded.frames[0];
+        assert_eq!(*delay, 2);
+        for (pixel, original) in pixels.iter().zip(gradient.pixels()) {
+            for channel in 0..3 {
+                let off = (pixel.0[channel] as i32 - original.0[channel] as i32).abs();
+                assert!(off <= 26, "{:?} for {:?}", pixel, original);
+            }
+        }
+    }
+
+    #[test]
+    fn test_svg_frames() {
+        let svg = r##"<svg width="4" height="2" frames="3" frame-delay="200">
+            <rect width="4" height="2" fill="#000"/>
+            <rect width="2" height="2" fill="#f00" frame="1"/>
+            <rect x="2" width="2" height="2" fill="#00f" frame="1-2"/>
+        </svg>"##;
+        let draw = |frame: usize| {
+            rasterize_frame(
+                svg,
+                &AssetImages::new(Path::new("assets")),
+                &Fonts::default(),
+                &LiveData::default(),
+                frame,
+            )
+            .unwrap()
+        };
+
+        // Assert each element is drawn only in its frames
+        assert_eq!(
+            animation(svg).unwrap(),
+            Animation {
+                frames: 3,
+                delay_ms: 200
+            }
+        );
+        let colors = |img: RgbImage| (*img.get_pixel(0, 0), *img.get_pixel(3, 0));
+        assert_eq!(colors(draw(0)), (Rgb([0, 0, 0]), Rgb([0, 0, 0])));
+        assert_eq!(colors(draw(1)), (Rgb([255, 0, 0]), Rgb([0, 0, 255])));
+        assert_eq!(colors(draw(2)), (Rgb([0, 0, 0]), Rgb([0, 0, 255])));
+
+        // Assert frames outside the animation, or too many of them, are refused
+        for bad in [
+            r#"<svg width="4" height="2" frames="3"><rect width="1" height="1" frame="3"/></svg>"#,
+            r#"<svg width="4" height="2" frames="31"></svg>"#,
+            r#"<svg width="4" height="2" frames="1.5"></svg>"#,
+        ] {
+            let err = rasterize_frame(
+                bad,
+                &AssetImages::new(Path::new("assets")),
+                &Fonts::default(),
+                &LiveData::default(),
+                0,
+            )
+            .unwrap_err();
+            assert!(matches!(err, ImageError::Svg(_)), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_shipped_auction_template_animates() {
+        let templates = ImageTemplates::load(Path::new("templates"), Path::new("assets")).unwrap();
+        let params = HashMap::from([("bid".to_string(), "1200 GOAT".to_string())]);
+
+        // Assert the auction image is a GIF of its four frames
+        let gif = templates
+            .render_gif("auction", &params, &LiveData::default())
+            .unwrap();
+        let decoded = decode(&gif);
+        assert_eq!(decoded.frames.len(), 4);
+        assert_ne!(decoded.frames[0].1, decoded.frames[2].1);
+    }
+}
@@ -0,0 +1,239 @@
+#[cfg(test)]
+mod gifts_tests {
+    use crate::frame_logic::state::FrameState;
+    use crate::gifts::{normalize_username, Gift, GiftStore, SavedGift, MAX_GIFTS};
+    use crate::tests::spawn_mock_server;
+    use crate::{handle_gift_tx, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use std::collections::HashMap;
+
+    const WALLET: &str = "0x1111111111111111111111111111111111111111";
+    const ALICE_WALLET: &str = "0x00000000000000000000000000000000000000aa";
+
+    // A hub that knows @alice, fid 7, with a verified wallet and @bob, fid 8, without
+    // one, and an RPC node holding 10 GOAT for every wallet, on the same server. The
+    // hub vouches for messages that are hex encoded JSON of their fid, state and text.
+    async fn hub_and_rpc() -> String {
+        spawn_mock_server(|cfg| {
+            cfg.route(
+                "/v1/validateMessage",
+                web::post().to(|body: web::Bytes| async move {
+                    let signed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                    let base64 =
+                        |field: &str| BASE64_STANDARD.encode(signed[field].as_str().unwrap());
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "valid": true,
+                        "message": {
+                            "data": {
+                                "fid": signed["fid"],
+                                "timestamp": 110000000,
+                                "frameActionBody": {
+                                    "url": "aHR0cDovL2xvY2FsaG9zdA==",
+                                    "buttonIndex": 1,
+                                    "state": base64("state"),
+                                    "inputText": base64("inputText")
+                                }
+                            }
+                        }
+                    }))
+                }),
+            )
+            .route(
+                "/v1/userNameProofByName",
+                web::get().to(|query: web::Query<HashMap<String, String>>| async move {
+                    match query.get("name").map(String::as_str) {
+                        Some("alice") => HttpResponse::Ok().json(serde_json::json!({ "fid": 7 })),
+                        Some("bob") => HttpResponse::Ok().json(serde_json::json!({ "fid": 8 })),
+                        _ => HttpResponse::NotFound().finish(),
+                    }
+                }),
+            )
+            .route(
+                "/v1/verificationsByFid",
+                web::get().to(|query: web::Query<HashMap<String, String>>| async move {
+                    let messages = match query.get("fid").map(String::as_str) {
+                        Some("7") => serde_json::json!([{
+                            "data": {
+                                "verificationAddAddressBody": {
+                                    "address": ALICE_WALLET,
+                                    "protocol": "PROTOCOL_ETHEREUM"
+                                }
+                            }
+                        }]),
+                        _ => serde_json::json!([]),
+                    };
+                    HttpResponse::Ok().json(serde_json::json!({ "messages": messages }))
+                }),
+            )
+            .route(
+                "/",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    assert_eq!(body["method"], "eth_getBalance");
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": "0x8ac7230489e80000"
+                    }))
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_gift_transaction_pays_the_recipient() {
+        let server = hub_and_rpc().await;
+        let path = std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()));
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            hub_url: server.clone(),
+            rpc_url: Some(server),
+            ..Config::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(GiftStore::open(&path).unwrap()))
+                .route("/api/tx/gift", web::post().to(handle_gift_tx)),
+        )
+        .await;
+        // The recipient is the input of the screen before, carried in the state. A
+        // signed post carries the same in its message, as fid 9.
+        let post = |recipient: &str, amount: &str, signed: bool| {
+            let state = FrameState {
+                input: Some(recipient.to_string()),
+                ..FrameState::default()
+            }
+            .encode();
+            let mut body = serde_json::json!({
+                "untrustedData": {
+                    "buttonIndex": 1,
+                    "fid": 42,
+                    "address": WALLET,
+                    "inputText": amount,
+                    "state": state
+                }
+            });
+            if signed {
+                let message = serde_json::json!({ "fid": 9, "state": state, "inputText": amount });
+                body["trustedData"] =
+                    serde_json::json!({ "messageBytes": hex::encode(message.to_string()) });
+            }
+            test::TestRequest::post()
+                .uri("/api/tx/gift")
+                .set_json(body)
+                .to_request()
+        };
+        let gift = |recipient: &str, amount: &str| post(recipient, amount, true);
+        let saved = || -> Vec<Gift> {
+            std::fs::read_to_string(&path)
+                .unwrap_or_default()
+                .lines()
+                .map(|line| serde_json::from_str::<SavedGift>(line).unwrap().gift)
+                .collect()
+        };
+
+        // Assert the GOAT goes straight to the recipient's verified wallet
+        let resp = test::call_service(&app, gift("@Alice", "2.5")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["method"], "eth_sendTransaction");
+        assert_eq!(body["params"]["to"], ALICE_WALLET);
+        assert_eq!(body["params"]["value"], "2500000000000000000");
+        assert_eq!(body["params"]["data"], "0x");
+
+        // Assert the gift was saved from the fid the hub vouched for, and is there for
+        // the next start
+        let gifts = saved();
+        assert_eq!(gifts.len(), 1);
+        assert_eq!(gifts[0].from_fid, 9);
+        assert_eq!(gifts[0].to_fid, 7);
+        assert_eq!(gifts[0].to_username, "alice");
+        assert_eq!(gifts[0].to_address, ALICE_WALLET);
+        assert_eq!(gifts[0].amount, "2500000000000000000");
+        assert!(GiftStore::open(&path).is_ok());
+
+        // Assert an unsigned post is still answered, but its claimed fid isn't recorded
+        let resp = test::call_service(&app, post("alice", "1", false)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(saved().len(), 1);
+
+        // Assert unknown users, ones without a wallet, no amount or more than the
+        // wallet holds are refused with a message, and not recorded
+        for (recipient, amount) in [
+            ("nobody", "1"),
+            ("bob", "1"),
+            ("alice", ""),
+            ("alice", "11"),
+        ] {
+            let resp = test::call_service(&app, gift(recipient, amount)).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", recipient);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(body["message"].is_string());
+        }
+        assert_eq!(saved().len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_saved_gifts_are_capped() {
+        let path = std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()));
+        let gift = |to_fid: usize| Gift {
+            from_fid: 9,
+            to_fid: to_fid as u64,
+            to_username: "alice".to_string(),
+            to_address: ALICE_WALLET.to_string(),
+            amount: "1".to_string(),
+            created_at: 0,
+        };
+        let lines: String = (0..2 * MAX_GIFTS)
+            .map(|n| {
+                let saved = SavedGift {
+                    id: n.to_string(),
+                    gift: gift(n),
+                };
+                serde_json::to_string(&saved).unwrap() + "\n"
+            })
+            .collect();
+        std::fs::write(&path, lines).unwrap();
+        let saved = || -> Vec<SavedGift> {
+            std::fs::read_to_string(&path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        };
+
+        // Assert a file past the cap is cut down to its latest gifts on opening
+        let store = GiftStore::open(&path).unwrap();
+        let kept = saved();
+        assert_eq!(kept.len(), MAX_GIFTS);
+        assert_eq!(kept[0].id, MAX_GIFTS.to_string());
+        assert_eq!(kept[MAX_GIFTS - 1].gift, gift(2 * MAX_GIFTS - 1));
+
+        // Assert new gifts are added to the end rather than rewriting what's there
+        let id = store.record(gift(0));
+        let kept = saved();
+        assert_eq!(kept.len(), MAX_GIFTS + 1);
+        assert_eq!(kept[MAX_GIFTS].id, id);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_normalize_username() {
+        // Assert usernames are taken as typed, with or without the @
+        assert_eq!(normalize_username(" @Alice "), Some("alice".to_string()));
+        assert_eq!(
+            normalize_username("vitalik.eth"),
+            Some("vitalik.eth".to_string())
+        );
+
+        // Assert what can't be a username isn't looked up
+        assert_eq!(normalize_username(""), None);
+        assert_eq!(normalize_username("@"), None);
+        assert_eq!(normalize_username("alice bob"), None);
+        assert_eq!(normalize_username("../v1/info"), None);
+    }
+}
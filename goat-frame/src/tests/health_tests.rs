@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod health_tests {
+    use crate::health::{health, ready, Readiness};
+    use crate::tests::spawn_mock_server;
+    use crate::Config;
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn test_health_is_ok() {
+        let app = test::init_service(App::new().route("/health", web::get().to(health))).await;
+
+        let req = test::TestRequest::get().uri("/health").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert liveness doesn't depend on anything
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "ok");
+    }
+
+    #[actix_web::test]
+    async fn test_ready_flips_once_hub_responds() {
+        // Mock hub that fails its info endpoint until switched on
+        let hub_up = Arc::new(AtomicBool::new(false));
+        let hub_flag = hub_up.clone();
+        let hub_url = spawn_mock_server(move |cfg: &mut web::ServiceConfig| {
+            let hub_flag = hub_flag.clone();
+            cfg.route(
+                "/v1/info",
+                web::get().to(move || {
+                    let up = hub_flag.load(Ordering::SeqCst);
+                    async move {
+                        if up {
+                            HttpResponse::Ok().json(serde_json::json!({ "version": "test" }))
+                        } else {
+                            HttpResponse::InternalServerError().finish()
+                        }
+                    }
+                }),
+            );
+        })
+        .await;
+
+        // Validation is enabled, so readiness depends on the hub
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            hub_url,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(Readiness::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/ready", web::get().to(ready)),
+        )
+        .await;
+
+        // Assert the server isn't ready while the hub is failing
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Assert readiness flips once the hub responds
+        hub_up.store(true, Ordering::SeqCst);
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Assert the cached contact keeps us ready even if the hub blips
+        hub_up.store(false, Ordering::SeqCst);
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_ready_without_config_is_unavailable() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Readiness::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/ready", web::get().to(ready)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/ready").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert a missing Config means not ready
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod i18n_tests {
+    use crate::errors::I18nError;
+    use crate::i18n::{Catalog, Translations};
+    use crate::tests::test_registry;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    fn translations(default_locale: Option<&str>) -> Translations {
+        let catalog = |label: &str| {
+            Arc::new(Catalog::from([(
+                "Buy & Boost".to_string(),
+                label.to_string(),
+            )]))
+        };
+        Translations::new(
+            HashMap::from([
+                ("es".to_string(), catalog("Comprar y Boost")),
+                ("pt_BR".to_string(), catalog("Comprar e Boost")),
+            ]),
+            default_locale,
+        )
+    }
+
+    fn label(catalog: Option<Arc<Catalog>>) -> Option<String> {
+        catalog.and_then(|catalog| catalog.get("Buy & Boost").cloned())
+    }
+
+    #[test]
+    fn test_locale_falls_back_to_language_then_default() {
+        let translations = translations(None);
+
+        // Assert exact, regional and differently written locales find their catalog
+        assert_eq!(
+            label(translations.catalog(Some("pt-BR"))).as_deref(),
+            Some("Comprar e Boost")
+        );
+        assert_eq!(
+            label(translations.catalog(Some("es-MX"))).as_deref(),
+            Some("Comprar y Boost")
+        );
+        assert_eq!(
+            label(translations.catalog(Some("ES"))).as_deref(),
+            Some("Comprar y Boost")
+        );
+
+        // Assert an unknown locale shows frames.toml's text unless there's a default
+        assert!(translations.catalog(Some("fr")).is_none());
+        assert!(translations.catalog(None).is_none());
+        let translations = self::translations(Some("es"));
+        assert_eq!(
+            label(translations.catalog(Some("fr"))).as_deref(),
+            Some("Comprar y Boost")
+        );
+    }
+
+    #[test]
+    fn test_missing_directory_has_no_translations() {
+        let dir = std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()));
+        let translations = Translations::load(&dir, Some("es")).unwrap();
+
+        // Assert everyone sees the text as written
+        assert_eq!(translations.locales(), 0);
+        assert!(translations.catalog(Some("es")).is_none());
+    }
+
+    #[test]
+    fn test_unparseable_file_fails_to_load() {
+        let dir = std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("de.toml"), "\"Buy & Boost\" = ").unwrap();
+
+        // Assert a broken file stops startup rather than silently showing English
+        let result = Translations::load(&dir, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(result, Err(I18nError::Parse(..))));
+    }
+
+    #[test]
+    fn test_shipped_locales_translate_every_label() {
+        let translations = Translations::load(Path::new("locales"), None).unwrap();
+        let catalog = translations.catalog(Some("es")).unwrap();
+
+        // Assert no store button is left in English, and none is too long to show
+        let registry = test_registry();
+        let labels = registry.frames["store"]
+            .screens
+            .values()
+            .flat_map(|screen| &screen.buttons)
+            .map(|button| button.label.as_str());
+        for label in labels {
+            let translated = catalog.get(label);
+            assert!(translated.is_some(), "{:?} has no translation", label);
+            assert!(translated.unwrap().len() <= 32, "{:?} is too long", label);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+#[cfg(test)]
+mod idempotency_tests {
+    use crate::analytics::{AnalyticsSink, ClickEvent, RecordFuture};
+    use crate::assets::AssetHashes;
+    use crate::error_image::ErrorImages;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache, Replay, Reserved};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::ReplayGuard;
+    use crate::tests::test_registry;
+    use crate::{handle_frame, Config};
+    use actix_web::{test, web, App};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    // Counts how many clicks actually got processed
+    #[derive(Default)]
+    struct CountingSink(AtomicUsize);
+
+    impl AnalyticsSink for CountingSink {
+        fn record(&self, _event: ClickEvent) -> RecordFuture<'_> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async {})
+        }
+    }
+
+    fn gift_click(key: &str) -> actix_web::test::TestRequest {
+        test::TestRequest::post()
+            .uri("/api/frame")
+            .insert_header(("Idempotency-Key", key.to_string()))
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 3
+                }
+            }))
+    }
+
+    #[actix_web::test]
+    async fn test_repeated_key_replays_response() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+        let sink = Arc::new(CountingSink::default());
+
+        // Create a mock application with the same routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(web::Data::from(sink.clone() as Arc<dyn AnalyticsSink>))
+                .app_data(web::Data::new(IdempotencyCache::new(
+                    config.idempotency_ttl_secs,
+                )))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let first = test::call_and_read_body(&app, gift_click("retry-1").to_request()).await;
+        let second = test::call_and_read_body(&app, gift_click("retry-1").to_request()).await;
+
+        // Assert the retry got the same frame without processing the click again
+        assert_eq!(first, second);
+        assert_eq!(sink.0.load(Ordering::SeqCst), 1);
+
+        test::call_service(&app, gift_click("retry-2").to_request()).await;
+
+        // Assert a new key is processed as a new click
+        assert_eq!(sink.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_repeated_transaction_is_confirmed_once() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+        let sink = Arc::new(CountingSink::default());
+
+        // Create a mock application with the same routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(web::Data::from(sink.clone() as Arc<dyn AnalyticsSink>))
+                .app_data(web::Data::new(IdempotencyCache::new(
+                    config.idempotency_ttl_secs,
+                )))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        // Posts after a Buy & Boost transaction, which carry no idempotency key
+        let confirm = |transaction_id: &str| {
+            test::TestRequest::post()
+                .uri("/api/frame")
+                .set_json(serde_json::json!({
+                    "untrustedData": {
+                        "buttonIndex": 1,
+                        "state": r#"{"stack":["main","buy_boost"]}"#,
+                        "transactionId": transaction_id
+                    }
+                }))
+                .to_request()
+        };
+        let hash = format!("0x{}", "ab".repeat(32));
+        let other = format!("0x{}", "cd".repeat(32));
+
+        let first = test::call_and_read_body(&app, confirm(&hash)).await;
+        let second = test::call_and_read_body(&app, confirm(&hash)).await;
+
+        // Assert the double-submitted confirmation got the same page, handled once
+        assert_eq!(first, second);
+        assert!(String::from_utf8(first.to_vec()).unwrap().contains(&hash));
+        assert_eq!(sink.0.load(Ordering::SeqCst), 1);
+
+        test::call_service(&app, confirm(&other)).await;
+
+        // Assert a different transaction is confirmed on its own
+        assert_eq!(sink.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_retry_waits_for_the_first_answer() {
+        let cache = IdempotencyCache::new(600);
+        let Reserved::Ours(first) = cache.reserve(Some("key".to_string())).await else {
+            panic!("the first request should have the key");
+        };
+
+        // Assert a retry arriving while the first is handled gets its answer once it's
+        // ready, rather than being handled alongside it
+        let (retry, ()) = tokio::join!(cache.reserve(Some("key".to_string())), async {
+            actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+            first.complete(Replay::Redirect("https://example.com".to_string()));
+        });
+        assert!(matches!(
+            retry,
+            Reserved::Replay(Replay::Redirect(location)) if location == "https://example.com"
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_unanswered_reservation_is_let_go() {
+        let cache = IdempotencyCache::new(600);
+
+        // Assert a request that ends without an answer, e.g. refused, leaves the key
+        // for a retry to be handled afresh
+        let reserved = cache.reserve(Some("key".to_string())).await;
+        assert!(matches!(reserved, Reserved::Ours(_)));
+        drop(reserved);
+        assert!(matches!(
+            cache.reserve(Some("key".to_string())).await,
+            Reserved::Ours(_)
+        ));
+        assert!(cache.get("key").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_zero_ttl_disables_cache() {
+        let cache = IdempotencyCache::new(0);
+        cache.insert("key".to_string(), Replay::Page("{}".to_string()));
+
+        // Assert nothing is replayed when the TTL is 0
+        assert!(cache.get("key").is_none());
+    }
+}
@@ -0,0 +1,666 @@
+#[cfg(test)]
+mod images_tests {
+    use crate::auction::Auction;
+    use crate::bridge::Bridge;
+    use crate::config::Config;
+    use crate::errors::ImageError;
+    use crate::images::cache::RenderCache;
+    use crate::images::font::Fonts;
+    use crate::images::svg::{chart_ranges, color_from, rasterize};
+    use crate::images::{self, fill, placeholders, AssetImages, ImageTemplates, LiveData};
+    use crate::leaderboard::Leaderboard;
+    use crate::metrics::Metrics;
+    use crate::moxie::MoxiePrice;
+    use crate::price::{PriceHistory, PriceRange};
+    use crate::profiles::ProfileCache;
+    use crate::receipts::Receipts;
+    use crate::tests::spawn_mock_server;
+    use actix_web::{test, web, App, HttpResponse};
+    use image::Rgb;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    const TEMPLATE: &str = r##"
+        <svg width="100" height="50">
+          <g>
+            <rect x="10" y="10" width="20" height="20" fill="#f00"/>
+            <circle cx="70" cy="25" r="10" fill="blue"/>
+            <text x="0" y="48" font-size="8">{{amount}}</text>
+          </g>
+        </svg>
+    "##;
+
+    fn assets() -> AssetImages {
+        AssetImages::new(Path::new("assets"))
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_fill_escapes_values() {
+        // Assert values are escaped, missing ones left empty and other braces kept
+        assert_eq!(
+            fill(
+                "<text>{{amount}} {{unit}}{{missing}} {{not a name}} {amount}</text>",
+                &params(&[("amount", "<1&2>"), ("unit", "GOAT")])
+            ),
+            "<text>&lt;1&amp;2&gt; GOAT {{not a name}} {amount}</text>"
+        );
+
+        // Assert a template's placeholders are listed once each
+        assert_eq!(
+            placeholders("{{{{a}} {{b}} {{a}} {c} .x{fill:red}"),
+            ["a", "b"].map(String::from).into()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_rasterize_draws_shapes_and_text() {
+        let img = rasterize(
+            &fill(TEMPLATE, &params(&[("amount", "8")])),
+            &assets(),
+            &Fonts::default(),
+            &LiveData::default(),
+        )
+        .unwrap();
+
+        // Assert the size and each shape land where the template puts them
+        assert_eq!(img.dimensions(), (100, 50));
+        assert_eq!(*img.get_pixel(20, 20), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(70, 25), Rgb([0, 0, 255]));
+        assert_eq!(*img.get_pixel(5, 5), Rgb([255, 255, 255]));
+
+        // Assert text is drawn above its baseline in black
+        let drawn = (0..8)
+            .flat_map(|x| (40..48).map(move |y| (x, y)))
+            .any(|(x, y)| *img.get_pixel(x, y) == Rgb([0, 0, 0]));
+        assert!(drawn);
+    }
+
+    #[actix_web::test]
+    async fn test_rasterize_rejects_what_it_cant_draw() {
+        let cases = [
+            "<rect width='1' height='1'/>",
+            r#"<svg width="0" height="10"></svg>"#,
+            r#"<svg width="10" height="10"><rect width="5" height="5" fill="url(#g)"/></svg>"#,
+            r#"<svg width="10" height="10"><circle r="wide"/></svg>"#,
+            r#"<svg width="10" height="10"><image href="../Cargo.toml"/></svg>"#,
+        ];
+        for svg in cases {
+            // Assert each problem is reported rather than drawn wrongly
+            assert!(
+                matches!(
+                    rasterize(svg, &assets(), &Fonts::default(), &LiveData::default()),
+                    Err(ImageError::Svg(_))
+                ),
+                "{}",
+                svg
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_rasterize_draws_on_assets() {
+        let svg = r##"
+            <svg width="100" height="100">
+              <image href="{{image}}" width="100" height="100"/>
+              <rect y="90" width="100" height="10" fill="#181818"/>
+            </svg>
+        "##;
+        let gift = image::open("assets/gift.png").unwrap().to_rgb8();
+        let gift = image::imageops::resize(&gift, 100, 100, image::imageops::FilterType::Triangle);
+
+        // Assert the asset is scaled into place, with the banner drawn over it
+        let img = rasterize(
+            &fill(svg, &params(&[("image", "gift.png")])),
+            &assets(),
+            &Fonts::default(),
+            &LiveData::default(),
+        )
+        .unwrap();
+        assert_eq!(img.get_pixel(50, 50), gift.get_pixel(50, 50));
+        assert_eq!(*img.get_pixel(50, 95), Rgb([24, 24, 24]));
+
+        // Assert an href left empty draws nothing rather than failing
+        let img = rasterize(
+            &fill(svg, &HashMap::new()),
+            &assets(),
+            &Fonts::default(),
+            &LiveData::default(),
+        )
+        .unwrap();
+        assert_eq!(*img.get_pixel(50, 50), Rgb([255, 255, 255]));
+    }
+
+    #[actix_web::test]
+    async fn test_rasterize_draws_avatar_in_a_circle() {
+        let svg =
+            r#"<svg width="40" height="40"><image href="avatar" width="40" height="40"/></svg>"#;
+        let red = image::RgbImage::from_pixel(8, 8, Rgb([255, 0, 0]));
+
+        // Assert the pfp fills the circle but not the corners of its box
+        let img = rasterize(
+            svg,
+            &assets(),
+            &Fonts::default(),
+            &LiveData {
+                avatar: Some(&red),
+                ..LiveData::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(*img.get_pixel(20, 20), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(1, 1), Rgb([255, 255, 255]));
+
+        // Assert someone without a pfp gets a gray circle
+        let img = rasterize(svg, &assets(), &Fonts::default(), &LiveData::default()).unwrap();
+        assert_eq!(*img.get_pixel(20, 20), Rgb([128, 128, 128]));
+    }
+
+    #[actix_web::test]
+    async fn test_rasterize_draws_price_chart() {
+        let svg = r##"<svg width="40" height="20"><chart x="0" y="0" width="40" height="20" range="24h" stroke="#000" stroke-width="2" fill="#0f0"/></svg>"##;
+        let rising = Arc::new(vec![1.0, 2.0]);
+
+        // Assert a rising price runs from the bottom left to the top right, filled below
+        let img = rasterize(
+            svg,
+            &assets(),
+            &Fonts::default(),
+            &LiveData {
+                prices: HashMap::from([(PriceRange::Day, rising)]),
+                ..LiveData::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(*img.get_pixel(0, 19), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(39, 0), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(39, 19), Rgb([0, 255, 0]));
+        assert_eq!(*img.get_pixel(0, 0), Rgb([255, 255, 255]));
+        assert_eq!(chart_ranges(svg), vec![PriceRange::Day]);
+
+        // Assert a chart without prices is left out rather than failing the image
+        let img = rasterize(svg, &assets(), &Fonts::default(), &LiveData::default()).unwrap();
+        assert_eq!(*img.get_pixel(39, 0), Rgb([255, 255, 255]));
+
+        // Assert a range we can't fetch is a template error
+        let bad = r#"<svg width="10" height="10"><chart width="10" height="10" range="1y"/></svg>"#;
+        assert!(matches!(
+            rasterize(bad, &assets(), &Fonts::default(), &LiveData::default()),
+            Err(ImageError::Svg(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_rasterize_draws_progress() {
+        let draw = |step: &str, steps: &str| {
+            let svg = format!(
+                r##"<svg width="100" height="10"><progress width="100" height="4" step="{}"
+                steps="{}" fill="#f00" background="#00f"/></svg>"##,
+                step, steps
+            );
+            rasterize(&svg, &assets(), &Fonts::default(), &LiveData::default())
+        };
+
+        // Assert the bar is split into steps, those done drawn in fill
+        let img = draw("2", "3").unwrap();
+        assert_eq!(*img.get_pixel(10, 2), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(50, 2), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(90, 2), Rgb([0, 0, 255]));
+        assert_eq!(*img.get_pixel(90, 6), Rgb([255, 255, 255]));
+
+        // Assert a screen outside a flow draws no bar, and nonsense steps are refused
+        assert_eq!(
+            *draw("", "").unwrap().get_pixel(10, 2),
+            Rgb([255, 255, 255])
+        );
+        for (step, steps) in [("4", "3"), ("1", "0"), ("1", "11"), ("one", "3")] {
+            assert!(matches!(draw(step, steps), Err(ImageError::Svg(_))));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_color_from() {
+        assert_eq!(color_from("#0f8"), Some(Rgb([0, 255, 136])));
+        assert_eq!(color_from("#C82020"), Some(Rgb([200, 32, 32])));
+        assert_eq!(color_from("White"), Some(Rgb([255, 255, 255])));
+        assert_eq!(color_from("#12345"), None);
+        assert_eq!(color_from("rebeccapurple"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_shipped_templates_load() {
+        let templates = ImageTemplates::load(Path::new("templates"), Path::new("assets")).unwrap();
+
+        // Assert the price template renders a PNG
+        let png = templates
+            .render(
+                "price",
+                &params(&[("price", "0.043")]),
+                &LiveData::default(),
+            )
+            .unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+
+        // Assert a missing directory means no templates rather than an error
+        let none =
+            ImageTemplates::load(Path::new("no_such_templates"), Path::new("assets")).unwrap();
+        assert_eq!(none.len(), 0);
+    }
+
+    #[actix_web::test]
+    async fn test_serve_template_image() {
+        let templates = ImageTemplates::new(
+            HashMap::from([
+                ("t".to_string(), TEMPLATE.to_string()),
+                (
+                    "a".to_string(),
+                    r#"<svg width="9" height="9"><image href="{{image}}"/></svg>"#.to_string(),
+                ),
+                (
+                    "p".to_string(),
+                    r#"<svg width="60" height="20"><text y="16" font-size="16">{{progress}}</text></svg>"#
+                        .to_string(),
+                ),
+                (
+                    "c".to_string(),
+                    r#"<svg width="60" height="20"><text y="16" font-size="16">{{countdown}}</text></svg>"#
+                        .to_string(),
+                ),
+                (
+                    "s".to_string(),
+                    r#"<svg width="60" height="20"><rect width="60" height="20" fill="{{tx_status_color}}"/></svg>"#
+                        .to_string(),
+                ),
+                (
+                    "q".to_string(),
+                    r#"<svg width="60" height="60"><qr size="60" data="{{deposit_uri}}"/></svg>"#
+                        .to_string(),
+                ),
+            ]),
+            Path::new("assets"),
+        );
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(templates))
+                .app_data(web::Data::new(RenderCache::new(0, 60)))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(ProfileCache::new(60)))
+                .app_data(web::Data::new(PriceHistory::new(60)))
+                .app_data(web::Data::new(Leaderboard::new(60)))
+                .app_data(web::Data::new(MoxiePrice::new(60)))
+                .app_data(web::Data::new(Receipts::default()))
+                .app_data(web::Data::new(Auction::new(15)))
+                .app_data(web::Data::new(Bridge::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(Config::default()))
+                .route(
+                    "/images/{file}",
+                    web::get().to(images::serve_template_image),
+                ),
+        )
+        .await;
+
+        // Assert a known template is served as a cacheable PNG
+        let req = test::TestRequest::get()
+            .uri("/images/t.png?amount=120%20GOAT")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+        assert!(resp.headers().contains_key("cache-control"));
+
+        // Assert the same template is served as a GIF, and other formats aren't
+        let req = test::TestRequest::get()
+            .uri("/images/t.gif?amount=120%20GOAT")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/gif");
+        assert!(test::read_body(resp).await.starts_with(b"GIF89a"));
+        let req = test::TestRequest::get().uri("/images/t.bmp").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+
+        // Assert clients accepting WebP get it for .png, varying on Accept, without
+        // the cached copy reaching clients that don't
+        let req = test::TestRequest::get()
+            .uri("/images/t.png?amount=120%20GOAT")
+            .insert_header(("accept", "image/avif,image/webp,*/*;q=0.8"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/webp");
+        assert_eq!(resp.headers().get("vary").unwrap(), "Accept");
+        assert!(test::read_body(resp).await.starts_with(b"RIFF"));
+        for accept in ["*/*", "image/webp;q=0"] {
+            let req = test::TestRequest::get()
+                .uri("/images/t.png?amount=120%20GOAT")
+                .insert_header(("accept", accept))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+        }
+
+        // Assert .webp is WebP whatever the client accepts
+        let req = test::TestRequest::get().uri("/images/t.webp").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/webp");
+        assert!(!resp.headers().contains_key("vary"));
+
+        // Assert unknown templates are 404 and oversized values 400
+        let req = test::TestRequest::get()
+            .uri("/images/nope.png")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 404);
+        let long = "9".repeat(images::MAX_PARAM_BYTES + 1);
+        let req = test::TestRequest::get()
+            .uri(&format!("/images/t.png?amount={}", long))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        // Assert the deposit QR can't be filled from the query, only DEPOSIT_ADDRESS
+        let mut bodies = Vec::new();
+        for uri in [
+            "/images/q.png",
+            "/images/q.png?deposit_uri=ethereum%3A0xevil",
+        ] {
+            let resp =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            bodies.push(test::read_body(resp).await);
+        }
+        assert_eq!(bodies[0], bodies[1]);
+
+        // Assert a flow's step and steps are written out as "step/steps"
+        let mut bodies = Vec::new();
+        for uri in [
+            "/images/p.png",
+            "/images/p.png?step=2&steps=3",
+            "/images/p.png?progress=2%2F3",
+        ] {
+            let resp =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            bodies.push(test::read_body(resp).await);
+        }
+        assert_ne!(bodies[0], bodies[1]);
+        assert_eq!(bodies[1], bodies[2]);
+
+        // Assert an auction's end time is written out as the time left
+        let mut bodies = Vec::new();
+        for uri in [
+            "/images/c.png",
+            "/images/c.png?ends_at=1&t=1",
+            "/images/c.png?countdown=Ended",
+        ] {
+            let resp =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            bodies.push(test::read_body(resp).await);
+        }
+        assert_ne!(bodies[0], bodies[1]);
+        assert_eq!(bodies[1], bodies[2]);
+
+        // Assert a transaction's status comes from its receipt, not the query, and
+        // a hash that isn't one is refused
+        let tx = format!("0x{}", "ab".repeat(32));
+        let mut bodies = Vec::new();
+        for uri in [
+            format!("/images/s.png?tx={}", tx),
+            format!("/images/s.png?tx={}&tx_status_color=%2317c964", tx),
+            "/images/s.png?tx_status_color=%2317c964".to_string(),
+        ] {
+            let resp =
+                test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+            bodies.push(test::read_body(resp).await);
+        }
+        assert_eq!(bodies[0], bodies[1]);
+        assert_ne!(bodies[1], bodies[2]);
+        let img = image::load_from_memory(&bodies[0]).unwrap().to_rgb8();
+        assert_eq!(*img.get_pixel(0, 0), Rgb([0x8a, 0x8f, 0x98]));
+        let req = test::TestRequest::get()
+            .uri("/images/s.png?tx=0x1234")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+        // Assert an image parameter outside assets/ is refused
+        let req = test::TestRequest::get()
+            .uri("/images/a.png?image=..%2FCargo.toml")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+
+    #[actix_web::test]
+    async fn test_render_cache_drops_least_recently_used() {
+        let cache = RenderCache::new(2, 60);
+        let png = |byte: u8| web::Bytes::from(vec![byte]);
+
+        // Assert parameters in any order make the same key, and the template matters
+        let key = RenderCache::key("t", &params(&[("a", "1"), ("b", "2")]));
+        assert_eq!(
+            key,
+            RenderCache::key("t", &params(&[("b", "2"), ("a", "1")]))
+        );
+        assert_ne!(
+            key,
+            RenderCache::key("u", &params(&[("a", "1"), ("b", "2")]))
+        );
+
+        // Assert a full cache drops the image used longest ago, not the oldest drawn
+        cache.insert("a".to_string(), png(1));
+        cache.insert("b".to_string(), png(2));
+        assert_eq!(cache.get("a"), Some(png(1)));
+        cache.insert("c".to_string(), png(3));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(png(1)));
+        assert_eq!(cache.get("c"), Some(png(3)));
+
+        // Assert a size of 0 keeps nothing
+        let off = RenderCache::new(0, 60);
+        off.insert("a".to_string(), png(1));
+        assert_eq!(off.get("a"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_serve_template_image_reuses_renders() {
+        let templates = ImageTemplates::new(
+            HashMap::from([("t".to_string(), TEMPLATE.to_string())]),
+            Path::new("assets"),
+        );
+        let metrics = web::Data::new(Metrics::default());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(templates))
+                .app_data(web::Data::new(RenderCache::new(16, 60)))
+                .app_data(metrics.clone())
+                .app_data(web::Data::new(ProfileCache::new(60)))
+                .app_data(web::Data::new(PriceHistory::new(60)))
+                .app_data(web::Data::new(Leaderboard::new(60)))
+                .app_data(web::Data::new(MoxiePrice::new(60)))
+                .app_data(web::Data::new(Receipts::default()))
+                .app_data(web::Data::new(Auction::new(15)))
+                .app_data(web::Data::new(Bridge::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(Config::default()))
+                .route(
+                    "/images/{file}",
+                    web::get().to(images::serve_template_image),
+                ),
+        )
+        .await;
+
+        // Assert the same image asked for again is served from the cache, byte for byte
+        let mut bodies = Vec::new();
+        for uri in [
+            "/images/t.png?amount=1&unit=GOAT",
+            "/images/t.png?unit=GOAT&amount=1",
+            "/images/t.png?amount=2&unit=GOAT",
+        ] {
+            let resp =
+                test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(resp.status(), 200);
+            bodies.push(test::read_body(resp).await);
+        }
+        assert_eq!(bodies[0], bodies[1]);
+        let rendered = metrics.render();
+        assert!(rendered.contains("frame_image_cache_requests_total{result=\"hit\"} 1"));
+        assert!(rendered.contains("frame_image_cache_requests_total{result=\"miss\"} 2"));
+
+        // Assert a WebP is cached apart from the PNG of the same image, and shared
+        // with .webp
+        for uri in [
+            "/images/t.png?amount=1&unit=GOAT",
+            "/images/t.webp?amount=1&unit=GOAT",
+        ] {
+            let req = test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("accept", "image/webp"))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert!(test::read_body(resp).await.starts_with(b"RIFF"));
+        }
+        let rendered = metrics.render();
+        assert!(rendered.contains("frame_image_cache_requests_total{result=\"hit\"} 2"));
+        assert!(rendered.contains("frame_image_cache_requests_total{result=\"miss\"} 3"));
+    }
+
+    #[actix_web::test]
+    async fn test_png_behind_a_cdn_is_always_png() {
+        let templates = ImageTemplates::new(
+            HashMap::from([("t".to_string(), TEMPLATE.to_string())]),
+            Path::new("assets"),
+        );
+        let config = Config {
+            cdn_base_url: Some("https://cdn.example.com".to_string()),
+            ..Config::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(templates))
+                .app_data(web::Data::new(RenderCache::new(0, 60)))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(ProfileCache::new(60)))
+                .app_data(web::Data::new(PriceHistory::new(60)))
+                .app_data(web::Data::new(Leaderboard::new(60)))
+                .app_data(web::Data::new(MoxiePrice::new(60)))
+                .app_data(web::Data::new(Receipts::default()))
+                .app_data(web::Data::new(Auction::new(15)))
+                .app_data(web::Data::new(Bridge::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(config))
+                .route(
+                    "/images/{file}",
+                    web::get().to(images::serve_template_image),
+                ),
+        )
+        .await;
+
+        // Assert a CDN keeping one copy per URL never gets a WebP under a .png URL
+        let req = test::TestRequest::get()
+            .uri("/images/t.png")
+            .insert_header(("accept", "image/webp"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+        assert!(!resp.headers().contains_key("vary"));
+
+        // Assert .webp still asks for one explicitly
+        let req = test::TestRequest::get().uri("/images/t.webp").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/webp");
+    }
+
+    #[actix_web::test]
+    async fn test_recipient_is_drawn_by_name() {
+        // A hub where @alice is fid 7, named Alice
+        let hub = spawn_mock_server(|cfg| {
+            cfg.route(
+                "/v1/userNameProofByName",
+                web::get().to(|query: web::Query<HashMap<String, String>>| async move {
+                    match query.get("name").map(String::as_str) {
+                        Some("alice") => HttpResponse::Ok().json(serde_json::json!({ "fid": 7 })),
+                        _ => HttpResponse::NotFound().finish(),
+                    }
+                }),
+            )
+            .route(
+                "/v1/userDataByFid",
+                web::get().to(|| async {
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "messages": [{
+                            "data": {
+                                "userDataBody": { "type": "USER_DATA_TYPE_DISPLAY", "value": "Alice" }
+                            }
+                        }]
+                    }))
+                }),
+            )
+            .route(
+                "/v1/verificationsByFid",
+                web::get().to(|| async { HttpResponse::Ok().json(serde_json::json!({})) }),
+            );
+        })
+        .await;
+        let text = |content: &str| {
+            format!(
+                r#"<svg width="60" height="20"><text y="16" font-size="16">{}</text></svg>"#,
+                content
+            )
+        };
+        let templates = ImageTemplates::new(
+            HashMap::from([
+                ("r".to_string(), text("{{recipient_name}}")),
+                ("alice".to_string(), text("Alice")),
+                ("blank".to_string(), text("")),
+            ]),
+            Path::new("assets"),
+        );
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(templates))
+                .app_data(web::Data::new(RenderCache::new(0, 60)))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(ProfileCache::new(60)))
+                .app_data(web::Data::new(PriceHistory::new(60)))
+                .app_data(web::Data::new(Leaderboard::new(60)))
+                .app_data(web::Data::new(MoxiePrice::new(60)))
+                .app_data(web::Data::new(Receipts::default()))
+                .app_data(web::Data::new(Auction::new(15)))
+                .app_data(web::Data::new(Bridge::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(Config {
+                    hub_url: hub,
+                    ..Config::default()
+                }))
+                .route(
+                    "/images/{file}",
+                    web::get().to(images::serve_template_image),
+                ),
+        )
+        .await;
+        let render = |uri: &'static str| {
+            let app = &app;
+            async move {
+                let resp =
+                    test::call_service(app, test::TestRequest::get().uri(uri).to_request()).await;
+                assert_eq!(resp.status(), 200);
+                test::read_body(resp).await
+            }
+        };
+
+        // Assert the recipient's name is looked up from their username, as typed
+        assert_eq!(
+            render("/images/r.png?recipient=%40Alice").await,
+            render("/images/alice.png").await
+        );
+
+        // Assert a username nobody has draws without one
+        assert_eq!(
+            render("/images/r.png?recipient=nobody").await,
+            render("/images/blank.png").await
+        );
+    }
+}
@@ -1,20 +1,37 @@
 #[cfg(test)]
 mod integration_tests {
-    use actix_web::{test, web, App};
+    use crate::assets::AssetHashes;
+    use crate::error_image::ErrorImages;
+    use crate::frame_logic::registry::FrameRegistry;
+    use crate::i18n::Translations;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::{ReplayGuard, FARCASTER_EPOCH};
+    use crate::tests::{noop_analytics, spawn_mock_server, test_registry};
     use crate::{handle_frame, index, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+    use std::path::Path;
+    use std::time::{SystemTime, UNIX_EPOCH};
 
     #[actix_web::test]
     async fn test_index_page() {
         // Create a mock application with the same routes as in main.rs
         let config = web::Data::new(Config {
             domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
         });
 
         let app = test::init_service(
             App::new()
                 .app_data(config.clone())
-                .route("/", web::get().to(index))
-        ).await;
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index)),
+        )
+        .await;
 
         // Simulate a GET request to the index page
         let req = test::TestRequest::get().uri("/").to_request();
@@ -24,23 +41,181 @@ mod integration_tests {
         assert!(resp.status().is_success());
     }
 
+    #[actix_web::test]
+    async fn test_index_has_open_graph_preview() {
+        let default_config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        });
+        let custom_config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            og_title: "GOAT <Store>".to_string(),
+            og_description: "Gift GOAT to friends".to_string(),
+            og_image: Some("gift.png".to_string()),
+            ..Config::default()
+        });
+
+        let mut pages = Vec::new();
+        for config in [default_config, custom_config] {
+            let app = test::init_service(
+                App::new()
+                    .app_data(config)
+                    .app_data(web::Data::new(test_registry()))
+                    .app_data(web::Data::new(AssetHashes::default()))
+                    .route("/", web::get().to(index)),
+            )
+            .await;
+            let req = test::TestRequest::get().uri("/").to_request();
+            let body = test::call_and_read_body(&app, req).await;
+            pages.push(String::from_utf8(body.to_vec()).unwrap());
+        }
+
+        // Assert the defaults preview the frame's own image
+        assert!(pages[0].contains(r#"<meta property="og:title" content="GOAT Frame" />"#));
+        assert!(pages[0].contains(r#"<meta property="og:description" content="Buy, boost and gift GOAT right from your feed." />"#));
+        assert!(pages[0].contains(
+            r#"<meta property="og:image" content="http://localhost/assets/main.png" />"#
+        ));
+
+        // Assert configured values are used, escaped, alongside the frame tags
+        assert!(pages[1].contains(r#"<meta property="og:title" content="GOAT &lt;Store&gt;" />"#));
+        assert!(pages[1]
+            .contains(r#"<meta property="og:description" content="Gift GOAT to friends" />"#));
+        assert!(pages[1].contains(
+            r#"<meta property="og:image" content="http://localhost/assets/gift.png" />"#
+        ));
+        assert!(pages[1].contains(
+            r#"<meta property="fc:frame:image" content="http://localhost/assets/main.png" />"#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_index_opens_requested_screen() {
+        // Create a mock application with the same routes as in main.rs
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/?screen=gift").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert Gift is shown, with main underneath it for Back
+        assert!(html.contains(
+            r#"<meta property="fc:frame:image" content="http://localhost/assets/gift.png" />"#
+        ));
+        assert!(html.contains("&quot;stack&quot;:[&quot;main&quot;,&quot;gift&quot;]"));
+
+        // Assert a screen the frame doesn't have is a 404
+        let req = test::TestRequest::get().uri("/?screen=nope").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_index_greets_mini_app_user() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index)),
+        )
+        .await;
+
+        let context = BASE64_URL_SAFE_NO_PAD.encode(r#"{"user":{"fid":42,"displayName":"Alice"}}"#);
+        let req = test::TestRequest::get()
+            .uri(&format!("/?context={}", context))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert the main screen greets the user the context names
+        assert!(html.contains(r#"content="Hi Alice! GOAT Store"#));
+
+        // Assert everyone else is greeted anonymously
+        let req = test::TestRequest::get().uri("/").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"content="Hi there! GOAT Store"#));
+    }
+
+    #[actix_web::test]
+    async fn test_index_speaks_the_viewers_language() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        });
+        let mut registry = test_registry();
+        registry.translations = Translations::load(Path::new("locales"), None).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(registry))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index)),
+        )
+        .await;
+
+        let context = BASE64_URL_SAFE_NO_PAD
+            .encode(r#"{"user":{"fid":42,"displayName":"Alice"},"locale":"es-MX"}"#);
+        let req = test::TestRequest::get()
+            .uri(&format!("/?context={}", context))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert labels and alt text are translated, with placeholders still filled in
+        assert!(html.contains(r#"<meta property="fc:frame:button:1" content="Comprar y Boost" />"#));
+        assert!(html.contains("¡Hola Alice!"));
+    }
+
     #[actix_web::test]
     async fn test_handle_frame_valid_button() {
         // Create a mock application with the same routes as in main.rs
         let config = web::Data::new(Config {
             domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
         });
 
         let app = test::init_service(
             App::new()
                 .app_data(config.clone())
-                .route("/api/frame", web::post().to(handle_frame))
-        ).await;
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
 
         // Create a valid request with a button index of 1 (Buy & Boost)
         let req = test::TestRequest::post()
             .uri("/api/frame")
-            .set_json(&serde_json::json!({
+            .set_json(serde_json::json!({
                 "untrusted_data": {
                     "button_index": 1
                 }
@@ -52,6 +227,390 @@ mod integration_tests {
 
         // Assert that the response has a 200 OK status
         assert!(resp.status().is_success());
+
+        // Assert the next screen comes back as fc:frame meta tags, not JSON
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/html");
+        let body = test::read_body(resp).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"<meta property="fc:frame" content="vNext" />"#));
+        assert!(html.contains(
+            r#"<meta property="fc:frame:image" content="http://localhost/images/buy_boost.png?image=buy_boost.png"#
+        ));
+        assert!(html.contains(r#"<meta property="fc:frame:button:1" content="Confirm" />"#));
+        assert!(
+            html.contains(r#"<meta property="fc:frame:post_url" content="http://localhost/" />"#)
+        );
+        assert!(html.contains(r#"<meta property="fc:frame:state""#));
+    }
+
+    #[actix_web::test]
+    async fn test_handle_frame_answers_json_when_asked() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let press = || {
+            test::TestRequest::post()
+                .uri("/api/frame")
+                .insert_header(("Idempotency-Key", "json-1"))
+                .set_json(serde_json::json!({
+                    "untrusted_data": {
+                        "button_index": 1
+                    }
+                }))
+        };
+
+        // Assert the same press comes back as JSON describing the page's tags
+        let req = press()
+            .insert_header(("Accept", "application/json"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+        let frame: serde_json::Value = test::read_body_json(resp).await;
+        assert!(frame["image"]
+            .as_str()
+            .unwrap()
+            .starts_with("http://localhost/images/buy_boost.png?image=buy_boost.png"));
+        assert_eq!(frame["post_url"], "http://localhost/");
+        assert_eq!(frame["buttons"][0]["label"], "Confirm");
+        assert!(frame["state"].is_string());
+
+        // Assert a repeat asking for HTML isn't handed the cached JSON
+        let req = press().insert_header(("Accept", "text/html")).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/html");
+        let body = test::read_body(resp).await;
+        assert!(String::from_utf8(body.to_vec())
+            .unwrap()
+            .contains(r#"<meta property="fc:frame" content="vNext" />"#));
+
+        // Assert the first screen can be fetched as JSON too
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("Accept", "application/json"))
+            .to_request();
+        let frame: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(frame["post_url"], "http://localhost/");
+        assert_eq!(frame["buttons"].as_array().unwrap().len(), 4);
+    }
+
+    #[actix_web::test]
+    async fn test_expired_state_shows_session_expired_frame() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        // A frame showing Buy & Boost that expired long ago
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .insert_header(("Accept", "application/json"))
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1,
+                    "state": r#"{"stack":["main","buy_boost"],"expires_at":1}"#
+                }
+            }))
+            .to_request();
+        let frame: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert the user is told to start over rather than the stale screen being acted on
+        assert_eq!(frame["image_alt"], "This session expired, start over");
+        assert_eq!(frame["buttons"].as_array().unwrap().len(), 1);
+        assert_eq!(frame["buttons"][0]["label"], "Start Over");
+        let state = frame["state"].as_str().unwrap();
+        assert!(state.contains(r#""stack":["main"]"#));
+
+        // Assert Start Over lands on main with a fresh expiry
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .insert_header(("Accept", "application/json"))
+            .set_json(serde_json::json!({
+                "untrusted_data": { "button_index": 1, "state": state }
+            }))
+            .to_request();
+        let frame: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(frame["image"], "http://localhost/assets/main.png");
+        assert!(frame["state"].as_str().unwrap().contains("expires_at"));
+    }
+
+    #[actix_web::test]
+    async fn test_dated_client_gets_screens_it_can_show() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let press = |button_index: usize, state: Option<&str>| {
+            test::TestRequest::post()
+                .uri("/api/frame")
+                .set_json(serde_json::json!({
+                    "clientProtocol": "farcaster@2024-01-26",
+                    "untrusted_data": { "button_index": button_index, "state": state }
+                }))
+                .to_request()
+        };
+
+        // Open Buy & Boost from a client pinned to the launch version
+        let body = test::call_and_read_body(&app, press(1, None)).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert the page names the client's version, without the box or tx button it can't use
+        assert!(html.contains(r#"<meta property="fc:frame" content="2024-01-26" />"#));
+        assert!(html.contains("http://localhost/images/buy_boost.png?image=buy_boost.png"));
+        assert!(!html.contains("fc:frame:input:text"));
+        assert!(html.contains(r#"<meta property="fc:frame:button:1" content="Back" />"#));
+        assert!(!html.contains("fc:frame:button:2"));
+
+        // Assert its only button still works, since indexes skip the hidden one
+        let state = r#"{"stack":["main","buy_boost"]}"#;
+        let body = test::call_and_read_body(&app, press(1, Some(state))).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("http://localhost/assets/main.png"));
+    }
+
+    #[actix_web::test]
+    async fn test_handle_frame_accepts_full_untrusted_data() {
+        // Create a mock application with the same routes as in main.rs
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        // Every field a client may send alongside the button press
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "fid": 2,
+                    "url": "http://localhost/",
+                    "message_hash": "0xd2b1ddc6c88e865a33cb1a565e0058d757042974",
+                    "timestamp": 1706243218,
+                    "network": 1,
+                    "button_index": 1,
+                    "input_text": "hello",
+                    "state": "",
+                    "cast_id": {
+                        "fid": 226,
+                        "hash": "0xa48dd46161d8e57725f5e26e34ec19c13ff7f3b9"
+                    },
+                    "address": "0x0000000000000000000000000000000000000001"
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the extra fields are accepted and the press handled as usual
+        assert!(resp.status().is_success());
+        let body = test::read_body(resp).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(
+            r#"<meta property="fc:frame:image" content="http://localhost/images/buy_boost.png?image=buy_boost.png"#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_handle_frame_accepts_warpcast_camel_case() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        // A Send Gift press on the Gift screen, shaped as Warpcast posts it
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrustedData": {
+                    "fid": 2,
+                    "url": "http://localhost/",
+                    "messageHash": "0xd2b1ddc6c88e865a33cb1a565e0058d757042974",
+                    "timestamp": 1706243218,
+                    "network": 1,
+                    "buttonIndex": 1,
+                    "inputText": "alice",
+                    "state": r#"{"stack":["main","gift"]}"#,
+                    "castId": {
+                        "fid": 226,
+                        "hash": "0xa48dd46161d8e57725f5e26e34ec19c13ff7f3b9"
+                    }
+                },
+                "trustedData": {
+                    "messageBytes": "d2b1ddc6c88e865a33cb1a565e0058d757042974"
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the camelCase fields are read, including the state and entered text
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(
+            r#"<meta property="fc:frame:image:alt" content="Confirm your gift to @alice" />"#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_handle_frame_validates_camel_case_trusted_data() {
+        // Mock hub that only vouches for the exact bytes the client signed
+        let hub_url = spawn_mock_server(|cfg| {
+            cfg.route(
+                "/v1/validateMessage",
+                web::post().to(|body: web::Bytes| async move {
+                    let timestamp = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs()
+                        - FARCASTER_EPOCH;
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "valid": body.as_ref() == [0x0a, 0x0b, 0x0c],
+                        "message": {
+                            "hash": "0xcafe",
+                            "data": {
+                                "fid": 42,
+                                "timestamp": timestamp,
+                                "frameActionBody": { "url": "aHR0cDovL2xvY2FsaG9zdA==", "buttonIndex": 1 }
+                            }
+                        }
+                    }))
+                }),
+            );
+        })
+        .await;
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            hub_url,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrustedData": { "fid": 42, "buttonIndex": 1 },
+                "trustedData": { "messageBytes": "0a0b0c" }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert messageBytes reaches the hub and the validated press is handled
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(
+            r#"<meta property="fc:frame:image" content="http://localhost/images/buy_boost.png?image=buy_boost.png"#
+        ));
     }
 
     #[actix_web::test]
@@ -59,18 +618,31 @@ mod integration_tests {
         // Create a mock application with the same routes as in main.rs
         let config = web::Data::new(Config {
             domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
         });
 
         let app = test::init_service(
             App::new()
                 .app_data(config.clone())
-                .route("/api/frame", web::post().to(handle_frame))
-        ).await;
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
 
         // Create an invalid request with an out-of-range button index (e.g., 999)
         let req = test::TestRequest::post()
             .uri("/api/frame")
-            .set_json(&serde_json::json!({
+            .set_json(serde_json::json!({
                 "untrusted_data": {
                     "button_index": 999
                 }
@@ -82,5 +654,544 @@ mod integration_tests {
 
         // Assert that the response has a 200 OK status
         assert!(resp.status().is_success());
+
+        // Assert the error frame says what went wrong and offers a way out
+        let body = test::read_body(resp).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"content="http://localhost/assets/error/"#));
+        assert!(html.contains("Invalid button index: 999"));
+        assert!(html.contains(r#"<meta property="fc:frame:button:1" content="Try Again" />"#));
+        assert!(html.contains(r#"<meta property="fc:frame:button:2" content="Start Over" />"#));
+
+        // Assert Start Over from the error frame lands back on main
+        let state = html
+            .split(r#"<meta property="fc:frame:state" content=""#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap()
+            .replace("&quot;", "\"");
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 2,
+                    "state": state
+                }
+            }))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("http://localhost/assets/main.png"));
+    }
+
+    #[actix_web::test]
+    async fn test_handle_frame_rejects_unsigned_request() {
+        // Validation stays on, as it would in production
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        // Create a request without trusted_data, as a forging client would
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1
+                }
+            }))
+            .to_request();
+
+        let resp = test::call_service(&app, req).await;
+
+        // Assert that the unsigned request is rejected
+        assert_eq!(resp.status(), actix_web::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Two independent frames sharing one server
+    fn two_frame_registry() -> FrameRegistry {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Shop", target = "more" }]
+
+            [frames.store.screens.more]
+            image = "more.png"
+            buttons = [{ label = "Back", target = "back" }]
+
+            [frames.airdrop.screens.main]
+            image = "gift.png"
+            buttons = [{ label = "Claim", target = "claimed" }, { label = "Skip" }]
+
+            [frames.airdrop.screens.claimed]
+            image = "add_liquidity.png"
+            buttons = [{ label = "Done", target = "back" }]
+            "#,
+        )
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        registry
+    }
+
+    #[actix_web::test]
+    async fn test_named_frames_serve_their_own_screens() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        // Create a mock application with the same named frame routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(two_frame_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .service(
+                    web::resource("/f/{name}")
+                        .route(web::get().to(index))
+                        .route(web::post().to(handle_frame)),
+                )
+                .service(
+                    web::resource("/frames/{name}")
+                        .route(web::get().to(index))
+                        .route(web::post().to(handle_frame)),
+                ),
+        )
+        .await;
+
+        // Assert /frames/{name} serves the same page, which posts to the canonical URL
+        let req = test::TestRequest::get().uri("/frames/airdrop").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(
+            r#"<meta property="fc:frame:post_url" content="http://localhost/f/airdrop" />"#
+        ));
+
+        let req = test::TestRequest::get().uri("/f/airdrop").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert the airdrop page shows its own image and posts back to its own route
+        assert!(html.contains("http://localhost/assets/gift.png"));
+        assert!(html.contains("content=\"Claim\""));
+        assert!(html.contains(
+            r#"<meta property="fc:frame:post_url" content="http://localhost/f/airdrop" />"#
+        ));
+
+        for (name, image, label) in [
+            ("store", "more.png", "Back"),
+            ("airdrop", "add_liquidity.png", "Done"),
+        ] {
+            let req = test::TestRequest::post()
+                .uri(&format!("/f/{}", name))
+                .set_json(serde_json::json!({
+                    "untrusted_data": {
+                        "button_index": 1
+                    }
+                }))
+                .to_request();
+            let body = test::call_and_read_body(&app, req).await;
+            let html = String::from_utf8(body.to_vec()).unwrap();
+
+            // Assert the same button leads somewhere different in each frame
+            assert!(html.contains(&format!("http://localhost/assets/{}", image)));
+            assert!(html.contains(&format!(
+                r#"<meta property="fc:frame:button:1" content="{}" />"#,
+                label
+            )));
+            assert!(html.contains(&format!(
+                r#"<meta property="fc:frame:post_url" content="http://localhost/f/{}" />"#,
+                name
+            )));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_frame_page_and_posts_share_a_url() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        // Create a mock application with the same frame routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .service(
+                    web::resource("/")
+                        .route(web::get().to(index))
+                        .route(web::post().to(handle_frame)),
+                )
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        // Assert the page posts back to itself
+        let req = test::TestRequest::get().uri("/").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            html.contains(r#"<meta property="fc:frame:post_url" content="http://localhost/" />"#)
+        );
+
+        // Assert a press is handled the same at the page and at the old endpoint
+        let mut pages = Vec::new();
+        for uri in ["/", "/api/frame"] {
+            let req = test::TestRequest::post()
+                .uri(uri)
+                .set_json(serde_json::json!({ "untrusted_data": { "button_index": 4 } }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+            pages.push(test::read_body(resp).await);
+        }
+        assert_eq!(pages[0], pages[1]);
+        assert!(String::from_utf8(pages[0].to_vec()).unwrap().contains(
+            r#"<meta property="fc:frame:image" content="http://localhost/assets/more.png" />"#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_hand_off_to_another_frame() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            state_secret: Some("secret".to_string()),
+            ..Config::default()
+        });
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.gift.screens.main]
+            image = "gift.png"
+            input = { placeholder = "Recipient" }
+            buttons = [{ label = "Send", frame = "rewards", target = "claim" }]
+
+            [frames.rewards.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Rewards" }]
+
+            [frames.rewards.screens.claim]
+            image = "more.png"
+            alt = "Claim your reward for gifting @{input}"
+            buttons = [{ label = "Back", target = "back" }]
+            "#,
+        )
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(registry))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/f/{name}", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/f/gift")
+            .set_json(serde_json::json!({
+                "untrusted_data": { "button_index": 1, "input_text": "alice" }
+            }))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert Rewards' screen is shown, with the recipient, posting to Rewards
+        assert!(html.contains("Claim your reward for gifting @alice"));
+        assert!(html.contains(
+            r#"<meta property="fc:frame:post_url" content="http://localhost/f/rewards" />"#
+        ));
+
+        // Assert the signed state carries on in Rewards, where Back returns to its main
+        let state = html
+            .split(r#"property="fc:frame:state" content=""#)
+            .nth(1)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap()
+            .replace("&quot;", "\"");
+        let req = test::TestRequest::post()
+            .uri("/f/rewards")
+            .set_json(serde_json::json!({
+                "untrusted_data": { "button_index": 1, "state": state }
+            }))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(
+            r#"<meta property="fc:frame:image" content="http://localhost/assets/main.png" />"#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_screens_post_to_their_own_step() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Buy", target = "confirm" }]
+
+            [frames.store.screens.confirm]
+            image = "buy_boost.png"
+            post_path = "confirm?flow=buy"
+            buttons = [{ label = "Back", target = "back" }, { label = "Done" }]
+            "#,
+        )
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(registry))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::post().to(handle_frame))
+                .route("/f/{name}/{step}", web::post().to(handle_frame)),
+        )
+        .await;
+        let press = |uri: &str, button_index: usize, state: &str| {
+            test::TestRequest::post()
+                .uri(uri)
+                .set_json(serde_json::json!({
+                    "untrusted_data": { "button_index": button_index, "state": state }
+                }))
+                .to_request()
+        };
+
+        // Assert the confirm screen's buttons post to its step
+        let body = test::call_and_read_body(&app, press("/", 1, "")).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(
+            r#"<meta property="fc:frame:post_url" content="http://localhost/f/store/confirm?flow=buy" />"#
+        ));
+
+        // Assert a press at the step is handled, and Back posts to the frame's URL again
+        let on_confirm = r#"{"stack":["main","confirm"]}"#;
+        let resp =
+            test::call_service(&app, press("/f/store/confirm?flow=buy", 1, on_confirm)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let html = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+        assert!(html.contains(
+            r#"<meta property="fc:frame:post_url" content="http://localhost/f/store" />"#
+        ));
+
+        // Assert a press at the step from another screen is refused
+        let resp = test::call_service(&app, press("/f/store/confirm", 1, "")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_refused_input_shows_error_image() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            aspect_ratio = "square"
+            input = { placeholder = "Amount", rules = { min = 1, message = "Enter at least 1 GOAT" } }
+            buttons = [{ label = "Buy", target = "more" }]
+
+            [frames.store.screens.more]
+            image = "more.png"
+            buttons = [{ label = "Back", target = "back" }]
+            "#,
+        )
+        .unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(registry))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/")
+            .set_json(serde_json::json!({
+                "untrusted_data": { "button_index": 1, "input_text": "0" }
+            }))
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert the screen is shown again with its box, drawn as the wide error image
+        assert!(html.contains(r#"content="http://localhost/assets/error/"#));
+        assert!(
+            html.contains(r#"<meta property="fc:frame:image:aspect_ratio" content="1.91:1" />"#)
+        );
+        assert!(html
+            .contains(r#"<meta property="fc:frame:image:alt" content="Enter at least 1 GOAT" />"#));
+        assert!(html.contains(r#"<meta property="fc:frame:input:text" content="Amount" />"#));
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_frame_returns_not_found() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(two_frame_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/f/{name}", web::get().to(index))
+                .route("/f/{name}/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/f/leaderboard").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert a frame that isn't configured is a 404 rather than an error frame
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::post()
+            .uri("/f/leaderboard/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_open_frames_posts() {
+        for open_frames in [false, true] {
+            // Validation stays on, as it would in production
+            let config = web::Data::new(Config {
+                domain: "http://localhost".to_string(),
+                open_frames,
+                ..Config::default()
+            });
+
+            // Create a mock application with the same routes as in main.rs
+            let app = test::init_service(
+                App::new()
+                    .app_data(config.clone())
+                    .app_data(web::Data::new(test_registry()))
+                    .app_data(web::Data::new(reqwest::Client::new()))
+                    .app_data(web::Data::new(ErrorImages::default()))
+                    .app_data(web::Data::new(Metrics::default()))
+                    .app_data(web::Data::new(RateLimiter::new(60)))
+                    .app_data(noop_analytics())
+                    .app_data(web::Data::new(IdempotencyCache::new(60)))
+                    .app_data(web::Data::new(ReplayGuard::new(600)))
+                    .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                    .app_data(web::Data::new(AssetHashes::default()))
+                    .route("/api/frame", web::post().to(handle_frame)),
+            )
+            .await;
+
+            // An XMTP client pressing More, with no Farcaster message to validate
+            let req = test::TestRequest::post()
+                .uri("/api/frame")
+                .set_json(serde_json::json!({
+                    "client_protocol": "xmtp@2024-02-01",
+                    "untrusted_data": {
+                        "button_index": 4
+                    }
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+
+            if !open_frames {
+                // Assert other protocols are refused unless OPEN_FRAMES is on
+                assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+                continue;
+            }
+
+            // Assert the post is served, answering in both tag sets
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = test::read_body(resp).await;
+            let html = String::from_utf8(body.to_vec()).unwrap();
+            assert!(html.contains(
+                r#"<meta property="of:image" content="http://localhost/assets/more.png" />"#
+            ));
+            assert!(html.contains(
+                r#"<meta property="fc:frame:image" content="http://localhost/assets/more.png" />"#
+            ));
+        }
     }
 }
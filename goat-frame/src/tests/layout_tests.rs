@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod layout_tests {
+    use crate::errors::ImageError;
+    use crate::images::font::Fonts;
+    use crate::images::layout::{Layout, Node, Rect};
+    use crate::images::svg::rasterize;
+    use crate::images::{AssetImages, ImageTemplates, LiveData};
+    use image::Rgb;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    fn area(width: f64, height: f64) -> Rect {
+        Rect {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+        }
+    }
+
+    // Where each of the root's children goes in a box of the layout's size
+    fn placed(json: &str) -> Vec<Rect> {
+        let layout = Layout::parse(json).unwrap();
+        let (row, container) = match &layout.root {
+            Node::Row(container) => (true, container),
+            Node::Column(container) => (false, container),
+            _ => panic!("root isn't a row or column"),
+        };
+        let size = area(layout.width as f64, layout.height as f64);
+        container
+            .place(row, size, &HashMap::new(), &Fonts::default())
+            .into_iter()
+            .map(|(_, rect)| rect)
+            .collect()
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_rows_and_columns_place_children() {
+        let square = r#"{"type": "image", "href": "a.png", "width": 10, "height": 10}"#;
+
+        // Assert a row runs left to right after its padding, gap apart, aligned across
+        let row = format!(
+            r#"{{"width": 100, "height": 50, "root": {{"type": "row", "padding": 5, "gap": 4,
+                "align": "center", "children": [{}, {}]}}}}"#,
+            square, square
+        );
+        assert_eq!(
+            placed(&row),
+            [rect(5.0, 20.0, 10.0, 10.0), rect(19.0, 20.0, 10.0, 10.0)]
+        );
+
+        // Assert justify spreads children out and grow shares the room left
+        let between = row.replace(r#""gap": 4"#, r#""justify": "space-between""#);
+        assert_eq!(placed(&between)[1].x, 85.0);
+        let grown = row.replacen(r#""height": 10}"#, r#""height": 10, "grow": 1}"#, 1);
+        assert_eq!(
+            placed(&grown),
+            [rect(5.0, 20.0, 76.0, 10.0), rect(85.0, 20.0, 10.0, 10.0)]
+        );
+
+        // Assert a column runs top to bottom, stretching children without a set width
+        let column = format!(
+            r#"{{"width": 100, "height": 50, "root": {{"type": "column", "align": "stretch",
+                "justify": "end", "children": [{}, {{"type": "row", "height": 8}}]}}}}"#,
+            square
+        );
+        assert_eq!(
+            placed(&column),
+            [rect(0.0, 32.0, 10.0, 10.0), rect(0.0, 42.0, 100.0, 8.0)]
+        );
+    }
+
+    #[test]
+    fn test_layout_draws_filled_text() {
+        let layout = Layout::parse(
+            r##"{"width": 40, "height": 20, "root": {"type": "row", "background": "{{bg}}",
+                "children": [{"type": "text", "text": "{{label}}", "color": "#f00"}]}}"##,
+        )
+        .unwrap();
+        let params = HashMap::from([
+            ("bg".to_string(), "#00f".to_string()),
+            ("label".to_string(), "<&>".to_string()),
+        ]);
+
+        // Assert values are escaped once in the SVG, and measured as they are
+        let svg = layout.to_svg(&params, &Fonts::default());
+        assert!(svg.contains(">&lt;&amp;&gt;</text>"), "{}", svg);
+        let img = rasterize(
+            &svg,
+            &AssetImages::new(Path::new("assets")),
+            &Fonts::default(),
+            &LiveData::default(),
+        )
+        .unwrap();
+        assert_eq!(img.dimensions(), (40, 20));
+        assert_eq!(*img.get_pixel(39, 19), Rgb([0, 0, 255]));
+        assert!(img.pixels().any(|pixel| *pixel == Rgb([255, 0, 0])));
+        assert_eq!(
+            layout.placeholders(),
+            ["bg", "label"].map(String::from).into()
+        );
+    }
+
+    #[test]
+    fn test_layout_rejects_what_it_cant_lay_out() {
+        let cases = [
+            r#"{"width": 10, "height": 10}"#,
+            r#"{"width": 10, "height": 10, "root": {"type": "grid"}}"#,
+            r#"{"width": 10, "height": 10, "root": {"type": "row", "margin": 4}}"#,
+            r#"{"width": 10, "height": 10, "root": {"type": "row", "gap": -1}}"#,
+            r#"{"width": 10, "height": 10, "root": {"type": "row", "align": "baseline"}}"#,
+            r#"{"width": 10, "height": 10, "root": {"type": "image", "href": "a.png"}}"#,
+        ];
+        for json in cases {
+            // Assert each mistake is reported when the layout is loaded
+            assert!(
+                matches!(Layout::parse(json), Err(ImageError::Svg(_))),
+                "{}",
+                json
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_reads_layouts() {
+        let dir = std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy("templates/portfolio.json", dir.join("portfolio.json")).unwrap();
+
+        // Assert a .json template is served like an SVG one, fetching what it draws
+        let templates = ImageTemplates::load(&dir, Path::new("assets")).unwrap();
+        assert!(templates.contains("portfolio"));
+        assert!(templates.draws_avatar("portfolio"));
+        assert!(templates
+            .placeholders("portfolio")
+            .unwrap()
+            .contains("balance"));
+        let params = HashMap::from([("balance".to_string(), "12".to_string())]);
+        let png = templates
+            .render("portfolio", &params, &LiveData::default())
+            .unwrap();
+        assert_eq!(image::load_from_memory(&png).unwrap().width(), 764);
+
+        // Assert a layout that doesn't draw, or shares an SVG's name, fails the load
+        std::fs::write(
+            dir.join("portfolio.svg"),
+            "<svg width=\"1\" height=\"1\"></svg>",
+        )
+        .unwrap();
+        assert!(matches!(
+            ImageTemplates::load(&dir, Path::new("assets")),
+            Err(ImageError::Template(..))
+        ));
+        std::fs::remove_file(dir.join("portfolio.svg")).unwrap();
+        std::fs::write(
+            dir.join("bad.json"),
+            r#"{"width": 4, "height": 4, "root": {"type": "image", "href": "nope.png",
+                "width": 4, "height": 4}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            ImageTemplates::load(&dir, Path::new("assets")),
+            Err(ImageError::Template(..))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod leaderboard_tests {
+    use crate::errors::ImageError;
+    use crate::images::font::Fonts;
+    use crate::images::leaderboard::{truncate_to_width, Page};
+    use crate::images::svg::{leaderboard_rows, rasterize, AVATAR_PLACEHOLDER};
+    use crate::images::{AssetImages, LiveData};
+    use crate::leaderboard::{
+        fetch_leaderboard, format_amount, Leaderboard, LeaderboardRow, MAX_ROWS,
+    };
+    use crate::tests::spawn_mock_server;
+    use actix_web::{web, HttpResponse};
+    use image::{Rgb, RgbImage};
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // A feed that answers out of rank order and counts how often it's asked
+    async fn mock_feed(requests: Arc<AtomicUsize>) -> String {
+        spawn_mock_server(move |cfg| {
+            let requests = requests.clone();
+            cfg.route(
+                "/leaderboard",
+                web::get().to(move || {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    async {
+                        HttpResponse::Ok().json(serde_json::json!({ "rows": [
+                            { "rank": 2, "username": "bob", "amount": 900 },
+                            { "rank": 1, "username": "alice", "amount": 1200.5, "fid": 3 },
+                        ]}))
+                    }
+                }),
+            )
+            .route(
+                "/many",
+                web::get().to(|| async {
+                    let rows: Vec<_> = (1..=MAX_ROWS + 5)
+                        .map(|rank| {
+                            serde_json::json!({ "rank": rank, "username": "a".repeat(100), "amount": 1 })
+                        })
+                        .collect();
+                    HttpResponse::Ok().json(serde_json::json!({ "rows": rows }))
+                }),
+            );
+        })
+        .await
+    }
+
+    fn rows(count: u32) -> Vec<LeaderboardRow> {
+        (1..=count)
+            .map(|rank| LeaderboardRow {
+                rank,
+                username: format!("user{}", rank),
+                amount: 1000.0 / rank as f64,
+                fid: Some(rank as u64),
+            })
+            .collect()
+    }
+
+    fn draw(page: &str, rows: Vec<LeaderboardRow>, avatars: HashMap<u64, &RgbImage>) -> RgbImage {
+        let svg = format!(
+            r##"<svg width="400" height="300"><leaderboard width="400" height="300" rows="2"
+            page="{}" fill="#000" accent="#f00" muted="#888" background="#ccc"/></svg>"##,
+            page
+        );
+        let live = LiveData {
+            leaderboard: Some(Arc::new(rows)),
+            leaderboard_avatars: avatars,
+            ..LiveData::default()
+        };
+        rasterize(
+            &svg,
+            &AssetImages::new(Path::new("assets")),
+            &Fonts::default(),
+            &live,
+        )
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_leaderboard() {
+        let feed = mock_feed(Arc::default()).await;
+        let client = reqwest::Client::new();
+
+        // Assert rows come back in rank order, with their fids when given
+        let rows = fetch_leaderboard(&client, &format!("{}/leaderboard", feed))
+            .await
+            .unwrap();
+        let ranked: Vec<_> = rows
+            .iter()
+            .map(|row| (row.username.as_str(), row.fid))
+            .collect();
+        assert_eq!(ranked, [("alice", Some(3)), ("bob", None)]);
+
+        // Assert a huge board is cut to the rows that can be paged to, and long names
+        // before they're measured
+        let rows = fetch_leaderboard(&client, &format!("{}/many", feed))
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), MAX_ROWS);
+        assert_eq!(rows[0].username.len(), 64);
+    }
+
+    #[actix_web::test]
+    async fn test_leaderboard_reuses_fetches() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let feed = mock_feed(requests.clone()).await;
+        let client = reqwest::Client::new();
+        let leaderboard = Leaderboard::new(60);
+        let url = format!("{}/leaderboard", feed);
+
+        // Assert pages drawn within the TTL share one fetch
+        for _ in 0..2 {
+            let rows = leaderboard.get(&client, Some(&url)).await;
+            assert_eq!(rows.unwrap().len(), 2);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Assert a failing feed, or none configured, leaves the rows out
+        let missing = format!("{}/missing", feed);
+        assert!(Leaderboard::new(60)
+            .get(&client, Some(&missing))
+            .await
+            .is_none());
+        assert!(leaderboard.get(&client, None).await.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_format_amount_and_truncate() {
+        assert_eq!(format_amount(1234567.0), "1,234,567");
+        assert_eq!(format_amount(1200.5), "1,200.5");
+        assert_eq!(format_amount(0.126), "0.13");
+        assert_eq!(format_amount(-999.0), "-999");
+
+        // Assert names are cut to fit with an ellipsis, and left alone when they fit
+        let fonts = Fonts::default();
+        assert_eq!(truncate_to_width(&fonts, "alice", 8.0, None, 40.0), "alice");
+        assert_eq!(
+            truncate_to_width(&fonts, "alexander", 8.0, None, 48.0),
+            "ale..."
+        );
+        assert_eq!(truncate_to_width(&fonts, "alice", 8.0, None, 32.0), "a...");
+        assert_eq!(truncate_to_width(&fonts, "alice", 8.0, None, 24.0), "");
+    }
+
+    #[actix_web::test]
+    async fn test_rasterize_draws_leaderboard_pages() {
+        // Three bands of 100 pixels: two rows, then the footer with its arrows
+        let pfp = RgbImage::from_pixel(8, 8, Rgb([0, 0, 255]));
+        let avatars = HashMap::from([(1, &pfp)]);
+
+        // Assert the first page stripes its first row and draws the pfp it has, a gray
+        // circle for the one it doesn't, and only a next arrow
+        let img = draw("", rows(5), avatars);
+        assert_eq!(*img.get_pixel(399, 1), Rgb([0xcc, 0xcc, 0xcc]));
+        assert_eq!(*img.get_pixel(399, 101), Rgb([255, 255, 255]));
+        assert!((0..400).any(|x| *img.get_pixel(x, 50) == Rgb([0, 0, 255])));
+        assert!((0..400).any(|x| *img.get_pixel(x, 150) == AVATAR_PLACEHOLDER));
+        let red_in = |img: &RgbImage, xs: std::ops::Range<u32>| {
+            xs.into_iter()
+                .any(|x| *img.get_pixel(x, 250) == Rgb([255, 0, 0]))
+        };
+        assert!(!red_in(&img, 0..100));
+        assert!(red_in(&img, 300..400));
+
+        // Assert the last page points back only, and a page past the end draws no rows
+        let img = draw("3", rows(5), HashMap::new());
+        assert!(red_in(&img, 0..100));
+        assert!(!red_in(&img, 300..400));
+        let img = draw("4", rows(5), HashMap::new());
+        assert!(!(0..400).any(|x| *img.get_pixel(x, 50) == AVATAR_PLACEHOLDER));
+    }
+
+    #[actix_web::test]
+    async fn test_leaderboard_rows() {
+        // Assert the page a template shows is read from its first <leaderboard>
+        let svg = r#"<svg width="9" height="9"><leaderboard rows="6" page="2"/></svg>"#;
+        let page = leaderboard_rows(svg).unwrap().unwrap();
+        assert_eq!(page, Page { rows: 6, page: 2 });
+        assert_eq!(page.range(), 6..12);
+        assert_eq!(page.count(13), 3);
+        assert_eq!(
+            leaderboard_rows(r#"<svg width="9" height="9"/>"#).unwrap(),
+            None
+        );
+
+        // Assert pages from 0, or of more rows than fit, are refused
+        for bad in [r#"page="0""#, r#"rows="11""#, r#"page="two""#] {
+            let svg = format!(r#"<svg width="9" height="9"><leaderboard {}/></svg>"#, bad);
+            assert!(
+                matches!(leaderboard_rows(&svg), Err(ImageError::Svg(_))),
+                "{}",
+                bad
+            );
+        }
+    }
+}
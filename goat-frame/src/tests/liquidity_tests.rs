@@ -0,0 +1,164 @@
+#[cfg(test)]
+mod liquidity_tests {
+    use crate::liquidity::quote_token;
+    use crate::tests::spawn_mock_server;
+    use crate::tx::{decode_amount, encode_amount, function_selector, mul_div};
+    use crate::{handle_liquidity_approve_tx, handle_liquidity_tx, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+
+    const ROUTER: &str = "0x00000000000000000000000000000000000000aa";
+    const WRAPPED_GOAT: &str = "0x00000000000000000000000000000000000000bb";
+    const TOKEN: &str = "0x00000000000000000000000000000000000000cc";
+    const PAIR: &str = "0x00000000000000000000000000000000000000dd";
+    const WALLET: &str = "0x1111111111111111111111111111111111111111";
+    const APPROVED_WALLET: &str = "0x2222222222222222222222222222222222222222";
+
+    // Mock RPC node holding 10 GOAT for every wallet, a pair of 100 GOAT to 400 of the
+    // token, and an unlimited allowance for APPROVED_WALLET only
+    async fn rpc_with_pair() -> String {
+        spawn_mock_server(|cfg| {
+            cfg.route(
+                "/",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    let result = match body["method"].as_str() {
+                        Some("eth_getBalance") => "0x8ac7230489e80000".to_string(),
+                        Some("eth_call") => {
+                            let to = body["params"][0]["to"].as_str().unwrap();
+                            let data = body["params"][0]["data"].as_str().unwrap();
+                            let output = if to == PAIR {
+                                assert_eq!(
+                                    &data[2..],
+                                    hex::encode(function_selector("getReserves()"))
+                                );
+                                let mut output = encode_amount(100 * 10u128.pow(18)).to_vec();
+                                output.extend_from_slice(&encode_amount(400 * 10u128.pow(18)));
+                                output.extend_from_slice(&encode_amount(0));
+                                output
+                            } else {
+                                assert_eq!(to, TOKEN);
+                                if data.contains(&APPROVED_WALLET[2..]) {
+                                    vec![0xff; 32]
+                                } else {
+                                    encode_amount(0).to_vec()
+                                }
+                            };
+                            format!("0x{}", hex::encode(output))
+                        }
+                        method => panic!("unexpected {:?}", method),
+                    };
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": result
+                    }))
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_liquidity_transactions() {
+        let config = web::Data::new(Config {
+            rpc_url: Some(rpc_with_pair().await),
+            swap_router: Some(ROUTER.to_string()),
+            swap_wrapped_goat: Some(WRAPPED_GOAT.to_string()),
+            swap_token: Some(TOKEN.to_string()),
+            swap_slippage_bps: 50,
+            liquidity_pair: Some(PAIR.to_string()),
+            ..Config::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/liquidity", web::post().to(handle_liquidity_tx))
+                .route(
+                    "/api/tx/liquidity/approve",
+                    web::post().to(handle_liquidity_approve_tx),
+                ),
+        )
+        .await;
+        let post = |uri: &str, wallet: &str, amount: &str| {
+            test::TestRequest::post()
+                .uri(uri)
+                .set_json(serde_json::json!({
+                    "untrustedData": {
+                        "buttonIndex": 2,
+                        "fid": 42,
+                        "address": wallet,
+                        "inputText": amount
+                    }
+                }))
+                .to_request()
+        };
+
+        // Assert a wallet that hasn't approved the token is told to, with the amount
+        let resp = test::call_service(&app, post("/api/tx/liquidity", WALLET, "1.5")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "Approve 6 tokens for the pool first");
+
+        // Assert Approve lets the router take the quoted tokens plus the slippage
+        let resp = test::call_service(&app, post("/api/tx/liquidity/approve", WALLET, "1.5")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["params"]["to"], TOKEN);
+        assert_eq!(body["params"]["value"], "0");
+        let data = hex::decode(&body["params"]["data"].as_str().unwrap()[2..]).unwrap();
+        assert_eq!(data[..4], function_selector("approve(address,uint256)"));
+        assert!(hex::encode(&data[4..36]).ends_with(&ROUTER[2..]));
+        assert_eq!(data[36..68], encode_amount(6_030_000_000_000_000_000));
+
+        // Assert an approved wallet pays the GOAT to the router with the paired tokens
+        let resp =
+            test::call_service(&app, post("/api/tx/liquidity", APPROVED_WALLET, "1.5")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["params"]["to"], ROUTER);
+        assert_eq!(body["params"]["value"], "1500000000000000000");
+        let data = hex::decode(&body["params"]["data"].as_str().unwrap()[2..]).unwrap();
+        let selector =
+            function_selector("addLiquidityETH(address,uint256,uint256,uint256,address,uint256)");
+        assert_eq!(data[..4], selector);
+        let word = |i: usize| &data[4 + 32 * i..4 + 32 * (i + 1)];
+        assert!(hex::encode(word(0)).ends_with(&TOKEN[2..]));
+        assert_eq!(word(1), encode_amount(6_000_000_000_000_000_000));
+        assert_eq!(word(2), encode_amount(5_970_000_000_000_000_000));
+        assert_eq!(word(3), encode_amount(1_492_500_000_000_000_000));
+        assert!(hex::encode(word(4)).ends_with(&APPROVED_WALLET[2..]));
+
+        // Assert approving again, no amount, or more than the wallet holds is refused
+        for (uri, wallet, amount) in [
+            ("/api/tx/liquidity/approve", APPROVED_WALLET, "1.5"),
+            ("/api/tx/liquidity", APPROVED_WALLET, ""),
+            ("/api/tx/liquidity", APPROVED_WALLET, "11"),
+        ] {
+            let resp = test::call_service(&app, post(uri, wallet, amount)).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(body["message"].is_string());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_quotes_and_amounts() {
+        // Assert the token side follows the reserves, rounding down
+        assert_eq!(quote_token(3, 2, 5), Some(7));
+        assert_eq!(
+            quote_token(10u128.pow(30), 1 << 111, 1 << 111),
+            Some(10u128.pow(30))
+        );
+        assert_eq!(quote_token(1, 0, 5), None);
+
+        // Assert the product can pass u128 on the way, but not the result
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+        assert_eq!(mul_div(u128::MAX, 6, 3), None);
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 90), Some(1 << 110));
+
+        // Assert an unlimited allowance reads as the most there is
+        assert_eq!(decode_amount(&[0xff; 32], 0), Some(u128::MAX));
+        assert_eq!(decode_amount(&encode_amount(9), 0), Some(9));
+        assert_eq!(decode_amount(&encode_amount(9), 1), None);
+    }
+}
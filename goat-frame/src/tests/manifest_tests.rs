@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod manifest_tests {
+    use crate::errors::ConfigError;
+    use crate::manifest::farcaster_manifest;
+    use crate::Config;
+    use actix_web::{http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn test_manifest_describes_the_store() {
+        let config = Config {
+            domain: "https://frame.example.com".to_string(),
+            account_association_header: Some("eyJoZWFkZXIiOjF9".to_string()),
+            account_association_payload: Some("eyJkb21haW4iOjF9".to_string()),
+            account_association_signature: Some("MHhhYmM".to_string()),
+            ..Config::default()
+        };
+        let app = test::init_service(App::new().app_data(web::Data::new(config)).route(
+            "/.well-known/farcaster.json",
+            web::get().to(farcaster_manifest),
+        ))
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/.well-known/farcaster.json")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+
+        // Assert the manifest is built from the configured domain and association
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "accountAssociation": {
+                    "header": "eyJoZWFkZXIiOjF9",
+                    "payload": "eyJkb21haW4iOjF9",
+                    "signature": "MHhhYmM"
+                },
+                "frame": {
+                    "version": "1",
+                    "name": "GOAT Store",
+                    "iconUrl": "https://frame.example.com/assets/main.png",
+                    "homeUrl": "https://frame.example.com/",
+                    "splashImageUrl": "https://frame.example.com/assets/main.png",
+                    "splashBackgroundColor": "#ffffff",
+                    "webhookUrl": "https://frame.example.com/api/webhook"
+                }
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_manifest_without_association() {
+        let config = Config {
+            domain: "https://frame.example.com".to_string(),
+            ..Config::default()
+        };
+        let app = test::init_service(App::new().app_data(web::Data::new(config)).route(
+            "/.well-known/farcaster.json",
+            web::get().to(farcaster_manifest),
+        ))
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/.well-known/farcaster.json")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert an unconfigured association is left out rather than sent empty
+        assert!(body.get("accountAssociation").is_none());
+        assert_eq!(body["frame"]["name"], "GOAT Store");
+    }
+
+    #[actix_web::test]
+    async fn test_partial_association_is_rejected() {
+        let result = Config::from_iter(vec![
+            (
+                "DOMAIN".to_string(),
+                "https://frame.example.com".to_string(),
+            ),
+            (
+                "ACCOUNT_ASSOCIATION_HEADER".to_string(),
+                "eyJoZWFkZXIiOjF9".to_string(),
+            ),
+        ]);
+
+        // Assert a header without its payload and signature fails at startup
+        assert!(matches!(result, Err(ConfigError::Invalid(_))));
+    }
+}
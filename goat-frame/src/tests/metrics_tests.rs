@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod metrics_tests {
+    use crate::assets::AssetHashes;
+    use crate::error_image::ErrorImages;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::{self, Metrics};
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::ReplayGuard;
+    use crate::tests::{noop_analytics, test_registry};
+    use crate::{handle_frame, Config};
+    use actix_web::{test, web, App};
+
+    fn click(button_index: usize) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": button_index
+                }
+            }))
+    }
+
+    #[actix_web::test]
+    async fn test_metrics_count_clicks_and_errors() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        // Create a mock application with the same routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame))
+                .route("/metrics", web::get().to(metrics::metrics)),
+        )
+        .await;
+
+        // Two presses of the first button and one of a button that doesn't exist
+        test::call_service(&app, click(1).to_request()).await;
+        test::call_service(&app, click(1).to_request()).await;
+        test::call_service(&app, click(9).to_request()).await;
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        // Assert every request was counted, with the bad index under its own label
+        assert!(body.contains("frame_requests_total 3\n"));
+        assert!(body.contains("frame_button_clicks_total{button=\"1\"} 2\n"));
+        assert!(body.contains("frame_errors_total{reason=\"invalid_button\"} 1\n"));
+        assert!(body.contains("frame_errors_total{reason=\"processing\"} 0\n"));
+    }
+
+    #[actix_web::test]
+    async fn test_metrics_ignore_out_of_range_clicks() {
+        let metrics = Metrics::default();
+        metrics.record_click(0);
+        metrics.record_click(5);
+        metrics.record_click(4);
+
+        // Assert only real button positions show up as clicks
+        let body = metrics.render();
+        assert!(body.contains("frame_button_clicks_total{button=\"4\"} 1\n"));
+        assert!(!body.contains("button=\"5\""));
+        assert!(!body.contains("button=\"0\""));
+    }
+}
@@ -0,0 +1,103 @@
+// Each test file wraps its cases in a module named after the file
+#![allow(clippy::module_inception)]
+
+use crate::analytics::{AnalyticsSink, NoopSink};
+use crate::frame_logic::registry::FrameRegistry;
+use crate::frame_logic::{FrameContext, UserContext};
+use actix_web::{web, App, HttpServer};
+use std::path::Path;
+use std::sync::Arc;
+
+mod actions_tests;
+mod analytics_tests;
+mod app_key_tests;
+mod apr_tests;
+mod assets_tests;
+mod auction_tests;
+mod auth_tests;
+mod bridge_tests;
+mod client_context_tests;
+mod client_detection_tests;
+mod config_tests;
+mod cors_tests;
+mod countdown_tests;
+mod debug_headers_tests;
+mod emoji_tests;
+mod error_frame_tests;
+mod error_image_tests;
+mod errors_tests;
+mod experiments_tests;
+mod font_tests;
+mod frame_check_tests;
+mod frame_html_tests;
+mod frame_logic_tests;
+mod gif_tests;
+mod gifts_tests;
+mod health_tests;
+mod i18n_tests;
+mod idempotency_tests;
+mod images_tests;
+mod integration_tests;
+mod layout_tests;
+mod leaderboard_tests;
+mod liquidity_tests;
+mod manifest_tests;
+mod metrics_tests;
+mod moxie_tests;
+mod notifications_tests;
+mod price_tests;
+mod profiles_tests;
+mod qr_tests;
+mod rate_limit_tests;
+mod receipts_tests;
+mod redirect_tests;
+mod replay_tests;
+mod rewards_tests;
+mod secp256k1_tests;
+mod shutdown_tests;
+mod signers_tests;
+mod state_tests;
+mod swaps_tests;
+mod theme_tests;
+mod tls_tests;
+mod trace_tests;
+mod tx_tests;
+mod validation_tests;
+mod watermark_tests;
+mod webp_tests;
+
+// Starts a throwaway server (e.g. a fake Farcaster hub) and returns its base URL
+pub async fn spawn_mock_server<F>(configure: F) -> String
+where
+    F: Fn(&mut web::ServiceConfig) + Clone + Send + 'static,
+{
+    let server = HttpServer::new(move || App::new().configure(configure.clone()))
+        .workers(1)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))
+        .expect("Mock server bind");
+    let addr = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    format!("http://{}", addr)
+}
+
+// The frames shipped in frames.toml, so tests exercise the real configuration
+pub fn test_registry() -> FrameRegistry {
+    FrameRegistry::load(Path::new("frames.toml"), Path::new("assets")).expect("Shipped frames.toml")
+}
+
+// A sink that drops click events, for apps that don't care about analytics
+pub fn noop_analytics() -> web::Data<dyn AnalyticsSink> {
+    web::Data::from(Arc::new(NoopSink) as Arc<dyn AnalyticsSink>)
+}
+
+// A press of the given button, as process_button receives it
+pub fn press(button_index: usize, input_text: Option<&str>, user: &UserContext) -> FrameContext {
+    FrameContext {
+        button_index,
+        user: user.clone(),
+        input_text: input_text.map(str::to_string),
+        ..FrameContext::default()
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod moxie_tests {
+    use crate::moxie::{fetch_moxie_price, format_moxie_price, MoxiePrice};
+    use crate::tests::spawn_mock_server;
+    use actix_web::{web, HttpResponse};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Mock quote service counting the quotes asked for
+    async fn mock_quote(requests: Arc<AtomicUsize>) -> String {
+        spawn_mock_server(move |cfg| {
+            let requests = requests.clone();
+            cfg.route(
+                "/price",
+                web::get().to(move || {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    async { HttpResponse::Ok().json(serde_json::json!({ "price": 0.043 })) }
+                }),
+            )
+            .route(
+                "/negative",
+                web::get()
+                    .to(|| async { HttpResponse::Ok().json(serde_json::json!({ "price": -1 })) }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_moxie_price_reuses_fetches() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let quote = mock_quote(requests.clone()).await;
+        let client = reqwest::Client::new();
+        let moxie = MoxiePrice::new(60);
+        let url = format!("{}/price", quote);
+
+        // Assert images drawn within the TTL share one quote
+        for _ in 0..2 {
+            assert_eq!(moxie.get(&client, Some(&url)).await, Some(0.043));
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Assert a nonsense price, a failing quote or none configured leaves it out
+        let negative = format!("{}/negative", quote);
+        assert!(fetch_moxie_price(&client, &negative).await.is_err());
+        let missing = format!("{}/missing", quote);
+        assert!(MoxiePrice::new(60)
+            .get(&client, Some(&missing))
+            .await
+            .is_none());
+        assert!(moxie.get(&client, None).await.is_none());
+    }
+
+    #[test]
+    fn test_format_moxie_price() {
+        // Assert small prices keep three significant digits, larger ones two decimals
+        assert_eq!(format_moxie_price(0.043), "0.043 MOXIE");
+        assert_eq!(format_moxie_price(0.0012345), "0.00123 MOXIE");
+        assert_eq!(format_moxie_price(0.5), "0.5 MOXIE");
+        assert_eq!(format_moxie_price(12.345), "12.35 MOXIE");
+        assert_eq!(format_moxie_price(3.0), "3 MOXIE");
+        assert_eq!(format_moxie_price(0.0), "0 MOXIE");
+    }
+}
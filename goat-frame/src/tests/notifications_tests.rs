@@ -0,0 +1,267 @@
+#[cfg(test)]
+mod notifications_tests {
+    use crate::notifications::{
+        send, webhook, Notification, NotificationDetails, NotificationStore,
+    };
+    use crate::tests::spawn_mock_server;
+    use crate::Config;
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use base64::prelude::{Engine, BASE64_URL_SAFE_NO_PAD};
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("goat-frame-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    fn details(url: &str, token: &str) -> NotificationDetails {
+        NotificationDetails {
+            url: url.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    // Config for a webhook that trusts the fid in each event's header
+    fn trusting_config() -> web::Data<Config> {
+        web::Data::new(Config {
+            skip_validation: true,
+            ..Config::default()
+        })
+    }
+
+    // Builds a webhook body the way clients sign it, minus a real signature
+    fn signed_event(fid: u64, payload: serde_json::Value) -> serde_json::Value {
+        let encode = |value: serde_json::Value| BASE64_URL_SAFE_NO_PAD.encode(value.to_string());
+        serde_json::json!({
+            "header": encode(serde_json::json!({ "fid": fid, "type": "app_key", "key": "0x00" })),
+            "payload": encode(payload),
+            "signature": "c2ln"
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_tracks_added_and_removed() {
+        let store = web::Data::new(NotificationStore::in_memory());
+        let app = test::init_service(
+            App::new()
+                .app_data(store.clone())
+                .app_data(trusting_config())
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/webhook", web::post().to(webhook)),
+        )
+        .await;
+
+        let added = signed_event(
+            42,
+            serde_json::json!({
+                "event": "frame_added",
+                "notificationDetails": {
+                    "url": "https://api.warpcast.com/v1/frame-notifications",
+                    "token": "abc"
+                }
+            }),
+        );
+        let req = test::TestRequest::post()
+            .uri("/api/webhook")
+            .set_json(&added)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Assert the token is stored against the fid from the header
+        assert_eq!(
+            store.get(42),
+            Some(details(
+                "https://api.warpcast.com/v1/frame-notifications",
+                "abc"
+            ))
+        );
+
+        let removed = signed_event(42, serde_json::json!({ "event": "frame_removed" }));
+        let req = test::TestRequest::post()
+            .uri("/api/webhook")
+            .set_json(&removed)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Assert removing the mini app forgets the token
+        assert_eq!(store.get(42), None);
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_refuses_unknown_notification_urls() {
+        let store = web::Data::new(NotificationStore::in_memory());
+        let app = test::init_service(
+            App::new()
+                .app_data(store.clone())
+                .app_data(trusting_config())
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/webhook", web::post().to(webhook)),
+        )
+        .await;
+
+        // Assert only https URLs on a known client's host are stored, so no event can
+        // have us post to a server of its choosing
+        for url in [
+            "http://api.warpcast.com/v1/frame-notifications",
+            "https://169.254.169.254/latest/meta-data",
+            "https://api.warpcast.com.attacker.example/notify",
+            "not a url",
+        ] {
+            let enabled = signed_event(
+                42,
+                serde_json::json!({
+                    "event": "notifications_enabled",
+                    "notificationDetails": { "url": url, "token": "abc" }
+                }),
+            );
+            let req = test::TestRequest::post()
+                .uri("/api/webhook")
+                .set_json(&enabled)
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", url);
+            assert_eq!(store.get(42), None);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_rejects_malformed_events() {
+        let store = web::Data::new(NotificationStore::in_memory());
+        let app = test::init_service(
+            App::new()
+                .app_data(store.clone())
+                .app_data(trusting_config())
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/webhook", web::post().to(webhook)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/webhook")
+            .set_json(serde_json::json!({ "header": "not base64!", "payload": "" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert an undecodable event is refused
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_refuses_unsigned_events() {
+        let store = web::Data::new(NotificationStore::in_memory());
+        let app = test::init_service(
+            App::new()
+                .app_data(store.clone())
+                .app_data(web::Data::new(Config::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/webhook", web::post().to(webhook)),
+        )
+        .await;
+
+        let forged = signed_event(
+            42,
+            serde_json::json!({
+                "event": "notifications_enabled",
+                "notificationDetails": {
+                    "url": "https://attacker.example.com/notify",
+                    "token": "stolen"
+                }
+            }),
+        );
+        let req = test::TestRequest::post()
+            .uri("/api/webhook")
+            .set_json(&forged)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert a bad signature is refused before the hub is asked and nothing is stored
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(store.get(42), None);
+    }
+
+    #[actix_web::test]
+    async fn test_store_persists_tokens() {
+        let path = temp_path();
+        let store = NotificationStore::open(&path).unwrap();
+        store.set(1, Some(details("https://client.example.com/notify", "one")));
+        store.set(2, Some(details("https://client.example.com/notify", "two")));
+        store.set(1, None);
+
+        // Assert a reopened store sees the saved tokens
+        let reopened = NotificationStore::open(&path).unwrap();
+        assert_eq!(reopened.get(1), None);
+        assert_eq!(
+            reopened.get(2),
+            Some(details("https://client.example.com/notify", "two"))
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_send_posts_and_drops_invalid_tokens() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let seen = received.clone();
+        let base = spawn_mock_server(move |cfg| {
+            let seen = seen.clone();
+            cfg.route(
+                "/notify",
+                web::post().to(move |body: web::Json<serde_json::Value>| {
+                    let seen = seen.clone();
+                    async move {
+                        let token = body["tokens"][0].as_str().unwrap_or_default().to_string();
+                        seen.lock().unwrap().push(body.into_inner());
+                        let (successful, invalid) = if token == "good" {
+                            (vec![token], vec![])
+                        } else {
+                            (vec![], vec![token])
+                        };
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "result": {
+                                "successfulTokens": successful,
+                                "invalidTokens": invalid,
+                                "rateLimitedTokens": []
+                            }
+                        }))
+                    }
+                }),
+            );
+        })
+        .await;
+
+        let config = Config {
+            domain: "https://frame.example.com".to_string(),
+            ..Config::default()
+        };
+        let client = reqwest::Client::new();
+        let store = NotificationStore::in_memory();
+        store.set(1, Some(details(&format!("{}/notify", base), "good")));
+        store.set(2, Some(details(&format!("{}/notify", base), "stale")));
+
+        let notification = Notification::outbid(1700000000, 150 * 10u128.pow(18), &config);
+        assert!(send(&client, &store, 1, &notification).await.unwrap());
+
+        // Assert the request carries the notification and the user's token
+        assert_eq!(
+            received.lock().unwrap()[0],
+            serde_json::json!({
+                "notificationId": "outbid-1700000000-150000000000000000000",
+                "title": "You've been outbid",
+                "body": "The highest bid is now 150 GOAT. Bid again to win it back.",
+                "targetUrl": "https://frame.example.com/",
+                "tokens": ["good"]
+            })
+        );
+
+        // Assert a token the client rejects is forgotten
+        assert!(!send(&client, &store, 2, &notification).await.unwrap());
+        assert_eq!(store.get(2), None);
+        assert!(store.get(1).is_some());
+
+        // Assert users without a token are skipped
+        assert!(!send(&client, &store, 3, &notification).await.unwrap());
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod price_tests {
+    use crate::price::{change, fetch_prices, format_price, PriceHistory, PriceRange};
+    use crate::tests::spawn_mock_server;
+    use actix_web::{web, HttpResponse};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // A price feed that answers out of order and counts how often it's asked
+    async fn mock_feed(requests: Arc<AtomicUsize>) -> String {
+        spawn_mock_server(move |cfg| {
+            let requests = requests.clone();
+            cfg.route(
+                "/prices/{range}",
+                web::get().to(move |range: web::Path<String>| {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        match range.as_str() {
+                            "24h" => HttpResponse::Ok().json(serde_json::json!({
+                                "prices": [[300, 0.05], [100, 0.04], [200, 0.045]]
+                            })),
+                            _ => HttpResponse::Ok().json(serde_json::json!({ "prices": [] })),
+                        }
+                    }
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_prices_orders_by_time() {
+        let feed = mock_feed(Arc::default()).await;
+        let url = format!("{}/prices/{{range}}", feed);
+        let client = reqwest::Client::new();
+
+        // Assert the range is filled in and prices come back oldest first
+        let prices = fetch_prices(&client, &url, PriceRange::Day).await.unwrap();
+        assert_eq!(prices, vec![0.04, 0.045, 0.05]);
+
+        // Assert an empty history is an error rather than an empty chart
+        assert!(fetch_prices(&client, &url, PriceRange::Week).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_price_history_reuses_fetches() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let feed = mock_feed(requests.clone()).await;
+        let url = format!("{}/prices/{{range}}", feed);
+        let client = reqwest::Client::new();
+        let history = PriceHistory::new(60);
+
+        // Assert charts drawn within the TTL share one fetch
+        for _ in 0..2 {
+            let prices = history.get(&client, Some(&url), PriceRange::Day).await;
+            assert_eq!(prices.unwrap().len(), 3);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Assert a failing feed, or none configured, leaves the chart out
+        assert!(history
+            .get(&client, Some(&url), PriceRange::Week)
+            .await
+            .is_none());
+        assert!(history.get(&client, None, PriceRange::Day).await.is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_change_and_format_price() {
+        assert_eq!(change(&[0.04, 0.05]).as_deref(), Some("+25.0%"));
+        assert_eq!(change(&[2.0, 1.99]).as_deref(), Some("-0.5%"));
+        assert_eq!(change(&[0.0, 1.0]), None);
+        assert_eq!(change(&[]), None);
+
+        assert_eq!(format_price(0.04321), "0.0432");
+        assert_eq!(format_price(12.345), "12.35");
+        assert_eq!(format_price(1234.5), "1234");
+        assert_eq!(PriceRange::parse("7d"), Some(PriceRange::Week));
+        assert_eq!(PriceRange::parse("1y"), None);
+    }
+}
@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod profiles_tests {
+    use crate::profiles::{
+        fetch_avatar, fetch_fid_by_username, fetch_profile, fetch_verified_address, Profile,
+        ProfileCache,
+    };
+    use crate::tests::spawn_mock_server;
+    use actix_web::{web, HttpResponse};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // A hub that knows fid 3, @dwr, and counts how often it's asked for their profile
+    async fn mock_hub(requests: Arc<AtomicUsize>) -> String {
+        spawn_mock_server(move |cfg| {
+            let requests = requests.clone();
+            cfg.route(
+                "/v1/userDataByFid",
+                web::get().to(move || {
+                    requests.fetch_add(1, Ordering::SeqCst);
+                    async {
+                        let data = |kind: &str, value: &str| {
+                            serde_json::json!({
+                                "data": {
+                                    "type": "MESSAGE_TYPE_USER_DATA_ADD",
+                                    "fid": 3,
+                                    "userDataBody": { "type": kind, "value": value }
+                                }
+                            })
+                        };
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "messages": [
+                                data("USER_DATA_TYPE_USERNAME", "dwr"),
+                                data("USER_DATA_TYPE_DISPLAY", "Dan Romero"),
+                                data("USER_DATA_TYPE_PFP", "http://127.0.0.1/pfp.png"),
+                                data("USER_DATA_TYPE_BIO", "Working on Farcaster"),
+                            ],
+                            "nextPageToken": ""
+                        }))
+                    }
+                }),
+            )
+            .route(
+                "/v1/userNameProofByName",
+                web::get().to(|query: web::Query<HashMap<String, String>>| async move {
+                    match query.get("name").map(String::as_str) {
+                        Some("dwr") => HttpResponse::Ok().json(serde_json::json!({
+                            "name": "dwr",
+                            "fid": 3,
+                            "type": "USERNAME_TYPE_FNAME"
+                        })),
+                        _ => HttpResponse::NotFound().json(serde_json::json!({
+                            "errCode": "not_found"
+                        })),
+                    }
+                }),
+            )
+            .route(
+                "/v1/verificationsByFid",
+                web::get().to(|| async {
+                    let verification = |protocol: &str, address: &str| {
+                        serde_json::json!({
+                            "data": {
+                                "type": "MESSAGE_TYPE_VERIFICATION_ADD_ETH_ADDRESS",
+                                "fid": 3,
+                                "verificationAddAddressBody": {
+                                    "address": address,
+                                    "protocol": protocol
+                                }
+                            }
+                        })
+                    };
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "messages": [
+                            verification("PROTOCOL_SOLANA", "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"),
+                            verification("PROTOCOL_ETHEREUM", "0x00000000000000000000000000000000000000aa"),
+                        ],
+                        "nextPageToken": ""
+                    }))
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_profile_reads_user_data() {
+        let hub = mock_hub(Arc::default()).await;
+
+        let profile = fetch_profile(&reqwest::Client::new(), &hub, 3)
+            .await
+            .unwrap();
+
+        // Assert the name and pfp are picked out of the user data
+        assert_eq!(
+            profile,
+            Profile {
+                username: Some("dwr".to_string()),
+                display_name: Some("Dan Romero".to_string()),
+                pfp_url: Some("http://127.0.0.1/pfp.png".to_string()),
+                address: None,
+            }
+        );
+        assert_eq!(profile.name(), Some("Dan Romero"));
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_verified_address_skips_other_chains() {
+        let hub = mock_hub(Arc::default()).await;
+        let client = reqwest::Client::new();
+
+        // Assert the first Ethereum address is picked, passing over a Solana one
+        let address = fetch_verified_address(&client, &hub, 3).await.unwrap();
+        assert_eq!(
+            address.as_deref(),
+            Some("0x00000000000000000000000000000000000000aa")
+        );
+
+        // Assert the cached profile carries it, for templates showing a balance
+        let cached = ProfileCache::new(60).get(&client, &hub, 3, false).await;
+        assert_eq!(cached.profile.address, address);
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_fid_by_username() {
+        let hub = mock_hub(Arc::default()).await;
+        let client = reqwest::Client::new();
+
+        // Assert a username resolves to its owner, and one nobody owns to nothing
+        assert_eq!(
+            fetch_fid_by_username(&client, &hub, "dwr").await,
+            Ok(Some(3))
+        );
+        assert_eq!(
+            fetch_fid_by_username(&client, &hub, "nobody").await,
+            Ok(None)
+        );
+        assert!(fetch_fid_by_username(&client, "http://127.0.0.1:1", "dwr")
+            .await
+            .is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_profile_cache_reuses_profiles() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let hub = mock_hub(requests.clone()).await;
+        let client = reqwest::Client::new();
+        let cache = ProfileCache::new(60);
+
+        // Assert a second image for the same fid doesn't ask the hub again, and that the
+        // pfp, on a host we won't fetch from, is left off rather than retried
+        for _ in 0..2 {
+            let cached = cache.get(&client, &hub, 3, true).await;
+            assert_eq!(cached.profile.username.as_deref(), Some("dwr"));
+            assert!(cached.avatar.is_none());
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        // Assert an unreachable hub draws the image without a profile
+        let cached = cache.get(&client, "http://127.0.0.1:1", 4, false).await;
+        assert_eq!(cached.profile, Profile::default());
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_avatar_only_fetches_public_https() {
+        let client = reqwest::Client::new();
+        for url in [
+            "http://example.com/pfp.png",
+            "https://127.0.0.1/pfp.png",
+            "https://localhost/pfp.png",
+            "not a url",
+        ] {
+            // Assert the URL is refused before anything is fetched
+            assert!(fetch_avatar(&client, url).await.is_err(), "{}", url);
+        }
+    }
+}
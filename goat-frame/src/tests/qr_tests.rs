@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod qr_tests {
+    use crate::images::font::Fonts;
+    use crate::images::qr::{
+        reed_solomon_divisor, reed_solomon_remainder, QrCode, MAX_QR_BYTES, QUIET_ZONE,
+    };
+    use crate::images::svg::rasterize;
+    use crate::images::{AssetImages, LiveData};
+    use image::Rgb;
+    use std::path::Path;
+
+    // Level M's format strings for masks 0 to 7, as the spec lists them
+    const LEVEL_M_FORMATS: [u32; 8] = [
+        0b101010000010010,
+        0b101000100100101,
+        0b101111001111100,
+        0b101101101001011,
+        0b100010111111001,
+        0b100000011001110,
+        0b100111110010111,
+        0b100101010100000,
+    ];
+
+    // Both copies of the format string, most significant bit first
+    fn format_bits(code: &QrCode) -> (u32, u32) {
+        let size = code.size();
+        let mut first = Vec::new();
+        first.extend((0..6).map(|i| (8, i)));
+        first.extend([(8, 7), (8, 8), (7, 8)]);
+        first.extend((9..15).map(|i| (14 - i, 8)));
+        let mut second: Vec<(usize, usize)> = (0..8).map(|i| (size - 1 - i, 8)).collect();
+        second.extend((8..15).map(|i| (8, size - 15 + i)));
+        let read = |modules: Vec<(usize, usize)>| {
+            modules
+                .iter()
+                .enumerate()
+                .map(|(i, (x, y))| (code.dark(*x, *y) as u32) << i)
+                .sum()
+        };
+        (read(first), read(second))
+    }
+
+    #[test]
+    fn test_reed_solomon_matches_the_spec_example() {
+        // Assert "HELLO WORLD" at 1-Q gets the error correction every QR reference shows
+        let data = [32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236];
+        let ecc = reed_solomon_remainder(&data, &reed_solomon_divisor(13));
+        assert_eq!(
+            ecc,
+            [168, 72, 22, 82, 217, 54, 156, 0, 46, 15, 180, 122, 16]
+        );
+    }
+
+    #[test]
+    fn test_encode_draws_the_fixed_patterns() {
+        let uri = "ethereum:0x1234567890abcdef1234567890abcdef12345678@48816";
+        let code = QrCode::encode(uri.as_bytes()).unwrap();
+
+        // Assert a payment URI fits version 4 at level M
+        let size = code.size();
+        assert_eq!(size, 33);
+
+        // Assert the three finder patterns with their light separators, the timing
+        // patterns, and the module that's always dark
+        for (x, y) in [(0, 0), (size - 7, 0), (0, size - 7)] {
+            assert!(code.dark(x, y) && code.dark(x + 6, y + 6) && code.dark(x + 3, y + 3));
+            assert!(!code.dark(x + 1, y + 1) && code.dark(x + 2, y + 2));
+        }
+        assert!(!code.dark(7, 7) && !code.dark(size - 8, 7) && !code.dark(7, size - 8));
+        for i in 8..size - 8 {
+            assert_eq!(code.dark(i, 6), i % 2 == 0);
+            assert_eq!(code.dark(6, i), i % 2 == 0);
+        }
+        assert!(code.dark(8, size - 8));
+
+        // Assert both copies of the format say level M with one of its masks
+        let (first, second) = format_bits(&code);
+        assert_eq!(first, second);
+        assert!(LEVEL_M_FORMATS.contains(&first), "{:015b}", first);
+    }
+
+    #[test]
+    fn test_encode_marks_versions_from_7() {
+        let code = QrCode::encode(&[b'a'; 120]).unwrap();
+        let size = code.size();
+        assert_eq!(size, 45);
+
+        // Assert both version blocks read 7 with its error correction
+        let version: u32 = (0..18)
+            .map(|i| (code.dark(size - 11 + i % 3, i / 3) as u32) << i)
+            .sum();
+        let mirrored: u32 = (0..18)
+            .map(|i| (code.dark(i / 3, size - 11 + i % 3) as u32) << i)
+            .sum();
+        assert_eq!(version, 0b000111110010010100);
+        assert_eq!(mirrored, version);
+
+        // Assert what can't fit version 10 isn't encoded at all
+        assert!(QrCode::encode(&[b'a'; MAX_QR_BYTES]).is_some());
+        assert!(QrCode::encode(&[b'a'; MAX_QR_BYTES + 1]).is_none());
+    }
+
+    #[test]
+    fn test_rasterize_draws_qr() {
+        let draw = |data: &str| {
+            let svg = format!(
+                r##"<svg width="100" height="100"><rect width="100" height="100" fill="#f00"/>
+                <qr x="10" y="10" size="87" data="{}"/></svg>"##,
+                data
+            );
+            rasterize(
+                &svg,
+                &AssetImages::new(Path::new("assets")),
+                &Fonts::default(),
+                &LiveData::default(),
+            )
+            .unwrap()
+        };
+
+        // Assert the code is drawn whole modules at a time inside its quiet zone: 21
+        // modules and 8 of quiet zone in 87 pixels is 3 pixels a module
+        let img = draw("GOAT");
+        let quiet = 10 + QUIET_ZONE as u32 * 3;
+        assert_eq!(*img.get_pixel(10, 10), Rgb([255, 255, 255]));
+        assert_eq!(*img.get_pixel(quiet, quiet), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(quiet + 3, quiet + 3), Rgb([255, 255, 255]));
+        assert_eq!(*img.get_pixel(5, 5), Rgb([255, 0, 0]));
+
+        // Assert a qr left empty by its placeholder isn't drawn
+        let img = draw("");
+        assert_eq!(*img.get_pixel(50, 50), Rgb([255, 0, 0]));
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod rate_limit_tests {
+    use crate::assets::AssetHashes;
+    use crate::error_image::ErrorImages;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::{RateKey, RateLimiter};
+    use crate::replay::ReplayGuard;
+    use crate::tests::{noop_analytics, test_registry};
+    use crate::{handle_frame, Config};
+    use actix_web::{http::header, http::StatusCode, test, web, App};
+
+    #[actix_web::test]
+    async fn test_frame_requests_past_limit_get_429() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            rate_limit_per_min: 3,
+            ..Config::default()
+        });
+
+        // Create a mock application with the same routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(config.rate_limit_per_min)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let mut statuses = Vec::new();
+        for _ in 0..5 {
+            let req = test::TestRequest::post()
+                .uri("/api/frame")
+                .peer_addr("203.0.113.7:4000".parse().unwrap())
+                .set_json(serde_json::json!({
+                    "untrusted_data": {
+                        "button_index": 1
+                    }
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+            if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                // Assert the client is told when to come back
+                let retry_after: u64 = resp
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .parse()
+                    .unwrap();
+                assert!((1..=60).contains(&retry_after));
+            }
+            statuses.push(resp.status());
+        }
+
+        // Assert the first three clicks are processed and the rest are refused
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::TOO_MANY_REQUESTS,
+            ]
+        );
+
+        // Assert another source still gets through
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .peer_addr("198.51.100.2:4000".parse().unwrap())
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limiter_keys_are_independent() {
+        let limiter = RateLimiter::new(1);
+
+        // Assert each fid has its own budget
+        assert!(limiter.check(RateKey::Fid(1)).is_ok());
+        assert!(limiter.check(RateKey::Fid(1)).is_err());
+        assert!(limiter.check(RateKey::Fid(2)).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limiter_zero_disables() {
+        let limiter = RateLimiter::new(0);
+
+        // Assert a limit of 0 never refuses anything
+        for _ in 0..100 {
+            assert!(limiter.check(RateKey::Ip("127.0.0.1".to_string())).is_ok());
+        }
+    }
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod receipts_tests {
+    use crate::receipts::Receipts;
+    use crate::tests::spawn_mock_server;
+    use crate::tx::TxStatus;
+    use actix_web::{web, HttpResponse};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn hash(byte: &str) -> String {
+        format!("0x{}", byte.repeat(32))
+    }
+
+    // Mock RPC node with 0xaa.. mined, 0xbb.. reverted and 0xcc.. not mined yet,
+    // counting the receipts asked for
+    async fn rpc_with_receipts(calls: Arc<AtomicUsize>) -> String {
+        spawn_mock_server(move |cfg| {
+            let calls = calls.clone();
+            cfg.route(
+                "/",
+                web::post().to(move |body: web::Json<serde_json::Value>| {
+                    let calls = calls.clone();
+                    async move {
+                        assert_eq!(body["method"], "eth_getTransactionReceipt");
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        let result = match body["params"][0].as_str().unwrap_or_default() {
+                            hash if hash.starts_with("0xaa") => {
+                                serde_json::json!({ "status": "0x1" })
+                            }
+                            hash if hash.starts_with("0xbb") => {
+                                serde_json::json!({ "status": "0x0" })
+                            }
+                            _ => serde_json::Value::Null,
+                        };
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": body["id"],
+                            "result": result
+                        }))
+                    }
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_receipts_tell_the_outcome() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let rpc_url = rpc_with_receipts(calls.clone()).await;
+        let client = reqwest::Client::new();
+        let receipts = Receipts::default();
+        let status = |byte: &'static str| {
+            let (receipts, client, rpc_url) = (&receipts, &client, rpc_url.clone());
+            async move { receipts.status(client, Some(&rpc_url), &hash(byte)).await }
+        };
+
+        // Assert the receipt's status decides the outcome, and no receipt is pending
+        assert_eq!(status("aa").await, TxStatus::Confirmed);
+        assert_eq!(status("bb").await, TxStatus::Failed);
+        assert_eq!(status("cc").await, TxStatus::Pending);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Assert a settled outcome is remembered, and a pending one isn't asked for
+        // again straight away
+        assert_eq!(status("aa").await, TxStatus::Confirmed);
+        assert_eq!(status("cc").await, TxStatus::Pending);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_receipts_without_rpc() {
+        let client = reqwest::Client::new();
+        let receipts = Receipts::default();
+
+        // Assert without RPC_URL the transaction is only known to be sent
+        assert_eq!(
+            receipts.status(&client, None, &hash("aa")).await,
+            TxStatus::Sent
+        );
+
+        // Assert an RPC node that can't be reached leaves it pending
+        assert_eq!(
+            receipts
+                .status(&client, Some("http://127.0.0.1:1"), &hash("aa"))
+                .await,
+            TxStatus::Pending
+        );
+    }
+}
@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod redirect_tests {
+    use crate::assets::AssetHashes;
+    use crate::debug_headers::HANDLER_HEADER;
+    use crate::error_image::ErrorImages;
+    use crate::errors::RegistryError;
+    use crate::frame_logic::registry::FrameRegistry;
+    use crate::frame_logic::state::FrameState;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::RateLimiter;
+    use crate::replay::ReplayGuard;
+    use crate::tests::{noop_analytics, test_registry};
+    use crate::{handle_frame, Config};
+    use actix_web::{http::header, http::StatusCode, test, web, App};
+    use std::path::Path;
+
+    // A store frame whose first button bounces to checkout
+    fn checkout_registry(redirect: &str) -> FrameRegistry {
+        let registry = FrameRegistry::from_toml(&format!(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [
+                {{ label = "Checkout", action = {{ type = "post_redirect" }}, redirect = "{}" }},
+                {{ label = "More", target = "more" }},
+            ]
+
+            [frames.store.screens.more]
+            image = "more.png"
+            buttons = [{{ label = "Back", target = "back" }}]
+            "#,
+            redirect
+        ))
+        .unwrap();
+        registry.validate(Path::new("assets")).unwrap();
+        registry
+    }
+
+    fn click(button_index: usize) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": button_index,
+                    "fid": 42
+                }
+            }))
+    }
+
+    #[actix_web::test]
+    async fn test_redirect_button_returns_302() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            checkout_base: Some("https://checkout.example.com".to_string()),
+            redirect_allowlist: vec!["checkout.example.com".to_string()],
+            debug_headers: true,
+            ..Config::default()
+        });
+
+        // Create a mock application with the same routes as in main.rs
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(checkout_registry("/boost")))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, click(1).to_request()).await;
+
+        // Assert the redirect button sends the user to checkout with their fid
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://checkout.example.com/boost?fid=42"
+        );
+
+        // Assert a retry of the press is sent to the same place without handling it again
+        let keyed = || click(1).insert_header(("Idempotency-Key", "checkout-1"));
+        test::call_service(&app, keyed().to_request()).await;
+        let resp = test::call_service(&app, keyed().to_request()).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://checkout.example.com/boost?fid=42"
+        );
+        assert_eq!(resp.headers().get(HANDLER_HEADER).unwrap(), "replay");
+
+        let resp = test::call_service(&app, click(2).to_request()).await;
+
+        // Assert an ordinary button still answers with the next screen
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains("http://localhost/assets/more.png"));
+    }
+
+    #[actix_web::test]
+    async fn test_redirect_to_unlisted_host_is_refused() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            redirect_allowlist: vec!["checkout.example.com".to_string()],
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(checkout_registry(
+                    "https://evil.example.net/phish",
+                )))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let resp = test::call_service(&app, click(1).to_request()).await;
+
+        // Assert no Location is handed out for a host off the allowlist
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(resp.headers().get(header::LOCATION).is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_rewards_moxie_button_opens_moxie() {
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            skip_validation: true,
+            ..Config::default()
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(60)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let mut state = FrameState::default();
+        state.push("rewards");
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 2,
+                    "fid": 42,
+                    "state": state.encode()
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the shipped Rewards screen sends the user on to the Moxie web app
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://moxie.xyz/?fid=42"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_post_redirect_needs_a_redirect() {
+        let registry = FrameRegistry::from_toml(
+            r#"
+            [frames.store.screens.main]
+            image = "main.png"
+            buttons = [{ label = "Checkout", action = { type = "post_redirect" } }]
+            "#,
+        )
+        .unwrap();
+
+        // Assert the missing redirect is caught at startup
+        let err = registry.validate(Path::new("assets")).unwrap_err();
+        assert!(matches!(err, RegistryError::InvalidScreen(..)));
+    }
+}
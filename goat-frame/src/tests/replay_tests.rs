@@ -0,0 +1,197 @@
+#[cfg(test)]
+mod replay_tests {
+    use crate::assets::AssetHashes;
+    use crate::error_image::ErrorImages;
+    use crate::errors::AppError;
+    use crate::idempotency::{ConfirmedTransactions, IdempotencyCache};
+    use crate::metrics::Metrics;
+    use crate::rate_limit::{RateKey, RateLimiter};
+    use crate::replay::{ReplayGuard, FARCASTER_EPOCH};
+    use crate::tests::{noop_analytics, spawn_mock_server, test_registry};
+    use crate::{handle_frame, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    // The current time as a Farcaster timestamp
+    fn farcaster_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            - FARCASTER_EPOCH
+    }
+
+    // Mock hub that vouches for a press of button 1 by fid 42, signed just now
+    fn fresh_hub(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/v1/validateMessage",
+            web::post().to(|| async {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "valid": true,
+                    "message": {
+                        "hash": "0xfeed",
+                        "data": {
+                            "fid": 42,
+                            "timestamp": farcaster_now(),
+                            "frameActionBody": { "url": "aHR0cDovL2xvY2FsaG9zdA==", "buttonIndex": 1 }
+                        }
+                    }
+                }))
+            }),
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_timestamp_window() {
+        let guard = ReplayGuard::new(600);
+
+        // Assert messages are accepted within the window either side of now
+        assert!(guard.check_timestamp(farcaster_now()).is_ok());
+        assert!(guard.check_timestamp(farcaster_now() - 500).is_ok());
+        assert!(guard.check_timestamp(farcaster_now() + 30).is_ok());
+
+        // Assert stale and far-future messages are refused
+        assert!(matches!(
+            guard.check_timestamp(farcaster_now() - 3600),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(guard.check_timestamp(farcaster_now() + 3600).is_err());
+        assert!(guard.check_timestamp(0).is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_hash_is_accepted_once() {
+        let guard = ReplayGuard::new(600);
+
+        assert!(guard.check_hash("0xabc", farcaster_now()).is_ok());
+
+        // Assert the same hash is refused, while others still pass
+        assert!(matches!(
+            guard.check_hash("0xabc", farcaster_now()),
+            Err(AppError::Unauthorized(_))
+        ));
+        assert!(guard.check_hash("0xdef", farcaster_now()).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_future_dated_hash_is_kept_until_it_expires() {
+        let guard = ReplayGuard::new(2);
+        let timestamp = farcaster_now() + 2;
+        assert!(guard.check_hash("0xabc", timestamp).is_ok());
+
+        // Assert a message dated ahead, which still passes the timestamp check once the
+        // window has gone by, is still refused as already used
+        actix_web::rt::time::sleep(Duration::from_millis(2100)).await;
+        assert!(guard.check_timestamp(timestamp).is_ok());
+        assert!(matches!(
+            guard.check_hash("0xabc", timestamp),
+            Err(AppError::Unauthorized(_))
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_zero_window_disables_checks() {
+        let guard = ReplayGuard::new(0);
+
+        // Assert nothing is refused when MAX_MESSAGE_AGE_SECS is 0
+        assert!(guard.check_timestamp(0).is_ok());
+        assert!(guard.check_hash("0xabc", farcaster_now()).is_ok());
+        assert!(guard.check_hash("0xabc", farcaster_now()).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_replayed_message_is_refused() {
+        let hub_url = spawn_mock_server(fresh_hub).await;
+
+        // Validation on and no response cache, so a repeat has to run the button again
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            hub_url,
+            idempotency_ttl_secs: 0,
+            ..Config::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(RateLimiter::new(60)))
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(0)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+
+        let body = serde_json::json!({
+            "untrusted_data": { "button_index": 1 },
+            "trusted_data": { "message_bytes": "0a0b0c" }
+        });
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Assert posting the same signed message again is refused
+        let req = test::TestRequest::post()
+            .uri("/api/frame")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limited_message_can_be_retried() {
+        let hub_url = spawn_mock_server(fresh_hub).await;
+        let config = web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            hub_url,
+            idempotency_ttl_secs: 0,
+            ..Config::default()
+        });
+        let rate_limiter = web::Data::new(RateLimiter::with_window(1, Duration::from_secs(1)));
+        let app = test::init_service(
+            App::new()
+                .app_data(config.clone())
+                .app_data(web::Data::new(test_registry()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(ErrorImages::default()))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(rate_limiter.clone())
+                .app_data(noop_analytics())
+                .app_data(web::Data::new(IdempotencyCache::new(0)))
+                .app_data(web::Data::new(ReplayGuard::new(600)))
+                .app_data(web::Data::new(ConfirmedTransactions::new(3600)))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/api/frame", web::post().to(handle_frame)),
+        )
+        .await;
+        let press = || {
+            test::TestRequest::post()
+                .uri("/api/frame")
+                .set_json(serde_json::json!({
+                    "untrusted_data": { "button_index": 1 },
+                    "trusted_data": { "message_bytes": "0a0b0c" }
+                }))
+                .to_request()
+        };
+
+        // Fid 42 has used up its window
+        assert!(rate_limiter.check(RateKey::Fid(42)).is_ok());
+        let resp = test::call_service(&app, press()).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Assert the press turned away is handled once the window has passed, rather
+        // than refused as already used
+        actix_web::rt::time::sleep(Duration::from_millis(1100)).await;
+        let resp = test::call_service(&app, press()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,167 @@
+#[cfg(test)]
+mod rewards_tests {
+    use crate::auction::Auction;
+    use crate::bridge::Bridge;
+    use crate::images::cache::RenderCache;
+    use crate::images::{self, ImageTemplates};
+    use crate::leaderboard::Leaderboard;
+    use crate::metrics::Metrics;
+    use crate::moxie::MoxiePrice;
+    use crate::price::PriceHistory;
+    use crate::profiles::ProfileCache;
+    use crate::receipts::Receipts;
+    use crate::tests::spawn_mock_server;
+    use crate::tx::{encode_amount, function_selector};
+    use crate::{handle_rewards_tx, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000000ee";
+    const WALLET: &str = "0x1111111111111111111111111111111111111111";
+    const EMPTY_WALLET: &str = "0x2222222222222222222222222222222222222222";
+
+    // An RPC node where WALLET has 12.5 GOAT to claim and everyone else none, and a hub
+    // where fid 3 verified WALLET, on the same server
+    async fn rpc_and_hub() -> String {
+        spawn_mock_server(|cfg| {
+            cfg.route(
+                "/",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    assert_eq!(body["method"], "eth_call");
+                    assert_eq!(body["params"][0]["to"], CONTRACT);
+                    let data = body["params"][0]["data"].as_str().unwrap();
+                    let selector = hex::encode(function_selector("claimable(address)"));
+                    assert!(data.starts_with(&format!("0x{}", selector)));
+                    let claimable = if data.ends_with(&WALLET[2..]) {
+                        12_500_000_000_000_000_000
+                    } else {
+                        0
+                    };
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": format!("0x{}", hex::encode(encode_amount(claimable)))
+                    }))
+                }),
+            )
+            .route(
+                "/v1/userDataByFid",
+                web::get().to(|| async { HttpResponse::Ok().json(serde_json::json!({})) }),
+            )
+            .route(
+                "/v1/verificationsByFid",
+                web::get().to(|| async {
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "messages": [{
+                            "data": { "verificationAddAddressBody": { "address": WALLET } }
+                        }]
+                    }))
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_claim_transaction() {
+        let config = web::Data::new(Config {
+            rpc_url: Some(rpc_and_hub().await),
+            rewards_contract: Some(CONTRACT.to_string()),
+            ..Config::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/rewards", web::post().to(handle_rewards_tx)),
+        )
+        .await;
+        let claim = |wallet: &str| {
+            test::TestRequest::post()
+                .uri("/api/tx/rewards")
+                .set_json(serde_json::json!({
+                    "untrustedData": { "buttonIndex": 1, "fid": 3, "address": wallet }
+                }))
+                .to_request()
+        };
+
+        // Assert a wallet with rewards calls claim() on the contract, paying nothing
+        let resp = test::call_service(&app, claim(WALLET)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["params"]["to"], CONTRACT);
+        assert_eq!(body["params"]["value"], "0");
+        assert_eq!(
+            body["params"]["data"],
+            format!("0x{}", hex::encode(function_selector("claim()")))
+        );
+
+        // Assert one without any is told so rather than sent a claim that does nothing
+        let resp = test::call_service(&app, claim(EMPTY_WALLET)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["message"], "No rewards to claim yet");
+    }
+
+    #[actix_web::test]
+    async fn test_claimable_is_drawn_for_the_viewer() {
+        let server = rpc_and_hub().await;
+        let text = |content: &str| {
+            format!(
+                r#"<svg width="120" height="20"><text y="16" font-size="16">{}</text></svg>"#,
+                content
+            )
+        };
+        let templates = ImageTemplates::new(
+            HashMap::from([
+                ("r".to_string(), text("{{claimable}}")),
+                ("expected".to_string(), text("12.5 GOAT")),
+            ]),
+            Path::new("assets"),
+        );
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(templates))
+                .app_data(web::Data::new(RenderCache::new(0, 60)))
+                .app_data(web::Data::new(Metrics::default()))
+                .app_data(web::Data::new(ProfileCache::new(60)))
+                .app_data(web::Data::new(PriceHistory::new(60)))
+                .app_data(web::Data::new(Leaderboard::new(60)))
+                .app_data(web::Data::new(MoxiePrice::new(60)))
+                .app_data(web::Data::new(Receipts::default()))
+                .app_data(web::Data::new(Auction::new(15)))
+                .app_data(web::Data::new(Bridge::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(Config {
+                    hub_url: server.clone(),
+                    rpc_url: Some(server),
+                    rewards_contract: Some(CONTRACT.to_string()),
+                    ..Config::default()
+                }))
+                .route(
+                    "/images/{file}",
+                    web::get().to(images::serve_template_image),
+                ),
+        )
+        .await;
+        let render = |uri: &'static str| {
+            let app = &app;
+            async move {
+                let resp =
+                    test::call_service(app, test::TestRequest::get().uri(uri).to_request()).await;
+                assert_eq!(resp.status(), 200);
+                test::read_body(resp).await
+            }
+        };
+
+        // Assert the fid's verified wallet's rewards are written out in GOAT, whatever
+        // the URL claims
+        let expected = render("/images/expected.png").await;
+        assert_eq!(render("/images/r.png?fid=3").await, expected);
+        assert_eq!(
+            render("/images/r.png?fid=3&claimable=9000%20GOAT").await,
+            expected
+        );
+    }
+}
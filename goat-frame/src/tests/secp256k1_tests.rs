@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod secp256k1_tests {
+    use crate::auth::personal_message_hash;
+    use crate::secp256k1;
+    use crate::tx::recover_signer;
+
+    // web3.js's accounts.sign example: this key signing "Some data"
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const ADDRESS: &str = "2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+    const SIGNATURE: &str = "b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd\
+                             6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c";
+
+    fn signature() -> [u8; 65] {
+        hex::decode(SIGNATURE).unwrap().try_into().unwrap()
+    }
+
+    fn recovered(hash: &[u8; 32], signature: &[u8; 65]) -> Option<String> {
+        recover_signer(hash, signature).map(hex::encode)
+    }
+
+    #[test]
+    fn test_known_signature_recovers_its_key() {
+        let hash = personal_message_hash("Some data");
+
+        // Assert the message hash and signer match web3.js's
+        assert_eq!(
+            hex::encode(hash),
+            "1da44b586eb0729ff70a73c326926f6ed5a25f5b056e7f47fbc6e58d86871655"
+        );
+        assert_eq!(recovered(&hash, &signature()).as_deref(), Some(ADDRESS));
+
+        // Assert v given as 0 or 1 recovers the same signer
+        let mut signature = signature();
+        signature[64] -= 27;
+        assert_eq!(recovered(&hash, &signature).as_deref(), Some(ADDRESS));
+    }
+
+    #[test]
+    fn test_tampered_signatures_recover_someone_else() {
+        let hash = personal_message_hash("Some data");
+
+        // Assert another message, or the other recovery id, recovers another address
+        let other = recovered(&personal_message_hash("Some other data"), &signature());
+        assert_ne!(other.as_deref(), Some(ADDRESS));
+        let mut flipped = signature();
+        flipped[64] = 27;
+        assert_ne!(recovered(&hash, &flipped).as_deref(), Some(ADDRESS));
+
+        // Assert r or s of zero or past the curve order, or a bad v, recovers nobody
+        for (range, byte) in [(0..32, 0x00), (32..64, 0x00), (0..32, 0xff), (32..64, 0xff)] {
+            let mut signature = signature();
+            signature[range].fill(byte);
+            assert_eq!(recovered(&hash, &signature), None);
+        }
+        let mut signature = signature();
+        signature[64] = 29;
+        assert_eq!(recovered(&hash, &signature), None);
+    }
+
+    #[test]
+    fn test_signatures_made_here_recover() {
+        let key: [u8; 32] = hex::decode(KEY).unwrap().try_into().unwrap();
+        let mut one = [0u8; 32];
+        one[31] = 1;
+
+        // Assert what sign makes recovers its key's address, e.g. key 1's well known one
+        for (key, address) in [
+            (key, ADDRESS),
+            (one, "7e5f4552091a69125d5dfcb7b8c2659029395bdf"),
+        ] {
+            for message in ["Some data", "", "farcaster://fid/42"] {
+                let hash = personal_message_hash(message);
+                let signature = secp256k1::sign(&hash, &key);
+                assert_eq!(recovered(&hash, &signature).as_deref(), Some(address));
+            }
+        }
+    }
+
+    // go-ethereum's ecrecover precompile test: r, s and v 28 over this hash
+    const GETH_HASH: &str = "456e9aea5e197a1f1af7a3e85a3212fa4049a3ba34c2289b4c860fc0b0c64ef3";
+    const GETH_SIGNATURE: &str = "9242685bf161793cc25603c231bc2f568eb630ea16aa137d2664ac8038825608\
+                                  4f8ae3bd7535248d0bd448298cc2e2071e56992d0774dc340c368ae950852ada1c";
+    const GETH_ADDRESS: &str = "7156526fbd7a3c72969b54f64e42c10fbb768c8a";
+
+    // The curve's order n, and n + 1, which is under the field's size so a valid x
+    const ORDER: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+    const ORDER_PLUS_ONE: &str = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364142";
+
+    fn bytes<const LEN: usize>(hex: &str) -> [u8; LEN] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    // n - s, for the other of the two s values that sign the same hash
+    fn negate(s: &[u8]) -> [u8; 32] {
+        let order: [u8; 32] = bytes(ORDER);
+        let mut negated = [0u8; 32];
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let difference = order[i] as i16 - s[i] as i16 - borrow;
+            borrow = i16::from(difference < 0);
+            negated[i] = difference.rem_euclid(256) as u8;
+        }
+        negated
+    }
+
+    #[test]
+    fn test_published_vector_recovers_its_address() {
+        // Assert go-ethereum's ecrecover vector recovers the address it expects
+        assert_eq!(
+            recovered(&bytes(GETH_HASH), &bytes(GETH_SIGNATURE)).as_deref(),
+            Some(GETH_ADDRESS)
+        );
+    }
+
+    #[test]
+    fn test_high_s_is_refused() {
+        let hash = personal_message_hash("Some data");
+
+        // Assert n - s with the other recovery id, which ecrecover itself would still
+        // take, recovers nobody
+        for (hash, signature) in [
+            (hash, signature()),
+            (bytes(GETH_HASH), bytes(GETH_SIGNATURE)),
+        ] {
+            let mut malleated = signature;
+            malleated[32..64].copy_from_slice(&negate(&signature[32..64]));
+            malleated[64] ^= 1;
+            assert_eq!(recovered(&hash, &malleated), None);
+        }
+    }
+
+    #[test]
+    fn test_malformed_signatures_recover_nobody() {
+        let hash = personal_message_hash("Some data");
+        let with = |r: Option<[u8; 32]>, s: Option<[u8; 32]>| {
+            let mut signature = signature();
+            if let Some(r) = r {
+                signature[..32].copy_from_slice(&r);
+            }
+            if let Some(s) = s {
+                signature[32..64].copy_from_slice(&s);
+            }
+            signature
+        };
+        let mut five = [0u8; 32];
+        five[31] = 5;
+        let mut one = [0u8; 32];
+        one[31] = 1;
+
+        // Assert r or s equal to the order, or r past it though a valid x, recovers
+        // nobody, nor does an r with no point on the curve (5³ + 7 has no square
+        // root mod p)
+        for signature in [
+            with(Some(bytes(ORDER)), None),
+            with(None, Some(bytes(ORDER))),
+            with(Some(bytes(ORDER_PLUS_ONE)), None),
+            with(Some(five), None),
+        ] {
+            assert_eq!(recovered(&hash, &signature), None);
+        }
+
+        // Assert the smallest r and s still recover someone, just not the signer
+        let smallest = recovered(&hash, &with(Some(one), Some(one)));
+        assert!(smallest.is_some());
+        assert_ne!(smallest.as_deref(), Some(ADDRESS));
+
+        // Assert v other than 0, 1, 27 or 28 recovers nobody
+        for v in [2, 26, 29, 255] {
+            let mut signature = signature();
+            signature[64] = v;
+            assert_eq!(recovered(&hash, &signature), None);
+        }
+    }
+}
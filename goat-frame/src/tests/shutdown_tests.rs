@@ -0,0 +1,29 @@
+#[cfg(test)]
+mod shutdown_tests {
+    use crate::shutdown::{track_in_flight, InFlight};
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn report_in_flight(in_flight: web::Data<InFlight>) -> HttpResponse {
+        HttpResponse::Ok().body(in_flight.current().to_string())
+    }
+
+    #[actix_web::test]
+    async fn test_in_flight_counts_active_requests() {
+        let in_flight = web::Data::new(InFlight::default());
+        let app = test::init_service(
+            App::new()
+                .app_data(in_flight.clone())
+                .wrap(from_fn(track_in_flight))
+                .route("/", web::get().to(report_in_flight)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        let body = test::call_and_read_body(&app, req).await;
+
+        // Assert the request counted itself while running and was released afterwards
+        assert_eq!(body, "1");
+        assert_eq!(in_flight.current(), 0);
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod signers_tests {
+    use crate::auth::Sessions;
+    use crate::config::Config;
+    use crate::countdown::unix_now;
+    use crate::signers::{self, SignerRecord, SignerState, SignerStore};
+    use crate::tests::spawn_mock_server;
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    const CUSTODY: &str = "0x1111111111111111111111111111111111111111";
+
+    // A JSON-RPC signer holding the app's custody key, and a client API whose request
+    // completes for fid 42 once approved is set
+    async fn mock_services(approved: Arc<AtomicBool>) -> String {
+        spawn_mock_server(move |cfg| {
+            let approved = approved.clone();
+            cfg.route(
+                "/rpc",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    assert_eq!(body["method"], "eth_signTypedData_v4");
+                    assert_eq!(body["params"][0], CUSTODY);
+                    let typed_data: serde_json::Value =
+                        serde_json::from_str(body["params"][1].as_str().unwrap()).unwrap();
+                    assert_eq!(typed_data["message"]["requestFid"], 9152);
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": "0xsigned"
+                    }))
+                }),
+            )
+            .route(
+                "/v2/signed-key-requests",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    assert_eq!(body["signature"], "0xsigned");
+                    assert_eq!(body["requestFid"], 9152);
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "result": { "signedKeyRequest": {
+                            "token": "tok",
+                            "deeplinkUrl": "farcaster://signed-key-request?token=tok",
+                            "key": body["key"],
+                            "state": "pending"
+                        }}
+                    }))
+                }),
+            )
+            .route(
+                "/v2/signed-key-request",
+                web::get().to(move || {
+                    let approved = approved.load(Ordering::SeqCst);
+                    async move {
+                        let request = if approved {
+                            serde_json::json!({ "state": "completed", "userFid": 42 })
+                        } else {
+                            serde_json::json!({ "state": "pending" })
+                        };
+                        let mut request = request.as_object().unwrap().clone();
+                        request.insert("token".into(), "tok".into());
+                        request.insert(
+                            "deeplinkUrl".into(),
+                            "farcaster://signed-key-request?token=tok".into(),
+                        );
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "result": { "signedKeyRequest": request }
+                        }))
+                    }
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_signer_request_flow() {
+        let approved = Arc::new(AtomicBool::new(false));
+        let base = mock_services(approved.clone()).await;
+        let config = web::Data::new(Config {
+            app_fid: Some(9152),
+            app_custody_address: Some(CUSTODY.to_string()),
+            app_signer_rpc_url: Some(format!("{}/rpc", base)),
+            signed_key_request_url: base,
+            ..Config::default()
+        });
+        let sessions = Sessions::new(Some("secret"), 3600);
+        let (token, _) = sessions.mint(42);
+        let store = web::Data::new(SignerStore::in_memory());
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(sessions))
+                .app_data(store.clone())
+                .route("/api/signer", web::get().to(signers::signer_status))
+                .route("/api/signer", web::post().to(signers::request_signer)),
+        )
+        .await;
+        let bearer = ("Authorization", format!("Bearer {}", token));
+        let poll = || {
+            test::TestRequest::get()
+                .uri("/api/signer")
+                .insert_header(bearer.clone())
+                .to_request()
+        };
+
+        // Assert a request left unapproved past its deadline counts as none
+        let lapsed = SignerRecord {
+            private_key: "00".to_string(),
+            public_key: "0x00".to_string(),
+            token: "old".to_string(),
+            deeplink_url: "farcaster://signed-key-request?token=old".to_string(),
+            state: SignerState::Pending,
+            deadline: unix_now() - 1,
+        };
+        store.set(42, lapsed);
+        let resp = test::call_service(&app, poll()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // Assert a request needs a session
+        let req = test::TestRequest::post().uri("/api/signer").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // Assert requesting a signer returns the deep link and stores a pending key in
+        // place of the lapsed one
+        let req = test::TestRequest::post()
+            .uri("/api/signer")
+            .insert_header(bearer.clone())
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["state"], "pending");
+        assert_eq!(
+            body["deeplinkUrl"],
+            "farcaster://signed-key-request?token=tok"
+        );
+        let record = store.get(42).unwrap();
+        assert_eq!(record.state, SignerState::Pending);
+        assert_eq!(record.token, "tok");
+        assert!(record.deadline > unix_now());
+        assert!(record.key_pair().is_none());
+
+        // Assert polling stays pending until the user approves, then the key is usable
+        let body: serde_json::Value = test::call_and_read_body_json(&app, poll()).await;
+        assert_eq!(body["state"], "pending");
+        approved.store(true, Ordering::SeqCst);
+        let body: serde_json::Value = test::call_and_read_body_json(&app, poll()).await;
+        assert_eq!(body["state"], "approved");
+        assert!(store.get(42).unwrap().key_pair().is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_signers_need_an_app_account() {
+        let sessions = Sessions::new(Some("secret"), 3600);
+        let (token, _) = sessions.mint(42);
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Config::default()))
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .app_data(web::Data::new(sessions))
+                .app_data(web::Data::new(SignerStore::in_memory()))
+                .route("/api/signer", web::post().to(signers::request_signer)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/signer")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the endpoint doesn't exist without APP_FID and friends
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_store_persists_signers() {
+        let path = std::env::temp_dir().join(format!("goat-frame-{}.json", uuid::Uuid::new_v4()));
+        let record = SignerRecord {
+            private_key: "00".to_string(),
+            public_key: "0x00".to_string(),
+            token: "tok".to_string(),
+            deeplink_url: "farcaster://signed-key-request?token=tok".to_string(),
+            state: SignerState::Pending,
+            deadline: unix_now() + 60,
+        };
+        SignerStore::open(&path).unwrap().set(42, record.clone());
+
+        // Assert a reopened store sees the saved signer
+        assert_eq!(
+            SignerStore::open(&path).unwrap().get(42),
+            Some(record.clone())
+        );
+
+        // Assert only the server's own user can read the keys, even in a file made
+        // with looser permissions
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = || std::fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(), 0o600);
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            SignerStore::open(&path).unwrap().set(43, record);
+            assert_eq!(mode(), 0o600);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod state_tests {
+    use crate::frame_logic::state::{FrameState, MAIN_SCREEN, MAX_STATE_BYTES};
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut state = FrameState::default();
+        state.push("more");
+
+        // Assert the encoded state decodes to the same stack
+        assert_eq!(FrameState::decode(&state.encode()), state);
+    }
+
+    #[test]
+    fn test_empty_state_resets_to_main() {
+        // Assert the initial frame's empty state starts on main
+        assert_eq!(FrameState::decode("").current(), MAIN_SCREEN);
+    }
+
+    #[test]
+    fn test_corrupt_state_resets_to_main() {
+        // Assert garbage and well-formed but rootless stacks both reset
+        assert_eq!(FrameState::decode("{not json").current(), MAIN_SCREEN);
+        assert_eq!(
+            FrameState::decode(r#"{"stack":["more"]}"#),
+            FrameState::default()
+        );
+    }
+
+    #[test]
+    fn test_back_on_main_stays_on_main() {
+        let mut state = FrameState::default();
+        state.pop();
+
+        // Assert main can never be popped off the stack
+        assert_eq!(state.current(), MAIN_SCREEN);
+    }
+
+    #[test]
+    fn test_deep_state_stays_under_limit() {
+        let mut state = FrameState::default();
+        for _ in 0..1000 {
+            state.push("add_liquidity");
+        }
+
+        // Assert the encoded state fits within Farcaster's limit and keeps its root
+        assert!(state.encode().len() < MAX_STATE_BYTES);
+        assert_eq!(state.stack[0], MAIN_SCREEN);
+    }
+
+    #[test]
+    fn test_signed_state_round_trip() {
+        let mut state = FrameState::default();
+        state.push("gift");
+        state.input = Some("alice".to_string());
+
+        let encoded = state.encode_signed(Some("secret"));
+
+        // Assert the signature travels with the state and verifies under the same secret
+        assert!(encoded.starts_with(&state.encode()));
+        assert_eq!(FrameState::decode_signed(&encoded, Some("secret")), state);
+    }
+
+    #[test]
+    fn test_forged_state_resets_to_main() {
+        let mut state = FrameState::default();
+        state.push("gift");
+        let encoded = state.encode_signed(Some("secret"));
+        let tampered = encoded.replace("gift", "more");
+
+        // Assert edited, unsigned and wrongly keyed state are all refused
+        for raw in [tampered, state.encode(), state.encode_signed(Some("other"))] {
+            assert_eq!(
+                FrameState::decode_signed(&raw, Some("secret")),
+                FrameState::default()
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsigned_state_without_secret() {
+        let mut state = FrameState::default();
+        state.push("more");
+
+        // Assert no secret keeps the plain JSON encoding
+        assert_eq!(state.encode_signed(None), state.encode());
+        assert_eq!(FrameState::decode_signed(&state.encode(), None), state);
+    }
+
+    #[test]
+    fn test_state_expires_after_its_ttl() {
+        let state = FrameState::default();
+
+        // Assert a fresh copy is good, and expiry survives signing
+        let expiring = state.expiring(60);
+        assert!(!expiring.is_expired());
+        let decoded =
+            FrameState::decode_signed(&expiring.encode_signed(Some("secret")), Some("secret"));
+        assert_eq!(decoded.expires_at, expiring.expires_at);
+
+        // Assert a ttl of 0 never expires, and a past expiry has
+        assert_eq!(state.expiring(0).expires_at, None);
+        let expired = FrameState {
+            expires_at: Some(1),
+            ..FrameState::default()
+        };
+        assert!(expired.is_expired());
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod swaps_tests {
+    use crate::swaps::{last_amount, min_out};
+    use crate::tests::spawn_mock_server;
+    use crate::tx::{encode_amount, encode_uint, function_selector};
+    use crate::{handle_swap_tx, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+
+    const ROUTER: &str = "0x00000000000000000000000000000000000000aa";
+    const WRAPPED_GOAT: &str = "0x00000000000000000000000000000000000000bb";
+    const TOKEN: &str = "0x00000000000000000000000000000000000000cc";
+    const WALLET: &str = "0x1111111111111111111111111111111111111111";
+
+    // An ABI-encoded uint256[] return value
+    fn amounts(values: &[u128]) -> Vec<u8> {
+        let mut output = encode_uint(32).to_vec();
+        output.extend_from_slice(&encode_uint(values.len() as u64));
+        for value in values {
+            output.extend_from_slice(&encode_amount(*value));
+        }
+        output
+    }
+
+    // Mock RPC node holding 10 GOAT for every wallet and quoting 1000 of the token
+    // for any amount
+    async fn rpc_with_router() -> String {
+        spawn_mock_server(|cfg| {
+            cfg.route(
+                "/",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    let result = match body["method"].as_str() {
+                        Some("eth_getBalance") => "0x8ac7230489e80000".to_string(),
+                        Some("eth_call") => {
+                            assert_eq!(body["params"][0]["to"], ROUTER);
+                            let data = body["params"][0]["data"].as_str().unwrap();
+                            let selector = function_selector("getAmountsOut(uint256,address[])");
+                            assert!(data.starts_with(&format!("0x{}", hex::encode(selector))));
+                            format!("0x{}", hex::encode(amounts(&[1, 1000])))
+                        }
+                        method => panic!("unexpected {:?}", method),
+                    };
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": result
+                    }))
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_swap_transaction_shape() {
+        let config = web::Data::new(Config {
+            rpc_url: Some(rpc_with_router().await),
+            swap_router: Some(ROUTER.to_string()),
+            swap_wrapped_goat: Some(WRAPPED_GOAT.to_string()),
+            swap_token: Some(TOKEN.to_string()),
+            swap_slippage_bps: 50,
+            ..Config::default()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/swap", web::post().to(handle_swap_tx)),
+        )
+        .await;
+        let swap = |amount: &str| {
+            test::TestRequest::post()
+                .uri("/api/tx/swap")
+                .set_json(serde_json::json!({
+                    "untrustedData": {
+                        "buttonIndex": 1,
+                        "fid": 42,
+                        "address": WALLET,
+                        "inputText": amount
+                    }
+                }))
+                .to_request()
+        };
+
+        // Assert the typed amount is paid to the router, which swaps it along the path
+        let resp = test::call_service(&app, swap("1.5")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["params"]["to"], ROUTER);
+        assert_eq!(body["params"]["value"], "1500000000000000000");
+        let data = hex::decode(&body["params"]["data"].as_str().unwrap()[2..]).unwrap();
+        let selector =
+            function_selector("swapExactETHForTokens(uint256,address[],address,uint256)");
+        assert_eq!(data[..4], selector);
+
+        // Assert the least accepted is the quote less the slippage, sent to the wallet
+        let word = |i: usize| &data[4 + 32 * i..4 + 32 * (i + 1)];
+        assert_eq!(word(0), encode_amount(995));
+        assert_eq!(word(1), encode_uint(128));
+        assert!(hex::encode(word(2)).ends_with(&WALLET[2..]));
+        assert_eq!(word(4), encode_uint(2));
+        assert!(hex::encode(word(5)).ends_with(&WRAPPED_GOAT[2..]));
+        assert!(hex::encode(word(6)).ends_with(&TOKEN[2..]));
+
+        // Assert no amount, or more than the wallet holds, is refused with a message
+        for amount in ["", "11"] {
+            let resp = test::call_service(&app, swap(amount)).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = test::read_body_json(resp).await;
+            assert!(body["message"].is_string());
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_min_out_and_quotes() {
+        // Assert slippage comes off the quote, rounding down, without overflowing
+        assert_eq!(min_out(1000, 50), 995);
+        assert_eq!(min_out(1999, 100), 1979);
+        assert_eq!(min_out(u128::MAX, 0), u128::MAX);
+        assert_eq!(min_out(1000, 10_000), 0);
+
+        // Assert the last amount of a quote is read, and malformed ones refused
+        assert_eq!(last_amount(&amounts(&[7, 42])), Some(42));
+        assert_eq!(last_amount(&amounts(&[])), None);
+        assert_eq!(last_amount(&amounts(&[7, 42])[..64]), None);
+        let mut huge = amounts(&[1]);
+        huge[64] = 1;
+        assert_eq!(last_amount(&huge), None);
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod theme_tests {
+    use crate::config::Config;
+    use crate::errors::ConfigError;
+    use crate::images::theme::{Theme, Themes};
+    use crate::images::{ImageTemplates, LiveData};
+    use image::Rgb;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    fn themed(dark: &[&str], light: &[&str]) -> Result<Themes, ConfigError> {
+        let strings = |entries: &[&str]| entries.iter().map(|e| e.to_string()).collect();
+        Themes::from_config(&Config {
+            theme_dark_colors: strings(dark),
+            theme_light_colors: strings(light),
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn test_theme_names() {
+        // Assert themes parse from the names buttons and URLs use, and nothing else
+        for theme in [Theme::Dark, Theme::Light] {
+            assert_eq!(Theme::parse(theme.as_str()), Some(theme));
+        }
+        assert_eq!(Theme::parse("Dark"), None);
+        assert_eq!(Theme::parse("blue"), None);
+    }
+
+    #[test]
+    fn test_themes_from_config() {
+        let themes = themed(&["accent=#f59e0b", "card = #202020"], &["card=#eeeeee"]).unwrap();
+
+        // Assert config colors replace or add to the built-in ones, per theme
+        let dark = themes.colors(Some("dark"));
+        assert_eq!(dark["accent"], "#f59e0b");
+        assert_eq!(dark["card"], "#202020");
+        assert_eq!(dark["background"], "#181818");
+        let light = themes.colors(Some("light"));
+        assert_eq!(light["card"], "#eeeeee");
+        assert_eq!(light["accent"], "#6d28d9");
+
+        // Assert a missing or unknown theme gets the default
+        assert_eq!(themes.colors(None), dark);
+        assert_eq!(themes.colors(Some("blue")), dark);
+        let light_default = Themes::from_config(&Config {
+            default_theme: Theme::Light,
+            ..Config::default()
+        })
+        .unwrap();
+        assert_eq!(light_default.colors(None)["background"], "#f4f4f4");
+    }
+
+    #[test]
+    fn test_themes_reject_bad_colors() {
+        for (dark, light, reason) in [
+            (vec!["accent"], vec![], "must be name=color"),
+            (vec!["accent=teal"], vec![], "must be a name of"),
+            (vec!["a-b=#000000"], vec![], "must be a name of"),
+            (vec!["card=#202020"], vec![], "must both set \"card\""),
+            (vec![], vec!["card=#eeeeee"], "must both set \"card\""),
+        ] {
+            // Assert every color is a valid one that both themes have
+            let err = themed(&dark, &light).unwrap_err();
+            assert!(
+                matches!(err, ConfigError::Invalid(ref r) if r.contains(reason)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_templates_draw_in_the_asked_theme() {
+        let templates = ImageTemplates::new(
+            HashMap::from([(
+                "t".to_string(),
+                r#"<svg width="4" height="4"><rect width="4" height="4" fill="{{theme_background}}"/></svg>"#
+                    .to_string(),
+            )]),
+            Path::new("assets"),
+        )
+        .with_themes(themed(&["background=#000080"], &[]).unwrap());
+        let pixel = |params: &[(&str, &str)]| {
+            let params = params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            let png = templates
+                .render("t", &params, &LiveData::default())
+                .unwrap();
+            *image::load_from_memory(&png)
+                .unwrap()
+                .to_rgb8()
+                .get_pixel(0, 0)
+        };
+
+        // Assert ?theme= picks the colors, the default is dark, and the query can't
+        // set a theme color itself
+        assert_eq!(pixel(&[("theme", "light")]), Rgb([0xf4, 0xf4, 0xf4]));
+        assert_eq!(pixel(&[]), Rgb([0, 0, 0x80]));
+        assert_eq!(pixel(&[("theme_background", "#ff0000")]), Rgb([0, 0, 0x80]));
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod tls_tests {
+    use crate::errors::TlsError;
+    use crate::tls::load_server_config;
+    use std::path::{Path, PathBuf};
+
+    fn temp_file(contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("goat-frame-{}.pem", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_missing_files_are_reported() {
+        let result = load_server_config(
+            Path::new("/nonexistent/cert.pem"),
+            Path::new("/nonexistent/key.pem"),
+        );
+
+        // Assert the error names the file that couldn't be read
+        assert!(
+            matches!(result, Err(TlsError::Read(ref path, _)) if path == "/nonexistent/cert.pem")
+        );
+    }
+
+    #[test]
+    fn test_files_without_pem_blocks_are_rejected() {
+        let cert = temp_file("not a certificate");
+        let key = temp_file("not a key either");
+
+        let result = load_server_config(&cert, &key);
+
+        // Assert a malformed certificate fails before the server binds
+        assert!(matches!(result, Err(TlsError::NoCertificates(_))));
+
+        std::fs::remove_file(&cert).unwrap();
+        std::fs::remove_file(&key).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod trace_tests {
+    use crate::assets::AssetHashes;
+    use crate::tests::test_registry;
+    use crate::trace::{trace_id, TRACE_HEADER};
+    use crate::{index, Config};
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App};
+
+    fn config() -> web::Data<Config> {
+        web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            ..Config::default()
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_response_carries_generated_trace_id() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .app_data(web::Data::new(test_registry()))
+                .wrap(from_fn(trace_id))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert a fresh UUID is generated when the client sends none
+        let header = resp.headers().get(TRACE_HEADER).unwrap().to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(header).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_supplied_trace_id_is_echoed() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .app_data(web::Data::new(test_registry()))
+                .wrap(from_fn(trace_id))
+                .app_data(web::Data::new(AssetHashes::default()))
+                .route("/", web::get().to(index)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((TRACE_HEADER, "client-trace-123"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert the client's id comes back unchanged
+        assert_eq!(
+            resp.headers().get(TRACE_HEADER).unwrap(),
+            "client-trace-123"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+#[cfg(test)]
+mod tx_tests {
+    use crate::errors::AppError;
+    use crate::tests::spawn_mock_server;
+    use crate::tx::{format_amount, parse_amount, parse_transaction_id, short_hash};
+    use crate::{handle_buy_boost_tx, Config};
+    use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000b0057";
+
+    fn config() -> web::Data<Config> {
+        web::Data::new(Config {
+            domain: "http://localhost".to_string(),
+            boost_contract: Some(CONTRACT.to_string()),
+            chain_id: 8453,
+            ..Config::default()
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_buy_boost_transaction_shape() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/buy_boost", web::post().to(handle_buy_boost_tx)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tx/buy_boost")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1,
+                    "fid": 42,
+                    "address": "0x1111111111111111111111111111111111111111"
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+
+        // Assert the Farcaster transaction shape and the configured contract
+        assert_eq!(body["chainId"], "eip155:8453");
+        assert_eq!(body["method"], "eth_sendTransaction");
+        assert_eq!(body["params"]["to"], CONTRACT);
+        assert_eq!(body["params"]["value"], "0");
+        assert!(body["params"]["abi"].is_array());
+
+        // Assert the calldata is a selector plus the fid and address words
+        let data = body["params"]["data"].as_str().unwrap();
+        assert_eq!(data.len(), 2 + 2 * (4 + 32 + 32));
+        assert!(data.ends_with("1111111111111111111111111111111111111111"));
+        assert_eq!(
+            &data[10..74],
+            "000000000000000000000000000000000000000000000000000000000000002a"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_buy_boost_rejects_bad_address() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/buy_boost", web::post().to(handle_buy_boost_tx)),
+        )
+        .await;
+
+        for address in [Some("0x1234"), Some("not an address"), None] {
+            let req = test::TestRequest::post()
+                .uri("/api/tx/buy_boost")
+                .set_json(serde_json::json!({
+                    "untrusted_data": {
+                        "button_index": 1,
+                        "fid": 42,
+                        "address": address
+                    }
+                }))
+                .to_request();
+            let resp = test::call_service(&app, req).await;
+
+            // Assert short, malformed and missing addresses are bad requests
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_buy_boost_sends_entered_amount() {
+        let app = test::init_service(
+            App::new()
+                .app_data(config())
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/buy_boost", web::post().to(handle_buy_boost_tx)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tx/buy_boost")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1,
+                    "fid": 42,
+                    "address": "0x1111111111111111111111111111111111111111",
+                    "input_text": " 1.5 "
+                }
+            }))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        // Assert the typed amount becomes the transaction value in wei
+        assert_eq!(body["params"]["value"], "1500000000000000000");
+    }
+
+    // Mock RPC node reporting a balance of 2 GOAT for every address
+    async fn rpc_with_two_goat() -> String {
+        spawn_mock_server(|cfg| {
+            cfg.route(
+                "/",
+                web::post().to(|body: web::Json<serde_json::Value>| async move {
+                    assert_eq!(body["method"], "eth_getBalance");
+                    assert_eq!(
+                        body["params"],
+                        serde_json::json!(["0x1111111111111111111111111111111111111111", "latest"])
+                    );
+                    HttpResponse::Ok().json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": "0x1bc16d674ec80000"
+                    }))
+                }),
+            );
+        })
+        .await
+    }
+
+    #[actix_web::test]
+    async fn test_buy_boost_checks_wallet_balance() {
+        let config = web::Data::new(Config {
+            rpc_url: Some(rpc_with_two_goat().await),
+            ..config().as_ref().clone()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/buy_boost", web::post().to(handle_buy_boost_tx)),
+        )
+        .await;
+        let buy = |amount: &str| {
+            test::TestRequest::post()
+                .uri("/api/tx/buy_boost")
+                .set_json(serde_json::json!({
+                    "untrustedData": {
+                        "buttonIndex": 1,
+                        "fid": 42,
+                        "address": "0x1111111111111111111111111111111111111111",
+                        "inputText": amount
+                    }
+                }))
+                .to_request()
+        };
+
+        // Assert an amount the wallet can pay for is built as usual
+        let resp = test::call_service(&app, buy("1.5")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Assert one it can't is refused with a message the client shows the user
+        let resp = test::call_service(&app, buy("2.5")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(
+            body["message"],
+            "Not enough GOAT: this wallet has 2 and the boost needs 2.5"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_buy_boost_skips_balance_check_when_rpc_fails() {
+        let config = web::Data::new(Config {
+            rpc_url: Some("http://127.0.0.1:1".to_string()),
+            ..config().as_ref().clone()
+        });
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .app_data(web::Data::new(reqwest::Client::new()))
+                .route("/api/tx/buy_boost", web::post().to(handle_buy_boost_tx)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/tx/buy_boost")
+            .set_json(serde_json::json!({
+                "untrusted_data": {
+                    "button_index": 1,
+                    "fid": 42,
+                    "address": "0x1111111111111111111111111111111111111111",
+                    "input_text": "100"
+                }
+            }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        // Assert an unreachable node doesn't block the transaction; the wallet has the final say
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_format_amount() {
+        // Assert wei is shown in GOAT without trailing zeros
+        assert_eq!(format_amount(0), "0");
+        assert_eq!(format_amount(2_000_000_000_000_000_000), "2");
+        assert_eq!(format_amount(1_500_000_000_000_000_000), "1.5");
+        assert_eq!(format_amount(1), "0.000000000000000001");
+    }
+
+    #[actix_web::test]
+    async fn test_parse_amount() {
+        // Assert whole and fractional amounts convert to wei
+        assert_eq!(parse_amount("").unwrap(), 0);
+        assert_eq!(parse_amount("2").unwrap(), 2_000_000_000_000_000_000);
+        assert_eq!(parse_amount(".25").unwrap(), 250_000_000_000_000_000);
+        assert_eq!(parse_amount("0.000000000000000001").unwrap(), 1);
+
+        // Assert anything that isn't a plain decimal is refused
+        for amount in ["-1", "1e3", "1.2.3", ".", "abc", "0.0000000000000000001"] {
+            assert!(
+                matches!(parse_amount(amount), Err(AppError::BadRequest(_))),
+                "{} was accepted",
+                amount
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_parse_transaction_id() {
+        let hash = format!("0x{}", "AB".repeat(32));
+
+        // Assert a 32 byte hash is accepted and normalized to lowercase
+        assert_eq!(
+            parse_transaction_id(&hash).unwrap(),
+            format!("0x{}", "ab".repeat(32))
+        );
+
+        // Assert short, unprefixed and non-hex ids are refused
+        for id in [
+            "0x1234",
+            &"ab".repeat(32),
+            &format!("0x{}", "zz".repeat(32)),
+        ] {
+            assert!(matches!(
+                parse_transaction_id(id),
+                Err(AppError::BadRequest(_))
+            ));
+        }
+
+        // Assert a hash is drawn cut short to its ends
+        assert_eq!(
+            short_hash(&format!("0x1234ab{}cdef12", "00".repeat(26))),
+            "0x1234ab...cdef12"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod validation_tests {
+    use crate::errors::AppError;
+    use crate::frame_logic::validation::validate_frame_message;
+    use crate::tests::spawn_mock_server;
+    use actix_web::{web, HttpResponse};
+
+    const DOMAIN: &str = "http://localhost";
+
+    // Mock hub that accepts any message and reports it as a click on button 3 by fid 42
+    fn valid_hub(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/v1/validateMessage",
+            web::post().to(|| async {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "valid": true,
+                    "message": {
+                        "hash": "0xabc123",
+                        "data": {
+                            "type": "MESSAGE_TYPE_FRAME_ACTION",
+                            "fid": 42,
+                            "timestamp": 110000000,
+                            "frameActionBody": {
+                                "url": "aHR0cDovL2xvY2FsaG9zdA==",
+                                "buttonIndex": 3,
+                                "inputText": "QGFsaWNl",
+                                "state": "",
+                                "transactionId": "q83v",
+                                "address": "ERERERERERERERERERERERERERE="
+                            }
+                        }
+                    }
+                }))
+            }),
+        );
+    }
+
+    // Mock hub that vouches for a press on a frame served from attacker.example
+    fn elsewhere_hub(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/v1/validateMessage",
+            web::post().to(|| async {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "valid": true,
+                    "message": {
+                        "data": {
+                            "fid": 42,
+                            "timestamp": 110000000,
+                            "frameActionBody": {
+                                "url": "aHR0cHM6Ly9hdHRhY2tlci5leGFtcGxlL2FwaS9mcmFtZQ==",
+                                "buttonIndex": 1
+                            }
+                        }
+                    }
+                }))
+            }),
+        );
+    }
+
+    // Mock hub that rejects every message as a forgery
+    fn forged_hub(cfg: &mut web::ServiceConfig) {
+        cfg.route(
+            "/v1/validateMessage",
+            web::post().to(|| async {
+                HttpResponse::Ok().json(serde_json::json!({
+                    "valid": false,
+                    "message": null
+                }))
+            }),
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_validate_valid_message() {
+        let hub_url = spawn_mock_server(valid_hub).await;
+
+        let action = validate_frame_message(&reqwest::Client::new(), &hub_url, DOMAIN, "0a0b0c")
+            .await
+            .unwrap();
+
+        // Assert the hub's view of the message wins over anything the client claimed
+        assert_eq!(action.fid, 42);
+        assert_eq!(action.timestamp, 110000000);
+        assert_eq!(action.button_index, 3);
+        assert_eq!(action.message_hash.as_deref(), Some("0xabc123"));
+        assert_eq!(action.input_text.as_deref(), Some("@alice"));
+        assert_eq!(action.state, None);
+        assert_eq!(action.transaction_id.as_deref(), Some("0xabcdef"));
+        assert_eq!(action.address, Some([0x11; 20]));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_message_for_another_frame() {
+        let client = reqwest::Client::new();
+
+        // Assert a message really signed, but on a frame served from elsewhere, can't
+        // be replayed against ours
+        let elsewhere = spawn_mock_server(elsewhere_hub).await;
+        let result = validate_frame_message(&client, &elsewhere, DOMAIN, "0a0b0c").await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+
+        // Assert another scheme or port counts as elsewhere too
+        let hub_url = spawn_mock_server(valid_hub).await;
+        for domain in [
+            "https://frame.example.com",
+            "https://localhost",
+            "http://localhost:8080",
+        ] {
+            let result = validate_frame_message(&client, &hub_url, domain, "0a0b0c").await;
+            assert!(
+                matches!(result, Err(AppError::Unauthorized(_))),
+                "{}",
+                domain
+            );
+        }
+
+        // Assert ours is ours whatever path the frame was posted from
+        let result = validate_frame_message(&client, &hub_url, "http://localhost/frame", "0a0b0c");
+        assert!(result.await.is_ok());
+    }
+
+    #[actix_web::test]
+    async fn test_validate_forged_message() {
+        let hub_url = spawn_mock_server(forged_hub).await;
+
+        let result =
+            validate_frame_message(&reqwest::Client::new(), &hub_url, DOMAIN, "0a0b0c").await;
+
+        // Assert a forged message is rejected as unauthorized
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_malformed_message_bytes() {
+        let hub_url = spawn_mock_server(valid_hub).await;
+
+        let result =
+            validate_frame_message(&reqwest::Client::new(), &hub_url, DOMAIN, "not hex").await;
+
+        // Assert garbage never reaches the hub
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[actix_web::test]
+    async fn test_validate_unreachable_hub() {
+        // Nothing listens on port 1, so the connection is refused
+        let result = validate_frame_message(
+            &reqwest::Client::new(),
+            "http://127.0.0.1:1",
+            DOMAIN,
+            "0a0b0c",
+        )
+        .await;
+
+        // Assert the request is not treated as valid when the hub is down
+        assert!(matches!(result, Err(AppError::InternalServerError)));
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod watermark_tests {
+    use crate::errors::ImageError;
+    use crate::images::watermark::Watermark;
+    use crate::images::{ImageTemplates, LiveData};
+    use image::{Rgb, Rgba, RgbaImage};
+    use std::collections::HashMap;
+
+    const TEMPLATE: &str =
+        r##"<svg width="400" height="200"><rect width="400" height="200" fill="#0000ff"/></svg>"##;
+
+    fn render(templates: &ImageTemplates) -> image::RgbImage {
+        let png = templates
+            .render("t", &HashMap::new(), &LiveData::default())
+            .unwrap();
+        image::load_from_memory(&png).unwrap().to_rgb8()
+    }
+
+    #[test]
+    fn test_watermark_is_stamped_on_images() {
+        let dir = std::env::temp_dir().join(format!("goat-frame-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // A red logo with a see-through left half
+        let logo = RgbaImage::from_fn(20, 20, |x, _| {
+            Rgba([255, 0, 0, if x < 10 { 0 } else { 255 }])
+        });
+        logo.save(dir.join("logo.png")).unwrap();
+        let templates = || {
+            ImageTemplates::new(
+                HashMap::from([("t".to_string(), TEMPLATE.to_string())]),
+                &dir,
+            )
+        };
+
+        // Assert the logo sits in the bottom right corner, keeping its transparency,
+        // and the text is drawn along the bottom left in the theme's text color
+        let watermark = Watermark::load(Some("logo.png"), Some("Partner"), &dir).unwrap();
+        let img = render(&templates().with_watermark(watermark));
+        assert_eq!(*img.get_pixel(389, 185), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(379, 185), Rgb([0, 0, 255]));
+        assert!((0..100)
+            .flat_map(|x| (170..200).map(move |y| (x, y)))
+            .any(|(x, y)| *img.get_pixel(x, y) == Rgb([255, 255, 255])));
+
+        // Assert without one the image is drawn as the template has it
+        let img = render(&templates().with_watermark(Watermark::default()));
+        assert!(img.pixels().all(|pixel| *pixel == Rgb([0, 0, 255])));
+
+        // Assert a logo outside assets/, or one that can't be read, fails the load
+        assert!(matches!(
+            Watermark::load(Some("../logo.png"), None, &dir),
+            Err(ImageError::Svg(_))
+        ));
+        assert!(matches!(
+            Watermark::load(Some("nope.png"), None, &dir),
+            Err(ImageError::Asset(..))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
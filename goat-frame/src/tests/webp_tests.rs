@@ -0,0 +1,295 @@
+#[cfg(test)]
+mod webp_tests {
+    use crate::images::webp::encode_webp;
+    use crate::images::{ImageTemplates, LiveData};
+    use image::{Rgb, RgbImage};
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    // A plain VP8L decoder for what the encoder writes, written apart from it to check
+    // it: transforms, prefix codes both simple and normal, and backward references
+    struct Bits<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl Bits<'_> {
+        fn read(&mut self, n: usize) -> u32 {
+            let mut value = 0;
+            for i in 0..n {
+                let bit = (self.data[(self.pos + i) / 8] >> ((self.pos + i) % 8)) & 1;
+                value |= (bit as u32) << i;
+            }
+            self.pos += n;
+            value
+        }
+    }
+
+    // Symbols by (length, code), codes read most significant bit first
+    struct Code(HashMap<(u32, u32), usize>);
+
+    impl Code {
+        fn from_lengths(lengths: &[u32]) -> Code {
+            let used: Vec<usize> = (0..lengths.len()).filter(|s| lengths[*s] > 0).collect();
+            if used.len() == 1 {
+                return Code(HashMap::from([((0, 0), used[0])]));
+            }
+            let mut symbols = HashMap::new();
+            let mut code = 0;
+            for length in 1..=15 {
+                for symbol in (0..lengths.len()).filter(|s| lengths[*s] == length) {
+                    symbols.insert((length, code), symbol);
+                    code += 1;
+                }
+                code <<= 1;
+            }
+            Code(symbols)
+        }
+
+        fn read(&self, bits: &mut Bits) -> usize {
+            let (mut length, mut code) = (0, 0);
+            loop {
+                if let Some(symbol) = self.0.get(&(length, code)) {
+                    return *symbol;
+                }
+                code = code << 1 | bits.read(1);
+                length += 1;
+                assert!(length <= 15, "no such code");
+            }
+        }
+    }
+
+    fn read_code(bits: &mut Bits, alphabet: usize) -> Code {
+        if bits.read(1) == 1 {
+            let count = bits.read(1) + 1;
+            let first_bits = if bits.read(1) == 1 { 8 } else { 1 };
+            let mut lengths = vec![0; alphabet];
+            lengths[bits.read(first_bits) as usize] = 1;
+            if count == 2 {
+                lengths[bits.read(8) as usize] = 1;
+            }
+            return Code::from_lengths(&lengths);
+        }
+        let order = [
+            17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+        ];
+        let mut code_length_lengths = [0; 19];
+        for symbol in order.iter().take(bits.read(4) as usize + 4) {
+            code_length_lengths[*symbol] = bits.read(3);
+        }
+        let code_length_code = Code::from_lengths(&code_length_lengths);
+        assert_eq!(bits.read(1), 0, "max_symbol isn't written");
+        let (mut lengths, mut previous) = (Vec::new(), 8);
+        while lengths.len() < alphabet {
+            match code_length_code.read(bits) as u32 {
+                length @ 0..=15 => {
+                    lengths.push(length);
+                    if length != 0 {
+                        previous = length;
+                    }
+                }
+                16 => {
+                    let n = 3 + bits.read(2);
+                    lengths.extend((0..n).map(|_| previous));
+                }
+                17 => lengths.extend((0..3 + bits.read(3)).map(|_| 0)),
+                _ => lengths.extend((0..11 + bits.read(7)).map(|_| 0)),
+            }
+        }
+        assert_eq!(lengths.len(), alphabet);
+        Code::from_lengths(&lengths)
+    }
+
+    fn prefix_value(symbol: usize, bits: &mut Bits) -> usize {
+        if symbol < 4 {
+            return symbol + 1;
+        }
+        let extra = (symbol - 2) >> 1;
+        let offset = (2 + (symbol & 1)) << extra;
+        offset + bits.read(extra) as usize + 1
+    }
+
+    fn read_image(bits: &mut Bits, width: usize, height: usize, main: bool) -> Vec<u32> {
+        assert_eq!(bits.read(1), 0, "no color cache");
+        if main {
+            assert_eq!(bits.read(1), 0, "one group of prefix codes");
+        }
+        let green = read_code(bits, 280);
+        let red = read_code(bits, 256);
+        let blue = read_code(bits, 256);
+        let alpha = read_code(bits, 256);
+        let distance = read_code(bits, 40);
+        let mut pixels: Vec<u32> = Vec::with_capacity(width * height);
+        while pixels.len() < width * height {
+            let g = green.read(bits);
+            if g < 256 {
+                let (r, b, a) = (red.read(bits), blue.read(bits), alpha.read(bits));
+                pixels.push((a << 24 | r << 16 | g << 8 | b) as u32);
+                continue;
+            }
+            let length = prefix_value(g - 256, bits);
+            let code = prefix_value(distance.read(bits), bits);
+            let back = match code {
+                1 => width,
+                2 => 1,
+                code if code > 120 => code - 120,
+                code => panic!("unexpected distance code {}", code),
+            };
+            for _ in 0..length {
+                pixels.push(pixels[pixels.len() - back]);
+            }
+        }
+        assert_eq!(pixels.len(), width * height);
+        pixels
+    }
+
+    fn channel_op(a: u32, b: u32, op: impl Fn(i32, i32) -> i32) -> u32 {
+        (0..4).fold(0, |pixel, i| {
+            let (x, y) = (
+                ((a >> (i * 8)) & 0xff) as i32,
+                ((b >> (i * 8)) & 0xff) as i32,
+            );
+            pixel | ((op(x, y) as u32 & 0xff) << (i * 8))
+        })
+    }
+
+    fn channel(p: u32, i: u32) -> i32 {
+        ((p >> (i * 8)) & 0xff) as i32
+    }
+
+    // The spec's predictors, by mode
+    fn predict(mode: u32, l: u32, t: u32, tl: u32, tr: u32) -> u32 {
+        let avg = |a, b| channel_op(a, b, |x, y| (x + y) / 2);
+        match mode {
+            0 => 0xff00_0000,
+            1 => l,
+            2 => t,
+            3 => tr,
+            4 => tl,
+            5 => avg(avg(l, tr), t),
+            6 => avg(l, tl),
+            7 => avg(l, t),
+            8 => avg(tl, t),
+            9 => avg(t, tr),
+            10 => avg(avg(l, tl), avg(t, tr)),
+            11 => {
+                let estimate = |i| channel(l, i) + channel(t, i) - channel(tl, i);
+                let pl: i32 = (0..4).map(|i| (estimate(i) - channel(l, i)).abs()).sum();
+                let pt: i32 = (0..4).map(|i| (estimate(i) - channel(t, i)).abs()).sum();
+                if pl < pt {
+                    l
+                } else {
+                    t
+                }
+            }
+            12 => (0..4).fold(0, |pixel, i| {
+                let value = (channel(l, i) + channel(t, i) - channel(tl, i)).clamp(0, 255);
+                pixel | (value as u32) << (i * 8)
+            }),
+            _ => panic!("mode {} isn't expected", mode),
+        }
+    }
+
+    fn decode(webp: &[u8]) -> RgbImage {
+        assert_eq!(&webp[..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(webp[4..8].try_into().unwrap()) as usize,
+            webp.len() - 8
+        );
+        assert_eq!(&webp[8..16], b"WEBPVP8L");
+        let mut bits = Bits {
+            data: &webp[20..],
+            pos: 0,
+        };
+        assert_eq!(bits.read(8), 0x2f);
+        let width = bits.read(14) as usize + 1;
+        let height = bits.read(14) as usize + 1;
+        bits.read(4);
+
+        let (mut subtract_green, mut predictor) = (false, None);
+        while bits.read(1) == 1 {
+            match bits.read(2) {
+                2 => subtract_green = true,
+                0 => {
+                    let block_bits = bits.read(3) as usize + 2;
+                    let block = 1 << block_bits;
+                    let (bw, bh) = (width.div_ceil(block), height.div_ceil(block));
+                    predictor = Some((block_bits, bw, read_image(&mut bits, bw, bh, false)));
+                }
+                other => panic!("unexpected transform {}", other),
+            }
+        }
+        let mut argb = read_image(&mut bits, width, height, true);
+
+        // Transforms are undone last to first; this encoder writes subtract green first
+        if let Some((block_bits, blocks_x, modes)) = predictor {
+            for y in 0..height {
+                for x in 0..width {
+                    let i = y * width + x;
+                    let predicted = match (x, y) {
+                        (0, 0) => 0xff00_0000,
+                        (_, 0) => argb[i - 1],
+                        (0, _) => argb[i - width],
+                        _ => {
+                            let mode = (modes[(y >> block_bits) * blocks_x + (x >> block_bits)]
+                                >> 8)
+                                & 0xf;
+                            let tr = argb[i - width + 1];
+                            predict(mode, argb[i - 1], argb[i - width], argb[i - width - 1], tr)
+                        }
+                    };
+                    argb[i] = channel_op(argb[i], predicted, |a, b| a + b);
+                }
+            }
+        }
+        if subtract_green {
+            for pixel in &mut argb {
+                let green = (*pixel >> 8) & 0xff;
+                *pixel = channel_op(*pixel, green << 16 | green, |a, b| a + b);
+            }
+        }
+        RgbImage::from_fn(width as u32, height as u32, |x, y| {
+            let pixel = argb[y as usize * width + x as usize];
+            assert_eq!(pixel >> 24, 0xff);
+            Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8])
+        })
+    }
+
+    #[test]
+    fn test_encode_webp_round_trips() {
+        // Flat areas that backward references copy, a gradient that prediction
+        // flattens, and noise that neither does much for
+        let img = RgbImage::from_fn(67, 45, |x, y| match (x / 23, y / 15) {
+            (0, _) => Rgb([24, 24, 24]),
+            (1, _) => Rgb([(x * 3) as u8, (y * 5) as u8, 200]),
+            _ => {
+                let n = x
+                    .wrapping_mul(2_654_435_761)
+                    .wrapping_add(y.wrapping_mul(40_503));
+                Rgb([(n >> 3) as u8, (n >> 11) as u8, (n >> 19) as u8])
+            }
+        });
+
+        // Assert every pixel decodes exactly as it was
+        assert_eq!(decode(&encode_webp(&img)), img);
+        let one = RgbImage::from_pixel(1, 1, Rgb([1, 2, 3]));
+        assert_eq!(decode(&encode_webp(&one)), one);
+    }
+
+    #[test]
+    fn test_webp_is_smaller_than_png() {
+        let templates = ImageTemplates::load(Path::new("templates"), Path::new("assets")).unwrap();
+        let params = HashMap::from([("image".to_string(), "gift.png".to_string())]);
+
+        // Assert a shipped template drawn on a photo comes out exact and smaller
+        let png = templates
+            .render("gift_confirm", &params, &LiveData::default())
+            .unwrap();
+        let webp = templates
+            .render_webp("gift_confirm", &params, &LiveData::default())
+            .unwrap();
+        assert!(webp.len() < png.len(), "{} >= {}", webp.len(), png.len());
+        let drawn = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(decode(&webp), drawn);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::errors::TlsError;
+use rustls::ServerConfig;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// Builds the rustls config for serving HTTPS directly from a PEM certificate
+// chain and private key
+pub fn load_server_config(cert_path: &Path, key_path: &Path) -> Result<ServerConfig, TlsError> {
+    let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| TlsError::Read(cert_path.display().to_string(), err))?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(cert_path.display().to_string()));
+    }
+
+    let key = rustls_pemfile::private_key(&mut open(key_path)?)
+        .map_err(|err| TlsError::Read(key_path.display().to_string(), err))?
+        .ok_or_else(|| TlsError::NoPrivateKey(key_path.display().to_string()))?;
+
+    ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(TlsError::Rejected)
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, TlsError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| TlsError::Read(path.display().to_string(), err))
+}
@@ -0,0 +1,60 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+use std::fmt;
+use std::future::{ready, Ready};
+use uuid::Uuid;
+
+pub const TRACE_HEADER: &str = "x-frame-trace-id";
+
+// Longest client-supplied trace id we'll reuse; anything else gets a fresh one
+const MAX_TRACE_ID_LEN: usize = 128;
+
+// Correlates every log line for one frame interaction
+#[derive(Clone, Debug)]
+pub struct TraceId(pub String);
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for TraceId {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        // Handlers mounted without the middleware (e.g. in tests) still get an id
+        let trace_id = req
+            .extensions()
+            .get::<TraceId>()
+            .cloned()
+            .unwrap_or_else(|| TraceId(Uuid::new_v4().to_string()));
+        ready(Ok(trace_id))
+    }
+}
+
+// Reuses the client's X-Frame-Trace-Id when it's sane, otherwise generates one, and echoes it back
+pub async fn trace_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let trace_id = req
+        .headers()
+        .get(TRACE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_TRACE_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    req.extensions_mut().insert(TraceId(trace_id.clone()));
+
+    let mut res = next.call(req).await?;
+    if let Ok(value) = HeaderValue::from_str(&trace_id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(TRACE_HEADER), value);
+    }
+    Ok(res)
+}
@@ -0,0 +1,417 @@
+use crate::config::Config;
+use crate::errors::AppError;
+use crate::secp256k1;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tiny_keccak::{Hasher, Keccak};
+
+// Boost contract entry point the Buy & Boost tx button calls
+const BUY_AND_BOOST_SIGNATURE: &str = "buyAndBoost(uint256,address)";
+
+// GOAT, like ETH, has 18 decimal places
+const DECIMALS: usize = 18;
+
+// The client is waiting on the transaction, so don't wait long on the RPC node
+const RPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Transaction a Farcaster client hands to the user's wallet
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionResponse {
+    // CAIP-2 chain id, e.g. eip155:8453
+    pub chain_id: String,
+    pub method: String,
+    pub params: TransactionParams,
+}
+
+#[derive(Serialize)]
+pub struct TransactionParams {
+    pub abi: serde_json::Value,
+    pub to: String,
+    pub data: String,
+    pub value: String,
+}
+
+// The amount is what the user typed into the Buy & Boost input, in whole GOAT;
+// leaving it empty sends no value. The address is the wallet the client connected,
+// which pays and receives the boost.
+pub async fn buy_boost_transaction(
+    fid: Option<u64>,
+    address: Option<&str>,
+    amount: Option<&str>,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<TransactionResponse, AppError> {
+    let Some(contract) = &config.boost_contract else {
+        error!("BOOST_CONTRACT is not configured");
+        return Err(AppError::InternalServerError);
+    };
+    let fid = fid.ok_or_else(|| AppError::BadRequest("Missing fid".to_string()))?;
+    let address = parse_address(address.unwrap_or_default())?;
+    let value = parse_amount(amount.unwrap_or_default())?;
+
+    check_balance(client, config, &address, value).await?;
+
+    let mut data = function_selector(BUY_AND_BOOST_SIGNATURE).to_vec();
+    data.extend_from_slice(&encode_uint(fid));
+    data.extend_from_slice(&encode_address(&address));
+
+    Ok(TransactionResponse {
+        chain_id: format!("eip155:{}", config.chain_id),
+        method: "eth_sendTransaction".to_string(),
+        params: TransactionParams {
+            abi: serde_json::json!([{
+                "type": "function",
+                "name": "buyAndBoost",
+                "stateMutability": "payable",
+                "inputs": [
+                    { "name": "fid", "type": "uint256" },
+                    { "name": "recipient", "type": "address" }
+                ],
+                "outputs": []
+            }]),
+            to: contract.clone(),
+            data: format!("0x{}", hex::encode(data)),
+            value: value.to_string(),
+        },
+    })
+}
+
+// GOAT is the chain's native token, so a transaction's value is paid directly and
+// there's no allowance to check, only the balance. Skipped without RPC_URL.
+pub async fn check_balance(
+    client: &reqwest::Client,
+    config: &Config,
+    address: &[u8; 20],
+    value: u128,
+) -> Result<(), AppError> {
+    let (Some(rpc_url), true) = (&config.rpc_url, value > 0) else {
+        return Ok(());
+    };
+    match fetch_balance(client, rpc_url, address).await {
+        Ok(balance) if balance < value => Err(AppError::InsufficientBalance {
+            balance: format_amount(balance),
+            needed: format_amount(value),
+        }),
+        Ok(_) => Ok(()),
+        // The wallet will still refuse a transaction it can't pay for
+        Err(err) => {
+            warn!("Failed to check wallet balance, skipping: {}", err);
+            Ok(())
+        }
+    }
+}
+
+// Parses a 0x-prefixed, 20 byte hex address
+pub fn parse_address(address: &str) -> Result<[u8; 20], AppError> {
+    let invalid = || AppError::BadRequest(format!("Invalid address: {:?}", address));
+    let hex_digits = address.strip_prefix("0x").ok_or_else(invalid)?;
+    let bytes = hex::decode(hex_digits).map_err(|_| invalid())?;
+    bytes.try_into().map_err(|_| invalid())
+}
+
+// Checks a transactionId reported after a tx button is a 0x-prefixed 32 byte hash
+pub fn parse_transaction_id(transaction_id: &str) -> Result<String, AppError> {
+    let hash = transaction_id
+        .strip_prefix("0x")
+        .filter(|hash| hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit()));
+    match hash {
+        Some(hash) => Ok(format!("0x{}", hash.to_ascii_lowercase())),
+        None => Err(AppError::BadRequest(format!(
+            "Invalid transaction id: {:?}",
+            transaction_id
+        ))),
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    // null for a receipt the chain doesn't have yet
+    #[serde(default)]
+    result: serde_json::Value,
+    error: Option<serde_json::Value>,
+}
+
+// Where a sent transaction is, as its receipt tells. Sent is all that's known
+// without an RPC_URL to ask.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxStatus {
+    Sent,
+    Pending,
+    Confirmed,
+    Failed,
+}
+
+impl TxStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            TxStatus::Sent => "Sent",
+            TxStatus::Pending => "Pending",
+            TxStatus::Confirmed => "Confirmed",
+            TxStatus::Failed => "Failed",
+        }
+    }
+
+    // The badge's color, the same in either theme
+    pub fn color(self) -> &'static str {
+        match self {
+            TxStatus::Sent => "#8a8f98",
+            TxStatus::Pending => "#f5a524",
+            TxStatus::Confirmed => "#17c964",
+            TxStatus::Failed => "#f31260",
+        }
+    }
+
+    // A mined transaction's outcome never changes
+    pub fn is_settled(self) -> bool {
+        matches!(self, TxStatus::Confirmed | TxStatus::Failed)
+    }
+}
+
+// "0x1234ab...cdef12", a hash short enough to draw
+pub fn short_hash(hash: &str) -> String {
+    match (hash.get(..8), hash.get(hash.len().saturating_sub(6)..)) {
+        (Some(start), Some(end)) if hash.len() > 16 => format!("{}...{}", start, end),
+        _ => hash.to_string(),
+    }
+}
+
+// Fetches a transaction's receipt with eth_getTransactionReceipt: none yet means
+// it's pending, and its status is 0x1 once it succeeded or 0x0 if it reverted
+pub async fn fetch_receipt_status(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    hash: &str,
+) -> Result<TxStatus, String> {
+    let receipt = rpc_request(
+        client,
+        rpc_url,
+        "eth_getTransactionReceipt",
+        serde_json::json!([hash]),
+    )
+    .await?;
+    if receipt.is_null() {
+        return Ok(TxStatus::Pending);
+    }
+    match receipt.get("status").and_then(|status| status.as_str()) {
+        Some("0x1") => Ok(TxStatus::Confirmed),
+        Some("0x0") => Ok(TxStatus::Failed),
+        _ => Err(format!("invalid receipt {}", receipt)),
+    }
+}
+
+// Fetches an address's native balance in wei with eth_getBalance
+pub async fn fetch_balance(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    address: &[u8; 20],
+) -> Result<u128, String> {
+    let result = rpc_call(
+        client,
+        rpc_url,
+        "eth_getBalance",
+        serde_json::json!([format!("0x{}", hex::encode(address)), "latest"]),
+    )
+    .await?;
+    let digits = result
+        .strip_prefix("0x")
+        .ok_or_else(|| format!("invalid balance {:?}", result))?;
+    // Nobody holds more than u128::MAX wei, but saturate rather than fail if they did
+    match u128::from_str_radix(digits, 16) {
+        Ok(balance) => Ok(balance),
+        Err(_) if digits.bytes().all(|b| b.is_ascii_hexdigit()) => Ok(u128::MAX),
+        Err(_) => Err(format!("invalid balance {:?}", result)),
+    }
+}
+
+// Recovers the address that signed a 32 byte hash, as the ecrecover precompile would
+// but here, so no RPC node can vouch for a signature that isn't. A 65 byte r, s, v
+// signature that recovers nobody gives None.
+pub fn recover_signer(hash: &[u8; 32], signature: &[u8; 65]) -> Option<[u8; 20]> {
+    // Wallets give v as 27 or 28, or sometimes 0 or 1
+    let recovery_id = match signature[64] {
+        v @ (0 | 1) => v,
+        v => v.checked_sub(27)?,
+    };
+    let public_key = secp256k1::recover(hash, signature[..64].try_into().ok()?, recovery_id)?;
+    keccak256(&public_key)[12..].try_into().ok()
+}
+
+// Calls a contract's view function with eth_call, returning what it returned
+pub async fn eth_call(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    to: &[u8; 20],
+    input: &[u8],
+) -> Result<Vec<u8>, String> {
+    let result = rpc_call(
+        client,
+        rpc_url,
+        "eth_call",
+        serde_json::json!([
+            {
+                "to": format!("0x{}", hex::encode(to)),
+                "data": format!("0x{}", hex::encode(input))
+            },
+            "latest"
+        ]),
+    )
+    .await?;
+    result
+        .strip_prefix("0x")
+        .and_then(|digits| hex::decode(digits).ok())
+        .ok_or_else(|| format!("invalid eth_call result {:?}", result))
+}
+
+// The ABI word at index of a return value as an amount, saturating past u128 as
+// "unlimited" allowances are. None when there's no such word.
+pub fn decode_amount(output: &[u8], index: usize) -> Option<u128> {
+    let word = output.get(index * 32..(index + 1) * 32)?;
+    if word[..16].iter().any(|b| *b != 0) {
+        return Some(u128::MAX);
+    }
+    Some(u128::from_be_bytes(word[16..].try_into().ok()?))
+}
+
+// a * b / c rounded down, without overflowing in between. None when c is 0 or the
+// result is past u128.
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    // The 256 bit product as high and low halves, from 64 bit limbs
+    const MASK: u128 = u64::MAX as u128;
+    let (a_hi, a_lo, b_hi, b_lo) = (a >> 64, a & MASK, b >> 64, b & MASK);
+    let (lo_lo, hi_lo, lo_hi, hi_hi) = (a_lo * b_lo, a_hi * b_lo, a_lo * b_hi, a_hi * b_hi);
+    let cross = (lo_lo >> 64) + (hi_lo & MASK) + (lo_hi & MASK);
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = hi_hi + (hi_lo >> 64) + (lo_hi >> 64) + (cross >> 64);
+    if hi >= c {
+        return None;
+    }
+    // Long division, a bit at a time; the remainder stays under c, so a bit carried
+    // out of it means it's c or more
+    let (mut remainder, mut quotient) = (hi, 0u128);
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    Some(quotient)
+}
+
+// Keccak-256, as Ethereum hashes everything
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+// Makes a JSON-RPC call, returning its result
+pub async fn rpc_call(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<String, String> {
+    match rpc_request(client, rpc_url, method, params).await? {
+        serde_json::Value::String(result) => Ok(result),
+        serde_json::Value::Null => Err("no result".to_string()),
+        result => Err(format!("unexpected result {}", result)),
+    }
+}
+
+// Makes a JSON-RPC call, returning its result whatever JSON it is
+async fn rpc_request(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let response = client
+        .post(rpc_url)
+        .timeout(RPC_TIMEOUT)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params
+        }))
+        .send()
+        .await
+        .map_err(|err| format!("request failed: {}", err))?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    let body: RpcResponse = response
+        .json()
+        .await
+        .map_err(|err| format!("unexpected response: {}", err))?;
+    if let Some(err) = body.error {
+        return Err(format!("RPC error {}", err));
+    }
+    Ok(body.result)
+}
+
+// Formats wei as GOAT without trailing zeros, e.g. 1500000000000000000 as "1.5"
+pub fn format_amount(wei: u128) -> String {
+    let unit = 10u128.pow(DECIMALS as u32);
+    let fraction = format!("{:0width$}", wei % unit, width = DECIMALS);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (wei / unit).to_string()
+    } else {
+        format!("{}.{}", wei / unit, fraction)
+    }
+}
+
+// Parses a decimal amount like "1.5" into wei
+pub fn parse_amount(amount: &str) -> Result<u128, AppError> {
+    let amount = amount.trim();
+    if amount.is_empty() {
+        return Ok(0);
+    }
+
+    let invalid = || AppError::BadRequest(format!("Invalid amount: {:?}", amount));
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty())
+        || !digits(whole)
+        || !digits(fraction)
+        || fraction.len() > DECIMALS
+    {
+        return Err(invalid());
+    }
+
+    let padded = format!("{}{:0<width$}", whole, fraction, width = DECIMALS);
+    padded.parse::<u128>().map_err(|_| invalid())
+}
+
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+// ABI words are 32 bytes, big-endian and left-padded
+pub fn encode_uint(value: u64) -> [u8; 32] {
+    encode_amount(value.into())
+}
+
+pub fn encode_amount(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+pub fn encode_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}